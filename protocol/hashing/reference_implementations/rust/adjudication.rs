@@ -0,0 +1,158 @@
+/// adjudication.rs - Verifying a ruling on a challenged contract
+///
+/// A dispute leaves three objects that point back at each other by hash: the contract,
+/// the challenge naming the contract's semantic hash, and the ruling naming both the
+/// contract's and the challenge's hashes, signed by its arbiter. A ruling may also cite
+/// earlier rulings as precedent by their semantic hashes.
+///
+/// `verify_ruling_chain` checks the whole chain in one call: that each object names the
+/// one before it by ID and hash, that the ruling came no earlier than the challenge, that
+/// every precedent it cites is a known earlier ruling, and that it is signed by a key
+/// valid for its arbiter when it was made. Altering any object breaks a link after it.
+
+use crate::timestamp::utc_timestamp;
+use crate::{
+    verify_semantic_hash_with, AgentRegistry, Canonicalize, CanonicalizeOptions, Challenge, ConstitutionalError,
+    Contract, Result, Ruling, SignedObject,
+};
+
+/// Whether an object hashes to a recorded semantic hash, under its own options.
+fn hashes_to<T: Canonicalize>(object: &T, hash: Option<&String>) -> Result<bool> {
+    match hash {
+        Some(hash) => verify_semantic_hash_with(&object.canonical_value(), hash, &object.canonical_options()),
+        None => Ok(false),
+    }
+}
+
+/// Check that every precedent a ruling cites is one of the known rulings, made before it.
+///
+/// # Arguments
+/// * `ruling` - The ruling citing precedents
+/// * `precedents` - The earlier rulings known, in any order
+///
+/// # Returns
+/// true if each cited hash is the semantic hash of a known ruling made earlier, false
+/// otherwise; an error if a hash or timestamp is malformed
+pub fn verify_precedents(ruling: &Ruling, precedents: &[Ruling]) -> Result<bool> {
+    let at = utc_timestamp(&ruling.timestamp)?;
+    for cited in &ruling.precedents {
+        let mut found = false;
+        for precedent in precedents {
+            if hashes_to(precedent, Some(cited))? && utc_timestamp(&precedent.timestamp)? < at {
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Check a signed ruling against the challenge it resolves and the contract challenged.
+///
+/// # Arguments
+/// * `contract` - The contract challenged
+/// * `challenge` - The challenge
+/// * `signed` - The arbiter's signed ruling, its payload a `Ruling`
+/// * `precedents` - The earlier rulings known, which must include every one cited
+/// * `registry` - The agents and their keys
+/// * `options` - Canonicalization options the ruling was signed under
+///
+/// # Returns
+/// true if the challenge names the contract by ID and hash, the ruling names both by ID
+/// and hash and is no earlier than the challenge, its precedents are known earlier
+/// rulings and its signer was a key of its arbiter when it was made; false otherwise. An
+/// error if the payload is not a ruling, or a hash, timestamp, signer or signature is
+/// malformed
+pub fn verify_ruling_chain(
+    contract: &Contract,
+    challenge: &Challenge,
+    signed: &SignedObject,
+    precedents: &[Ruling],
+    registry: &AgentRegistry,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    let ruling: Ruling = serde_json::from_value(signed.payload.clone())
+        .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid ruling: {}", e)))?;
+    let linked = challenge.challenges(contract)?
+        && ruling.contract_id == contract.id
+        && hashes_to(contract, ruling.contract_hash.as_ref())?
+        && ruling.challenge_id.as_ref() == Some(&challenge.id)
+        && hashes_to(challenge, ruling.challenge_hash.as_ref())?
+        && utc_timestamp(&challenge.timestamp)? <= utc_timestamp(&ruling.timestamp)?;
+    Ok(linked
+        && verify_precedents(&ruling, precedents)?
+        && registry.verify_signed_at(signed, &ruling.arbiter_agent, &ruling.timestamp, options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::contract;
+    use crate::{sign, ChallengeGrounds, Evidence, Keypair, Reasoning, RulingOutcome};
+    use serde_json::json;
+
+    fn challenge(contract: &Contract) -> Challenge {
+        Challenge::builder()
+            .id("9b2e4f6a-1c3d-4e5f-8a7b-6c5d4e3f2a1b")
+            .contract(&contract.id, &contract.semantic_hash().unwrap())
+            .challenger_agent("Grok")
+            .grounds(ChallengeGrounds::ConstitutionalViolation)
+            .evidence(Evidence::new("citation", "Article-III.1"))
+            .reasoning(Reasoning::new("Exceeds Article III.1", 0.8).unwrap())
+            .timestamp("2025-11-22T10:00:00Z")
+            .build()
+            .unwrap()
+    }
+
+    fn ruling(challenge: &Challenge, precedents: &[&Ruling], timestamp: &str) -> Ruling {
+        let builder = Ruling::builder()
+            .id("e1f04a2b-7c6d-4e5f-9a8b-1c2d3e4f5a6b")
+            .challenge(challenge)
+            .unwrap()
+            .arbiter_agent("DeepSeek")
+            .outcome(RulingOutcome::Upheld)
+            .reasoning(Reasoning::new("Within the mandate of Article III.1", 0.9).unwrap())
+            .timestamp(timestamp);
+        let builder = precedents.iter().fold(builder, |b, precedent| b.precedent(&precedent.semantic_hash().unwrap()));
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_ruling_chain_verifies() {
+        let mut registry = AgentRegistry::new();
+        let arbiter = Keypair::from_secret(&[3; 32]);
+        registry.register("DeepSeek", arbiter.public_key(), "2025-01-01T00:00:00Z").unwrap();
+        let options = CanonicalizeOptions::new();
+        let (contract, challenge) = (contract(), challenge(&contract()));
+
+        let earlier = ruling(&challenge, &[], "2025-11-23T00:00:00Z");
+        let ruling = ruling(&challenge, &[&earlier], "2025-11-28T00:00:00Z");
+        assert_eq!(ruling.validate(), []);
+        assert_eq!(serde_json::from_value::<Ruling>(ruling.to_value()).unwrap(), ruling);
+        let signed = sign(&ruling.to_value(), &arbiter).unwrap();
+        let precedents = std::slice::from_ref(&earlier);
+        assert!(verify_ruling_chain(&contract, &challenge, &signed, precedents, &registry, &options).unwrap());
+
+        // A changed contract, an unknown precedent or another signer breaks the chain
+        let mut changed = contract.clone();
+        changed.action = json!({"target": "amendment-article-4"});
+        assert!(!verify_ruling_chain(&changed, &challenge, &signed, precedents, &registry, &options).unwrap());
+        assert!(!verify_ruling_chain(&contract, &challenge, &signed, &[], &registry, &options).unwrap());
+        let forged = sign(&ruling.to_value(), &Keypair::from_secret(&[4; 32])).unwrap();
+        assert!(!verify_ruling_chain(&contract, &challenge, &forged, &[earlier], &registry, &options).unwrap());
+        let unsigned = sign(&json!({"id": "e1f0"}), &arbiter).unwrap();
+        assert!(verify_ruling_chain(&contract, &challenge, &unsigned, &[], &registry, &options).is_err());
+    }
+
+    #[test]
+    fn test_precedents_must_be_earlier() {
+        let challenge = challenge(&contract());
+        let later = ruling(&challenge, &[], "2025-12-01T00:00:00Z");
+        let citing = ruling(&challenge, &[&later], "2025-11-28T00:00:00Z");
+        assert!(!verify_precedents(&citing, std::slice::from_ref(&later)).unwrap());
+        assert!(verify_precedents(&later, &[]).unwrap());
+    }
+}
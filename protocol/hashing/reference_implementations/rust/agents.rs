@@ -0,0 +1,441 @@
+/// agents.rs - Agents' keys over time
+///
+/// Verifying a signature only says that some key made it. Whether that key spoke for an
+/// agent depends on when: keys are rotated, and a compromised key is revoked from the
+/// moment it was compromised, which may be before the revocation was recorded. An
+/// `AgentRegistry` keeps each agent's keys with the window each was valid in, so a
+/// verifier can ask whether a key was valid for an agent at a timestamp. It also keeps
+/// the roles agents hold, which a `SignaturePolicy` checks against what they sign:
+///
+/// ```json
+/// {"agents":{"claude":[
+///   {"key":"ed25519:3b6a...","valid_from":"2025-01-01T00:00:00.000000000Z",
+///    "valid_until":"2025-06-01T00:00:00.000000000Z",
+///    "revoked":{"at":"2025-05-20T00:00:00.000000000Z","reason":"laptop stolen"}},
+///   {"key":"ed25519:8a88...","valid_from":"2025-06-01T00:00:00.000000000Z"}]},
+///  "roles":{"claude":["amender","reviewer"]}}
+/// ```
+///
+/// Timestamps are RFC 3339 and kept as UTC with nanosecond digits, so they compare as
+/// strings. A window includes its start and excludes its end.
+
+use crate::objects::take;
+use crate::timestamp::utc_timestamp;
+use crate::{verify_signed_with, CanonicalizeOptions, ConstitutionalError, PublicKey, Result, SignedObject};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+const FIELDS: &[&str] = &["key", "valid_from", "valid_until", "revoked"];
+const REVOCATION_FIELDS: &[&str] = &["at", "reason"];
+const REGISTRY_FIELDS: &[&str] = &["agents", "roles"];
+
+/// One of an agent's keys and when it was valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRecord {
+    /// The key
+    pub key: PublicKey,
+    /// When the key took effect
+    pub valid_from: String,
+    /// When the key was rotated out, if it has been
+    pub valid_until: Option<String>,
+    /// The revocation of the key, if it was revoked
+    pub revoked: Option<Revocation>,
+}
+
+impl KeyRecord {
+    /// Whether the key was valid at a normalized timestamp.
+    fn valid_at(&self, at: &str) -> bool {
+        self.valid_from.as_str() <= at
+            && self.valid_until.as_deref().is_none_or(|until| at < until)
+            && self.revoked.as_ref().is_none_or(|revoked| at < revoked.at.as_str())
+    }
+}
+
+/// The record that a key stopped being trusted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revocation {
+    /// From when signatures by the key are invalid, e.g. when it was compromised
+    pub at: String,
+    /// Why the key was revoked
+    pub reason: String,
+}
+
+/// Every agent's current and past keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentRegistry {
+    /// Keys by agent, oldest first
+    agents: BTreeMap<String, Vec<KeyRecord>>,
+    /// Roles by agent, for agents holding any
+    roles: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl AgentRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new agent with its first key.
+    ///
+    /// # Arguments
+    /// * `agent` - The agent's name
+    /// * `key` - Its key
+    /// * `valid_from` - When the key takes effect, RFC 3339
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the agent is registered, the key belongs to an agent
+    /// already or the timestamp is malformed
+    pub fn register(&mut self, agent: &str, key: PublicKey, valid_from: &str) -> Result<()> {
+        let valid_from = utc_timestamp(valid_from)?;
+        if self.agents.contains_key(agent) {
+            return Err(ConstitutionalError::ProtocolError(format!("Agent {:?} is already registered", agent)));
+        }
+        self.check_unused(&key)?;
+        self.agents.insert(agent.to_string(), vec![KeyRecord { key, valid_from, valid_until: None, revoked: None }]);
+        Ok(())
+    }
+
+    /// Replace an agent's current key with a new one from a moment on.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the agent is unknown, the key was used before, the
+    /// timestamp is malformed or it predates the current key
+    pub fn rotate(&mut self, agent: &str, key: PublicKey, at: &str) -> Result<()> {
+        let at = utc_timestamp(at)?;
+        self.check_unused(&key)?;
+        let keys = self.keys_mut(agent)?;
+        let current = keys.last_mut().expect("registered agents have a key");
+        if at <= current.valid_from {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "A rotation at {} does not follow the current key of {:?}, valid from {}",
+                at, agent, current.valid_from
+            )));
+        }
+        current.valid_until = Some(at.clone());
+        keys.push(KeyRecord { key, valid_from: at, valid_until: None, revoked: None });
+        Ok(())
+    }
+
+    /// Revoke one of an agent's keys from a moment on, which may be in the past.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the agent does not hold the key, the key is revoked
+    /// already or the timestamp is malformed or predates the key
+    pub fn revoke(&mut self, agent: &str, key: &PublicKey, at: &str, reason: &str) -> Result<()> {
+        let at = utc_timestamp(at)?;
+        let record = self
+            .keys_mut(agent)?
+            .iter_mut()
+            .find(|record| record.key == *key)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("{:?} never held {}", agent, key)))?;
+        if record.revoked.is_some() {
+            return Err(ConstitutionalError::ProtocolError(format!("{} is already revoked", key)));
+        }
+        if at < record.valid_from {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "A revocation at {} predates {}, valid from {}",
+                at, key, record.valid_from
+            )));
+        }
+        record.revoked = Some(Revocation { at, reason: reason.to_string() });
+        Ok(())
+    }
+
+    /// An agent's keys, oldest first.
+    pub fn keys(&self, agent: &str) -> Option<&[KeyRecord]> {
+        self.agents.get(agent).map(Vec::as_slice)
+    }
+
+    /// The agent's key in effect now: its latest, unless that was revoked.
+    pub fn current_key(&self, agent: &str) -> Option<&PublicKey> {
+        let current = self.agents.get(agent)?.last()?;
+        current.revoked.is_none().then_some(&current.key)
+    }
+
+    /// The agent a key belongs or belonged to.
+    pub fn agent_of(&self, key: &PublicKey) -> Option<&str> {
+        self.agents
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|record| record.key == *key))
+            .map(|(agent, _)| agent.as_str())
+    }
+
+    /// Names of the registered agents, sorted.
+    pub fn agents(&self) -> impl Iterator<Item = &str> {
+        self.agents.keys().map(String::as_str)
+    }
+
+    /// Give a registered agent a role, e.g. `amender`.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the agent is not registered
+    pub fn grant_role(&mut self, agent: &str, role: &str) -> Result<()> {
+        self.keys_mut(agent)?;
+        self.roles.entry(agent.to_string()).or_default().insert(role.to_string());
+        Ok(())
+    }
+
+    /// Take a role from an agent.
+    ///
+    /// # Returns
+    /// Whether the agent held it
+    pub fn revoke_role(&mut self, agent: &str, role: &str) -> bool {
+        let Some(roles) = self.roles.get_mut(agent) else {
+            return false;
+        };
+        let held = roles.remove(role);
+        if roles.is_empty() {
+            self.roles.remove(agent);
+        }
+        held
+    }
+
+    /// The roles an agent holds, sorted.
+    pub fn roles(&self, agent: &str) -> impl Iterator<Item = &str> {
+        self.roles.get(agent).into_iter().flatten().map(String::as_str)
+    }
+
+    /// Whether an agent holds a role.
+    pub fn has_role(&self, agent: &str, role: &str) -> bool {
+        self.roles.get(agent).is_some_and(|roles| roles.contains(role))
+    }
+
+    /// Whether a key was valid for an agent at a timestamp: inside its window and before
+    /// any revocation.
+    ///
+    /// # Returns
+    /// The answer, or a ProtocolError if the timestamp is malformed
+    pub fn valid_at(&self, agent: &str, key: &PublicKey, at: &str) -> Result<bool> {
+        let at = utc_timestamp(at)?;
+        let keys = self.agents.get(agent).map(Vec::as_slice).unwrap_or_default();
+        Ok(keys.iter().any(|record| record.key == *key && record.valid_at(&at)))
+    }
+
+    /// Check a signed object as the agent's attestation at a timestamp: the signer must
+    /// be a key valid for the agent then, and the signature must verify.
+    ///
+    /// # Returns
+    /// true if both hold, false otherwise; an error if the timestamp, signer or
+    /// signature is malformed
+    pub fn verify_signed_at(
+        &self,
+        signed: &SignedObject,
+        agent: &str,
+        at: &str,
+        options: &CanonicalizeOptions,
+    ) -> Result<bool> {
+        let key: PublicKey = signed.signer.parse()?;
+        Ok(self.valid_at(agent, &key, at)? && verify_signed_with(signed, options)?)
+    }
+
+    fn keys_mut(&mut self, agent: &str) -> Result<&mut Vec<KeyRecord>> {
+        self.agents
+            .get_mut(agent)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Agent {:?} is not registered", agent)))
+    }
+
+    /// Keys are never reused, so each names one agent for good.
+    fn check_unused(&self, key: &PublicKey) -> Result<()> {
+        match self.agent_of(key) {
+            Some(agent) => Err(ConstitutionalError::ProtocolError(format!("{} is a key of {:?}", key, agent))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Serialize for AgentRegistry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AgentRegistry", 1 + usize::from(!self.roles.is_empty()))?;
+        state.serialize_field("agents", &self.agents)?;
+        // Registries from before roles have none, and are written as they were
+        if !self.roles.is_empty() {
+            state.serialize_field("roles", &self.roles)?;
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AgentRegistry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<AgentRegistry, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected an agent registry object"));
+        };
+        if let Some(key) = map.keys().find(|key| !REGISTRY_FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, REGISTRY_FIELDS));
+        }
+        let agents: BTreeMap<String, Vec<KeyRecord>> = take(&mut map, "agents")?;
+        let roles: BTreeMap<String, BTreeSet<String>> =
+            map.contains_key("roles").then(|| take(&mut map, "roles")).transpose()?.unwrap_or_default();
+        // Rebuild through `register` and `rotate` so stored histories obey the same rules
+        let mut registry = AgentRegistry::new();
+        for (agent, keys) in &agents {
+            let invalid = |e: ConstitutionalError| de::Error::custom(format!("{}: {}", agent, e));
+            let Some((first, rest)) = keys.split_first() else {
+                return Err(de::Error::custom(format!("{}: no keys", agent)));
+            };
+            registry.register(agent, first.key, &first.valid_from).map_err(invalid)?;
+            for (previous, record) in keys.iter().zip(rest) {
+                let until = previous.valid_until.as_deref().map(utc_timestamp).transpose().map_err(invalid)?;
+                if until != Some(utc_timestamp(&record.valid_from).map_err(invalid)?) {
+                    return Err(de::Error::custom(format!("{}: key windows do not meet", agent)));
+                }
+                registry.rotate(agent, record.key, &record.valid_from).map_err(invalid)?;
+            }
+            if keys.last().is_some_and(|record| record.valid_until.is_some()) {
+                return Err(de::Error::custom(format!("{}: the current key has an end", agent)));
+            }
+            for record in keys {
+                if let Some(revoked) = &record.revoked {
+                    registry.revoke(agent, &record.key, &revoked.at, &revoked.reason).map_err(invalid)?;
+                }
+            }
+        }
+        for (agent, roles) in &roles {
+            for role in roles {
+                registry.grant_role(agent, role).map_err(|e| de::Error::custom(format!("roles: {}", e)))?;
+            }
+        }
+        Ok(registry)
+    }
+}
+
+impl Serialize for KeyRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let len = 2 + usize::from(self.valid_until.is_some()) + usize::from(self.revoked.is_some());
+        let mut state = serializer.serialize_struct("KeyRecord", len)?;
+        state.serialize_field("key", &self.key.to_string())?;
+        state.serialize_field("valid_from", &self.valid_from)?;
+        if let Some(valid_until) = &self.valid_until {
+            state.serialize_field("valid_until", valid_until)?;
+        }
+        if let Some(revoked) = &self.revoked {
+            state.serialize_field("revoked", revoked)?;
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<KeyRecord, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a key record object"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        let key: String = take(&mut map, "key")?;
+        Ok(KeyRecord {
+            key: key.parse().map_err(|e| de::Error::custom(format!("key: {}", e)))?,
+            valid_from: take(&mut map, "valid_from")?,
+            valid_until: map.contains_key("valid_until").then(|| take(&mut map, "valid_until")).transpose()?,
+            revoked: map.contains_key("revoked").then(|| take(&mut map, "revoked")).transpose()?,
+        })
+    }
+}
+
+impl Serialize for Revocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Revocation", REVOCATION_FIELDS.len())?;
+        state.serialize_field("at", &self.at)?;
+        state.serialize_field("reason", &self.reason)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Revocation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Revocation, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a revocation object"));
+        };
+        if let Some(key) = map.keys().find(|key| !REVOCATION_FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, REVOCATION_FIELDS));
+        }
+        Ok(Revocation { at: take(&mut map, "at")?, reason: take(&mut map, "reason")? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+    use serde_json::json;
+
+    fn key(seed: u8) -> PublicKey {
+        Keypair::from_secret(&[seed; 32]).public_key()
+    }
+
+    fn registry() -> AgentRegistry {
+        let mut registry = AgentRegistry::new();
+        registry.register("claude", key(1), "2025-01-01T00:00:00Z").unwrap();
+        registry.rotate("claude", key(2), "2025-06-01T02:00:00+02:00").unwrap();
+        registry.revoke("claude", &key(1), "2025-05-20T00:00:00Z", "laptop stolen").unwrap();
+        registry.register("gemini", key(3), "2025-03-01T00:00:00Z").unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_keys_are_valid_in_their_windows() {
+        let registry = registry();
+        assert!(registry.valid_at("claude", &key(1), "2025-01-01T00:00:00Z").unwrap());
+        assert!(registry.valid_at("claude", &key(1), "2025-05-19T23:59:59.999Z").unwrap());
+        // Revoked before it was rotated out
+        assert!(!registry.valid_at("claude", &key(1), "2025-05-25T00:00:00Z").unwrap());
+        assert!(!registry.valid_at("claude", &key(2), "2025-05-31T23:59:59Z").unwrap());
+        assert!(registry.valid_at("claude", &key(2), "2025-06-01T00:00:00Z").unwrap());
+        assert!(!registry.valid_at("gemini", &key(2), "2025-07-01T00:00:00Z").unwrap());
+        assert!(!registry.valid_at("claude", &key(1), "2024-12-31T23:59:59Z").unwrap());
+        assert!(registry.valid_at("claude", &key(1), "yesterday").is_err());
+
+        assert_eq!(registry.current_key("claude"), Some(&key(2)));
+        assert_eq!(registry.agent_of(&key(1)), Some("claude"));
+        assert_eq!(registry.keys("claude").unwrap().len(), 2);
+        assert_eq!(registry.agents().collect::<Vec<_>>(), ["claude", "gemini"]);
+
+        let contract = json!({"contract_id": "c-17"});
+        let signed = sign(&contract, &Keypair::from_secret(&[1; 32])).unwrap();
+        let options = CanonicalizeOptions::new();
+        assert!(registry.verify_signed_at(&signed, "claude", "2025-02-01T00:00:00Z", &options).unwrap());
+        assert!(!registry.verify_signed_at(&signed, "claude", "2025-07-01T00:00:00Z", &options).unwrap());
+        assert!(!registry.verify_signed_at(&signed, "gemini", "2025-04-01T00:00:00Z", &options).unwrap());
+    }
+
+    #[test]
+    fn test_history_rules_and_storage() {
+        let mut registry = registry();
+        assert!(registry.register("claude", key(4), "2025-01-01T00:00:00Z").is_err());
+        assert!(registry.register("gpt", key(3), "2025-01-01T00:00:00Z").is_err());
+        assert!(registry.rotate("claude", key(1), "2025-08-01T00:00:00Z").is_err());
+        assert!(registry.rotate("claude", key(4), "2025-06-01T00:00:00Z").is_err());
+        assert!(registry.rotate("gpt", key(4), "2025-08-01T00:00:00Z").is_err());
+        assert!(registry.revoke("claude", &key(1), "2025-05-21T00:00:00Z", "again").is_err());
+        assert!(registry.revoke("gemini", &key(1), "2025-05-21T00:00:00Z", "wrong agent").is_err());
+        registry.revoke("gemini", &key(3), "2025-09-01T00:00:00Z", "retired").unwrap();
+        assert_eq!(registry.current_key("gemini"), None);
+        assert!(serde_json::to_value(&registry).unwrap().get("roles").is_none());
+
+        registry.grant_role("claude", "reviewer").unwrap();
+        registry.grant_role("claude", "amender").unwrap();
+        registry.grant_role("gemini", "reviewer").unwrap();
+        assert!(registry.grant_role("gpt", "reviewer").is_err());
+        assert!(registry.revoke_role("gemini", "reviewer"));
+        assert!(!registry.revoke_role("gemini", "reviewer"));
+        assert_eq!(registry.roles("claude").collect::<Vec<_>>(), ["amender", "reviewer"]);
+        assert!(registry.has_role("claude", "amender") && !registry.has_role("gemini", "amender"));
+
+        let stored = serde_json::to_value(&registry).unwrap();
+        assert_eq!(stored["roles"], json!({"claude": ["amender", "reviewer"]}));
+        assert_eq!(stored["agents"]["claude"][0]["revoked"]["reason"], "laptop stolen");
+        assert_eq!(stored["agents"]["claude"][1]["valid_from"], "2025-06-01T00:00:00.000000000Z");
+        assert_eq!(serde_json::from_value::<AgentRegistry>(stored.clone()).unwrap(), registry);
+
+        let mut gap = stored.clone();
+        gap["agents"]["claude"][0]["valid_until"] = json!("2025-05-01T00:00:00.000000000Z");
+        assert!(serde_json::from_value::<AgentRegistry>(gap).is_err());
+        let mut reused = stored;
+        reused["agents"]["gemini"][0]["key"] = json!(key(2).to_string());
+        assert!(serde_json::from_value::<AgentRegistry>(reused).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+/// aggregate.rs - Combining agents' confidences in a claim
+///
+/// When several agents attest to one claim, each with its own confidence, a
+/// `ConfidenceAggregator` combines the confidences into one by a chosen `Aggregation`:
+///
+/// - `WeightedMean`: the mean of the confidences, each counted as many times as its
+///   agent's weight;
+/// - `Minimum`: the lowest confidence of an agent with a weight;
+/// - `Bayesian`: the confidences taken as independent evidence for the claim, their odds
+///   multiplied, each as many times as its agent's weight: Π pᵢ / (Π pᵢ + Π (1 − pᵢ)).
+///
+/// The result is an `AggregateConfidence` that records the method, every agent's
+/// confidence and weight and the combined confidence, so it can be hashed and checked
+/// with `verify`. Weights are whole numbers and the arithmetic is only additions,
+/// multiplications and one division, done in agent order, so every node computes the
+/// same bits regardless of the order the attestations arrive in or its maths library:
+///
+/// ```json
+/// {"subject":"c-17","method":"weighted_mean","confidence":0.85,
+///  "attestations":{"claude":{"confidence":0.9,"weight":2},"gemini":{"confidence":0.75,"weight":1}}}
+/// ```
+
+use crate::{Canonicalize, ConstitutionalError, Reasoning, Result, Vote, VoteChoice};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Number, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// How confidences are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    /// The weighted mean.
+    WeightedMean,
+    /// The lowest confidence.
+    Minimum,
+    /// The weighted product of the odds.
+    Bayesian,
+}
+
+impl Aggregation {
+    /// The method as written in an aggregate, e.g. `weighted_mean`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Aggregation::WeightedMean => "weighted_mean",
+            Aggregation::Minimum => "minimum",
+            Aggregation::Bayesian => "bayesian",
+        }
+    }
+
+    /// Combine confidences, each with its weight, in the order given.
+    ///
+    /// # Returns
+    /// The combined confidence, or a ProtocolError if there is nothing to combine or the
+    /// Bayesian combination meets both certainty and certainty of the opposite
+    fn combine(self, attestations: &[(f64, u32)]) -> Result<f64> {
+        if attestations.iter().all(|(_, weight)| *weight == 0) {
+            return Err(ConstitutionalError::ProtocolError("No weighted confidences to combine".to_string()));
+        }
+        match self {
+            Aggregation::WeightedMean => {
+                let total: f64 = attestations.iter().map(|(_, weight)| f64::from(*weight)).sum();
+                Ok(attestations.iter().map(|(p, weight)| p * f64::from(*weight)).sum::<f64>() / total)
+            }
+            Aggregation::Minimum => {
+                let counted = attestations.iter().filter(|(_, weight)| *weight > 0);
+                Ok(counted.map(|(p, _)| *p).fold(f64::INFINITY, f64::min))
+            }
+            Aggregation::Bayesian => {
+                let (mut for_claim, mut against) = (1.0, 1.0);
+                for (p, weight) in attestations {
+                    for_claim *= power(*p, *weight);
+                    against *= power(1.0 - p, *weight);
+                }
+                if for_claim + against == 0.0 {
+                    return Err(ConstitutionalError::ProtocolError(
+                        "Confidences of 0 and 1 cannot be combined".to_string(),
+                    ));
+                }
+                Ok(for_claim / (for_claim + against))
+            }
+        }
+    }
+}
+
+/// `base` to a whole power by repeated squaring, which, unlike `powi`, rounds the same
+/// way everywhere.
+fn power(mut base: f64, mut exponent: u32) -> f64 {
+    let mut result = 1.0;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        exponent >>= 1;
+    }
+    result
+}
+
+impl FromStr for Aggregation {
+    type Err = ConstitutionalError;
+
+    fn from_str(method: &str) -> Result<Aggregation> {
+        match method {
+            "weighted_mean" => Ok(Aggregation::WeightedMean),
+            "minimum" => Ok(Aggregation::Minimum),
+            "bayesian" => Ok(Aggregation::Bayesian),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown aggregation {:?}", method))),
+        }
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Combines agents' confidences by a method, weighting agents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfidenceAggregator {
+    method: Aggregation,
+    /// Agents' weights; agents not listed weigh 1
+    weights: BTreeMap<String, u32>,
+}
+
+impl ConfidenceAggregator {
+    /// An aggregator by a method, every agent weighing 1.
+    pub fn new(method: Aggregation) -> Self {
+        ConfidenceAggregator { method, weights: BTreeMap::new() }
+    }
+
+    /// Set an agent's weight; 0 leaves its confidence out.
+    pub fn weight(mut self, agent: &str, weight: u32) -> Self {
+        self.weights.insert(agent.to_string(), weight);
+        self
+    }
+
+    /// Combine agents' reasoning about a claim.
+    ///
+    /// # Arguments
+    /// * `subject` - What the claim is about, e.g. a contract ID
+    /// * `attestations` - Each agent with its reasoning, in any order
+    ///
+    /// # Returns
+    /// The aggregate, or a ProtocolError if an agent attests twice, a confidence is
+    /// outside [0, 1], or the confidences cannot be combined
+    pub fn aggregate(&self, subject: &str, attestations: &[(&str, &Reasoning)]) -> Result<AggregateConfidence> {
+        let mut by_agent = BTreeMap::new();
+        for (agent, reasoning) in attestations {
+            let confidence = reasoning.confidence.as_f64().filter(|p| (0.0..=1.0).contains(p));
+            let confidence = confidence.ok_or_else(|| {
+                let message = format!("{}'s confidence {} is outside [0, 1]", agent, reasoning.confidence);
+                ConstitutionalError::ProtocolError(message)
+            })?;
+            let weight = self.weights.get(*agent).copied().unwrap_or(1);
+            if by_agent.insert(agent.to_string(), Attestation { confidence, weight }).is_some() {
+                return Err(ConstitutionalError::ProtocolError(format!("{} attests twice", agent)));
+            }
+        }
+        AggregateConfidence::new(subject, self.method, by_agent)
+    }
+
+    /// Combine voters' confidence that a contract should be approved: an approval counts
+    /// its reasoning's confidence, a rejection one minus it. Abstentions and votes
+    /// without reasoning are left out.
+    ///
+    /// # Returns
+    /// The aggregate, with the contract as its subject, or a ProtocolError as for
+    /// `aggregate` or if a vote is on another contract
+    pub fn aggregate_votes(&self, contract_id: &str, votes: &[Vote]) -> Result<AggregateConfidence> {
+        let mut flipped = Vec::new();
+        for vote in votes {
+            if vote.contract_id != contract_id {
+                return Err(ConstitutionalError::ProtocolError(format!(
+                    "A vote by {} is on {}, not {}",
+                    vote.voter_agent, vote.contract_id, contract_id
+                )));
+            }
+            let reasoning = match (vote.choice, &vote.reasoning) {
+                (VoteChoice::Approve, Some(reasoning)) => reasoning.clone(),
+                (VoteChoice::Reject, Some(reasoning)) => {
+                    let against = reasoning.confidence.as_f64().and_then(|p| Number::from_f64(1.0 - p));
+                    let confidence = against.ok_or_else(|| {
+                        ConstitutionalError::ProtocolError(format!("{}'s confidence is not finite", vote.voter_agent))
+                    })?;
+                    Reasoning { confidence, ..reasoning.clone() }
+                }
+                _ => continue,
+            };
+            flipped.push((vote.voter_agent.as_str(), reasoning));
+        }
+        let attestations: Vec<(&str, &Reasoning)> = flipped.iter().map(|(agent, r)| (*agent, r)).collect();
+        self.aggregate(contract_id, &attestations)
+    }
+}
+
+/// One agent's confidence and weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attestation {
+    /// The agent's confidence, in [0, 1]
+    pub confidence: f64,
+    /// How many times it counts
+    pub weight: u32,
+}
+
+/// Agents' confidences in a claim and their combination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateConfidence {
+    /// What the claim is about
+    pub subject: String,
+    /// How the confidences were combined
+    pub method: Aggregation,
+    /// Each agent's confidence and weight
+    pub attestations: BTreeMap<String, Attestation>,
+    /// The combined confidence
+    pub confidence: f64,
+}
+
+impl AggregateConfidence {
+    fn new(subject: &str, method: Aggregation, attestations: BTreeMap<String, Attestation>) -> Result<Self> {
+        let inputs: Vec<(f64, u32)> = attestations.values().map(|a| (a.confidence, a.weight)).collect();
+        let confidence = method.combine(&inputs)?;
+        Ok(AggregateConfidence { subject: subject.to_string(), method, attestations, confidence })
+    }
+
+    /// Recombine the recorded confidences and check the recorded result.
+    ///
+    /// # Returns
+    /// true if the recorded confidence is the combination's, bit for bit, false
+    /// otherwise; a ProtocolError if the confidences cannot be combined
+    pub fn verify(&self) -> Result<bool> {
+        let recomputed = AggregateConfidence::new(&self.subject, self.method, self.attestations.clone())?;
+        Ok(recomputed.confidence.to_bits() == self.confidence.to_bits())
+    }
+
+    /// The aggregate's JSON object.
+    pub fn to_value(&self) -> Value {
+        let attestations: serde_json::Map<String, Value> = self
+            .attestations
+            .iter()
+            .map(|(agent, a)| (agent.clone(), json!({"confidence": a.confidence, "weight": a.weight})))
+            .collect();
+        json!({
+            "subject": self.subject,
+            "method": self.method.as_str(),
+            "attestations": attestations,
+            "confidence": self.confidence,
+        })
+    }
+}
+
+impl Canonicalize for AggregateConfidence {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl Serialize for AggregateConfidence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasoning(confidence: f64) -> Reasoning {
+        Reasoning::new("Well grounded", confidence).unwrap()
+    }
+
+    #[test]
+    fn test_methods_combine() {
+        let (high, low) = (reasoning(0.9), reasoning(0.6));
+        let attestations = [("gemini", &low), ("claude", &high)];
+        let combine = |aggregator: ConfidenceAggregator| aggregator.aggregate("c-17", &attestations).unwrap();
+
+        let mean = combine(ConfidenceAggregator::new(Aggregation::WeightedMean).weight("claude", 2));
+        assert_eq!(mean.confidence, (0.9 * 2.0 + 0.6) / 3.0);
+        assert_eq!(combine(ConfidenceAggregator::new(Aggregation::Minimum)).confidence, 0.6);
+        assert_eq!(combine(ConfidenceAggregator::new(Aggregation::Minimum).weight("gemini", 0)).confidence, 0.9);
+        let bayesian = combine(ConfidenceAggregator::new(Aggregation::Bayesian));
+        assert_eq!(bayesian.confidence, 0.9 * 0.6 / (0.9 * 0.6 + 0.1 * 0.4));
+        assert!(bayesian.confidence > 0.9);
+
+        // The order attestations arrive in does not change the bits
+        let reversed = [("claude", &high), ("gemini", &low)];
+        let again = ConfidenceAggregator::new(Aggregation::WeightedMean).weight("claude", 2);
+        assert_eq!(again.aggregate("c-17", &reversed).unwrap().semantic_hash().unwrap(), mean.semantic_hash().unwrap());
+        assert!(mean.verify().unwrap());
+        assert_eq!(mean.to_value()["attestations"]["claude"], json!({"confidence": 0.9, "weight": 2}));
+        let mut forged = mean.clone();
+        forged.confidence = 0.95;
+        assert!(!forged.verify().unwrap());
+    }
+
+    #[test]
+    fn test_votes_and_bad_inputs() {
+        let vote = |agent: &str, choice, confidence| {
+            Vote::builder()
+                .contract_id("c-17")
+                .voter_agent(agent)
+                .choice(choice)
+                .reasoning(reasoning(confidence))
+                .timestamp("2025-11-21T09:00:00Z")
+                .build()
+                .unwrap()
+        };
+        let votes = [
+            vote("claude", VoteChoice::Approve, 0.75),
+            vote("gemini", VoteChoice::Reject, 0.75),
+            vote("grok", VoteChoice::Abstain, 0.5),
+        ];
+        let aggregate = ConfidenceAggregator::new(Aggregation::WeightedMean).aggregate_votes("c-17", &votes).unwrap();
+        assert_eq!(aggregate.confidence, 0.5);
+        assert_eq!(aggregate.attestations.len(), 2);
+
+        let bayesian = ConfidenceAggregator::new(Aggregation::Bayesian);
+        let (sure, doubtful) = (reasoning(1.0), reasoning(0.0));
+        assert!(bayesian.aggregate("c-17", &[("claude", &sure), ("gemini", &doubtful)]).is_err());
+        assert!(bayesian.aggregate("c-17", &[("claude", &sure), ("claude", &sure)]).is_err());
+        assert!(bayesian.aggregate("c-17", &[("claude", &reasoning(1.5))]).is_err());
+        assert!(bayesian.aggregate("c-17", &[]).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+/// algorithm.rs - Hash functions applied to the canonical bytes
+///
+/// SHA-256 is the protocol default and the algorithm canonicalizer.py and canonicalizer.js
+/// use. SHA-512 comes with the `sha2` dependency; SHA3-256 and BLAKE3 are behind the
+/// `sha3` and `blake3` features for deployments that mandate them.
+///
+/// A digest means nothing without its algorithm, so each algorithm has a short
+/// identifier (`sha256`, `sha512`, `sha3-256`, `blake3`) that verification accepts as a
+/// prefix of the expected hash, as in `blake3:6437b3ac...`.
+///
+/// `CanonicalHasher` hashes canonical bytes that arrive in chunks. With the `blake3`
+/// feature it can also run BLAKE3 in keyed mode, for tenants whose hashes must not be
+/// reproducible by anyone who merely knows the canonicalization.
+
+use crate::encoding::digest_text;
+use crate::{versioned_hasher, CanonicalizeOptions, ConstitutionalError, Encoding, Result, SemanticHash};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+
+/// Hash function used for semantic hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashAlgorithm {
+    /// SHA-256 (default); matches the Python and JavaScript implementations.
+    #[default]
+    Sha256,
+    /// SHA-512, for deployments that want a 512-bit digest.
+    Sha512,
+    /// SHA3-256 (FIPS 202). Requires the `sha3` feature.
+    #[cfg(feature = "sha3")]
+    Sha3_256,
+    /// BLAKE3 with 256-bit output. Requires the `blake3` feature.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Short identifier naming the algorithm in tagged hashes, e.g. `sha3-256`.
+    pub fn identifier(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            #[cfg(feature = "sha3")]
+            HashAlgorithm::Sha3_256 => "sha3-256",
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Length of the digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha512 => 64,
+            _ => 32,
+        }
+    }
+
+    /// Code of the algorithm in the multicodec table, which prefixes multihash digests.
+    pub fn multihash_code(self) -> u64 {
+        match self {
+            HashAlgorithm::Sha256 => 0x12,
+            HashAlgorithm::Sha512 => 0x13,
+            #[cfg(feature = "sha3")]
+            HashAlgorithm::Sha3_256 => 0x16,
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => 0x1e,
+        }
+    }
+
+    /// Every algorithm compiled in, in declaration order.
+    pub fn all() -> &'static [HashAlgorithm] {
+        &[
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha512,
+            #[cfg(feature = "sha3")]
+            HashAlgorithm::Sha3_256,
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3,
+        ]
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = ConstitutionalError;
+
+    fn from_str(identifier: &str) -> Result<HashAlgorithm> {
+        match identifier {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            #[cfg(feature = "sha3")]
+            "sha3-256" => Ok(HashAlgorithm::Sha3_256),
+            #[cfg(feature = "blake3")]
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            _ => Err(ConstitutionalError::HashingError(format!(
+                "Unknown or disabled hash algorithm {:?}",
+                identifier
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.identifier())
+    }
+}
+
+/// A hash in progress under any `HashAlgorithm`, fed canonical bytes through `io::Write`.
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    #[cfg(feature = "sha3")]
+    Sha3_256(sha3::Sha3_256),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// Incremental semantic hash of canonical bytes that arrive in chunks, such as a
+/// canonical document streamed back from a ledger file.
+///
+/// The canonicalization version tag is fed first, so `finalize` returns exactly what
+/// `semantic_hash_with` returns for the document whose canonical form was fed, and the
+/// digest checks out with `verify_semantic_hash_with`. Chunks are hashed as given; they
+/// are not checked to be canonical.
+pub struct CanonicalHasher {
+    hasher: Hasher,
+    encoding: Encoding,
+}
+
+impl CanonicalHasher {
+    /// Start a hash under the options' algorithm and canonicalization version.
+    pub fn new(options: &CanonicalizeOptions) -> CanonicalHasher {
+        CanonicalHasher { hasher: versioned_hasher(options), encoding: options.hash_encoding }
+    }
+
+    /// Start a BLAKE3 hash in keyed mode under the options' canonicalization version.
+    /// The options' hash algorithm is ignored.
+    #[cfg(feature = "blake3")]
+    pub fn keyed(key: &[u8; 32], options: &CanonicalizeOptions) -> CanonicalHasher {
+        let mut hasher = Hasher::Blake3(Box::new(blake3::Hasher::new_keyed(key)));
+        hasher.update(options.canon_version.tag().as_bytes());
+        CanonicalHasher { hasher, encoding: options.hash_encoding }
+    }
+
+    /// Feed the next chunk of canonical bytes.
+    pub fn update(&mut self, chunk: &[u8]) -> &mut CanonicalHasher {
+        self.hasher.update(chunk);
+        self
+    }
+
+    /// The digest, written in the options' encoding; a HashingError under
+    /// `Encoding::Binary`, for which `finalize_bytes` returns the digest itself.
+    pub fn finalize(self) -> Result<String> {
+        digest_text(self.encoding, &self.hasher.finalize())
+    }
+
+    /// The digest as a `SemanticHash`; a HashingError under SHA-512.
+    pub fn finalize_hash(self) -> Result<SemanticHash> {
+        SemanticHash::try_from(self.hasher.finalize().as_slice())
+    }
+
+    /// The digest bytes, whatever the options' encoding.
+    pub fn finalize_bytes(self) -> Vec<u8> {
+        self.hasher.finalize()
+    }
+}
+
+impl std::io::Write for CanonicalHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            #[cfg(feature = "sha3")]
+            HashAlgorithm::Sha3_256 => Hasher::Sha3_256(sha3::Sha3_256::new()),
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Sha512(hasher) => hasher.update(bytes),
+            #[cfg(feature = "sha3")]
+            Hasher::Sha3_256(hasher) => hasher.update(bytes),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            #[cfg(feature = "sha3")]
+            Hasher::Sha3_256(hasher) => hasher.finalize().to_vec(),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+impl std::io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Lowercase hex encoding.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        // Writing to a String cannot fail
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// Decode hex in either case; None if `text` is not an even number of hex digits.
+pub(crate) fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    // Digit by digit, since `u8::from_str_radix` would also take a leading `+`
+    let digit = |byte: u8| char::from(byte).to_digit(16);
+    text.as_bytes().chunks(2).map(|pair| Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(algorithm: HashAlgorithm, bytes: &[u8]) -> String {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(bytes);
+        hex(&hasher.finalize())
+    }
+
+    #[test]
+    fn test_known_answers() {
+        // FIPS 180-2 and the BLAKE3 reference vectors for "abc"
+        #[allow(unused_mut)]
+        let mut cases = vec![
+            (HashAlgorithm::Sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                HashAlgorithm::Sha512,
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                 2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+        ];
+        #[cfg(feature = "sha3")]
+        cases.push((HashAlgorithm::Sha3_256, "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"));
+        #[cfg(feature = "blake3")]
+        cases.push((HashAlgorithm::Blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"));
+
+        for (algorithm, expected) in cases {
+            let hash = digest(algorithm, b"abc");
+            assert_eq!(hash, expected, "{}", algorithm);
+            assert_eq!(hash.len(), algorithm.digest_len() * 2);
+            assert_eq!(algorithm.identifier().parse::<HashAlgorithm>().unwrap(), algorithm);
+        }
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_canonical_hasher_matches_semantic_hash() {
+        let data = serde_json::json!({"ledger": [{"action_id": "001", "agent": "agent-7"}], "height": 12});
+        for options in [
+            CanonicalizeOptions::new(),
+            CanonicalizeOptions::new().canon_version(crate::CanonVersion::V1).hash_algorithm(HashAlgorithm::Sha512),
+        ] {
+            let canonical = crate::canonicalize_with(&data, &options).unwrap();
+            let mut hasher = CanonicalHasher::new(&options);
+            for chunk in canonical.as_bytes().chunks(7) {
+                hasher.update(chunk);
+            }
+            let hash = hasher.finalize().unwrap();
+            assert_eq!(hash, crate::semantic_hash_with(&data, &options).unwrap());
+            assert!(crate::verify_semantic_hash_with(&data, &hash, &options).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_unhex_takes_only_hex_digits() {
+        assert_eq!(unhex("00fFa9"), Some(vec![0x00, 0xff, 0xa9]));
+        assert_eq!(unhex(""), Some(vec![]));
+        for text in ["+f", "0", "-1", " f", "0x", "g0", "é0"] {
+            assert_eq!(unhex(text), None, "{:?}", text);
+        }
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_keyed_blake3() {
+        let data = serde_json::json!({"tenant": "acme", "amount": 100});
+        let options = CanonicalizeOptions::new();
+        let key = [7u8; 32];
+
+        let keyed = crate::semantic_hash_keyed(&data, &key).unwrap();
+        assert_eq!(keyed.len(), 64);
+        assert_ne!(keyed, crate::semantic_hash_keyed(&data, &[8u8; 32]).unwrap());
+        assert_ne!(keyed, crate::semantic_hash_using(&data, HashAlgorithm::Blake3).unwrap());
+        assert_eq!(
+            keyed,
+            blake3::keyed_hash(&key, crate::canonicalize(&data, true).unwrap().as_bytes()).to_hex().to_string()
+        );
+
+        assert!(crate::verify_semantic_hash_keyed(&data, &key, &keyed, &options).unwrap());
+        assert!(!crate::verify_semantic_hash_keyed(&data, &[8u8; 32], &keyed, &options).unwrap());
+    }
+}
@@ -0,0 +1,314 @@
+/// amendment_graph.rs - Ordering pending amendments by their dependencies
+///
+/// Each amendment replaces or changes one article, its target, and may list in `depends_on` the
+/// amendments that must be applied before it. An `AmendmentGraph` over the pending
+/// amendments checks that they can be applied at all and gives the order to apply them
+/// in:
+///
+/// - every dependency is pending too, or marked as applied already;
+/// - the dependencies have no cycle;
+/// - no two amendments target the same article unless one depends on the other, directly
+///   or through others, since otherwise nothing says which replacement wins.
+///
+/// The order is topological, and among the amendments free to go next at any point the
+/// one with the lowest ID in byte order goes first, so every node derives the same order
+/// from the same set.
+
+use crate::{Amendment, Constitution, ConstitutionalError, Result};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A reason a set of amendments cannot be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphIssue {
+    /// Two pending amendments with the same ID.
+    DuplicateId(String),
+    /// An amendment has no `article_id` to target.
+    NoTarget(String),
+    /// A dependency that is neither pending nor applied.
+    MissingDependency { amendment: String, dependency: String },
+    /// Amendments that depend on each other in a cycle, by ID.
+    Cycle(Vec<String>),
+    /// Two amendments target the same article and neither depends on the other.
+    Conflict { article: String, amendments: [String; 2] },
+}
+
+impl fmt::Display for GraphIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphIssue::DuplicateId(id) => write!(f, "amendment {} is pending twice", id),
+            GraphIssue::NoTarget(id) => write!(f, "amendment {} targets no article", id),
+            GraphIssue::MissingDependency { amendment, dependency } => {
+                write!(f, "amendment {} depends on {}, which is neither pending nor applied", amendment, dependency)
+            }
+            GraphIssue::Cycle(ids) => write!(f, "amendments {} depend on each other", ids.join(", ")),
+            GraphIssue::Conflict { article, amendments: [a, b] } => {
+                write!(f, "amendments {} and {} both replace article {} in no set order", a, b, article)
+            }
+        }
+    }
+}
+
+/// Pending amendments and the dependencies between them.
+#[derive(Debug, Clone)]
+pub struct AmendmentGraph<'a> {
+    /// The pending amendments, by ID
+    amendments: BTreeMap<&'a str, &'a Amendment>,
+    /// Amendments whose IDs repeat an earlier one's
+    duplicates: Vec<String>,
+    /// IDs of amendments applied already, which satisfy dependencies on them
+    applied: BTreeSet<String>,
+}
+
+impl<'a> AmendmentGraph<'a> {
+    /// The graph of a set of pending amendments.
+    pub fn new(amendments: &'a [Amendment]) -> Self {
+        let mut by_id = BTreeMap::new();
+        let mut duplicates = Vec::new();
+        for amendment in amendments {
+            match by_id.entry(amendment.id.as_str()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(amendment);
+                }
+                Entry::Occupied(_) => duplicates.push(amendment.id.clone()),
+            }
+        }
+        AmendmentGraph { amendments: by_id, duplicates, applied: BTreeSet::new() }
+    }
+
+    /// Mark an amendment as applied already, so amendments may depend on it.
+    pub fn applied(mut self, id: &str) -> Self {
+        self.applied.insert(id.to_string());
+        self
+    }
+
+    /// The pending amendments an amendment depends on, in ID order.
+    fn pending_dependencies(&self, amendment: &Amendment) -> BTreeSet<&'a str> {
+        amendment
+            .depends_on
+            .iter()
+            .filter_map(|id| self.amendments.get_key_value(id.as_str()))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Whether `from` depends on `to`, directly or through other pending amendments.
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            for dependency in self.pending_dependencies(self.amendments[id]) {
+                if dependency == to {
+                    return true;
+                }
+                stack.push(dependency);
+            }
+        }
+        false
+    }
+
+    /// The cycles among the pending amendments: each set of amendments that depend on
+    /// each other, sorted, in order of their lowest ID.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles: Vec<Vec<String>> = Vec::new();
+        for id in self.amendments.keys() {
+            if cycles.iter().any(|cycle| cycle.iter().any(|member| member == id)) {
+                continue;
+            }
+            // An amendment on a cycle reaches itself, and so is in its own set
+            let cycle: Vec<String> = self
+                .amendments
+                .keys()
+                .filter(|other| self.reaches(id, other) && self.reaches(other, id))
+                .map(|other| other.to_string())
+                .collect();
+            if !cycle.is_empty() {
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    /// The pairs of amendments that target the same article in no set order, by
+    /// article and then IDs.
+    pub fn conflicts(&self) -> Vec<GraphIssue> {
+        let mut by_article: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for (id, amendment) in &self.amendments {
+            if let Some(article) = amendment.article_id() {
+                by_article.entry(article).or_default().push(id);
+            }
+        }
+        let mut conflicts = Vec::new();
+        for (article, ids) in by_article {
+            for (i, a) in ids.iter().enumerate() {
+                for b in &ids[i + 1..] {
+                    if !self.reaches(a, b) && !self.reaches(b, a) {
+                        conflicts.push(GraphIssue::Conflict {
+                            article: article.clone(),
+                            amendments: [a.to_string(), b.to_string()],
+                        });
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Everything that keeps the amendments from being applied.
+    ///
+    /// # Returns
+    /// Duplicate IDs, amendments without a target, missing dependencies, cycles and
+    /// conflicts, in that order; empty if the amendments can be applied
+    pub fn issues(&self) -> Vec<GraphIssue> {
+        let mut issues: Vec<GraphIssue> = self.duplicates.iter().cloned().map(GraphIssue::DuplicateId).collect();
+        for (id, amendment) in &self.amendments {
+            if amendment.article_id().is_none() {
+                issues.push(GraphIssue::NoTarget(id.to_string()));
+            }
+        }
+        for (id, amendment) in &self.amendments {
+            for dependency in &amendment.depends_on {
+                if !self.amendments.contains_key(dependency.as_str()) && !self.applied.contains(dependency) {
+                    issues.push(GraphIssue::MissingDependency {
+                        amendment: id.to_string(),
+                        dependency: dependency.clone(),
+                    });
+                }
+            }
+        }
+        issues.extend(self.cycles().into_iter().map(GraphIssue::Cycle));
+        issues.extend(self.conflicts());
+        issues
+    }
+
+    /// The order to apply the amendments in: each after those it depends on, ties
+    /// broken by lowest ID.
+    ///
+    /// # Returns
+    /// The amendments in order, or a ProtocolError listing every issue if they cannot
+    /// be applied
+    pub fn order(&self) -> Result<Vec<&'a Amendment>> {
+        let issues = self.issues();
+        if !issues.is_empty() {
+            let issues: Vec<String> = issues.iter().map(GraphIssue::to_string).collect();
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "The amendments cannot be applied: {}",
+                issues.join("; ")
+            )));
+        }
+        let mut waiting: BTreeMap<&str, BTreeSet<&str>> =
+            self.amendments.iter().map(|(id, amendment)| (*id, self.pending_dependencies(amendment))).collect();
+        let mut ready: BTreeSet<&str> = waiting.iter().filter(|(_, deps)| deps.is_empty()).map(|(id, _)| *id).collect();
+        let mut order = Vec::with_capacity(self.amendments.len());
+        while let Some(id) = ready.pop_first() {
+            waiting.remove(id);
+            order.push(self.amendments[id]);
+            for (other, deps) in waiting.iter_mut() {
+                if deps.remove(id) && deps.is_empty() {
+                    ready.insert(other);
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Apply the amendments to a constitution in `order`.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if they cannot be applied, in which case the constitution
+    /// is unchanged
+    pub fn apply(&self, constitution: &mut Constitution) -> Result<()> {
+        let mut amended = constitution.clone();
+        for amendment in self.order()? {
+            amendment.apply(&mut amended)?;
+        }
+        *constitution = amended;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reasoning;
+    use serde_json::json;
+
+    fn amendment(id: &str, article: &str, depends_on: &[&str]) -> Amendment {
+        let builder = Amendment::builder()
+            .id(id)
+            .proposer_agent("Claude")
+            .article(json!({"article_id": article, "text": id}))
+            .reasoning(Reasoning::new("Needed", 0.9).unwrap())
+            .timestamp("2025-11-20T14:30:00Z");
+        depends_on.iter().fold(builder, |builder, id| builder.depends_on(id)).build().unwrap()
+    }
+
+    fn ids(order: &[&Amendment]) -> Vec<String> {
+        order.iter().map(|amendment| amendment.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_order_follows_dependencies() {
+        let pending = [
+            amendment("d", "V", &["b", "c"]),
+            amendment("c", "IV", &[]),
+            amendment("b", "III", &["a"]),
+            amendment("a", "III", &[]),
+            amendment("e", "VI", &["applied-1"]),
+        ];
+        let value = pending[0].to_value();
+        assert_eq!(value["depends_on"], json!(["b", "c"]));
+        assert_eq!(serde_json::from_value::<Amendment>(value).unwrap(), pending[0]);
+        assert!(pending[1].to_value().get("depends_on").is_none());
+
+        let graph = AmendmentGraph::new(&pending).applied("applied-1");
+        assert_eq!(graph.issues(), []);
+        assert_eq!(ids(&graph.order().unwrap()), ["a", "b", "c", "d", "e"]);
+
+        let mut reversed = pending.to_vec();
+        reversed.reverse();
+        let order = AmendmentGraph::new(&reversed).applied("applied-1").order().unwrap();
+        assert_eq!(ids(&order), ids(&graph.order().unwrap()));
+
+        let mut constitution =
+            Constitution::from_value(json!({"articles": [{"article_id": "III", "text": "original"}]})).unwrap();
+        graph.apply(&mut constitution).unwrap();
+        assert_eq!(constitution.to_value()["articles"][0], json!({"article_id": "III", "text": "b"}));
+    }
+
+    #[test]
+    fn test_issues_are_reported() {
+        let pending = [
+            amendment("a", "I", &["c"]),
+            amendment("b", "II", &["a"]),
+            amendment("c", "III", &["b"]),
+            amendment("d", "IV", &["d"]),
+            amendment("e", "IV", &[]),
+            amendment("f", "VI", &["gone"]),
+            amendment("f", "VI", &[]),
+        ];
+        let graph = AmendmentGraph::new(&pending);
+        let issues = graph.issues();
+        assert_eq!(
+            issues,
+            [
+                GraphIssue::DuplicateId("f".to_string()),
+                GraphIssue::MissingDependency { amendment: "f".to_string(), dependency: "gone".to_string() },
+                GraphIssue::Cycle(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
+                GraphIssue::Cycle(vec!["d".to_string()]),
+                GraphIssue::Conflict { article: "IV".to_string(), amendments: ["d".to_string(), "e".to_string()] },
+            ]
+        );
+        let error = graph.order().unwrap_err().to_string();
+        assert!(error.contains("amendments a, b, c depend on each other"), "{}", error);
+
+        let mut constitution = Constitution::from_value(json!({"articles": []})).unwrap();
+        let before = constitution.clone();
+        assert!(graph.apply(&mut constitution).is_err());
+        assert_eq!(constitution, before);
+    }
+}
@@ -0,0 +1,397 @@
+/// bls.rs - BLS12-381 attestations that aggregate across agents
+///
+/// Ratifying a contract takes an attestation from each of N agents, and N separate
+/// signatures per contract make the ledger grow with N. BLS signatures over the same
+/// message add up instead: N attestations over one hash envelope compress into a single
+/// 96-byte aggregate signature, checked against the signers' public keys with one pairing
+/// check. An `AggregateAttestation` names the signers with a bitmap over the registered
+/// agent set:
+///
+/// ```json
+/// {"hash_envelope":{...},"signers":"0b","signature":"bls12-381:a3f1..."}
+/// ```
+///
+/// Bit `i` of the bitmap (byte `i / 8`, least significant bit first) is set when agent
+/// `i` of the set signed. The message is the one `signing.rs` signs. Signatures use the
+/// proof-of-possession ciphersuite of the IETF BLS draft, with public keys in G1 and
+/// signatures in G2. Adding up keys over one message is only safe when every key's owner
+/// has shown it holds the secret key, so an agent's key should be registered only after
+/// `BlsPublicKey::verify_possession` accepts its proof. Requires the `bls` feature.
+
+use crate::algorithm::{hex, unhex};
+use crate::commitment::os_random;
+use crate::objects::take;
+use crate::signing::signing_message;
+use crate::{ConstitutionalError, HashEnvelope, Result, Secret};
+use blst::min_pk::{AggregatePublicKey, AggregateSignature, PublicKey, SecretKey, Signature};
+use blst::{blst_scalar, BLST_ERROR};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+const PREFIX: &str = "bls12-381";
+const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+const POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+const FIELDS: &[&str] = &["hash_envelope", "signers", "signature"];
+
+/// A BLS12-381 secret key and its public key.
+#[derive(Clone)]
+pub struct BlsKeypair {
+    secret: SecretKey,
+}
+
+impl BlsKeypair {
+    /// A fresh keypair from the operating system's random number generator.
+    ///
+    /// # Returns
+    /// The keypair, or an IoError if no random bytes could be read
+    pub fn generate() -> Result<BlsKeypair> {
+        let mut seed = Secret::new([0u8; 32]);
+        os_random(seed.expose_mut())?;
+        BlsKeypair::from_seed(seed.expose())
+    }
+
+    /// The keypair the standard KeyGen derives from a secret seed.
+    ///
+    /// # Returns
+    /// The keypair, or a HashingError if the seed is shorter than 32 bytes
+    pub fn from_seed(seed: &[u8]) -> Result<BlsKeypair> {
+        let secret = SecretKey::key_gen(seed, &[])
+            .map_err(|_| ConstitutionalError::HashingError("A BLS key seed needs at least 32 bytes".to_string()))?;
+        Ok(BlsKeypair { secret })
+    }
+
+    /// The 32-byte secret key. Keep it out of logs and unencrypted storage.
+    pub fn secret_bytes(&self) -> Secret<[u8; 32]> {
+        Secret::new(self.secret.to_bytes())
+    }
+
+    /// The public key.
+    pub fn public_key(&self) -> BlsPublicKey {
+        BlsPublicKey(self.secret.sk_to_pk().to_bytes())
+    }
+
+    /// Attest to a hash envelope.
+    ///
+    /// # Returns
+    /// The signature as `bls12-381:<hex>`, ready for `AggregateAttestation::aggregate`
+    pub fn attest(&self, envelope: &HashEnvelope) -> Result<String> {
+        let signature = self.secret.sign(&signing_message(envelope)?, SIGNATURE_DST, &[]);
+        Ok(format!("{}:{}", PREFIX, hex(&signature.to_bytes())))
+    }
+
+    /// Proof that this keypair's owner holds the secret key, to register the public key with.
+    pub fn proof_of_possession(&self) -> String {
+        let proof = self.secret.sign(&self.public_key().0, POP_DST, &[]);
+        format!("{}:{}", PREFIX, hex(&proof.to_bytes()))
+    }
+}
+
+impl fmt::Debug for BlsKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlsKeypair").field("public_key", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+/// A BLS12-381 public key, a compressed G1 point written as `bls12-381:<hex>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlsPublicKey([u8; 48]);
+
+impl BlsPublicKey {
+    /// The 48-byte compressed point.
+    pub fn as_bytes(&self) -> &[u8; 48] {
+        &self.0
+    }
+
+    /// Check a proof of possession made by `BlsKeypair::proof_of_possession`.
+    ///
+    /// # Returns
+    /// true if the proof is valid for this key, false otherwise; a HashingError if it is
+    /// not a well-formed BLS signature
+    pub fn verify_possession(&self, proof: &str) -> Result<bool> {
+        let proof = parse_signature(proof)?;
+        Ok(proof.verify(true, &self.0, POP_DST, &[], &self.point(), true) == BLST_ERROR::BLST_SUCCESS)
+    }
+
+    fn point(&self) -> PublicKey {
+        PublicKey::from_bytes(&self.0).expect("public keys are validated when created")
+    }
+}
+
+impl fmt::Display for BlsPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", PREFIX, hex(&self.0))
+    }
+}
+
+impl FromStr for BlsPublicKey {
+    type Err = ConstitutionalError;
+
+    fn from_str(text: &str) -> Result<BlsPublicKey> {
+        let key = strip_prefix(text)
+            .filter(|bytes| PublicKey::key_validate(bytes).is_ok())
+            .and_then(|bytes| <[u8; 48]>::try_from(bytes.as_slice()).ok())
+            .ok_or_else(|| ConstitutionalError::HashingError(format!("Not a BLS12-381 public key: {}", text)))?;
+        Ok(BlsPublicKey(key))
+    }
+}
+
+/// Check one agent's attestation before it is aggregated.
+///
+/// # Returns
+/// true if `signer` made the signature over the envelope, false otherwise; a
+/// HashingError if it is not a well-formed BLS signature
+pub fn verify_attestation(envelope: &HashEnvelope, signature: &str, signer: &BlsPublicKey) -> Result<bool> {
+    let signature = parse_signature(signature)?;
+    let message = signing_message(envelope)?;
+    Ok(signature.verify(true, &message, SIGNATURE_DST, &[], &signer.point(), true) == BLST_ERROR::BLST_SUCCESS)
+}
+
+/// Attestations of several agents over one hash envelope, as one aggregate signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateAttestation {
+    /// Semantic hash of the attested payload and how it was computed
+    pub hash_envelope: HashEnvelope,
+    /// Bitmap of the agents who signed, one bit per agent of the set
+    pub signers: Vec<u8>,
+    /// Aggregate signature, as `bls12-381:<hex>`
+    pub signature: String,
+}
+
+impl AggregateAttestation {
+    /// Aggregate the attestations of agents over one envelope.
+    ///
+    /// # Arguments
+    /// * `envelope` - The envelope every attestation signs
+    /// * `attestations` - Each signer's index in the agent set and its signature from
+    ///   `BlsKeypair::attest`
+    /// * `agent_count` - Size of the agent set, which fixes the bitmap's length
+    ///
+    /// # Returns
+    /// The aggregate, or a HashingError if there are no attestations, an index is out of
+    /// range or repeated, or a signature is malformed
+    pub fn aggregate(
+        envelope: &HashEnvelope,
+        attestations: &[(usize, &str)],
+        agent_count: usize,
+    ) -> Result<AggregateAttestation> {
+        let mut signers = vec![0u8; agent_count.div_ceil(8)];
+        let mut signatures = Vec::with_capacity(attestations.len());
+        for &(index, signature) in attestations {
+            if index >= agent_count || signers[index / 8] >> (index % 8) & 1 == 1 {
+                return Err(ConstitutionalError::HashingError(format!(
+                    "Agent {} is out of range or attested twice",
+                    index
+                )));
+            }
+            signers[index / 8] |= 1 << (index % 8);
+            signatures.push(parse_signature(signature)?);
+        }
+        let refs: Vec<_> = signatures.iter().collect();
+        let aggregate = AggregateSignature::aggregate(&refs, true)
+            .map_err(|_| ConstitutionalError::HashingError("Cannot aggregate these BLS signatures".to_string()))?;
+        Ok(AggregateAttestation {
+            hash_envelope: envelope.clone(),
+            signers,
+            signature: format!("{}:{}", PREFIX, hex(&aggregate.to_signature().to_bytes())),
+        })
+    }
+
+    /// Indices in the agent set of the agents who signed, in increasing order.
+    pub fn signer_indices(&self) -> Vec<usize> {
+        (0..self.signers.len() * 8).filter(|i| self.signers[i / 8] >> (i % 8) & 1 == 1).collect()
+    }
+
+    /// Check the aggregate signature against the agent set it was made over.
+    ///
+    /// # Arguments
+    /// * `agents` - Public keys of the registered agents, in bitmap order
+    ///
+    /// # Returns
+    /// true if every agent in the bitmap signed the envelope and someone did, false
+    /// otherwise; a HashingError if the bitmap does not fit the set or the signature is
+    /// malformed
+    pub fn verify(&self, agents: &[BlsPublicKey]) -> Result<bool> {
+        let signature = parse_signature(&self.signature)?;
+        let keys = self.signer_keys(agents)?;
+        if keys.is_empty() {
+            return Ok(false);
+        }
+        let refs: Vec<_> = keys.iter().collect();
+        let message = signing_message(&self.hash_envelope)?;
+        Ok(signature.fast_aggregate_verify(true, &message, SIGNATURE_DST, &refs) == BLST_ERROR::BLST_SUCCESS)
+    }
+
+    fn signer_keys(&self, agents: &[BlsPublicKey]) -> Result<Vec<PublicKey>> {
+        let indices = self.signer_indices();
+        if self.signers.len() != agents.len().div_ceil(8) || indices.last().is_some_and(|&i| i >= agents.len()) {
+            return Err(ConstitutionalError::HashingError(format!(
+                "A signer bitmap of {} bytes does not fit a set of {} agents",
+                self.signers.len(),
+                agents.len()
+            )));
+        }
+        Ok(indices.into_iter().map(|i| agents[i].point()).collect())
+    }
+}
+
+/// Check many aggregate attestations at once, e.g. a ledger batch, with one multi-pairing
+/// instead of one pairing check each. Each attestation is weighted by a random scalar so
+/// an invalid one cannot be cancelled out by another.
+///
+/// # Arguments
+/// * `batch` - Each attestation with the agent set it was made over
+///
+/// # Returns
+/// true if every attestation verifies, false if any does not; an error if one is
+/// malformed or no random scalars could be drawn
+pub fn verify_aggregates(batch: &[(&AggregateAttestation, &[BlsPublicKey])]) -> Result<bool> {
+    let mut messages = Vec::with_capacity(batch.len());
+    let mut keys = Vec::with_capacity(batch.len());
+    let mut signatures = Vec::with_capacity(batch.len());
+    for (attestation, agents) in batch {
+        let signers = attestation.signer_keys(agents)?;
+        if signers.is_empty() {
+            return Ok(false);
+        }
+        let refs: Vec<_> = signers.iter().collect();
+        let key = AggregatePublicKey::aggregate(&refs, false)
+            .map_err(|_| ConstitutionalError::HashingError("Cannot aggregate these BLS public keys".to_string()))?;
+        keys.push(key.to_public_key());
+        messages.push(signing_message(&attestation.hash_envelope)?);
+        signatures.push(parse_signature(&attestation.signature)?);
+    }
+    if batch.is_empty() {
+        return Ok(true);
+    }
+    let mut scalars = vec![blst_scalar::default(); batch.len()];
+    for scalar in &mut scalars {
+        os_random(&mut scalar.b[..8])?;
+        scalar.b[0] |= 1;
+    }
+    let messages: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+    let keys: Vec<_> = keys.iter().collect();
+    let signatures: Vec<_> = signatures.iter().collect();
+    let result = Signature::verify_multiple_aggregate_signatures(
+        &messages,
+        SIGNATURE_DST,
+        &keys,
+        false,
+        &signatures,
+        true,
+        &scalars,
+        64,
+    );
+    Ok(result == BLST_ERROR::BLST_SUCCESS)
+}
+
+fn parse_signature(text: &str) -> Result<Signature> {
+    strip_prefix(text)
+        .and_then(|bytes| Signature::from_bytes(&bytes).ok())
+        .ok_or_else(|| ConstitutionalError::HashingError(format!("Not a BLS12-381 signature: {}", text)))
+}
+
+fn strip_prefix(text: &str) -> Option<Vec<u8>> {
+    text.strip_prefix(PREFIX)?.strip_prefix(':').and_then(unhex)
+}
+
+impl Serialize for AggregateAttestation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AggregateAttestation", FIELDS.len())?;
+        state.serialize_field("hash_envelope", &self.hash_envelope)?;
+        state.serialize_field("signers", &hex(&self.signers))?;
+        state.serialize_field("signature", &self.signature)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AggregateAttestation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<AggregateAttestation, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected an aggregate attestation object"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        let signers: String = take(&mut map, "signers")?;
+        Ok(AggregateAttestation {
+            hash_envelope: take(&mut map, "hash_envelope")?,
+            signers: unhex(&signers).ok_or_else(|| de::Error::custom("signers: expected a hex bitmap"))?,
+            signature: take(&mut map, "signature")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash_envelope, CanonicalizeOptions};
+    use serde_json::json;
+
+    fn agents(n: u8) -> Vec<BlsKeypair> {
+        (0..n).map(|i| BlsKeypair::from_seed(&[i; 32]).unwrap()).collect()
+    }
+
+    fn envelope(contract: &str) -> HashEnvelope {
+        semantic_hash_envelope(&json!({"contract_id": contract, "action_type": "ratify"}), &CanonicalizeOptions::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_attestation() {
+        let keypairs = agents(10);
+        let set: Vec<_> = keypairs.iter().map(BlsKeypair::public_key).collect();
+        let envelope = envelope("c-17");
+        let signatures: Vec<_> = [0, 3, 4, 9].iter().map(|&i| (i, keypairs[i].attest(&envelope).unwrap())).collect();
+        assert!(verify_attestation(&envelope, &signatures[1].1, &set[3]).unwrap());
+        assert!(!verify_attestation(&envelope, &signatures[1].1, &set[2]).unwrap());
+
+        let refs: Vec<_> = signatures.iter().map(|(i, s)| (*i, s.as_str())).collect();
+        let aggregate = AggregateAttestation::aggregate(&envelope, &refs, set.len()).unwrap();
+        assert_eq!(aggregate.signer_indices(), [0, 3, 4, 9]);
+        assert_eq!(aggregate.signers, [0x19, 0x02]);
+        assert!(aggregate.verify(&set).unwrap());
+
+        let stored = serde_json::to_string(&aggregate).unwrap();
+        assert!(stored.contains(r#""signers":"1902""#));
+        assert_eq!(serde_json::from_str::<AggregateAttestation>(&stored).unwrap(), aggregate);
+
+        // Claiming an agent who did not sign fails
+        let mut inflated = aggregate.clone();
+        inflated.signers[0] |= 0x02;
+        assert!(!inflated.verify(&set).unwrap());
+        assert!(aggregate.verify(&set[..8]).is_err());
+        assert!(AggregateAttestation::aggregate(&envelope, &[refs[0], refs[0]], set.len()).is_err());
+        assert!(AggregateAttestation::aggregate(&envelope, &[(10, refs[0].1)], set.len()).is_err());
+    }
+
+    #[test]
+    fn test_batch_verification_and_possession() {
+        let keypairs = agents(4);
+        let set: Vec<_> = keypairs.iter().map(BlsKeypair::public_key).collect();
+        let aggregates: Vec<_> = ["c-1", "c-2", "c-3"]
+            .iter()
+            .map(|contract| {
+                let envelope = envelope(contract);
+                let signatures: Vec<_> = keypairs.iter().map(|k| k.attest(&envelope).unwrap()).collect();
+                let refs: Vec<_> = signatures.iter().enumerate().map(|(i, s)| (i, s.as_str())).collect();
+                AggregateAttestation::aggregate(&envelope, &refs, set.len()).unwrap()
+            })
+            .collect();
+        let batch: Vec<_> = aggregates.iter().map(|a| (a, set.as_slice())).collect();
+        assert!(verify_aggregates(&batch).unwrap());
+
+        let mut swapped = aggregates[1].clone();
+        swapped.signature = aggregates[2].signature.clone();
+        assert!(!verify_aggregates(&[batch[0], (&swapped, &set)]).unwrap());
+
+        let proof = keypairs[0].proof_of_possession();
+        assert!(set[0].verify_possession(&proof).unwrap());
+        assert!(!set[1].verify_possession(&proof).unwrap());
+        assert_eq!(set[0].to_string().parse::<BlsPublicKey>().unwrap(), set[0]);
+        assert!(BlsKeypair::from_seed(&[1; 16]).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+/// cache.rs - Memoized semantic hashes
+///
+/// The governance engine hashes the same evidence objects over and over. `HashCache`
+/// remembers the digest of each canonical form it has hashed, keyed by the exact bytes
+/// fed to the hash function (algorithm, version tag, type tag and canonical JSON), so
+/// a document that canonicalizes to something already seen is never hashed again. The
+/// cache is a least-recently-used map with a fixed number of entries, and counts its
+/// hits, misses and evictions.
+
+use crate::algorithm::Hasher;
+use crate::{canonicalize_with, CanonicalizeOptions, Result, SemanticHash};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Hit and miss counts of a `HashCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to hash
+    pub misses: u64,
+    /// Entries dropped to stay within capacity
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache, or 0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    hash: SemanticHash,
+    last_used: u64,
+}
+
+/// Least-recently-used cache of semantic hashes.
+#[derive(Debug, Default)]
+pub struct HashCache {
+    capacity: usize,
+    entries: HashMap<Arc<[u8]>, Entry>,
+    /// Keys by the tick they were last used, oldest first
+    recency: BTreeMap<u64, Arc<[u8]>>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl HashCache {
+    /// Create a cache holding at most `capacity` hashes; a capacity of 0 caches nothing.
+    pub fn new(capacity: usize) -> Self {
+        HashCache { capacity, ..Self::default() }
+    }
+
+    /// Semantic hash of data, as `SemanticHash::compute` returns it.
+    pub fn hash(&mut self, data: &Value, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        self.lookup(data, None, options)
+    }
+
+    /// Typed semantic hash of data, as `semantic_hash_typed_with` returns it.
+    pub fn hash_typed(&mut self, data: &Value, object_type: &str, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        self.lookup(data, Some(object_type), options)
+    }
+
+    /// Semantic hashes of many documents, in input order, as `semantic_hash_batch`
+    /// returns them.
+    pub fn hash_batch(&mut self, items: &[Value], options: &CanonicalizeOptions) -> Vec<Result<SemanticHash>> {
+        items.iter().map(|data| self.hash(data, options)).collect()
+    }
+
+    /// Hit, miss and eviction counts so far.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Number of cached hashes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every cached hash; the counts are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn lookup(&mut self, data: &Value, object_type: Option<&str>, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        let key = preimage(data, object_type, options)?;
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(&key[..]) {
+            let key = self.recency.remove(&entry.last_used).expect("cached key has a recency tick");
+            entry.last_used = self.tick;
+            self.recency.insert(self.tick, key);
+            self.stats.hits += 1;
+            return Ok(entry.hash);
+        }
+
+        self.stats.misses += 1;
+        // Everything after the algorithm identifier is exactly what `semantic_digest` hashes
+        let split = options.hash_algorithm.identifier().len() + 1;
+        let mut hasher = Hasher::new(options.hash_algorithm);
+        hasher.update(&key[split..]);
+        let hash = SemanticHash::try_from(hasher.finalize().as_slice())?;
+
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                if let Some((_, oldest)) = self.recency.pop_first() {
+                    self.entries.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+            }
+            let key: Arc<[u8]> = key.into();
+            self.recency.insert(self.tick, Arc::clone(&key));
+            self.entries.insert(key, Entry { hash, last_used: self.tick });
+        }
+        Ok(hash)
+    }
+}
+
+/// The algorithm identifier, a NUL, and the bytes a semantic hash of data is computed over.
+fn preimage(data: &Value, object_type: Option<&str>, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    let canonical = canonicalize_with(data, options)?;
+    let mut key = Vec::with_capacity(canonical.len() + 32);
+    key.extend_from_slice(options.hash_algorithm.identifier().as_bytes());
+    key.push(0);
+    key.extend_from_slice(options.canon_version.tag().as_bytes());
+    if let Some(object_type) = object_type {
+        key.extend_from_slice(crate::type_tag(object_type)?.as_bytes());
+    }
+    key.extend_from_slice(canonical.as_bytes());
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash_typed_with, CanonVersion};
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_hits_and_evicts() {
+        let options = CanonicalizeOptions::new();
+        let mut cache = HashCache::new(2);
+        let a = json!({"claim": "delivered", "sources": ["b", "a"]});
+        let a_reordered = json!({"sources": ["a", "b"], "claim": "delivered"});
+        let b = json!({"claim": "paid"});
+        let c = json!({"claim": "disputed"});
+
+        let hash = cache.hash(&a, &options).unwrap();
+        assert_eq!(hash, SemanticHash::of(&a).unwrap());
+        assert_eq!(cache.hash(&a_reordered, &options).unwrap(), hash);
+        cache.hash(&b, &options).unwrap();
+        cache.hash(&a, &options).unwrap();
+        // `b` is now the least recently used, so `c` evicts it
+        cache.hash(&c, &options).unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.hash(&a, &options).unwrap();
+        cache.hash(&b, &options).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 4, 2));
+        assert!((stats.hit_rate() - 3.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cache_separates_settings() {
+        let data = json!({"id": "obj-1"});
+        let mut cache = HashCache::new(16);
+        let plain = cache.hash(&data, &CanonicalizeOptions::new()).unwrap();
+        let v1 = cache.hash(&data, &CanonicalizeOptions::new().canon_version(CanonVersion::V1)).unwrap();
+        let typed = cache.hash_typed(&data, "Contract", &CanonicalizeOptions::new()).unwrap();
+        assert_ne!(plain, v1);
+        assert_ne!(plain, typed);
+        assert_eq!(typed.to_hex(), semantic_hash_typed_with(&data, "Contract", &CanonicalizeOptions::new()).unwrap());
+        assert_eq!(cache.stats().hits, 0);
+
+        let batch = cache.hash_batch(&[data.clone(), json!([1])], &CanonicalizeOptions::new());
+        assert_eq!(*batch[0].as_ref().unwrap(), plain);
+        assert!(batch[1].is_err());
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(HashCache::new(0).hash(&data, &CanonicalizeOptions::new()).unwrap(), plain);
+    }
+}
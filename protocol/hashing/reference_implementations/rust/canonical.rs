@@ -0,0 +1,211 @@
+/// canonical.rs - The `Canonicalize` trait for hashing domain types
+///
+/// A type that can say what JSON it stands for can be canonicalized and hashed without
+/// its callers building a `Value` first. Implementations cover the JSON-like std types
+/// (strings, numbers, booleans, options, sequences and string-keyed maps) and smart
+/// pointers to them, so a domain type usually only has to assemble an object from its
+/// fields; `#[derive(Canonicalize)]` from `ocp_canon_derive` writes that for structs.
+
+use crate::{canonicalize_owned, semantic_hash_owned, CanonicalizeOptions, Result};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::BuildHasher;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A value with a canonical JSON form.
+pub trait Canonicalize {
+    /// The JSON this value stands for, before the OCP rules are applied.
+    fn canonical_value(&self) -> Value;
+
+    /// Options this value is canonicalized under; the defaults unless overridden.
+    fn canonical_options(&self) -> CanonicalizeOptions {
+        CanonicalizeOptions::default()
+    }
+
+    /// Canonical JSON string of this value.
+    fn canonical_json(&self) -> Result<String> {
+        canonicalize_owned(self.canonical_value(), &self.canonical_options())
+    }
+
+    /// Semantic hash of this value.
+    fn semantic_hash(&self) -> Result<String> {
+        semantic_hash_owned(self.canonical_value(), &self.canonical_options())
+    }
+}
+
+impl Canonicalize for Value {
+    fn canonical_value(&self) -> Value {
+        crate::clone_iteratively(self)
+    }
+}
+
+impl Canonicalize for str {
+    fn canonical_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Canonicalize for String {
+    fn canonical_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl Canonicalize for char {
+    fn canonical_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Canonicalize for bool {
+    fn canonical_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl Canonicalize for () {
+    fn canonical_value(&self) -> Value {
+        Value::Null
+    }
+}
+
+macro_rules! canonicalize_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl Canonicalize for $ty {
+                fn canonical_value(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+        )*
+    };
+}
+
+// Non-finite floats have no JSON form and become `null`, as in `serde_json`
+canonicalize_numbers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl<T: Canonicalize> Canonicalize for Option<T> {
+    fn canonical_value(&self) -> Value {
+        self.as_ref().map_or(Value::Null, Canonicalize::canonical_value)
+    }
+}
+
+impl<T: Canonicalize> Canonicalize for [T] {
+    fn canonical_value(&self) -> Value {
+        Value::Array(self.iter().map(Canonicalize::canonical_value).collect())
+    }
+}
+
+impl<T: Canonicalize, const N: usize> Canonicalize for [T; N] {
+    fn canonical_value(&self) -> Value {
+        self.as_slice().canonical_value()
+    }
+}
+
+impl<T: Canonicalize> Canonicalize for Vec<T> {
+    fn canonical_value(&self) -> Value {
+        self.as_slice().canonical_value()
+    }
+}
+
+impl<T: Canonicalize> Canonicalize for VecDeque<T> {
+    fn canonical_value(&self) -> Value {
+        Value::Array(self.iter().map(Canonicalize::canonical_value).collect())
+    }
+}
+
+impl<T: Canonicalize> Canonicalize for BTreeSet<T> {
+    fn canonical_value(&self) -> Value {
+        Value::Array(self.iter().map(Canonicalize::canonical_value).collect())
+    }
+}
+
+/// Sets iterate in no particular order, so the array is only canonical under an array
+/// policy that sorts it.
+impl<T: Canonicalize, S: BuildHasher> Canonicalize for HashSet<T, S> {
+    fn canonical_value(&self) -> Value {
+        Value::Array(self.iter().map(Canonicalize::canonical_value).collect())
+    }
+}
+
+impl<K: AsRef<str>, V: Canonicalize> Canonicalize for BTreeMap<K, V> {
+    fn canonical_value(&self) -> Value {
+        object(self.iter())
+    }
+}
+
+impl<K: AsRef<str>, V: Canonicalize, S: BuildHasher> Canonicalize for HashMap<K, V, S> {
+    fn canonical_value(&self) -> Value {
+        object(self.iter())
+    }
+}
+
+fn object<'a, K: AsRef<str> + 'a, V: Canonicalize + 'a>(entries: impl Iterator<Item = (&'a K, &'a V)>) -> Value {
+    let map: Map<String, Value> =
+        entries.map(|(key, value)| (key.as_ref().to_string(), value.canonical_value())).collect();
+    Value::Object(map)
+}
+
+macro_rules! canonicalize_pointers {
+    ($($ty:ty),*) => {
+        $(
+            impl<T: Canonicalize + ?Sized> Canonicalize for $ty {
+                fn canonical_value(&self) -> Value {
+                    (**self).canonical_value()
+                }
+            }
+        )*
+    };
+}
+
+canonicalize_pointers!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+impl<T: Canonicalize + ToOwned + ?Sized> Canonicalize for Cow<'_, T> {
+    fn canonical_value(&self) -> Value {
+        (**self).canonical_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash, ArraySortPolicy};
+    use serde_json::json;
+
+    struct Evidence {
+        claim: &'static str,
+        sources: Vec<String>,
+        weight: Option<f64>,
+    }
+
+    impl Canonicalize for Evidence {
+        fn canonical_value(&self) -> Value {
+            let mut map = Map::new();
+            map.insert("claim".to_string(), self.claim.canonical_value());
+            map.insert("sources".to_string(), self.sources.canonical_value());
+            map.insert("weight".to_string(), self.weight.canonical_value());
+            Value::Object(map)
+        }
+
+        fn canonical_options(&self) -> CanonicalizeOptions {
+            CanonicalizeOptions::new().array_sort(ArraySortPolicy::Never)
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_trait() {
+        let evidence = Evidence { claim: "delivered", sources: vec!["b".into(), "a".into()], weight: None };
+        assert_eq!(evidence.canonical_json().unwrap(), r#"{"claim":"delivered","sources":["b","a"],"weight":null}"#);
+
+        let mut ledger = HashMap::new();
+        ledger.insert("002", vec![Some(2u8), None]);
+        ledger.insert("001", vec![Some(1)]);
+        let expected = json!({"001": [1], "002": [2, null]});
+        assert_eq!(ledger.canonical_value(), expected);
+        assert_eq!(ledger.semantic_hash().unwrap(), semantic_hash(&expected).unwrap());
+        assert_eq!(Box::new(Rc::new(f64::NAN)).canonical_value(), Value::Null);
+        assert_eq!(Cow::Borrowed("x").canonical_value(), json!("x"));
+    }
+}
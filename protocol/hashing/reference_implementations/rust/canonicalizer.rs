@@ -6,10 +6,169 @@
 ///
 /// Must produce byte-for-byte identical output to canonicalizer.py and canonicalizer.js
 
-use serde_json::{json, Value, Map};
+use serde::Serialize;
+use serde_json::{json, Map, Number, Value};
 use sha2::{Sha256, Digest};
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::io::Write;
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(test)]
+extern crate self as ocp_canon;
+
+mod adjudication;
+mod agents;
+mod aggregate;
+mod algorithm;
+mod amendment_graph;
+#[cfg(feature = "bls")]
+mod bls;
+mod cache;
+mod canonical;
+mod chunked;
+mod commitment;
+mod constitution;
+mod content_id;
+mod contract;
+mod cose;
+mod countersign;
+mod did;
+mod digest;
+mod document;
+mod encoding;
+mod envelope;
+mod escape;
+mod evidence;
+mod frontier;
+mod hints;
+mod hmac;
+mod incremental;
+mod intern;
+mod jws;
+mod kinds;
+#[cfg(feature = "keystore")]
+mod keystore;
+mod ledger;
+mod ledger_index;
+mod merkle;
+mod migrate;
+mod multisig;
+mod number;
+mod objects;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod parse;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+mod pointer;
+mod policy;
+mod proof;
+mod quorum;
+mod reasoning_chain;
+mod redact;
+mod registry;
+mod replay;
+mod reputation;
+mod resolve;
+mod revision;
+mod schema;
+mod secret;
+mod short_id;
+mod signing;
+mod sparse;
+mod stream;
+mod tally;
+mod target;
+mod timestamp;
+mod validation;
+mod vector;
+mod workflow;
+
+pub use adjudication::{verify_precedents, verify_ruling_chain};
+pub use agents::{AgentRegistry, KeyRecord, Revocation};
+pub use aggregate::{AggregateConfidence, Aggregation, Attestation, ConfidenceAggregator};
+pub use algorithm::{CanonicalHasher, HashAlgorithm};
+pub use amendment_graph::{AmendmentGraph, GraphIssue};
+#[cfg(feature = "bls")]
+pub use bls::{verify_aggregates, verify_attestation, AggregateAttestation, BlsKeypair, BlsPublicKey};
+pub use cache::{CacheStats, HashCache};
+pub use canonical::Canonicalize;
+pub use chunked::{chunk_root, hash_file, hash_reader, Chunk, ChunkedDigest, Chunking};
+pub use commitment::{commit, commit_with, verify_commitment, verify_commitment_with, Nonce, NONCE_LEN};
+pub use constitution::Constitution;
+pub use content_id::{content_id, verify_content_id, ContentAddressed, CONTENT_ID_PREFIX};
+pub use contract::{Contract, ContractBuilder, Validity, ACTION_TYPES};
+pub use cose::{sign_cose, verify_cose, CosePayload};
+pub use countersign::{AttestationChain, Countersignature};
+pub use did::{Did, DidResolver, DidResolvers, KeyResolver};
+pub use digest::SemanticHash;
+pub use document::{parse_markdown, Address, Article, Section};
+pub use encoding::Encoding;
+pub use envelope::{semantic_hash_envelope, verify_semantic_hash_envelope, ExpectedHash, HashEnvelope};
+pub use escape::ControlEscaping;
+pub use evidence::EvidencePointer;
+pub use frontier::{IncrementalMerkleTree, MerkleSnapshot};
+pub use hints::SchemaHints;
+pub use hmac::HmacKey;
+pub use incremental::IncrementalHasher;
+pub use jws::{sign_jws, verify_jws};
+pub use kinds::{ConstitutionalObject, ObjectRegistry, OBJECT_TYPE};
+#[cfg(feature = "keystore")]
+pub use keystore::Keystore;
+pub use ledger::{
+    Ledger, LedgerCheckpoint, LedgerCorruption, LedgerFault, LedgerRecord, RecordHandle, CHECKPOINT_TYPE,
+};
+pub use ledger_index::{LedgerIndex, LedgerQuery};
+pub use merkle::{
+    leaf_hash, node_hash, verify_consistency, verify_inclusion, verify_multi, ConsistencyProof, InclusionProof,
+    MerkleTree, MultiProof,
+};
+pub use migrate::{schema_version, Migrations, SCHEMA_VERSION};
+pub use multisig::{CoSigningPolicy, MultiSignedObject};
+pub use objects::{
+    Amendment, AmendmentBuilder, AmendmentOperation, Challenge, ChallengeBuilder, ChallengeGrounds, DisputeRecord,
+    DisputeStatus, Evidence, Reasoning, Ruling, RulingBuilder, RulingOutcome, Vote, VoteBuilder, VoteChoice,
+};
+#[cfg(feature = "pkcs11")]
+pub use pkcs11::Pkcs11Signer;
+pub use pointer::JsonPointer;
+pub use policy::{Decision, Denial, SignaturePolicy};
+pub use quorum::{AgentSet, QuorumCertificate, QuorumCollector};
+pub use reasoning_chain::{ReasoningChain, ReasoningStep};
+pub use redact::{verify_redacted, RedactableDocument, REDACTED_KEY};
+pub use registry::{AlgorithmRegistry, DigestAlgorithm, DigestState};
+pub use replay::{verify_signed_once, MemoryNonceStore, NonceStore, ReplayGuard};
+pub use reputation::{
+    reputation_events, FixedPoints, ReputationEvent, ReputationOutcome, ReputationScorer, ReputationSnapshot, ScoringRule,
+};
+pub use resolve::{DirectoryResolver, EvidenceResolver, EvidenceResolvers, HttpResolver, MemoryResolver};
+pub use revision::{
+    apply_amendment, changelog, AmendmentRecord, ArticleChange, ChangeKind, Changelog, Revision, RevisionHistory,
+    RevisionId, REVISION_MEMBER,
+};
+pub use schema::{json_schema, validate_against_schema, OBJECT_TYPES};
+pub use secret::{Secret, Wipe};
+pub use sparse::{state_key, SparseMerkleTree, SparseProof};
+pub use short_id::{short_id, short_id_with, ShortIdRegistry, MIN_SHORT_ID_LEN};
+pub use signing::{
+    sign, sign_as, sign_with, verify_signed, verify_signed_resolving, verify_signed_with, Keypair, PublicKey,
+    SignatureScheme, SignedObject, Signer,
+};
+#[cfg(feature = "secp256k1")]
+pub use signing::recover_signer;
+pub use tally::{tally, tally_signed, verify_tally, TallyOutcome, TallyResult, TallyRules};
+pub use target::TargetResolver;
+pub use validation::{validate, validate_str, Violation, ViolationKind};
+pub use vector::{verify_position, VectorCommitment};
+pub use workflow::{Clock, EventStore, MemoryEventStore, RatificationWorkflow, SystemClock, WorkflowEvent};
+use algorithm::Hasher;
+use encoding::{decode_digest, digest_text};
+use hmac::{constant_time_eq, HmacSha256};
+use escape::{write_string, Escaping};
+use intern::Scratch;
+use number::{compare_numbers, format_ecmascript_number, normalize_number};
+use timestamp::normalize_rfc3339;
 
 // --- Constants ---
 pub const HASH_ALGORITHM: &str = "sha256";
@@ -21,374 +180,3047 @@ pub enum ConstitutionalError {
     #[error("Constitutional protocol error: {0}")]
     ProtocolError(String),
     
-    #[error("Canonicalization error: {0}")]
-    CanonicalizationError(String),
+    #[error("Canonicalization error: {message}{}", at_pointer(.pointer))]
+    CanonicalizationError {
+        message: String,
+        /// Location of the offending value, when the error concerns one
+        pointer: Option<JsonPointer>,
+    },
     
     #[error("Hashing error: {0}")]
     HashingError(String),
+
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Validation failed: {}", list_violations(.0))]
+    ValidationFailed(Vec<Violation>),
+
+    #[error("Limit exceeded: {limit} is limited to {max}{}", at_pointer(.pointer))]
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+        /// Location of the value that crossed the limit, when there is one
+        pointer: Option<JsonPointer>,
+    },
 }
 
-pub type Result<T> = std::result::Result<T, ConstitutionalError>;
+/// Resource limits for untrusted input, set on `CanonicalizeOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Length of raw JSON input in bytes.
+    Bytes,
+    /// Number of values (objects, arrays and scalars) in the document.
+    Nodes,
+    /// Length in bytes of a single string value or object key.
+    StringLength,
+}
 
-/// Recursively sort all dictionaries by keys and sort arrays where appropriate.
-/// This ensures complete deterministic ordering of nested structures.
-/// Matches Python's _deep_sort and JavaScript's deepSort functions.
-fn deep_sort(value: &Value) -> Value {
-    match value {
-        Value::Object(map) => {
-            // Convert to BTreeMap (automatically sorted by keys)
-            let mut sorted_map = BTreeMap::new();
-            for (k, v) in map.iter() {
-                sorted_map.insert(k.clone(), deep_sort(v));
-            }
-            
-            // Convert back to serde_json::Map
-            let mut result_map = Map::new();
-            for (k, v) in sorted_map.into_iter() {
-                result_map.insert(k, v);
-            }
-            Value::Object(result_map)
-        }
-        Value::Array(arr) => {
-            // Check if all elements are primitives of same type
-            let all_primitives = arr.iter().all(|v| {
-                matches!(
-                    v,
-                    Value::String(_) | Value::Number(_) | Value::Bool(_) | Value::Null
-                )
-            });
-            
-            if all_primitives && arr.len() > 0 {
-                // Check if all are same type
-                let first_type = std::mem::discriminant(&arr[0]);
-                let all_same_type = arr.iter().all(|v| std::mem::discriminant(v) == first_type);
-                
-                if all_same_type {
-                    // Sort primitives of same type
-                    let mut sorted = arr
-                        .iter()
-                        .map(|v| deep_sort(v))
-                        .collect::<Vec<_>>();
-                    
-                    sorted.sort_by(|a, b| {
-                        // Custom comparison for JSON values
-                        match (a, b) {
-                            (Value::String(s1), Value::String(s2)) => s1.cmp(s2),
-                            (Value::Number(n1), Value::Number(n2)) => {
-                                // Compare as f64 for consistency
-                                let f1 = n1.as_f64().unwrap_or(0.0);
-                                let f2 = n2.as_f64().unwrap_or(0.0);
-                                f1.partial_cmp(&f2).unwrap_or(std::cmp::Ordering::Equal)
-                            }
-                            (Value::Bool(b1), Value::Bool(b2)) => b1.cmp(b2),
-                            _ => std::cmp::Ordering::Equal,
-                        }
-                    });
-                    
-                    Value::Array(sorted)
-                } else {
-                    // Mixed types - maintain order
-                    Value::Array(arr.iter().map(|v| deep_sort(v)).collect())
-                }
-            } else {
-                // Empty array or non-primitive - maintain order
-                Value::Array(arr.iter().map(|v| deep_sort(v)).collect())
-            }
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Limit::Bytes => "input size in bytes",
+            Limit::Nodes => "number of values",
+            Limit::StringLength => "string length in bytes",
+        })
+    }
+}
+
+impl ConstitutionalError {
+    pub(crate) fn canonicalization(message: impl Into<String>) -> Self {
+        ConstitutionalError::CanonicalizationError { message: message.into(), pointer: None }
+    }
+
+    /// A canonicalization error about the value reached through `path`.
+    pub(crate) fn canonicalization_at(message: impl Into<String>, path: &[String]) -> Self {
+        ConstitutionalError::CanonicalizationError {
+            message: message.into(),
+            pointer: Some(JsonPointer::from_tokens(path.iter().cloned())),
         }
-        _ => {
-            // Primitives are returned as-is
-            value.clone()
+    }
+
+    /// JSON Pointer of the value a canonicalization error refers to, if it refers to one.
+    /// For a failed validation this is the location of the first violation.
+    pub fn pointer(&self) -> Option<&JsonPointer> {
+        match self {
+            ConstitutionalError::CanonicalizationError { pointer, .. } => pointer.as_ref(),
+            ConstitutionalError::ValidationFailed(violations) => violations.first().map(|v| &v.pointer),
+            ConstitutionalError::LimitExceeded { pointer, .. } => pointer.as_ref(),
+            _ => None,
         }
     }
 }
 
-/// Convert a serde_json::Value to a deterministically ordered, canonical JSON string.
-/// Matches Python's canonicalize and JavaScript's canonicalize functions.
-///
-/// # Arguments
-/// * `data` - Input JSON value to canonicalize
-/// * `strict` - If true, returns error on non-canonicalizable data
-///
-/// # Returns
-/// Canonical JSON string (compact, no whitespace, sorted keys)
-pub fn canonicalize(data: &Value, strict: bool) -> Result<String> {
-    // Ensure we have an object
-    if !data.is_object() {
-        if strict {
-            return Err(ConstitutionalError::CanonicalizationError(
-                format!("Input must be an object, got {:?}", data.type_str())
-            ));
-        } else {
-            // Wrap in object
-            let wrapped = json!({ "value": data });
-            return canonicalize(&wrapped, false);
+fn at_pointer(pointer: &Option<JsonPointer>) -> String {
+    pointer.as_ref().map_or_else(String::new, |p| format!(" at {:?}", p.to_string()))
+}
+
+fn list_violations(violations: &[Violation]) -> String {
+    violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
+pub type Result<T> = std::result::Result<T, ConstitutionalError>;
+
+// --- Canonicalization Options ---
+
+/// Canonicalization rule sets selectable through `CanonicalizeOptions::profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanonicalizationProfile {
+    /// OCP-native rules shared with canonicalizer.py and canonicalizer.js (default).
+    Ocp,
+    /// RFC 8785 JSON Canonicalization Scheme (JCS): keys ordered by UTF-16 code units,
+    /// numbers serialized as ECMAScript `Number.prototype.toString`, arrays never reordered.
+    ///
+    /// Parse JSON text with serde_json's `float_roundtrip` feature enabled, otherwise
+    /// parsed floats may differ from the IEEE 754 value other JCS implementations see.
+    Jcs,
+}
+
+impl CanonicalizationProfile {
+    /// Array policy the profile applies unless overridden with `CanonicalizeOptions::array_sort`.
+    pub fn default_array_sort(self) -> ArraySortPolicy {
+        match self {
+            CanonicalizationProfile::Ocp => ArraySortPolicy::PrimitivesOnly,
+            CanonicalizationProfile::Jcs => ArraySortPolicy::Never,
         }
     }
 
-    // Deep sort the entire structure
-    let sorted_data = deep_sort(data);
-    
-    // Convert to canonical JSON string using compact representation
-    // serde_json::to_string produces compact JSON with no extra whitespace
-    match serde_json::to_string(&sorted_data) {
-        Ok(canonical_json) => {
-            if canonical_json.is_empty() {
-                Err(ConstitutionalError::CanonicalizationError(
-                    "Failed to produce canonical JSON string".to_string()
-                ))
-            } else {
-                Ok(canonical_json)
-            }
-        }
-        Err(e) => {
-            if strict {
-                Err(ConstitutionalError::CanonicalizationError(
-                    format!("Failed to canonicalize data: {}", e)
-                ))
-            } else {
-                // Fallback: convert all values to strings and retry
-                if let Value::Object(map) = data {
-                    let mut stringified = Map::new();
-                    for (k, v) in map.iter() {
-                        stringified.insert(k.clone(), Value::String(v.to_string()));
-                    }
-                    let wrapped_obj = Value::Object(stringified);
-                    canonicalize(&wrapped_obj, false)
-                } else {
-                    Err(ConstitutionalError::CanonicalizationError(
-                        format!("Data cannot be canonicalized even with fallback: {}", e)
-                    ))
-                }
-            }
+    /// Key collation the profile applies unless overridden with `CanonicalizeOptions::key_collation`.
+    pub fn default_key_collation(self) -> KeyCollation {
+        match self {
+            CanonicalizationProfile::Ocp => KeyCollation::Bytes,
+            CanonicalizationProfile::Jcs => KeyCollation::Utf16CodeUnits,
         }
     }
 }
 
-/// Calculate the cryptographic hash of canonicalized data.
-/// Matches Python's semantic_hash and JavaScript's semanticHash functions.
+/// Controls how arrays are reordered during canonicalization.
 ///
-/// # Arguments
-/// * `data` - Input JSON value to hash
+/// Sorting gives an array set semantics; use `Never` for data whose order carries
+/// meaning, such as ranked votes or ordered reasoning steps.
 ///
-/// # Returns
-/// Hexadecimal string of the SHA256 hash
-pub fn semantic_hash(data: &Value) -> Result<String> {
-    let canonical_string = canonicalize(data, true)?;
-    let canonical_bytes = canonical_string.as_bytes();
-    
-    let mut hasher = Sha256::new();
-    hasher.update(canonical_bytes);
-    let result = hasher.finalize();
-    
-    Ok(format!("{:x}", result))
+/// The value-ordered policies other than `PrimitivesOnly` use a single total order
+/// across JSON types: `null < false < true < numbers < strings < arrays < objects`.
+/// Numbers compare by exact decimal value, strings by the key collation, and arrays and
+/// objects by the bytes of their canonical form, so `[1, "a"]` and `["a", 1]`
+/// canonicalize identically just as `[1, 2]` and `[2, 1]` do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArraySortPolicy {
+    /// Never reorder arrays; element order is always significant.
+    Never,
+    /// Sort arrays whose elements are all primitives of the same JSON type (default).
+    /// Matches the behavior of canonicalizer.py and canonicalizer.js.
+    PrimitivesOnly,
+    /// Sort arrays whose elements are all primitives, of any mix of types, by the total
+    /// order. Hashes of arrays mixing types differ from those under `PrimitivesOnly`.
+    MixedPrimitives,
+    /// Sort all-primitive arrays by the total order, and arrays made up entirely of
+    /// objects and arrays by each element's semantic hash. Arrays mixing primitives and
+    /// containers keep their order.
+    PrimitivesAndObjects,
+    /// Sort every array by the SHA256 digest of each element's canonical form.
+    ByElementHash,
+    /// Sort every array by the total order.
+    Always,
 }
 
-/// Verify that data produces the expected semantic hash.
-/// Matches Python's verify_semantic_hash and JavaScript's verifySemanticHash functions.
-///
-/// # Arguments
-/// * `data` - Input JSON value to verify
-/// * `expected_hash` - Expected hash value (hex string)
+/// Controls the order in which object keys are emitted, and in which string elements
+/// are placed when an array is sorted.
 ///
-/// # Returns
-/// true if hash matches, false otherwise
-pub fn verify_semantic_hash(data: &Value, expected_hash: &str) -> Result<bool> {
-    let actual_hash = semantic_hash(data)?;
-    Ok(actual_hash == expected_hash)
+/// The orders only disagree for text outside the Basic Multilingual Plane: UTF-16 encodes
+/// U+10000 and above as surrogates (0xD800-0xDFFF), which sort below U+E000-U+FFFF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCollation {
+    /// Compare by UTF-8 bytes, as Rust's `str` ordering does (default).
+    Bytes,
+    /// Compare by Unicode code points, as Python's `sorted` does. UTF-8 preserves code point
+    /// order, so this always agrees with `Bytes`; it is provided to state intent.
+    Codepoint,
+    /// Compare by UTF-16 code units, as JavaScript's `Array.prototype.sort` does.
+    /// Use this for parity with canonicalizer.js; RFC 8785 also requires it.
+    Utf16CodeUnits,
 }
 
-/// Compare two JSON values for canonical equality.
-///
-/// # Arguments
-/// * `data1` - First JSON value
-/// * `data2` - Second JSON value
+impl KeyCollation {
+    fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            KeyCollation::Bytes => a.as_bytes().cmp(b.as_bytes()),
+            KeyCollation::Codepoint => a.chars().cmp(b.chars()),
+            KeyCollation::Utf16CodeUnits => a.encode_utf16().cmp(b.encode_utf16()),
+        }
+    }
+}
+
+/// Controls how numeric values are represented in the canonical form, including the
+/// values JSON cannot express portably: NaN, Infinity and negative zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberPolicy {
+    /// Emit numbers exactly as serde_json represents them (default). Non-finite values are
+    /// rejected in strict mode and emitted as `null` otherwise; `-0.0` is kept as-is.
+    Preserve,
+    /// Reject non-finite values and `-0.0` with an error naming their location.
+    Reject,
+    /// Replace non-finite values with `null` and `-0.0` with `0`.
+    Nullify,
+    /// Rewrite integral floats as integers (`1.0` becomes `1`, `-0.0` becomes `0`),
+    /// per Rule 2.4.1 of the Canonical JSON Specification. Non-finite values are rejected.
+    Normalize,
+}
+
+/// How a top-level value that is not an object (an array or a scalar) is canonicalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopLevelPolicy {
+    /// Fail with an error (default, and the behavior of strict mode).
+    Reject,
+    /// Wrap the value as `{"value": ...}` before canonicalizing, the legacy non-strict
+    /// behavior shared with canonicalizer.py and canonicalizer.js.
+    Wrap,
+    /// Canonicalize the value itself, so `[3,1,2]` becomes `[1,2,3]` and `"x"` stays `"x"`.
+    /// This is the only option RFC 8785 defines.
+    Native,
+}
+
+/// Object members removed because their value carries no information, so that
+/// `{"reasoning": null}` and `{}` hash identically.
 ///
-/// # Returns
-/// true if canonical forms are identical
-pub fn canonically_equal(data1: &Value, data2: &Value) -> bool {
-    match (canonicalize(data1, true), canonicalize(data2, true)) {
-        (Ok(canon1), Ok(canon2)) => canon1 == canon2,
-        _ => false,
+/// Pruning runs bottom-up after exclusions and the number, timestamp and Unicode policies
+/// and before array sorting: a member nullified by `NumberPolicy::Nullify` is dropped, and
+/// under `NullsAndEmpty` an object emptied by pruning is dropped from its own parent.
+/// Array elements and the top-level value are never removed, since that would shift or
+/// erase positions that carry meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrunePolicy {
+    /// Keep every member (default).
+    Keep,
+    /// Drop members whose value is `null`.
+    Nulls,
+    /// Drop members whose value is `null`, `{}` or `[]`.
+    NullsAndEmpty,
+}
+
+impl PrunePolicy {
+    fn drops(self, value: &Value) -> bool {
+        match (self, value) {
+            (PrunePolicy::Keep, _) => false,
+            (_, Value::Null) => true,
+            (PrunePolicy::NullsAndEmpty, Value::Object(map)) => map.is_empty(),
+            (PrunePolicy::NullsAndEmpty, Value::Array(arr)) => arr.is_empty(),
+            _ => false,
+        }
     }
 }
 
-/// Helper trait to get type name of JSON value
-trait JsonTypeStr {
-    fn type_str(&self) -> &'static str;
+/// Version of the canonicalization rules a semantic hash is computed under, recorded by
+/// prefixing the hashed bytes with a domain tag. Canonical strings themselves are never
+/// tagged, only the hash input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanonVersion {
+    /// Untagged hashes, identical to canonicalizer.py and canonicalizer.js and to every
+    /// hash computed before version tags existed (default).
+    Legacy,
+    /// Hash input prefixed with `ocp-canon/v1\n`.
+    V1,
 }
 
-impl JsonTypeStr for Value {
-    fn type_str(&self) -> &'static str {
+impl CanonVersion {
+    /// Bytes hashed ahead of the canonical form.
+    pub fn tag(self) -> &'static str {
         match self {
-            Value::Null => "null",
-            Value::Bool(_) => "bool",
-            Value::Number(_) => "number",
-            Value::String(_) => "string",
-            Value::Array(_) => "array",
-            Value::Object(_) => "object",
+            CanonVersion::Legacy => "",
+            CanonVersion::V1 => "ocp-canon/v1\n",
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Stable name of the version, e.g. for storing next to a hash.
+    pub fn identifier(self) -> &'static str {
+        match self {
+            CanonVersion::Legacy => "legacy",
+            CanonVersion::V1 => "ocp-canon/v1",
+        }
+    }
+}
 
-    #[test]
-    fn test_basic_canonicalization() {
-        let dict_a = json!({
-            "a": 1,
-            "b": 2,
-            "c": 3
-        });
+impl std::str::FromStr for CanonVersion {
+    type Err = ConstitutionalError;
 
-        let dict_b = json!({
-            "c": 3,
-            "a": 1,
-            "b": 2
-        });
+    fn from_str(identifier: &str) -> Result<CanonVersion> {
+        match identifier {
+            "legacy" => Ok(CanonVersion::Legacy),
+            "ocp-canon/v1" => Ok(CanonVersion::V1),
+            _ => Err(ConstitutionalError::HashingError(format!(
+                "Unknown canonicalization version {:?}",
+                identifier
+            ))),
+        }
+    }
+}
 
-        let canonical_a = canonicalize(&dict_a, true).unwrap();
-        let canonical_b = canonicalize(&dict_b, true).unwrap();
+/// Unicode normalization form applied to string values and object keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    /// Canonical composition (e.g. `e` + U+0301 becomes U+00E9).
+    Nfc,
+    /// Canonical decomposition (e.g. U+00E9 becomes `e` + U+0301).
+    Nfd,
+}
 
-        assert_eq!(canonical_a, canonical_b);
-        assert_eq!(
-            semantic_hash(&dict_a).unwrap(),
-            semantic_hash(&dict_b).unwrap()
-        );
+impl NormalizationForm {
+    fn apply(self, s: &str) -> String {
+        match self {
+            NormalizationForm::Nfc => s.nfc().collect(),
+            NormalizationForm::Nfd => s.nfd().collect(),
+        }
     }
+}
 
-    #[test]
-    fn test_nested_structures() {
-        let complex_dict = json!({
-            "z": [3, 1, 2],
-            "a": {
-                "c": 3,
-                "a": 1,
-                "b": {
-                    "f": 6,
-                    "d": 4,
-                    "e": 5
-                }
-            },
-            "b": 2
-        });
+/// Configuration for canonicalization and semantic hashing.
+///
+/// Built with chained setters starting from `CanonicalizeOptions::new()`, which matches
+/// the behavior of `canonicalize(data, true)`:
+///
+/// ```ignore
+/// let options = CanonicalizeOptions::new()
+///     .array_sort(ArraySortPolicy::Never)
+///     .max_depth(64)
+///     .exclude_field("signature");
+/// let hash = semantic_hash_with(&contract, &options)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalizeOptions {
+    profile: CanonicalizationProfile,
+    strict: bool,
+    top_level: TopLevelPolicy,
+    array_sort: ArraySortPolicy,
+    field_array_sort: Vec<(String, ArraySortPolicy)>,
+    key_collation: KeyCollation,
+    number_policy: NumberPolicy,
+    typed_numbers: bool,
+    normalization: Option<NormalizationForm>,
+    escaping: Escaping,
+    timestamp_fields: Vec<String>,
+    timestamp_precision: usize,
+    prune: PrunePolicy,
+    canon_version: CanonVersion,
+    hash_algorithm: HashAlgorithm,
+    hash_encoding: Encoding,
+    max_depth: Option<usize>,
+    max_bytes: Option<usize>,
+    max_nodes: Option<usize>,
+    max_string_length: Option<usize>,
+    exclude_paths: Vec<JsonPointer>,
+    schema_hints: SchemaHints,
+}
 
-        let canonical = canonicalize(&complex_dict, true).unwrap();
-        let expected = r#"{"a":{"a":1,"b":{"d":4,"e":5,"f":6},"c":3},"b":2,"z":[1,2,3]}"#;
-        assert_eq!(canonical, expected);
+impl Default for CanonicalizeOptions {
+    fn default() -> Self {
+        CanonicalizeOptions {
+            profile: CanonicalizationProfile::Ocp,
+            strict: true,
+            top_level: TopLevelPolicy::Reject,
+            array_sort: ArraySortPolicy::PrimitivesOnly,
+            field_array_sort: Vec::new(),
+            key_collation: KeyCollation::Bytes,
+            number_policy: NumberPolicy::Preserve,
+            typed_numbers: false,
+            normalization: None,
+            escaping: Escaping::default(),
+            timestamp_fields: Vec::new(),
+            timestamp_precision: 3,
+            prune: PrunePolicy::Keep,
+            canon_version: CanonVersion::Legacy,
+            hash_algorithm: HashAlgorithm::Sha256,
+            hash_encoding: Encoding::Hex,
+            max_depth: None,
+            max_bytes: None,
+            max_nodes: None,
+            max_string_length: None,
+            exclude_paths: Vec::new(),
+            schema_hints: SchemaHints::default(),
+        }
     }
+}
 
-    #[test]
-    fn test_hash_sensitivity() {
-        let original = json!({
-            "action_id": "001-XYZ",
-            "agent": "Claude-3",
-            "claim": "The initial cost is $500",
-            "evidence_ptr": "archive://0000001",
-            "timestamp": 1700000000
-        });
+impl CanonicalizeOptions {
+    /// Create options with the default (strict) OCP behavior.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let modified = json!({
-            "action_id": "001-XYZ",
-            "agent": "Claude-3",
-            "claim": "The initial cost is $501",
-            "evidence_ptr": "archive://0000001",
-            "timestamp": 1700000000
-        });
+    /// Select a canonicalization profile.
+    ///
+    /// Resets the array policy and key collation to the profile's defaults, so call this
+    /// before any setter that should override them.
+    pub fn profile(mut self, profile: CanonicalizationProfile) -> Self {
+        self.profile = profile;
+        self.array_sort = profile.default_array_sort();
+        self.key_collation = profile.default_key_collation();
+        self
+    }
 
-        let hash_original = semantic_hash(&original).unwrap();
-        let hash_modified = semantic_hash(&modified).unwrap();
+    /// If true, returns error on non-canonicalizable data instead of wrapping it.
+    ///
+    /// Also resets the top-level policy to `Reject` (strict) or `Wrap`, so call this
+    /// before `top_level`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self.top_level = if strict { TopLevelPolicy::Reject } else { TopLevelPolicy::Wrap };
+        self
+    }
 
-        assert_ne!(hash_original, hash_modified);
+    /// Set how a non-object top-level value is handled.
+    pub fn top_level(mut self, policy: TopLevelPolicy) -> Self {
+        self.top_level = policy;
+        self
     }
 
-    #[test]
-    fn test_verify_hash() {
-        let test_data = json!({
-            "action": "propose",
-            "value": 42
-        });
+    /// Set the array reordering policy.
+    pub fn array_sort(mut self, policy: ArraySortPolicy) -> Self {
+        self.array_sort = policy;
+        self
+    }
 
-        let test_hash = semantic_hash(&test_data).unwrap();
+    /// Override the array policy for arrays stored directly under members named `field`,
+    /// at any depth. For example, give `evidence` set semantics while `steps` stays ordered:
+    ///
+    /// ```ignore
+    /// let options = CanonicalizeOptions::new()
+    ///     .array_sort(ArraySortPolicy::Never)
+    ///     .array_sort_for("evidence", ArraySortPolicy::PrimitivesAndObjects);
+    /// ```
+    pub fn array_sort_for(mut self, field: impl Into<String>, policy: ArraySortPolicy) -> Self {
+        let field = field.into();
+        self.field_array_sort.retain(|(name, _)| *name != field);
+        self.field_array_sort.push((field, policy));
+        self
+    }
 
-        assert!(verify_semantic_hash(&test_data, &test_hash).unwrap());
+    /// Array policy for an array stored under `field` (None for array elements and the root).
+    fn array_sort_at(&self, path: &[String], field: Option<&str>) -> ArraySortPolicy {
+        if let Some(policy) = self.schema_hints.array_sort(path) {
+            return policy;
+        }
+        field
+            .and_then(|field| self.field_array_sort.iter().find(|(name, _)| name == field))
+            .map_or(self.array_sort, |(_, policy)| *policy)
+    }
 
-        let tampered_data = json!({
-            "action": "propose",
-            "value": 43
-        });
+    /// Set the object key collation.
+    pub fn key_collation(mut self, collation: KeyCollation) -> Self {
+        self.key_collation = collation;
+        self
+    }
 
-        assert!(!verify_semantic_hash(&tampered_data, &test_hash).unwrap());
+    /// Set the numeric representation policy.
+    pub fn number_policy(mut self, policy: NumberPolicy) -> Self {
+        self.number_policy = policy;
+        self
     }
 
-    #[test]
-    fn test_canonical_equality() {
-        let obj1 = json!({"z": 1, "a": 2});
-        let obj2 = json!({"a": 2, "z": 1});
+    /// Keep integers and floats distinct: `1` and `1.0` always hash differently.
+    ///
+    /// Integral floats survive `NumberPolicy::Normalize` as floats (`1.00` becomes `1.0`),
+    /// and numerically equal integers sort before floats so `[1.0, 1]` and `[1, 1.0]`
+    /// canonicalize identically. Has no effect under the JCS profile, which defines a
+    /// single number type.
+    pub fn preserve_number_types(mut self, preserve: bool) -> Self {
+        self.typed_numbers = preserve;
+        self
+    }
 
-        assert!(canonically_equal(&obj1, &obj2));
+    /// Normalize every string value and object key to the given Unicode form, so visually
+    /// identical text hashes identically. Off by default.
+    pub fn unicode_normalization(mut self, form: NormalizationForm) -> Self {
+        self.normalization = Some(form);
+        self
     }
 
-    #[test]
-    fn test_complex_contract() {
-        let contract = json!({
-            "id": "550e8400-e29b-41d4-a716-446655440000",
-            "proposer_agent": "Claude",
-            "action_type": "amend",
-            "action": {
-                "target": "amendment-article-3",
-                "operation": "modify"
-            },
-            "evidence": [
-                {
-                    "type": "archive_reference",
-                    "pointer": "sha256:abc123def456"
-                }
-            ],
-            "reasoning": {
-                "rationale": "Clarifies Article III.1",
-                "confidence": 0.87
-            },
-            "timestamp": "2025-11-20T14:30:00Z"
-        });
+    /// Escape every character outside printable ASCII as `\uXXXX` (astral characters as
+    /// surrogate pairs, lowercase hex), byte-for-byte like Python's
+    /// `json.dumps(ensure_ascii=True)` and Rule 2.1.2 of the Canonical JSON Specification.
+    /// Off by default, matching canonicalizer.py; RFC 8785 forbids it under JCS.
+    pub fn ensure_ascii(mut self, ensure_ascii: bool) -> Self {
+        self.escaping.ascii_only = ensure_ascii;
+        self
+    }
 
-        let hash = semantic_hash(&contract).unwrap();
-        assert!(hash.len() == 64); // SHA256 hex is 64 chars
-        assert!(verify_semantic_hash(&contract, &hash).unwrap());
+    /// Choose between short (`\n`) and `\u000a`-style escapes for the control characters
+    /// that have both. See escape.rs for the full escaping table.
+    pub fn control_escaping(mut self, control: ControlEscaping) -> Self {
+        self.escaping.control = control;
+        self
     }
 
-    #[test]
-    fn test_array_sorting() {
-        let with_unsorted = json!({
-            "numbers": [5, 3, 1, 4, 2]
-        });
+    /// Escape `/` as `\/`, as some legacy producers do. Off by default.
+    pub fn escape_solidus(mut self, escape: bool) -> Self {
+        self.escaping.solidus = escape;
+        self
+    }
 
-        let with_sorted = json!({
-            "numbers": [1, 2, 3, 4, 5]
-        });
+    /// Rewrite RFC 3339 strings in members named `field`, at any depth, to UTC with a fixed
+    /// number of fractional digits (`2025-11-20T14:30:00+02:00` becomes
+    /// `2025-11-20T12:30:00.000Z`). Values that are not RFC 3339 date-times are left as-is.
+    pub fn normalize_timestamps(mut self, field: impl Into<String>) -> Self {
+        self.timestamp_fields.push(field.into());
+        self
+    }
 
-        assert_eq!(
-            semantic_hash(&with_unsorted).unwrap(),
-            semantic_hash(&with_sorted).unwrap()
-        );
+    /// Fractional second digits written by `normalize_timestamps`, at most 9 (default 3,
+    /// the precision of JavaScript's `Date.prototype.toISOString`). Extra digits are truncated.
+    pub fn timestamp_precision(mut self, digits: usize) -> Self {
+        self.timestamp_precision = digits.min(9);
+        self
     }
 
-    #[test]
-    fn test_cross_language_vector() {
-        // Test vector for cross-language validation
-        let python_test_vector = json!({
-            "action": "propose",
-            "agent": "Claude",
-            "confidence": 0.88,
-            "timestamp": "2025-11-20T14:30:00Z"
-        });
+    /// Drop null-valued and optionally empty members before canonicalizing.
+    pub fn prune(mut self, policy: PrunePolicy) -> Self {
+        self.prune = policy;
+        self
+    }
+
+    /// Select the canonicalization version whose domain tag prefixes the hashed bytes.
+    pub fn canon_version(mut self, version: CanonVersion) -> Self {
+        self.canon_version = version;
+        self
+    }
+
+    /// Select the hash function applied to the canonical bytes (SHA-256 by default).
+    pub fn hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    /// Select how string-returning hash functions write the digest (lowercase hex by
+    /// default). Verification accepts any encoding whatever this selects.
+    pub fn hash_encoding(mut self, encoding: Encoding) -> Self {
+        self.hash_encoding = encoding;
+        self
+    }
+
+    /// Reject input nested deeper than `depth` levels below the top-level object.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Reject raw JSON text (`canonicalize_str`, `canonicalize_bytes` and the matching hash
+    /// functions) longer than `bytes` before parsing it. A `Value` is already in memory;
+    /// bound it with `max_nodes` and `max_string_length`.
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Reject documents containing more than `nodes` values, counting every object,
+    /// array and scalar including the top-level value.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Reject any string value or object key longer than `bytes` in UTF-8.
+    pub fn max_string_length(mut self, bytes: usize) -> Self {
+        self.max_string_length = Some(bytes);
+        self
+    }
+
+    /// Remove a top-level member (e.g. an embedded `signature`) before canonicalizing.
+    pub fn exclude_field(mut self, name: impl Into<String>) -> Self {
+        self.exclude_paths.push(JsonPointer::from_tokens([name.into()]));
+        self
+    }
+
+    /// Remove the value at `pointer` (e.g. `/evidence/0/signature`) before canonicalizing.
+    /// Array indices refer to input positions; pointers that match nothing are ignored.
+    /// Excluding the whole document (`""`) is an error at canonicalization time.
+    pub fn exclude_path(mut self, pointer: JsonPointer) -> Self {
+        self.exclude_paths.push(pointer);
+        self
+    }
+
+    /// Consult per-path hints from an annotated JSON Schema while canonicalizing. A hinted
+    /// array order takes precedence over `array_sort_for` and `array_sort`; hinted
+    /// exclusions apply alongside `exclude_path`.
+    pub fn schema_hints(mut self, hints: SchemaHints) -> Self {
+        self.schema_hints = hints;
+        self
+    }
+}
+
+/// Sort arrays where the array policy allows it and apply the number and Unicode policies.
+/// This ensures complete deterministic ordering of nested structures.
+/// Matches Python's _deep_sort and JavaScript's deepSort functions.
+///
+/// Runs on an explicit work stack rather than recursion, so hostile nesting depth cannot
+/// overflow the call stack; `max_depth` bounds it with a path-qualified error instead.
+/// Object key order is applied by `write_canonical` according to the key collation.
+///
+/// Takes ownership so keys and values are moved, not cloned, into the result.
+fn deep_sort(value: Value, options: &CanonicalizeOptions) -> Result<Value> {
+    if options.exclude_paths.iter().any(JsonPointer::is_root) {
+        drop_iteratively(value);
+        return Err(ConstitutionalError::canonicalization("Cannot exclude the document root"));
+    }
+    deep_sort_at(value, options, Vec::new(), None)
+}
+
+/// Deep sort the value found at `path`, under the member name `field` if its parent is
+/// an object. Exclusions, hints and errors are resolved relative to the whole document.
+fn deep_sort_at(value: Value, options: &CanonicalizeOptions, path: Vec<String>, field: Option<&str>) -> Result<Value> {
+    let mut stack: Vec<SortFrame> = Vec::new();
+    let sorted = deep_sort_on(value, options, path, field, &mut stack);
+    // On error the stack still holds unvisited input, which may be arbitrarily deep
+    for frame in stack {
+        frame.drop_iteratively();
+    }
+    sorted
+}
+
+fn deep_sort_on(
+    value: Value,
+    options: &CanonicalizeOptions,
+    mut path: Vec<String>,
+    field: Option<&str>,
+    stack: &mut Vec<SortFrame>,
+) -> Result<Value> {
+    // `path` holds the reference tokens leading to the value currently being visited
+    let mut nodes = 1;
+    if let Err(e) = check_limit(Limit::Nodes, nodes, options.max_nodes, &path) {
+        drop_iteratively(value);
+        return Err(e);
+    }
+
+    match SortFrame::open(value, options.array_sort_at(&path, field)) {
+        Ok(frame) => stack.push(frame),
+        Err(leaf) => return sort_leaf(leaf, options, field, &path),
+    }
+
+    while let Some(frame) = stack.last_mut() {
+        match frame.next_child() {
+            Some((token, child)) => {
+                if options.exclude_paths.iter().any(|p| p.is_child(&path, &token))
+                    || options.schema_hints.excludes(&path, &token)
+                {
+                    drop_iteratively(child);
+                    continue;
+                }
+                let field = match frame {
+                    SortFrame::Object { .. } => Some(token.as_str()),
+                    SortFrame::Array { .. } => None,
+                };
+                path.push(token.clone());
+                nodes += 1;
+                if let Err(e) = check_child(options, &path, nodes, field) {
+                    drop_iteratively(child);
+                    return Err(e);
+                }
+                match SortFrame::open(child, options.array_sort_at(&path, field)) {
+                    Ok(child_frame) => stack.push(child_frame),
+                    Err(leaf) => {
+                        let sorted = sort_leaf(leaf, options, field, &path)?;
+                        path.pop();
+                        if let Some(parent) = stack.last_mut() {
+                            parent.accept(token, sorted, options, &path)?;
+                        }
+                    }
+                }
+            }
+            None => {
+                let finished = match stack.pop() {
+                    Some(frame) => frame.finish(options),
+                    None => break,
+                };
+                let Some(parent) = stack.last_mut() else {
+                    return Ok(finished);
+                };
+                let token = path.pop().unwrap_or_default();
+                parent.accept(token, finished, options, &path)?;
+            }
+        }
+    }
+
+    unreachable!("deep_sort work stack emptied without producing a value")
+}
+
+/// Depth, node count and key length checks for a child about to be visited at `path`.
+fn check_child(options: &CanonicalizeOptions, path: &[String], nodes: usize, field: Option<&str>) -> Result<()> {
+    check_depth(options, path)?;
+    check_limit(Limit::Nodes, nodes, options.max_nodes, path)?;
+    match field {
+        Some(key) => check_limit(Limit::StringLength, key.len(), options.max_string_length, path),
+        None => Ok(()),
+    }
+}
+
+/// A partially sorted container on the `deep_sort` work stack.
+enum SortFrame {
+    Object {
+        entries: serde_json::map::IntoIter,
+        result: Map<String, Value>,
+    },
+    Array {
+        items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+        policy: ArraySortPolicy,
+        result: Vec<Value>,
+    },
+}
+
+impl SortFrame {
+    /// Start a frame for a container; primitives are handed back as `Err`.
+    /// `policy` applies to an array.
+    fn open(value: Value, policy: ArraySortPolicy) -> std::result::Result<SortFrame, Value> {
+        match value {
+            Value::Object(map) => Ok(SortFrame::Object {
+                entries: map.into_iter(),
+                result: Map::new(),
+            }),
+            Value::Array(arr) => Ok(SortFrame::Array {
+                result: Vec::with_capacity(arr.len()),
+                items: arr.into_iter().enumerate(),
+                policy,
+            }),
+            leaf => Err(leaf),
+        }
+    }
+
+    /// Next child to visit, with its reference token.
+    fn next_child(&mut self) -> Option<(String, Value)> {
+        match self {
+            SortFrame::Object { entries, .. } => entries.next(),
+            SortFrame::Array { items, .. } => items.next().map(|(i, v)| (i.to_string(), v)),
+        }
+    }
+
+    /// Store a finished child. `path` is the location of this frame's container.
+    fn accept(&mut self, token: String, child: Value, options: &CanonicalizeOptions, path: &[String]) -> Result<()> {
+        match self {
+            SortFrame::Object { .. } if options.prune.drops(&child) => {}
+            SortFrame::Object { result, .. } => {
+                let key = match options.normalization {
+                    Some(form) => form.apply(&token),
+                    None => token,
+                };
+                if result.contains_key(&key) {
+                    // Two distinct keys collapsed into one; either value would be a silent choice
+                    drop_iteratively(child);
+                    return Err(ConstitutionalError::canonicalization_at(
+                        format!("Key {:?} collides with another key after Unicode normalization", key),
+                        path,
+                    ));
+                }
+                result.insert(key, child);
+            }
+            SortFrame::Array { result, .. } => result.push(child),
+        }
+        Ok(())
+    }
+
+    fn finish(self, options: &CanonicalizeOptions) -> Value {
+        match self {
+            SortFrame::Object { result, .. } => Value::Object(result),
+            SortFrame::Array { mut result, policy, .. } => {
+                sort_array(&mut result, policy, options);
+                Value::Array(result)
+            }
+        }
+    }
+
+    fn drop_iteratively(self) {
+        match self {
+            SortFrame::Object { entries, result } => {
+                entries.for_each(|(_, v)| drop_iteratively(v));
+                drop_iteratively(Value::Object(result));
+            }
+            SortFrame::Array { items, result, .. } => {
+                items.for_each(|(_, v)| drop_iteratively(v));
+                drop_iteratively(Value::Array(result));
+            }
+        }
+    }
+}
+
+fn check_limit(limit: Limit, value: usize, max: Option<usize>, path: &[String]) -> Result<()> {
+    match max {
+        Some(max) if value > max => Err(ConstitutionalError::LimitExceeded {
+            limit,
+            max,
+            pointer: Some(JsonPointer::from_tokens(path.iter().cloned())),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn check_depth(options: &CanonicalizeOptions, path: &[String]) -> Result<()> {
+    match options.max_depth {
+        Some(max_depth) if path.len() > max_depth => Err(ConstitutionalError::canonicalization_at(
+            format!("Maximum nesting depth of {} exceeded", max_depth),
+            path,
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn sort_leaf(value: Value, options: &CanonicalizeOptions, field: Option<&str>, path: &[String]) -> Result<Value> {
+    match value {
+        Value::Number(n) => apply_number_policy(&n, options, path),
+        Value::String(s) => {
+            check_limit(Limit::StringLength, s.len(), options.max_string_length, path)?;
+            let s = match field {
+                Some(field) if options.timestamp_fields.iter().any(|f| f == field) => {
+                    normalize_rfc3339(&s, options.timestamp_precision).unwrap_or(s)
+                }
+                _ => s,
+            };
+            match options.normalization {
+                Some(form) => Ok(Value::String(form.apply(&s))),
+                None => Ok(Value::String(s)),
+            }
+        }
+        // Primitives are returned as-is
+        value => Ok(value),
+    }
+}
+
+/// Deep-copy a value without recursing, unlike `Value::clone`.
+fn clone_iteratively(value: &Value) -> Value {
+    enum CloneFrame<'a> {
+        Object(serde_json::map::Iter<'a>, Map<String, Value>),
+        Array(std::slice::Iter<'a, Value>, Vec<Value>),
+    }
+
+    // Open containers, each with the key it will be stored under in its parent object
+    let mut stack: Vec<(CloneFrame, Option<String>)> = Vec::new();
+    let mut next = Some((value, None));
+
+    loop {
+        let mut copied = match next.take() {
+            Some((Value::Object(map), key)) => {
+                stack.push((CloneFrame::Object(map.iter(), Map::new()), key));
+                None
+            }
+            Some((Value::Array(arr), key)) => {
+                stack.push((CloneFrame::Array(arr.iter(), Vec::with_capacity(arr.len())), key));
+                None
+            }
+            Some((scalar, key)) => Some((scalar.clone(), key)),
+            None => None,
+        };
+
+        if copied.is_none() {
+            let child = match stack.last_mut() {
+                Some((CloneFrame::Object(entries, _), _)) => entries.next().map(|(k, v)| (v, Some(k.clone()))),
+                Some((CloneFrame::Array(items, _), _)) => items.next().map(|v| (v, None)),
+                None => None,
+            };
+            if child.is_some() {
+                next = child;
+                continue;
+            }
+            copied = stack.pop().map(|(frame, key)| match frame {
+                CloneFrame::Object(_, map) => (Value::Object(map), key),
+                CloneFrame::Array(_, arr) => (Value::Array(arr), key),
+            });
+        }
+
+        let (value, key) = copied.unwrap_or((Value::Null, None));
+        match stack.last_mut() {
+            Some((CloneFrame::Object(_, map), _)) => {
+                map.insert(key.unwrap_or_default(), value);
+            }
+            Some((CloneFrame::Array(_, arr), _)) => arr.push(value),
+            None => return value,
+        }
+    }
+}
+
+/// Drop a value without recursing, so deeply nested documents cannot overflow the stack
+/// in `Value`'s recursive destructor.
+fn drop_iteratively(value: Value) {
+    let mut pending = vec![value];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Array(arr) => pending.extend(arr),
+            Value::Object(map) => pending.extend(map.into_iter().map(|(_, v)| v)),
+            _ => {}
+        }
+    }
+}
+
+/// Apply the number policy to NaN, Infinity and `-0.0`; other numbers pass through,
+/// subject to integral-float normalization.
+fn apply_number_policy(n: &Number, options: &CanonicalizeOptions, path: &[String]) -> Result<Value> {
+    let f = n.as_f64();
+    let non_finite = f.is_some_and(|f| !f.is_finite());
+    let negative_zero = f.is_some_and(|f| f == 0.0 && f.is_sign_negative());
+
+    let reject = |what: &str| {
+        Err(ConstitutionalError::canonicalization_at(
+            format!("{} number {} is not canonicalizable", what, n),
+            path,
+        ))
+    };
+
+    // JCS serializes every number as an IEEE 754 double; arbitrary-precision values outside
+    // its range have no RFC 8785 representation
+    if options.profile == CanonicalizationProfile::Jcs && f.is_none() {
+        return reject("Out-of-range");
+    }
+
+    match options.number_policy {
+        NumberPolicy::Preserve if non_finite && options.strict => reject("Non-finite"),
+        NumberPolicy::Preserve if non_finite => Ok(Value::Null),
+        NumberPolicy::Preserve => Ok(Value::Number(n.clone())),
+        NumberPolicy::Reject if non_finite => reject("Non-finite"),
+        NumberPolicy::Reject if negative_zero => reject("Negative zero"),
+        NumberPolicy::Reject => Ok(Value::Number(n.clone())),
+        NumberPolicy::Nullify if non_finite => Ok(Value::Null),
+        NumberPolicy::Nullify if negative_zero && options.typed_numbers => {
+            Ok(Value::Number(Number::from_f64(0.0).unwrap_or_else(|| Number::from(0))))
+        }
+        NumberPolicy::Nullify if negative_zero => Ok(Value::Number(Number::from(0))),
+        NumberPolicy::Nullify => Ok(Value::Number(n.clone())),
+        NumberPolicy::Normalize if non_finite => reject("Non-finite"),
+        NumberPolicy::Normalize => Ok(Value::Number(normalize_number(n, options.typed_numbers))),
+    }
+}
+
+/// Reorder the already deep-sorted elements of an array according to `policy`.
+fn sort_array(arr: &mut Vec<Value>, policy: ArraySortPolicy, options: &CanonicalizeOptions) {
+    match policy {
+        ArraySortPolicy::Never => {}
+        ArraySortPolicy::PrimitivesOnly if is_uniform_primitive_array(arr) => {
+            arr.sort_by(|a, b| compare_primitives(a, b, options));
+        }
+        ArraySortPolicy::MixedPrimitives | ArraySortPolicy::PrimitivesAndObjects if is_primitive_array(arr) => {
+            arr.sort_by(|a, b| compare_primitives(a, b, options));
+        }
+        ArraySortPolicy::PrimitivesOnly | ArraySortPolicy::MixedPrimitives => {}
+        ArraySortPolicy::PrimitivesAndObjects if is_container_array(arr) => {
+            arr.sort_by_cached_key(|v| Sha256::digest(canonical_bytes(v, options)));
+        }
+        ArraySortPolicy::PrimitivesAndObjects => {}
+        ArraySortPolicy::ByElementHash => {
+            arr.sort_by_cached_key(|v| Sha256::digest(canonical_bytes(v, options)));
+        }
+        ArraySortPolicy::Always => sort_total(arr, options),
+    }
+}
+
+/// Sort by the total order documented on `ArraySortPolicy`. Containers are compared by
+/// their canonical form, computed once per element rather than by recursing, so the
+/// comparison is bounded regardless of nesting depth.
+fn sort_total(arr: &mut Vec<Value>, options: &CanonicalizeOptions) {
+    let mut keyed: Vec<(Option<Vec<u8>>, Value)> = arr
+        .drain(..)
+        .map(|v| match v {
+            Value::Array(_) | Value::Object(_) => (Some(canonical_bytes(&v, options)), v),
+            _ => (None, v),
+        })
+        .collect();
+
+    keyed.sort_by(|(key_a, a), (key_b, b)| match (key_a, key_b) {
+        (Some(x), Some(y)) if type_rank(a) == type_rank(b) => x.cmp(y),
+        _ => compare_primitives(a, b, options),
+    });
+    arr.extend(keyed.into_iter().map(|(_, v)| v));
+}
+
+fn canonical_bytes(value: &Value, options: &CanonicalizeOptions) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Writing to a Vec cannot fail
+    let _ = write_canonical(value, options, &mut out);
+    out
+}
+
+/// True if every element is a primitive (null, bool, number or string).
+fn is_primitive_array(arr: &[Value]) -> bool {
+    arr.iter().all(|v| {
+        matches!(
+            v,
+            Value::String(_) | Value::Number(_) | Value::Bool(_) | Value::Null
+        )
+    })
+}
+
+/// True if the array is non-empty and every element is a primitive of the same type.
+pub(crate) fn is_uniform_primitive_array<V: std::borrow::Borrow<Value>>(arr: &[V]) -> bool {
+    match arr.first() {
+        Some(first) if !matches!(first.borrow(), Value::Array(_) | Value::Object(_)) => {
+            let first_type = std::mem::discriminant(first.borrow());
+            arr.iter().all(|v| std::mem::discriminant(v.borrow()) == first_type)
+        }
+        _ => false,
+    }
+}
+
+/// True if every element is an object or an array.
+fn is_container_array(arr: &[Value]) -> bool {
+    arr.iter().all(|v| v.is_object() || v.is_array())
+}
+
+/// Position of a value's JSON type in the cross-type total order.
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    }
+}
+
+/// Custom comparison for primitive JSON values, ranking by type first so mixed-type
+/// arrays sort deterministically. Containers of the same type compare as equal.
+/// Strings follow the key collation; with `typed_numbers`, numerically equal integers
+/// order before floats.
+fn compare_primitives(a: &Value, b: &Value, options: &CanonicalizeOptions) -> Ordering {
+    match (a, b) {
+        (Value::String(s1), Value::String(s2)) => options.key_collation.compare(s1, s2),
+        (Value::Number(n1), Value::Number(n2)) if options.typed_numbers => {
+            compare_numbers(n1, n2).then_with(|| n1.is_f64().cmp(&n2.is_f64()))
+        }
+        // Exact decimal comparison; going through f64 would merge distinct large integers
+        (Value::Number(n1), Value::Number(n2)) => compare_numbers(n1, n2),
+        (Value::Bool(b1), Value::Bool(b2)) => b1.cmp(b2),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Bytes `write_canonical` buffers before each write to its sink.
+const WRITE_CHUNK: usize = 8 * 1024;
+
+/// Serialize a deep-sorted value as compact JSON, emitting object keys in collation order.
+/// Strings go through the escaping table in escape.rs; under the OCP profile other
+/// scalars use serde_json's formatting, so with default escaping the output matches
+/// `serde_json::to_string` whenever the collation is `KeyCollation::Bytes`.
+///
+/// Like `deep_sort`, this walks an explicit stack instead of recursing. Output is staged
+/// in a buffer of about `WRITE_CHUNK` bytes and handed to `sink` as it fills.
+fn write_canonical<W: Write + ?Sized>(value: &Value, options: &CanonicalizeOptions, sink: &mut W) -> std::io::Result<()> {
+    enum WriteFrame<'a> {
+        Object(std::vec::IntoIter<(&'a String, &'a Value)>, bool),
+        Array(std::slice::Iter<'a, Value>, bool),
+    }
+
+    let mut stack: Vec<WriteFrame> = Vec::new();
+    let mut next = Some(value);
+    let mut buffer = String::with_capacity(WRITE_CHUNK);
+    let out = &mut buffer;
+
+    loop {
+        if out.len() >= WRITE_CHUNK {
+            sink.write_all(out.as_bytes())?;
+            out.clear();
+        }
+
+        if let Some(value) = next.take() {
+            match value {
+                Value::Object(map) => {
+                    let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+                    entries.sort_by(|a, b| options.key_collation.compare(a.0, b.0));
+                    out.push('{');
+                    stack.push(WriteFrame::Object(entries.into_iter(), true));
+                }
+                Value::Array(arr) => {
+                    out.push('[');
+                    stack.push(WriteFrame::Array(arr.iter(), true));
+                }
+                leaf => write_leaf(leaf, options, out),
+            }
+        }
+
+        let Some(frame) = stack.last_mut() else {
+            return sink.write_all(out.as_bytes());
+        };
+        match frame {
+            WriteFrame::Object(entries, first) => match entries.next() {
+                Some((k, v)) => {
+                    if !std::mem::replace(first, false) {
+                        out.push(',');
+                    }
+                    write_string(k, options.escaping, out);
+                    out.push(':');
+                    next = Some(v);
+                }
+                None => {
+                    out.push('}');
+                    stack.pop();
+                }
+            },
+            WriteFrame::Array(items, first) => match items.next() {
+                Some(v) => {
+                    if !std::mem::replace(first, false) {
+                        out.push(',');
+                    }
+                    next = Some(v);
+                }
+                None => {
+                    out.push(']');
+                    stack.pop();
+                }
+            },
+        }
+    }
+}
+
+/// Serialize a primitive that has already been through `sort_leaf`.
+fn write_leaf(value: &Value, options: &CanonicalizeOptions, out: &mut String) {
+    match value {
+        Value::Number(n) if options.profile == CanonicalizationProfile::Jcs => {
+            out.push_str(&format_ecmascript_number(n.as_f64().unwrap_or(0.0)));
+        }
+        Value::String(s) => write_string(s, options.escaping, out),
+        _ => write_scalar(value, out),
+    }
+}
+
+fn write_scalar(value: &Value, out: &mut String) {
+    // Serializing a scalar Value cannot fail
+    out.push_str(&serde_json::to_string(value).unwrap_or_default());
+}
+
+/// Convert a serde_json::Value to a deterministically ordered, canonical JSON string.
+/// Matches Python's canonicalize and JavaScript's canonicalize functions.
+///
+/// # Arguments
+/// * `data` - Input JSON value to canonicalize
+/// * `strict` - If true, returns error on non-canonicalizable data
+///
+/// # Returns
+/// Canonical JSON string (compact, no whitespace, sorted keys)
+pub fn canonicalize(data: &Value, strict: bool) -> Result<String> {
+    canonicalize_with(data, &CanonicalizeOptions::new().strict(strict))
+}
+
+/// Convert a serde_json::Value to a canonical JSON string using explicit options.
+///
+/// # Arguments
+/// * `data` - Input JSON value to canonicalize
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Canonical JSON string (compact, no whitespace, sorted keys)
+pub fn canonicalize_with(data: &Value, options: &CanonicalizeOptions) -> Result<String> {
+    let mut canonical_json = Vec::new();
+    canonicalize_to_writer(data, options, &mut canonical_json)?;
+    into_string(canonical_json)
+}
+
+/// Canonicalize a value the caller no longer needs, sorting it in place.
+///
+/// When the options need a sorted copy of the document (see `canonicalize_to_writer`),
+/// keys and strings are moved into it instead of cloned, so this allocates far less than
+/// `canonicalize_with` on large documents. The output is identical.
+///
+/// # Arguments
+/// * `data` - Input JSON value to canonicalize, consumed
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Canonical JSON string (compact, no whitespace, sorted keys)
+pub fn canonicalize_owned(data: Value, options: &CanonicalizeOptions) -> Result<String> {
+    let mut canonical_json = Vec::new();
+    write_owned(data, options, &mut canonical_json)?;
+    into_string(canonical_json)
+}
+
+/// Canonicalize any serializable Rust value, such as a typed contract struct, without
+/// building a `serde_json::Value` by hand.
+///
+/// # Arguments
+/// * `data` - Value to serialize and canonicalize
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Canonical JSON string, identical to `canonicalize_with` on the value's JSON form
+pub fn canonicalize_serializable<T: Serialize + ?Sized>(data: &T, options: &CanonicalizeOptions) -> Result<String> {
+    canonicalize_owned(to_json(data)?, options)
+}
+
+fn to_json<T: Serialize + ?Sized>(data: &T) -> Result<Value> {
+    serde_json::to_value(data)
+        .map_err(|e| ConstitutionalError::canonicalization(format!("Cannot serialize value to JSON: {}", e)))
+}
+
+/// Support for the code `#[derive(Canonicalize)]` generates; not a stable API.
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+    pub use serde_json::{Map, Value};
+
+    /// Default options, with the top-level members `fields` kept in input order.
+    pub fn ordered_fields(fields: &[&str]) -> CanonicalizeOptions {
+        let properties: Map<String, Value> =
+            fields.iter().map(|field| (field.to_string(), json!({"x-ocp-ordered": true}))).collect();
+        // Boolean annotations on `properties` are always valid hints
+        let hints = SchemaHints::from_schema(&json!({"properties": properties})).unwrap_or_default();
+        CanonicalizeOptions::new().schema_hints(hints)
+    }
+}
+
+/// Canonicalize many documents that share field names, such as the entries of a ledger.
+///
+/// Path buffers and the normalized forms of keys are kept from one document to the next,
+/// so keys repeated across documents stop allocating after their first occurrence. Each
+/// output is identical to `canonicalize_with` on that document.
+///
+/// # Arguments
+/// * `items` - Input JSON values to canonicalize
+/// * `options` - Canonicalization options shared by every document
+///
+/// # Returns
+/// One canonical JSON string or error per document, in input order
+pub fn canonicalize_batch(items: &[Value], options: &CanonicalizeOptions) -> Vec<Result<String>> {
+    if !stream::streamable(options) {
+        return items.iter().map(|data| canonicalize_with(data, options)).collect();
+    }
+
+    let mut scratch = Scratch::default();
+    items
+        .iter()
+        .map(|data| {
+            check_top_level(data, options)?;
+            let mut canonical_json = Vec::new();
+            stream::write_streaming(data, options, &mut scratch, &mut canonical_json)?;
+            into_string(canonical_json)
+        })
+        .collect()
+}
+
+fn into_string(canonical_json: Vec<u8>) -> Result<String> {
+    // The writer only emits UTF-8
+    String::from_utf8(canonical_json).map_err(|e| ConstitutionalError::canonicalization(e.to_string()))
+}
+
+/// Canonicalize data straight into a byte sink (a file, socket or hasher) without building
+/// the canonical string in memory. The bytes written are exactly those of `canonicalize_with`.
+///
+/// Unless the options prune empty containers or sort arrays of containers, the output is
+/// produced in a single pass over `data` with no sorted copy, so memory grows with nesting
+/// depth rather than document size. On error, part of the output may have been written.
+///
+/// With the `rayon` feature, the members of a large top-level object are canonicalized
+/// in parallel and joined in order; the bytes are unchanged.
+///
+/// # Arguments
+/// * `data` - Input JSON value to canonicalize
+/// * `options` - Canonicalization options
+/// * `writer` - Destination for the canonical UTF-8 bytes
+///
+/// # Returns
+/// Ok once every byte has been written; I/O failures surface as `IoError`
+pub fn canonicalize_to_writer(data: &Value, options: &CanonicalizeOptions, writer: &mut impl Write) -> Result<()> {
+    check_top_level(data, options)?;
+    #[cfg(feature = "rayon")]
+    if let Some(map) = parallel::eligible(data, options) {
+        return parallel::write_parallel(map, options, writer);
+    }
+    if stream::streamable(options) {
+        stream::write_streaming(data, options, &mut Scratch::default(), writer)
+    } else {
+        write_owned(clone_iteratively(data), options, writer)
+    }
+}
+
+fn write_owned(data: Value, options: &CanonicalizeOptions, writer: &mut impl Write) -> Result<()> {
+    if let Err(e) = check_top_level(&data, options) {
+        drop_iteratively(data);
+        return Err(e);
+    }
+    #[cfg(feature = "rayon")]
+    if let Some(map) = parallel::eligible(&data, options) {
+        let written = parallel::write_parallel(map, options, writer);
+        drop_iteratively(data);
+        return written;
+    }
+    if stream::streamable(options) {
+        let written = stream::write_streaming(&data, options, &mut Scratch::default(), writer);
+        drop_iteratively(data);
+        return written;
+    }
+
+    // Deep sort the entire structure, dropping excluded members
+    let sorted_data = if data.is_object() || options.top_level == TopLevelPolicy::Native {
+        deep_sort(data, options)?
+    } else {
+        let mut wrapped = Map::new();
+        wrapped.insert("value".to_string(), deep_sort(data, options)?);
+        Value::Object(wrapped)
+    };
+
+    // Write the canonical JSON using compact representation
+    let written = write_canonical(&sorted_data, options, writer);
+    drop_iteratively(sorted_data);
+    Ok(written?)
+}
+
+fn check_top_level(data: &Value, options: &CanonicalizeOptions) -> Result<()> {
+    match (data, options.top_level) {
+        (Value::Object(_), _) | (_, TopLevelPolicy::Wrap | TopLevelPolicy::Native) => Ok(()),
+        (_, TopLevelPolicy::Reject) => Err(ConstitutionalError::canonicalization_at(
+            format!("Input must be an object, got {:?}", data.type_str()),
+            &[],
+        )),
+    }
+}
+
+/// Parse raw JSON text and canonicalize it, rejecting documents with duplicate object keys.
+///
+/// serde_json keeps the last of several duplicate keys while other parsers keep the first
+/// or refuse the document, so accepting duplicates would make the semantic hash ambiguous.
+/// In strict mode the text is first checked with `validate_str`, and every violation found
+/// is returned at once as `ValidationFailed`.
+///
+/// # Arguments
+/// * `json` - Raw JSON text
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Canonical JSON string (compact, no whitespace, sorted keys)
+pub fn canonicalize_str(json: &str, options: &CanonicalizeOptions) -> Result<String> {
+    let data = parse_validated(json, options)?;
+    canonicalize_owned(data, options)
+}
+
+fn parse_validated(json: &str, options: &CanonicalizeOptions) -> Result<Value> {
+    if let Some(max) = options.max_bytes.filter(|max| json.len() > *max) {
+        return Err(ConstitutionalError::LimitExceeded { limit: Limit::Bytes, max, pointer: None });
+    }
+    if options.strict {
+        let violations = validate_str(json, options);
+        if !violations.is_empty() {
+            return Err(ConstitutionalError::ValidationFailed(violations));
+        }
+    }
+    parse::parse_unique_keys(json)
+}
+
+/// Decode a UTF-8 byte buffer (e.g. a contract received over the wire) and canonicalize
+/// it, rejecting invalid UTF-8 and duplicate object keys.
+///
+/// # Arguments
+/// * `bytes` - Raw JSON bytes
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Canonical JSON string (compact, no whitespace, sorted keys)
+pub fn canonicalize_bytes(bytes: &[u8], options: &CanonicalizeOptions) -> Result<String> {
+    let json = std::str::from_utf8(bytes).map_err(|e| {
+        ConstitutionalError::canonicalization(format!("Input is not valid UTF-8: {}", e))
+    })?;
+    canonicalize_str(json, options)
+}
+
+/// Canonical form of a document together with its semantic hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalDigest {
+    /// Canonical JSON string that was hashed
+    pub canonical: String,
+    /// Hexadecimal string of the semantic hash
+    pub hash: String,
+}
+
+/// Decode, parse, canonicalize and hash a UTF-8 byte buffer in one call.
+///
+/// # Arguments
+/// * `bytes` - Raw JSON bytes
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The canonical JSON string and its semantic hash
+pub fn semantic_hash_bytes(bytes: &[u8], options: &CanonicalizeOptions) -> Result<CanonicalDigest> {
+    let canonical = canonicalize_bytes(bytes, options)?;
+    let mut hasher = versioned_hasher(options);
+    hasher.update(canonical.as_bytes());
+    let hash = digest_text(options.hash_encoding, &hasher.finalize())?;
+    Ok(CanonicalDigest { canonical, hash })
+}
+
+/// Calculate the cryptographic hash of canonicalized data.
+/// Matches Python's semantic_hash and JavaScript's semanticHash functions.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+///
+/// # Returns
+/// Hexadecimal string of the SHA256 hash
+pub fn semantic_hash(data: &Value) -> Result<String> {
+    semantic_hash_with(data, &CanonicalizeOptions::default())
+}
+
+/// Calculate the cryptographic hash of data canonicalized with explicit options.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The hash under the algorithm the options select, written in the options' encoding;
+/// a HashingError for `Encoding::Binary`, which only `semantic_hash_encoded` returns
+pub fn semantic_hash_with(data: &Value, options: &CanonicalizeOptions) -> Result<String> {
+    digest_text(options.hash_encoding, &semantic_digest(data, options)?)
+}
+
+/// Calculate the semantic hash of data as the bytes of the options' encoding: ASCII
+/// text, or the digest itself under `Encoding::Binary`.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The encoded digest
+pub fn semantic_hash_encoded(data: &Value, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    Ok(options.hash_encoding.encode(&semantic_digest(data, options)?))
+}
+
+/// Calculate the semantic hash of data as raw digest bytes, for callers that feed it
+/// into further hashing or signing and would only decode hex again.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+///
+/// # Returns
+/// The 32-byte SHA-256 digest
+pub fn semantic_hash_raw(data: &Value) -> Result<[u8; 32]> {
+    semantic_hash_raw_with(data, &CanonicalizeOptions::default())
+}
+
+/// Calculate the semantic hash of data canonicalized with explicit options as raw bytes.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `options` - Canonicalization options; the algorithm must have a 256-bit digest
+///
+/// # Returns
+/// The 32-byte digest, or a HashingError under SHA-512
+pub fn semantic_hash_raw_with(data: &Value, options: &CanonicalizeOptions) -> Result<[u8; 32]> {
+    Ok(SemanticHash::compute(data, options)?.into())
+}
+
+fn semantic_digest(data: &Value, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    // Stream the canonical bytes into the hasher rather than materializing them
+    let mut hasher = versioned_hasher(options);
+    canonicalize_to_writer(data, options, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// Calculate the semantic hash of data under a chosen hash algorithm, with otherwise
+/// default options.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `algorithm` - Hash function applied to the canonical bytes
+///
+/// # Returns
+/// Hexadecimal string of the digest
+pub fn semantic_hash_using(data: &Value, algorithm: HashAlgorithm) -> Result<String> {
+    semantic_hash_with(data, &CanonicalizeOptions::default().hash_algorithm(algorithm))
+}
+
+/// Calculate the semantic hashes of many documents, such as every contract in a ledger.
+///
+/// With the `rayon` feature the documents are hashed on the rayon thread pool; without
+/// it they are hashed in turn, sharing key buffers as `canonicalize_batch` does. Either
+/// way results are in input order and one failing document does not stop the others.
+///
+/// # Arguments
+/// * `items` - Input JSON values to hash
+/// * `options` - Canonicalization options shared by every document; the algorithm must
+///   have a 256-bit digest
+///
+/// # Returns
+/// One hash or error per document, in input order
+pub fn semantic_hash_batch(items: &[Value], options: &CanonicalizeOptions) -> Vec<Result<SemanticHash>> {
+    batch_digests(items, options, |digest| SemanticHash::try_from(digest.as_slice()))
+}
+
+/// Verify many documents against their expected hashes, as `semantic_hash_batch` hashes
+/// them. Each digest is compared in constant time.
+///
+/// # Arguments
+/// * `items` - Input JSON values to verify
+/// * `expected` - Expected hash of each document, in the same order
+/// * `options` - Canonicalization options the hashes were produced with
+///
+/// # Returns
+/// For each document, whether its hash matches, or the error hashing it raised; a
+/// HashingError if `items` and `expected` differ in length
+pub fn verify_batch(
+    items: &[Value],
+    expected: &[SemanticHash],
+    options: &CanonicalizeOptions,
+) -> Result<Vec<Result<bool>>> {
+    if items.len() != expected.len() {
+        return Err(ConstitutionalError::HashingError(format!(
+            "verify_batch needs one expected hash per document, got {} for {}",
+            expected.len(),
+            items.len()
+        )));
+    }
+    // Results come back in input order on both paths, so they pair up with `expected`
+    Ok(batch_digests(items, options, Ok)
+        .into_iter()
+        .zip(expected)
+        .map(|(digest, hash)| digest.map(|digest| constant_time_eq(&digest, hash.as_bytes())))
+        .collect())
+}
+
+/// Hash every document and map each digest through `finish`, in input order.
+fn batch_digests<T: Send>(
+    items: &[Value],
+    options: &CanonicalizeOptions,
+    finish: impl Fn(Vec<u8>) -> Result<T> + Sync + Send,
+) -> Vec<Result<T>> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(|data| semantic_digest(data, options).and_then(&finish)).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        if !stream::streamable(options) {
+            return items.iter().map(|data| semantic_digest(data, options).and_then(&finish)).collect();
+        }
+        let mut scratch = Scratch::default();
+        items
+            .iter()
+            .map(|data| {
+                check_top_level(data, options)?;
+                let mut hasher = versioned_hasher(options);
+                stream::write_streaming(data, options, &mut scratch, &mut hasher)?;
+                finish(hasher.finalize())
+            })
+            .collect()
+    }
+}
+
+/// Calculate the semantic hash of an object of a named type, so that objects of
+/// different types never share a hash even when their JSON is identical.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `object_type` - Name of the object's type, e.g. `Contract` or `Evidence`
+///
+/// # Returns
+/// Hexadecimal string of the typed hash
+pub fn semantic_hash_typed(data: &Value, object_type: &str) -> Result<String> {
+    semantic_hash_typed_with(data, object_type, &CanonicalizeOptions::default())
+}
+
+/// Calculate the typed semantic hash of data canonicalized with explicit options.
+///
+/// The hashed bytes are the version tag, then the type tag `<length>:<object_type>`
+/// (the type name's length in bytes, in decimal ASCII), then the canonical form. The
+/// length delimits the name, so no choice of type name and document can produce the
+/// bytes of another pair, or of an untyped hash.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `object_type` - Name of the object's type; must not be empty
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The typed hash, written in the options' encoding
+pub fn semantic_hash_typed_with(data: &Value, object_type: &str, options: &CanonicalizeOptions) -> Result<String> {
+    digest_text(options.hash_encoding, &typed_digest(data, object_type, options)?)
+}
+
+/// Calculate the typed semantic hash of data as raw digest bytes.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `object_type` - Name of the object's type; must not be empty
+/// * `options` - Canonicalization options; the algorithm must have a 256-bit digest
+///
+/// # Returns
+/// The 32-byte typed digest
+pub fn semantic_hash_typed_raw(data: &Value, object_type: &str, options: &CanonicalizeOptions) -> Result<[u8; 32]> {
+    Ok(SemanticHash::try_from(typed_digest(data, object_type, options)?.as_slice())?.into())
+}
+
+fn typed_digest(data: &Value, object_type: &str, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    let mut hasher = versioned_hasher(options);
+    hasher.update(type_tag(object_type)?.as_bytes());
+    canonicalize_to_writer(data, options, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// The length-delimited tag hashed between the version tag and a typed object.
+fn type_tag(object_type: &str) -> Result<String> {
+    if object_type.is_empty() {
+        return Err(ConstitutionalError::HashingError("Object type must not be empty".to_string()));
+    }
+    Ok(format!("{}:{}", object_type.len(), object_type))
+}
+
+/// Calculate a keyed BLAKE3 semantic hash, which only holders of `key` can reproduce.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `key` - 256-bit BLAKE3 key
+///
+/// # Returns
+/// Hexadecimal string of the keyed BLAKE3 digest
+#[cfg(feature = "blake3")]
+pub fn semantic_hash_keyed(data: &Value, key: &[u8; 32]) -> Result<String> {
+    semantic_hash_keyed_with(data, key, &CanonicalizeOptions::default())
+}
+
+/// Calculate a keyed BLAKE3 semantic hash of data canonicalized with explicit options.
+/// The options' hash algorithm is ignored.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `key` - 256-bit BLAKE3 key
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Hexadecimal string of the keyed BLAKE3 digest
+#[cfg(feature = "blake3")]
+pub fn semantic_hash_keyed_with(data: &Value, key: &[u8; 32], options: &CanonicalizeOptions) -> Result<String> {
+    let mut hasher = CanonicalHasher::keyed(key, options);
+    canonicalize_to_writer(data, options, &mut hasher)?;
+    hasher.finalize()
+}
+
+/// Calculate the semantic hash of any serializable Rust value.
+///
+/// # Arguments
+/// * `data` - Value to serialize and hash
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The hash, identical to `semantic_hash_with` on the value's JSON form
+pub fn semantic_hash_serializable<T: Serialize + ?Sized>(data: &T, options: &CanonicalizeOptions) -> Result<String> {
+    semantic_hash_owned(to_json(data)?, options)
+}
+
+fn semantic_hash_owned(data: Value, options: &CanonicalizeOptions) -> Result<String> {
+    let mut hasher = versioned_hasher(options);
+    write_owned(data, options, &mut hasher)?;
+    digest_text(options.hash_encoding, &hasher.finalize())
+}
+
+/// A hasher under the options' algorithm, already fed the canonicalization version tag.
+fn versioned_hasher(options: &CanonicalizeOptions) -> Hasher {
+    let mut hasher = Hasher::new(options.hash_algorithm);
+    hasher.update(options.canon_version.tag().as_bytes());
+    hasher
+}
+
+/// Calculate the semantic hash of raw JSON text, rejecting duplicate object keys.
+///
+/// # Arguments
+/// * `json` - Raw JSON text
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// Hexadecimal string of the hash, under the algorithm the options select
+pub fn semantic_hash_str(json: &str, options: &CanonicalizeOptions) -> Result<String> {
+    let data = parse_validated(json, options)?;
+    semantic_hash_owned(data, options)
+}
+
+/// Verify that data produces the expected semantic hash.
+/// Matches Python's verify_semantic_hash and JavaScript's verifySemanticHash functions,
+/// except that hex is accepted in either case and compared in constant time.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `expected_hash` - Expected hash value (hex string, or any encoding `verify_semantic_hash_with` reads)
+///
+/// # Returns
+/// true if hash matches, false otherwise
+pub fn verify_semantic_hash(data: &Value, expected_hash: &str) -> Result<bool> {
+    verify_semantic_hash_with(data, expected_hash, &CanonicalizeOptions::default())
+}
+
+/// Verify that data produces the expected semantic hash under explicit options.
+///
+/// The expected hash may name its algorithm, as in `sha3-256:3a98...`; otherwise it is
+/// checked under the algorithm the options select. The digest may be in any encoding
+/// (hex in either case, or multibase base64url or base58btc), whatever the options'
+/// `hash_encoding`.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `expected_hash` - Expected hash value, optionally `algorithm:`-prefixed
+/// * `options` - Canonicalization options the hash was produced with
+///
+/// # Returns
+/// true if hash matches, false otherwise; a HashingError for an unknown algorithm prefix
+pub fn verify_semantic_hash_with(
+    data: &Value,
+    expected_hash: &str,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    verify_digest(expected_hash, options, |options| semantic_digest(data, options))
+}
+
+/// Verify that an object of a named type produces the expected typed semantic hash.
+/// The expected hash is read as `verify_semantic_hash_with` reads it.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `object_type` - Name of the type the hash was produced for
+/// * `expected_hash` - Expected hash value, optionally `algorithm:`-prefixed
+/// * `options` - Canonicalization options the hash was produced with
+///
+/// # Returns
+/// true if hash matches, false otherwise (including when the type differs)
+pub fn verify_semantic_hash_typed(
+    data: &Value,
+    object_type: &str,
+    expected_hash: &str,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    verify_digest(expected_hash, options, |options| typed_digest(data, object_type, options))
+}
+
+/// Decode an expected hash in any encoding, with an optional algorithm prefix, and
+/// compare it with the digest `digest` computes under the algorithm it names. Digests
+/// are compared as bytes in constant time, so a network-facing verifier does not reveal
+/// how much of a forged hash was right.
+fn verify_digest(
+    expected_hash: &str,
+    options: &CanonicalizeOptions,
+    digest: impl FnOnce(&CanonicalizeOptions) -> Result<Vec<u8>>,
+) -> Result<bool> {
+    let (algorithm, encoded) = match expected_hash.split_once(':') {
+        Some((identifier, encoded)) => (identifier.parse()?, encoded),
+        None => (options.hash_algorithm, expected_hash),
+    };
+    let Some(expected) = decode_digest(encoded, algorithm.digest_len()) else {
+        return Ok(false);
+    };
+    let actual = digest(&options.clone().hash_algorithm(algorithm))?;
+    Ok(constant_time_eq(&actual, &expected))
+}
+
+/// Verify a keyed BLAKE3 semantic hash.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `key` - 256-bit BLAKE3 key the hash was produced with
+/// * `expected_hash` - Expected hash value, in any encoding
+/// * `options` - Canonicalization options the hash was produced with
+///
+/// # Returns
+/// true if hash matches, false otherwise
+#[cfg(feature = "blake3")]
+pub fn verify_semantic_hash_keyed(
+    data: &Value,
+    key: &[u8; 32],
+    expected_hash: &str,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    let Some(expected) = decode_digest(expected_hash, 32) else {
+        return Ok(false);
+    };
+    let mut hasher = CanonicalHasher::keyed(key, options);
+    canonicalize_to_writer(data, options, &mut hasher)?;
+    Ok(constant_time_eq(&hasher.finalize_bytes(), &expected))
+}
+
+/// Calculate an authenticated semantic hash (HMAC-SHA256 over the canonical form), which
+/// only holders of `key` can produce or check.
+///
+/// # Arguments
+/// * `data` - Input JSON value to authenticate
+/// * `key` - The tenant's secret key
+///
+/// # Returns
+/// Hexadecimal string of the HMAC
+pub fn semantic_hmac(data: &Value, key: &HmacKey) -> Result<String> {
+    semantic_hmac_with(data, key, &CanonicalizeOptions::default())
+}
+
+/// Calculate an authenticated semantic hash of data canonicalized with explicit options.
+/// The HMAC is always HMAC-SHA256; the options' hash algorithm is ignored.
+///
+/// # Arguments
+/// * `data` - Input JSON value to authenticate
+/// * `key` - The tenant's secret key
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The HMAC, written in the options' encoding
+pub fn semantic_hmac_with(data: &Value, key: &HmacKey, options: &CanonicalizeOptions) -> Result<String> {
+    digest_text(options.hash_encoding, &hmac_bytes(data, key, options)?)
+}
+
+/// Calculate an authenticated semantic hash as the raw HMAC bytes.
+///
+/// # Arguments
+/// * `data` - Input JSON value to authenticate
+/// * `key` - The tenant's secret key
+/// * `options` - Canonicalization options
+///
+/// # Returns
+/// The 32-byte HMAC-SHA256 tag
+pub fn semantic_hmac_raw(data: &Value, key: &HmacKey, options: &CanonicalizeOptions) -> Result<[u8; 32]> {
+    hmac_bytes(data, key, options)
+}
+
+/// Verify an authenticated semantic hash, comparing in constant time.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `key` - The tenant's secret key
+/// * `expected_mac` - Expected HMAC, in any encoding
+///
+/// # Returns
+/// true if the HMAC matches, false otherwise
+pub fn verify_semantic_hmac(data: &Value, key: &HmacKey, expected_mac: &str) -> Result<bool> {
+    verify_semantic_hmac_with(data, key, expected_mac, &CanonicalizeOptions::default())
+}
+
+/// Verify an authenticated semantic hash produced under explicit options.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `key` - The tenant's secret key
+/// * `expected_mac` - Expected HMAC, in any encoding
+/// * `options` - Canonicalization options the HMAC was produced with
+///
+/// # Returns
+/// true if the HMAC matches, false otherwise
+pub fn verify_semantic_hmac_with(
+    data: &Value,
+    key: &HmacKey,
+    expected_mac: &str,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    let actual = hmac_bytes(data, key, options)?;
+    Ok(decode_digest(expected_mac, 32).is_some_and(|expected| constant_time_eq(&actual, &expected)))
+}
+
+fn hmac_bytes(data: &Value, key: &HmacKey, options: &CanonicalizeOptions) -> Result<[u8; 32]> {
+    let mut mac = HmacSha256::new(key);
+    mac.update(options.canon_version.tag().as_bytes());
+    canonicalize_to_writer(data, options, &mut mac)?;
+    Ok(mac.finalize())
+}
+
+/// Compare two JSON values for canonical equality.
+///
+/// # Arguments
+/// * `data1` - First JSON value
+/// * `data2` - Second JSON value
+///
+/// # Returns
+/// true if canonical forms are identical
+pub fn canonically_equal(data1: &Value, data2: &Value) -> bool {
+    match (canonicalize(data1, true), canonicalize(data2, true)) {
+        (Ok(canon1), Ok(canon2)) => canon1 == canon2,
+        _ => false,
+    }
+}
+
+/// Helper trait to get type name of JSON value
+trait JsonTypeStr {
+    fn type_str(&self) -> &'static str;
+}
+
+impl JsonTypeStr for Value {
+    fn type_str(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algorithm::hex;
+
+    fn sha256_hex(canonical_string: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(canonical_string.as_bytes());
+        let result = hasher.finalize();
+
+        format!("{:x}", result)
+    }
+
+    #[test]
+    fn test_basic_canonicalization() {
+        let dict_a = json!({
+            "a": 1,
+            "b": 2,
+            "c": 3
+        });
+
+        let dict_b = json!({
+            "c": 3,
+            "a": 1,
+            "b": 2
+        });
+
+        let canonical_a = canonicalize(&dict_a, true).unwrap();
+        let canonical_b = canonicalize(&dict_b, true).unwrap();
+
+        assert_eq!(canonical_a, canonical_b);
+        assert_eq!(
+            semantic_hash(&dict_a).unwrap(),
+            semantic_hash(&dict_b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nested_structures() {
+        let complex_dict = json!({
+            "z": [3, 1, 2],
+            "a": {
+                "c": 3,
+                "a": 1,
+                "b": {
+                    "f": 6,
+                    "d": 4,
+                    "e": 5
+                }
+            },
+            "b": 2
+        });
+
+        let canonical = canonicalize(&complex_dict, true).unwrap();
+        let expected = r#"{"a":{"a":1,"b":{"d":4,"e":5,"f":6},"c":3},"b":2,"z":[1,2,3]}"#;
+        assert_eq!(canonical, expected);
+    }
+
+    #[test]
+    fn test_hash_sensitivity() {
+        let original = json!({
+            "action_id": "001-XYZ",
+            "agent": "Claude-3",
+            "claim": "The initial cost is $500",
+            "evidence_ptr": "archive://0000001",
+            "timestamp": 1700000000
+        });
+
+        let modified = json!({
+            "action_id": "001-XYZ",
+            "agent": "Claude-3",
+            "claim": "The initial cost is $501",
+            "evidence_ptr": "archive://0000001",
+            "timestamp": 1700000000
+        });
+
+        let hash_original = semantic_hash(&original).unwrap();
+        let hash_modified = semantic_hash(&modified).unwrap();
+
+        assert_ne!(hash_original, hash_modified);
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let test_data = json!({
+            "action": "propose",
+            "value": 42
+        });
+
+        let test_hash = semantic_hash(&test_data).unwrap();
+
+        assert!(verify_semantic_hash(&test_data, &test_hash).unwrap());
+
+        let tampered_data = json!({
+            "action": "propose",
+            "value": 43
+        });
+
+        assert!(!verify_semantic_hash(&tampered_data, &test_hash).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_equality() {
+        let obj1 = json!({"z": 1, "a": 2});
+        let obj2 = json!({"a": 2, "z": 1});
+
+        assert!(canonically_equal(&obj1, &obj2));
+    }
+
+    #[test]
+    fn test_complex_contract() {
+        let contract = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "proposer_agent": "Claude",
+            "action_type": "amend",
+            "action": {
+                "target": "amendment-article-3",
+                "operation": "modify"
+            },
+            "evidence": [
+                {
+                    "type": "archive_reference",
+                    "pointer": "sha256:abc123def456"
+                }
+            ],
+            "reasoning": {
+                "rationale": "Clarifies Article III.1",
+                "confidence": 0.87
+            },
+            "timestamp": "2025-11-20T14:30:00Z"
+        });
+
+        let hash = semantic_hash(&contract).unwrap();
+        assert!(hash.len() == 64); // SHA256 hex is 64 chars
+        assert!(verify_semantic_hash(&contract, &hash).unwrap());
+    }
+
+    #[test]
+    fn test_array_sorting() {
+        let with_unsorted = json!({
+            "numbers": [5, 3, 1, 4, 2]
+        });
+
+        let with_sorted = json!({
+            "numbers": [1, 2, 3, 4, 5]
+        });
+
+        assert_eq!(
+            semantic_hash(&with_unsorted).unwrap(),
+            semantic_hash(&with_sorted).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cross_language_vector() {
+        // Test vector for cross-language validation
+        let python_test_vector = json!({
+            "action": "propose",
+            "agent": "Claude",
+            "confidence": 0.88,
+            "timestamp": "2025-11-20T14:30:00Z"
+        });
 
         let hash = semantic_hash(&python_test_vector).unwrap();
         println!("Rust hash: {}", hash);
         println!("Compare with Python/JavaScript implementation for validation");
 
-        assert!(hash.len() == 64);
+        assert!(hash.len() == 64);
+    }
+
+    #[test]
+    fn test_default_options_match_legacy() {
+        let data = json!({"z": [3, 1, 2], "a": {"c": true, "b": null}});
+
+        assert_eq!(
+            canonicalize_with(&data, &CanonicalizeOptions::new()).unwrap(),
+            canonicalize(&data, true).unwrap()
+        );
+        assert_eq!(
+            semantic_hash_with(&data, &CanonicalizeOptions::default()).unwrap(),
+            semantic_hash(&data).unwrap()
+        );
+        assert!(canonicalize_with(&json!([1, 2]), &CanonicalizeOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_options_array_sort_and_numbers() {
+        let data = json!({"steps": [3, 1, 2], "amount": 1.0});
+
+        let ordered = CanonicalizeOptions::new().array_sort(ArraySortPolicy::Never);
+        assert_eq!(
+            canonicalize_with(&data, &ordered).unwrap(),
+            r#"{"amount":1.0,"steps":[3,1,2]}"#
+        );
+
+        let normalized = CanonicalizeOptions::new().number_policy(NumberPolicy::Normalize);
+        assert_eq!(
+            canonicalize_with(&data, &normalized).unwrap(),
+            r#"{"amount":1,"steps":[1,2,3]}"#
+        );
+    }
+
+    #[test]
+    fn test_options_key_collation() {
+        // U+1F600 sorts after U+FF5E by code point but before it by UTF-16 code unit
+        let data = json!({"\u{ff5e}": 1, "\u{1f600}": 2});
+
+        let bytes = canonicalize_with(&data, &CanonicalizeOptions::new()).unwrap();
+        let utf16 = canonicalize_with(
+            &data,
+            &CanonicalizeOptions::new().key_collation(KeyCollation::Utf16CodeUnits),
+        )
+        .unwrap();
+
+        assert_eq!(bytes, "{\"\u{ff5e}\":1,\"\u{1f600}\":2}");
+        assert_eq!(utf16, "{\"\u{1f600}\":2,\"\u{ff5e}\":1}");
+    }
+
+    #[test]
+    fn test_jcs_profile() {
+        let jcs = CanonicalizeOptions::new().profile(CanonicalizationProfile::Jcs);
+
+        // Example from RFC 8785 3.2.3
+        let mut input: Value = serde_json::from_str(
+            r#"{
+                "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+                "literals": [null, true, false]
+            }"#,
+        )
+        .unwrap();
+        input["numbers"] = json!([333333333.3333333, 1E30, 4.50, 2e-3, 0.000000000000000000000000001]);
+        assert_eq!(
+            canonicalize_with(&input, &jcs).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        // Key ordering example from RFC 8785 3.2.3
+        let keys = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{0080}": "Control",
+            "\u{00f6}": "Latin Small Letter O With Diaeresis"
+        });
+        let canonical = canonicalize_with(&keys, &jcs).unwrap();
+        let order = ["\\r", "\"1\"", "\u{0080}", "\u{00f6}", "\u{20ac}", "\u{1f600}", "\u{fb33}"];
+        let positions: Vec<usize> = order.iter().map(|k| canonical.find(k).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_unicode_normalization() {
+        let composed = json!({"claim": "caf\u{e9}", "r\u{e9}sum\u{e9}": ["\u{e9}"]});
+        let decomposed = json!({"claim": "cafe\u{301}", "re\u{301}sume\u{301}": ["e\u{301}"]});
+
+        // Off by default: the byte sequences differ, so the hashes do too
+        assert_ne!(semantic_hash(&composed).unwrap(), semantic_hash(&decomposed).unwrap());
+
+        for form in [NormalizationForm::Nfc, NormalizationForm::Nfd] {
+            let options = CanonicalizeOptions::new().unicode_normalization(form);
+            assert_eq!(
+                semantic_hash_with(&composed, &options).unwrap(),
+                semantic_hash_with(&decomposed, &options).unwrap()
+            );
+        }
+
+        let nfc = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+        assert_eq!(
+            canonicalize_with(&decomposed, &nfc).unwrap(),
+            canonicalize(&composed, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unicode_normalization_key_collision() {
+        let data = json!({"caf\u{e9}": 1, "cafe\u{301}": 2});
+        let options = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+
+        assert!(canonicalize(&data, true).is_ok());
+        assert!(matches!(
+            canonicalize_with(&data, &options),
+            Err(ConstitutionalError::CanonicalizationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_negative_zero_policy() {
+        let data = json!({"votes": {"delta": [-0.0]}});
+
+        assert_eq!(
+            canonicalize(&data, true).unwrap(),
+            r#"{"votes":{"delta":[-0.0]}}"#
+        );
+
+        for policy in [NumberPolicy::Nullify, NumberPolicy::Normalize] {
+            let options = CanonicalizeOptions::new().number_policy(policy);
+            assert_eq!(
+                canonicalize_with(&data, &options).unwrap(),
+                r#"{"votes":{"delta":[0]}}"#
+            );
+        }
+
+        let reject = CanonicalizeOptions::new().number_policy(NumberPolicy::Reject);
+        let err = canonicalize_with(&data, &reject).unwrap_err();
+        assert_eq!(err.pointer().map(JsonPointer::to_string).as_deref(), Some("/votes/delta/0"));
+        assert!(err.to_string().ends_with(r#"is not canonicalizable at "/votes/delta/0""#), "{}", err);
+        assert!(canonicalize_with(&json!({"delta": [0.5, 0]}), &reject).is_ok());
+    }
+
+    #[test]
+    fn test_large_integer_array_sorting() {
+        // Both values round to the same f64, so an f64 comparison would keep input order
+        let a = json!({"amounts": [9007199254740993u64, 9007199254740992u64]});
+        let b = json!({"amounts": [9007199254740992u64, 9007199254740993u64]});
+
+        assert_eq!(
+            canonicalize(&a, true).unwrap(),
+            r#"{"amounts":[9007199254740992,9007199254740993]}"#
+        );
+        assert_eq!(semantic_hash(&a).unwrap(), semantic_hash(&b).unwrap());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_end_to_end() {
+        let data: Value = serde_json::from_str(
+            r#"{"amounts": [123456789012345678901234567891, 123456789012345678901234567890],
+                "rate": 0.1000000000000000000000000001}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            canonicalize(&data, true).unwrap(),
+            r#"{"amounts":[123456789012345678901234567890,123456789012345678901234567891],"rate":0.1000000000000000000000000001}"#
+        );
+
+        let jcs = CanonicalizeOptions::new().profile(CanonicalizationProfile::Jcs);
+        assert!(canonicalize_with(&serde_json::from_str(r#"{"x": 1e400}"#).unwrap(), &jcs).is_err());
+    }
+
+    #[test]
+    fn test_preserve_number_types() {
+        let integer = json!({"amount": 1});
+        let float = json!({"amount": 1.0});
+
+        let normalize = CanonicalizeOptions::new().number_policy(NumberPolicy::Normalize);
+        assert_eq!(
+            semantic_hash_with(&integer, &normalize).unwrap(),
+            semantic_hash_with(&float, &normalize).unwrap()
+        );
+
+        let typed = normalize.preserve_number_types(true);
+        assert_ne!(
+            semantic_hash_with(&integer, &typed).unwrap(),
+            semantic_hash_with(&float, &typed).unwrap()
+        );
+        assert_eq!(
+            canonicalize_with(&json!({"delta": -0.0}), &typed).unwrap(),
+            r#"{"delta":0.0}"#
+        );
+
+        // Equal values of different types sort deterministically, integers first
+        let typed = CanonicalizeOptions::new().preserve_number_types(true);
+        let a = json!({"amounts": [2, 1.0, 1]});
+        let b = json!({"amounts": [1, 2, 1.0]});
+        assert_eq!(canonicalize_with(&a, &typed).unwrap(), r#"{"amounts":[1,1.0,2]}"#);
+        assert_eq!(
+            canonicalize_with(&a, &typed).unwrap(),
+            canonicalize_with(&b, &typed).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_str() {
+        let options = CanonicalizeOptions::new();
+        let text = r#"{ "b": [3, 1, 2], "a": {"y": true, "x": null} }"#;
+
+        assert_eq!(
+            canonicalize_str(text, &options).unwrap(),
+            r#"{"a":{"x":null,"y":true},"b":[1,2,3]}"#
+        );
+        assert_eq!(
+            semantic_hash_str(text, &options).unwrap(),
+            semantic_hash(&serde_json::from_str(text).unwrap()).unwrap()
+        );
+
+        // serde_json alone would silently hash {"claim": "b"}
+        let ambiguous = r#"{"claim": "a", "claim": "b"}"#;
+        assert!(serde_json::from_str::<Value>(ambiguous).is_ok());
+        assert!(canonicalize_str(ambiguous, &options).is_err());
+        assert!(semantic_hash_str(ambiguous, &options).is_err());
+    }
+
+    #[test]
+    fn test_bytes_entry_points() {
+        let options = CanonicalizeOptions::new();
+        let wire = br#"{"claim": "caf\u00e9", "agent": "Claude"}"#;
+
+        let digest = semantic_hash_bytes(wire, &options).unwrap();
+        assert_eq!(digest.canonical, "{\"agent\":\"Claude\",\"claim\":\"caf\u{e9}\"}");
+        assert_eq!(digest.canonical, canonicalize_bytes(wire, &options).unwrap());
+        assert_eq!(
+            digest.hash,
+            semantic_hash(&json!({"agent": "Claude", "claim": "caf\u{e9}"})).unwrap()
+        );
+
+        // Truncated multi-byte sequence
+        assert!(canonicalize_bytes(b"{\"claim\": \"caf\xc3\"}", &options).is_err());
+        assert!(semantic_hash_bytes(br#"{"a": 1, "a": 2}"#, &options).is_err());
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow() {
+        // Built by hand: json! would clone `bomb` through recursive serialization
+        let mut bomb = json!(1);
+        for _ in 0..50_000 {
+            let mut wrapper = Map::new();
+            wrapper.insert("a".to_string(), Value::Array(vec![bomb]));
+            bomb = Value::Object(wrapper);
+        }
+
+        let canonical = canonicalize(&bomb, true).unwrap();
+        assert_eq!(canonical.len(), 50_000 * "{\"a\":[]}".len() + 1);
+
+        match canonicalize_with(&bomb, &CanonicalizeOptions::new().max_depth(64)) {
+            Err(err @ ConstitutionalError::CanonicalizationError { .. }) => {
+                let pointer = "/a/0".repeat(32) + "/a";
+                assert_eq!(err.pointer(), Some(&JsonPointer::parse(&pointer).unwrap()));
+            }
+            other => panic!("expected depth error, got {:?}", other),
+        }
+
+        drop_iteratively(bomb);
+    }
+
+    #[test]
+    fn test_array_sort_policies() {
+        let ballot = json!({"ranking": ["carol", "alice", "bob"], "mixed": [2, "a", 1]});
+
+        let canonical = |policy| {
+            canonicalize_with(&ballot, &CanonicalizeOptions::new().array_sort(policy)).unwrap()
+        };
+        assert_eq!(
+            canonical(ArraySortPolicy::Never),
+            r#"{"mixed":[2,"a",1],"ranking":["carol","alice","bob"]}"#
+        );
+        assert_eq!(
+            canonical(ArraySortPolicy::PrimitivesOnly),
+            r#"{"mixed":[2,"a",1],"ranking":["alice","bob","carol"]}"#
+        );
+        assert_eq!(
+            canonical(ArraySortPolicy::MixedPrimitives),
+            r#"{"mixed":[1,2,"a"],"ranking":["alice","bob","carol"]}"#
+        );
+        assert_eq!(
+            canonical(ArraySortPolicy::Always),
+            r#"{"mixed":[1,2,"a"],"ranking":["alice","bob","carol"]}"#
+        );
+
+        let jcs = CanonicalizeOptions::new().profile(CanonicalizationProfile::Jcs);
+        assert_eq!(CanonicalizationProfile::Jcs.default_array_sort(), ArraySortPolicy::Never);
+        assert!(canonicalize_with(&ballot, &jcs).unwrap().contains(r#"["carol","alice","bob"]"#));
+        let jcs_sorted = jcs.array_sort(ArraySortPolicy::PrimitivesOnly);
+        assert!(canonicalize_with(&ballot, &jcs_sorted).unwrap().contains(r#"["alice","bob","carol"]"#));
+    }
+
+    #[test]
+    fn test_array_sort_by_element_hash() {
+        let a = json!({"evidence": [
+            {"type": "archive_reference", "pointer": "archive://0000001"},
+            {"type": "hash", "pointer": "sha256:abc123"},
+            [3, 1, 2]
+        ]});
+        let b = json!({"evidence": [
+            [2, 3, 1],
+            {"pointer": "sha256:abc123", "type": "hash"},
+            {"pointer": "archive://0000001", "type": "archive_reference"}
+        ]});
+
+        let by_hash = CanonicalizeOptions::new().array_sort(ArraySortPolicy::ByElementHash);
+        assert_ne!(semantic_hash(&a).unwrap(), semantic_hash(&b).unwrap());
+        assert_eq!(
+            semantic_hash_with(&a, &by_hash).unwrap(),
+            semantic_hash_with(&b, &by_hash).unwrap()
+        );
+
+        let always = CanonicalizeOptions::new().array_sort(ArraySortPolicy::Always);
+        assert_eq!(
+            canonicalize_with(&a, &always).unwrap(),
+            canonicalize_with(&b, &always).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_object_array_set_semantics() {
+        let a = json!({
+            "evidence": [
+                {"type": "archive_reference", "pointer": "archive://0000001"},
+                {"type": "hash", "pointer": "sha256:abc123"}
+            ],
+            "reasoning": {"steps": [{"n": 2}, {"n": 1}]},
+            "tags": ["b", "a"]
+        });
+        let b = json!({
+            "evidence": [
+                {"pointer": "sha256:abc123", "type": "hash"},
+                {"pointer": "archive://0000001", "type": "archive_reference"}
+            ],
+            "reasoning": {"steps": [{"n": 2}, {"n": 1}]},
+            "tags": ["a", "b"]
+        });
+
+        let sets = CanonicalizeOptions::new().array_sort(ArraySortPolicy::PrimitivesAndObjects);
+        assert_ne!(semantic_hash(&a).unwrap(), semantic_hash(&b).unwrap());
+        assert_eq!(semantic_hash_with(&a, &sets).unwrap(), semantic_hash_with(&b, &sets).unwrap());
+
+        // Only `evidence` is a set; reordering `steps` must still change the hash
+        let evidence_only = CanonicalizeOptions::new()
+            .array_sort_for("evidence", ArraySortPolicy::PrimitivesAndObjects);
+        assert_eq!(
+            semantic_hash_with(&a, &evidence_only).unwrap(),
+            semantic_hash_with(&b, &evidence_only).unwrap()
+        );
+        let mut reordered = b.clone();
+        reordered["reasoning"]["steps"] = json!([{"n": 1}, {"n": 2}]);
+        assert_ne!(
+            semantic_hash_with(&a, &evidence_only).unwrap(),
+            semantic_hash_with(&reordered, &evidence_only).unwrap()
+        );
+
+        // Mixed primitive/container arrays keep their order
+        let mixed = json!({"evidence": [{"a": 1}, "note"]});
+        assert_eq!(
+            canonicalize_with(&mixed, &sets).unwrap(),
+            r#"{"evidence":[{"a":1},"note"]}"#
+        );
+    }
+
+    #[test]
+    fn test_mixed_type_total_order() {
+        // The default keeps mixed arrays in order, as the other implementations do
+        assert_ne!(
+            semantic_hash(&json!({"v": [1, "a"]})).unwrap(),
+            semantic_hash(&json!({"v": ["a", 1]})).unwrap()
+        );
+        let mixed = CanonicalizeOptions::new().array_sort(ArraySortPolicy::MixedPrimitives);
+        assert_eq!(
+            semantic_hash_with(&json!({"v": [1, "a"]}), &mixed).unwrap(),
+            semantic_hash_with(&json!({"v": ["a", 1]}), &mixed).unwrap()
+        );
+        assert_eq!(
+            canonicalize_with(&json!({"v": ["b", 2, true, null, 1.5, false, "a"]}), &mixed).unwrap(),
+            r#"{"v":[null,false,true,1.5,2,"a","b"]}"#
+        );
+
+        // Containers keep their order unless the policy sorts them too
+        let with_containers = json!({"v": [{"a": 1}, [2], "x", 1, null, [1], {"a": 0}]});
+        assert_eq!(
+            canonicalize_with(&with_containers, &mixed).unwrap(),
+            r#"{"v":[{"a":1},[2],"x",1,null,[1],{"a":0}]}"#
+        );
+        let always = CanonicalizeOptions::new().array_sort(ArraySortPolicy::Always);
+        assert_eq!(
+            canonicalize_with(&with_containers, &always).unwrap(),
+            r#"{"v":[null,1,"x",[1],[2],{"a":0},{"a":1}]}"#
+        );
+    }
+
+    #[test]
+    fn test_key_collation_parity() {
+        // U+FB33 (BMP, above the surrogate range) vs U+1F600 (astral, a surrogate pair in UTF-16)
+        let data = json!({"\u{fb33}": 1, "\u{1f600}": 2, "a": 3, "tags": ["\u{1f600}", "\u{fb33}", "a"]});
+
+        let canonical = |collation| {
+            canonicalize_with(&data, &CanonicalizeOptions::new().key_collation(collation)).unwrap()
+        };
+        let bytes = canonical(KeyCollation::Bytes);
+        assert_eq!(
+            bytes,
+            "{\"a\":3,\"tags\":[\"a\",\"\u{fb33}\",\"\u{1f600}\"],\"\u{fb33}\":1,\"\u{1f600}\":2}"
+        );
+        assert_eq!(canonical(KeyCollation::Codepoint), bytes);
+
+        // Matches `Object.keys(obj).sort()` and `arr.sort()` in canonicalizer.js
+        assert_eq!(
+            canonical(KeyCollation::Utf16CodeUnits),
+            "{\"a\":3,\"tags\":[\"a\",\"\u{1f600}\",\"\u{fb33}\"],\"\u{1f600}\":2,\"\u{fb33}\":1}"
+        );
+    }
+
+    #[test]
+    fn test_ensure_ascii_matches_python() {
+        let data = json!({"claim": "caf\u{e9} \u{1f600}\u{7f}\n\u{2028}", "\u{e9}": 1});
+        let options = CanonicalizeOptions::new().ensure_ascii(true);
+
+        // json.dumps(data, ensure_ascii=True, sort_keys=True, separators=(",", ":"))
+        assert_eq!(
+            canonicalize_with(&data, &options).unwrap(),
+            r#"{"claim":"caf\u00e9 \ud83d\ude00\u007f\n\u2028","\u00e9":1}"#
+        );
+        assert_eq!(
+            canonicalize(&data, true).unwrap(),
+            "{\"claim\":\"caf\u{e9} \u{1f600}\u{7f}\\n\u{2028}\",\"\u{e9}\":1}"
+        );
+        assert!(canonicalize_with(&data, &options).unwrap().is_ascii());
+    }
+
+    #[test]
+    fn test_string_escaping_options() {
+        let data = json!({"path/to": "line\tend", "url": "https://ocp.example/a"});
+        assert_eq!(
+            canonicalize(&data, true).unwrap(),
+            r#"{"path/to":"line\tend","url":"https://ocp.example/a"}"#
+        );
+
+        let options = CanonicalizeOptions::new()
+            .control_escaping(ControlEscaping::Unicode)
+            .escape_solidus(true);
+        assert_eq!(
+            canonicalize_with(&data, &options).unwrap(),
+            r#"{"path\/to":"line\u0009end","url":"https:\/\/ocp.example\/a"}"#
+        );
+    }
+
+    #[test]
+    fn test_exclude_paths() {
+        let signed = json!({
+            "action": "propose",
+            "hash": "sha256:def",
+            "evidence": [{"claim": "a", "signature": "s0"}, {"claim": "b", "signature": "s1"}],
+            "a/b": {"x": 1, "y": 2}
+        });
+        let options = CanonicalizeOptions::new()
+            .exclude_path("/hash".parse().unwrap())
+            .exclude_path("/evidence/0/signature".parse().unwrap())
+            .exclude_path("/a~1b/y".parse().unwrap())
+            .exclude_path("/missing/field".parse().unwrap());
+        assert_eq!(
+            canonicalize_with(&signed, &options).unwrap(),
+            r#"{"a/b":{"x":1},"action":"propose","evidence":[{"claim":"a"},{"claim":"b","signature":"s1"}]}"#
+        );
+
+        let root = CanonicalizeOptions::new().exclude_path(JsonPointer::parse("").unwrap());
+        assert!(canonicalize_with(&signed, &root).is_err());
+        assert!(JsonPointer::parse("signature").is_err());
+    }
+
+    #[test]
+    fn test_timestamp_normalization() {
+        let local = json!({"action": "vote", "timestamp": "2025-11-20T14:30:00+02:00", "note": "2025-11-20T14:30:00+02:00"});
+        let utc = json!({"action": "vote", "timestamp": "2025-11-20T12:30:00Z", "note": "2025-11-20T14:30:00+02:00"});
+        let options = CanonicalizeOptions::new().normalize_timestamps("timestamp");
+
+        assert_ne!(semantic_hash(&local).unwrap(), semantic_hash(&utc).unwrap());
+        assert_eq!(
+            canonicalize_with(&local, &options).unwrap(),
+            r#"{"action":"vote","note":"2025-11-20T14:30:00+02:00","timestamp":"2025-11-20T12:30:00.000Z"}"#
+        );
+        assert_eq!(
+            semantic_hash_with(&local, &options).unwrap(),
+            semantic_hash_with(&utc, &options).unwrap()
+        );
+
+        let nested = json!({"votes": [{"timestamp": "2025-11-20T12:30:00.123456Z"}], "timestamp": "not a date"});
+        assert_eq!(
+            canonicalize_with(&nested, &options.timestamp_precision(6)).unwrap(),
+            r#"{"timestamp":"not a date","votes":[{"timestamp":"2025-11-20T12:30:00.123456Z"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_to_writer() {
+        // Large enough to cross several write chunks
+        let evidence: Vec<Value> = (0..2000).map(|i| json!({"id": i, "claim": "x".repeat(i % 17)})).collect();
+        let data = json!({"evidence": evidence, "action": "snapshot"});
+        let options = CanonicalizeOptions::new();
+
+        let mut streamed = Vec::new();
+        canonicalize_to_writer(&data, &options, &mut streamed).unwrap();
+        assert!(streamed.len() > 4 * WRITE_CHUNK);
+        assert_eq!(streamed, canonicalize_with(&data, &options).unwrap().into_bytes());
+        assert_eq!(
+            semantic_hash(&data).unwrap(),
+            sha256_hex(&canonicalize(&data, true).unwrap())
+        );
+
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        assert!(matches!(
+            canonicalize_to_writer(&data, &options, &mut Broken),
+            Err(ConstitutionalError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_top_level_policies() {
+        let evidence = json!(["c", "a", "b"]);
+
+        let reject = CanonicalizeOptions::new().top_level(TopLevelPolicy::Reject);
+        assert!(canonicalize_with(&evidence, &reject).is_err());
+
+        let wrap = CanonicalizeOptions::new().top_level(TopLevelPolicy::Wrap);
+        assert_eq!(canonicalize_with(&evidence, &wrap).unwrap(), r#"{"value":["a","b","c"]}"#);
+        assert_eq!(canonicalize_with(&evidence, &wrap).unwrap(), canonicalize(&evidence, false).unwrap());
+
+        let native = CanonicalizeOptions::new().top_level(TopLevelPolicy::Native);
+        assert_eq!(canonicalize_with(&evidence, &native).unwrap(), r#"["a","b","c"]"#);
+        assert_eq!(canonicalize_with(&json!("caf\u{e9}"), &native).unwrap(), "\"caf\u{e9}\"");
+        assert_eq!(canonicalize_with(&json!(null), &native).unwrap(), "null");
+        assert_eq!(
+            semantic_hash_with(&json!([{"b": 1, "a": 2}]), &native).unwrap(),
+            sha256_hex(r#"[{"a":2,"b":1}]"#)
+        );
+
+        // Objects are unaffected by the policy
+        let contract = json!({"b": 1, "a": [2, 1]});
+        for options in [reject, wrap, native] {
+            assert_eq!(canonicalize_with(&contract, &options).unwrap(), r#"{"a":[1,2],"b":1}"#);
+        }
+    }
+
+    #[test]
+    fn test_prune_policies() {
+        let data = json!({
+            "action": "propose",
+            "reasoning": null,
+            "amendments": [],
+            "evidence": [null, {}, {"source": null}],
+            "meta": {"tags": [], "note": null}
+        });
+
+        assert_eq!(
+            canonicalize_with(&data, &CanonicalizeOptions::new().prune(PrunePolicy::Nulls)).unwrap(),
+            r#"{"action":"propose","amendments":[],"evidence":[null,{},{}],"meta":{"tags":[]}}"#
+        );
+        // "meta" empties once its members are pruned, and is then pruned itself
+        assert_eq!(
+            canonicalize_with(&data, &CanonicalizeOptions::new().prune(PrunePolicy::NullsAndEmpty)).unwrap(),
+            r#"{"action":"propose","evidence":[null,{},{}]}"#
+        );
+
+        let options = CanonicalizeOptions::new().prune(PrunePolicy::Nulls);
+        assert_eq!(
+            semantic_hash_with(&json!({"action": "vote", "reasoning": null}), &options).unwrap(),
+            semantic_hash_with(&json!({"action": "vote"}), &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canon_version_tags() {
+        let data = json!({"action": "propose", "contract_id": "c-001"});
+        let canonical = canonicalize(&data, true).unwrap();
+
+        assert_eq!(semantic_hash(&data).unwrap(), sha256_hex(&canonical));
+        let v1 = CanonicalizeOptions::new().canon_version(CanonVersion::V1);
+        assert_eq!(
+            semantic_hash_with(&data, &v1).unwrap(),
+            sha256_hex(&format!("ocp-canon/v1\n{}", canonical))
+        );
+        assert_eq!(canonicalize_with(&data, &v1).unwrap(), canonical);
+        assert_eq!(
+            semantic_hash_bytes(canonical.as_bytes(), &v1).unwrap().hash,
+            semantic_hash_with(&data, &v1).unwrap()
+        );
+
+        let envelope = semantic_hash_envelope(&data, &v1).unwrap();
+        assert_eq!(envelope.canon_version.identifier(), "ocp-canon/v1");
+        assert_eq!(envelope.canon_version.identifier().parse::<CanonVersion>().unwrap(), CanonVersion::V1);
+        assert!(envelope.verify(&data, &CanonicalizeOptions::new()).unwrap());
+        assert!(!verify_semantic_hash(&data, &envelope.digest).unwrap());
+    }
+
+    #[test]
+    fn test_strict_validation_collects_violations() {
+        let text = r#"{"claim": "a", "claim": "b", "score": NaN, "nested": {"x": "\udc00"}}"#;
+
+        match canonicalize_str(text, &CanonicalizeOptions::new()) {
+            Err(err @ ConstitutionalError::ValidationFailed(_)) => {
+                let ConstitutionalError::ValidationFailed(violations) = &err else { unreachable!() };
+                assert_eq!(violations.len(), 3);
+                assert_eq!(err.pointer(), Some(&violations[0].pointer));
+                assert!(err.to_string().contains(r#"duplicate key "claim" at """#), "{}", err);
+            }
+            other => panic!("expected validation failure, got {:?}", other),
+        }
+
+        // Non-strict parsing still rejects the first problem it meets
+        let lenient = CanonicalizeOptions::new().strict(false);
+        assert!(matches!(
+            semantic_hash_str(text, &lenient),
+            Err(ConstitutionalError::CanonicalizationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_schema_hints() {
+        let schema = json!({
+            "properties": {
+                "signature": {"x-ocp-exclude": true},
+                "evidence": {"type": "array", "x-ocp-ordered": false},
+                "reasoning": {"properties": {"steps": {"type": "array", "x-ocp-ordered": true}}}
+            }
+        });
+        let contract = json!({
+            "signature": "ed25519:abc",
+            "evidence": [{"id": 2}, {"id": 1}],
+            "reasoning": {"steps": ["observe", "conclude", "act"], "tags": ["b", "a"]}
+        });
+        let options = CanonicalizeOptions::new()
+            .array_sort(ArraySortPolicy::Never)
+            .schema_hints(SchemaHints::from_schema(&schema).unwrap());
+
+        assert_eq!(
+            canonicalize_with(&contract, &options).unwrap(),
+            r#"{"evidence":[{"id":1},{"id":2}],"reasoning":{"steps":["observe","conclude","act"],"tags":["b","a"]}}"#
+        );
+    }
+
+    #[test]
+    fn test_input_limits() {
+        let text = r#"{"agent": "claude", "votes": [1, 2, 3], "claim": "xxxxxxxxxx"}"#;
+        let data: Value = serde_json::from_str(text).unwrap();
+
+        let limited = CanonicalizeOptions::new().max_bytes(text.len()).max_nodes(7).max_string_length(10);
+        assert!(canonicalize_str(text, &limited).is_ok());
+
+        let cases = [
+            (CanonicalizeOptions::new().max_bytes(text.len() - 1), Limit::Bytes, None),
+            (CanonicalizeOptions::new().max_nodes(6), Limit::Nodes, Some("/votes/2")),
+            (CanonicalizeOptions::new().max_string_length(9), Limit::StringLength, Some("/claim")),
+            (CanonicalizeOptions::new().max_string_length(4), Limit::StringLength, Some("/agent")),
+        ];
+        for (options, expected, pointer) in cases {
+            match semantic_hash_str(text, &options) {
+                Err(err @ ConstitutionalError::LimitExceeded { .. }) => {
+                    assert!(matches!(err, ConstitutionalError::LimitExceeded { limit, .. } if limit == expected));
+                    assert_eq!(err.pointer().map(ToString::to_string).as_deref(), pointer);
+                }
+                other => panic!("expected {:?} limit error, got {:?}", expected, other),
+            }
+        }
+
+        // Value inputs are bounded by node count and string length only
+        assert!(canonicalize_with(&data, &CanonicalizeOptions::new().max_bytes(1)).is_ok());
+        assert!(canonicalize_with(&data, &CanonicalizeOptions::new().max_nodes(6)).is_err());
+    }
+
+    /// Counts heap allocations made by the current thread, and the bytes it holds.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        static LIVE_BYTES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        static PEAK_BYTES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            let _ = LIVE_BYTES.try_with(|live| {
+                live.set(live.get() + layout.size());
+                let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+            });
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            // Memory freed on another thread than it was allocated on is not tracked
+            let _ = LIVE_BYTES.try_with(|live| live.set(live.get().saturating_sub(layout.size())));
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(|n| n.get());
+        let result = f();
+        (result, ALLOCATIONS.with(|n| n.get()) - before)
+    }
+
+    /// Most heap memory held at once by the current thread while `f` runs, beyond what
+    /// it held before.
+    fn peak_bytes_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = LIVE_BYTES.with(|live| live.get());
+        PEAK_BYTES.with(|peak| peak.set(before));
+        let result = f();
+        (result, PEAK_BYTES.with(|peak| peak.get()) - before)
+    }
+
+    #[test]
+    fn test_semantic_hash_memory_independent_of_size() {
+        let articles: Vec<Value> = (0..20_000)
+            .map(|i| json!({"article_id": format!("art-{}", i), "title": "Transparency", "clauses": ["b", "a"]}))
+            .collect();
+        let archive = json!({"version": "1.0", "articles": articles});
+        let canonical = canonicalize(&archive, true).unwrap();
+
+        let (hash, peak) = peak_bytes_during(|| semantic_hash(&archive).unwrap());
+        assert_eq!(hash, sha256_hex(&canonical));
+        // Neither the canonical string nor a sorted copy of the archive is ever held
+        assert!(peak * 20 < canonical.len(), "peak {} bytes for {} canonical bytes", peak, canonical.len());
+    }
+
+    #[test]
+    fn test_hash_algorithms() {
+        let data = json!({"action": "propose", "value": 42});
+        let canonical = canonicalize(&data, true).unwrap();
+
+        let sha512 = semantic_hash_using(&data, HashAlgorithm::Sha512).unwrap();
+        let mut hasher = Hasher::new(HashAlgorithm::Sha512);
+        hasher.update(canonical.as_bytes());
+        assert_eq!(sha512, hex(&hasher.finalize()));
+        assert_eq!(semantic_hash_using(&data, HashAlgorithm::Sha256).unwrap(), sha256_hex(&canonical));
+
+        // An algorithm prefix overrides the options; a bare digest uses them
+        assert!(verify_semantic_hash(&data, &format!("sha512:{}", sha512)).unwrap());
+        assert!(!verify_semantic_hash(&data, &sha512).unwrap());
+        let options = CanonicalizeOptions::new().hash_algorithm(HashAlgorithm::Sha512);
+        assert!(verify_semantic_hash_with(&data, &sha512, &options).unwrap());
+        assert!(matches!(
+            verify_semantic_hash(&data, &format!("md5:{}", sha512)),
+            Err(ConstitutionalError::HashingError(_))
+        ));
+
+        let envelope = semantic_hash_envelope(&data, &options).unwrap();
+        assert_eq!(envelope.algorithm, HashAlgorithm::Sha512);
+        assert!(envelope.verify(&data, &CanonicalizeOptions::new()).unwrap());
+    }
+
+    #[test]
+    fn test_hash_encodings() {
+        let data = json!({"action": "propose", "value": 42});
+        let hex_hash = semantic_hash(&data).unwrap();
+        let digest = semantic_hash_encoded(&data, &CanonicalizeOptions::new().hash_encoding(Encoding::Binary)).unwrap();
+        assert_eq!(hex(&digest), hex_hash);
+        assert!(semantic_hash_with(&data, &CanonicalizeOptions::new().hash_encoding(Encoding::Binary)).is_err());
+
+        for encoding in [Encoding::Base64Url, Encoding::Base58Btc] {
+            let options = CanonicalizeOptions::new().hash_encoding(encoding);
+            let hash = semantic_hash_with(&data, &options).unwrap();
+            assert_eq!(hash, encoding.encode_text(&digest).unwrap());
+            assert_eq!(semantic_hash_encoded(&data, &options).unwrap(), hash.as_bytes());
+
+            // Verification reads the encoding off the expected value, not the options
+            assert!(verify_semantic_hash(&data, &hash).unwrap());
+            assert!(verify_semantic_hash(&data, &format!("sha256:{}", hash)).unwrap());
+            assert!(!verify_semantic_hash(&json!({"action": "propose", "value": 43}), &hash).unwrap());
+        }
+        assert!(verify_semantic_hash_with(&data, &hex_hash.to_uppercase(), &CanonicalizeOptions::new()).unwrap());
+        assert!(!verify_semantic_hash(&data, "not a hash").unwrap());
+    }
+
+    #[test]
+    fn test_typed_hashes_are_domain_separated() {
+        let data = json!({"id": "obj-1", "parties": ["a", "b"]});
+        let options = CanonicalizeOptions::new();
+        let contract = semantic_hash_typed(&data, "Contract").unwrap();
+        let evidence = semantic_hash_typed(&data, "Evidence").unwrap();
+        assert_ne!(contract, evidence);
+        assert_ne!(contract, semantic_hash(&data).unwrap());
+        assert_eq!(
+            contract,
+            sha256_hex(&format!("8:Contract{}", canonicalize(&data, true).unwrap()))
+        );
+
+        assert!(verify_semantic_hash_typed(&data, "Contract", &contract, &options).unwrap());
+        assert!(!verify_semantic_hash_typed(&data, "Evidence", &contract, &options).unwrap());
+        assert!(!verify_semantic_hash(&data, &contract).unwrap());
+        assert!(semantic_hash_typed(&data, "").is_err());
+    }
+
+    #[test]
+    fn test_hash_serializable() {
+        struct Contract {
+            parties: Vec<&'static str>,
+            amount: u64,
+            memo: Option<String>,
+        }
+
+        impl Serialize for Contract {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                use serde::ser::SerializeStruct;
+                let mut state = serializer.serialize_struct("Contract", 3)?;
+                state.serialize_field("parties", &self.parties)?;
+                state.serialize_field("amount", &self.amount)?;
+                state.serialize_field("memo", &self.memo)?;
+                state.end()
+            }
+        }
+
+        let contract = Contract { parties: vec!["bob", "alice"], amount: 100, memo: None };
+        let value = json!({"amount": 100, "memo": null, "parties": ["bob", "alice"]});
+        let options = CanonicalizeOptions::new();
+        assert_eq!(canonicalize_serializable(&contract, &options).unwrap(), canonicalize_with(&value, &options).unwrap());
+        assert_eq!(semantic_hash_serializable(&contract, &options).unwrap(), semantic_hash(&value).unwrap());
+
+        // Maps with non-string keys have no JSON form
+        let keyed: std::collections::BTreeMap<Vec<u8>, u8> = [(vec![1], 1)].into_iter().collect();
+        assert!(semantic_hash_serializable(&keyed, &options).is_err());
+    }
+
+    #[test]
+    fn test_derive_canonicalize() {
+        #[derive(ocp_canon_derive::Canonicalize)]
+        struct Decision {
+            #[ocp(rename = "decision_id")]
+            id: String,
+            #[ocp(ordered)]
+            steps: Vec<&'static str>,
+            voters: Vec<&'static str>,
+            #[ocp(skip)]
+            cached_hash: Option<String>,
+        }
+
+        let decision = Decision {
+            id: "d-1".to_string(),
+            steps: vec!["propose", "debate", "adopt"],
+            voters: vec!["carol", "alice"],
+            cached_hash: Some("stale".to_string()),
+        };
+        struct Ballot {
+            decision: Decision,
+        }
+
+        impl Canonicalize for Ballot {
+            fn canonical_value(&self) -> Value {
+                json!({"decision": self.decision.canonical_value()})
+            }
+        }
+
+        let canonical = decision.canonical_json().unwrap();
+        assert_eq!(canonical, r#"{"decision_id":"d-1","steps":["propose","debate","adopt"],"voters":["alice","carol"]}"#);
+        assert_eq!(decision.semantic_hash().unwrap(), sha256_hex(&canonical));
+        assert!(decision.canonical_value().get("cached_hash").is_none());
+        assert_eq!(decision.cached_hash.as_deref(), Some("stale"));
+
+        // Derived types compose with hand-written implementations, under the outer type's options
+        let ballot = Ballot { decision };
+        assert_eq!(
+            ballot.canonical_json().unwrap(),
+            r#"{"decision":{"decision_id":"d-1","steps":["adopt","debate","propose"],"voters":["alice","carol"]}}"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_hash_batch() {
+        let mut ledger: Vec<Value> = (0..50)
+            .map(|i| json!({"action_id": format!("act-{}", i), "agent": "agent-7", "refs": [i, 0]}))
+            .collect();
+        ledger[7] = json!(["not", "an", "object"]);
+
+        for options in [CanonicalizeOptions::new(), CanonicalizeOptions::new().prune(PrunePolicy::NullsAndEmpty)] {
+            let hashes = semantic_hash_batch(&ledger, &options);
+            assert_eq!(hashes.len(), ledger.len());
+            for (data, hash) in ledger.iter().zip(&hashes) {
+                match hash {
+                    Ok(hash) => assert_eq!(hash.to_hex(), semantic_hash_with(data, &options).unwrap()),
+                    Err(_) => assert!(semantic_hash_with(data, &options).is_err()),
+                }
+            }
+            assert!(hashes[7].is_err());
+
+            let mut expected: Vec<SemanticHash> =
+                hashes.iter().map(|hash| hash.as_ref().copied().unwrap_or([0; 32].into())).collect();
+            expected[3] = expected[4];
+            let verified = verify_batch(&ledger, &expected, &options).unwrap();
+            assert!(verified[0].as_ref().unwrap());
+            assert!(!verified[3].as_ref().unwrap());
+            assert!(verified[7].is_err());
+            assert!(verify_batch(&ledger, &expected[1..], &options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_raw_digests() {
+        let data = json!({"action": "propose", "value": 42});
+        let options = CanonicalizeOptions::new().canon_version(CanonVersion::V1);
+        assert_eq!(hex(&semantic_hash_raw(&data).unwrap()), semantic_hash(&data).unwrap());
+        assert_eq!(hex(&semantic_hash_raw_with(&data, &options).unwrap()), semantic_hash_with(&data, &options).unwrap());
+        assert_eq!(
+            hex(&semantic_hash_typed_raw(&data, "Contract", &options).unwrap()),
+            semantic_hash_typed_with(&data, "Contract", &options).unwrap()
+        );
+        let key = HmacKey::new(vec![0x42; 32]).unwrap();
+        assert_eq!(hex(&semantic_hmac_raw(&data, &key, &options).unwrap()), semantic_hmac_with(&data, &key, &options).unwrap());
+        assert!(semantic_hash_raw_with(&data, &options.hash_algorithm(HashAlgorithm::Sha512)).is_err());
+    }
+
+    #[test]
+    fn test_semantic_hmac() {
+        let data = json!({"tenant": "acme", "amount": 100});
+        let key = HmacKey::new(vec![0x42; 32]).unwrap();
+        let other = HmacKey::new(vec![0x43; 32]).unwrap();
+
+        let mac = semantic_hmac(&data, &key).unwrap();
+        let mut expected = HmacSha256::new(&key);
+        expected.update(canonicalize(&data, true).unwrap().as_bytes());
+        assert_eq!(mac, hex(&expected.finalize()));
+        assert_ne!(mac, semantic_hmac(&data, &other).unwrap());
+        assert_ne!(mac, semantic_hash(&data).unwrap());
+
+        assert!(verify_semantic_hmac(&data, &key, &mac).unwrap());
+        assert!(verify_semantic_hmac(&data, &key, &mac.to_uppercase()).unwrap());
+        assert!(!verify_semantic_hmac(&data, &other, &mac).unwrap());
+        assert!(!verify_semantic_hmac(&json!({"tenant": "acme", "amount": 101}), &key, &mac).unwrap());
+        assert!(!verify_semantic_hmac(&data, &key, "not hex").unwrap());
+    }
+
+    #[test]
+    fn test_canonicalize_batch_reuses_keys() {
+        let ledger: Vec<Value> = (0..300)
+            .map(|i| {
+                json!({
+                    "action_id": format!("act-{}", i),
+                    "agent": "agent-7",
+                    "timestamp": "2025-11-20T12:30:00Z",
+                    "payload": {"kind": "vote", "weights": [3, 1], "agent_signature": null}
+                })
+            })
+            .collect();
+        let options = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+
+        let (batch, batch_allocs) = allocations_during(|| canonicalize_batch(&ledger, &options));
+        let (single, single_allocs) = allocations_during(|| {
+            ledger.iter().map(|entry| canonicalize_with(entry, &options)).collect::<Vec<_>>()
+        });
+
+        assert_eq!(
+            batch.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            single.into_iter().map(Result::unwrap).collect::<Vec<_>>()
+        );
+        assert!(batch_allocs * 2 < single_allocs, "batch {} vs single {}", batch_allocs, single_allocs);
+        assert!(canonicalize_batch(&[json!({"a": 1}), json!([1])], &options)[1].is_err());
+    }
+
+    #[test]
+    fn test_canonicalize_owned_allocates_less() {
+        let articles: Vec<Value> = (0..500)
+            .map(|i| json!({"article_id": format!("art-{}", i), "title": "Transparency", "clauses": ["a", "b"]}))
+            .collect();
+        let constitution = json!({"version": "1.0", "articles": articles});
+        // Pruning empty containers needs the sorted copy that streaming avoids
+        let options = CanonicalizeOptions::new().prune(PrunePolicy::NullsAndEmpty);
+
+        let (borrowed, borrowed_allocs) = allocations_during(|| canonicalize_with(&constitution, &options).unwrap());
+        let input = constitution.clone();
+        let (owned, owned_allocs) = allocations_during(|| canonicalize_owned(input, &options).unwrap());
+
+        assert_eq!(owned, borrowed);
+        // Path tracking and output buffers still allocate; the copies of keys, strings and
+        // containers are what disappears
+        assert!(owned_allocs * 3 < borrowed_allocs * 2, "owned {} vs borrowed {}", owned_allocs, borrowed_allocs);
+        assert_eq!(canonicalize_owned(json!([3, 1]), &options.clone().strict(false)).unwrap(), r#"{"value":[1,3]}"#);
+    }
+
+    #[test]
+    fn test_options_depth_and_exclusions() {
+        let signed = json!({
+            "action": "propose",
+            "signature": "ed25519:abc",
+            "nested": {"signature": "kept"}
+        });
+        let options = CanonicalizeOptions::new().exclude_field("signature");
+        assert_eq!(
+            canonicalize_with(&signed, &options).unwrap(),
+            r#"{"action":"propose","nested":{"signature":"kept"}}"#
+        );
+
+        let deep = json!({"a": {"b": {"c": 1}}});
+        assert!(canonicalize_with(&deep, &CanonicalizeOptions::new().max_depth(3)).is_ok());
+        assert!(matches!(
+            canonicalize_with(&deep, &CanonicalizeOptions::new().max_depth(2)),
+            Err(ConstitutionalError::CanonicalizationError { .. })
+        ));
     }
 }
 
@@ -0,0 +1,316 @@
+/// chunked.rs - Chunked hashing of large binary attachments
+///
+/// Evidence may reference multi-gigabyte blobs by `sha256:` pointers. Hashing such a blob
+/// in one piece means a receiver can only check it once every byte has arrived. Here the
+/// blob is read as a stream and cut into chunks, each hashed on its own. A root digest
+/// over the chunk list commits to all of them. A receiver holding the list can check
+/// chunks as they arrive, in any order, and resume an interrupted upload from the first
+/// chunk it is missing. The plain digest of the whole blob is computed in the same pass,
+/// so the result still matches the blob's existing pointer.
+///
+/// Chunks are either of a fixed size or content-defined. Content-defined boundaries
+/// depend only on the nearby bytes, found with a gear rolling hash, so an insertion near
+/// the start of a blob changes only the chunks around it. The gear table and the root
+/// layout are part of the format: changing either changes every root.
+
+use crate::algorithm::{hex, Hasher};
+use crate::hmac::constant_time_eq;
+use crate::{ConstitutionalError, HashAlgorithm, Result};
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+
+/// Separates chunk-list roots from digests of a blob's own bytes.
+const ROOT_TAG: &[u8] = b"ocp-chunks:";
+
+/// Size of the reads from the source, independent of the chunk sizes.
+const READ_BUF_LEN: usize = 64 * 1024;
+
+/// Pseudo-random gear table, fixed by the format, from splitmix64 seeded with 0.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// How a blob is cut into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// Chunks of exactly this many bytes, except a shorter last chunk.
+    Fixed(usize),
+    /// Content-defined chunks of at least `min` and at most `max` bytes, cut where the
+    /// rolling hash makes chunks about `avg` bytes long on average.
+    ContentDefined { min: usize, avg: usize, max: usize },
+}
+
+impl Default for Chunking {
+    /// Fixed chunks of 4 MiB.
+    fn default() -> Self {
+        Chunking::Fixed(4 << 20)
+    }
+}
+
+impl Chunking {
+    /// Content-defined chunking averaging `avg` bytes, with chunks from a quarter to four
+    /// times that.
+    pub fn content_defined(avg: usize) -> Self {
+        Chunking::ContentDefined { min: avg / 4, avg, max: avg.saturating_mul(4) }
+    }
+
+    fn check(self) -> Result<()> {
+        let valid = match self {
+            Chunking::Fixed(size) => size > 0,
+            Chunking::ContentDefined { min, avg, max } => min > 0 && avg >= 2 && min <= avg && avg <= max,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(ConstitutionalError::HashingError(format!("Invalid chunking {:?}", self)))
+        }
+    }
+
+    /// Length of the current chunk's remainder in `data`, if the chunk ends there.
+    /// `len` bytes of the chunk came before `data`; `gear` is its rolling hash so far.
+    fn boundary(self, len: usize, gear: &mut u64, data: &[u8]) -> Option<usize> {
+        match self {
+            Chunking::Fixed(size) => (data.len() >= size - len).then_some(size - len),
+            Chunking::ContentDefined { min, avg, max } => {
+                // A boundary needs the top log2(avg) bits of the hash to be zero
+                let shift = 64 - avg.ilog2();
+                for (i, &byte) in data.iter().enumerate() {
+                    *gear = (*gear << 1).wrapping_add(GEAR[byte as usize]);
+                    let chunk_len = len + i + 1;
+                    if chunk_len >= max || (chunk_len >= min && *gear >> shift == 0) {
+                        return Some(i + 1);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+/// One chunk of a blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Position of the chunk's first byte in the blob
+    pub offset: u64,
+    /// Length in bytes
+    pub len: u64,
+    /// Digest of the chunk's bytes alone
+    pub digest: Vec<u8>,
+}
+
+/// Chunk list, root and whole-blob digest of a blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedDigest {
+    /// Hash function of every digest here
+    pub algorithm: HashAlgorithm,
+    /// Length of the blob in bytes
+    pub size: u64,
+    /// Digest of the blob's bytes, as its `sha256:` pointer names it
+    pub content_digest: Vec<u8>,
+    /// Digest of the chunk list, as `chunk_root` computes it
+    pub root: Vec<u8>,
+    /// Chunks in blob order
+    pub chunks: Vec<Chunk>,
+}
+
+impl ChunkedDigest {
+    /// Pointer to the blob by its content digest, e.g. `sha256:9f86d081...`.
+    pub fn pointer(&self) -> String {
+        format!("{}:{}", self.algorithm.identifier(), hex(&self.content_digest))
+    }
+
+    /// Check a received chunk against the list.
+    ///
+    /// # Arguments
+    /// * `index` - Position of the chunk in `chunks`
+    /// * `bytes` - The chunk's bytes
+    ///
+    /// # Returns
+    /// true if the chunk exists and `bytes` has its length and digest
+    pub fn verify_chunk(&self, index: usize, bytes: &[u8]) -> bool {
+        let Some(chunk) = self.chunks.get(index) else {
+            return false;
+        };
+        let mut hasher = Hasher::new(self.algorithm);
+        hasher.update(bytes);
+        chunk.len == bytes.len() as u64 && constant_time_eq(&hasher.finalize(), &chunk.digest)
+    }
+
+    /// Whether the chunk list is consistent: chunks are contiguous, add up to `size` and
+    /// hash to `root`. Check this before trusting a list received alongside a root.
+    pub fn verify_root(&self) -> bool {
+        let mut offset = 0u64;
+        for chunk in &self.chunks {
+            if chunk.offset != offset {
+                return false;
+            }
+            offset = offset.saturating_add(chunk.len);
+        }
+        offset == self.size && constant_time_eq(&chunk_root(self.algorithm, &self.chunks), &self.root)
+    }
+
+    /// Index of the first chunk not yet fully received, for resuming an upload of which
+    /// `received` bytes arrived in order; None if the upload is complete.
+    pub fn resume_from(&self, received: u64) -> Option<usize> {
+        self.chunks.iter().position(|chunk| chunk.offset + chunk.len > received)
+    }
+}
+
+/// Root digest of a chunk list: the hash of a domain tag followed by each chunk's length
+/// as eight big-endian bytes and its digest.
+pub fn chunk_root(algorithm: HashAlgorithm, chunks: &[Chunk]) -> Vec<u8> {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(ROOT_TAG);
+    for chunk in chunks {
+        hasher.update(&chunk.len.to_be_bytes());
+        hasher.update(&chunk.digest);
+    }
+    hasher.finalize()
+}
+
+/// Hash a blob read from `reader`, chunk by chunk, without holding it in memory.
+///
+/// # Arguments
+/// * `reader` - Source of the blob's bytes, read to the end
+/// * `chunking` - How the blob is cut into chunks
+/// * `algorithm` - Hash function for the chunks, the root and the whole blob
+///
+/// # Returns
+/// The chunk list and digests, or an error if the chunking is invalid or reading fails
+pub fn hash_reader<R: Read>(mut reader: R, chunking: Chunking, algorithm: HashAlgorithm) -> Result<ChunkedDigest> {
+    chunking.check()?;
+    let mut buf = vec![0u8; READ_BUF_LEN];
+    let mut whole = Hasher::new(algorithm);
+    let mut chunks = Vec::new();
+    let mut current = Hasher::new(algorithm);
+    let (mut offset, mut len, mut gear) = (0u64, 0usize, 0u64);
+
+    loop {
+        let mut data = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => &buf[..n],
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        whole.update(data);
+        while let Some(end) = chunking.boundary(len, &mut gear, data) {
+            current.update(&data[..end]);
+            let finished = std::mem::replace(&mut current, Hasher::new(algorithm));
+            let chunk_len = (len + end) as u64;
+            chunks.push(Chunk { offset, len: chunk_len, digest: finished.finalize() });
+            offset += chunk_len;
+            len = 0;
+            gear = 0;
+            data = &data[end..];
+        }
+        current.update(data);
+        len += data.len();
+    }
+    if len > 0 {
+        chunks.push(Chunk { offset, len: len as u64, digest: current.finalize() });
+        offset += len as u64;
+    }
+
+    Ok(ChunkedDigest {
+        algorithm,
+        size: offset,
+        content_digest: whole.finalize(),
+        root: chunk_root(algorithm, &chunks),
+        chunks,
+    })
+}
+
+/// Hash the file at `path` as `hash_reader` does.
+pub fn hash_file<P: AsRef<Path>>(path: P, chunking: Chunking, algorithm: HashAlgorithm) -> Result<ChunkedDigest> {
+    hash_reader(File::open(path)?, chunking, algorithm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Deterministic pseudo-random bytes.
+    fn blob(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// A reader that returns a few bytes per call, to cross read and chunk boundaries.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_fixed_chunks() {
+        let data = blob(10_000, 1);
+        let digest = hash_reader(&data[..], Chunking::Fixed(4096), HashAlgorithm::Sha256).unwrap();
+        assert_eq!(digest.chunks.iter().map(|c| c.len).collect::<Vec<_>>(), [4096, 4096, 1808]);
+        assert_eq!(digest.size, 10_000);
+        let mut whole = Hasher::new(HashAlgorithm::Sha256);
+        whole.update(&data);
+        assert_eq!(digest.pointer(), format!("sha256:{}", hex(&whole.finalize())));
+
+        assert!(digest.verify_root());
+        assert!(digest.verify_chunk(2, &data[8192..]));
+        assert!(!digest.verify_chunk(1, &data[8192..]));
+        assert!(!digest.verify_chunk(3, &[]));
+        assert_eq!(digest.resume_from(5000), Some(1));
+        assert_eq!(digest.resume_from(10_000), None);
+
+        assert_eq!(hash_reader(Trickle(&data), Chunking::Fixed(4096), HashAlgorithm::Sha256).unwrap(), digest);
+        let mut tampered = digest.clone();
+        tampered.chunks.swap(0, 1);
+        assert!(!tampered.verify_root());
+
+        let empty = hash_reader(&[][..], Chunking::default(), HashAlgorithm::Sha256).unwrap();
+        assert!(empty.chunks.is_empty() && empty.verify_root());
+        assert!(hash_reader(&data[..], Chunking::Fixed(0), HashAlgorithm::Sha256).is_err());
+    }
+
+    #[test]
+    fn test_content_defined_chunks_survive_insertion() {
+        let chunking = Chunking::content_defined(1024);
+        let data = blob(64 * 1024, 7);
+        let digest = hash_reader(&data[..], chunking, HashAlgorithm::Sha256).unwrap();
+        assert!(digest.chunks.iter().all(|c| c.len <= 4096));
+        assert!(digest.chunks[..digest.chunks.len() - 1].iter().all(|c| c.len >= 256));
+        assert_eq!(hash_reader(Trickle(&data), chunking, HashAlgorithm::Sha256).unwrap(), digest);
+
+        let mut edited = b"an inserted preamble".to_vec();
+        edited.extend_from_slice(&data);
+        let shifted = hash_reader(&edited[..], chunking, HashAlgorithm::Sha256).unwrap();
+        let before: HashSet<_> = digest.chunks.iter().map(|c| &c.digest).collect();
+        let kept = shifted.chunks.iter().filter(|c| before.contains(&c.digest)).count();
+        assert!(kept + 2 >= digest.chunks.len(), "only {} of {} chunks kept", kept, digest.chunks.len());
+        assert_ne!(shifted.root, digest.root);
+    }
+}
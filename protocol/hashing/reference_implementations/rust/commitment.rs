@@ -0,0 +1,184 @@
+/// commitment.rs - Salted commitments to documents (commit now, reveal later)
+///
+/// A sealed-bid proposal is published as a commitment: a hash that binds the proposer to
+/// one contract without disclosing it. Hashing the contract alone would not hide it, as
+/// anyone could hash candidate contracts and compare, so a secret random nonce is hashed
+/// in too and revealed together with the contract. The hashed bytes are the version tag,
+/// the domain tag `ocp-commit:`, the 32 nonce bytes and the canonical form, so the
+/// canonicalization is exactly that of `semantic_hash_with` and no commitment can equal a
+/// semantic hash.
+
+use crate::encoding::digest_text;
+use crate::secret::wipe;
+use crate::{
+    canonicalize_to_writer, verify_digest, versioned_hasher, CanonicalizeOptions, ConstitutionalError, Result, Secret,
+};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of a commitment nonce in bytes.
+pub const NONCE_LEN: usize = 32;
+
+/// Separates commitments from semantic hashes, whose canonical forms never start with it.
+const COMMIT_TAG: &[u8] = b"ocp-commit:";
+
+/// The secret that hides a committed document until it is revealed. `Debug` does not
+/// print it; `to_hex` and `FromStr` carry it to the reveal. Wiped when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Nonce([u8; NONCE_LEN]);
+
+impl Nonce {
+    /// Draw a fresh nonce from the operating system's random number generator.
+    ///
+    /// # Returns
+    /// The nonce, or an IoError if the generator cannot be read
+    pub fn generate() -> Result<Nonce> {
+        let mut nonce = Nonce([0u8; NONCE_LEN]);
+        os_random(&mut nonce.0)?;
+        Ok(nonce)
+    }
+
+    /// The nonce bytes.
+    pub fn as_bytes(&self) -> &[u8; NONCE_LEN] {
+        &self.0
+    }
+
+    /// Lowercase hex, for publishing at reveal time.
+    pub fn to_hex(&self) -> Secret<String> {
+        Secret::new(crate::algorithm::hex(&self.0))
+    }
+}
+
+impl From<[u8; NONCE_LEN]> for Nonce {
+    fn from(bytes: [u8; NONCE_LEN]) -> Self {
+        Nonce(bytes)
+    }
+}
+
+impl FromStr for Nonce {
+    type Err = ConstitutionalError;
+
+    /// Parse a nonce revealed as hex in either case.
+    fn from_str(text: &str) -> Result<Nonce> {
+        crate::algorithm::unhex(text)
+            .map(Secret::new)
+            .and_then(|bytes| <[u8; NONCE_LEN]>::try_from(bytes.expose().as_slice()).ok())
+            .map(Nonce)
+            .ok_or_else(|| ConstitutionalError::HashingError(format!("A nonce must be {} hex-encoded bytes", NONCE_LEN)))
+    }
+}
+
+impl Drop for Nonce {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl fmt::Debug for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Nonce([REDACTED])")
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn os_random(bytes: &mut [u8]) -> Result<()> {
+    use std::io::Read;
+    std::fs::File::open("/dev/urandom")?.read_exact(bytes)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_random(_bytes: &mut [u8]) -> Result<()> {
+    Err(ConstitutionalError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "No OS random number generator on this platform; construct nonces from 32 random bytes",
+    )))
+}
+
+/// Commit to data under a nonce, with default options.
+///
+/// # Arguments
+/// * `data` - Document to commit to, e.g. a sealed bid
+/// * `nonce` - Fresh secret nonce, kept until the reveal
+///
+/// # Returns
+/// Hexadecimal string of the commitment
+pub fn commit(data: &Value, nonce: &Nonce) -> Result<String> {
+    commit_with(data, nonce, &CanonicalizeOptions::default())
+}
+
+/// Commit to data canonicalized with explicit options.
+///
+/// # Arguments
+/// * `data` - Document to commit to
+/// * `nonce` - Fresh secret nonce, kept until the reveal
+/// * `options` - Canonicalization options, including the algorithm and encoding
+///
+/// # Returns
+/// The commitment, written in the options' encoding
+pub fn commit_with(data: &Value, nonce: &Nonce, options: &CanonicalizeOptions) -> Result<String> {
+    digest_text(options.hash_encoding, &commitment_digest(data, nonce, options)?)
+}
+
+/// Check a revealed document and nonce against a published commitment.
+///
+/// # Arguments
+/// * `data` - The revealed document
+/// * `nonce` - The revealed nonce
+/// * `commitment` - The commitment published earlier, in any encoding
+///
+/// # Returns
+/// true if the reveal matches the commitment, false otherwise
+pub fn verify_commitment(data: &Value, nonce: &Nonce, commitment: &str) -> Result<bool> {
+    verify_commitment_with(data, nonce, commitment, &CanonicalizeOptions::default())
+}
+
+/// Check a reveal against a commitment made under explicit options. The commitment may
+/// name its algorithm as `verify_semantic_hash_with` accepts, and is compared in
+/// constant time.
+pub fn verify_commitment_with(
+    data: &Value,
+    nonce: &Nonce,
+    commitment: &str,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    verify_digest(commitment, options, |options| commitment_digest(data, nonce, options))
+}
+
+fn commitment_digest(data: &Value, nonce: &Nonce, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    let mut hasher = versioned_hasher(options);
+    hasher.update(COMMIT_TAG);
+    hasher.update(&nonce.0);
+    canonicalize_to_writer(data, options, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash, Encoding};
+    use serde_json::json;
+
+    #[test]
+    fn test_commit_and_reveal() {
+        let bid = json!({"bidder": "acme", "amount": 1000});
+        let nonce = Nonce::generate().unwrap();
+        assert_ne!(nonce, Nonce::generate().unwrap());
+        assert_eq!(format!("{:?}", nonce), "Nonce([REDACTED])");
+
+        let commitment = commit(&bid, &nonce).unwrap();
+        assert_ne!(commitment, semantic_hash(&bid).unwrap());
+        assert!(verify_commitment(&json!({"amount": 1000, "bidder": "acme"}), &nonce, &commitment).unwrap());
+        let revealed: Nonce = nonce.to_hex().expose().parse().unwrap();
+        assert!(verify_commitment(&bid, &revealed, &commitment).unwrap());
+
+        assert!(!verify_commitment(&json!({"bidder": "acme", "amount": 999}), &nonce, &commitment).unwrap());
+        assert!(!verify_commitment(&bid, &Nonce::from([0; NONCE_LEN]), &commitment).unwrap());
+        assert!("abcd".parse::<Nonce>().is_err());
+
+        let options = CanonicalizeOptions::new().hash_encoding(Encoding::Base58Btc);
+        let encoded = commit_with(&bid, &nonce, &options).unwrap();
+        assert!(verify_commitment(&bid, &nonce, &encoded).unwrap());
+    }
+}
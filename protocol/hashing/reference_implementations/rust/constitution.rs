@@ -0,0 +1,377 @@
+/// constitution.rs - State root of the constitution
+///
+/// Two nodes agree on the constitution when they hold the same articles with the same
+/// amendments applied. Exchanging the documents to find out is costly, and the semantic
+/// hash of the whole document only says whether they differ. The state root is one hash
+/// both nodes can compare, built so that a mismatch can be narrowed down to articles.
+///
+/// A constitution document is an object with an `articles` array, each article an object
+/// with a unique string `article_id`. Every other member, such as `version` or
+/// `preamble`, belongs to the header:
+///
+/// ```json
+/// {"version":"2.1","articles":[{"article_id":"III","title":"Obligations","clauses":["..."]}]}
+/// ```
+///
+/// The articles form a Merkle tree (see `merkle.rs`) whose leaves are their semantic
+/// hashes ordered by `article_id` in byte order, so the order of the array does not
+/// matter. The state root hashes the version tag, `ocp-constitution:`, the semantic hash
+/// of the header and the article root.
+///
+/// An article written as in `document.rs`, with numbered sections of clauses, can also be
+/// addressed below the article: `resolve` finds the value at an address such as
+/// `article-3.1.2` and `hash_at` its semantic hash.
+
+use crate::{
+    versioned_hasher, Address, AmendmentOperation, Article, CanonicalizeOptions, ConstitutionalError, MerkleTree,
+    Result, Section, SemanticHash,
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+const ROOT_TAG: &[u8] = b"ocp-constitution:";
+const ARTICLES: &str = "articles";
+const ARTICLE_ID: &str = "article_id";
+
+/// The constitution as its header and its articles by ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constitution {
+    header: Map<String, Value>,
+    articles: BTreeMap<String, Value>,
+}
+
+impl Constitution {
+    /// Read a constitution document.
+    ///
+    /// # Returns
+    /// The constitution, or a CanonicalizationError if the document is not an object, has
+    /// no `articles` array, or an article has no string `article_id` or repeats one
+    pub fn from_value(document: Value) -> Result<Constitution> {
+        let Value::Object(mut header) = document else {
+            return Err(ConstitutionalError::canonicalization_at("A constitution must be an object", &[]));
+        };
+        let Some(Value::Array(list)) = header.remove(ARTICLES) else {
+            return Err(ConstitutionalError::canonicalization_at(
+                "A constitution needs an articles array",
+                &[ARTICLES.to_string()],
+            ));
+        };
+        let mut articles = BTreeMap::new();
+        for (i, article) in list.into_iter().enumerate() {
+            let path = [ARTICLES.to_string(), i.to_string()];
+            let id = article_id(&article).map_err(|message| ConstitutionalError::canonicalization_at(message, &path))?;
+            if articles.insert(id.clone(), article).is_some() {
+                return Err(ConstitutionalError::canonicalization_at(format!("Duplicate article_id {:?}", id), &path));
+            }
+        }
+        Ok(Constitution { header, articles })
+    }
+
+    /// The document, with its articles in `article_id` order.
+    pub fn to_value(&self) -> Value {
+        let mut document = self.header.clone();
+        document.insert(ARTICLES.to_string(), Value::Array(self.articles.values().cloned().collect()));
+        Value::Object(document)
+    }
+
+    /// The article with the given ID.
+    pub fn article(&self, id: &str) -> Option<&Value> {
+        self.articles.get(id)
+    }
+
+    /// Number of articles.
+    pub fn len(&self) -> usize {
+        self.articles.len()
+    }
+
+    /// Whether the constitution has no articles.
+    pub fn is_empty(&self) -> bool {
+        self.articles.is_empty()
+    }
+
+    /// The article, section or clause at an address.
+    pub fn resolve(&self, address: &Address) -> Option<&Value> {
+        let article = self.articles.get(&address.article_id())?;
+        let Some(number) = address.section else {
+            return Some(article);
+        };
+        let sections = article.get("sections")?.as_array()?;
+        let section = sections.iter().find(|section| section.get("number") == Some(&Value::from(number)))?;
+        match address.clause {
+            Some(clause) => section.get("clauses")?.as_array()?.get((clause as usize).checked_sub(1)?),
+            None => Some(section),
+        }
+    }
+
+    /// The semantic hash of the article, section or clause at an address, a clause hashed
+    /// as the object `{"clause":"..."}`.
+    ///
+    /// # Returns
+    /// The hash, or a ProtocolError if nothing is at the address
+    pub fn hash_at(&self, address: &Address, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        let value = self
+            .resolve(address)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Nothing at {} in the constitution", address)))?;
+        match value {
+            Value::String(_) => SemanticHash::compute(&json!({ "clause": value }), options),
+            _ => SemanticHash::compute(value, options),
+        }
+    }
+
+    /// Every address in the constitution: each article, then each of its sections
+    /// followed by that section's clauses, articles and sections in number order.
+    ///
+    /// # Returns
+    /// The addresses, or a CanonicalizationError if an article is not in the form of
+    /// `document.rs`
+    pub fn addresses(&self) -> Result<Vec<Address>> {
+        let mut articles = self.articles.values().map(Article::from_value).collect::<Result<Vec<_>>>()?;
+        articles.sort_by_key(|article| article.number);
+        let mut addresses = Vec::new();
+        for article in &mut articles {
+            addresses.push(Address::article(article.number));
+            article.sections.sort_by_key(|section| section.number);
+            for section in &article.sections {
+                addresses.push(Address::section(article.number, section.number));
+                for clause in 1..=section.clauses.len() as u32 {
+                    addresses.push(Address::clause(article.number, section.number, clause));
+                }
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// Apply an amendment that adds an article or replaces the one with its `article_id`.
+    ///
+    /// # Returns
+    /// The article it replaced, if any, or a CanonicalizationError if the article has no
+    /// string `article_id`
+    pub fn amend(&mut self, article: Value) -> Result<Option<Value>> {
+        let id = article_id(&article).map_err(ConstitutionalError::canonicalization)?;
+        Ok(self.articles.insert(id, article))
+    }
+
+    /// Apply a targeted amendment: add, modify or repeal the article, section or clause at
+    /// an address. A new article or section gets its `article_id` or `number` from the
+    /// address, and a new section goes in number order. Adding or repealing a clause
+    /// moves the clauses after it, so they take the next or previous address.
+    ///
+    /// # Arguments
+    /// * `operation` - What to do at the address
+    /// * `target` - The address
+    /// * `text` - For an add or modify, a string for a clause and an article or section
+    ///   object otherwise
+    ///
+    /// # Returns
+    /// The text replaced or removed, None for an add; a ProtocolError if there is text
+    /// to add over or nothing to modify or repeal, or the text is missing or of the wrong
+    /// kind, and a CanonicalizationError if the article is not in the form of
+    /// `document.rs`. On an error the constitution is unchanged.
+    pub fn change(
+        &mut self,
+        operation: AmendmentOperation,
+        target: &Address,
+        text: Option<&Value>,
+    ) -> Result<Option<Value>> {
+        let error =
+            |message: &str| ConstitutionalError::ProtocolError(format!("Cannot {} {}: {}", operation, target, message));
+        let id = target.article_id();
+        let Some(number) = target.section else {
+            let present = self.articles.contains_key(&id);
+            return match (operation, present) {
+                (AmendmentOperation::Repeal, true) => Ok(self.articles.remove(&id)),
+                (AmendmentOperation::Add, false) | (AmendmentOperation::Modify, true) => {
+                    let article = numbered(text, ARTICLE_ID, Value::from(id.as_str()))
+                        .ok_or_else(|| error("the text must be an article object"))?;
+                    Article::from_value(&article)?;
+                    Ok(self.articles.insert(id, article))
+                }
+                (_, present) => Err(error(if present { "it exists already" } else { "there is no such article" })),
+            };
+        };
+        let mut article = self.articles.get(&id).cloned().ok_or_else(|| error("there is no such article"))?;
+        Article::from_value(&article)?;
+        let Some(sections) = article.get_mut("sections").and_then(Value::as_array_mut) else {
+            return Err(error("the article has no sections"));
+        };
+        let of = |section: &Value| section.get("number").and_then(Value::as_u64);
+        let position = sections.iter().position(|section| of(section) == Some(number as u64));
+        let old = match (target.clause, position) {
+            (None, _) => {
+                let section = || {
+                    let section = numbered(text, "number", Value::from(number))
+                        .ok_or_else(|| error("the text must be a section object"))?;
+                    Section::from_value(&section, &[])?;
+                    Ok::<_, ConstitutionalError>(section)
+                };
+                match (operation, position) {
+                    (AmendmentOperation::Repeal, Some(i)) => Some(sections.remove(i)),
+                    (AmendmentOperation::Modify, Some(i)) => Some(std::mem::replace(&mut sections[i], section()?)),
+                    (AmendmentOperation::Add, None) => {
+                        let at = sections.iter().position(|other| of(other) > Some(number as u64));
+                        sections.insert(at.unwrap_or(sections.len()), section()?);
+                        None
+                    }
+                    (_, position) => {
+                        return Err(error(if position.is_some() {
+                            "it exists already"
+                        } else {
+                            "there is no such section"
+                        }))
+                    }
+                }
+            }
+            (Some(_), None) => return Err(error("there is no such section")),
+            (Some(clause), Some(i)) => {
+                let Some(clauses) = sections[i].get_mut("clauses").and_then(Value::as_array_mut) else {
+                    return Err(error("the section has no clauses"));
+                };
+                let at = (clause as usize).checked_sub(1).ok_or_else(|| error("clauses are numbered from 1"))?;
+                let clause = || match text {
+                    Some(Value::String(clause)) => Ok(Value::from(clause.as_str())),
+                    _ => Err(error("the text must be a string")),
+                };
+                match operation {
+                    AmendmentOperation::Add if at <= clauses.len() => {
+                        clauses.insert(at, clause()?);
+                        None
+                    }
+                    AmendmentOperation::Modify if at < clauses.len() => {
+                        Some(std::mem::replace(&mut clauses[at], clause()?))
+                    }
+                    AmendmentOperation::Repeal if at < clauses.len() => Some(clauses.remove(at)),
+                    AmendmentOperation::Add => return Err(error("the clauses before it do not exist")),
+                    _ => return Err(error("there is no such clause")),
+                }
+            }
+        };
+        self.articles.insert(id, article);
+        Ok(old)
+    }
+
+    /// Apply an amendment that repeals an article.
+    ///
+    /// # Returns
+    /// The repealed article, if there was one
+    pub fn repeal(&mut self, id: &str) -> Option<Value> {
+        self.articles.remove(id)
+    }
+
+    /// The semantic hash of each article, in `article_id` order. Two nodes whose state
+    /// roots differ compare these to find the articles they disagree on.
+    pub fn article_hashes(&self, options: &CanonicalizeOptions) -> Result<Vec<(&str, SemanticHash)>> {
+        self.articles
+            .iter()
+            .map(|(id, article)| Ok((id.as_str(), SemanticHash::compute(article, options)?)))
+            .collect()
+    }
+
+    /// The Merkle tree over the article hashes, in `article_id` order.
+    pub fn article_tree(&self, options: &CanonicalizeOptions) -> Result<MerkleTree> {
+        let leaves: Vec<_> = self.article_hashes(options)?.into_iter().map(|(_, hash)| hash).collect();
+        MerkleTree::from_leaves(&leaves, options.hash_algorithm)
+    }
+
+    /// The state root, binding the header and every article.
+    ///
+    /// # Arguments
+    /// * `options` - Canonicalization options the header and articles are hashed under
+    ///
+    /// # Returns
+    /// The root, or an error if the header or an article breaks the options' rules or
+    /// the options' algorithm is not 256-bit
+    pub fn state_root(&self, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        let articles = self.article_tree(options)?.root();
+        let header = SemanticHash::compute(&Value::Object(self.header.clone()), options)?;
+        let mut hasher = versioned_hasher(options);
+        hasher.update(ROOT_TAG);
+        hasher.update(header.as_bytes());
+        hasher.update(articles.as_bytes());
+        SemanticHash::try_from(hasher.finalize().as_slice())
+    }
+}
+
+/// The text as an object with a member set to the number its address gives it.
+fn numbered(text: Option<&Value>, member: &str, number: Value) -> Option<Value> {
+    let mut object = text?.as_object()?.clone();
+    object.insert(member.to_string(), number);
+    Some(Value::Object(object))
+}
+
+fn article_id(article: &Value) -> std::result::Result<String, &'static str> {
+    match article.get(ARTICLE_ID) {
+        Some(Value::String(id)) => Ok(id.clone()),
+        _ => Err("An article needs a string article_id"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_inclusion;
+    use serde_json::json;
+
+    fn document() -> Value {
+        let articles: Vec<Value> = ["I", "II", "III", "IV", "V"]
+            .iter()
+            .map(|id| json!({"article_id": id, "title": format!("Article {}", id), "clauses": ["a", "b"]}))
+            .collect();
+        json!({"version": "2.1", "articles": articles})
+    }
+
+    #[test]
+    fn test_state_root_is_deterministic() {
+        let options = CanonicalizeOptions::new();
+        let constitution = Constitution::from_value(document()).unwrap();
+        let root = constitution.state_root(&options).unwrap();
+
+        let mut shuffled = document();
+        shuffled["articles"].as_array_mut().unwrap().reverse();
+        assert_eq!(Constitution::from_value(shuffled).unwrap().state_root(&options).unwrap(), root);
+        assert_eq!(Constitution::from_value(constitution.to_value()).unwrap(), constitution);
+
+        let mut other_version = document();
+        other_version["version"] = json!("2.2");
+        assert_ne!(Constitution::from_value(other_version).unwrap().state_root(&options).unwrap(), root);
+
+        // Each article hash is provable against the article root
+        let tree = constitution.article_tree(&options).unwrap();
+        let (id, hash) = constitution.article_hashes(&options).unwrap()[2];
+        assert_eq!(id, "III");
+        assert!(verify_inclusion(&hash, &tree.prove_inclusion(2).unwrap(), &tree.root()));
+    }
+
+    #[test]
+    fn test_amendments_change_the_root() {
+        let options = CanonicalizeOptions::new();
+        let original = Constitution::from_value(document()).unwrap();
+        let mut amended = original.clone();
+        let old = amended.amend(json!({"article_id": "III", "title": "Article III", "clauses": ["a", "c"]})).unwrap();
+        assert_eq!(old.as_ref(), original.article("III"));
+        assert_ne!(amended.state_root(&options).unwrap(), original.state_root(&options).unwrap());
+
+        // The hashes point at the one article that differs
+        let before = original.article_hashes(&options).unwrap();
+        let after = amended.article_hashes(&options).unwrap();
+        let differing: Vec<_> = before.iter().zip(&after).filter(|(a, b)| a != b).map(|(a, _)| a.0).collect();
+        assert_eq!(differing, ["III"]);
+
+        amended.amend(json!({"article_id": "VI", "title": "Fraud Proofs"})).unwrap();
+        assert!(amended.repeal("VI").is_some());
+        assert!(amended.repeal("VI").is_none());
+        assert_eq!(amended.len(), 5);
+        assert!(amended.amend(json!({"title": "No ID"})).is_err());
+    }
+
+    #[test]
+    fn test_malformed_documents() {
+        assert!(Constitution::from_value(json!([])).is_err());
+        assert!(Constitution::from_value(json!({"version": "2.1"})).is_err());
+        let err = Constitution::from_value(json!({"articles": [{"article_id": "I"}, {"article_id": 2}]})).unwrap_err();
+        assert_eq!(err.pointer().map(|p| p.to_string()).as_deref(), Some("/articles/1"));
+        assert!(Constitution::from_value(json!({"articles": [{"article_id": "I"}, {"article_id": "I"}]})).is_err());
+        let empty = Constitution::from_value(json!({"articles": []})).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.state_root(&CanonicalizeOptions::new()).is_ok());
+    }
+}
@@ -0,0 +1,142 @@
+/// content_id.rs - Object IDs derived from the objects' own content
+///
+/// An object can be identified by its content instead of a random UUID: its content ID
+/// is the semantic hash of the object with its `id` member left out, as
+/// `ocp:<algorithm>:<hex digest>`, e.g. `ocp:sha256:9f86...`. Anyone holding the object
+/// can check that its `id` is the one its content gives, and two agents that build the
+/// same object give it the same ID. The digest is always lowercase hex, whatever the
+/// options' encoding, so an ID has one spelling.
+
+use crate::algorithm::hex;
+use crate::{semantic_digest, Canonicalize, CanonicalizeOptions, ConstitutionalError, HashAlgorithm, Result};
+use serde_json::Value;
+
+/// Prefix of every content ID.
+pub const CONTENT_ID_PREFIX: &str = "ocp:";
+
+/// The content ID of an object: its semantic hash without its `id` member.
+///
+/// # Arguments
+/// * `data` - The object, with or without an `id`
+/// * `options` - Canonicalization options, whose algorithm the ID is computed under
+///
+/// # Returns
+/// The ID as `ocp:<algorithm>:<hex>`, or an error if the object cannot be hashed
+pub fn content_id(data: &Value, options: &CanonicalizeOptions) -> Result<String> {
+    let digest = semantic_digest(data, &options.clone().exclude_field("id"))?;
+    Ok(format!("{}{}:{}", CONTENT_ID_PREFIX, options.hash_algorithm.identifier(), hex(&digest)))
+}
+
+/// Check that an object's `id` is its content ID, under the algorithm the ID names.
+///
+/// # Returns
+/// true if it is, false otherwise; a ProtocolError if the object has no string `id` or
+/// the ID is not a content ID, a HashingError if it names an unknown algorithm
+pub fn verify_content_id(data: &Value, options: &CanonicalizeOptions) -> Result<bool> {
+    let id = data.get("id").and_then(Value::as_str).ok_or_else(|| {
+        ConstitutionalError::ProtocolError("A content-addressed object needs a string id".to_string())
+    })?;
+    let (algorithm, _) = parse_content_id(id)
+        .ok_or_else(|| ConstitutionalError::ProtocolError(format!("{:?} is not a content ID", id)))?;
+    let algorithm: HashAlgorithm = algorithm.parse()?;
+    Ok(content_id(data, &options.clone().hash_algorithm(algorithm))? == id)
+}
+
+/// The algorithm and digest of a well-formed content ID.
+pub(crate) fn parse_content_id(id: &str) -> Option<(&str, &str)> {
+    let (algorithm, digest) = id.strip_prefix(CONTENT_ID_PREFIX)?.split_once(':')?;
+    let well_formed = !algorithm.is_empty()
+        && !digest.is_empty()
+        && digest.len() % 2 == 0
+        && digest.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    well_formed.then_some((algorithm, digest))
+}
+
+/// A typed object with an `id` member that can be its content ID.
+pub trait ContentAddressed: Canonicalize {
+    /// The object's `id` field.
+    fn id_mut(&mut self) -> &mut String;
+
+    /// The object's content ID, under its canonicalization options.
+    fn content_id(&self) -> Result<String> {
+        content_id(&self.canonical_value(), &self.canonical_options())
+    }
+
+    /// Whether the object's `id` is its content ID.
+    fn verify_content_id(&self) -> Result<bool> {
+        verify_content_id(&self.canonical_value(), &self.canonical_options())
+    }
+
+    /// The object with its `id` set to its content ID.
+    fn with_content_id(mut self) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        *self.id_mut() = self.content_id()?;
+        Ok(self)
+    }
+}
+
+impl ContentAddressed for crate::Contract {
+    fn id_mut(&mut self) -> &mut String {
+        &mut self.id
+    }
+}
+
+impl ContentAddressed for crate::Amendment {
+    fn id_mut(&mut self) -> &mut String {
+        &mut self.id
+    }
+}
+
+impl ContentAddressed for crate::Challenge {
+    fn id_mut(&mut self) -> &mut String {
+        &mut self.id
+    }
+}
+
+impl ContentAddressed for crate::Ruling {
+    fn id_mut(&mut self) -> &mut String {
+        &mut self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::contract;
+    use crate::semantic_hash;
+    use serde_json::json;
+
+    #[test]
+    fn test_content_ids() {
+        let options = CanonicalizeOptions::new();
+        let id = content_id(&contract().to_value(), &options).unwrap();
+        let mut without_id = contract().to_value();
+        without_id.as_object_mut().unwrap().remove("id");
+        assert_eq!(id, format!("ocp:sha256:{}", semantic_hash(&without_id).unwrap()));
+        assert_eq!(content_id(&without_id, &options).unwrap(), id);
+        assert!(content_id(&contract().to_value(), &options.clone().hash_algorithm(HashAlgorithm::Sha512))
+            .unwrap()
+            .starts_with("ocp:sha512:"));
+
+        let mut addressed = contract().to_value();
+        addressed["id"] = json!(id);
+        assert!(verify_content_id(&addressed, &options).unwrap());
+        addressed["action_type"] = json!("repeal");
+        assert!(!verify_content_id(&addressed, &options).unwrap());
+        assert!(verify_content_id(&contract().to_value(), &options).is_err());
+        assert!(verify_content_id(&json!({"id": "ocp:md5:00"}), &options).is_err());
+        assert_eq!(parse_content_id("ocp:sha256:AB"), None);
+    }
+
+    #[test]
+    fn test_typed_objects_take_content_ids() {
+        let contract = contract();
+        assert!(!contract.verify_content_id().is_ok_and(|valid| valid));
+        let addressed = contract.clone().with_content_id().unwrap();
+        assert!(addressed.verify_content_id().unwrap());
+        assert_eq!(addressed.id, contract.content_id().unwrap());
+        assert_eq!(addressed.validate(), []);
+    }
+}
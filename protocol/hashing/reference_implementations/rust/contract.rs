@@ -0,0 +1,480 @@
+/// contract.rs - Contract proposals as a Rust type
+///
+/// A `Contract` is the proposal of `contract.schema.json` with its members as fields,
+/// so producers build one with a `ContractBuilder` instead of assembling an untyped
+/// `json!` object:
+///
+/// ```json
+/// {"id":"550e8400-e29b-41d4-a716-446655440000","proposer_agent":"Claude","action_type":"amend",
+///  "action":{"target":"amendment-article-3","operation":"modify"},
+///  "evidence":[{"type":"archive_reference","pointer":"sha256:abc123def456"}],
+///  "reasoning":{"rationale":"Clarifies Article III.1","confidence":0.87},"timestamp":"2025-11-20T14:30:00Z"}
+/// ```
+///
+/// Members the struct has no field for, such as `reversibility_class`, are kept in
+/// `extra` as they are. A contract's JSON is exactly the object it was read from, so its
+/// canonical JSON and semantic hash are those of the untyped value, byte for byte.
+///
+/// A contract may limit when it can be acted on with `valid_from` and `valid_until`,
+/// RFC 3339 timestamps: it is valid from the first, inclusive, until the second,
+/// exclusive. `validity_at` says where a time falls, and `SignaturePolicy::evaluate`
+/// denies signatures made outside the window.
+
+use crate::objects::{check_extra, take, take_optional};
+use crate::timestamp::utc_timestamp;
+use crate::validation::FieldReport;
+use crate::{
+    Canonicalize, ConstitutionalError, Evidence, EvidenceResolvers, JsonPointer, Reasoning, Result, Violation,
+    ViolationKind,
+};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// The action types a contract may propose.
+pub const ACTION_TYPES: &[&str] = &["approve", "reject", "amend", "delegate", "suspend", "override"];
+
+const FIELDS: &[&str] = &[
+    "id",
+    "proposer_agent",
+    "action_type",
+    "action",
+    "evidence",
+    "reasoning",
+    "timestamp",
+    "valid_from",
+    "valid_until",
+    "schema_version",
+];
+
+/// A contract proposal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    /// Unique contract identifier, a UUID or content ID
+    pub id: String,
+    /// The agent proposing the contract
+    pub proposer_agent: String,
+    /// Category of the action, e.g. `amend`
+    pub action_type: String,
+    /// What is proposed: its `target`, `operation` and any `parameters`
+    pub action: Value,
+    /// References supporting the proposal
+    pub evidence: Vec<Evidence>,
+    /// Why the contract should be approved
+    pub reasoning: Reasoning,
+    /// When the contract was submitted, RFC 3339
+    pub timestamp: String,
+    /// When the contract becomes valid, RFC 3339; absent means from the start
+    pub valid_from: Option<String>,
+    /// When the contract expires, RFC 3339; absent means never
+    pub valid_until: Option<String>,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Contract {
+    /// Start building a contract.
+    pub fn builder() -> ContractBuilder {
+        ContractBuilder::default()
+    }
+
+    /// Read a contract from its JSON object.
+    ///
+    /// # Returns
+    /// The contract, or a ProtocolError if a member is missing or of the wrong type
+    pub fn from_value(value: Value) -> Result<Contract> {
+        serde_json::from_value(value)
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid contract: {}", e)))
+    }
+
+    /// Check the contract's fields: an `id` that is a UUID or content ID, a non-empty
+    /// `proposer_agent`, a known `action_type`, a `reasoning` confidence in [0, 1], an
+    /// RFC 3339 `timestamp` and a validity window of RFC 3339 timestamps that is not
+    /// empty.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field; empty if the contract
+    /// is valid
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["id"], &self.id);
+        report.agent(&["proposer_agent"], &self.proposer_agent);
+        report.action_type(&["action_type"], &self.action_type);
+        for (index, evidence) in self.evidence.iter().enumerate() {
+            report.evidence_pointer(&["evidence", &index.to_string(), "pointer"], &evidence.pointer);
+        }
+        self.reasoning.check(&mut report, "reasoning");
+        report.timestamp(&["timestamp"], &self.timestamp);
+        report.window(self.valid_from.as_deref(), self.valid_until.as_deref());
+        report.violations
+    }
+
+    /// Where a time falls in the contract's validity window.
+    ///
+    /// # Returns
+    /// The validity, or a ProtocolError if the time or the window is malformed
+    pub fn validity_at(&self, at: &str) -> Result<Validity> {
+        validity(self.valid_from.as_deref(), self.valid_until.as_deref(), at)
+    }
+
+    /// Whether the contract may be acted on at a time.
+    ///
+    /// # Returns
+    /// The answer, or a ProtocolError if the time or the window is malformed
+    pub fn is_valid_at(&self, at: &str) -> Result<bool> {
+        Ok(self.validity_at(at)? == Validity::Valid)
+    }
+
+    /// Fetch every piece of cited evidence and check it against its pointer.
+    ///
+    /// # Returns
+    /// A violation at each `/evidence/<index>/pointer` that is malformed, cannot be
+    /// fetched or does not match the content fetched; empty if all the evidence checks out
+    pub fn verify_evidence(&self, resolvers: &EvidenceResolvers) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (index, evidence) in self.evidence.iter().enumerate() {
+            let kind = match evidence.evidence_pointer() {
+                Err(_) => ViolationKind::InvalidEvidencePointer(evidence.pointer.clone()),
+                Ok(pointer) => match resolvers.verify(&pointer) {
+                    Ok(true) => continue,
+                    Ok(false) => ViolationKind::EvidenceMismatch(pointer.to_string()),
+                    Err(e) => ViolationKind::EvidenceUnavailable(e.to_string()),
+                },
+            };
+            let path = JsonPointer::from_tokens(["evidence", &index.to_string(), "pointer"]);
+            violations.push(Violation { kind, pointer: path });
+        }
+        violations
+    }
+
+    /// The contract's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("id".to_string(), Value::from(self.id.as_str()));
+        map.insert("proposer_agent".to_string(), Value::from(self.proposer_agent.as_str()));
+        map.insert("action_type".to_string(), Value::from(self.action_type.as_str()));
+        map.insert("action".to_string(), self.action.clone());
+        map.insert("evidence".to_string(), Value::Array(self.evidence.iter().map(Evidence::to_value).collect()));
+        map.insert("reasoning".to_string(), self.reasoning.to_value());
+        map.insert("timestamp".to_string(), Value::from(self.timestamp.as_str()));
+        if let Some(valid_from) = &self.valid_from {
+            map.insert("valid_from".to_string(), Value::from(valid_from.as_str()));
+        }
+        if let Some(valid_until) = &self.valid_until {
+            map.insert("valid_until".to_string(), Value::from(valid_until.as_str()));
+        }
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Where a time falls in a contract's validity window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    /// Before `valid_from`
+    NotYetValid,
+    /// From `valid_from` and before `valid_until`
+    Valid,
+    /// At or after `valid_until`
+    Expired,
+}
+
+/// Where a time falls in a window, either end of which may be open.
+///
+/// # Returns
+/// The validity, or a ProtocolError if a timestamp is malformed
+pub(crate) fn validity(valid_from: Option<&str>, valid_until: Option<&str>, at: &str) -> Result<Validity> {
+    let at = utc_timestamp(at)?;
+    if let Some(valid_from) = valid_from {
+        if at < utc_timestamp(valid_from)? {
+            return Ok(Validity::NotYetValid);
+        }
+    }
+    if let Some(valid_until) = valid_until {
+        if at >= utc_timestamp(valid_until)? {
+            return Ok(Validity::Expired);
+        }
+    }
+    Ok(Validity::Valid)
+}
+
+impl Canonicalize for Contract {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+/// Builds a `Contract`, checking that every member is set.
+#[derive(Debug, Clone, Default)]
+pub struct ContractBuilder {
+    id: Option<String>,
+    proposer_agent: Option<String>,
+    action_type: Option<String>,
+    action: Option<Value>,
+    evidence: Vec<Evidence>,
+    reasoning: Option<Reasoning>,
+    timestamp: Option<String>,
+    valid_from: Option<String>,
+    valid_until: Option<String>,
+    schema_version: Option<u32>,
+    extra: Map<String, Value>,
+}
+
+impl ContractBuilder {
+    /// Set the contract's ID.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Set the proposing agent.
+    pub fn proposer_agent(mut self, agent: &str) -> Self {
+        self.proposer_agent = Some(agent.to_string());
+        self
+    }
+
+    /// Set the action type.
+    pub fn action_type(mut self, action_type: &str) -> Self {
+        self.action_type = Some(action_type.to_string());
+        self
+    }
+
+    /// Set the action.
+    pub fn action(mut self, action: Value) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// Add an evidence item after those added so far.
+    pub fn evidence(mut self, item: Evidence) -> Self {
+        self.evidence.push(item);
+        self
+    }
+
+    /// Set the reasoning.
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Set the submission timestamp.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Set when the contract becomes valid.
+    pub fn valid_from(mut self, valid_from: &str) -> Self {
+        self.valid_from = Some(valid_from.to_string());
+        self
+    }
+
+    /// Set when the contract expires.
+    pub fn valid_until(mut self, valid_until: &str) -> Self {
+        self.valid_until = Some(valid_until.to_string());
+        self
+    }
+
+    /// Set the version of the schema the object follows.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set a member the struct has no field for, e.g. `reversibility_class`.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Build the contract.
+    ///
+    /// # Returns
+    /// The contract, or a ProtocolError naming a member that was not set, or one set
+    /// with `field` that has a field of its own
+    pub fn build(self) -> Result<Contract> {
+        fn required<T>(value: Option<T>, name: &str) -> Result<T> {
+            value.ok_or_else(|| ConstitutionalError::ProtocolError(format!("A contract needs {}", name)))
+        }
+        check_extra(&self.extra, FIELDS, "a contract")?;
+        Ok(Contract {
+            id: required(self.id, "an id")?,
+            proposer_agent: required(self.proposer_agent, "a proposer_agent")?,
+            action_type: required(self.action_type, "an action_type")?,
+            action: required(self.action, "an action")?,
+            evidence: self.evidence,
+            reasoning: required(self.reasoning, "reasoning")?,
+            timestamp: required(self.timestamp, "a timestamp")?,
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            schema_version: self.schema_version,
+            extra: self.extra,
+        })
+    }
+}
+
+impl Serialize for Contract {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Contract {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Contract, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a contract"));
+        };
+        Ok(Contract {
+            id: take(&mut map, "id")?,
+            proposer_agent: take(&mut map, "proposer_agent")?,
+            action_type: take(&mut map, "action_type")?,
+            action: take(&mut map, "action")?,
+            evidence: take(&mut map, "evidence")?,
+            reasoning: take(&mut map, "reasoning")?,
+            timestamp: take(&mut map, "timestamp")?,
+            valid_from: take_optional(&mut map, "valid_from")?,
+            valid_until: take_optional(&mut map, "valid_until")?,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+/// The contract the tests across the crate build on: Claude proposing to amend Article III.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{Contract, ContractBuilder};
+    use crate::Reasoning;
+    use serde_json::json;
+
+    pub(crate) const CONTRACT_ID: &str = "550e8400-e29b-41d4-a716-446655440000";
+
+    /// A builder with the fixture's members set, for a test to change some of them.
+    pub(crate) fn builder() -> ContractBuilder {
+        Contract::builder()
+            .id(CONTRACT_ID)
+            .proposer_agent("Claude")
+            .action_type("amend")
+            .action(json!({"target": "amendment-article-3"}))
+            .reasoning(Reasoning::new("Clarifies Article III.1", 0.87).unwrap())
+            .timestamp("2025-11-20T14:30:00Z")
+    }
+
+    pub(crate) fn contract() -> Contract {
+        builder().build().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::builder;
+    use super::*;
+    use crate::{canonicalize, semantic_hash, ViolationKind};
+    use serde_json::json;
+
+    fn untyped() -> Value {
+        json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "proposer_agent": "Claude",
+            "action_type": "amend",
+            "action": {"target": "amendment-article-3", "operation": "modify"},
+            "evidence": [
+                {"type": "archive_reference", "pointer": "sha256:abc123def456"},
+                {"type": "constitutional_citation", "pointer": "Article-III.1"}
+            ],
+            "reasoning": {"rationale": "Clarifies Article III.1", "confidence": 0.87},
+            "reversibility_class": "easily_reversible",
+            "timestamp": "2025-11-20T14:30:00Z"
+        })
+    }
+
+    fn typed() -> Contract {
+        builder()
+            .action(json!({"target": "amendment-article-3", "operation": "modify"}))
+            .evidence(Evidence::new("archive_reference", "sha256:abc123def456"))
+            .evidence(Evidence::new("constitutional_citation", "Article-III.1"))
+            .field("reversibility_class", json!("easily_reversible"))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_matches_untyped_contract() {
+        let contract = typed();
+        assert_eq!(contract.canonical_json().unwrap(), canonicalize(&untyped(), true).unwrap());
+        assert_eq!(contract.semantic_hash().unwrap(), semantic_hash(&untyped()).unwrap());
+        assert_eq!(Contract::from_value(untyped()).unwrap(), contract);
+        assert_eq!(serde_json::to_value(&contract).unwrap(), untyped());
+        assert_eq!(contract.extra["reversibility_class"], "easily_reversible");
+    }
+
+    #[test]
+    fn test_validity_window() {
+        let open = typed();
+        assert_eq!(open.validity_at("1970-01-01T00:00:00Z").unwrap(), Validity::Valid);
+        assert!(open.to_value().get("valid_until").is_none());
+
+        let mut contract = typed();
+        contract.valid_from = Some("2025-11-20T14:30:00Z".to_string());
+        contract.valid_until = Some("2025-11-27T14:30:00Z".to_string());
+        assert_eq!(contract.validity_at("2025-11-20T14:29:59.9Z").unwrap(), Validity::NotYetValid);
+        assert!(contract.is_valid_at("2025-11-20T15:30:00+01:00").unwrap());
+        assert_eq!(contract.validity_at("2025-11-27T14:30:00Z").unwrap(), Validity::Expired);
+        assert!(contract.validity_at("next week").is_err());
+        assert_eq!(Contract::from_value(contract.to_value()).unwrap(), contract);
+        assert_eq!(contract.validate(), []);
+
+        contract.valid_until = Some("2025-11-20T15:30:00+01:00".to_string());
+        let violations = contract.validate();
+        assert_eq!(violations[0].kind, ViolationKind::EmptyValidityWindow);
+        assert_eq!(violations[0].pointer.to_string(), "/valid_until");
+        contract.valid_from = Some("soon".to_string());
+        assert_eq!(contract.validate()[0].kind, ViolationKind::InvalidTimestamp("soon".to_string()));
+        assert_eq!(contract.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_reports_every_field() {
+        assert_eq!(typed().validate(), []);
+        let mut contract = typed();
+        contract.id = "c-17".to_string();
+        contract.proposer_agent = String::new();
+        contract.action_type = "abolish".to_string();
+        contract.reasoning.confidence = serde_json::Number::from(2);
+        contract.timestamp = "2025-11-20 14:30".to_string();
+        contract.evidence[1].pointer = "the third article".to_string();
+        let violations = contract.validate();
+        let pointers: Vec<String> = violations.iter().map(|v| v.pointer.to_string()).collect();
+        let expected =
+            ["/id", "/proposer_agent", "/action_type", "/evidence/1/pointer", "/reasoning/confidence", "/timestamp"];
+        assert_eq!(pointers, expected);
+        assert_eq!(violations[3].kind, ViolationKind::InvalidEvidencePointer("the third article".to_string()));
+        assert_eq!(violations[2].kind, ViolationKind::UnknownActionType("abolish".to_string()));
+        assert_eq!(violations[1].to_string(), r#"empty agent name at "/proposer_agent""#);
+        // Uppercase hex is not the UUID form the schema takes
+        contract.id = "550E8400-E29B-41D4-A716-446655440000".to_string();
+        assert_eq!(contract.validate()[0].kind, ViolationKind::InvalidId(contract.id.clone()));
+    }
+
+    #[test]
+    fn test_incomplete_contracts_fail() {
+        let missing = Contract::builder().id("c-17").proposer_agent("Claude").action_type("amend").build();
+        assert!(missing.unwrap_err().to_string().contains("an action"));
+        let shadowed = Contract::builder().field("id", json!("c-18")).build();
+        assert!(shadowed.is_err());
+
+        let mut untimed = untyped();
+        untimed.as_object_mut().unwrap().remove("timestamp");
+        assert!(Contract::from_value(untimed).unwrap_err().to_string().contains("timestamp"));
+        let mut mistyped = untyped();
+        mistyped["evidence"] = json!({"type": "archive_reference"});
+        assert!(Contract::from_value(mistyped).is_err());
+        let mut unpointed = untyped();
+        unpointed["evidence"][1] = json!({"type": "constitutional_citation"});
+        assert!(Contract::from_value(unpointed).unwrap_err().to_string().contains("pointer"));
+    }
+}
@@ -0,0 +1,393 @@
+/// cose.rs - COSE_Sign1 messages over semantic hashes
+///
+/// Edge devices exchange contracts over CBOR and cannot carry a JSON or JOSE stack. They
+/// take attestations as COSE_Sign1 messages (RFC 9052), tagged 18:
+///
+/// ```text
+/// 18([h'a10127', {4: h'6564...'}, h'a466646967657374...', h'92a0...'])
+/// ```
+///
+/// The protected header names the algorithm: EdDSA (-8) for Ed25519 keys, ES256K (-47,
+/// RFC 8812) for secp256k1 keys. The unprotected header's `kid` is the signer's public
+/// key in its protocol text form. The payload is either the hash envelope, as a CBOR map
+/// of its four text fields so that a device can read the digest without parsing JSON,
+/// or the document's canonical JSON with content type `application/json`. The signature
+/// covers the `Signature1` structure with empty external data, as RFC 9052 section 4.4
+/// has it.
+///
+/// Only the CBOR COSE_Sign1 needs is read and written here: definite-length integers,
+/// byte and text strings, arrays, maps, tags and null.
+
+use crate::algorithm::hex;
+use crate::signing::split_tagged;
+use crate::{
+    canonicalize_with, semantic_hash_envelope, CanonicalizeOptions, ConstitutionalError, HashEnvelope, PublicKey,
+    Result, Signer,
+};
+use serde_json::{Map, Value};
+
+const SIGN1_TAG: u64 = 18;
+const ALG: i64 = 1;
+const CONTENT_TYPE: i64 = 3;
+const KID: i64 = 4;
+/// CoAP content format of `application/json`, which a device may send instead of text
+const JSON_FORMAT: i64 = 50;
+const MAX_DEPTH: usize = 8;
+
+/// What a COSE_Sign1 message carries as its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CosePayload {
+    /// The hash envelope, as a CBOR map of text fields. The default.
+    #[default]
+    Hash,
+    /// The document's canonical JSON.
+    Document,
+}
+
+/// Sign a document as a tagged COSE_Sign1 message.
+///
+/// # Arguments
+/// * `data` - Document to attest to
+/// * `signer` - The signer's keypair, or any other `Signer`
+/// * `payload` - Whether the message carries the hash envelope or the whole document
+/// * `options` - Canonicalization options the document is hashed under
+///
+/// # Returns
+/// The encoded message, or an error if the data cannot be hashed or the signer fails
+pub fn sign_cose(
+    data: &Value,
+    signer: &dyn Signer,
+    payload: CosePayload,
+    options: &CanonicalizeOptions,
+) -> Result<Vec<u8>> {
+    let key = signer.public_key()?;
+    let mut protected = vec![(Cbor::Int(ALG), Cbor::Int(algorithm(&key)))];
+    let content = match payload {
+        CosePayload::Hash => encode(&envelope_map(&semantic_hash_envelope(data, options)?)),
+        CosePayload::Document => {
+            protected.push((Cbor::Int(CONTENT_TYPE), Cbor::Text("application/json".to_string())));
+            canonicalize_with(data, options)?.into_bytes()
+        }
+    };
+    let protected = encode(&Cbor::Map(protected));
+    let signature = signer.sign_message(&sig_structure(&protected, &content))?;
+    let (scheme, mut signature) = split_tagged(&signature, "signature")?;
+    if scheme == "secp256k1-recoverable" {
+        // ES256K has no room for the recovery byte
+        signature.pop();
+    }
+    let unprotected = Cbor::Map(vec![(Cbor::Int(KID), Cbor::Bytes(key.to_string().into_bytes()))]);
+    let message = Cbor::Array(vec![Cbor::Bytes(protected), unprotected, Cbor::Bytes(content), Cbor::Bytes(signature)]);
+    Ok(encode(&Cbor::Tag(SIGN1_TAG, Box::new(message))))
+}
+
+/// Check a COSE_Sign1 message over a document, tagged or not.
+///
+/// # Arguments
+/// * `message` - The encoded message, with its payload attached
+/// * `data` - The document it is expected to attest to
+/// * `key` - The signer's public key
+/// * `options` - Canonicalization options the document was hashed under; a hash
+///   payload's own algorithm and version override them
+///
+/// # Returns
+/// true if the message is the key's signature over the document or its hash, false
+/// otherwise; a HashingError if the message is malformed, uses critical headers or its
+/// algorithm does not fit the key
+pub fn verify_cose(message: &[u8], data: &Value, key: &PublicKey, options: &CanonicalizeOptions) -> Result<bool> {
+    let message = match decode(message).ok_or_else(|| malformed("not CBOR"))? {
+        Cbor::Tag(SIGN1_TAG, message) => *message,
+        message => message,
+    };
+    let Cbor::Array(parts) = message else {
+        return Err(malformed("not a COSE_Sign1 array"));
+    };
+    let [Cbor::Bytes(protected_bytes), Cbor::Map(_), payload, Cbor::Bytes(signature)] = parts.as_slice() else {
+        return Err(malformed("not a COSE_Sign1 array"));
+    };
+    let Cbor::Bytes(payload) = payload else {
+        return Err(malformed("the payload is detached"));
+    };
+    let protected = match protected_bytes.as_slice() {
+        [] => Vec::new(),
+        bytes => match decode(bytes) {
+            Some(Cbor::Map(entries)) => entries,
+            _ => return Err(malformed("the protected header is not a map")),
+        },
+    };
+    let header = |label| protected.iter().find(|(key, _)| *key == Cbor::Int(label)).map(|(_, value)| value);
+    if header(2).is_some() {
+        return Err(ConstitutionalError::HashingError("Unsupported critical COSE headers".to_string()));
+    }
+    match header(ALG) {
+        Some(Cbor::Int(alg)) if *alg == algorithm(key) => {}
+        Some(Cbor::Int(alg)) => {
+            return Err(ConstitutionalError::HashingError(format!(
+                "A COSE algorithm {} signature cannot be checked against a {} key",
+                alg, key
+            )))
+        }
+        _ => return Err(malformed("no algorithm in the protected header")),
+    }
+    let signature = format!("{}:{}", key.prefix(), hex(signature));
+    if !key.verify_message(&sig_structure(protected_bytes, payload), &signature)? {
+        return Ok(false);
+    }
+
+    match header(CONTENT_TYPE) {
+        None => {
+            let envelope = decode(payload)
+                .and_then(|map| envelope_from_map(&map))
+                .ok_or_else(|| malformed("the payload is not a hash envelope"))?;
+            envelope.verify(data, options)
+        }
+        Some(Cbor::Text(json)) if json == "application/json" => document_matches(payload, data, options),
+        Some(Cbor::Int(JSON_FORMAT)) => document_matches(payload, data, options),
+        Some(_) => Err(ConstitutionalError::HashingError("Unsupported COSE content type".to_string())),
+    }
+}
+
+/// Whether a JSON payload has the document's canonical form.
+fn document_matches(payload: &[u8], data: &Value, options: &CanonicalizeOptions) -> Result<bool> {
+    let document: Value = serde_json::from_slice(payload).map_err(|_| malformed("the payload is not JSON"))?;
+    Ok(canonicalize_with(&document, options)? == canonicalize_with(data, options)?)
+}
+
+/// The COSE algorithm a key signs under.
+fn algorithm(key: &PublicKey) -> i64 {
+    match key.prefix() {
+        "ed25519" => -8,
+        _ => -47,
+    }
+}
+
+/// The bytes a COSE_Sign1 signature covers.
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+    encode(&Cbor::Array(vec![
+        Cbor::Text("Signature1".to_string()),
+        Cbor::Bytes(protected.to_vec()),
+        Cbor::Bytes(Vec::new()),
+        Cbor::Bytes(payload.to_vec()),
+    ]))
+}
+
+/// An envelope as a CBOR map, its keys in the length-first order of deterministic CBOR.
+fn envelope_map(envelope: &HashEnvelope) -> Cbor {
+    let text = |value: &str| Cbor::Text(value.to_string());
+    Cbor::Map(vec![
+        (text("digest"), text(&envelope.digest)),
+        (text("encoding"), text(envelope.encoding.identifier())),
+        (text("algorithm"), text(envelope.algorithm.identifier())),
+        (text("canon_version"), text(envelope.canon_version.identifier())),
+    ])
+}
+
+fn envelope_from_map(map: &Cbor) -> Option<HashEnvelope> {
+    let Cbor::Map(entries) = map else {
+        return None;
+    };
+    let mut fields = Map::new();
+    for (key, value) in entries {
+        let (Cbor::Text(key), Cbor::Text(value)) = (key, value) else {
+            return None;
+        };
+        fields.insert(key.clone(), Value::String(value.clone()));
+    }
+    serde_json::from_value(Value::Object(fields)).ok()
+}
+
+fn malformed(what: &str) -> ConstitutionalError {
+    ConstitutionalError::HashingError(format!("Malformed COSE_Sign1 message: {}", what))
+}
+
+/// A CBOR data item of the kinds COSE_Sign1 uses.
+#[derive(Debug, Clone, PartialEq)]
+enum Cbor {
+    Int(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Tag(u64, Box<Cbor>),
+    Null,
+}
+
+fn encode(item: &Cbor) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_item(item, &mut out);
+    out
+}
+
+fn write_item(item: &Cbor, out: &mut Vec<u8>) {
+    match item {
+        Cbor::Int(n) if *n >= 0 => write_head(0, *n as u64, out),
+        Cbor::Int(n) => write_head(1, !*n as u64, out),
+        Cbor::Bytes(bytes) => {
+            write_head(2, bytes.len() as u64, out);
+            out.extend_from_slice(bytes);
+        }
+        Cbor::Text(text) => {
+            write_head(3, text.len() as u64, out);
+            out.extend_from_slice(text.as_bytes());
+        }
+        Cbor::Array(items) => {
+            write_head(4, items.len() as u64, out);
+            items.iter().for_each(|item| write_item(item, out));
+        }
+        Cbor::Map(entries) => {
+            write_head(5, entries.len() as u64, out);
+            for (key, value) in entries {
+                write_item(key, out);
+                write_item(value, out);
+            }
+        }
+        Cbor::Tag(tag, item) => {
+            write_head(6, *tag, out);
+            write_item(item, out);
+        }
+        Cbor::Null => out.push(0xf6),
+    }
+}
+
+/// Write a major type and argument in the shortest form, as deterministic CBOR requires.
+fn write_head(major: u8, n: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match n {
+        0..=23 => out.push(major | n as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, n as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+/// Decode exactly one data item.
+fn decode(bytes: &[u8]) -> Option<Cbor> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let item = reader.item(0)?;
+    (reader.pos == bytes.len()).then_some(item)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Some(taken)
+    }
+
+    fn item(&mut self, depth: usize) -> Option<Cbor> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let initial = *self.take(1)?.first()?;
+        let (major, info) = (initial >> 5, initial & 31);
+        if initial == 0xf6 {
+            return Some(Cbor::Null);
+        }
+        let n = match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().ok()?)),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().ok()?)),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().ok()?),
+            // Indefinite lengths and reserved values
+            _ => return None,
+        };
+        // Every item takes at least a byte, which bounds lengths before allocating
+        let count = usize::try_from(n).ok().filter(|count| *count <= self.bytes.len() - self.pos);
+        match major {
+            0 => Some(Cbor::Int(i64::try_from(n).ok()?)),
+            1 => Some(Cbor::Int(!i64::try_from(n).ok()?)),
+            2 => Some(Cbor::Bytes(self.take(count?)?.to_vec())),
+            3 => Some(Cbor::Text(String::from_utf8(self.take(count?)?.to_vec()).ok()?)),
+            4 => (0..count?).map(|_| self.item(depth + 1)).collect::<Option<_>>().map(Cbor::Array),
+            5 => (0..count?)
+                .map(|_| Some((self.item(depth + 1)?, self.item(depth + 1)?)))
+                .collect::<Option<_>>()
+                .map(Cbor::Map),
+            6 => Some(Cbor::Tag(n, Box::new(self.item(depth + 1)?))),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::fixtures::{contract, keypair};
+    use crate::Keypair;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign1_round_trip() {
+        let options = CanonicalizeOptions::new();
+        let keypair = keypair();
+        let key = keypair.public_key();
+        for payload in [CosePayload::Hash, CosePayload::Document] {
+            let message = sign_cose(&contract(), &keypair, payload, &options).unwrap();
+            // Tag 18 around a four-item array
+            assert_eq!(&message[..2], [0xd2, 0x84]);
+            assert!(verify_cose(&message, &contract(), &key, &options).unwrap());
+            assert!(!verify_cose(&message, &json!({"contract_id": "c-18"}), &key, &options).unwrap());
+            let other = Keypair::from_secret(&[8; 32]).public_key();
+            assert!(!verify_cose(&message, &contract(), &other, &options).unwrap());
+        }
+
+        let message = sign_cose(&contract(), &keypair, CosePayload::Hash, &options).unwrap();
+        let Some(Cbor::Tag(_, sign1)) = decode(&message) else { panic!() };
+        let Cbor::Array(parts) = *sign1 else { panic!() };
+        let envelope = semantic_hash_envelope(&contract(), &options).unwrap();
+        assert_eq!(parts[0], Cbor::Bytes(vec![0xa1, 0x01, 0x27]));
+        assert_eq!(parts[2], Cbor::Bytes(encode(&envelope_map(&envelope))));
+        // Untagged messages verify too
+        assert!(verify_cose(&encode(&Cbor::Array(parts)), &contract(), &key, &options).unwrap());
+    }
+
+    #[test]
+    fn test_cbor_and_malformed_messages() {
+        for item in [
+            Cbor::Int(0),
+            Cbor::Int(-47),
+            Cbor::Int(i64::MAX),
+            Cbor::Int(i64::MIN),
+            Cbor::Text("Signature1".to_string()),
+            Cbor::Map(vec![(Cbor::Int(300), Cbor::Array(vec![Cbor::Null, Cbor::Bytes(vec![0; 70_000])]))]),
+        ] {
+            assert_eq!(decode(&encode(&item)), Some(item));
+        }
+        assert_eq!(encode(&Cbor::Int(-47)), [0x38, 0x2e]);
+        // Truncated, trailing, indefinite-length and oversized items
+        let oversized = [0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        for bytes in [&[0x43, 1, 2][..], &[0x01, 0x01], &[0x9f, 0xff], &oversized] {
+            assert_eq!(decode(bytes), None, "{:?}", bytes);
+        }
+        let nested = [vec![0x81; MAX_DEPTH + 2], vec![0x00]].concat();
+        assert_eq!(decode(&nested), None);
+
+        let options = CanonicalizeOptions::new();
+        let key = keypair().public_key();
+        let detached = encode(&Cbor::Array(vec![
+            Cbor::Bytes(encode(&Cbor::Map(vec![(Cbor::Int(ALG), Cbor::Int(-8))]))),
+            Cbor::Map(vec![]),
+            Cbor::Null,
+            Cbor::Bytes(vec![0; 64]),
+        ]));
+        assert!(verify_cose(&detached, &contract(), &key, &options).is_err());
+        assert!(verify_cose(b"not cbor", &contract(), &key, &options).is_err());
+    }
+}
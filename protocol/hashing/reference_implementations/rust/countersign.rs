@@ -0,0 +1,288 @@
+/// countersign.rs - Notaries' receipts over signed contracts
+///
+/// A notary checks a signed contract and then countersigns it. Its `Countersignature`
+/// is a receipt: the semantic hash it attests to, the signature it countersigns, the
+/// notary's key, when it notarized and its signature over all of these:
+///
+/// ```json
+/// {"hash_envelope":{...},"countersigns":"ed25519:92a0...","signer":"ed25519:8a88...",
+///  "notarized_at":"2025-06-01T12:00:00.000000000Z","signature":"ed25519:e556..."}
+/// ```
+///
+/// An `AttestationChain` is a signed contract with the countersignatures made after it,
+/// each over the same hash and countersigning the one before it, the first the
+/// contract's own signature. Removing, reordering or swapping out a link breaks the
+/// chain. Countersignatures are signed under their own tag, so a receipt's signature is
+/// never also a valid `SignedObject` signature.
+
+use crate::objects::take;
+use crate::timestamp::utc_timestamp;
+use crate::{
+    canonicalize_with, verify_signed_with, CanonicalizeOptions, ConstitutionalError, HashEnvelope, PublicKey, Result,
+    SignedObject, Signer,
+};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Domain separation for countersignatures.
+const RECEIPT_TAG: &[u8] = b"ocp-countersignature:";
+
+const FIELDS: &[&str] = &["hash_envelope", "countersigns", "signer", "notarized_at", "signature"];
+const CHAIN_FIELDS: &[&str] = &["signed", "countersignatures"];
+
+/// A notary's signed receipt for a signature over a semantic hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Countersignature {
+    /// Semantic hash of the contract, as in the original signed object
+    pub hash_envelope: HashEnvelope,
+    /// The signature countersigned, as `<scheme>:<hex>`
+    pub countersigns: String,
+    /// The notary's public key
+    pub signer: String,
+    /// When the notary countersigned, RFC 3339 UTC with nanosecond digits
+    pub notarized_at: String,
+    /// The notary's signature over the other fields
+    pub signature: String,
+}
+
+impl Countersignature {
+    /// Countersign a signature over a hash.
+    ///
+    /// # Arguments
+    /// * `hash_envelope` - The hash the signature is over
+    /// * `countersigns` - The signature to countersign
+    /// * `notary` - The notary's keypair, or any other `Signer`
+    /// * `at` - When the notary countersigns, RFC 3339
+    ///
+    /// # Returns
+    /// The countersignature, or an error if the timestamp is malformed or the notary
+    /// fails
+    pub fn new(hash_envelope: &HashEnvelope, countersigns: &str, notary: &dyn Signer, at: &str) -> Result<Self> {
+        let mut receipt = Countersignature {
+            hash_envelope: hash_envelope.clone(),
+            countersigns: countersigns.to_string(),
+            signer: notary.public_key()?.to_string(),
+            notarized_at: utc_timestamp(at)?,
+            signature: String::new(),
+        };
+        receipt.signature = notary.sign_message(&receipt.message()?)?;
+        Ok(receipt)
+    }
+
+    /// Whether the notary signed this receipt.
+    ///
+    /// # Returns
+    /// true if the signature verifies, false otherwise; an error if the signer or
+    /// signature is malformed
+    pub fn verify(&self) -> Result<bool> {
+        self.signer.parse::<PublicKey>()?.verify_message(&self.message()?, &self.signature)
+    }
+
+    /// The bytes the notary signs: the receipt without its signature, canonicalized.
+    fn message(&self) -> Result<Vec<u8>> {
+        let envelope =
+            serde_json::to_value(&self.hash_envelope).map_err(|e| ConstitutionalError::HashingError(e.to_string()))?;
+        let receipt = json!({
+            "hash_envelope": envelope,
+            "countersigns": self.countersigns,
+            "signer": self.signer,
+            "notarized_at": self.notarized_at,
+        });
+        let mut message = RECEIPT_TAG.to_vec();
+        message.extend_from_slice(canonicalize_with(&receipt, &CanonicalizeOptions::new())?.as_bytes());
+        Ok(message)
+    }
+}
+
+/// A signed contract and the notaries' countersignatures after it, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttestationChain {
+    /// The original signed contract
+    pub signed: SignedObject,
+    /// Countersignatures, each of the signature before it
+    pub countersignatures: Vec<Countersignature>,
+}
+
+impl AttestationChain {
+    /// Start a chain from a signed contract, with no countersignatures yet.
+    pub fn new(signed: SignedObject) -> Self {
+        AttestationChain { signed, countersignatures: Vec::new() }
+    }
+
+    /// Check the chain, then countersign its last signature as a notary.
+    ///
+    /// # Arguments
+    /// * `notary` - The notary's keypair, or any other `Signer`
+    /// * `at` - When the notary countersigns, RFC 3339; not before the last
+    ///   countersignature
+    /// * `options` - Canonicalization options the contract was hashed under
+    ///
+    /// # Returns
+    /// The new countersignature, also appended to the chain; a ProtocolError if the
+    /// chain does not verify
+    pub fn countersign(
+        &mut self,
+        notary: &dyn Signer,
+        at: &str,
+        options: &CanonicalizeOptions,
+    ) -> Result<&Countersignature> {
+        if !self.verify(options)? {
+            return Err(ConstitutionalError::ProtocolError("Refusing to countersign an invalid chain".to_string()));
+        }
+        let receipt = Countersignature::new(&self.signed.hash_envelope, self.last_signature(), notary, at)?;
+        if self.countersignatures.last().is_some_and(|last| receipt.notarized_at < last.notarized_at) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Cannot countersign at {} before the last countersignature",
+                receipt.notarized_at
+            )));
+        }
+        self.countersignatures.push(receipt);
+        Ok(self.countersignatures.last().expect("just pushed"))
+    }
+
+    /// Check the whole chain: the contract's signature, and that each countersignature
+    /// is over the same hash, countersigns the signature before it, is no earlier than
+    /// the one before it and verifies.
+    ///
+    /// # Returns
+    /// true if all hold, false otherwise; an error if a signer or signature is
+    /// malformed
+    pub fn verify(&self, options: &CanonicalizeOptions) -> Result<bool> {
+        if !verify_signed_with(&self.signed, options)? {
+            return Ok(false);
+        }
+        let mut previous: Option<&Countersignature> = None;
+        for receipt in &self.countersignatures {
+            let countersigned = previous.map_or(&self.signed.signature, |previous| &previous.signature);
+            if receipt.hash_envelope != self.signed.hash_envelope
+                || receipt.countersigns != *countersigned
+                || previous.is_some_and(|previous| receipt.notarized_at < previous.notarized_at)
+                || !receipt.verify()?
+            {
+                return Ok(false);
+            }
+            previous = Some(receipt);
+        }
+        Ok(true)
+    }
+
+    /// Public keys of the notaries, in the order they countersigned.
+    pub fn notaries(&self) -> impl Iterator<Item = &str> {
+        self.countersignatures.iter().map(|receipt| receipt.signer.as_str())
+    }
+
+    fn last_signature(&self) -> &str {
+        self.countersignatures.last().map_or(&self.signed.signature, |receipt| &receipt.signature)
+    }
+}
+
+impl Serialize for Countersignature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Countersignature", FIELDS.len())?;
+        state.serialize_field("hash_envelope", &self.hash_envelope)?;
+        state.serialize_field("countersigns", &self.countersigns)?;
+        state.serialize_field("signer", &self.signer)?;
+        state.serialize_field("notarized_at", &self.notarized_at)?;
+        state.serialize_field("signature", &self.signature)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Countersignature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Countersignature, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a countersignature"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        Ok(Countersignature {
+            hash_envelope: take(&mut map, "hash_envelope")?,
+            countersigns: take(&mut map, "countersigns")?,
+            signer: take(&mut map, "signer")?,
+            notarized_at: take(&mut map, "notarized_at")?,
+            signature: take(&mut map, "signature")?,
+        })
+    }
+}
+
+impl Serialize for AttestationChain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AttestationChain", CHAIN_FIELDS.len())?;
+        state.serialize_field("signed", &self.signed)?;
+        state.serialize_field("countersignatures", &self.countersignatures)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for AttestationChain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<AttestationChain, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected an attestation chain"));
+        };
+        if let Some(key) = map.keys().find(|key| !CHAIN_FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, CHAIN_FIELDS));
+        }
+        Ok(AttestationChain {
+            signed: take(&mut map, "signed")?,
+            countersignatures: take(&mut map, "countersignatures")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_with, Keypair};
+
+    fn chain() -> AttestationChain {
+        let options = CanonicalizeOptions::new();
+        let contract = json!({"contract_id": "c-17", "action_type": "amend"});
+        let mut chain = AttestationChain::new(sign_with(&contract, &Keypair::from_secret(&[1; 32]), &options).unwrap());
+        chain.countersign(&Keypair::from_secret(&[2; 32]), "2025-06-01T12:00:00Z", &options).unwrap();
+        chain.countersign(&Keypair::from_secret(&[3; 32]), "2025-06-01T13:00:00+01:00", &options).unwrap();
+        chain
+    }
+
+    #[test]
+    fn test_chain_links_each_signature() {
+        let options = CanonicalizeOptions::new();
+        let chain = chain();
+        assert!(chain.verify(&options).unwrap());
+        assert_eq!(chain.countersignatures[0].countersigns, chain.signed.signature);
+        assert_eq!(chain.countersignatures[1].countersigns, chain.countersignatures[0].signature);
+        assert_eq!(chain.countersignatures[1].notarized_at, "2025-06-01T12:00:00.000000000Z");
+        let notaries: Vec<_> = [2, 3].map(|seed| Keypair::from_secret(&[seed; 32]).public_key().to_string()).into();
+        assert!(chain.notaries().eq(notaries.iter().map(String::as_str)));
+
+        let stored = serde_json::to_string(&chain).unwrap();
+        assert_eq!(serde_json::from_str::<AttestationChain>(&stored).unwrap(), chain);
+        let receipt = serde_json::to_value(&chain.countersignatures[0]).unwrap();
+        assert_eq!(serde_json::from_value::<Countersignature>(receipt).unwrap(), chain.countersignatures[0]);
+    }
+
+    #[test]
+    fn test_broken_chains_fail() {
+        let options = CanonicalizeOptions::new();
+        let mut dropped = chain();
+        dropped.countersignatures.remove(0);
+        assert!(!dropped.verify(&options).unwrap());
+        let mut swapped = chain();
+        swapped.countersignatures.swap(0, 1);
+        assert!(!swapped.verify(&options).unwrap());
+        let mut backdated = chain();
+        backdated.countersignatures[1].notarized_at = "2025-01-01T00:00:00.000000000Z".to_string();
+        assert!(!backdated.verify(&options).unwrap());
+        let mut tampered = chain();
+        tampered.signed.payload["action_type"] = json!("repeal");
+        assert!(!tampered.verify(&options).unwrap());
+        assert!(tampered.countersign(&Keypair::from_secret(&[4; 32]), "2025-06-02T00:00:00Z", &options).is_err());
+
+        let mut chain = chain();
+        assert!(chain.countersign(&Keypair::from_secret(&[4; 32]), "2025-06-01T11:00:00Z", &options).is_err());
+        assert!(chain.countersign(&Keypair::from_secret(&[4; 32]), "yesterday", &options).is_err());
+        assert_eq!(chain.countersignatures.len(), 2);
+    }
+}
@@ -0,0 +1,127 @@
+/// lib.rs - `#[derive(Canonicalize)]` for Rust structs
+///
+/// Companion proc-macro crate (`ocp_canon_derive`) to the canonicalizer. Deriving
+/// `Canonicalize` on a struct with named fields implements the `Canonicalize` trait by
+/// building the struct's JSON object field by field, so typed contract structs get
+/// `canonical_json` and `semantic_hash` with no hand-written `Value` conversions. Every
+/// field must implement `Canonicalize` itself.
+///
+/// Field attributes:
+///
+/// * `#[ocp(skip)]` leaves the field out of the canonical form (e.g. a cached hash).
+/// * `#[ocp(rename = "name")]` uses `name` as the object key instead of the field name.
+/// * `#[ocp(ordered)]` keeps an array field in input order. Only that field is affected;
+///   arrays elsewhere follow the default policy. Like all options, this applies when the
+///   struct itself is canonicalized, not when it is nested in another value.
+///
+/// The container attribute `#[ocp(crate = "path")]` names the canonicalizer crate when
+/// it is not a dependency called `ocp_canon`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+#[proc_macro_derive(Canonicalize, attributes(ocp))]
+pub fn derive_canonicalize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// How one field appears in the canonical form.
+struct Field {
+    ident: syn::Ident,
+    key: String,
+    ordered: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = crate_path(input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(unsupported(input)),
+        },
+        _ => return Err(unsupported(input)),
+    };
+
+    let mut included = Vec::new();
+    for field in fields {
+        if let Some(field) = parse_field(field)? {
+            if let Some(earlier) = included.iter().find(|f: &&Field| f.key == field.key) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    format!("key {:?} is already used by field `{}`", field.key, earlier.ident),
+                ));
+            }
+            included.push(field);
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents = included.iter().map(|f| &f.ident);
+    let keys = included.iter().map(|f| &f.key);
+    let ordered = included.iter().filter(|f| f.ordered).map(|f| &f.key);
+
+    Ok(quote! {
+        impl #impl_generics #krate::Canonicalize for #name #ty_generics #where_clause {
+            fn canonical_value(&self) -> #krate::__private::Value {
+                let mut object = #krate::__private::Map::new();
+                #(
+                    object.insert(#keys.to_string(), #krate::Canonicalize::canonical_value(&self.#idents));
+                )*
+                #krate::__private::Value::Object(object)
+            }
+
+            fn canonical_options(&self) -> #krate::CanonicalizeOptions {
+                #krate::__private::ordered_fields(&[#(#ordered),*])
+            }
+        }
+    })
+}
+
+/// Read a field's `#[ocp(...)]` attributes; None if it is skipped.
+fn parse_field(field: &syn::Field) -> syn::Result<Option<Field>> {
+    // Only named fields reach here
+    let ident = field.ident.clone().expect("named field");
+    let mut key = ident.to_string().trim_start_matches("r#").to_string();
+    let mut ordered = false;
+    let mut skip = false;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("ocp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+            } else if meta.path.is_ident("ordered") {
+                ordered = true;
+            } else if meta.path.is_ident("rename") {
+                key = meta.value()?.parse::<LitStr>()?.value();
+            } else {
+                return Err(meta.error("expected `skip`, `ordered` or `rename = \"...\"`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok((!skip).then_some(Field { ident, key, ordered }))
+}
+
+/// The `#[ocp(crate = "...")]` path, or `::ocp_canon`.
+fn crate_path(input: &DeriveInput) -> syn::Result<syn::Path> {
+    let mut path = syn::parse_quote!(::ocp_canon);
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("ocp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `crate = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(path)
+}
+
+fn unsupported(input: &DeriveInput) -> syn::Error {
+    syn::Error::new_spanned(&input.ident, "Canonicalize can only be derived for structs with named fields")
+}
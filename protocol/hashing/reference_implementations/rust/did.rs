@@ -0,0 +1,246 @@
+/// did.rs - Decentralized identifiers for agents
+///
+/// An agent named by a free-form string ("Claude-3") can be impersonated by anyone who
+/// picks the same string. A DID (W3C Decentralized Identifiers) names an agent by
+/// something its key material resolves from, so a `SignedObject` whose `signer` is a DID
+/// verifies only against the keys that DID resolves to.
+///
+/// `did:key` is built in. Its identifier is the public key itself, written multibase
+/// base58btc over the key's multicodec code (a varint) and bytes, so it resolves without
+/// any lookup:
+///
+/// ```text
+/// did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK
+/// ```
+///
+/// Ed25519 keys (code 0xed) start `z6Mk`, compressed secp256k1 keys (code 0xe7) `zQ3s`.
+/// Other methods, such as `did:web` or a ledger's own, are resolved by a `DidResolver`
+/// registered in a `DidResolvers` under its method name.
+
+use crate::encoding::decode_multibase;
+use crate::registry::{read_varint, write_varint};
+use crate::{ConstitutionalError, Encoding, PublicKey, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Multicodec codes of the public key types, by the prefix of their text form.
+const KEY_CODES: &[(&str, u64)] = &[("ed25519", 0xed), ("secp256k1", 0xe7)];
+
+/// A decentralized identifier, `did:<method>:<method-specific-id>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Did {
+    text: String,
+    /// Length of the method name
+    method_len: usize,
+}
+
+impl Did {
+    /// The `did:key` identifier of a public key.
+    pub fn key(key: &PublicKey) -> Did {
+        let code = KEY_CODES.iter().find(|(prefix, _)| *prefix == key.prefix()).map(|(_, code)| *code);
+        let mut bytes = Vec::new();
+        write_varint(code.expect("every key type has a multicodec code"), &mut bytes);
+        bytes.extend_from_slice(&key.to_bytes());
+        let id = Encoding::Base58Btc.encode_text(&bytes).expect("base58btc is a text encoding");
+        Did { text: format!("did:key:{}", id), method_len: 3 }
+    }
+
+    /// The method name, e.g. `key`.
+    pub fn method(&self) -> &str {
+        &self.text[4..4 + self.method_len]
+    }
+
+    /// Everything after the method name and its colon.
+    pub fn method_specific_id(&self) -> &str {
+        &self.text[5 + self.method_len..]
+    }
+
+    /// The identifier as text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for Did {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl FromStr for Did {
+    type Err = ConstitutionalError;
+
+    fn from_str(text: &str) -> Result<Did> {
+        let malformed = || ConstitutionalError::ProtocolError(format!("Not a DID: {:?}", text));
+        let (method, id) = text.strip_prefix("did:").and_then(|rest| rest.split_once(':')).ok_or_else(malformed)?;
+        let method_ok = !method.is_empty() && method.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit());
+        let id_ok = !id.is_empty()
+            && !id.ends_with(':')
+            && id.bytes().all(|b| b.is_ascii_alphanumeric() || b".-_:%".contains(&b));
+        if !method_ok || !id_ok {
+            return Err(malformed());
+        }
+        Ok(Did { text: text.to_string(), method_len: method.len() })
+    }
+}
+
+/// Resolves the DIDs of one method to the public keys their controllers sign with.
+pub trait DidResolver: Send + Sync {
+    /// The DID method this resolver handles, e.g. `web`.
+    fn method(&self) -> &str;
+
+    /// The public keys the DID's controller may sign with.
+    fn resolve(&self, did: &Did) -> Result<Vec<PublicKey>>;
+}
+
+/// Resolver of `did:key`, which decodes the key from the identifier itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyResolver;
+
+impl DidResolver for KeyResolver {
+    fn method(&self) -> &str {
+        "key"
+    }
+
+    fn resolve(&self, did: &Did) -> Result<Vec<PublicKey>> {
+        let key = decode_multibase(did.method_specific_id())
+            .filter(|_| did.method_specific_id().starts_with('z'))
+            .and_then(|bytes| {
+                let (code, key) = read_varint(&bytes)?;
+                let (prefix, _) = KEY_CODES.iter().find(|(_, known)| *known == code)?;
+                PublicKey::from_parts(prefix, key)
+            })
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Not a supported did:key: {}", did)))?;
+        Ok(vec![key])
+    }
+}
+
+/// DID resolvers by method.
+#[derive(Clone)]
+pub struct DidResolvers {
+    by_method: HashMap<String, Arc<dyn DidResolver>>,
+}
+
+impl Default for DidResolvers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DidResolvers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut methods: Vec<_> = self.by_method.keys().collect();
+        methods.sort();
+        f.debug_struct("DidResolvers").field("methods", &methods).finish()
+    }
+}
+
+impl DidResolvers {
+    /// Resolvers for the built-in methods: `did:key`.
+    pub fn new() -> Self {
+        let mut resolvers = Self::empty();
+        let _ = resolvers.register(KeyResolver);
+        resolvers
+    }
+
+    /// No resolvers at all.
+    pub fn empty() -> Self {
+        DidResolvers { by_method: HashMap::new() }
+    }
+
+    /// Add a resolver for its method.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the method already has a resolver
+    pub fn register(&mut self, resolver: impl DidResolver + 'static) -> Result<()> {
+        let method = resolver.method().to_string();
+        if self.by_method.contains_key(&method) {
+            return Err(ConstitutionalError::ProtocolError(format!("did:{} already has a resolver", method)));
+        }
+        self.by_method.insert(method, Arc::new(resolver));
+        Ok(())
+    }
+
+    /// The keys a DID resolves to.
+    ///
+    /// # Returns
+    /// At least one key, or a ProtocolError if no resolver handles the method or the DID
+    /// resolves to no keys
+    pub fn resolve(&self, did: &Did) -> Result<Vec<PublicKey>> {
+        let resolver = self
+            .by_method
+            .get(did.method())
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("No resolver for did:{}", did.method())))?;
+        let keys = resolver.resolve(did)?;
+        if keys.is_empty() {
+            return Err(ConstitutionalError::ProtocolError(format!("{} resolves to no keys", did)));
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_as, verify_signed, verify_signed_resolving, CanonicalizeOptions, Keypair};
+    use serde_json::json;
+
+    /// A registry of agents by name, as a ledger's own DID method might keep.
+    struct Roster(HashMap<String, Vec<PublicKey>>);
+
+    impl DidResolver for Roster {
+        fn method(&self) -> &str {
+            "ocp"
+        }
+
+        fn resolve(&self, did: &Did) -> Result<Vec<PublicKey>> {
+            Ok(self.0.get(did.method_specific_id()).cloned().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn test_did_key_round_trip() {
+        let key = Keypair::from_secret(&[7; 32]).public_key();
+        let did = Did::key(&key);
+        assert!(did.as_str().starts_with("did:key:z6Mk"));
+        assert_eq!(did.method(), "key");
+        assert_eq!(did.to_string().parse::<Did>().unwrap(), did);
+        assert_eq!(DidResolvers::new().resolve(&did).unwrap(), [key]);
+
+        for text in ["did:key", "did::abc", "did:KEY:abc", "did:key:", "did:key:a b", "key:abc"] {
+            assert!(text.parse::<Did>().is_err(), "{}", text);
+        }
+        let unknown: Did = "did:web:example.com".parse().unwrap();
+        assert!(DidResolvers::new().resolve(&unknown).is_err());
+        assert!(KeyResolver.resolve(&"did:key:f00".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_signatures_bind_to_dids() {
+        let options = CanonicalizeOptions::new();
+        let contract = json!({"contract_id": "c-17", "action_type": "amend"});
+        let keypair = Keypair::from_secret(&[7; 32]);
+
+        let did = Did::key(&keypair.public_key());
+        let signed = sign_as(&contract, &keypair, &did, &options).unwrap();
+        assert_eq!(signed.signer, did.to_string());
+        assert!(verify_signed(&signed).unwrap());
+        let impostor = Did::key(&Keypair::from_secret(&[8; 32]).public_key());
+        assert!(!verify_signed(&sign_as(&contract, &keypair, &impostor, &options).unwrap()).unwrap());
+
+        // A custom method, with a rotated-out key still listed next to the current one
+        let mut roster = HashMap::new();
+        roster.insert("claude".to_string(), vec![Keypair::from_secret(&[1; 32]).public_key(), keypair.public_key()]);
+        let mut resolvers = DidResolvers::new();
+        resolvers.register(Roster(roster)).unwrap();
+        assert!(resolvers.register(KeyResolver).is_err());
+
+        let named = sign_as(&contract, &keypair, &"did:ocp:claude".parse().unwrap(), &options).unwrap();
+        assert!(verify_signed_resolving(&named, &options, &resolvers).unwrap());
+        assert!(verify_signed(&named).is_err());
+        let unknown = sign_as(&contract, &keypair, &"did:ocp:gemini".parse().unwrap(), &options).unwrap();
+        assert!(verify_signed_resolving(&unknown, &options, &resolvers).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+/// digest.rs - A typed 256-bit semantic hash
+///
+/// Hashes passed around as `String` are easy to confuse with every other hex string in
+/// a ledger record (signatures, key IDs, evidence pointers). `SemanticHash` holds the 32
+/// digest bytes of any 256-bit algorithm, parses from every encoding the verifiers
+/// accept, prints as lowercase hex, and serializes as that hex string, so existing
+/// stored hashes deserialize unchanged.
+
+use crate::encoding::decode_digest;
+use crate::hmac::constant_time_eq;
+use crate::{semantic_digest, CanonicalizeOptions, ConstitutionalError, Encoding, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Digest length in bytes.
+const LEN: usize = 32;
+
+/// A 256-bit semantic hash (SHA-256 by default; also SHA3-256 or BLAKE3).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SemanticHash([u8; LEN]);
+
+impl SemanticHash {
+    /// All zero bytes, the hash of no subtree in a sparse Merkle tree.
+    pub const ZERO: SemanticHash = SemanticHash([0; LEN]);
+
+    /// Calculate the semantic hash of data with default options.
+    pub fn of(data: &Value) -> Result<SemanticHash> {
+        SemanticHash::compute(data, &CanonicalizeOptions::default())
+    }
+
+    /// Calculate the semantic hash of data canonicalized with explicit options.
+    ///
+    /// # Arguments
+    /// * `data` - Input JSON value to hash
+    /// * `options` - Canonicalization options; the hash algorithm must have a 256-bit digest
+    ///
+    /// # Returns
+    /// The hash, or a HashingError under SHA-512
+    pub fn compute(data: &Value, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        SemanticHash::try_from(semantic_digest(data, options)?.as_slice())
+    }
+
+    /// Check that data canonicalized with `options` produces this hash, in constant time.
+    pub fn verify(&self, data: &Value, options: &CanonicalizeOptions) -> Result<bool> {
+        Ok(constant_time_eq(&semantic_digest(data, options)?, &self.0))
+    }
+
+    /// The digest bytes.
+    pub fn as_bytes(&self) -> &[u8; LEN] {
+        &self.0
+    }
+
+    /// Lowercase hex, as `semantic_hash` returns it.
+    pub fn to_hex(&self) -> String {
+        crate::algorithm::hex(&self.0)
+    }
+
+    /// Multibase base64url (`u` prefix, no padding).
+    pub fn to_base64url(&self) -> String {
+        self.encode(Encoding::Base64Url).into_iter().map(char::from).collect()
+    }
+
+    /// The hash in any encoding: ASCII text, or the bytes themselves for `Binary`.
+    pub fn encode(&self, encoding: Encoding) -> Vec<u8> {
+        encoding.encode(&self.0)
+    }
+}
+
+impl From<[u8; LEN]> for SemanticHash {
+    fn from(bytes: [u8; LEN]) -> Self {
+        SemanticHash(bytes)
+    }
+}
+
+impl From<SemanticHash> for [u8; LEN] {
+    fn from(hash: SemanticHash) -> Self {
+        hash.0
+    }
+}
+
+impl TryFrom<&[u8]> for SemanticHash {
+    type Error = ConstitutionalError;
+
+    fn try_from(bytes: &[u8]) -> Result<SemanticHash> {
+        <[u8; LEN]>::try_from(bytes).map(SemanticHash).map_err(|_| {
+            ConstitutionalError::HashingError(format!("Semantic hash must be {} bytes, got {}", LEN, bytes.len()))
+        })
+    }
+}
+
+impl AsRef<[u8]> for SemanticHash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FromStr for SemanticHash {
+    type Err = ConstitutionalError;
+
+    /// Parse hex in either case, or multibase hex, base64url or base58btc.
+    fn from_str(text: &str) -> Result<SemanticHash> {
+        decode_digest(text, LEN)
+            .and_then(|bytes| <[u8; LEN]>::try_from(bytes).ok())
+            .map(SemanticHash)
+            .ok_or_else(|| ConstitutionalError::HashingError(format!("{:?} is not a 256-bit semantic hash", text)))
+    }
+}
+
+impl fmt::Display for SemanticHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for SemanticHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SemanticHash({})", self.to_hex())
+    }
+}
+
+impl Serialize for SemanticHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for SemanticHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SemanticHash, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash, HashAlgorithm};
+    use serde_json::json;
+
+    #[test]
+    fn test_semantic_hash_conversions() {
+        let data = json!({"action_id": "001", "agent": "agent-7"});
+        let hash = SemanticHash::of(&data).unwrap();
+        let hex = semantic_hash(&data).unwrap();
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(hex.parse::<SemanticHash>().unwrap(), hash);
+        assert_eq!(hex.to_uppercase().parse::<SemanticHash>().unwrap(), hash);
+        assert_eq!(hash.to_base64url().parse::<SemanticHash>().unwrap(), hash);
+        assert_eq!(SemanticHash::try_from(&hash.encode(Encoding::Binary)[..]).unwrap(), hash);
+
+        assert_eq!(serde_json::to_value(hash).unwrap(), json!(hex));
+        assert_eq!(serde_json::from_value::<SemanticHash>(json!(hex)).unwrap(), hash);
+        assert!(serde_json::from_value::<SemanticHash>(json!("abc")).is_err());
+
+        assert!(hash.verify(&data, &CanonicalizeOptions::new()).unwrap());
+        assert!(!hash.verify(&json!({"action_id": "002"}), &CanonicalizeOptions::new()).unwrap());
+        assert!(SemanticHash::compute(&data, &CanonicalizeOptions::new().hash_algorithm(HashAlgorithm::Sha512)).is_err());
+        assert!(hex[..62].parse::<SemanticHash>().is_err());
+    }
+}
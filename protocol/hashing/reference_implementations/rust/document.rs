@@ -0,0 +1,424 @@
+/// document.rs - Articles, sections and clauses of the constitution
+///
+/// An article is an object with a Roman numeral `article_id`, a `title` and its
+/// numbered `sections`, each section a list of clauses, one per paragraph or list item:
+///
+/// ```json
+/// {"article_id":"III","title":"Obligations","sections":[{"number":1,"title":"Truthfulness","clauses":["..."]}]}
+/// ```
+///
+/// Every article, section and clause has a stable address: `article-3` for Article III,
+/// `article-3.1` for its section 3.1 and `article-3.1.2` for that section's second
+/// clause. An amendment names the location it changes by address, and `Constitution`
+/// resolves an address to the value there and its semantic hash.
+///
+/// `parse_markdown` reads the headed Markdown the constitution is written in: a `#`
+/// title, `## ARTICLE III — Title` headings for articles, `### 3.1 Title` headings for
+/// sections, and any other `##` heading, such as `## Version 2.1` or `## PREAMBLE`, for
+/// a member of the header.
+
+use crate::{Constitution, ConstitutionalError, Result};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+const PREFIX: &str = "article-";
+
+/// The address of an article, a section in it or a clause in that section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address {
+    /// The article's number, 3 for Article III
+    pub article: u32,
+    /// The section's number within the article, 1 for section 3.1
+    pub section: Option<u32>,
+    /// The clause's position in the section, from 1; only with a section
+    pub clause: Option<u32>,
+}
+
+impl Address {
+    /// The address of a whole article.
+    pub fn article(article: u32) -> Self {
+        Address { article, section: None, clause: None }
+    }
+
+    /// The address of a section.
+    pub fn section(article: u32, section: u32) -> Self {
+        Address { article, section: Some(section), clause: None }
+    }
+
+    /// The address of a clause.
+    pub fn clause(article: u32, section: u32, clause: u32) -> Self {
+        Address { article, section: Some(section), clause: Some(clause) }
+    }
+
+    /// The `article_id` of the article addressed, its number in Roman numerals.
+    pub fn article_id(&self) -> String {
+        roman(self.article)
+    }
+
+    /// The address of the article with a Roman numeral `article_id`, if it is one.
+    pub fn of_article(article_id: &str) -> Option<Address> {
+        from_roman(article_id).map(Address::article)
+    }
+
+    /// Whether the address is this one or inside it.
+    pub fn contains(&self, other: &Address) -> bool {
+        self.article == other.article
+            && self.section.is_none_or(|section| other.section == Some(section))
+            && self.clause.is_none_or(|clause| other.clause == Some(clause))
+    }
+
+    /// The address of the section or article containing this one; None for an article.
+    pub fn parent(&self) -> Option<Address> {
+        match (self.section, self.clause) {
+            (Some(section), Some(_)) => Some(Address::section(self.article, section)),
+            (Some(_), None) => Some(Address::article(self.article)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", PREFIX, self.article)?;
+        if let Some(section) = self.section {
+            write!(f, ".{}", section)?;
+        }
+        if let Some(clause) = self.clause {
+            write!(f, ".{}", clause)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Address {
+    type Err = ConstitutionalError;
+
+    /// Parse `article-3`, `article-3.1` or `article-3.1.2`; numbers start at 1 and have
+    /// no leading zeros, so each location has one address.
+    fn from_str(text: &str) -> Result<Address> {
+        let invalid = || ConstitutionalError::ProtocolError(format!("Not a constitution address: {:?}", text));
+        let numbers = text.strip_prefix(PREFIX).ok_or_else(invalid)?;
+        let numbers: Vec<u32> = numbers.split('.').map(|n| number(n).ok_or_else(invalid)).collect::<Result<_>>()?;
+        match numbers[..] {
+            [article] if article <= MAX_ARTICLE => Ok(Address::article(article)),
+            [article, section] if article <= MAX_ARTICLE => Ok(Address::section(article, section)),
+            [article, section, clause] if article <= MAX_ARTICLE => Ok(Address::clause(article, section, clause)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A positive decimal number without leading zeros.
+fn number(text: &str) -> Option<u32> {
+    if text.starts_with('0') || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// The largest article number Roman numerals can write.
+const MAX_ARTICLE: u32 = 3999;
+
+const NUMERALS: [(u32, &str); 13] = [
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+fn roman(mut n: u32) -> String {
+    let mut text = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            text.push_str(numeral);
+            n -= value;
+        }
+    }
+    text
+}
+
+/// The number a Roman numeral writes, if it is the one way of writing it.
+pub(crate) fn from_roman(text: &str) -> Option<u32> {
+    let mut rest = text;
+    let mut n = 0;
+    for (value, numeral) in NUMERALS {
+        while let Some(after) = rest.strip_prefix(numeral) {
+            n += value;
+            rest = after;
+        }
+    }
+    (rest.is_empty() && n > 0 && n <= MAX_ARTICLE && roman(n) == text).then_some(n)
+}
+
+/// A numbered section of an article.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// The section's number within its article, 1 for section 3.1
+    pub number: u32,
+    /// The section's heading
+    pub title: String,
+    /// The section's clauses, in order
+    pub clauses: Vec<String>,
+}
+
+impl Section {
+    /// The section's JSON object.
+    pub fn to_value(&self) -> Value {
+        json!({"number": self.number, "title": self.title, "clauses": self.clauses})
+    }
+
+    pub(crate) fn from_value(value: &Value, path: &[String]) -> Result<Section> {
+        let malformed = |message: &str| ConstitutionalError::canonicalization_at(message, path);
+        let number = value.get("number").and_then(Value::as_u64).filter(|n| (1..=u32::MAX as u64).contains(n));
+        let title = value.get("title").and_then(Value::as_str);
+        let clauses = value.get("clauses").and_then(Value::as_array);
+        match (number, title, clauses) {
+            (Some(number), Some(title), Some(clauses)) => Ok(Section {
+                number: number as u32,
+                title: title.to_string(),
+                clauses: clauses
+                    .iter()
+                    .map(|clause| clause.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| malformed("A clause must be a string"))?,
+            }),
+            _ => Err(malformed("A section needs a positive number, a title and clauses")),
+        }
+    }
+}
+
+/// An article of the constitution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Article {
+    /// The article's number, written in Roman numerals as its `article_id`
+    pub number: u32,
+    /// The article's heading
+    pub title: String,
+    /// The article's sections, in order
+    pub sections: Vec<Section>,
+}
+
+impl Article {
+    /// The article's `article_id`, its number in Roman numerals.
+    pub fn article_id(&self) -> String {
+        roman(self.number)
+    }
+
+    /// The section with the given number.
+    pub fn section(&self, number: u32) -> Option<&Section> {
+        self.sections.iter().find(|section| section.number == number)
+    }
+
+    /// The article's JSON object, the form `Constitution` holds it in.
+    pub fn to_value(&self) -> Value {
+        let sections: Vec<Value> = self.sections.iter().map(Section::to_value).collect();
+        json!({"article_id": self.article_id(), "title": self.title, "sections": sections})
+    }
+
+    /// Read an article object.
+    ///
+    /// # Returns
+    /// The article, or a CanonicalizationError if its `article_id` is not a Roman
+    /// numeral, it has no `title` or `sections`, or two sections share a number
+    pub fn from_value(value: &Value) -> Result<Article> {
+        let number = value.get("article_id").and_then(Value::as_str).and_then(from_roman).ok_or_else(|| {
+            ConstitutionalError::canonicalization_at("An article_id must be a Roman numeral", &["article_id".into()])
+        })?;
+        let title = value.get("title").and_then(Value::as_str);
+        let Some((title, list)) = title.zip(value.get("sections").and_then(Value::as_array)) else {
+            return Err(ConstitutionalError::canonicalization("An article needs a title and sections"));
+        };
+        let mut article = Article { number, title: title.to_string(), sections: Vec::new() };
+        for (i, section) in list.iter().enumerate() {
+            let path = ["sections".to_string(), i.to_string()];
+            let section = Section::from_value(section, &path)?;
+            if article.section(section.number).is_some() {
+                return Err(ConstitutionalError::canonicalization_at(
+                    format!("Duplicate section {}.{}", number, section.number),
+                    &path,
+                ));
+            }
+            article.sections.push(section);
+        }
+        Ok(article)
+    }
+}
+
+/// Where text being read belongs.
+enum Part {
+    Header(String),
+    Article,
+    Section,
+}
+
+/// Read a constitution written in headed Markdown.
+///
+/// Paragraphs and list items under a `###` section heading are its clauses; under any
+/// other `##` heading they are an array in the header named after the heading, except
+/// `## Version 2.1`, which sets the header's `version`. Horizontal rules are ignored.
+///
+/// # Returns
+/// The constitution, or a ProtocolError naming the line of a malformed heading, a
+/// section outside its article, a repeated article or section, or text outside any
+/// section or heading
+pub fn parse_markdown(text: &str) -> Result<Constitution> {
+    let mut header = Map::new();
+    let mut articles: Vec<Article> = Vec::new();
+    let mut part = None;
+    let mut paragraph: Vec<&str> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: String| ConstitutionalError::ProtocolError(format!("Line {}: {}", i + 1, message));
+        let line = line.trim();
+        let heading = line.starts_with('#');
+        let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "));
+        if (heading || item.is_some() || line.is_empty() || line == "---") && !paragraph.is_empty() {
+            let clause = paragraph.join(" ");
+            paragraph.clear();
+            push(&mut header, &mut articles, part.as_ref(), clause)
+                .ok_or_else(|| error("Text outside a section".into()))?;
+        }
+        if let Some(title) = line.strip_prefix("# ") {
+            header.insert("title".into(), Value::from(title.trim()));
+            part = None;
+        } else if let Some(version) = line.strip_prefix("## Version ") {
+            header.insert("version".into(), Value::from(version.trim()));
+            part = None;
+        } else if let Some(rest) = line.strip_prefix("## ARTICLE ") {
+            let (id, title) = rest
+                .split_once('—')
+                .or_else(|| rest.split_once(" - "))
+                .ok_or_else(|| error(format!("An article heading needs a title: {:?}", line)))?;
+            let number = from_roman(id.trim()).ok_or_else(|| error(format!("Not a Roman numeral: {:?}", id.trim())))?;
+            if articles.iter().any(|article| article.number == number) {
+                return Err(error(format!("Duplicate article {}", id.trim())));
+            }
+            articles.push(Article { number, title: title.trim().to_string(), sections: Vec::new() });
+            part = Some(Part::Article);
+        } else if let Some(name) = line.strip_prefix("## ") {
+            let key = name.trim().to_lowercase().replace(|c: char| !c.is_alphanumeric(), "_");
+            header.insert(key.clone(), Value::Array(Vec::new()));
+            part = Some(Part::Header(key));
+        } else if let Some(rest) = line.strip_prefix("### ") {
+            let (numbers, title) = rest.split_once(' ').unwrap_or((rest, ""));
+            let numbers = numbers.split_once('.').and_then(|(a, s)| number(a).zip(number(s)));
+            let Some((article_number, section_number)) = numbers else {
+                return Err(error(format!("A section heading needs a number like 3.1: {:?}", line)));
+            };
+            let article = match (articles.last_mut(), &part) {
+                (Some(article), Some(Part::Article | Part::Section)) if article.number == article_number => article,
+                _ => {
+                    return Err(error(format!("Section {}.{} is outside its article", article_number, section_number)))
+                }
+            };
+            if article.section(section_number).is_some() {
+                return Err(error(format!("Duplicate section {}.{}", article_number, section_number)));
+            }
+            let title = title.trim().to_string();
+            article.sections.push(Section { number: section_number, title, clauses: Vec::new() });
+            part = Some(Part::Section);
+        } else if heading {
+            return Err(error(format!("Unexpected heading: {:?}", line)));
+        } else if let Some(item) = item {
+            push(&mut header, &mut articles, part.as_ref(), item.trim().to_string())
+                .ok_or_else(|| error("Text outside a section".into()))?;
+        } else if !line.is_empty() && line != "---" {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        push(&mut header, &mut articles, part.as_ref(), paragraph.join(" "))
+            .ok_or_else(|| ConstitutionalError::ProtocolError("Text outside a section".into()))?;
+    }
+    header.insert("articles".into(), articles.iter().map(Article::to_value).collect());
+    Constitution::from_value(Value::Object(header))
+}
+
+/// Add a clause where the text being read belongs; None if it belongs nowhere.
+fn push(header: &mut Map<String, Value>, articles: &mut [Article], part: Option<&Part>, clause: String) -> Option<()> {
+    match part? {
+        Part::Header(key) => header.get_mut(key)?.as_array_mut()?.push(Value::from(clause)),
+        Part::Section => articles.last_mut()?.sections.last_mut()?.clauses.push(clause),
+        Part::Article => return None,
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanonicalizeOptions, SemanticHash};
+
+    const CONSTITUTION: &str = include_str!("../../../../constitution/constitution_v2.1.md");
+
+    #[test]
+    fn test_addresses_round_trip() {
+        for text in ["article-3", "article-3.1", "article-12.4.2"] {
+            assert_eq!(text.parse::<Address>().unwrap().to_string(), text);
+        }
+        let address: Address = "article-3.1.2".parse().unwrap();
+        assert_eq!(address, Address::clause(3, 1, 2));
+        assert_eq!(address.article_id(), "III");
+        assert_eq!(address.parent(), Some(Address::section(3, 1)));
+        assert_eq!(Address::section(3, 1).parent(), Some(Address::article(3)));
+        assert!(Address::article(3).contains(&address) && Address::section(3, 1).contains(&address));
+        assert!(!address.contains(&Address::section(3, 1)) && !Address::section(3, 2).contains(&address));
+        assert_eq!(Address::of_article("III"), Some(Address::article(3)));
+        for text in ["article-0", "article-03.1", "article-3.", "article-3.1.2.1", "Article-III.1", "article-4000"] {
+            assert!(text.parse::<Address>().is_err(), "{}", text);
+        }
+        assert_eq!((from_roman("XII"), from_roman("IIII"), from_roman("")), (Some(12), None, None));
+        assert_eq!(roman(1994), "MCMXCIV");
+    }
+
+    #[test]
+    fn test_parses_the_constitution() {
+        let constitution = parse_markdown(CONSTITUTION).unwrap();
+        assert_eq!(constitution.len(), 12);
+        let header = constitution.to_value();
+        assert_eq!(header["version"], "2.1");
+        assert_eq!(header["title"], "The Constitutional Protocol for Multi-AI Democratic Collaboration");
+        assert_eq!(header["preamble"].as_array().unwrap().len(), 5);
+
+        let article = Article::from_value(constitution.article("V").unwrap()).unwrap();
+        assert_eq!(article.title, "THE IMMUTABLE ARCHIVE");
+        let section = article.section(1).unwrap();
+        assert_eq!(section.title, "Required Elements");
+        assert_eq!(section.clauses[0], "Every contract or constitutional action must include:");
+        assert_eq!(section.clauses[1], "Pre-state hash");
+        assert_eq!(Article::from_value(&article.to_value()).unwrap(), article);
+
+        // Each address resolves to its own value and hash
+        let options = CanonicalizeOptions::new();
+        let clause = Address::clause(5, 1, 2);
+        assert_eq!(constitution.resolve(&clause), Some(&json!("Pre-state hash")));
+        let expected = SemanticHash::compute(&json!({"clause": "Pre-state hash"}), &options).unwrap();
+        assert_eq!(constitution.hash_at(&clause, &options).unwrap(), expected);
+        let addresses = constitution.addresses().unwrap();
+        assert_eq!(addresses[0], Address::article(1));
+        assert_eq!(addresses[1], Address::section(1, 1));
+        assert!(addresses.contains(&Address::clause(12, 4, 1)));
+        assert!(constitution.hash_at(&Address::section(5, 9), &options).is_err());
+    }
+
+    #[test]
+    fn test_malformed_markdown() {
+        let err = parse_markdown("## ARTICLE I — Definitions\n\n### 2.1 Agents\n").unwrap_err();
+        assert!(err.to_string().contains("Line 3"), "{}", err);
+        assert!(parse_markdown("## ARTICLE IIII — Definitions\n").is_err());
+        assert!(parse_markdown("## ARTICLE I — Definitions\n\nUnsectioned text\n").is_err());
+        assert!(parse_markdown("Text before any heading\n").is_err());
+        let duplicate = "## ARTICLE I — A\n### 1.1 B\n### 1.1 C\n";
+        assert!(parse_markdown(duplicate).is_err());
+        assert!(parse_markdown("## ARTICLE I — A\n### 1.1 B\n\nText\n").is_ok());
+    }
+}
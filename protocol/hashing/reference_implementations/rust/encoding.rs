@@ -0,0 +1,217 @@
+/// encoding.rs - Text encodings for digests
+///
+/// Hashes are lowercase hex by default, as canonicalizer.py and canonicalizer.js produce
+/// them. Systems that want shorter identifiers can ask for base64url or base58btc,
+/// written as multibase strings (a one-character prefix naming the base: `u` for
+/// base64url without padding, `z` for base58btc) so a verifier can tell which it was given.
+
+use crate::{ConstitutionalError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Encoding of a digest returned by the hashing APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    /// Lowercase hex with no prefix (default).
+    #[default]
+    Hex,
+    /// Multibase base64url (RFC 4648 5, no padding), prefixed with `u`.
+    Base64Url,
+    /// Multibase base58btc (the Bitcoin alphabet), prefixed with `z`.
+    Base58Btc,
+    /// The digest bytes themselves; only `semantic_hash_encoded` can return these.
+    Binary,
+}
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const BASE58BTC: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+impl Encoding {
+    /// Name of the encoding in hash envelopes: `hex`, `base64url`, `base58btc` or `binary`.
+    pub fn identifier(self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base58Btc => "base58btc",
+            Encoding::Binary => "binary",
+        }
+    }
+
+    /// Encode a digest as bytes: ASCII text, or the digest itself for `Binary`.
+    pub fn encode(self, digest: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Binary => digest.to_vec(),
+            text => text.encode_text(digest).unwrap_or_default().into_bytes(),
+        }
+    }
+
+    /// Encode a digest as text, or None for `Binary`.
+    pub fn encode_text(self, digest: &[u8]) -> Option<String> {
+        match self {
+            Encoding::Hex => Some(crate::algorithm::hex(digest)),
+            Encoding::Base64Url => Some(format!("u{}", base64url(digest))),
+            Encoding::Base58Btc => Some(format!("z{}", base58btc(digest))),
+            Encoding::Binary => None,
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = ConstitutionalError;
+
+    fn from_str(identifier: &str) -> Result<Encoding> {
+        match identifier {
+            "hex" => Ok(Encoding::Hex),
+            "base64url" => Ok(Encoding::Base64Url),
+            "base58btc" => Ok(Encoding::Base58Btc),
+            "binary" => Ok(Encoding::Binary),
+            _ => Err(ConstitutionalError::HashingError(format!("Unknown digest encoding {:?}", identifier))),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.identifier())
+    }
+}
+
+/// Encode a digest as text for a string-returning API.
+pub(crate) fn digest_text(encoding: Encoding, digest: &[u8]) -> Result<String> {
+    encoding.encode_text(digest).ok_or_else(|| {
+        ConstitutionalError::HashingError(
+            "Binary encoding has no text form; use semantic_hash_encoded for raw digests".to_string(),
+        )
+    })
+}
+
+/// Decode a digest of `len` bytes written in any text encoding: bare hex in either case,
+/// or a multibase string prefixed `f` (hex), `u` (base64url) or `z` (base58btc).
+/// None if the text is in none of them or decodes to the wrong length.
+pub(crate) fn decode_digest(text: &str, len: usize) -> Option<Vec<u8>> {
+    let decoded = if text.len() == len * 2 { crate::algorithm::unhex(text) } else { decode_multibase(text) };
+    decoded.filter(|digest| digest.len() == len)
+}
+
+/// Decode a multibase string prefixed `f`, `u` or `z`, of any length.
+pub(crate) fn decode_multibase(text: &str) -> Option<Vec<u8>> {
+    match text.split_at_checked(1)? {
+        ("f", rest) => crate::algorithm::unhex(rest),
+        ("u", rest) => unbase64url(rest),
+        ("z", rest) => unbase58btc(rest),
+        _ => None,
+    }
+}
+
+pub(crate) fn base64url(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+pub(crate) fn unbase64url(text: &str) -> Option<Vec<u8>> {
+    // A lone trailing character carries fewer than 8 bits
+    if text.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64URL.iter().position(|a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    // Reject non-canonical text whose unused low bits are set
+    (base64url(&out) == text).then_some(out)
+}
+
+fn base58btc(bytes: &[u8]) -> String {
+    // Little-endian base-58 digits of the big-endian number `bytes`
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in bytes {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    // Each leading zero byte is written as a leading '1'
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n('1', zeros).chain(digits.iter().rev().map(|d| BASE58BTC[*d as usize] as char)).collect()
+}
+
+fn unbase58btc(text: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+    for c in text.bytes() {
+        let mut carry = BASE58BTC.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = text.bytes().take_while(|c| *c == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_round_trip() {
+        // RFC 4648 10 and the base58btc examples of draft-msporny-base58
+        assert_eq!(base64url(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64url(b"fooba"), "Zm9vYmE");
+        assert_eq!(base64url(&[0xfb, 0xff]), "-_8");
+        assert_eq!(base58btc(b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+        assert_eq!(base58btc(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+
+        let digest: Vec<u8> = (0u8..32).map(|i| i.wrapping_mul(37)).collect();
+        for encoding in [Encoding::Hex, Encoding::Base64Url, Encoding::Base58Btc] {
+            let text = encoding.encode_text(&digest).unwrap();
+            assert_eq!(decode_digest(&text, 32), Some(digest.clone()), "{}", text);
+            assert_eq!(encoding.encode(&digest), text.into_bytes());
+        }
+        assert_eq!(decode_digest(&crate::algorithm::hex(&digest).to_uppercase(), 32), Some(digest.clone()));
+        assert_eq!(decode_digest(&format!("f{}", crate::algorithm::hex(&digest)), 32), Some(digest.clone()));
+        assert_eq!(Encoding::Binary.encode(&digest), digest);
+        assert_eq!(Encoding::Binary.encode_text(&digest), None);
+    }
+
+    #[test]
+    fn test_malformed_digests_rejected() {
+        let digest = [0xabu8; 32];
+        let base58 = Encoding::Base58Btc.encode_text(&digest).unwrap();
+        for text in [
+            "",
+            "xyz",
+            &crate::algorithm::hex(&digest[..31]),
+            &base58.replace('z', "0"),
+            &Encoding::Base58Btc.encode_text(&digest[..31]).unwrap(),
+            "uq6urq6urq6urq6urq6urq6urq6urq6urq6urq6urq6v",
+        ] {
+            assert_eq!(decode_digest(text, 32), None, "{:?}", text);
+        }
+    }
+}
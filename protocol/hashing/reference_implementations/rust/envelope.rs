@@ -0,0 +1,226 @@
+/// envelope.rs - Semantic hashes that carry how they were computed
+///
+/// A bare digest says nothing about the algorithm, canonicalization version or encoding
+/// it was produced under, so a verifier has to be told out of band. A `HashEnvelope`
+/// records all three next to the digest and serializes as a flat object of strings:
+///
+/// ```json
+/// {"algorithm":"sha256","canon_version":"ocp-canon/v1","encoding":"hex","digest":"9f86..."}
+/// ```
+///
+/// Stored hashes predating envelopes are plain strings; `verify_semantic_hash_envelope`
+/// accepts either form, so a ledger can migrate one record at a time.
+
+use crate::encoding::digest_text;
+use crate::{
+    semantic_digest, verify_semantic_hash_with, CanonVersion, CanonicalizeOptions, ConstitutionalError, Encoding,
+    HashAlgorithm, Result, SemanticHash,
+};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+const FIELDS: &[&str] = &["algorithm", "canon_version", "encoding", "digest"];
+
+/// A semantic hash together with the algorithm, canonicalization version and encoding
+/// it was computed under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashEnvelope {
+    /// Hash function applied to the canonical bytes
+    pub algorithm: HashAlgorithm,
+    /// Version whose domain tag was hashed ahead of the canonical form
+    pub canon_version: CanonVersion,
+    /// Text encoding of `digest`
+    pub encoding: Encoding,
+    /// The digest, written in `encoding`
+    pub digest: String,
+}
+
+impl HashEnvelope {
+    /// Check data against this envelope, hashing under the envelope's version and
+    /// algorithm whatever `options` selects.
+    pub fn verify(&self, data: &Value, options: &CanonicalizeOptions) -> Result<bool> {
+        let options = options.clone().canon_version(self.canon_version).hash_algorithm(self.algorithm);
+        verify_semantic_hash_with(data, &self.digest, &options)
+    }
+}
+
+impl Serialize for HashEnvelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("HashEnvelope", FIELDS.len())?;
+        state.serialize_field("algorithm", self.algorithm.identifier())?;
+        state.serialize_field("canon_version", self.canon_version.identifier())?;
+        state.serialize_field("encoding", self.encoding.identifier())?;
+        state.serialize_field("digest", &self.digest)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HashEnvelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<HashEnvelope, D::Error> {
+        deserializer.deserialize_struct("HashEnvelope", FIELDS, EnvelopeVisitor)
+    }
+}
+
+struct EnvelopeVisitor;
+
+impl<'de> Visitor<'de> for EnvelopeVisitor {
+    type Value = HashEnvelope;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a hash envelope object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<HashEnvelope, A::Error> {
+        let mut fields: [Option<String>; 4] = Default::default();
+        while let Some(key) = map.next_key::<String>()? {
+            let Some(index) = FIELDS.iter().position(|field| *field == key) else {
+                return Err(de::Error::unknown_field(&key, FIELDS));
+            };
+            if fields[index].is_some() {
+                return Err(de::Error::custom(format_args!("duplicate field `{}`", key)));
+            }
+            fields[index] = Some(map.next_value()?);
+        }
+        let [algorithm, canon_version, encoding, digest] = fields;
+        let field = |value: Option<String>, index: usize| value.ok_or_else(|| de::Error::missing_field(FIELDS[index]));
+        Ok(HashEnvelope {
+            algorithm: field(algorithm, 0)?.parse().map_err(de::Error::custom)?,
+            canon_version: field(canon_version, 1)?.parse().map_err(de::Error::custom)?,
+            encoding: field(encoding, 2)?.parse().map_err(de::Error::custom)?,
+            digest: field(digest, 3)?,
+        })
+    }
+}
+
+/// An expected hash as stored: an envelope, or a plain string.
+#[derive(Debug, Clone, Copy)]
+pub enum ExpectedHash<'a> {
+    /// An envelope naming its own algorithm, version and encoding.
+    Envelope(&'a HashEnvelope),
+    /// A typed hash, checked under the options' algorithm and version.
+    Hash(SemanticHash),
+    /// A digest string as `verify_semantic_hash_with` reads it, or an envelope
+    /// serialized as JSON (anything starting with `{`).
+    Text(&'a str),
+}
+
+impl<'a> From<&'a HashEnvelope> for ExpectedHash<'a> {
+    fn from(envelope: &'a HashEnvelope) -> Self {
+        ExpectedHash::Envelope(envelope)
+    }
+}
+
+impl From<SemanticHash> for ExpectedHash<'_> {
+    fn from(hash: SemanticHash) -> Self {
+        ExpectedHash::Hash(hash)
+    }
+}
+
+impl<'a> From<&'a str> for ExpectedHash<'a> {
+    fn from(text: &'a str) -> Self {
+        ExpectedHash::Text(text)
+    }
+}
+
+impl<'a> From<&'a String> for ExpectedHash<'a> {
+    fn from(text: &'a String) -> Self {
+        ExpectedHash::Text(text)
+    }
+}
+
+/// Calculate the semantic hash of data as an envelope recording how it was computed.
+///
+/// # Arguments
+/// * `data` - Input JSON value to hash
+/// * `options` - Canonicalization options, including the algorithm, version and encoding
+///
+/// # Returns
+/// The envelope; a HashingError under `Encoding::Binary`, which has no text form
+pub fn semantic_hash_envelope(data: &Value, options: &CanonicalizeOptions) -> Result<HashEnvelope> {
+    Ok(HashEnvelope {
+        algorithm: options.hash_algorithm,
+        canon_version: options.canon_version,
+        encoding: options.hash_encoding,
+        digest: digest_text(options.hash_encoding, &semantic_digest(data, options)?)?,
+    })
+}
+
+/// Verify data against an expected hash stored either as an envelope or as a plain
+/// (legacy) digest string.
+///
+/// # Arguments
+/// * `data` - Input JSON value to verify
+/// * `expected` - A `HashEnvelope`, a `SemanticHash`, a serialized envelope, or a digest string
+/// * `options` - Canonicalization options; an envelope overrides their algorithm and version
+///
+/// # Returns
+/// true if hash matches, false otherwise; a HashingError for a malformed envelope
+pub fn verify_semantic_hash_envelope<'a>(
+    data: &Value,
+    expected: impl Into<ExpectedHash<'a>>,
+    options: &CanonicalizeOptions,
+) -> Result<bool> {
+    match expected.into() {
+        ExpectedHash::Envelope(envelope) => envelope.verify(data, options),
+        ExpectedHash::Hash(hash) => hash.verify(data, options),
+        ExpectedHash::Text(text) if text.trim_start().starts_with('{') => {
+            let envelope: HashEnvelope = serde_json::from_str(text)
+                .map_err(|e| ConstitutionalError::HashingError(format!("Malformed hash envelope: {}", e)))?;
+            envelope.verify(data, options)
+        }
+        ExpectedHash::Text(text) => verify_semantic_hash_with(data, text, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic_hash;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let data = json!({"action_id": "001", "agent": "agent-7"});
+        let options = CanonicalizeOptions::new()
+            .canon_version(CanonVersion::V1)
+            .hash_algorithm(HashAlgorithm::Sha512)
+            .hash_encoding(Encoding::Base58Btc);
+        let envelope = semantic_hash_envelope(&data, &options).unwrap();
+        assert_eq!(envelope.algorithm, HashAlgorithm::Sha512);
+        assert!(envelope.digest.starts_with('z'));
+
+        let stored = serde_json::to_string(&envelope).unwrap();
+        assert!(stored.starts_with(r#"{"algorithm":"sha512","canon_version":"ocp-canon/v1","encoding":"base58btc","#));
+        assert_eq!(serde_json::from_str::<HashEnvelope>(&stored).unwrap(), envelope);
+
+        // Envelopes verify under their own settings, whatever the caller's options
+        let defaults = CanonicalizeOptions::new();
+        assert!(verify_semantic_hash_envelope(&data, &envelope, &defaults).unwrap());
+        assert!(verify_semantic_hash_envelope(&data, &stored, &defaults).unwrap());
+        assert!(!verify_semantic_hash_envelope(&json!({"action_id": "002"}), &envelope, &defaults).unwrap());
+        assert!(verify_semantic_hash_envelope(&data, &semantic_hash(&data).unwrap(), &defaults).unwrap());
+        assert!(verify_semantic_hash_envelope(&data, SemanticHash::of(&data).unwrap(), &defaults).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_envelopes_rejected() {
+        let data = json!({"a": 1});
+        let options = CanonicalizeOptions::new();
+        for stored in [
+            r#"{"algorithm":"md5","canon_version":"legacy","encoding":"hex","digest":"00"}"#,
+            r#"{"algorithm":"sha256","canon_version":"legacy","encoding":"hex"}"#,
+            r#"{"algorithm":"sha256","canon_version":"v9","encoding":"hex","digest":"00"}"#,
+            r#"{"algorithm":"sha256","canon_version":"legacy","encoding":"hex","digest":"00","extra":1}"#,
+        ] {
+            assert!(
+                matches!(verify_semantic_hash_envelope(&data, stored, &options), Err(ConstitutionalError::HashingError(_))),
+                "{}",
+                stored
+            );
+        }
+        assert!(semantic_hash_envelope(&data, &options.hash_encoding(Encoding::Binary)).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+/// escape.rs - Canonical JSON string escaping
+///
+/// JSON allows several spellings of the same string (`"/"` and `"\/"`, `"\n"` and
+/// `"\u000a"`), so canonical output cannot rely on a serializer's defaults. Every string
+/// value and object key, under every profile, is written through this table:
+///
+/// | Character                       | Written as                                             |
+/// |---------------------------------|--------------------------------------------------------|
+/// | `"` (U+0022)                    | `\"`                                                   |
+/// | `\` (U+005C)                    | `\\`                                                   |
+/// | U+0008, U+0009, U+000A, U+000C, U+000D | `\b` `\t` `\n` `\f` `\r`, or `\u00xx` with `ControlEscaping::Unicode` |
+/// | other U+0000 to U+001F          | `\u00xx`                                               |
+/// | `/` (U+002F)                    | `/`, or `\/` with `escape_solidus`                     |
+/// | U+007F and non-ASCII            | as-is (UTF-8), or `\uxxxx` with `ensure_ascii`; astral characters as a surrogate pair |
+/// | anything else                   | as-is                                                  |
+///
+/// Hex digits are always lowercase. The defaults are the minimal escaping required by
+/// RFC 8785 3.2.2.2, which is also what Python's `json.dumps(ensure_ascii=False)` and
+/// JavaScript's `JSON.stringify` produce.
+
+/// How the five control characters with a short escape form are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlEscaping {
+    /// `\b`, `\t`, `\n`, `\f` and `\r` (default, required by RFC 8785).
+    #[default]
+    Short,
+    /// `\u0008`, `\u0009`, `\u000a`, `\u000c` and `\u000d`, like every other control character.
+    Unicode,
+}
+
+/// The configurable rows of the escaping table, held by `CanonicalizeOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Escaping {
+    pub(crate) control: ControlEscaping,
+    pub(crate) solidus: bool,
+    pub(crate) ascii_only: bool,
+}
+
+/// Write `s` as a quoted JSON string according to the escaping table.
+pub(crate) fn write_string(s: &str, escaping: Escaping, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '/' if escaping.solidus => out.push_str("\\/"),
+            '\u{08}' | '\t' | '\n' | '\u{0c}' | '\r' if escaping.control == ControlEscaping::Short => {
+                out.push_str(match c {
+                    '\u{08}' => "\\b",
+                    '\t' => "\\t",
+                    '\n' => "\\n",
+                    '\u{0c}' => "\\f",
+                    _ => "\\r",
+                });
+            }
+            '\u{00}'..='\u{1f}' => push_unicode_escape(c, out),
+            '\u{7f}'..=char::MAX if escaping.ascii_only => push_unicode_escape(c, out),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_unicode_escape(c: char, out: &mut String) {
+    let mut units = [0u16; 2];
+    for unit in c.encode_utf16(&mut units) {
+        out.push_str(&format!("\\u{:04x}", unit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escaped(s: &str, escaping: Escaping) -> String {
+        let mut out = String::new();
+        write_string(s, escaping, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_escaping_table() {
+        let minimal = Escaping::default();
+        let cases = [
+            ("\"\\", r#""\"\\""#),
+            ("\u{08}\t\n\u{0c}\r", r#""\b\t\n\f\r""#),
+            ("\u{00}\u{1f}", r#""\u0000\u001f""#),
+            ("a/b", r#""a/b""#),
+            ("\u{7f}caf\u{e9}\u{1f600}", "\"\u{7f}caf\u{e9}\u{1f600}\""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(escaped(input, minimal), expected);
+        }
+
+        let explicit = Escaping { control: ControlEscaping::Unicode, solidus: true, ascii_only: true };
+        let cases = [
+            ("\u{08}\t\n\u{0c}\r", r#""\u0008\u0009\u000a\u000c\u000d""#),
+            ("a/b", r#""a\/b""#),
+            ("\u{7f}caf\u{e9}\u{1f600}", r#""\u007fcaf\u00e9\ud83d\ude00""#),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(escaped(input, explicit), expected);
+        }
+    }
+
+    #[test]
+    fn test_default_escaping_matches_serde_json() {
+        let text: String = (0u32..0x250)
+            .chain([0x2028, 0x2029, 0xfeff, 0x1f600])
+            .filter_map(char::from_u32)
+            .collect();
+        assert_eq!(escaped(&text, Escaping::default()), serde_json::to_string(&text).unwrap());
+    }
+}
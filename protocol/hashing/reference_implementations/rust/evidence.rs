@@ -0,0 +1,212 @@
+/// evidence.rs - Typed evidence pointers
+///
+/// Evidence names where it is with a pointer string. The protocol uses a few kinds:
+///
+/// ```text
+/// archive://0000001                  an entry of the immutable archive
+/// sha256:9f86d081...                 a blob, by the digest of its bytes
+/// ocp:sha256:9f86d081...             a protocol object, by its content ID
+/// Article-III.1                      a clause of the constitution, or Article-III for all of it
+/// https://example.org/report.pdf     a document on the web
+/// ```
+///
+/// `EvidencePointer` parses a pointer into its kind, so a malformed one is caught where
+/// it is read rather than when someone tries to follow it. Parsing normalizes: scheme,
+/// algorithm and hex digits are lowercased, `article-` becomes `Article-` and clause
+/// numbers lose leading zeros, so `Display` gives one spelling per pointer. The archive
+/// entry, article and URL are otherwise kept as written.
+
+use crate::content_id::parse_content_id;
+use crate::{ConstitutionalError, Result, CONTENT_ID_PREFIX};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const ARCHIVE_SCHEME: &str = "archive://";
+const ARTICLE_PREFIX: &str = "Article-";
+
+/// Where a piece of evidence is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EvidencePointer {
+    /// An entry of the immutable archive, `archive://<entry>`
+    Archive(String),
+    /// Bytes with a digest, `<algorithm>:<hex>`
+    Digest { algorithm: String, digest: String },
+    /// A protocol object with a content ID, `ocp:<algorithm>:<hex>`
+    ContentId { algorithm: String, digest: String },
+    /// A constitutional article, or one of its clauses: `Article-<article_id>[.<clause>]`
+    Citation { article: String, clause: Option<u32> },
+    /// A document at an `http` or `https` URL
+    Url(String),
+}
+
+impl EvidencePointer {
+    /// Parse and normalize a pointer.
+    ///
+    /// # Returns
+    /// The pointer, or a ProtocolError naming the pointer if it is of no known kind or is
+    /// malformed for its kind
+    pub fn parse(pointer: &str) -> Result<EvidencePointer> {
+        Self::read(pointer).ok_or_else(|| {
+            ConstitutionalError::ProtocolError(format!("Invalid evidence pointer {:?}", pointer))
+        })
+    }
+
+    fn read(pointer: &str) -> Option<EvidencePointer> {
+        if pointer.is_empty() || pointer.chars().any(char::is_whitespace) {
+            return None;
+        }
+        let lower = pointer.to_ascii_lowercase();
+        if let Some(entry) = strip_prefix_ignore_case(pointer, ARCHIVE_SCHEME) {
+            let valid = !entry.is_empty() && entry.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b));
+            return valid.then(|| EvidencePointer::Archive(entry.to_string()));
+        }
+        if lower.starts_with(CONTENT_ID_PREFIX) {
+            let (algorithm, digest) = parse_content_id(&lower)?;
+            return Some(EvidencePointer::ContentId { algorithm: algorithm.to_string(), digest: digest.to_string() });
+        }
+        for scheme in ["http://", "https://"] {
+            if let Some(rest) = strip_prefix_ignore_case(pointer, scheme) {
+                let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+                return (!host.is_empty()).then(|| EvidencePointer::Url(format!("{}{}", scheme, rest)));
+            }
+        }
+        if let Some(cited) = strip_prefix_ignore_case(pointer, ARTICLE_PREFIX) {
+            let (article, clause) = match cited.split_once('.') {
+                Some((article, clause)) => {
+                    let all_digits = !clause.is_empty() && clause.bytes().all(|b| b.is_ascii_digit());
+                    (article, Some(clause.parse().ok().filter(|_| all_digits)?))
+                }
+                None => (cited, None),
+            };
+            let valid = !article.is_empty() && article.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+            return valid.then(|| EvidencePointer::Citation { article: article.to_string(), clause });
+        }
+        let (algorithm, digest) = lower.split_once(':')?;
+        let valid = !algorithm.is_empty()
+            && algorithm.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'-'))
+            && !digest.is_empty()
+            && digest.len() % 2 == 0
+            && digest.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        valid.then(|| EvidencePointer::Digest { algorithm: algorithm.to_string(), digest: digest.to_string() })
+    }
+
+    /// The algorithm and hex digest the pointed-to content must hash to, for the kinds
+    /// that carry one.
+    pub fn expected_digest(&self) -> Option<(&str, &str)> {
+        match self {
+            EvidencePointer::Digest { algorithm, digest } | EvidencePointer::ContentId { algorithm, digest } => {
+                Some((algorithm, digest))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+impl fmt::Display for EvidencePointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvidencePointer::Archive(entry) => write!(f, "{}{}", ARCHIVE_SCHEME, entry),
+            EvidencePointer::Digest { algorithm, digest } => write!(f, "{}:{}", algorithm, digest),
+            EvidencePointer::ContentId { algorithm, digest } => {
+                write!(f, "{}{}:{}", CONTENT_ID_PREFIX, algorithm, digest)
+            }
+            EvidencePointer::Citation { article, clause: Some(clause) } => {
+                write!(f, "{}{}.{}", ARTICLE_PREFIX, article, clause)
+            }
+            EvidencePointer::Citation { article, clause: None } => write!(f, "{}{}", ARTICLE_PREFIX, article),
+            EvidencePointer::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+impl FromStr for EvidencePointer {
+    type Err = ConstitutionalError;
+
+    fn from_str(pointer: &str) -> Result<EvidencePointer> {
+        EvidencePointer::parse(pointer)
+    }
+}
+
+impl Serialize for EvidencePointer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for EvidencePointer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<EvidencePointer, D::Error> {
+        EvidencePointer::parse(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_kinds() {
+        let kinds = [
+            ("archive://0000001", EvidencePointer::Archive("0000001".to_string())),
+            (
+                "sha256:abc123def456",
+                EvidencePointer::Digest { algorithm: "sha256".to_string(), digest: "abc123def456".to_string() },
+            ),
+            (
+                "ocp:sha3-256:abcd",
+                EvidencePointer::ContentId { algorithm: "sha3-256".to_string(), digest: "abcd".to_string() },
+            ),
+            ("Article-III.1", EvidencePointer::Citation { article: "III".to_string(), clause: Some(1) }),
+            ("Article-VII", EvidencePointer::Citation { article: "VII".to_string(), clause: None }),
+            ("https://example.org/r.pdf", EvidencePointer::Url("https://example.org/r.pdf".to_string())),
+        ];
+        for (text, pointer) in kinds {
+            assert_eq!(EvidencePointer::parse(text).unwrap(), pointer, "{}", text);
+            assert_eq!(pointer.to_string(), text);
+        }
+        assert_eq!(EvidencePointer::parse("sha256:ab").unwrap().expected_digest(), Some(("sha256", "ab")));
+        assert_eq!(EvidencePointer::parse("Article-III").unwrap().expected_digest(), None);
+    }
+
+    #[test]
+    fn test_pointers_normalize() {
+        for (text, normal) in [
+            ("SHA256:ABC123", "sha256:abc123"),
+            ("OCP:SHA256:ABCD", "ocp:sha256:abcd"),
+            ("ARCHIVE://0000001", "archive://0000001"),
+            ("article-III.01", "Article-III.1"),
+            ("HTTPS://example.org/Report", "https://example.org/Report"),
+        ] {
+            let pointer: EvidencePointer = text.parse().unwrap();
+            assert_eq!(pointer.to_string(), normal);
+            assert_eq!(serde_json::to_value(&pointer).unwrap(), normal);
+        }
+    }
+
+    #[test]
+    fn test_malformed_pointers_fail() {
+        for text in [
+            "",
+            "archive://",
+            "archive://a b",
+            "sha256:abc",
+            "sha256:xyz1",
+            "sha256:",
+            "ocp:sha256",
+            "Article-",
+            "Article-III.",
+            "Article-III.one",
+            "https://",
+            "see the report",
+        ] {
+            assert!(EvidencePointer::parse(text).is_err(), "{:?}", text);
+        }
+        assert!(serde_json::from_str::<EvidencePointer>("\"sha256:abc\"").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+/// frontier.rs - Append-only Merkle tree kept as its frontier
+///
+/// The ledger appends one contract at a time, and rebuilding a `MerkleTree` for every
+/// new root costs O(n). An RFC 6962 tree of `n` leaves is a run of perfect subtrees, one
+/// per set bit of `n`, largest first, and its root folds their roots from the right.
+/// `IncrementalMerkleTree` keeps only those subtree roots, the frontier. An append merges
+/// equal-sized subtrees the way a binary increment carries, so appends and roots take
+/// O(log n) time and the state is O(log n) hashes. Roots are identical to
+/// `MerkleTree::root` over the same leaves.
+///
+/// A `MerkleSnapshot` is the frontier in a form the ledger can store and restore:
+///
+/// ```json
+/// {"algorithm":"sha256","size":5,"frontier":["9c1f...","07ab..."]}
+/// ```
+
+use crate::merkle::{check_algorithm, empty_root, hash_leaf, hash_node};
+use crate::objects::take;
+use crate::{ConstitutionalError, HashAlgorithm, Result, SemanticHash};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const SNAPSHOT_FIELDS: &[&str] = &["algorithm", "size", "frontier"];
+
+/// Append-only Merkle tree that keeps only the roots of its perfect subtrees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalMerkleTree {
+    algorithm: HashAlgorithm,
+    size: u64,
+    /// Roots of the perfect subtrees, largest first
+    frontier: Vec<SemanticHash>,
+}
+
+impl IncrementalMerkleTree {
+    /// An empty tree whose nodes are hashed with `algorithm`.
+    ///
+    /// # Returns
+    /// The tree, or a HashingError if the algorithm's digest is not 256 bits
+    pub fn new(algorithm: HashAlgorithm) -> Result<IncrementalMerkleTree> {
+        check_algorithm(algorithm)?;
+        Ok(IncrementalMerkleTree { algorithm, size: 0, frontier: Vec::new() })
+    }
+
+    /// Append a leaf, the semantic hash of the next object in the log.
+    ///
+    /// # Returns
+    /// The root of the tree with the leaf appended
+    pub fn append(&mut self, leaf: &SemanticHash) -> SemanticHash {
+        let mut node = hash_leaf(self.algorithm, leaf);
+        // Each trailing one bit of the old size is a perfect subtree the new leaf completes
+        let mut carries = self.size.trailing_ones();
+        while carries > 0 {
+            let left = self.frontier.pop().expect("one frontier node per set bit of the size");
+            node = hash_node(self.algorithm, &left, &node);
+            carries -= 1;
+        }
+        self.frontier.push(node);
+        self.size += 1;
+        self.root()
+    }
+
+    /// The current root; for an empty tree, the hash of no bytes.
+    pub fn root(&self) -> SemanticHash {
+        let mut nodes = self.frontier.iter().rev();
+        match nodes.next() {
+            Some(last) => nodes.fold(*last, |right, left| hash_node(self.algorithm, left, &right)),
+            None => empty_root(self.algorithm),
+        }
+    }
+
+    /// Number of leaves appended.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// Whether no leaf was appended.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The tree's state, for storing and later `restore`.
+    pub fn snapshot(&self) -> MerkleSnapshot {
+        MerkleSnapshot { algorithm: self.algorithm, size: self.size, frontier: self.frontier.clone() }
+    }
+
+    /// Resume a tree from a snapshot.
+    ///
+    /// # Returns
+    /// The tree, or a HashingError if the algorithm is not 256-bit or the frontier does
+    /// not have one node per set bit of the size
+    pub fn restore(snapshot: MerkleSnapshot) -> Result<IncrementalMerkleTree> {
+        check_algorithm(snapshot.algorithm)?;
+        if snapshot.frontier.len() != snapshot.size.count_ones() as usize {
+            return Err(ConstitutionalError::HashingError(format!(
+                "A Merkle snapshot of {} leaves needs {} frontier nodes, not {}",
+                snapshot.size,
+                snapshot.size.count_ones(),
+                snapshot.frontier.len()
+            )));
+        }
+        Ok(IncrementalMerkleTree { algorithm: snapshot.algorithm, size: snapshot.size, frontier: snapshot.frontier })
+    }
+}
+
+/// Stored state of an `IncrementalMerkleTree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleSnapshot {
+    /// Algorithm the tree's nodes are hashed with
+    pub algorithm: HashAlgorithm,
+    /// Number of leaves appended
+    pub size: u64,
+    /// Roots of the perfect subtrees, largest first
+    pub frontier: Vec<SemanticHash>,
+}
+
+impl Serialize for MerkleSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MerkleSnapshot", SNAPSHOT_FIELDS.len())?;
+        state.serialize_field("algorithm", self.algorithm.identifier())?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("frontier", &self.frontier)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for MerkleSnapshot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<MerkleSnapshot, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a Merkle snapshot object"));
+        };
+        if let Some(key) = map.keys().find(|key| !SNAPSHOT_FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, SNAPSHOT_FIELDS));
+        }
+        let algorithm: String = take(&mut map, "algorithm")?;
+        Ok(MerkleSnapshot {
+            algorithm: algorithm.parse().map_err(de::Error::custom)?,
+            size: take(&mut map, "size")?,
+            frontier: take(&mut map, "frontier")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+    use serde_json::json;
+
+    #[test]
+    fn test_appends_match_full_rebuild() {
+        let leaves: Vec<_> = (0..40).map(|i| SemanticHash::of(&json!({"ledger_entry": i})).unwrap()).collect();
+        let mut tree = IncrementalMerkleTree::new(HashAlgorithm::Sha256).unwrap();
+        assert_eq!(tree.root(), MerkleTree::from_leaves(&[], HashAlgorithm::Sha256).unwrap().root());
+        for (n, leaf) in leaves.iter().enumerate() {
+            let root = tree.append(leaf);
+            assert_eq!(root, MerkleTree::from_leaves(&leaves[..=n], HashAlgorithm::Sha256).unwrap().root(), "{}", n + 1);
+            assert_eq!(tree.snapshot().frontier.len(), (n + 1).count_ones() as usize);
+        }
+        assert_eq!(tree.len(), 40);
+        assert!(IncrementalMerkleTree::new(HashAlgorithm::Sha512).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let leaves: Vec<_> = (0..20).map(|i| SemanticHash::of(&json!({"ledger_entry": i})).unwrap()).collect();
+        let mut tree = IncrementalMerkleTree::new(HashAlgorithm::Sha256).unwrap();
+        for leaf in &leaves[..13] {
+            tree.append(leaf);
+        }
+        let stored = serde_json::to_string(&tree.snapshot()).unwrap();
+        assert!(stored.starts_with(r#"{"algorithm":"sha256","size":13,"frontier":[""#));
+
+        let mut resumed = IncrementalMerkleTree::restore(serde_json::from_str(&stored).unwrap()).unwrap();
+        assert_eq!(resumed, tree);
+        for leaf in &leaves[13..] {
+            tree.append(leaf);
+            resumed.append(leaf);
+        }
+        assert_eq!(resumed.root(), tree.root());
+
+        let mut truncated = tree.snapshot();
+        truncated.frontier.pop();
+        assert!(IncrementalMerkleTree::restore(truncated).is_err());
+        assert!(serde_json::from_str::<MerkleSnapshot>(r#"{"algorithm":"sha256","size":0}"#).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+/// hints.rs - Schema-driven canonicalization hints
+///
+/// A JSON Schema can state per path what a global option cannot: that
+/// `reasoning.steps` is an ordered sequence while `evidence` is a set, or that
+/// `signature` never belongs in the hashed form. Two annotations are read:
+///
+/// * `"x-ocp-ordered": true` on an array schema keeps the array in input order;
+///   `false` sorts it as a set under the total order of `ArraySortPolicy::Always`.
+/// * `"x-ocp-exclude": true` on any subschema removes the value it describes.
+///
+/// Subschemas are found through `properties`, `additionalProperties` and `items`
+/// (an object schema, applying to every element), following local `$ref`s such as
+/// `#/definitions/step`. Other keywords carry no hints.
+
+use crate::{ArraySortPolicy, ConstitutionalError, Result};
+use serde_json::Value;
+
+/// One step of a hinted path: a named member, or any member or array element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Hint {
+    ArraySort(ArraySortPolicy),
+    Exclude,
+}
+
+/// Per-path canonicalization hints extracted from annotated JSON Schema.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SchemaHints {
+    rules: Vec<(Vec<Segment>, Hint)>,
+}
+
+impl SchemaHints {
+    /// Collect the `x-ocp-ordered` and `x-ocp-exclude` annotations of a schema.
+    ///
+    /// # Arguments
+    /// * `schema` - A JSON Schema document describing the values to canonicalize
+    ///
+    /// # Returns
+    /// The hints, or a CanonicalizationError naming the schema location of an annotation
+    /// that is not a boolean or a `$ref` that cannot be resolved
+    pub fn from_schema(schema: &Value) -> Result<SchemaHints> {
+        let mut rules = Vec::new();
+        // (subschema, data path it describes, `$ref`s followed to reach it, schema location)
+        let mut stack: Vec<(&Value, Vec<Segment>, Vec<&str>, String)> =
+            vec![(schema, Vec::new(), Vec::new(), String::new())];
+
+        while let Some((node, path, refs, location)) = stack.pop() {
+            let Value::Object(node) = node else {
+                continue;
+            };
+
+            if let Some(reference) = node.get("$ref") {
+                let reference = reference.as_str().unwrap_or_default();
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| schema.pointer(pointer))
+                    .ok_or_else(|| invalid(&location, &format!("cannot resolve $ref {:?}", reference)))?;
+                // A recursive schema describes unboundedly deep data, so hints stop at the first
+                // repeated `$ref` and deeper levels fall back to the global options
+                if !refs.contains(&reference) {
+                    let mut refs = refs.clone();
+                    refs.push(reference);
+                    stack.push((target, path.clone(), refs, reference.trim_start_matches('#').to_string()));
+                }
+            }
+
+            if let Some(true) = annotation(node, "x-ocp-exclude", &location)? {
+                rules.push((path.clone(), Hint::Exclude));
+            }
+            match annotation(node, "x-ocp-ordered", &location)? {
+                Some(true) => rules.push((path.clone(), Hint::ArraySort(ArraySortPolicy::Never))),
+                Some(false) => rules.push((path.clone(), Hint::ArraySort(ArraySortPolicy::Always))),
+                None => {}
+            }
+
+            if let Some(Value::Object(properties)) = node.get("properties") {
+                for (name, subschema) in properties {
+                    let mut child = path.clone();
+                    child.push(Segment::Key(name.clone()));
+                    let location = format!("{}/properties/{}", location, name.replace('~', "~0").replace('/', "~1"));
+                    stack.push((subschema, child, refs.clone(), location));
+                }
+            }
+            for keyword in ["additionalProperties", "items"] {
+                if let Some(subschema) = node.get(keyword) {
+                    let mut child = path.clone();
+                    child.push(Segment::Any);
+                    stack.push((subschema, child, refs.clone(), format!("{}/{}", location, keyword)));
+                }
+            }
+        }
+
+        Ok(SchemaHints { rules })
+    }
+
+    /// Array policy hinted for the array at `path`, if any.
+    pub(crate) fn array_sort(&self, path: &[String]) -> Option<ArraySortPolicy> {
+        self.rules.iter().find_map(|(pattern, hint)| match hint {
+            Hint::ArraySort(policy) if matches(pattern, path) => Some(*policy),
+            _ => None,
+        })
+    }
+
+    /// Every array policy the hints can select.
+    pub(crate) fn array_policies(&self) -> impl Iterator<Item = ArraySortPolicy> + '_ {
+        self.rules.iter().filter_map(|(_, hint)| match hint {
+            Hint::ArraySort(policy) => Some(*policy),
+            Hint::Exclude => None,
+        })
+    }
+
+    /// True if the child `token` of the value at `parent` is hinted as excluded.
+    pub(crate) fn excludes(&self, parent: &[String], token: &str) -> bool {
+        self.rules.iter().any(|(pattern, hint)| {
+            *hint == Hint::Exclude
+                && pattern.len() == parent.len() + 1
+                && matches(&pattern[..parent.len()], parent)
+                && segment_matches(&pattern[parent.len()], token)
+        })
+    }
+}
+
+fn matches(pattern: &[Segment], path: &[String]) -> bool {
+    pattern.len() == path.len() && pattern.iter().zip(path).all(|(segment, token)| segment_matches(segment, token))
+}
+
+fn segment_matches(segment: &Segment, token: &str) -> bool {
+    match segment {
+        Segment::Key(name) => name == token,
+        Segment::Any => true,
+    }
+}
+
+fn annotation(node: &serde_json::Map<String, Value>, keyword: &str, location: &str) -> Result<Option<bool>> {
+    match node.get(keyword) {
+        None => Ok(None),
+        Some(Value::Bool(flag)) => Ok(Some(*flag)),
+        Some(other) => Err(invalid(location, &format!("{} must be a boolean, got {}", keyword, other))),
+    }
+}
+
+fn invalid(location: &str, reason: &str) -> ConstitutionalError {
+    ConstitutionalError::canonicalization(format!("Invalid schema hint at {:?}: {}", location, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_hints_from_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "signature": {"type": "string", "x-ocp-exclude": true},
+                "evidence": {"type": "array", "x-ocp-ordered": false, "items": {"$ref": "#/definitions/evidence"}},
+                "reasoning": {
+                    "type": "object",
+                    "properties": {"steps": {"type": "array", "x-ocp-ordered": true}}
+                }
+            },
+            "definitions": {
+                "evidence": {"type": "object", "properties": {"hash": {"x-ocp-exclude": true}}}
+            }
+        });
+        let hints = SchemaHints::from_schema(&schema).unwrap();
+
+        assert_eq!(hints.array_sort(&path(&["evidence"])), Some(ArraySortPolicy::Always));
+        assert_eq!(hints.array_sort(&path(&["reasoning", "steps"])), Some(ArraySortPolicy::Never));
+        assert_eq!(hints.array_sort(&path(&["reasoning"])), None);
+        assert!(hints.excludes(&[], "signature"));
+        assert!(hints.excludes(&path(&["evidence", "3"]), "hash"));
+        assert!(!hints.excludes(&path(&["reasoning"]), "signature"));
+    }
+
+    #[test]
+    fn test_recursive_and_invalid_schemas() {
+        let tree = json!({
+            "$ref": "#/definitions/node",
+            "definitions": {
+                "node": {
+                    "properties": {"children": {"x-ocp-ordered": true, "items": {"$ref": "#/definitions/node"}}}
+                }
+            }
+        });
+        let hints = SchemaHints::from_schema(&tree).unwrap();
+        assert_eq!(hints.array_sort(&path(&["children"])), Some(ArraySortPolicy::Never));
+
+        for schema in [
+            json!({"properties": {"a": {"x-ocp-ordered": "yes"}}}),
+            json!({"properties": {"a": {"$ref": "#/definitions/missing"}}}),
+        ] {
+            let err = SchemaHints::from_schema(&schema).unwrap_err();
+            assert!(err.to_string().contains("/properties/a"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+/// hmac.rs - Authenticated semantic hashes (HMAC-SHA256, RFC 2104)
+///
+/// A semantic hash can be recomputed by anyone who knows the canonicalization, so it
+/// proves integrity but not origin. An HMAC over the same canonical bytes can only be
+/// produced by holders of the tenant's key. The canonical form is streamed into the
+/// inner hash exactly as `semantic_hash_with` streams it into SHA-256, version tag first.
+///
+/// Key material lives in `HmacKey`, which never prints its bytes and wipes them on drop.
+
+use crate::secret::wipe;
+use crate::{ConstitutionalError, Result, Secret};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// SHA-256 block size in bytes.
+const BLOCK_SIZE: usize = 64;
+
+/// Shortest key accepted; RFC 2104 discourages keys shorter than the digest.
+const MIN_KEY_LEN: usize = 32;
+
+/// Secret key for authenticated semantic hashes.
+///
+/// Not `Clone`, so each copy of the key is an explicit decision; the bytes are
+/// overwritten when the key is dropped and never appear in `Debug` output.
+pub struct HmacKey {
+    bytes: Secret<Vec<u8>>,
+}
+
+impl HmacKey {
+    /// Wrap key material, e.g. bytes read from a tenant's secret store.
+    ///
+    /// # Arguments
+    /// * `bytes` - At least 32 bytes of secret key material
+    ///
+    /// # Returns
+    /// The key, or a HashingError if it is shorter than 32 bytes
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Result<HmacKey> {
+        let bytes = Secret::new(bytes.into());
+        if bytes.expose().len() < MIN_KEY_LEN {
+            return Err(ConstitutionalError::HashingError(format!(
+                "HMAC key must be at least {} bytes",
+                MIN_KEY_LEN
+            )));
+        }
+        Ok(HmacKey { bytes })
+    }
+}
+
+impl fmt::Debug for HmacKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("HmacKey([REDACTED])")
+    }
+}
+
+/// HMAC-SHA256 in progress, fed the message through `io::Write`.
+pub(crate) struct HmacSha256 {
+    inner: Sha256,
+    outer_pad: [u8; BLOCK_SIZE],
+}
+
+impl HmacSha256 {
+    pub(crate) fn new(key: &HmacKey) -> HmacSha256 {
+        // Keys longer than a block are hashed first; shorter ones are zero-padded
+        let key = key.bytes.expose();
+        let mut block = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            let mut hashed = Sha256::digest(key);
+            block[..32].copy_from_slice(&hashed);
+            wipe(&mut hashed);
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner_pad = block.map(|b| b ^ 0x36);
+        let outer_pad = block.map(|b| b ^ 0x5c);
+        let mut inner = Sha256::new();
+        inner.update(inner_pad);
+        wipe(&mut block);
+        wipe(&mut inner_pad);
+        HmacSha256 { inner, outer_pad }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.inner.update(bytes);
+    }
+
+    pub(crate) fn finalize(mut self) -> [u8; 32] {
+        let inner = std::mem::take(&mut self.inner).finalize();
+        let mut outer = Sha256::new();
+        outer.update(self.outer_pad);
+        outer.update(inner);
+        outer.finalize().into()
+    }
+}
+
+impl std::io::Write for HmacSha256 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for HmacSha256 {
+    fn drop(&mut self) {
+        wipe(&mut self.outer_pad);
+    }
+}
+
+/// Compare two byte strings in time that depends only on their lengths.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(difference) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::hex;
+
+    #[test]
+    fn test_rfc4231_vectors() {
+        // RFC 4231 test case 6: a key longer than the block size is hashed first
+        let key = HmacKey::new(vec![0xaa; 131]).unwrap();
+        let mut mac = HmacSha256::new(&key);
+        mac.update(b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(hex(&mac.finalize()), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+
+        assert!(HmacKey::new(b"Jefe".to_vec()).is_err());
+        assert_eq!(format!("{:?}", key), "HmacKey([REDACTED])");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}
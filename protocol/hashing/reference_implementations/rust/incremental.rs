@@ -0,0 +1,235 @@
+/// incremental.rs - Hashing an object assembled one member at a time
+///
+/// The archive writer builds snapshot objects piecewise and should not have to hold the
+/// assembled object just to hash it. `IncrementalHasher` takes top-level members in any
+/// order, canonicalizes each as it arrives (so only its canonical text is kept), and on
+/// `finalize` writes them in collation order into the hasher. The digest is exactly
+/// `semantic_hash_with` on the assembled object.
+///
+/// Members are canonicalized with the same function the `rayon` feature fans out over
+/// threads, so both stitch identical fragments.
+
+use crate::encoding::digest_text;
+use crate::escape::write_string;
+use crate::intern::Scratch;
+use crate::{
+    check_child, clone_iteratively, deep_sort_at, drop_iteratively, stream, versioned_hasher, write_canonical,
+    CanonicalizeOptions, ConstitutionalError, JsonPointer, Result,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Semantic hash of an object whose top-level members are added one at a time.
+#[derive(Debug, Clone)]
+pub struct IncrementalHasher {
+    options: CanonicalizeOptions,
+    /// (key as it is written, canonical text of the value)
+    members: Vec<(String, String)>,
+    keys: HashSet<String>,
+}
+
+impl IncrementalHasher {
+    /// Start hashing an object under `options`.
+    ///
+    /// # Arguments
+    /// * `options` - Canonicalization options the assembled object is hashed under
+    ///
+    /// # Returns
+    /// The hasher, or an error if the options exclude the root or set `max_nodes`, which
+    /// counts nodes across the whole document and cannot be checked member by member
+    pub fn new(options: &CanonicalizeOptions) -> Result<IncrementalHasher> {
+        if options.exclude_paths.iter().any(JsonPointer::is_root) {
+            return Err(ConstitutionalError::canonicalization("Cannot exclude the document root"));
+        }
+        if options.max_nodes.is_some() {
+            return Err(ConstitutionalError::canonicalization(
+                "max_nodes cannot be enforced on an object hashed incrementally",
+            ));
+        }
+        Ok(IncrementalHasher { options: options.clone(), members: Vec::new(), keys: HashSet::new() })
+    }
+
+    /// Add the member `key` with value `value`, canonicalizing the value now.
+    ///
+    /// Excluded and pruned members are dropped, as they would be from the assembled object.
+    ///
+    /// # Returns
+    /// The hasher, or the error canonicalizing the value would raise in the assembled
+    /// object; adding a key twice (or two keys equal after normalization) is an error
+    pub fn insert(&mut self, key: &str, value: &Value) -> Result<&mut IncrementalHasher> {
+        if self.excluded(key) {
+            return Ok(self);
+        }
+        if let Some(text) = canonical_member(key, value, &self.options)? {
+            self.push(key, text)?;
+        }
+        Ok(self)
+    }
+
+    /// Add the member `key` with a value already in canonical form under these options,
+    /// such as a fragment kept from an earlier canonicalization. The fragment is hashed
+    /// as given; it is not checked to be canonical.
+    pub fn insert_canonical(&mut self, key: &str, fragment: &str) -> Result<&mut IncrementalHasher> {
+        if !self.excluded(key) {
+            check_child(&self.options, &[key.to_string()], 0, Some(key))?;
+            self.push(key, fragment.to_string())?;
+        }
+        Ok(self)
+    }
+
+    /// Number of members that will be hashed.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Whether no members will be hashed.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// The semantic hash of the assembled object, written in the options' encoding.
+    pub fn finalize(self) -> Result<String> {
+        digest_text(self.options.hash_encoding, &self.finalize_bytes()?)
+    }
+
+    /// The digest bytes of the assembled object, whatever the options' encoding.
+    pub fn finalize_bytes(mut self) -> Result<Vec<u8>> {
+        let mut hasher = versioned_hasher(&self.options);
+        write_members(&mut self.members, &self.options, &mut hasher)?;
+        Ok(hasher.finalize())
+    }
+
+    fn excluded(&self, key: &str) -> bool {
+        self.options.exclude_paths.iter().any(|p| p.is_child(&[], key)) || self.options.schema_hints.excludes(&[], key)
+    }
+
+    fn push(&mut self, key: &str, text: String) -> Result<()> {
+        let key = match self.options.normalization {
+            Some(form) => form.apply(key),
+            None => key.to_string(),
+        };
+        if !self.keys.insert(key.clone()) {
+            return Err(ConstitutionalError::canonicalization_at(
+                format!("Key {:?} was already added, or collides with another key after Unicode normalization", key),
+                &[],
+            ));
+        }
+        self.members.push((key, text));
+        Ok(())
+    }
+}
+
+/// Canonical form of the top-level member `token`, or None if it is pruned.
+pub(crate) fn canonical_member(token: &str, value: &Value, options: &CanonicalizeOptions) -> Result<Option<String>> {
+    canonical_member_at(&[], token, value, options)
+}
+
+/// Canonical form of the member `token` of the object at `parent`, or None if it is
+/// pruned. Exclusions of the member itself are the caller's to apply.
+pub(crate) fn canonical_member_at(
+    parent: &[String],
+    token: &str,
+    value: &Value,
+    options: &CanonicalizeOptions,
+) -> Result<Option<String>> {
+    let mut path = parent.to_vec();
+    path.push(token.to_string());
+    // Node counts only matter under a node limit, which callers rule out
+    check_child(options, &path, 0, Some(token))?;
+
+    let mut out = Vec::new();
+    match value {
+        Value::Object(_) | Value::Array(_) if stream::streamable(options) => {
+            stream::write_value(value, options, &path, Some(token), &mut Scratch::default(), &mut out)?;
+        }
+        _ => {
+            let sorted = deep_sort_at(clone_iteratively(value), options, path, Some(token))?;
+            if options.prune.drops(&sorted) {
+                drop_iteratively(sorted);
+                return Ok(None);
+            }
+            let written = write_canonical(&sorted, options, &mut out);
+            drop_iteratively(sorted);
+            written?;
+        }
+    }
+    // The writers only emit UTF-8
+    String::from_utf8(out).map(Some).map_err(|e| ConstitutionalError::canonicalization(e.to_string()))
+}
+
+/// Write an object from (written key, canonical value) pairs, in collation order.
+pub(crate) fn write_members<W: Write + ?Sized>(
+    members: &mut [(String, String)],
+    options: &CanonicalizeOptions,
+    sink: &mut W,
+) -> Result<()> {
+    members.sort_by(|a, b| options.key_collation.compare(&a.0, &b.0));
+
+    let mut out = String::new();
+    sink.write_all(b"{")?;
+    for (i, (key, text)) in members.iter().enumerate() {
+        out.clear();
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(key, options.escaping, &mut out);
+        out.push(':');
+        sink.write_all(out.as_bytes())?;
+        sink.write_all(text.as_bytes())?;
+    }
+    sink.write_all(b"}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash_with, NormalizationForm, PrunePolicy};
+    use serde_json::json;
+
+    #[test]
+    fn test_incremental_matches_semantic_hash() {
+        let snapshot = json!({
+            "zeta": {"clauses": ["b", "a"], "amended": null},
+            "alpha": [3, 1, 2],
+            "caf\u{e9}": "coffee",
+            "empty": {},
+            "signature": "ed25519:abc"
+        });
+        for options in [
+            CanonicalizeOptions::new(),
+            CanonicalizeOptions::new().exclude_field("signature").prune(PrunePolicy::NullsAndEmpty),
+            CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfd),
+        ] {
+            let mut hasher = IncrementalHasher::new(&options).unwrap();
+            // Members arrive in reverse order; fragments may also be pre-canonicalized
+            for (key, value) in snapshot.as_object().unwrap().iter().rev() {
+                if key == "alpha" {
+                    hasher.insert_canonical(key, "[1,2,3]").unwrap();
+                } else {
+                    hasher.insert(key, value).unwrap();
+                }
+            }
+            assert_eq!(hasher.finalize().unwrap(), semantic_hash_with(&snapshot, &options).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_incremental_rejects_duplicates() {
+        let options = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+        let mut hasher = IncrementalHasher::new(&options).unwrap();
+        hasher.insert("caf\u{e9}", &json!(1)).unwrap();
+        assert!(hasher.insert("cafe\u{301}", &json!(2)).is_err());
+        assert!(hasher.insert("caf\u{e9}", &json!(3)).is_err());
+        assert_eq!(hasher.len(), 1);
+
+        assert!(IncrementalHasher::new(&CanonicalizeOptions::new().max_nodes(10)).is_err());
+        let err = IncrementalHasher::new(&CanonicalizeOptions::new().max_depth(1))
+            .unwrap()
+            .insert("a", &json!({"b": {"c": 1}}))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.pointer().map(|p| p.to_string()).as_deref(), Some("/a/b"));
+    }
+}
@@ -0,0 +1,126 @@
+/// intern.rs - Reusable key storage for canonicalizing many similar documents
+///
+/// Ledgers repeat the same few field names (`action_id`, `agent`, `timestamp`) across
+/// millions of entries. Two kinds of per-key work would allocate on every occurrence:
+/// recording the key in the path that limits, hints and error pointers are resolved
+/// against, and Unicode-normalizing it. `TokenPath` reuses the buffers of popped tokens
+/// and `KeyInterner` remembers each key's normalized form, so once a `Scratch` has seen a
+/// document's shape, canonicalizing another of the same shape allocates neither.
+
+use crate::NormalizationForm;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// Most distinct keys a `KeyInterner` remembers, so documents with unbounded key sets
+/// (maps keyed by ID, say) cannot grow it without limit. Later keys are still normalized.
+const MAX_INTERNED_KEYS: usize = 4096;
+
+/// Per-key state kept from one document to the next.
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    pub(crate) path: TokenPath,
+    pub(crate) keys: KeyInterner,
+}
+
+/// Reference tokens leading to the value being visited, as a stack whose popped entries
+/// keep their buffers. Dereferences to the live tokens.
+#[derive(Debug, Default)]
+pub(crate) struct TokenPath {
+    tokens: Vec<String>,
+    len: usize,
+}
+
+impl TokenPath {
+    /// Replace the path with `tokens`.
+    pub(crate) fn reset(&mut self, tokens: &[String]) {
+        self.len = 0;
+        for token in tokens {
+            self.push(token);
+        }
+    }
+
+    pub(crate) fn push(&mut self, token: &str) {
+        self.next_buffer().push_str(token);
+    }
+
+    /// Push an array index without formatting it into a fresh `String`.
+    pub(crate) fn push_index(&mut self, index: usize) {
+        // Writing to a String cannot fail
+        let _ = write!(self.next_buffer(), "{}", index);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.len = self.len.saturating_sub(1);
+    }
+
+    fn next_buffer(&mut self) -> &mut String {
+        if self.len == self.tokens.len() {
+            self.tokens.push(String::new());
+        }
+        let buffer = &mut self.tokens[self.len];
+        buffer.clear();
+        self.len += 1;
+        buffer
+    }
+}
+
+impl Deref for TokenPath {
+    type Target = [String];
+
+    fn deref(&self) -> &[String] {
+        &self.tokens[..self.len]
+    }
+}
+
+/// Normalized forms of the object keys seen so far.
+#[derive(Debug, Default)]
+pub(crate) struct KeyInterner {
+    form: Option<NormalizationForm>,
+    normalized: HashMap<String, Rc<str>>,
+}
+
+impl KeyInterner {
+    /// `key` under `form`, shared with every earlier occurrence of the same key.
+    pub(crate) fn normalize(&mut self, form: NormalizationForm, key: &str) -> Rc<str> {
+        if self.form != Some(form) {
+            self.normalized.clear();
+            self.form = Some(form);
+        }
+        if let Some(normalized) = self.normalized.get(key) {
+            return Rc::clone(normalized);
+        }
+        let normalized: Rc<str> = form.apply(key).into();
+        if self.normalized.len() < MAX_INTERNED_KEYS {
+            self.normalized.insert(key.to_string(), Rc::clone(&normalized));
+        }
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_path_reuses_buffers() {
+        let mut path = TokenPath::default();
+        path.reset(&["evidence".to_string()]);
+        path.push_index(12);
+        path.push("claim");
+        assert_eq!(&*path, ["evidence", "12", "claim"]);
+
+        path.pop();
+        path.pop();
+        path.push("hash");
+        assert_eq!(&*path, ["evidence", "hash"]);
+        assert_eq!(path.tokens.len(), 3);
+
+        let mut keys = KeyInterner::default();
+        let first = keys.normalize(NormalizationForm::Nfc, "cafe\u{301}");
+        assert_eq!(&*first, "caf\u{e9}");
+        assert!(Rc::ptr_eq(&first, &keys.normalize(NormalizationForm::Nfc, "cafe\u{301}")));
+        assert_eq!(&*keys.normalize(NormalizationForm::Nfd, "caf\u{e9}"), "cafe\u{301}");
+    }
+}
@@ -0,0 +1,171 @@
+/// jws.rs - Detached JWS over canonical payloads
+///
+/// Partners running JOSE pipelines take attestations as JWS (RFC 7515) rather than as
+/// `SignedObject`s. `sign_jws` signs the canonical JSON of a document as a detached JWS
+/// with an unencoded payload (RFC 7797): the protected header is
+///
+/// ```json
+/// {"alg":"EdDSA","b64":false,"crit":["b64"],"kid":"ed25519:3b6a..."}
+/// ```
+///
+/// and the compact serialization leaves the payload out, `<header>..<signature>`, since
+/// the verifier canonicalizes the document itself. `kid` is the signer's public key in
+/// its protocol text form. Ed25519 keys sign as `EdDSA` and secp256k1 keys as `ES256K`
+/// (RFC 8812), whose 64-byte `r || s` signatures are the ones `Keypair` makes.
+///
+/// `verify_jws` also takes ordinary JWS compact serializations from other producers:
+/// with a base64url payload attached, or detached as in RFC 7515 Appendix F. An
+/// attached payload must be JSON with the same canonical form as the document.
+
+use crate::algorithm::hex;
+use crate::encoding::{base64url, unbase64url};
+use crate::signing::split_tagged;
+use crate::{canonicalize_with, CanonicalizeOptions, ConstitutionalError, PublicKey, Result, Signer};
+use serde_json::{json, Value};
+
+/// Sign a document's canonical JSON as a detached JWS with an unencoded payload.
+///
+/// # Arguments
+/// * `data` - Document to attest to
+/// * `signer` - The signer's keypair, or any other `Signer`
+/// * `options` - Canonicalization options the payload is serialized under
+///
+/// # Returns
+/// The JWS compact serialization with an empty payload part, or an error if the data
+/// cannot be canonicalized or the signer fails
+pub fn sign_jws(data: &Value, signer: &dyn Signer, options: &CanonicalizeOptions) -> Result<String> {
+    let key = signer.public_key()?;
+    let header = json!({"alg": algorithm(&key), "b64": false, "crit": ["b64"], "kid": key.to_string()});
+    let header = base64url(canonicalize_with(&header, &CanonicalizeOptions::new())?.as_bytes());
+    let payload = canonicalize_with(data, options)?;
+    let signature = signer.sign_message(&signing_input(&header, payload.as_bytes()))?;
+    let (scheme, mut bytes) = split_tagged(&signature, "signature")?;
+    if scheme == "secp256k1-recoverable" {
+        // ES256K has no room for the recovery byte
+        bytes.pop();
+    }
+    Ok(format!("{}..{}", header, base64url(&bytes)))
+}
+
+/// Check a JWS compact serialization over a document.
+///
+/// # Arguments
+/// * `jws` - The JWS, detached or with its payload attached
+/// * `data` - The document it is expected to sign
+/// * `key` - The signer's public key
+/// * `options` - Canonicalization options the payload was serialized under
+///
+/// # Returns
+/// true if the JWS is the key's signature over the document, false otherwise; a
+/// HashingError if the JWS is malformed, critical headers it uses are not understood or
+/// its algorithm does not fit the key
+pub fn verify_jws(jws: &str, data: &Value, key: &PublicKey, options: &CanonicalizeOptions) -> Result<bool> {
+    let malformed = |what: &str| ConstitutionalError::HashingError(format!("Malformed JWS: {}", what));
+    let mut parts = jws.split('.');
+    let (Some(header_part), Some(payload_part), Some(signature_part), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(malformed("not three dot-separated parts"));
+    };
+    let header: Value = unbase64url(header_part)
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .filter(Value::is_object)
+        .ok_or_else(|| malformed("the header is not a base64url JSON object"))?;
+    let signature = unbase64url(signature_part).ok_or_else(|| malformed("the signature is not base64url"))?;
+
+    let critical = match header.get("crit") {
+        None => Vec::new(),
+        Some(Value::Array(names)) if !names.is_empty() => names.iter().map(Value::as_str).collect(),
+        Some(_) => return Err(malformed("crit is not a list of header names")),
+    };
+    if let Some(name) = critical.iter().find(|name| *name != &Some("b64")) {
+        return Err(ConstitutionalError::HashingError(format!("Unsupported critical JWS header {:?}", name)));
+    }
+    let encoded = match header.get("b64") {
+        None => true,
+        // RFC 7797 section 6: b64 only counts when marked critical
+        Some(Value::Bool(b64)) if !critical.is_empty() => *b64,
+        Some(_) => return Err(malformed("b64 is not a critical boolean")),
+    };
+    let alg = header.get("alg").and_then(Value::as_str).ok_or_else(|| malformed("no alg"))?;
+    if alg != algorithm(key) {
+        return Err(ConstitutionalError::HashingError(format!("A {} JWS cannot be checked against a {} key", alg, key)));
+    }
+
+    let canonical = canonicalize_with(data, options)?;
+    let input = match (encoded, payload_part) {
+        (false, "") => signing_input(header_part, canonical.as_bytes()),
+        (false, _) => return Err(malformed("an unencoded payload must be detached")),
+        (true, "") => signing_input(header_part, base64url(canonical.as_bytes()).as_bytes()),
+        (true, attached) => {
+            let payload: Value = unbase64url(attached)
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .ok_or_else(|| malformed("the payload is not base64url JSON"))?;
+            if canonicalize_with(&payload, options)? != canonical {
+                return Ok(false);
+            }
+            signing_input(header_part, attached.as_bytes())
+        }
+    };
+    key.verify_message(&input, &format!("{}:{}", key.prefix(), hex(&signature)))
+}
+
+/// The JWS algorithm a key signs under.
+fn algorithm(key: &PublicKey) -> &'static str {
+    match key.prefix() {
+        "ed25519" => "EdDSA",
+        _ => "ES256K",
+    }
+}
+
+/// The JWS signing input, the encoded header and the payload joined by a dot.
+fn signing_input(header: &str, payload: &[u8]) -> Vec<u8> {
+    [header.as_bytes(), b".", payload].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::fixtures::{contract, keypair};
+    use crate::Keypair;
+
+    #[test]
+    fn test_detached_jws_round_trip() {
+        let options = CanonicalizeOptions::new();
+        let keypair = keypair();
+        let key = keypair.public_key();
+        let jws = sign_jws(&contract(), &keypair, &options).unwrap();
+        let (header, signature) = jws.split_once("..").unwrap();
+        let header: Value = serde_json::from_slice(&unbase64url(header).unwrap()).unwrap();
+        assert_eq!(header, json!({"alg": "EdDSA", "b64": false, "crit": ["b64"], "kid": key.to_string()}));
+        assert_eq!(unbase64url(signature).unwrap().len(), 64);
+
+        assert!(verify_jws(&jws, &contract(), &key, &options).unwrap());
+        let mut amended = contract();
+        amended["action_type"] = json!("repeal");
+        assert!(!verify_jws(&jws, &amended, &key, &options).unwrap());
+        assert!(!verify_jws(&jws, &contract(), &Keypair::from_secret(&[8; 32]).public_key(), &options).unwrap());
+    }
+
+    #[test]
+    fn test_accepts_encoded_payloads() {
+        let options = CanonicalizeOptions::new();
+        let keypair = keypair();
+        let key = keypair.public_key();
+        // An ordinary JWS as a JOSE library would produce it, over non-canonical JSON
+        let header = base64url(br#"{"alg":"EdDSA"}"#);
+        let payload = base64url(br#"{ "parties": ["agent-claude", "agent-gemini"], "contract_id": "c-17",
+            "action_type": "amend" }"#);
+        let signature = keypair.sign_message(&signing_input(&header, payload.as_bytes()));
+        let signature = base64url(&split_tagged(&signature, "signature").unwrap().1);
+        assert!(verify_jws(&format!("{}.{}.{}", header, payload, signature), &contract(), &key, &options).unwrap());
+        assert!(!verify_jws(&format!("{}.{}.{}", header, payload, signature), &json!({}), &key, &options).unwrap());
+
+        let headers = [r#"{"alg":"EdDSA","crit":["exp"],"exp":1}"#, r#"{"alg":"EdDSA","b64":false}"#, r#"{"alg":"ES256"}"#];
+        for header in headers {
+            let jws = format!("{}..{}", base64url(header.as_bytes()), signature);
+            assert!(verify_jws(&jws, &contract(), &key, &options).is_err(), "{}", header);
+        }
+        assert!(verify_jws("a.b", &contract(), &key, &options).is_err());
+    }
+}
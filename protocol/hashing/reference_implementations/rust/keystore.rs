@@ -0,0 +1,393 @@
+/// keystore.rs - Agent keypairs encrypted at rest
+///
+/// A `Keystore` is a directory holding one file per named key. The secret key is
+/// encrypted with AES-256-GCM under a key that Argon2id derives from the operator's
+/// passphrase and a per-file random salt. Everything else in the file is stored in the
+/// clear, so keys can be listed and their public keys read without the passphrase, and
+/// is authenticated as the cipher's associated data, so none of it can be altered:
+///
+/// ```json
+/// {"version":1,"name":"claude","scheme":"ed25519","public_key":"ed25519:3b6a...",
+///  "kdf":"argon2id","m_cost":19456,"t_cost":2,"p_cost":1,"salt":"9c1f...",
+///  "cipher":"aes-256-gcm","nonce":"07ab...","ciphertext":"e556..."}
+/// ```
+///
+/// The associated data is the canonical JSON of the file without `ciphertext`. A file
+/// moves between keystores as it is, still encrypted, with `export` and `import`.
+/// Requires the `keystore` feature.
+
+use crate::algorithm::{hex, unhex};
+use crate::commitment::os_random;
+use crate::{
+    canonicalize_with, sign_with, CanonicalizeOptions, ConstitutionalError, Keypair, PublicKey, Result, Secret,
+    SignatureScheme, SignedObject,
+};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const VERSION: u64 = 1;
+const EXTENSION: &str = "key.json";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Largest Argon2id cost a key file may ask for: 1 GiB of memory, 16 passes and 16
+/// lanes. Files come from other keystores, so their cost is bounded before deriving.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+/// A directory of encrypted, named agent keypairs.
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl Keystore {
+    /// Open the keystore in `dir`, creating the directory if needed. New keys are
+    /// encrypted with Argon2id at 19 MiB of memory and 2 passes, OWASP's minimum.
+    ///
+    /// # Returns
+    /// The keystore, or an IoError if the directory cannot be created
+    pub fn open(dir: impl AsRef<Path>) -> Result<Keystore> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Keystore { dir: dir.as_ref().to_path_buf(), m_cost: 19 * 1024, t_cost: 2, p_cost: 1 })
+    }
+
+    /// Set the Argon2id cost new keys are encrypted with: memory in KiB, passes and lanes.
+    /// Files record their own cost, so changing it does not affect stored keys. A cost
+    /// above 1 GiB, 16 passes or 16 lanes is refused when a key is added.
+    pub fn kdf_cost(mut self, m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        self.m_cost = m_cost;
+        self.t_cost = t_cost;
+        self.p_cost = p_cost;
+        self
+    }
+
+    /// Store a keypair under a name, encrypted under a passphrase.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the name is malformed or taken; an IoError if the file
+    /// cannot be written
+    pub fn add(&self, name: &str, keypair: &Keypair, passphrase: &str) -> Result<()> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        os_random(&mut salt)?;
+        os_random(&mut nonce)?;
+        let mut file = Map::new();
+        file.insert("version".to_string(), json!(VERSION));
+        file.insert("name".to_string(), json!(name));
+        file.insert("scheme".to_string(), json!(keypair.scheme().identifier()));
+        file.insert("public_key".to_string(), json!(keypair.public_key().to_string()));
+        file.insert("kdf".to_string(), json!("argon2id"));
+        file.insert("m_cost".to_string(), json!(self.m_cost));
+        file.insert("t_cost".to_string(), json!(self.t_cost));
+        file.insert("p_cost".to_string(), json!(self.p_cost));
+        file.insert("salt".to_string(), json!(hex(&salt)));
+        file.insert("cipher".to_string(), json!("aes-256-gcm"));
+        file.insert("nonce".to_string(), json!(hex(&nonce)));
+
+        let cipher = file_cipher(&file, passphrase)?;
+        let aad = associated_data(&file)?;
+        let secret = keypair.secret_bytes();
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret.expose(), aad: &aad })
+            .map_err(|_| keystore_error("Encryption failed"))?;
+        file.insert("ciphertext".to_string(), json!(hex(&ciphertext)));
+        self.write_new(name, &Value::Object(file))
+    }
+
+    /// Decrypt the keypair stored under a name.
+    ///
+    /// # Returns
+    /// The keypair, or a ProtocolError if there is no such key, the passphrase is wrong
+    /// or the file was altered
+    pub fn load(&self, name: &str, passphrase: &str) -> Result<Keypair> {
+        let file = self.read(name)?;
+        let fields = file.as_object().expect("key files are objects");
+        let nonce = hex_field(fields, "nonce").filter(|nonce| nonce.len() == NONCE_LEN);
+        let ciphertext = hex_field(fields, "ciphertext");
+        let (Some(nonce), Some(ciphertext)) = (nonce, ciphertext) else {
+            return Err(keystore_error(format!("Key file {:?} is malformed", name)));
+        };
+        let cipher = file_cipher(fields, passphrase)?;
+        let aad = associated_data(fields)?;
+        let secret = cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+            .map(Secret::new)
+            .map_err(|_| keystore_error(format!("Wrong passphrase for key {:?}, or its file was altered", name)))?;
+        let mut bytes = Secret::new([0u8; 32]);
+        if secret.expose().len() != 32 {
+            return Err(keystore_error(format!("Key file {:?} is malformed", name)));
+        }
+        bytes.expose_mut().copy_from_slice(secret.expose());
+        let keypair = Keypair::restore(str_field(&file, "scheme")?.parse()?, bytes.expose())?;
+        if keypair.public_key().to_string() != str_field(&file, "public_key")? {
+            return Err(keystore_error(format!("Key {:?} does not match its public key", name)));
+        }
+        Ok(keypair)
+    }
+
+    /// Sign data with the key stored under a name.
+    ///
+    /// # Returns
+    /// The signed object, or an error if the key cannot be loaded or the data hashed
+    pub fn sign(
+        &self,
+        name: &str,
+        passphrase: &str,
+        data: &Value,
+        options: &CanonicalizeOptions,
+    ) -> Result<SignedObject> {
+        sign_with(data, &self.load(name, passphrase)?, options)
+    }
+
+    /// The public key stored under a name, read without the passphrase.
+    pub fn public_key(&self, name: &str) -> Result<PublicKey> {
+        str_field(&self.read(name)?, "public_key")?.parse()
+    }
+
+    /// The scheme the key stored under a name signs with.
+    pub fn scheme(&self, name: &str) -> Result<SignatureScheme> {
+        str_field(&self.read(name)?, "scheme")?.parse()
+    }
+
+    /// Names of the stored keys, sorted.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let file_name = entry?.file_name();
+            let name = file_name.to_str().and_then(|file| file.strip_suffix(EXTENSION)?.strip_suffix('.'));
+            if let Some(name) = name.filter(|name| check_name(name).is_ok()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Delete the key stored under a name.
+    ///
+    /// # Returns
+    /// Whether there was such a key
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        match fs::remove_file(self.path(name)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The encrypted file of a key, for `import` into another keystore.
+    pub fn export(&self, name: &str) -> Result<String> {
+        Ok(serde_json::to_string(&self.read(name)?).expect("key files serialize to JSON"))
+    }
+
+    /// Store an exported key file under the name it carries.
+    ///
+    /// # Returns
+    /// The name, or a ProtocolError if the file is malformed or the name is taken
+    pub fn import(&self, exported: &str) -> Result<String> {
+        let file: Value =
+            serde_json::from_str(exported).map_err(|e| keystore_error(format!("Not a key file: {}", e)))?;
+        check_file(&file)?;
+        let name = str_field(&file, "name")?.to_string();
+        self.write_new(&name, &file)?;
+        Ok(name)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        check_name(name)?;
+        Ok(self.dir.join(format!("{}.{}", name, EXTENSION)))
+    }
+
+    fn read(&self, name: &str) -> Result<Value> {
+        let text = match fs::read_to_string(self.path(name)?) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(keystore_error(format!("No key named {:?}", name)))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let file: Value = serde_json::from_str(&text).map_err(|e| keystore_error(format!("{}: {}", name, e)))?;
+        check_file(&file)?;
+        if str_field(&file, "name")? != name {
+            return Err(keystore_error(format!("Key file {:?} holds another key", name)));
+        }
+        Ok(file)
+    }
+
+    /// Write a key file, failing if the name is taken. Owner-only on Unix.
+    fn write_new(&self, name: &str, file: &Value) -> Result<()> {
+        let path = self.path(name)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut out = match options.open(&path) {
+            Ok(out) => out,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(keystore_error(format!("A key named {:?} already exists", name)))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        out.write_all(serde_json::to_string(file).expect("key files serialize to JSON").as_bytes())?;
+        out.sync_all()?;
+        Ok(())
+    }
+}
+
+/// The cipher for a file, keyed by Argon2id over the passphrase with the file's salt
+/// and cost.
+fn file_cipher(file: &Map<String, Value>, passphrase: &str) -> Result<Aes256Gcm> {
+    let (params, salt) = kdf_params(file)?;
+    let mut key = Secret::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.expose_mut())
+        .map_err(|e| keystore_error(format!("Key derivation failed: {}", e)))?;
+    Ok(Aes256Gcm::new_from_slice(key.expose()).expect("AES-256 takes a 32-byte key"))
+}
+
+/// A file's Argon2id parameters and salt.
+///
+/// # Returns
+/// Both, or a ProtocolError if they are missing or malformed or the cost exceeds the
+/// maxima
+fn kdf_params(file: &Map<String, Value>) -> Result<(Params, Vec<u8>)> {
+    let cost = |name| file.get(name).and_then(Value::as_u64).and_then(|cost| u32::try_from(cost).ok());
+    let salt = file.get("salt").and_then(Value::as_str).and_then(unhex);
+    let (Some(m_cost), Some(t_cost), Some(p_cost), Some(salt)) = (cost("m_cost"), cost("t_cost"), cost("p_cost"), salt)
+    else {
+        return Err(keystore_error("Key file has no valid Argon2id parameters"));
+    };
+    if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err(keystore_error(format!(
+            "Argon2id cost m={}, t={}, p={} exceeds the maximum of m={}, t={}, p={}",
+            m_cost, t_cost, p_cost, MAX_M_COST, MAX_T_COST, MAX_P_COST
+        )));
+    }
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| keystore_error(format!("Invalid Argon2id cost: {}", e)))?;
+    Ok((params, salt))
+}
+
+/// Canonical JSON of a file without its ciphertext.
+fn associated_data(file: &Map<String, Value>) -> Result<Vec<u8>> {
+    let mut header = file.clone();
+    header.remove("ciphertext");
+    Ok(canonicalize_with(&Value::Object(header), &CanonicalizeOptions::new())?.into_bytes())
+}
+
+/// Check a key file's format, version and algorithms.
+fn check_file(file: &Value) -> Result<()> {
+    let fits = file.get("version").and_then(Value::as_u64) == Some(VERSION)
+        && file.get("kdf").and_then(Value::as_str) == Some("argon2id")
+        && file.get("cipher").and_then(Value::as_str) == Some("aes-256-gcm");
+    if !fits {
+        return Err(keystore_error("Not a version 1 argon2id/aes-256-gcm key file"));
+    }
+    if let Some(fields) = file.as_object() {
+        kdf_params(fields)?;
+    }
+    check_name(str_field(file, "name")?)
+}
+
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b));
+    if valid {
+        Ok(())
+    } else {
+        Err(keystore_error(format!("Invalid key name {:?}", name)))
+    }
+}
+
+fn str_field<'a>(file: &'a Value, name: &str) -> Result<&'a str> {
+    file.get(name).and_then(Value::as_str).ok_or_else(|| keystore_error(format!("Key file has no {}", name)))
+}
+
+fn hex_field(file: &Map<String, Value>, name: &str) -> Option<Vec<u8>> {
+    file.get(name).and_then(Value::as_str).and_then(unhex)
+}
+
+fn keystore_error(message: impl Into<String>) -> ConstitutionalError {
+    ConstitutionalError::ProtocolError(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_signed;
+
+    fn keystore(test: &str) -> Keystore {
+        let dir = std::env::temp_dir().join(format!("ocp-keystore-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        // Cheap parameters keep the tests fast
+        Keystore::open(dir).unwrap().kdf_cost(64, 1, 1)
+    }
+
+    #[test]
+    fn test_store_load_and_sign() {
+        let store = keystore("load");
+        let keypair = Keypair::from_secret(&[7; 32]);
+        store.add("claude", &keypair, "correct horse").unwrap();
+        assert!(store.add("claude", &keypair, "correct horse").is_err());
+        store.add("gemini", &Keypair::from_secret(&[8; 32]), "battery staple").unwrap();
+
+        assert_eq!(store.names().unwrap(), ["claude", "gemini"]);
+        assert_eq!(store.public_key("claude").unwrap(), keypair.public_key());
+        let loaded = store.load("claude", "correct horse").unwrap();
+        assert_eq!(loaded.secret_bytes().expose(), keypair.secret_bytes().expose());
+        assert!(store.load("claude", "battery staple").is_err());
+        assert!(store.load("gpt", "correct horse").is_err());
+
+        let stored = fs::read_to_string(store.path("claude").unwrap()).unwrap();
+        assert!(!stored.contains(&hex(keypair.secret_bytes().expose())));
+        let contract = json!({"contract_id": "c-17"});
+        let signed = store.sign("claude", "correct horse", &contract, &CanonicalizeOptions::new()).unwrap();
+        assert!(verify_signed(&signed).unwrap());
+
+        assert!(store.remove("gemini").unwrap());
+        assert!(!store.remove("gemini").unwrap());
+        assert!(store.add("../escape", &keypair, "x").is_err());
+        fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_import_and_tampering() {
+        let source = keystore("export");
+        let target = keystore("import");
+        let keypair = Keypair::from_secret(&[7; 32]);
+        source.add("claude", &keypair, "pass").unwrap();
+
+        let exported = source.export("claude").unwrap();
+        assert_eq!(target.import(&exported).unwrap(), "claude");
+        assert_eq!(target.load("claude", "pass").unwrap().public_key(), keypair.public_key());
+        assert!(target.import(&exported).is_err());
+
+        // Swapping in another public key breaks the associated data
+        let mut file: Value = serde_json::from_str(&exported).unwrap();
+        file["name"] = json!("forged");
+        file["public_key"] = json!(Keypair::from_secret(&[8; 32]).public_key().to_string());
+        target.import(&file.to_string()).unwrap();
+        assert!(target.load("forged", "pass").is_err());
+        assert!(target.import(r#"{"version":2}"#).is_err());
+
+        // Nor can an imported file make loading it cost more than the maxima
+        for (field, cost) in [("m_cost", MAX_M_COST + 1), ("t_cost", MAX_T_COST + 1), ("p_cost", MAX_P_COST + 1)] {
+            let mut costly: Value = serde_json::from_str(&exported).unwrap();
+            costly["name"] = json!(format!("costly-{}", field));
+            costly[field] = json!(cost);
+            assert!(target.import(&costly.to_string()).is_err(), "{}", field);
+        }
+        fs::remove_dir_all(&source.dir).unwrap();
+        fs::remove_dir_all(&target.dir).unwrap();
+    }
+}
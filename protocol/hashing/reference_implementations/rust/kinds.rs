@@ -0,0 +1,290 @@
+/// kinds.rs - Dispatch on incoming objects by their `object_type`
+///
+/// Objects arriving from other agents say what they are in an `object_type` member next
+/// to their own:
+///
+/// ```json
+/// {"object_type":"Vote","contract_id":"550e...","voter_agent":"Gemini","vote":"approve",...}
+/// ```
+///
+/// A `ConstitutionalObject` reads such an object into the typed object it names, or
+/// keeps it as `Custom` when the name is not one of `OBJECT_TYPES`, so handling any
+/// incoming object is one `match`. Deployments add kinds of their own to an
+/// `ObjectRegistry` with a validator; the registry then accepts them and rejects object
+/// types nobody registered, and validates every object, built-in kinds against their
+/// JSON Schemas.
+
+use crate::{
+    validate_against_schema, Amendment, Canonicalize, Challenge, ConstitutionalError, Contract, DisputeRecord, Evidence,
+    Reasoning, Result, Ruling, Violation, Vote, OBJECT_TYPES,
+};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name of the member tagging an object with its type.
+pub const OBJECT_TYPE: &str = "object_type";
+
+/// A protocol object of any kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstitutionalObject {
+    Contract(Contract),
+    Amendment(Amendment),
+    Evidence(Evidence),
+    Reasoning(Reasoning),
+    Vote(Vote),
+    Challenge(Challenge),
+    DisputeRecord(DisputeRecord),
+    Ruling(Ruling),
+    /// An object of a kind this crate does not know, its members without the tag.
+    Custom { object_type: String, members: Map<String, Value> },
+}
+
+impl ConstitutionalObject {
+    /// Read a tagged object.
+    ///
+    /// # Returns
+    /// The object, or a ProtocolError if it has no string `object_type` or does not fit
+    /// the built-in kind it names
+    pub fn from_value(value: Value) -> Result<ConstitutionalObject> {
+        let Value::Object(mut members) = value else {
+            return Err(ConstitutionalError::ProtocolError("A protocol object must be an object".to_string()));
+        };
+        let object_type = match members.remove(OBJECT_TYPE) {
+            Some(Value::String(object_type)) => object_type,
+            _ => return Err(ConstitutionalError::ProtocolError("A protocol object needs a string object_type".into())),
+        };
+        Ok(match object_type.as_str() {
+            "Contract" => ConstitutionalObject::Contract(read(members, &object_type)?),
+            "Amendment" => ConstitutionalObject::Amendment(read(members, &object_type)?),
+            "Evidence" => ConstitutionalObject::Evidence(read(members, &object_type)?),
+            "Reasoning" => ConstitutionalObject::Reasoning(read(members, &object_type)?),
+            "Vote" => ConstitutionalObject::Vote(read(members, &object_type)?),
+            "Challenge" => ConstitutionalObject::Challenge(read(members, &object_type)?),
+            "DisputeRecord" => ConstitutionalObject::DisputeRecord(read(members, &object_type)?),
+            "Ruling" => ConstitutionalObject::Ruling(read(members, &object_type)?),
+            _ => ConstitutionalObject::Custom { object_type: object_type.clone(), members },
+        })
+    }
+
+    /// The object's type, e.g. `Vote`.
+    pub fn object_type(&self) -> &str {
+        match self {
+            ConstitutionalObject::Contract(_) => "Contract",
+            ConstitutionalObject::Amendment(_) => "Amendment",
+            ConstitutionalObject::Evidence(_) => "Evidence",
+            ConstitutionalObject::Reasoning(_) => "Reasoning",
+            ConstitutionalObject::Vote(_) => "Vote",
+            ConstitutionalObject::Challenge(_) => "Challenge",
+            ConstitutionalObject::DisputeRecord(_) => "DisputeRecord",
+            ConstitutionalObject::Ruling(_) => "Ruling",
+            ConstitutionalObject::Custom { object_type, .. } => object_type,
+        }
+    }
+
+    /// The object's JSON without its tag.
+    pub fn untagged(&self) -> Value {
+        match self {
+            ConstitutionalObject::Contract(contract) => contract.to_value(),
+            ConstitutionalObject::Amendment(amendment) => amendment.to_value(),
+            ConstitutionalObject::Evidence(evidence) => evidence.to_value(),
+            ConstitutionalObject::Reasoning(reasoning) => reasoning.to_value(),
+            ConstitutionalObject::Vote(vote) => vote.to_value(),
+            ConstitutionalObject::Challenge(challenge) => challenge.to_value(),
+            ConstitutionalObject::DisputeRecord(record) => record.to_value(),
+            ConstitutionalObject::Ruling(ruling) => ruling.to_value(),
+            ConstitutionalObject::Custom { members, .. } => Value::Object(members.clone()),
+        }
+    }
+
+    /// The object's JSON, tagged with its `object_type`.
+    pub fn to_value(&self) -> Value {
+        let mut value = self.untagged();
+        value[OBJECT_TYPE] = Value::from(self.object_type());
+        value
+    }
+}
+
+fn read<T: de::DeserializeOwned>(members: Map<String, Value>, object_type: &str) -> Result<T> {
+    serde_json::from_value(Value::Object(members))
+        .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid {}: {}", object_type, e)))
+}
+
+/// The tag is part of the canonical form, so objects of different kinds with the same
+/// members hash differently.
+impl Canonicalize for ConstitutionalObject {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl Serialize for ConstitutionalObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConstitutionalObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<ConstitutionalObject, D::Error> {
+        ConstitutionalObject::from_value(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Checks an object of a custom kind, its members without the tag, and reports every
+/// violation.
+type Validator = dyn Fn(&Map<String, Value>) -> Vec<Violation> + Send + Sync;
+
+/// The object kinds a deployment accepts: the built-in ones and those it registers.
+#[derive(Clone, Default)]
+pub struct ObjectRegistry {
+    custom: HashMap<String, Arc<Validator>>,
+}
+
+impl std::fmt::Debug for ObjectRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut kinds: Vec<_> = self.custom.keys().collect();
+        kinds.sort();
+        f.debug_struct("ObjectRegistry").field("custom", &kinds).finish()
+    }
+}
+
+impl ObjectRegistry {
+    /// A registry of the built-in kinds only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a custom kind with the validator its objects must pass.
+    ///
+    /// # Arguments
+    /// * `object_type` - The kind's `object_type`, e.g. `Treaty`
+    /// * `validator` - Reports every violation in an object's members, without the tag
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the name is empty, built in or already registered
+    pub fn register(
+        &mut self,
+        object_type: &str,
+        validator: impl Fn(&Map<String, Value>) -> Vec<Violation> + Send + Sync + 'static,
+    ) -> Result<()> {
+        if object_type.is_empty() || OBJECT_TYPES.contains(&object_type) || self.custom.contains_key(object_type) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Object type {:?} is empty, built in or already registered",
+                object_type
+            )));
+        }
+        self.custom.insert(object_type.to_string(), Arc::new(validator));
+        Ok(())
+    }
+
+    /// Whether objects of a type are accepted.
+    pub fn accepts(&self, object_type: &str) -> bool {
+        OBJECT_TYPES.contains(&object_type) || self.custom.contains_key(object_type)
+    }
+
+    /// Read a tagged object of a kind the registry accepts.
+    ///
+    /// # Returns
+    /// The object, or a ProtocolError if it is malformed or its kind is not registered
+    pub fn parse(&self, value: Value) -> Result<ConstitutionalObject> {
+        let object = ConstitutionalObject::from_value(value)?;
+        if !self.accepts(object.object_type()) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Unregistered object type {:?}",
+                object.object_type()
+            )));
+        }
+        Ok(object)
+    }
+
+    /// Check an object against its kind's rules: a built-in kind's JSON Schema, or the
+    /// validator a custom kind was registered with.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of the offending value within the
+    /// object; a ProtocolError if the object has no string `object_type` or its kind is
+    /// not registered
+    pub fn validate(&self, value: &Value) -> Result<Vec<Violation>> {
+        let object_type = value.get(OBJECT_TYPE).and_then(Value::as_str).ok_or_else(|| {
+            ConstitutionalError::ProtocolError("A protocol object needs a string object_type".to_string())
+        })?;
+        let mut members = value.as_object().cloned().unwrap_or_default();
+        members.remove(OBJECT_TYPE);
+        if OBJECT_TYPES.contains(&object_type) {
+            return validate_against_schema(&Value::Object(members), object_type);
+        }
+        match self.custom.get(object_type) {
+            Some(validator) => Ok(validator(&members)),
+            None => Err(ConstitutionalError::ProtocolError(format!("Unregistered object type {:?}", object_type))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonPointer, VoteChoice, ViolationKind};
+    use serde_json::json;
+
+    fn vote() -> Value {
+        json!({
+            "object_type": "Vote",
+            "contract_id": "550e8400-e29b-41d4-a716-446655440000",
+            "voter_agent": "Gemini",
+            "vote": "approve",
+            "timestamp": "2025-11-21T09:00:00Z"
+        })
+    }
+
+    #[test]
+    fn test_dispatch_on_object_type() {
+        let object: ConstitutionalObject = serde_json::from_value(vote()).unwrap();
+        match &object {
+            ConstitutionalObject::Vote(vote) => assert_eq!(vote.choice, VoteChoice::Approve),
+            other => panic!("read as {}", other.object_type()),
+        }
+        assert_eq!(serde_json::to_value(&object).unwrap(), vote());
+        assert!(object.untagged().get(OBJECT_TYPE).is_none());
+        // The tag keeps a vote from hashing like its untagged members
+        assert_ne!(object.semantic_hash().unwrap(), crate::semantic_hash(&object.untagged()).unwrap());
+
+        let treaty = json!({"object_type": "Treaty", "parties": ["Claude", "Gemini"]});
+        let custom = ConstitutionalObject::from_value(treaty.clone()).unwrap();
+        assert_eq!(custom.object_type(), "Treaty");
+        assert_eq!(custom.to_value(), treaty);
+
+        let mut mistyped = vote();
+        mistyped["vote"] = json!("maybe");
+        assert!(serde_json::from_value::<ConstitutionalObject>(mistyped).is_err());
+        assert!(ConstitutionalObject::from_value(json!({"vote": "approve"})).is_err());
+    }
+
+    #[test]
+    fn test_registry_validates_custom_kinds() {
+        let mut registry = ObjectRegistry::new();
+        let treaty = json!({"object_type": "Treaty", "parties": ["Claude"]});
+        assert!(registry.parse(treaty.clone()).is_err());
+        registry
+            .register("Treaty", |members| match members.get("parties").and_then(Value::as_array) {
+                Some(parties) if parties.len() >= 2 => Vec::new(),
+                _ => vec![Violation {
+                    kind: ViolationKind::SchemaMismatch("a treaty needs two parties".to_string()),
+                    pointer: JsonPointer::parse("/parties").unwrap(),
+                }],
+            })
+            .unwrap();
+        assert!(registry.register("Treaty", |_| Vec::new()).is_err());
+        assert!(registry.register("Vote", |_| Vec::new()).is_err());
+
+        assert!(registry.accepts("Treaty"));
+        assert_eq!(registry.parse(treaty.clone()).unwrap().object_type(), "Treaty");
+        assert_eq!(registry.validate(&treaty).unwrap()[0].pointer.to_string(), "/parties");
+        assert_eq!(registry.validate(&vote()).unwrap(), []);
+        let mut anonymous = vote();
+        anonymous["voter_agent"] = json!("");
+        assert_eq!(registry.validate(&anonymous).unwrap()[0].pointer.to_string(), "/voter_agent");
+        assert!(registry.validate(&json!({"object_type": "Pact"})).is_err());
+    }
+}
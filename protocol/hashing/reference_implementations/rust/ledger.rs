@@ -0,0 +1,865 @@
+/// ledger.rs - An append-only, hash-chained file of constitutional objects
+///
+/// A `Ledger` is a file of JSON Lines, one record per line, each record holding an
+/// object, the object's semantic hash and the hash of the record before it:
+///
+/// ```json
+/// {"hash":"3c9a...","index":0,"object":{"event":"proposed",...},"object_hash":"5c1d..."}
+/// {"hash":"e1f0...","index":1,"object":{"event":"voted",...},"object_hash":"9b2e...","previous":"3c9a..."}
+/// ```
+///
+/// A record's `hash` is the semantic hash of its `index`, `object_hash` and `previous`,
+/// so it commits to the object and, through `previous`, to every record before it. The
+/// hash of the last record is the ledger's head: changing, removing or reordering any
+/// record changes the head, so a reader holding a head it trusts can tell whether the
+/// file is the one it was given.
+///
+/// Records are only ever appended, each one flushed to disk before `append` returns.
+/// Every hash is computed under the options the ledger was opened with, so a ledger must
+/// be reopened with the options it was written under.
+/// `verify` replays the file from the first record, rehashing every object and checking
+/// every link, and reports the first record that fails and where it starts in the file.
+/// A verifier that already trusts the head at some length, from an earlier run, skips
+/// the records up to it with `verify_from` and checks only the ones after.
+///
+/// So that no verifier has to start from the first record, a ledger can be checkpointed.
+/// A checkpoint is a record whose object is a signed statement of the ledger so far: how
+/// many records it holds, its head, and the state root, the RFC 6962 Merkle root of
+/// their hashes, with the root's frontier:
+///
+/// ```json
+/// {"object_type":"ledger_checkpoint","tree_size":1000000,"head":"e1f0...","state_root":"9b2e...",
+///  "frontier":{"algorithm":"sha256","size":1000000,"frontier":["07ab...",...]}}
+/// ```
+///
+/// A verifier that trusts the signer checks from the checkpoint on with
+/// `verify_checkpoint`. A checkpoint record exported as a snapshot is the ledger's state
+/// at the checkpoint: `restore` starts a new ledger file from it, which carries on from
+/// the checkpoint's head and state root without the records before.
+///
+/// A `RecordHandle` says where a record is and what its hash was when it was found, so
+/// it can be read again without a scan; `read` checks the record's hashes every time.
+///
+/// Behind a `Mutex`, a ledger is an `EventStore`, keeping a ratification workflow's
+/// events on disk.
+
+use crate::objects::{object, take, take_optional};
+use crate::{
+    semantic_hash_with, sign_with, verify_signed_with, Canonicalize, CanonicalizeOptions, ConstitutionalError,
+    EventStore, IncrementalMerkleTree, MerkleSnapshot, PublicKey, Result, SemanticHash, SignedObject, Signer,
+    WorkflowEvent, OBJECT_TYPE,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One line of a ledger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerRecord {
+    /// Position in the ledger, 0 for the first record
+    pub index: u64,
+    /// The object appended
+    pub object: Value,
+    /// The object's semantic hash
+    pub object_hash: String,
+    /// The hash of the record before; None for the first record
+    pub previous: Option<String>,
+    /// The hash of this record
+    pub hash: String,
+}
+
+impl LedgerRecord {
+    /// The record's JSON object, as it is written to its line.
+    pub fn to_value(&self) -> Value {
+        let mut record = json!({
+            "index": self.index,
+            "object": self.object,
+            "object_hash": self.object_hash,
+            "hash": self.hash,
+        });
+        if let Some(previous) = &self.previous {
+            record["previous"] = json!(previous);
+        }
+        record
+    }
+
+    /// The hash a record with these fields should have.
+    ///
+    /// # Returns
+    /// The semantic hash of `index`, `object_hash` and `previous`, or an error if the
+    /// options cannot hash it
+    pub fn link_hash(
+        index: u64,
+        object_hash: &str,
+        previous: Option<&str>,
+        options: &CanonicalizeOptions,
+    ) -> Result<String> {
+        let mut link = Map::new();
+        link.insert("index".to_string(), json!(index));
+        link.insert("object_hash".to_string(), json!(object_hash));
+        if let Some(previous) = previous {
+            link.insert("previous".to_string(), json!(previous));
+        }
+        semantic_hash_with(&Value::Object(link), options)
+    }
+
+    /// Read a record from its line, without checking any of its hashes.
+    ///
+    /// # Returns
+    /// The record, or a ProtocolError naming the line if it is not one
+    pub(crate) fn from_line(line: &str, number: u64) -> Result<LedgerRecord> {
+        serde_json::from_str(line)
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Ledger line {}: {}", number, e)))
+    }
+}
+
+impl Serialize for LedgerRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LedgerRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<LedgerRecord, D::Error> {
+        let mut map = object(deserializer, "a ledger record")?;
+        Ok(LedgerRecord {
+            index: take(&mut map, "index")?,
+            object: take(&mut map, "object")?,
+            object_hash: take(&mut map, "object_hash")?,
+            previous: take_optional(&mut map, "previous")?,
+            hash: take(&mut map, "hash")?,
+        })
+    }
+}
+
+/// The `object_type` of a checkpoint's statement.
+pub const CHECKPOINT_TYPE: &str = "ledger_checkpoint";
+
+/// A statement of a ledger's state after some number of records, signed into a
+/// checkpoint record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerCheckpoint {
+    /// Number of records before the checkpoint
+    pub tree_size: u64,
+    /// Hash of the last of them
+    pub head: String,
+    /// Merkle root of their hashes
+    pub state_root: SemanticHash,
+    /// The Merkle tree's frontier, from which a restored ledger carries on
+    pub frontier: MerkleSnapshot,
+}
+
+impl LedgerCheckpoint {
+    /// The statement's JSON object.
+    pub fn to_value(&self) -> Value {
+        json!({
+            OBJECT_TYPE: CHECKPOINT_TYPE,
+            "tree_size": self.tree_size,
+            "head": self.head,
+            "state_root": self.state_root.to_hex(),
+            "frontier": self.frontier,
+        })
+    }
+
+    /// Read a statement, checking that its frontier has its tree size and state root.
+    ///
+    /// # Returns
+    /// The statement, or a ProtocolError if the value is not one or does not agree with
+    /// its frontier
+    pub fn from_value(value: &Value) -> Result<LedgerCheckpoint> {
+        if value.get(OBJECT_TYPE).and_then(Value::as_str) != Some(CHECKPOINT_TYPE) {
+            return Err(ConstitutionalError::ProtocolError("Not a ledger checkpoint".to_string()));
+        }
+        let checkpoint: LedgerCheckpoint = serde_json::from_value(value.clone())
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Malformed ledger checkpoint: {}", e)))?;
+        let tree = IncrementalMerkleTree::restore(checkpoint.frontier.clone())?;
+        if tree.len() != checkpoint.tree_size || tree.root() != checkpoint.state_root {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Ledger checkpoint at {} records does not match its frontier",
+                checkpoint.tree_size
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Read the statement of a checkpoint signed by `signer`.
+    ///
+    /// # Arguments
+    /// * `signed` - The signed checkpoint
+    /// * `signer` - The key trusted to sign checkpoints
+    /// * `options` - Canonicalization options the ledger's hashes are computed under
+    ///
+    /// # Returns
+    /// The statement, or a ProtocolError if it is not signed by `signer` or is not a
+    /// checkpoint
+    pub fn from_signed(
+        signed: &SignedObject,
+        signer: &PublicKey,
+        options: &CanonicalizeOptions,
+    ) -> Result<LedgerCheckpoint> {
+        if signed.signer.parse::<PublicKey>()? != *signer || !verify_signed_with(signed, options)? {
+            return Err(ConstitutionalError::ProtocolError(format!("Ledger checkpoint is not signed by {}", signer)));
+        }
+        LedgerCheckpoint::from_value(&signed.payload)
+    }
+
+    /// The statement of a checkpoint record, without checking its signature.
+    ///
+    /// # Returns
+    /// The statement, or a ProtocolError if the record's object is not a signed
+    /// checkpoint of the records before it
+    fn of_record(record: &LedgerRecord) -> Result<LedgerCheckpoint> {
+        let signed: SignedObject = serde_json::from_value(record.object.clone()).map_err(|e| {
+            ConstitutionalError::ProtocolError(format!("Ledger record {} is not a checkpoint: {}", record.index, e))
+        })?;
+        let checkpoint = LedgerCheckpoint::from_value(&signed.payload)?;
+        if checkpoint.tree_size != record.index || record.previous.as_deref() != Some(checkpoint.head.as_str()) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Ledger record {} does not checkpoint the records before it",
+                record.index
+            )));
+        }
+        Ok(checkpoint)
+    }
+}
+
+impl Serialize for LedgerCheckpoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LedgerCheckpoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<LedgerCheckpoint, D::Error> {
+        let mut map = object(deserializer, "a ledger checkpoint")?;
+        Ok(LedgerCheckpoint {
+            tree_size: take(&mut map, "tree_size")?,
+            head: take(&mut map, "head")?,
+            state_root: take(&mut map, "state_root")?,
+            frontier: take(&mut map, "frontier")?,
+        })
+    }
+}
+
+/// What is wrong with a ledger record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedgerFault {
+    /// The line is not a whole record; why it cannot be read
+    Unreadable(String),
+    /// The record's `index` is not its position.
+    Index,
+    /// The record's `previous` is not the hash of the record before it.
+    Link,
+    /// The object's semantic hash is not the record's `object_hash`.
+    ObjectHash,
+    /// The record's `hash` is not the hash of its fields.
+    Hash,
+    /// The record is not the one trusted to end the ledger at its length.
+    Anchor,
+    /// The file ends before the position; the ledger has been cut short.
+    Truncated,
+}
+
+impl fmt::Display for LedgerFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LedgerFault::Unreadable(reason) => write!(f, "is not a record: {}", reason),
+            LedgerFault::Index => write!(f, "is numbered out of place"),
+            LedgerFault::Link => write!(f, "does not follow the record before it"),
+            LedgerFault::ObjectHash => write!(f, "does not hash its object"),
+            LedgerFault::Hash => write!(f, "does not hash its fields"),
+            LedgerFault::Anchor => write!(f, "is not the trusted head"),
+            LedgerFault::Truncated => write!(f, "is missing"),
+        }
+    }
+}
+
+/// The first record of a ledger that fails verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerCorruption {
+    /// Position of the record, 0 for the first
+    pub index: u64,
+    /// Byte offset of its line in the file, or of the end of the file if it is missing
+    pub offset: u64,
+    /// The record as read; None if its line is not one or it is missing
+    pub record: Option<LedgerRecord>,
+    /// What is wrong with it
+    pub fault: LedgerFault,
+}
+
+impl fmt::Display for LedgerCorruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ledger record {} at byte {} {}", self.index, self.offset, self.fault)
+    }
+}
+
+/// Where a record is in a ledger file, and the hash it had there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordHandle {
+    index: u64,
+    offset: u64,
+    hash: String,
+}
+
+impl RecordHandle {
+    /// Position of the record, 0 for the first.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Byte offset of its line in the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The record's hash.
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// An append-only, hash-chained file of records.
+#[derive(Debug)]
+pub struct Ledger {
+    path: PathBuf,
+    file: File,
+    options: CanonicalizeOptions,
+    head: Option<String>,
+    len: u64,
+    /// Index of the file's first record, past 0 if the ledger was restored
+    start: u64,
+    /// Hash of the record before the file's first; None if the file starts the ledger
+    base: Option<String>,
+    /// Merkle tree of the record hashes
+    tree: IncrementalMerkleTree,
+}
+
+impl Ledger {
+    /// Open the ledger at `path` under the default options, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Ledger> {
+        Ledger::open_with(path, CanonicalizeOptions::default())
+    }
+
+    /// Open the ledger at `path`, creating an empty one if there is no file. An existing
+    /// ledger continues from its last record, and a restored one from the checkpoint it
+    /// starts with.
+    ///
+    /// # Arguments
+    /// * `path` - The ledger file
+    /// * `options` - Canonicalization options every hash is computed under, with a
+    ///   256-bit algorithm
+    ///
+    /// # Returns
+    /// The ledger, an IoError if the file cannot be opened or read, a HashingError if
+    /// the algorithm is not 256-bit, or a ProtocolError if a line is not a record
+    /// following the lines before it
+    pub fn open_with(path: impl AsRef<Path>, options: CanonicalizeOptions) -> Result<Ledger> {
+        let path = path.as_ref().to_path_buf();
+        let file = fs::OpenOptions::new().create(true).read(true).append(true).open(&path)?;
+        let mut tree = IncrementalMerkleTree::new(options.hash_algorithm)?;
+        let (mut start, mut base, mut head, mut len) = (0, None, None, 0);
+        for (line, number) in BufReader::new(&file).lines().zip(1..) {
+            let record = LedgerRecord::from_line(&line?, number)?;
+            if number == 1 && record.index > 0 {
+                let checkpoint = LedgerCheckpoint::of_record(&record)?;
+                tree = IncrementalMerkleTree::restore(checkpoint.frontier)?;
+                (start, base, len) = (record.index, Some(checkpoint.head), record.index);
+            }
+            if record.index != len {
+                return Err(ConstitutionalError::ProtocolError(format!(
+                    "Ledger line {} holds record {}, not {}",
+                    number, record.index, len
+                )));
+            }
+            tree.append(&record.hash.parse()?);
+            head = Some(record.hash);
+            len += 1;
+        }
+        Ok(Ledger { path, file, options, head, len, start, base, tree })
+    }
+
+    /// Start a ledger from a snapshot, as the state of the ledger it was exported from
+    /// at its checkpoint.
+    ///
+    /// # Arguments
+    /// * `snapshot` - The snapshot file, written by `export_snapshot`
+    /// * `path` - The new ledger file, which must not exist
+    /// * `signer` - The key trusted to sign checkpoints
+    /// * `options` - Canonicalization options the exporting ledger was opened with
+    ///
+    /// # Returns
+    /// The ledger, an IoError if either file cannot be read or written, or a
+    /// ProtocolError if the snapshot is not a single checkpoint record signed by
+    /// `signer`
+    pub fn restore(
+        snapshot: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+        signer: &PublicKey,
+        options: CanonicalizeOptions,
+    ) -> Result<Ledger> {
+        let text = fs::read_to_string(snapshot)?;
+        let record = match text.strip_suffix('\n') {
+            Some(line) if !line.contains('\n') => LedgerRecord::from_line(line, 1)?,
+            _ => return Err(ConstitutionalError::ProtocolError("A snapshot is one checkpoint record".to_string())),
+        };
+        let signed: SignedObject = serde_json::from_value(record.object.clone())
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Snapshot is not a checkpoint: {}", e)))?;
+        LedgerCheckpoint::from_signed(&signed, signer, &options)?;
+        fs::OpenOptions::new().write(true).create_new(true).open(path.as_ref())?.write_all(text.as_bytes())?;
+        let ledger = Ledger::open_with(path, options)?;
+        if let Some(corruption) = ledger.verify()? {
+            return Err(ConstitutionalError::ProtocolError(format!("Snapshot holds a corrupt {}", corruption)));
+        }
+        Ok(ledger)
+    }
+
+    /// Append an object as the next record.
+    ///
+    /// # Returns
+    /// The new head hash, or an error if the object cannot be hashed or the record
+    /// cannot be written, in which case the head does not move
+    pub fn append<T: Canonicalize + ?Sized>(&mut self, object: &T) -> Result<String> {
+        let object = object.canonical_value();
+        let object_hash = semantic_hash_with(&object, &self.options)?;
+        let hash = LedgerRecord::link_hash(self.len, &object_hash, self.head.as_deref(), &self.options)?;
+        let leaf = hash.parse()?;
+        let record = LedgerRecord { index: self.len, object, object_hash, previous: self.head.clone(), hash };
+
+        let mut line = record.to_value().to_string();
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        self.tree.append(&leaf);
+        self.len += 1;
+        self.head = Some(record.hash.clone());
+        Ok(record.hash)
+    }
+
+    /// The hash of the last record; None if the ledger is empty.
+    pub fn head(&self) -> Option<&str> {
+        self.head.as_deref()
+    }
+
+    /// The Merkle root of every record's hash.
+    pub fn state_root(&self) -> SemanticHash {
+        self.tree.root()
+    }
+
+    /// Number of records, including any before a restored ledger's checkpoint.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the ledger has no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The ledger file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Options the ledger's hashes are computed under.
+    pub fn options(&self) -> &CanonicalizeOptions {
+        &self.options
+    }
+
+    /// Sign a checkpoint of the records so far and append it as the next record.
+    ///
+    /// # Returns
+    /// The signed checkpoint, or a ProtocolError if the ledger is empty, or the error
+    /// signing or appending gives
+    pub fn checkpoint(&mut self, signer: &dyn Signer) -> Result<SignedObject> {
+        let Some(head) = self.head.clone() else {
+            return Err(ConstitutionalError::ProtocolError("An empty ledger has nothing to checkpoint".to_string()));
+        };
+        let checkpoint = LedgerCheckpoint {
+            tree_size: self.len,
+            head,
+            state_root: self.tree.root(),
+            frontier: self.tree.snapshot(),
+        };
+        let signed = sign_with(&checkpoint.to_value(), signer, &self.options)?;
+        let object = serde_json::to_value(&signed).map_err(|e| ConstitutionalError::HashingError(e.to_string()))?;
+        self.append(&object)?;
+        Ok(signed)
+    }
+
+    /// Write the record of a checkpoint to `path` as a snapshot for `restore`.
+    ///
+    /// # Returns
+    /// An IoError if the ledger cannot be read or the snapshot written, or a
+    /// ProtocolError if the checkpoint is not one of the ledger's records
+    pub fn export_snapshot(&self, checkpoint: &SignedObject, path: impl AsRef<Path>) -> Result<()> {
+        let tree_size = LedgerCheckpoint::from_value(&checkpoint.payload)?.tree_size;
+        let object = serde_json::to_value(checkpoint).map_err(|e| ConstitutionalError::HashingError(e.to_string()))?;
+        for record in self.records()? {
+            let record = record?;
+            if record.index == tree_size && record.object == object {
+                let mut line = record.to_value().to_string();
+                line.push('\n');
+                fs::write(path, line)?;
+                return Ok(());
+            }
+        }
+        Err(ConstitutionalError::ProtocolError(format!("Ledger has no checkpoint at record {}", tree_size)))
+    }
+
+    /// Read the record a handle points to, checking that its object hashes to its
+    /// `object_hash`, that its fields hash to its `hash` and that the hash is the handle's.
+    ///
+    /// # Returns
+    /// The record, an IoError if the file cannot be read, or a ProtocolError if the
+    /// record fails any check
+    pub fn read(&self, handle: &RecordHandle) -> Result<LedgerRecord> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(handle.offset))?;
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        let fault = match self.check_line(&line, handle.index, None) {
+            (Some(record), None) if record.hash == handle.hash => return Ok(record),
+            (_, Some(fault)) => fault.to_string(),
+            (_, None) => "is not the record the handle was taken of".to_string(),
+        };
+        Err(ConstitutionalError::ProtocolError(format!(
+            "Ledger record {} at byte {} {}",
+            handle.index, handle.offset, fault
+        )))
+    }
+
+    /// Read every whole record from `offset` on, without checking their hashes.
+    ///
+    /// # Returns
+    /// The offset after the last whole record, an IoError if the file cannot be read, or
+    /// a ProtocolError if a line is not a record
+    pub(crate) fn scan(&self, offset: u64, mut visit: impl FnMut(RecordHandle, LedgerRecord)) -> Result<u64> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut line = String::new();
+        let mut offset = offset;
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)? as u64;
+            // A line without its newline is still being written
+            let Some(text) = line.strip_suffix('\n') else {
+                return Ok(offset);
+            };
+            let record: LedgerRecord = serde_json::from_str(text)
+                .map_err(|e| ConstitutionalError::ProtocolError(format!("Ledger record at byte {}: {}", offset, e)))?;
+            visit(RecordHandle { index: record.index, offset, hash: record.hash.clone() }, record);
+            offset += read;
+        }
+    }
+
+    /// Read the records from the first, without checking their hashes.
+    ///
+    /// # Returns
+    /// The records in order, each an error if its line is not a record, or an IoError
+    /// if the file cannot be opened
+    pub fn records(&self) -> Result<impl Iterator<Item = Result<LedgerRecord>>> {
+        let lines = BufReader::new(File::open(&self.path)?).lines();
+        Ok(lines.zip(1..).map(|(line, number)| LedgerRecord::from_line(&line?, number)))
+    }
+
+    /// Replay the whole ledger, checking every record's object hash, link and hash, and
+    /// that the file still holds every record appended through this ledger.
+    ///
+    /// # Returns
+    /// The first corrupt record, None if there is none, or an IoError if the file cannot
+    /// be read
+    pub fn verify(&self) -> Result<Option<LedgerCorruption>> {
+        self.replay(None)
+    }
+
+    /// Replay the ledger from a checkpoint signed by a trusted key, as `verify_from` its
+    /// tree size and head.
+    ///
+    /// # Returns
+    /// The first corrupt record, None if there is none, or a ProtocolError if the
+    /// checkpoint is not signed by `signer`
+    pub fn verify_checkpoint(&self, checkpoint: &SignedObject, signer: &PublicKey) -> Result<Option<LedgerCorruption>> {
+        let checkpoint = LedgerCheckpoint::from_signed(checkpoint, signer, &self.options)?;
+        self.verify_from(checkpoint.tree_size, &checkpoint.head)
+    }
+
+    /// Replay the ledger from a head already trusted, checking only the record that
+    /// ends there and the records after it. The ones before are skipped unread.
+    ///
+    /// # Arguments
+    /// * `tree_size` - The number of records the trusted head ends
+    /// * `head` - The hash of the last of them
+    ///
+    /// # Returns
+    /// The first corrupt record, None if there is none, or an IoError if the file cannot
+    /// be read. With no records to skip, the whole ledger is replayed.
+    pub fn verify_from(&self, tree_size: u64, head: &str) -> Result<Option<LedgerCorruption>> {
+        match tree_size {
+            0 => self.replay(None),
+            _ => self.replay(Some((tree_size - 1, head))),
+        }
+    }
+
+    /// Check the records from the anchor's position on, trusting the link of the record
+    /// there if its hash is the anchor's, or every record if there is no anchor.
+    fn replay(&self, anchor: Option<(u64, &str)>) -> Result<Option<LedgerCorruption>> {
+        // A restored ledger holds no records before its checkpoint, which trusts the head
+        let anchor = match anchor {
+            Some((at, head)) if at < self.start => {
+                if at + 1 != self.start || self.base.as_deref() != Some(head) {
+                    return Ok(Some(LedgerCorruption {
+                        index: at,
+                        offset: 0,
+                        record: None,
+                        fault: LedgerFault::Anchor,
+                    }));
+                }
+                None
+            }
+            anchor => anchor,
+        };
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let first = anchor.map_or(self.start, |(at, _)| at);
+        let mut line = Vec::new();
+        let (mut index, mut offset) = (self.start, 0);
+        let mut previous = self.base.clone();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)? as u64;
+            if read == 0 {
+                break;
+            }
+            if index >= first {
+                let linked = anchor.is_none() || index > first;
+                let (record, mut fault) = self.check_line(&line, index, linked.then_some(previous.as_deref()));
+                if let (None, Some((at, head)), Some(record)) = (&fault, anchor, &record) {
+                    if at == index && record.hash != head {
+                        fault = Some(LedgerFault::Anchor);
+                    }
+                }
+                if let Some(fault) = fault {
+                    return Ok(Some(LedgerCorruption { index, offset, record, fault }));
+                }
+                previous = record.map(|record| record.hash);
+            }
+            index += 1;
+            offset += read;
+        }
+        let expected = anchor.map_or(0, |(at, _)| at + 1).max(self.len);
+        if index < expected {
+            return Ok(Some(LedgerCorruption { index, offset, record: None, fault: LedgerFault::Truncated }));
+        }
+        Ok(None)
+    }
+
+    /// Read and check one line. `previous` is the hash the record's `previous` must be,
+    /// None for the first record; the link is not checked if it is not given.
+    fn check_line(
+        &self,
+        line: &[u8],
+        index: u64,
+        previous: Option<Option<&str>>,
+    ) -> (Option<LedgerRecord>, Option<LedgerFault>) {
+        let Some(text) = line.strip_suffix(b"\n") else {
+            return (None, Some(LedgerFault::Unreadable("the line is not terminated".to_string())));
+        };
+        let record = std::str::from_utf8(text)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str::<LedgerRecord>(text).map_err(|e| e.to_string()));
+        let record = match record {
+            Ok(record) => record,
+            Err(reason) => return (None, Some(LedgerFault::Unreadable(reason))),
+        };
+        let object_hash = semantic_hash_with(&record.object, &self.options).ok();
+        let hash = LedgerRecord::link_hash(index, &record.object_hash, record.previous.as_deref(), &self.options).ok();
+        let fault = if record.index != index {
+            Some(LedgerFault::Index)
+        } else if previous.is_some_and(|previous| record.previous.as_deref() != previous) {
+            Some(LedgerFault::Link)
+        } else if object_hash.as_ref() != Some(&record.object_hash) {
+            Some(LedgerFault::ObjectHash)
+        } else if hash.as_ref() != Some(&record.hash) {
+            Some(LedgerFault::Hash)
+        } else {
+            None
+        };
+        (Some(record), fault)
+    }
+}
+
+/// A ledger records every workflow event it is given, in order.
+impl EventStore for Mutex<Ledger> {
+    fn append(&self, event: &WorkflowEvent) -> Result<()> {
+        self.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).append(event).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashAlgorithm, Keypair, MerkleTree};
+
+    fn ledger_path(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ocp-ledger-{}-{}.jsonl", test, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_append_chains_records() {
+        let path = ledger_path("append");
+        let mut ledger = Ledger::open(&path).unwrap();
+        assert!(ledger.is_empty());
+        assert_eq!(ledger.head(), None);
+
+        let first = ledger.append(&json!({"contract_id": "c-17", "event": "proposed"})).unwrap();
+        let second = ledger.append(&json!({"contract_id": "c-17", "event": "voted"})).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(ledger.head(), Some(second.as_str()));
+        assert_eq!(ledger.len(), 2);
+
+        let records: Vec<LedgerRecord> = ledger.records().unwrap().map(Result::unwrap).collect();
+        assert_eq!(records[0].previous, None);
+        assert_eq!(records[0].hash, first);
+        assert_eq!(records[1].previous.as_deref(), Some(first.as_str()));
+        assert_eq!(records[1].object["event"], "voted");
+        let options = CanonicalizeOptions::default();
+        assert_eq!(records[1].object_hash, semantic_hash_with(&records[1].object, &options).unwrap());
+        assert_eq!(LedgerRecord::link_hash(1, &records[1].object_hash, Some(&first), &options).unwrap(), second);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_reopen_continues_from_head() {
+        let path = ledger_path("reopen");
+        let head = {
+            let mut ledger = Ledger::open(&path).unwrap();
+            ledger.append(&json!({"n": 1})).unwrap();
+            ledger.append(&json!({"n": 2})).unwrap()
+        };
+        let mut ledger = Ledger::open(&path).unwrap();
+        assert_eq!((ledger.len(), ledger.head()), (2, Some(head.as_str())));
+        ledger.append(&json!({"n": 3})).unwrap();
+        let records: Vec<LedgerRecord> = ledger.records().unwrap().map(Result::unwrap).collect();
+        assert_eq!(records[2].index, 2);
+        assert_eq!(records[2].previous, Some(head));
+
+        // A torn last line is not a record
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"hash\":").unwrap();
+        assert!(Ledger::open(&path).is_err());
+        let _ = fs::remove_file(path);
+    }
+
+    fn written(test: &str, count: u64) -> (Ledger, Vec<String>) {
+        let mut ledger = Ledger::open(ledger_path(test)).unwrap();
+        for n in 0..count {
+            ledger.append(&json!({"agent": "Claude", "n": n})).unwrap();
+        }
+        let lines = fs::read_to_string(ledger.path()).unwrap().lines().map(|line| format!("{}\n", line)).collect();
+        (ledger, lines)
+    }
+
+    #[test]
+    fn test_verify_finds_first_corruption() {
+        let (ledger, lines) = written("verify", 4);
+        assert_eq!(ledger.verify().unwrap(), None);
+        let fault_after = |lines: &[String]| {
+            fs::write(ledger.path(), lines.concat()).unwrap();
+            ledger.verify().unwrap().map(|corruption| (corruption.index, corruption.offset, corruption.fault))
+        };
+        let start = |index: usize| lines[..index].iter().map(|line| line.len() as u64).sum::<u64>();
+
+        let mut edited = lines.clone();
+        edited[1] = edited[1].replace("Claude", "Gemini");
+        edited[2] = edited[2].replace("Claude", "Gemini");
+        assert_eq!(fault_after(&edited), Some((1, start(1), LedgerFault::ObjectHash)));
+        let mut removed = lines.clone();
+        removed.remove(1);
+        assert_eq!(fault_after(&removed), Some((1, start(1), LedgerFault::Index)));
+        let mut torn = lines.clone();
+        torn[3].truncate(20);
+        assert!(matches!(fault_after(&torn), Some((3, _, LedgerFault::Unreadable(_)))));
+        assert_eq!(fault_after(&lines[..3]), Some((3, start(3), LedgerFault::Truncated)));
+        fs::write(ledger.path(), edited.concat()).unwrap();
+        assert_eq!(ledger.verify().unwrap().unwrap().record.unwrap().object["agent"], "Gemini");
+        let _ = fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_verify_from_trusted_head() {
+        let (ledger, mut lines) = written("verify-from", 4);
+        let trusted = LedgerRecord::from_line(&lines[1], 2).unwrap().hash;
+        assert_eq!(ledger.verify_from(2, &trusted).unwrap(), None);
+        assert_eq!(ledger.verify_from(2, ledger.head().unwrap()).unwrap().unwrap().fault, LedgerFault::Anchor);
+        assert_eq!(ledger.verify_from(9, &trusted).unwrap().unwrap().fault, LedgerFault::Truncated);
+
+        // Records before the trusted head are not read again
+        lines[0] = "not a record\n".to_string();
+        fs::write(ledger.path(), lines.concat()).unwrap();
+        assert_eq!(ledger.verify_from(2, &trusted).unwrap(), None);
+        assert_eq!(ledger.verify().unwrap().unwrap().index, 0);
+        let _ = fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_checkpoint_anchors_verification() {
+        let (mut ledger, _) = written("checkpoint", 3);
+        let key = Keypair::from_secret(&[7; 32]);
+        let head = ledger.head().unwrap().to_string();
+        let signed = ledger.checkpoint(&key).unwrap();
+        let checkpoint = LedgerCheckpoint::from_signed(&signed, &key.public_key(), ledger.options()).unwrap();
+        assert_eq!((checkpoint.tree_size, checkpoint.head.as_str()), (3, head.as_str()));
+        let leaves: Vec<SemanticHash> =
+            ledger.records().unwrap().take(3).map(|record| record.unwrap().hash.parse().unwrap()).collect();
+        assert_eq!(checkpoint.state_root, MerkleTree::from_leaves(&leaves, HashAlgorithm::Sha256).unwrap().root());
+        assert_eq!(ledger.len(), 4);
+        ledger.append(&json!({"agent": "Gemini", "n": 4})).unwrap();
+        assert_eq!(ledger.verify_checkpoint(&signed, &key.public_key()).unwrap(), None);
+
+        // Records before the checkpoint are trusted, not read; a forged checkpoint is refused
+        let text = fs::read_to_string(ledger.path()).unwrap();
+        fs::write(ledger.path(), text.replacen("Claude", "Gemini", 1)).unwrap();
+        assert_eq!(ledger.verify().unwrap().unwrap().fault, LedgerFault::ObjectHash);
+        assert_eq!(ledger.verify_checkpoint(&signed, &key.public_key()).unwrap(), None);
+        let other = Keypair::from_secret(&[8; 32]);
+        assert!(ledger.verify_checkpoint(&signed, &other.public_key()).is_err());
+        let mut forged = sign_with(&checkpoint.to_value(), &other, ledger.options()).unwrap();
+        forged.signer = signed.signer.clone();
+        assert!(ledger.verify_checkpoint(&forged, &key.public_key()).is_err());
+        let _ = fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_snapshot_restores_at_checkpoint() {
+        let (mut ledger, _) = written("snapshot", 3);
+        let key = Keypair::from_secret(&[7; 32]);
+        let signed = ledger.checkpoint(&key).unwrap();
+        let (head, state_root) = (ledger.head().unwrap().to_string(), ledger.state_root());
+        ledger.append(&json!({"agent": "Gemini", "n": 4})).unwrap();
+        let snapshot = ledger_path("snapshot-export");
+        ledger.export_snapshot(&signed, &snapshot).unwrap();
+
+        let path = ledger_path("snapshot-restored");
+        let options = CanonicalizeOptions::default();
+        let other = Keypair::from_secret(&[8; 32]).public_key();
+        assert!(Ledger::restore(&snapshot, &path, &other, options.clone()).is_err());
+        let mut restored = Ledger::restore(&snapshot, &path, &key.public_key(), options.clone()).unwrap();
+        assert_eq!((restored.len(), restored.head(), restored.state_root()), (4, Some(head.as_str()), state_root));
+        assert!(Ledger::restore(&snapshot, &path, &key.public_key(), options).is_err());
+
+        // The restored ledger carries on as the original did
+        assert_eq!(restored.append(&json!({"agent": "Gemini", "n": 4})).unwrap(), ledger.head().unwrap());
+        assert_eq!(restored.state_root(), ledger.state_root());
+        let reopened = Ledger::open(&path).unwrap();
+        assert_eq!(reopened.verify().unwrap(), None);
+        assert_eq!(reopened.verify_checkpoint(&signed, &key.public_key()).unwrap(), None);
+        assert_eq!(reopened.verify_from(2, &head).unwrap().unwrap().fault, LedgerFault::Anchor);
+        for file in [ledger.path(), &snapshot, &path] {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+/// ledger_index.rs - Secondary indexes and queries over a ledger
+///
+/// Finding every vote one agent cast should not mean reading a ledger of millions of
+/// records. A `LedgerIndex` reads the ledger once and keeps, for each of a few keys, the
+/// records that have it:
+///
+/// ```text
+/// agent         proposer_agent, voter_agent, challenger_agent or arbiter_agent, or a
+///               workflow event's proposer or voter
+/// action_type   a contract's action_type
+/// object_type   the object_type tag, or a workflow event's name, e.g. voted
+/// article       the number of the article a contract or amendment targets
+/// timestamp     timestamp, or a workflow event's at, to the second
+/// ```
+///
+/// The keys of a signed object are read from its payload. A `LedgerQuery` names keys a
+/// record must all have, and the index answers with `RecordHandle`s in ledger order.
+/// `Ledger::read` turns a handle back into its record and checks the record's hashes,
+/// so an index gone stale, or a file altered after it was indexed, can yield no record
+/// that does not hash to its place in the chain. `refresh` indexes the records appended
+/// since the index last read the ledger.
+
+use crate::timestamp::unix_seconds;
+use crate::{ConstitutionalError, Ledger, RecordHandle, Result, TargetResolver, OBJECT_TYPE};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+/// Members naming an agent.
+const AGENT_MEMBERS: &[&str] =
+    &["proposer_agent", "voter_agent", "challenger_agent", "arbiter_agent", "proposer", "voter"];
+
+/// Keys a record must have to match, all of them; a query with none matches every record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LedgerQuery {
+    agent: Option<String>,
+    action_type: Option<String>,
+    object_type: Option<String>,
+    article: Option<u32>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+impl LedgerQuery {
+    /// A query matching every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match records naming the agent.
+    pub fn agent(mut self, agent: &str) -> Self {
+        self.agent = Some(agent.to_string());
+        self
+    }
+
+    /// Match contracts with the action type.
+    pub fn action_type(mut self, action_type: &str) -> Self {
+        self.action_type = Some(action_type.to_string());
+        self
+    }
+
+    /// Match objects of the type, or workflow events of the name.
+    pub fn object_type(mut self, object_type: &str) -> Self {
+        self.object_type = Some(object_type.to_string());
+        self
+    }
+
+    /// Match contracts and amendments targeting the article or a place in it.
+    pub fn article(mut self, number: u32) -> Self {
+        self.article = Some(number);
+        self
+    }
+
+    /// Match records timestamped at or after an RFC 3339 date-time.
+    pub fn since(mut self, timestamp: &str) -> Self {
+        self.since = Some(timestamp.to_string());
+        self
+    }
+
+    /// Match records timestamped before an RFC 3339 date-time.
+    pub fn until(mut self, timestamp: &str) -> Self {
+        self.until = Some(timestamp.to_string());
+        self
+    }
+}
+
+/// The records of a ledger by agent, action type, object type, target article and
+/// timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct LedgerIndex {
+    resolver: TargetResolver,
+    /// Byte offset after the last record indexed
+    end: u64,
+    handles: BTreeMap<u64, RecordHandle>,
+    agents: BTreeMap<String, BTreeSet<u64>>,
+    action_types: BTreeMap<String, BTreeSet<u64>>,
+    object_types: BTreeMap<String, BTreeSet<u64>>,
+    articles: BTreeMap<u32, BTreeSet<u64>>,
+    /// Records by Unix seconds
+    timestamps: BTreeMap<i64, BTreeSet<u64>>,
+}
+
+impl LedgerIndex {
+    /// An index of no records, resolving targets with no aliases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve the targets of records indexed from now on with `resolver`.
+    pub fn resolver(mut self, resolver: TargetResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Index every record of a ledger.
+    ///
+    /// # Returns
+    /// The index, or the error `refresh` gives
+    pub fn build(ledger: &Ledger) -> Result<LedgerIndex> {
+        let mut index = LedgerIndex::new();
+        index.refresh(ledger)?;
+        Ok(index)
+    }
+
+    /// Index the records appended to the ledger since it was last read.
+    ///
+    /// # Returns
+    /// How many records were indexed, an IoError if the file cannot be read, or a
+    /// ProtocolError if a line is not a record
+    pub fn refresh(&mut self, ledger: &Ledger) -> Result<u64> {
+        let mut count = 0;
+        self.end = ledger.scan(self.end, |handle, record| {
+            self.insert(handle, &record.object);
+            count += 1;
+        })?;
+        Ok(count)
+    }
+
+    /// Number of records indexed.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Whether no records are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Find the records matching a query.
+    ///
+    /// # Returns
+    /// Handles of the matching records in ledger order, or a ProtocolError if a bound
+    /// of the query's range is not an RFC 3339 date-time
+    pub fn query(&self, query: &LedgerQuery) -> Result<Vec<RecordHandle>> {
+        let mut matches: Option<BTreeSet<u64>> = None;
+        let mut narrow = |records: Option<&BTreeSet<u64>>| {
+            let records = records.cloned().unwrap_or_default();
+            matches = Some(match matches.take() {
+                Some(matches) => matches.intersection(&records).copied().collect(),
+                None => records,
+            });
+        };
+        if let Some(agent) = &query.agent {
+            narrow(self.agents.get(agent));
+        }
+        if let Some(action_type) = &query.action_type {
+            narrow(self.action_types.get(action_type));
+        }
+        if let Some(object_type) = &query.object_type {
+            narrow(self.object_types.get(object_type));
+        }
+        if let Some(article) = query.article {
+            narrow(self.articles.get(&article));
+        }
+        if query.since.is_some() || query.until.is_some() {
+            let since = query.since.as_deref().map(seconds).transpose()?;
+            let until = query.until.as_deref().map(seconds).transpose()?;
+            let in_range = match (since, until) {
+                (Some(since), Some(until)) if since >= until => BTreeSet::new(),
+                _ => {
+                    let range = (
+                        since.map_or(Bound::Unbounded, Bound::Included),
+                        until.map_or(Bound::Unbounded, Bound::Excluded),
+                    );
+                    self.timestamps.range(range).flat_map(|(_, records)| records).copied().collect()
+                }
+            };
+            narrow(Some(&in_range));
+        }
+        Ok(match matches {
+            Some(matches) => matches.iter().filter_map(|index| self.handles.get(index)).cloned().collect(),
+            None => self.handles.values().cloned().collect(),
+        })
+    }
+
+    fn insert(&mut self, handle: RecordHandle, object: &Value) {
+        let index = handle.index();
+        let subject = match object.get("payload") {
+            Some(payload) if object.get("signature").is_some() => payload,
+            _ => object,
+        };
+        let text = |member: &str| subject.get(member).and_then(Value::as_str);
+        for agent in AGENT_MEMBERS.iter().filter_map(|member| text(member)) {
+            self.agents.entry(agent.to_string()).or_default().insert(index);
+        }
+        if let Some(action_type) = text("action_type") {
+            self.action_types.entry(action_type.to_string()).or_default().insert(index);
+        }
+        if let Some(object_type) = text(OBJECT_TYPE).or_else(|| text("event")) {
+            self.object_types.entry(object_type.to_string()).or_default().insert(index);
+        }
+        if let Some(article) = self.article_of(subject) {
+            self.articles.entry(article).or_default().insert(index);
+        }
+        if let Some(seconds) = text("timestamp").or_else(|| text("at")).and_then(unix_seconds) {
+            self.timestamps.entry(seconds).or_default().insert(index);
+        }
+        self.handles.insert(index, handle);
+    }
+
+    /// The article a contract's action, an amendment's target or an amended article
+    /// names, if it resolves.
+    fn article_of(&self, subject: &Value) -> Option<u32> {
+        let address = if let Some(action) = subject.get("action") {
+            let id = subject.get("id").and_then(Value::as_str).unwrap_or_default();
+            self.resolver.resolve_action(id, action)
+        } else if let Some(target) = subject.get("target").and_then(Value::as_str) {
+            self.resolver.resolve(target)
+        } else {
+            let article_id = subject.pointer("/article/article_id").and_then(Value::as_str)?;
+            self.resolver.resolve(&format!("article-{}", article_id))
+        };
+        address.ok().map(|address| address.article)
+    }
+}
+
+fn seconds(timestamp: &str) -> Result<i64> {
+    unix_seconds(timestamp).ok_or_else(|| {
+        ConstitutionalError::ProtocolError(format!("Invalid timestamp {:?} in a ledger query", timestamp))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+    use serde_json::json;
+    use std::fs;
+
+    fn ledger(test: &str) -> Ledger {
+        let path = std::env::temp_dir().join(format!("ocp-ledger-index-{}-{}.jsonl", test, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut ledger = Ledger::open(path).unwrap();
+        let contract = json!({
+            "object_type": "Contract",
+            "id": "c-17",
+            "proposer_agent": "Claude",
+            "action_type": "amend",
+            "action": {"target": "amendment-article-3", "parameters": {"article": "III.1"}},
+            "timestamp": "2025-11-20T14:30:00Z",
+        });
+        let signed = sign(&contract, &Keypair::from_secret(&[7; 32])).unwrap();
+        ledger.append(&serde_json::to_value(signed).unwrap()).unwrap();
+        ledger
+            .append(&json!({"event": "voted", "contract_id": "c-17", "voter": "Gemini", "at": "2025-11-21T09:00:00Z"}))
+            .unwrap();
+        ledger
+            .append(&json!({"event": "voted", "contract_id": "c-17", "voter": "Claude", "at": "2025-11-22T09:00:00Z"}))
+            .unwrap();
+        ledger
+            .append(&json!({
+                "object_type": "Amendment",
+                "id": "a-3",
+                "proposer_agent": "Grok",
+                "target": "article-12.4",
+                "timestamp": "2025-12-01T00:00:00+01:00",
+            }))
+            .unwrap();
+        ledger
+    }
+
+    fn indices(index: &LedgerIndex, query: LedgerQuery) -> Vec<u64> {
+        index.query(&query).unwrap().iter().map(RecordHandle::index).collect()
+    }
+
+    #[test]
+    fn test_queries_match_keys() {
+        let ledger = ledger("query");
+        let index = LedgerIndex::build(&ledger).unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(indices(&index, LedgerQuery::new()), [0, 1, 2, 3]);
+        assert_eq!(indices(&index, LedgerQuery::new().agent("Claude")), [0, 2]);
+        assert_eq!(indices(&index, LedgerQuery::new().agent("Claude").object_type("voted")), [2]);
+        assert_eq!(indices(&index, LedgerQuery::new().action_type("amend")), [0]);
+        assert_eq!(indices(&index, LedgerQuery::new().object_type("Amendment")), [3]);
+        assert_eq!(indices(&index, LedgerQuery::new().article(3)), [0]);
+        assert_eq!(indices(&index, LedgerQuery::new().article(12)), [3]);
+        assert_eq!(indices(&index, LedgerQuery::new().agent("Gpt")), Vec::<u64>::new());
+
+        let range = LedgerQuery::new().since("2025-11-21T09:00:00Z").until("2025-12-01T00:00:00+01:00");
+        assert_eq!(indices(&index, range), [1, 2]);
+        assert_eq!(indices(&index, LedgerQuery::new().since("2025-11-30T23:00:00Z")), [3]);
+        let backwards = LedgerQuery::new().since("2026-01-01T00:00:00Z").until("2025-01-01T00:00:00Z");
+        assert!(indices(&index, backwards).is_empty());
+        assert!(index.query(&LedgerQuery::new().until("next week")).is_err());
+        let _ = fs::remove_file(ledger.path());
+    }
+
+    #[test]
+    fn test_handles_are_verified_on_read() {
+        let mut ledger = ledger("read");
+        let mut index = LedgerIndex::build(&ledger).unwrap();
+        let handles = index.query(&LedgerQuery::new().agent("Claude")).unwrap();
+        let record = ledger.read(&handles[1]).unwrap();
+        assert_eq!((record.index, record.object["voter"].as_str()), (2, Some("Claude")));
+
+        ledger.append(&json!({"event": "voted", "contract_id": "c-17", "voter": "Claude"})).unwrap();
+        assert_eq!(index.refresh(&ledger).unwrap(), 1);
+        assert_eq!(index.refresh(&ledger).unwrap(), 0);
+        assert_eq!(indices(&index, LedgerQuery::new().agent("Claude")), [0, 2, 4]);
+
+        let text = fs::read_to_string(ledger.path()).unwrap();
+        fs::write(ledger.path(), text.replace("\"voter\":\"Claude\"", "\"voter\":\"Gemini\"")).unwrap();
+        assert!(ledger.read(&handles[1]).is_err());
+        assert!(ledger.read(&handles[0]).is_ok());
+        let _ = fs::remove_file(ledger.path());
+    }
+}
@@ -0,0 +1,591 @@
+/// merkle.rs - Merkle trees over batches of constitutional objects
+///
+/// An archive batch (see `archive/integrity/merkle_notes.md`) is committed to by a single
+/// Merkle root. Each leaf is the semantic hash of one object, the same hash its signature
+/// covers. The tree has the shape and domain separation of RFC 6962 (Certificate
+/// Transparency). A leaf node is the hash of `0x00` and the leaf's semantic hash. An
+/// internal node is the hash of `0x01` and its two children. A tree of `n` leaves splits
+/// at the largest power of two below `n`. The prefixes stop a leaf from being passed off
+/// as an internal node or the reverse. The split fixes the shape by the leaf count
+/// alone, so the same batch always has the same root.
+///
+/// Nodes are hashed with the algorithm of the options the leaves were hashed under,
+/// which must have a 256-bit digest.
+///
+/// An inclusion proof is the RFC 6962 audit path: the sibling hashes from a leaf up to
+/// the root. A light client holding one contract, its proof and the ratified root can
+/// check the contract was in the batch without downloading the batch. A multiproof does
+/// the same for many leaves at once, sending each subtree hash the leaves share only once.
+///
+/// The object log only ever grows, and a consistency proof between two of its sizes shows
+/// that the larger tree extends the smaller one. A transparency-log monitor that kept an
+/// old root checks each new root against it, so history cannot be rewritten unnoticed.
+
+use crate::algorithm::Hasher;
+use crate::hmac::constant_time_eq;
+use crate::{semantic_hash_batch, CanonicalizeOptions, ConstitutionalError, HashAlgorithm, Result, SemanticHash};
+use serde_json::Value;
+
+pub(crate) const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// A Merkle tree over the semantic hashes of a batch of objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    algorithm: HashAlgorithm,
+    /// Node hashes by height: leaf nodes first, the root last. A node without a sibling
+    /// is carried up unchanged, which yields exactly the RFC 6962 shape.
+    levels: Vec<Vec<SemanticHash>>,
+}
+
+impl MerkleTree {
+    /// Build the tree over a batch of objects, each leaf being an object's semantic hash.
+    ///
+    /// # Arguments
+    /// * `objects` - The batch, in the order its leaves take
+    /// * `options` - Canonicalization options the objects are hashed under
+    ///
+    /// # Returns
+    /// The tree, or the first error hashing an object raised
+    pub fn build(objects: &[Value], options: &CanonicalizeOptions) -> Result<MerkleTree> {
+        let leaves = semantic_hash_batch(objects, options).into_iter().collect::<Result<Vec<_>>>()?;
+        MerkleTree::from_leaves(&leaves, options.hash_algorithm)
+    }
+
+    /// Build the tree over semantic hashes computed earlier.
+    ///
+    /// # Arguments
+    /// * `leaves` - Semantic hash of each object, in leaf order
+    /// * `algorithm` - Algorithm the leaves were hashed with, used for the nodes too
+    ///
+    /// # Returns
+    /// The tree, or a HashingError if the algorithm's digest is not 256 bits
+    pub fn from_leaves(leaves: &[SemanticHash], algorithm: HashAlgorithm) -> Result<MerkleTree> {
+        check_algorithm(algorithm)?;
+        let mut levels = vec![leaves.iter().map(|leaf| hash_leaf(algorithm, leaf)).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let below = &levels[levels.len() - 1];
+            let level = below
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(algorithm, left, right),
+                    [odd] => *odd,
+                    _ => unreachable!("chunks(2) yields one or two nodes"),
+                })
+                .collect();
+            levels.push(level);
+        }
+        Ok(MerkleTree { algorithm, levels })
+    }
+
+    /// The Merkle root; for an empty batch, the hash of no bytes, as in RFC 6962.
+    pub fn root(&self) -> SemanticHash {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => *root,
+            None => empty_root(self.algorithm),
+        }
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Whether the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Algorithm the nodes are hashed with.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Audit path proving the leaf at `index` is in the tree.
+    ///
+    /// # Returns
+    /// The proof, or None if there is no leaf at `index`
+    pub fn prove_inclusion(&self, index: usize) -> Option<InclusionProof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        self.audit_path(index, 0, self.len(), &mut path);
+        Some(InclusionProof { algorithm: self.algorithm, index: index as u64, tree_size: self.len() as u64, path })
+    }
+
+    /// Proof that the leaves at `indices` are all in the tree. It holds the hash of every
+    /// maximal subtree containing none of them, in left-to-right order, so no hash is
+    /// sent twice and none can be derived from the leaves.
+    ///
+    /// # Returns
+    /// The proof over the indices sorted and deduplicated, or None if one is out of range
+    pub fn prove_multi(&self, indices: &[usize]) -> Option<MultiProof> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.last().is_some_and(|last| *last >= self.len()) {
+            return None;
+        }
+        let mut hashes = Vec::new();
+        if !indices.is_empty() {
+            self.multi_path(&indices, 0, self.len(), &mut hashes);
+        }
+        Some(MultiProof {
+            algorithm: self.algorithm,
+            tree_size: self.len() as u64,
+            indices: indices.into_iter().map(|index| index as u64).collect(),
+            hashes,
+        })
+    }
+
+    fn multi_path(&self, indices: &[usize], start: usize, end: usize, hashes: &mut Vec<SemanticHash>) {
+        if indices.is_empty() {
+            hashes.push(self.subtree_hash(start, end));
+        } else if end - start > 1 {
+            let mid = start + split_point(end - start);
+            let split = indices.partition_point(|index| *index < mid);
+            self.multi_path(&indices[..split], start, mid, hashes);
+            self.multi_path(&indices[split..], mid, end, hashes);
+        }
+    }
+
+    /// Root of the tree over the first `size` leaves, as it was when the log had that size.
+    ///
+    /// # Returns
+    /// The root, or None if the tree has fewer than `size` leaves
+    pub fn root_at(&self, size: usize) -> Option<SemanticHash> {
+        match size {
+            0 => Some(empty_root(self.algorithm)),
+            _ if size <= self.len() => Some(self.subtree_hash(0, size)),
+            _ => None,
+        }
+    }
+
+    /// Proof that the tree over the first `new_size` leaves extends the tree over the
+    /// first `old_size`.
+    ///
+    /// # Returns
+    /// The proof, or None unless `0 < old_size <= new_size <= len()`
+    pub fn prove_consistency(&self, old_size: usize, new_size: usize) -> Option<ConsistencyProof> {
+        if old_size == 0 || old_size > new_size || new_size > self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        self.consistency_path(old_size, 0, new_size, true, &mut path);
+        Some(ConsistencyProof {
+            algorithm: self.algorithm,
+            old_size: old_size as u64,
+            new_size: new_size as u64,
+            path,
+        })
+    }
+
+    /// RFC 6962 SUBPROOF(old_size, D[start:end], complete).
+    fn consistency_path(&self, old_size: usize, start: usize, end: usize, complete: bool, path: &mut Vec<SemanticHash>) {
+        if old_size == end - start {
+            if !complete {
+                path.push(self.subtree_hash(start, end));
+            }
+            return;
+        }
+        let k = split_point(end - start);
+        if old_size <= k {
+            self.consistency_path(old_size, start, start + k, complete, path);
+            path.push(self.subtree_hash(start + k, end));
+        } else {
+            self.consistency_path(old_size - k, start + k, end, false, path);
+            path.push(self.subtree_hash(start, start + k));
+        }
+    }
+
+    /// RFC 6962 PATH(index, D[start:end]), leaf end first.
+    fn audit_path(&self, index: usize, start: usize, end: usize, path: &mut Vec<SemanticHash>) {
+        if end - start == 1 {
+            return;
+        }
+        let mid = start + split_point(end - start);
+        if index < mid {
+            self.audit_path(index, start, mid, path);
+            path.push(self.subtree_hash(mid, end));
+        } else {
+            self.audit_path(index, mid, end, path);
+            path.push(self.subtree_hash(start, mid));
+        }
+    }
+
+    /// Hash of the subtree over leaves `start..end`. Callers only ask for ranges the
+    /// RFC 6962 split produces, whose left parts are complete aligned subtrees.
+    fn subtree_hash(&self, start: usize, end: usize) -> SemanticHash {
+        let n = end - start;
+        if n.is_power_of_two() && start.is_multiple_of(n) {
+            return self.levels[n.trailing_zeros() as usize][start / n];
+        }
+        let mid = start + split_point(n);
+        hash_node(self.algorithm, &self.subtree_hash(start, mid), &self.subtree_hash(mid, end))
+    }
+}
+
+/// Proof that a leaf is in a Merkle tree of a given size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    /// Algorithm the tree's nodes are hashed with
+    pub algorithm: HashAlgorithm,
+    /// Position of the leaf
+    pub index: u64,
+    /// Number of leaves in the tree
+    pub tree_size: u64,
+    /// Sibling hashes from the leaf up to the root
+    pub path: Vec<SemanticHash>,
+}
+
+/// Check that a leaf is in the tree with root `root`.
+///
+/// # Arguments
+/// * `leaf` - Semantic hash of the object, as the tree's leaf holds it
+/// * `proof` - Audit path from `MerkleTree::prove_inclusion`
+/// * `root` - Trusted Merkle root, e.g. from a signed archive batch
+///
+/// # Returns
+/// true if the path leads from the leaf to the root, false otherwise
+pub fn verify_inclusion(leaf: &SemanticHash, proof: &InclusionProof, root: &SemanticHash) -> bool {
+    if check_algorithm(proof.algorithm).is_err() || proof.index >= proof.tree_size {
+        return false;
+    }
+    // RFC 9162 section 2.1.3.2
+    let (mut index, mut last) = (proof.index, proof.tree_size - 1);
+    let mut hash = hash_leaf(proof.algorithm, leaf);
+    for sibling in &proof.path {
+        if last == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last {
+            hash = hash_node(proof.algorithm, sibling, &hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            hash = hash_node(proof.algorithm, &hash, sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    last == 0 && constant_time_eq(hash.as_bytes(), root.as_bytes())
+}
+
+/// Proof that several leaves are in a Merkle tree of a given size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiProof {
+    /// Algorithm the tree's nodes are hashed with
+    pub algorithm: HashAlgorithm,
+    /// Number of leaves in the tree
+    pub tree_size: u64,
+    /// Positions of the proven leaves, strictly increasing
+    pub indices: Vec<u64>,
+    /// Hashes of the subtrees holding no proven leaf, left to right
+    pub hashes: Vec<SemanticHash>,
+}
+
+/// Check that leaves are all in the tree with root `root`.
+///
+/// # Arguments
+/// * `leaves` - Semantic hash of each proven object, in the order of `proof.indices`
+/// * `proof` - Multiproof from `MerkleTree::prove_multi`
+/// * `root` - Trusted Merkle root
+///
+/// # Returns
+/// true if the leaves and proof rebuild the root, false otherwise
+pub fn verify_multi(leaves: &[SemanticHash], proof: &MultiProof, root: &SemanticHash) -> bool {
+    let increasing = proof.indices.windows(2).all(|pair| pair[0] < pair[1]);
+    let in_range = proof.indices.last().is_some_and(|last| *last < proof.tree_size);
+    if check_algorithm(proof.algorithm).is_err() || !increasing || !in_range || leaves.len() != proof.indices.len() {
+        return false;
+    }
+    let mut hashes = proof.hashes.iter();
+    let rebuilt = rebuild(proof.algorithm, &proof.indices, leaves, 0, proof.tree_size, &mut hashes);
+    hashes.next().is_none() && rebuilt.is_some_and(|hash| constant_time_eq(hash.as_bytes(), root.as_bytes()))
+}
+
+/// Hash of the subtree over leaves `start..end` from the proven leaves in it and the
+/// proof's hashes, in the order `MerkleTree::multi_path` wrote them.
+fn rebuild<'a>(
+    algorithm: HashAlgorithm,
+    indices: &[u64],
+    leaves: &[SemanticHash],
+    start: u64,
+    end: u64,
+    hashes: &mut impl Iterator<Item = &'a SemanticHash>,
+) -> Option<SemanticHash> {
+    if indices.is_empty() {
+        return hashes.next().copied();
+    }
+    if end - start == 1 {
+        return Some(hash_leaf(algorithm, &leaves[0]));
+    }
+    let mid = start + split_point((end - start) as usize) as u64;
+    let split = indices.partition_point(|index| *index < mid);
+    let left = rebuild(algorithm, &indices[..split], &leaves[..split], start, mid, hashes)?;
+    let right = rebuild(algorithm, &indices[split..], &leaves[split..], mid, end, hashes)?;
+    Some(hash_node(algorithm, &left, &right))
+}
+
+/// Proof that a Merkle tree of one size extends a tree of a smaller size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    /// Algorithm the trees' nodes are hashed with
+    pub algorithm: HashAlgorithm,
+    /// Number of leaves in the older tree
+    pub old_size: u64,
+    /// Number of leaves in the newer tree
+    pub new_size: u64,
+    /// Subtree hashes linking the two roots
+    pub path: Vec<SemanticHash>,
+}
+
+/// Check that the tree with root `new_root` extends the tree with root `old_root`, at
+/// the sizes the proof names.
+///
+/// # Arguments
+/// * `old_root` - Root the monitor recorded at the older size
+/// * `new_root` - Root now published at the newer size
+/// * `proof` - Proof from `MerkleTree::prove_consistency`
+///
+/// # Returns
+/// true if the newer tree only appended leaves to the older one, false otherwise
+pub fn verify_consistency(old_root: &SemanticHash, new_root: &SemanticHash, proof: &ConsistencyProof) -> bool {
+    let (old_size, new_size) = (proof.old_size, proof.new_size);
+    if check_algorithm(proof.algorithm).is_err() || old_size == 0 || old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.path.is_empty() && constant_time_eq(old_root.as_bytes(), new_root.as_bytes());
+    }
+    // RFC 9162 section 2.1.4.2
+    let mut path = proof.path.iter();
+    let first = if old_size.is_power_of_two() { Some(old_root) } else { path.next() };
+    let Some(first) = first else {
+        return false;
+    };
+    let (mut index, mut last) = (old_size - 1, new_size - 1);
+    while index & 1 == 1 {
+        index >>= 1;
+        last >>= 1;
+    }
+    let (mut old_hash, mut new_hash) = (*first, *first);
+    for sibling in path {
+        if last == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last {
+            old_hash = hash_node(proof.algorithm, sibling, &old_hash);
+            new_hash = hash_node(proof.algorithm, sibling, &new_hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            new_hash = hash_node(proof.algorithm, &new_hash, sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    last == 0
+        && constant_time_eq(old_hash.as_bytes(), old_root.as_bytes())
+        && constant_time_eq(new_hash.as_bytes(), new_root.as_bytes())
+}
+
+/// Largest power of two smaller than `n`, for `n > 1`.
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// Leaf node hash of a semantic hash.
+///
+/// # Returns
+/// The hash, or a HashingError if the algorithm is not a 256-bit one
+pub fn leaf_hash(algorithm: HashAlgorithm, leaf: &SemanticHash) -> Result<SemanticHash> {
+    check_algorithm(algorithm)?;
+    Ok(hash_leaf(algorithm, leaf))
+}
+
+/// Internal node hash of two children.
+///
+/// # Returns
+/// The hash, or a HashingError if the algorithm is not a 256-bit one
+pub fn node_hash(algorithm: HashAlgorithm, left: &SemanticHash, right: &SemanticHash) -> Result<SemanticHash> {
+    check_algorithm(algorithm)?;
+    Ok(hash_node(algorithm, left, right))
+}
+
+/// `leaf_hash` for an algorithm already checked.
+pub(crate) fn hash_leaf(algorithm: HashAlgorithm, leaf: &SemanticHash) -> SemanticHash {
+    prefixed_hash(algorithm, LEAF_PREFIX, &[leaf.as_bytes()])
+}
+
+/// `node_hash` for an algorithm already checked.
+pub(crate) fn hash_node(algorithm: HashAlgorithm, left: &SemanticHash, right: &SemanticHash) -> SemanticHash {
+    prefixed_hash(algorithm, NODE_PREFIX, &[left.as_bytes(), right.as_bytes()])
+}
+
+pub(crate) fn empty_root(algorithm: HashAlgorithm) -> SemanticHash {
+    digest(Hasher::new(algorithm))
+}
+
+pub(crate) fn check_algorithm(algorithm: HashAlgorithm) -> Result<()> {
+    if algorithm.digest_len() == 32 {
+        Ok(())
+    } else {
+        Err(ConstitutionalError::HashingError(format!(
+            "Merkle trees need a 256-bit hash; {} is not one",
+            algorithm
+        )))
+    }
+}
+
+pub(crate) fn prefixed_hash(algorithm: HashAlgorithm, prefix: u8, parts: &[&[u8; 32]]) -> SemanticHash {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(&[prefix]);
+    for part in parts {
+        hasher.update(*part);
+    }
+    digest(hasher)
+}
+
+pub(crate) fn digest(hasher: Hasher) -> SemanticHash {
+    // Only 256-bit algorithms get past `check_algorithm`
+    SemanticHash::try_from(hasher.finalize().as_slice()).expect("256-bit digest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// RFC 6962 root, computed by recursion straight from the definition.
+    fn reference_root(leaves: &[SemanticHash]) -> SemanticHash {
+        let algorithm = HashAlgorithm::Sha256;
+        match leaves {
+            [] => empty_root(algorithm),
+            [leaf] => hash_leaf(algorithm, leaf),
+            _ => {
+                let k = split_point(leaves.len());
+                hash_node(algorithm, &reference_root(&leaves[..k]), &reference_root(&leaves[k..]))
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_root_matches_rfc6962_shape() {
+        let contracts: Vec<Value> = (0..13).map(|i| json!({"contract_id": format!("c-{}", i)})).collect();
+        let options = CanonicalizeOptions::new();
+        let leaves: Vec<_> = contracts.iter().map(|c| SemanticHash::of(c).unwrap()).collect();
+        for n in 0..=leaves.len() {
+            let tree = MerkleTree::from_leaves(&leaves[..n], HashAlgorithm::Sha256).unwrap();
+            assert_eq!(tree.root(), reference_root(&leaves[..n]), "{} leaves", n);
+            assert_eq!(tree.len(), n);
+        }
+
+        let tree = MerkleTree::build(&contracts, &options).unwrap();
+        assert_eq!(tree.root(), reference_root(&leaves));
+        // A lone leaf's root is its leaf node, never the bare semantic hash
+        assert_ne!(MerkleTree::build(&contracts[..1], &options).unwrap().root(), leaves[0]);
+        assert_eq!(
+            empty_root(HashAlgorithm::Sha256).to_hex(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(MerkleTree::build(&contracts, &options.clone().hash_algorithm(HashAlgorithm::Sha512)).is_err());
+        assert!(MerkleTree::build(&[json!([1])], &options).is_err());
+
+        // The node hashes are public, and refuse what the tree refuses
+        assert_eq!(
+            tree.root(),
+            node_hash(HashAlgorithm::Sha256, &reference_root(&leaves[..8]), &reference_root(&leaves[8..])).unwrap()
+        );
+        assert_eq!(leaf_hash(HashAlgorithm::Sha256, &leaves[0]).unwrap(), reference_root(&leaves[..1]));
+        assert!(leaf_hash(HashAlgorithm::Sha512, &leaves[0]).is_err());
+        assert!(node_hash(HashAlgorithm::Sha512, &leaves[0], &leaves[1]).is_err());
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let leaves: Vec<_> = (0..11).map(|i| SemanticHash::of(&json!({"contract_id": i})).unwrap()).collect();
+        for n in 1..=leaves.len() {
+            let tree = MerkleTree::from_leaves(&leaves[..n], HashAlgorithm::Sha256).unwrap();
+            let root = tree.root();
+            for (i, leaf) in leaves[..n].iter().enumerate() {
+                let proof = tree.prove_inclusion(i).unwrap();
+                assert!(verify_inclusion(leaf, &proof, &root), "leaf {} of {}", i, n);
+                assert!(!verify_inclusion(&leaves[(i + 1) % leaves.len()], &proof, &root));
+
+                let mut moved = proof.clone();
+                moved.index = (moved.index + 1) % n as u64;
+                assert!(n == 1 || !verify_inclusion(leaf, &moved, &root));
+                let mut longer = proof.clone();
+                longer.path.push(root);
+                assert!(!verify_inclusion(leaf, &longer, &root));
+            }
+            assert!(tree.prove_inclusion(n).is_none());
+        }
+    }
+
+    #[test]
+    fn test_multiproofs() {
+        let leaves: Vec<_> = (0..23).map(|i| SemanticHash::of(&json!({"contract_id": i})).unwrap()).collect();
+        let tree = MerkleTree::from_leaves(&leaves, HashAlgorithm::Sha256).unwrap();
+        let root = tree.root();
+
+        let proof = tree.prove_multi(&[17, 2, 3, 9, 3]).unwrap();
+        assert_eq!(proof.indices, [2, 3, 9, 17]);
+        let proven: Vec<_> = proof.indices.iter().map(|i| leaves[*i as usize]).collect();
+        assert!(verify_multi(&proven, &proof, &root));
+        // Fewer hashes than the four audit paths together
+        let separate: usize = proof.indices.iter().map(|i| tree.prove_inclusion(*i as usize).unwrap().path.len()).sum();
+        assert!(proof.hashes.len() < separate);
+
+        let mut swapped = proven.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_multi(&swapped, &proof, &root));
+        assert!(!verify_multi(&proven[..3], &proof, &root));
+        let mut extra = proof.clone();
+        extra.hashes.push(root);
+        assert!(!verify_multi(&proven, &extra, &root));
+
+        let all: Vec<usize> = (0..leaves.len()).collect();
+        let everything = tree.prove_multi(&all).unwrap();
+        assert!(everything.hashes.is_empty() && verify_multi(&leaves, &everything, &root));
+        assert!(tree.prove_multi(&[23]).is_none());
+        assert!(!verify_multi(&[], &tree.prove_multi(&[]).unwrap(), &root));
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let leaves: Vec<_> = (0..12).map(|i| SemanticHash::of(&json!({"entry": i})).unwrap()).collect();
+        let tree = MerkleTree::from_leaves(&leaves, HashAlgorithm::Sha256).unwrap();
+        for new_size in 1..=leaves.len() {
+            let new_root = tree.root_at(new_size).unwrap();
+            assert_eq!(new_root, reference_root(&leaves[..new_size]));
+            for old_size in 1..=new_size {
+                let old_root = tree.root_at(old_size).unwrap();
+                let proof = tree.prove_consistency(old_size, new_size).unwrap();
+                assert!(verify_consistency(&old_root, &new_root, &proof), "{} -> {}", old_size, new_size);
+
+                // A rewritten history has a different old root, which the proof rejects
+                let mut rewritten = leaves[..old_size].to_vec();
+                rewritten[0] = leaves[11];
+                let forged = reference_root(&rewritten);
+                assert!(!verify_consistency(&forged, &new_root, &proof));
+                if old_size < new_size {
+                    assert!(!verify_consistency(&old_root, &old_root, &proof));
+                    let mut truncated = proof.clone();
+                    truncated.path.pop();
+                    assert!(!verify_consistency(&old_root, &new_root, &truncated));
+                }
+            }
+        }
+        assert!(tree.prove_consistency(0, 4).is_none());
+        assert!(tree.prove_consistency(5, 4).is_none());
+        assert!(tree.prove_consistency(4, 13).is_none());
+        assert_eq!(tree.root_at(13), None);
+    }
+}
@@ -0,0 +1,213 @@
+/// migrate.rs - Upgrading objects written under older schema versions
+///
+/// Typed objects carry an optional `schema_version`; an object without one follows
+/// version 1. When a later version adds or reshapes members, a deployment registers a
+/// transform from each version to the next, and `Migrations::migrate` chains them to
+/// bring an old ledger entry up to the version it wants, stamping the result with its
+/// new `schema_version`.
+///
+/// A migrated object is a different object and hashes differently. The hash recorded
+/// for a ledger entry stays the hash of the entry as written, under its original
+/// version, so `Migrations::upgrade` checks the entry against that hash before
+/// migrating it. The original is never rewritten.
+
+use crate::{verify_semantic_hash_with, CanonicalizeOptions, ConstitutionalError, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The schema version the typed objects of this crate follow.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Rewrites an object of one schema version as the next.
+type Transform = dyn Fn(Value) -> Result<Value> + Send + Sync;
+
+/// Transforms between consecutive schema versions.
+#[derive(Clone, Default)]
+pub struct Migrations {
+    /// Transform from each version to the one after it
+    steps: BTreeMap<u32, Arc<Transform>>,
+}
+
+impl std::fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Migrations").field("from_versions", &self.steps.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+impl Migrations {
+    /// No transforms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the transform from version `from` to `from + 1`.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if `from` is 0 or already has a transform
+    pub fn register(
+        &mut self,
+        from: u32,
+        transform: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) -> Result<()> {
+        if from == 0 || self.steps.contains_key(&from) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Schema version {} is invalid or already has a migration",
+                from
+            )));
+        }
+        self.steps.insert(from, Arc::new(transform));
+        Ok(())
+    }
+
+    /// Migrate an object from one schema version to a later one, through every version
+    /// in between.
+    ///
+    /// # Arguments
+    /// * `value` - The object, following version `from`
+    /// * `from` - Its version, which must be the one its `schema_version` gives
+    /// * `to` - The version wanted, no earlier than `from`
+    ///
+    /// # Returns
+    /// The migrated object with `schema_version` set to `to`, or the object unchanged if
+    /// the versions are equal; a ProtocolError if the object is not at `from`, `to` is
+    /// earlier, a transform is missing or one fails
+    pub fn migrate(&self, value: Value, from: u32, to: u32) -> Result<Value> {
+        let stated = schema_version(&value)?;
+        if stated != from {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "The object is at schema version {}, not {}",
+                stated, from
+            )));
+        }
+        if to < from {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Cannot migrate from schema version {} back to {}",
+                from, to
+            )));
+        }
+        let mut value = value;
+        for version in from..to {
+            let step = self.steps.get(&version).ok_or_else(|| {
+                ConstitutionalError::ProtocolError(format!("No migration from schema version {}", version))
+            })?;
+            value = step(value)?;
+            if !value.is_object() {
+                return Err(ConstitutionalError::ProtocolError(format!(
+                    "The migration from schema version {} did not produce an object",
+                    version
+                )));
+            }
+            value["schema_version"] = Value::from(version + 1);
+        }
+        Ok(value)
+    }
+
+    /// Check a ledger entry against the hash recorded for it, then migrate it to a
+    /// version.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry as written
+    /// * `recorded_hash` - Its semantic hash as recorded, under its original version
+    /// * `to` - The version wanted
+    /// * `options` - Canonicalization options the entry was hashed under
+    ///
+    /// # Returns
+    /// The migrated entry, or a ProtocolError if the entry does not match its hash or
+    /// cannot be migrated
+    pub fn upgrade(&self, entry: &Value, recorded_hash: &str, to: u32, options: &CanonicalizeOptions) -> Result<Value> {
+        if !verify_semantic_hash_with(entry, recorded_hash, options)? {
+            return Err(ConstitutionalError::ProtocolError(
+                "The entry does not match the hash recorded for it".to_string(),
+            ));
+        }
+        self.migrate(entry.clone(), schema_version(entry)?, to)
+    }
+}
+
+/// The schema version an object states, or 1 if it states none.
+///
+/// # Returns
+/// The version, or a ProtocolError if `schema_version` is not a positive integer that
+/// fits a u32
+pub fn schema_version(value: &Value) -> Result<u32> {
+    match value.get("schema_version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|version| *version > 0)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Invalid schema_version {}", version))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::contract;
+    use crate::{semantic_hash, Contract};
+    use serde_json::json;
+
+    /// Version 2 nests `action_type` in the action; version 3 renames `proposer_agent`
+    /// to `proposer`.
+    fn migrations() -> Migrations {
+        let mut migrations = Migrations::new();
+        migrations
+            .register(1, |mut value| {
+                let action_type = value.as_object_mut().and_then(|map| map.remove("action_type"));
+                value["action"]["type"] = action_type.unwrap_or(Value::Null);
+                Ok(value)
+            })
+            .unwrap();
+        migrations
+            .register(2, |mut value| {
+                let proposer = value.as_object_mut().and_then(|map| map.remove("proposer_agent"));
+                value["proposer"] = proposer.unwrap_or(Value::Null);
+                Ok(value)
+            })
+            .unwrap();
+        migrations
+    }
+
+    fn entry() -> Value {
+        json!({"id": "c-17", "proposer_agent": "Claude", "action_type": "amend", "action": {"target": "article-3"}})
+    }
+
+    #[test]
+    fn test_migrations_chain() {
+        let migrations = migrations();
+        let v3 = migrations.migrate(entry(), 1, 3).unwrap();
+        let expected = json!({"id": "c-17", "proposer": "Claude", "action": {"target": "article-3", "type": "amend"},
+            "schema_version": 3});
+        assert_eq!(v3, expected);
+        assert_eq!(migrations.migrate(v3.clone(), 3, 3).unwrap(), v3);
+        assert_eq!(migrations.migrate(entry(), 1, 2).unwrap()["schema_version"], 2);
+
+        assert!(migrations.migrate(entry(), 2, 3).is_err());
+        assert!(migrations.migrate(v3.clone(), 3, 1).is_err());
+        assert!(migrations.migrate(v3, 3, 4).is_err());
+        assert!(migrations.clone().register(2, Ok).is_err());
+        assert!(schema_version(&json!({"schema_version": 0})).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_checks_the_original_hash() {
+        let options = CanonicalizeOptions::new();
+        let migrations = migrations();
+        let recorded = semantic_hash(&entry()).unwrap();
+        let upgraded = migrations.upgrade(&entry(), &recorded, 3, &options).unwrap();
+        assert_eq!(upgraded["proposer"], "Claude");
+        assert_ne!(semantic_hash(&upgraded).unwrap(), recorded);
+        let mut tampered = entry();
+        tampered["proposer_agent"] = json!("Gemini");
+        assert!(migrations.upgrade(&tampered, &recorded, 3, &options).is_err());
+
+        // The typed model keeps the version, and leaves it out when absent
+        let mut contract = contract().to_value();
+        assert_eq!(Contract::from_value(contract.clone()).unwrap().schema_version, None);
+        contract["schema_version"] = json!(SCHEMA_VERSION);
+        let typed = Contract::from_value(contract.clone()).unwrap();
+        assert_eq!(typed.schema_version, Some(1));
+        assert_eq!(typed.to_value(), contract);
+    }
+}
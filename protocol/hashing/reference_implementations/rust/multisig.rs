@@ -0,0 +1,305 @@
+/// multisig.rs - Contracts co-signed by agents in required roles
+///
+/// Some action types need more than one signature: an amendment might take its proposer
+/// and two reviewers. A `MultiSignedObject` is a payload with its hash envelope and any
+/// number of independent signatures over that envelope, each the signature `sign` would
+/// make, keyed by signer:
+///
+/// ```json
+/// {"payload":{"action_type":"amend",...},"hash_envelope":{...},
+///  "signatures":{"ed25519:3b6a...":"ed25519:92a0...","ed25519:8a88...":"ed25519:e556..."}}
+/// ```
+///
+/// A `CoSigningPolicy` says which roles each agent holds and which combinations of roles
+/// each action type requires, read from the payload's `action_type`. A requirement is
+/// met when the signers can be assigned to its slots one each, so an agent holding two
+/// roles still counts once. An action type may list several alternative requirements;
+/// meeting any of them is enough.
+
+use crate::objects::take;
+use crate::signing::signing_message;
+use crate::{
+    semantic_hash_envelope, CanonicalizeOptions, ConstitutionalError, HashEnvelope, PublicKey, Result, SignedObject,
+    Signer,
+};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+const FIELDS: &[&str] = &["payload", "hash_envelope", "signatures"];
+
+/// Roles of agents and the roles each action type needs signatures from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoSigningPolicy {
+    /// Roles of each agent, by public key in text form
+    roles: BTreeMap<String, BTreeSet<String>>,
+    /// Alternative requirements of each action type, each a count per role
+    requirements: BTreeMap<String, Vec<BTreeMap<String, usize>>>,
+}
+
+impl CoSigningPolicy {
+    /// A policy with no roles and no requirements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give an agent a role. An agent may hold several.
+    pub fn role(mut self, agent: &PublicKey, role: &str) -> Self {
+        self.roles.entry(agent.to_string()).or_default().insert(role.to_string());
+        self
+    }
+
+    /// Add a requirement for an action type: a number of signers for each role. Calling
+    /// this again for the same action type adds an alternative.
+    pub fn require(mut self, action_type: &str, roles: &[(&str, usize)]) -> Self {
+        let mut requirement = BTreeMap::new();
+        for (role, count) in roles {
+            *requirement.entry(role.to_string()).or_default() += count;
+        }
+        self.requirements.entry(action_type.to_string()).or_default().push(requirement);
+        self
+    }
+
+    /// Whether a set of signers meets one of an action type's requirements.
+    ///
+    /// # Arguments
+    /// * `action_type` - The action being signed
+    /// * `signers` - Public keys in text form of agents whose signatures are valid
+    ///
+    /// # Returns
+    /// The answer, or a ProtocolError if the policy has no requirement for the action
+    pub fn satisfied_by<'a>(&self, action_type: &str, signers: impl IntoIterator<Item = &'a str>) -> Result<bool> {
+        let requirements = self.requirements.get(action_type).ok_or_else(|| {
+            ConstitutionalError::ProtocolError(format!("No co-signing requirement for action {:?}", action_type))
+        })?;
+        let signers: BTreeSet<&str> = signers.into_iter().collect();
+        let none = BTreeSet::new();
+        let roles: Vec<_> = signers.iter().map(|signer| self.roles.get(*signer).unwrap_or(&none)).collect();
+        Ok(requirements.iter().any(|requirement| {
+            let slots: Vec<&str> =
+                requirement.iter().flat_map(|(role, count)| std::iter::repeat_n(role.as_str(), *count)).collect();
+            fills(&slots, &roles)
+        }))
+    }
+}
+
+/// Whether every slot can get its own signer holding the slot's role: a bipartite
+/// matching, found by augmenting paths.
+fn fills(slots: &[&str], roles: &[&BTreeSet<String>]) -> bool {
+    fn assign(
+        slot: usize,
+        slots: &[&str],
+        roles: &[&BTreeSet<String>],
+        seen: &mut [bool],
+        holder: &mut [Option<usize>],
+    ) -> bool {
+        for signer in 0..roles.len() {
+            if seen[signer] || !roles[signer].contains(slots[slot]) {
+                continue;
+            }
+            seen[signer] = true;
+            if holder[signer].is_none_or(|other| assign(other, slots, roles, seen, holder)) {
+                holder[signer] = Some(slot);
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut holder = vec![None; roles.len()];
+    (0..slots.len()).all(|slot| assign(slot, slots, roles, &mut vec![false; roles.len()], &mut holder))
+}
+
+/// A payload with its hash envelope and the co-signers' signatures over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiSignedObject {
+    /// The co-signed document, with an `action_type`
+    pub payload: Value,
+    /// Semantic hash of the payload and how it was computed
+    pub hash_envelope: HashEnvelope,
+    /// Signature over the envelope of each signer, by public key
+    pub signatures: BTreeMap<String, String>,
+}
+
+impl MultiSignedObject {
+    /// Start collecting signatures over data.
+    ///
+    /// # Returns
+    /// The object with no signatures yet, or an error if the data cannot be hashed
+    pub fn new(data: &Value, options: &CanonicalizeOptions) -> Result<MultiSignedObject> {
+        let hash_envelope = semantic_hash_envelope(data, options)?;
+        Ok(MultiSignedObject { payload: data.clone(), hash_envelope, signatures: BTreeMap::new() })
+    }
+
+    /// Add a signature by a keypair or other `Signer`.
+    pub fn sign(&mut self, signer: &dyn Signer) -> Result<()> {
+        let signature = signer.sign_message(&signing_message(&self.hash_envelope)?)?;
+        self.signatures.insert(signer.public_key()?.to_string(), signature);
+        Ok(())
+    }
+
+    /// Add the signature of a signed object made independently over the same payload. It
+    /// is kept under the signer's key in canonical form, whatever case its hex is in.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the object is over another hash or its signature does
+    /// not verify
+    pub fn add(&mut self, signed: &SignedObject) -> Result<()> {
+        if signed.hash_envelope != self.hash_envelope {
+            return Err(ConstitutionalError::ProtocolError("The signed object is over another hash".to_string()));
+        }
+        let key: PublicKey = signed.signer.parse()?;
+        if !key.verify_message(&signing_message(&self.hash_envelope)?, &signed.signature)? {
+            return Err(ConstitutionalError::ProtocolError(format!("Invalid signature from {}", signed.signer)));
+        }
+        self.signatures.insert(key.to_string(), signed.signature.clone());
+        Ok(())
+    }
+
+    /// Check the object against a policy: the envelope is the payload's hash, every
+    /// signature verifies, and the signers meet a requirement of the payload's action.
+    ///
+    /// # Returns
+    /// true if all hold, false otherwise; a ProtocolError if the payload has no
+    /// `action_type` or the policy no requirement for it, another error if a signer or
+    /// signature is malformed
+    pub fn verify(&self, policy: &CoSigningPolicy, options: &CanonicalizeOptions) -> Result<bool> {
+        let action_type = self.payload.get("action_type").and_then(Value::as_str).ok_or_else(|| {
+            ConstitutionalError::ProtocolError("A co-signed payload needs a string action_type".to_string())
+        })?;
+        if !self.hash_envelope.verify(&self.payload, options)? {
+            return Ok(false);
+        }
+        let message = signing_message(&self.hash_envelope)?;
+        for (signer, signature) in &self.signatures {
+            if !signer.parse::<PublicKey>()?.verify_message(&message, signature)? {
+                return Ok(false);
+            }
+        }
+        policy.satisfied_by(action_type, self.signatures.keys().map(String::as_str))
+    }
+}
+
+impl Serialize for MultiSignedObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MultiSignedObject", FIELDS.len())?;
+        state.serialize_field("payload", &self.payload)?;
+        state.serialize_field("hash_envelope", &self.hash_envelope)?;
+        state.serialize_field("signatures", &self.signatures)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for MultiSignedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<MultiSignedObject, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a multi-signed object"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        Ok(MultiSignedObject {
+            payload: take(&mut map, "payload")?,
+            hash_envelope: take(&mut map, "hash_envelope")?,
+            signatures: take(&mut map, "signatures")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_with, Keypair};
+    use serde_json::json;
+
+    fn agents() -> Vec<Keypair> {
+        (1..=4).map(|i| Keypair::from_secret(&[i; 32])).collect()
+    }
+
+    /// Agent 0 proposes, 1 and 2 review, 3 both proposes and reviews.
+    fn policy(agents: &[Keypair]) -> CoSigningPolicy {
+        CoSigningPolicy::new()
+            .role(&agents[0].public_key(), "proposer")
+            .role(&agents[1].public_key(), "reviewer")
+            .role(&agents[2].public_key(), "reviewer")
+            .role(&agents[3].public_key(), "proposer")
+            .role(&agents[3].public_key(), "reviewer")
+            .require("amend", &[("proposer", 1), ("reviewer", 2)])
+            .require("emergency", &[("proposer", 2)])
+            .require("emergency", &[("reviewer", 3)])
+    }
+
+    fn amendment() -> Value {
+        json!({"contract_id": "c-17", "action_type": "amend"})
+    }
+
+    #[test]
+    fn test_roles_fill_requirements() {
+        let agents = agents();
+        let policy = policy(&agents);
+        let options = CanonicalizeOptions::new();
+        let mut object = MultiSignedObject::new(&amendment(), &options).unwrap();
+        object.sign(&agents[0]).unwrap();
+        object.sign(&agents[1]).unwrap();
+        assert!(!object.verify(&policy, &options).unwrap());
+        object.add(&sign_with(&amendment(), &agents[2], &options).unwrap()).unwrap();
+        assert!(object.verify(&policy, &options).unwrap());
+
+        let stored = serde_json::to_string(&object).unwrap();
+        assert_eq!(serde_json::from_str::<MultiSignedObject>(&stored).unwrap(), object);
+        let mut tampered = object.clone();
+        tampered.payload["contract_id"] = json!("c-18");
+        assert!(!tampered.verify(&policy, &options).unwrap());
+        let mut forged = object.clone();
+        let signature = forged.signatures[&agents[0].public_key().to_string()].clone();
+        forged.signatures.insert(agents[1].public_key().to_string(), signature);
+        assert!(!forged.verify(&policy, &options).unwrap());
+
+        assert!(object.add(&sign_with(&json!({"action_type": "amend"}), &agents[3], &options).unwrap()).is_err());
+        let mut repeal = MultiSignedObject::new(&json!({"action_type": "repeal"}), &options).unwrap();
+        repeal.sign(&agents[0]).unwrap();
+        assert!(repeal.verify(&policy, &options).is_err());
+    }
+
+    #[test]
+    fn test_added_signers_are_canonical() {
+        let agents = agents();
+        let policy = policy(&agents);
+        let options = CanonicalizeOptions::new();
+        let mut object = MultiSignedObject::new(&amendment(), &options).unwrap();
+        object.sign(&agents[0]).unwrap();
+        object.sign(&agents[1]).unwrap();
+
+        // A signer written in uppercase hex is the same agent, with the same roles
+        let upper = |i: usize| {
+            let signed = sign_with(&amendment(), &agents[i], &options).unwrap();
+            let (scheme, key) = signed.signer.split_once(':').unwrap();
+            SignedObject { signer: format!("{}:{}", scheme, key.to_uppercase()), ..signed }
+        };
+        object.add(&upper(2)).unwrap();
+        assert!(object.verify(&policy, &options).unwrap());
+        object.add(&upper(0)).unwrap();
+        assert_eq!(object.signatures.len(), 3);
+        assert!(object.signatures.contains_key(&agents[2].public_key().to_string()));
+    }
+
+    #[test]
+    fn test_each_signer_fills_one_slot() {
+        let agents = agents();
+        let policy = policy(&agents);
+        let key = |i: usize| agents[i].public_key().to_string();
+        // Agent 3 cannot be both the proposer and a reviewer
+        assert!(!policy.satisfied_by("amend", [key(3).as_str(), key(1).as_str()]).unwrap());
+        // It has to review so that agent 0 can propose
+        assert!(policy.satisfied_by("amend", [key(3).as_str(), key(1).as_str(), key(0).as_str()]).unwrap());
+        // Either alternative suffices
+        assert!(policy.satisfied_by("emergency", [key(0).as_str(), key(3).as_str()]).unwrap());
+        assert!(policy.satisfied_by("emergency", [key(1).as_str(), key(2).as_str(), key(3).as_str()]).unwrap());
+        assert!(!policy.satisfied_by("emergency", [key(0).as_str(), key(1).as_str(), key(2).as_str()]).unwrap());
+        // Unknown agents hold no roles
+        let outsider = Keypair::from_secret(&[9; 32]).public_key().to_string();
+        assert!(!policy.satisfied_by("emergency", [key(0).as_str(), outsider.as_str()]).unwrap());
+    }
+}
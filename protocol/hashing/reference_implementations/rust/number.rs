@@ -0,0 +1,247 @@
+/// number.rs - Exact numeric handling for OCP canonicalization
+///
+/// JSON numbers are compared and rewritten on their decimal text rather than through `f64`,
+/// so large integers (e.g. token amounts above 2^53) and high-precision decimals keep every
+/// digit when serde_json's `arbitrary_precision` feature is enabled.
+
+use serde_json::Number;
+use std::cmp::Ordering;
+
+/// A JSON number decomposed as `(-1)^negative * 0.d1d2...dn * 10^exponent`, with leading
+/// and trailing zero digits stripped. Zero has no digits.
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn parse(text: &str) -> Decimal {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exp_part) = match unsigned.find(['e', 'E']) {
+            Some(i) => (&unsigned[..i], &unsigned[i + 1..]),
+            None => (unsigned, "0"),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        // Clamp absurd exponents; digits beyond i64 range cannot be ordered meaningfully anyway
+        let exp_value = exp_part
+            .trim_start_matches('+')
+            .parse::<i64>()
+            .unwrap_or(if exp_part.starts_with('-') { i64::MIN / 2 } else { i64::MAX / 2 });
+
+        let all_digits: Vec<u8> = int_part.bytes().chain(frac_part.bytes()).map(|b| b - b'0').collect();
+        let leading = all_digits.iter().take_while(|d| **d == 0).count();
+        let mut digits = all_digits[leading..].to_vec();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        let exponent = if digits.is_empty() {
+            0
+        } else {
+            int_part.len() as i64 - leading as i64 + exp_value
+        };
+
+        Decimal { negative: negative && !digits.is_empty(), digits, exponent }
+    }
+
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        match (self.digits.is_empty(), other.digits.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self
+                .exponent
+                .cmp(&other.exponent)
+                .then_with(|| self.digits.cmp(&other.digits)),
+        }
+    }
+}
+
+/// Compare two JSON numbers by exact decimal value.
+///
+/// Orders f64 values exactly as `f64::partial_cmp` does (shortest round-trip text preserves
+/// order) while never rounding integers or arbitrary-precision decimals.
+pub(crate) fn compare_numbers(a: &Number, b: &Number) -> Ordering {
+    if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+        return x.cmp(&y);
+    }
+    if let (Some(x), Some(y)) = (a.as_u64(), b.as_u64()) {
+        return x.cmp(&y);
+    }
+
+    let x = Decimal::parse(&a.to_string());
+    let y = Decimal::parse(&b.to_string());
+    match (x.negative, y.negative) {
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+        (false, false) => x.cmp_magnitude(&y),
+        (true, true) => y.cmp_magnitude(&x),
+    }
+}
+
+/// Rewrite an integral float as an integer; other numbers are returned unchanged.
+///
+/// With `keep_float`, integral floats stay floats and only `-0.0` is rewritten (to `0.0`).
+#[cfg(not(feature = "arbitrary_precision"))]
+pub(crate) fn normalize_number(n: &Number, keep_float: bool) -> Number {
+    // Largest magnitude at which every integer is exactly representable as an f64
+    const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+    match n.as_f64() {
+        Some(f) if n.is_f64() && keep_float && f == 0.0 => {
+            Number::from_f64(0.0).unwrap_or_else(|| n.clone())
+        }
+        Some(f) if n.is_f64() && !keep_float && f.fract() == 0.0 && f.abs() <= MAX_SAFE_INTEGER => {
+            Number::from(f as i64)
+        }
+        _ => n.clone(),
+    }
+}
+
+/// Remove trailing zeros after the decimal point (`1.50` becomes `1.5`, `1.0` becomes `1`,
+/// `-0.0` becomes `0`) on the number's exact text. Exponents are kept as written.
+///
+/// With `keep_float`, one fractional zero is kept so floats stay floats (`1.00` becomes `1.0`).
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) fn normalize_number(n: &Number, keep_float: bool) -> Number {
+    let text = n.to_string();
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text.as_str(), ""),
+    };
+
+    let mut mantissa = if mantissa.contains('.') {
+        let trimmed = mantissa.trim_end_matches('0');
+        match trimmed.strip_suffix('.') {
+            Some(integral) if keep_float => format!("{}.0", integral),
+            Some(integral) => integral.to_string(),
+            None => trimmed.to_string(),
+        }
+    } else {
+        mantissa.to_string()
+    };
+    if mantissa == "-0" || mantissa == "-0.0" {
+        mantissa.remove(0);
+    }
+
+    format!("{}{}", mantissa, exponent)
+        .parse::<Number>()
+        .unwrap_or_else(|_| n.clone())
+}
+
+/// Format a finite f64 exactly as ECMAScript's `Number.prototype.toString` does,
+/// as required by RFC 8785 3.2.2.3.
+pub(crate) fn format_ecmascript_number(value: f64) -> String {
+    if value == 0.0 {
+        // Covers -0.0 as well
+        return "0".to_string();
+    }
+
+    // Rust's exponent formatting yields the shortest round-trip digits, e.g. "1.2345e3"
+    let formatted = format!("{:e}", value.abs());
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat((-n) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n - 1 < 0 { '-' } else { '+' });
+        out.push_str(&(n - 1).abs().to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(text: &str) -> Number {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_jcs_number_serialization() {
+        // Sample values from RFC 8785 Appendix B
+        let cases: [(u64, &str); 12] = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x4415af1d78b58c3f, "99999999999999980000"),
+        ];
+
+        for (bits, expected) in cases {
+            assert_eq!(format_ecmascript_number(f64::from_bits(bits)), expected);
+        }
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        let ascending = ["-1e3", "-2.5", "-0.001", "0", "0.0001", "1", "1.5", "10", "9007199254740993", "1e300"];
+        for pair in ascending.windows(2) {
+            assert_eq!(compare_numbers(&num(pair[0]), &num(pair[1])), Ordering::Less, "{:?}", pair);
+            assert_eq!(compare_numbers(&num(pair[1]), &num(pair[0])), Ordering::Greater, "{:?}", pair);
+        }
+        assert_eq!(compare_numbers(&num("1"), &num("1.0")), Ordering::Equal);
+        assert_eq!(compare_numbers(&num("-0.0"), &num("0")), Ordering::Equal);
+        assert_eq!(
+            compare_numbers(&Number::from(u64::MAX), &Number::from(u64::MAX - 1)),
+            Ordering::Greater
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_ordering_and_normalization() {
+        // Adjacent integers above 2^53 collapse to the same f64
+        let a = num("123456789012345678901234567890");
+        let b = num("123456789012345678901234567891");
+        assert_eq!(a.as_f64(), b.as_f64());
+        assert_eq!(compare_numbers(&a, &b), Ordering::Less);
+
+        assert_eq!(normalize_number(&num("1.500"), false).to_string(), "1.5");
+        assert_eq!(normalize_number(&num("100.000"), false).to_string(), "100");
+        assert_eq!(normalize_number(&num("100.000"), true).to_string(), "100.0");
+        assert_eq!(normalize_number(&num("-0.0"), false).to_string(), "0");
+        assert_eq!(normalize_number(&num("-0.00"), true).to_string(), "0.0");
+        assert_eq!(normalize_number(&num("2.50e10"), false), num("2.5e10"));
+        assert_eq!(
+            normalize_number(&num("0.10000000000000000000001"), false).to_string(),
+            "0.10000000000000000000001"
+        );
+    }
+}
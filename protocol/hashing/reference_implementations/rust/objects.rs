@@ -0,0 +1,1614 @@
+/// objects.rs - Typed protocol objects besides contracts
+///
+/// The parts of a contract and the objects governance produces around it, as Rust
+/// types: the `Evidence` and `Reasoning` a contract carries, `Amendment`s to the
+/// constitution, agents' `Vote`s on contracts, the `Challenge`s raised against them
+/// in the optimistic window, the `DisputeRecord` of each challenge being adjudicated and
+/// the `Ruling`s that settle them.
+///
+/// ```json
+/// {"id":"7d4c...","proposer_agent":"Claude",
+///  "article":{"article_id":"III","title":"Obligations","clauses":["..."]},
+///  "reasoning":{"rationale":"Clarifies Article III.1","confidence":0.87},"timestamp":"2025-11-20T14:30:00Z"}
+/// {"id":"8e5d...","proposer_agent":"Claude","operation":"modify","target":"article-3.1.2","text":"...",
+///  "reasoning":{"rationale":"Narrows Article III.1","confidence":0.9},"timestamp":"2025-11-20T14:30:00Z"}
+/// {"contract_id":"550e...","voter_agent":"Gemini","vote":"approve","timestamp":"2025-11-21T09:00:00Z"}
+/// {"id":"9b2e...","contract_id":"550e...","contract_hash":"a3f5...","challenger_agent":"Grok",
+///  "grounds":"constitutional_violation","evidence":[{"type":"citation","pointer":"Article-III.1"}],
+///  "reasoning":{"rationale":"Exceeds Article III.1","confidence":0.8},"timestamp":"2025-11-22T10:00:00Z"}
+/// {"challenge_id":"9b2e...","challenge_hash":"5c1d...","contract_id":"550e...","status":"resolved",
+///  "opened_at":"2025-11-22T10:00:00Z","closes_at":"2025-11-29T10:00:00Z","ruling_id":"e1f0..."}
+/// {"id":"e1f0...","contract_id":"550e...","arbiter_agent":"DeepSeek","outcome":"upheld",
+///  "reasoning":{"rationale":"No fraud proof was substantiated","confidence":0.95},"timestamp":"2025-11-28T00:00:00Z"}
+/// ```
+///
+/// Like `Contract`, each keeps members it has no field for in `extra`, and its JSON is
+/// the object it was read from, so its canonical JSON and semantic hash match the
+/// untyped value's. Builders and deserialization check that required members are present
+/// with the right types; optional members are left out of the JSON when unset. Whether
+/// the values themselves are valid is for `validate`, which reports every violation.
+
+use crate::contract::validity;
+use crate::validation::FieldReport;
+use crate::{
+    verify_semantic_hash_with, Address, Canonicalize, Constitution, ConstitutionalError, Contract, EvidencePointer,
+    Result, Validity, Violation, ViolationKind,
+};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// A reference supporting a proposal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evidence {
+    /// Category of evidence, the `type` member, e.g. `archive_reference`
+    pub evidence_type: String,
+    /// Where the evidence is: an archive entry, a digest, an article such as
+    /// `Article-III.1`; see `EvidencePointer`
+    pub pointer: String,
+    /// Why the evidence is relevant
+    pub description: Option<String>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Evidence {
+    /// Evidence of a type at a pointer.
+    pub fn new(evidence_type: &str, pointer: &str) -> Self {
+        Evidence {
+            evidence_type: evidence_type.to_string(),
+            pointer: pointer.to_string(),
+            description: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// Say why the evidence is relevant.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set a member the struct has no field for.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// The pointer, parsed.
+    ///
+    /// # Returns
+    /// The pointer, or a ProtocolError if it is malformed
+    pub fn evidence_pointer(&self) -> Result<EvidencePointer> {
+        EvidencePointer::parse(&self.pointer)
+    }
+
+    /// The evidence's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("type".to_string(), Value::from(self.evidence_type.as_str()));
+        map.insert("pointer".to_string(), Value::from(self.pointer.as_str()));
+        if let Some(description) = &self.description {
+            map.insert("description".to_string(), Value::from(description.as_str()));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Why an agent proposes or decides something.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reasoning {
+    /// The explanation
+    pub rationale: String,
+    /// The agent's confidence, from 0 to 1, as written: `1` and `1.0` hash differently
+    pub confidence: Number,
+    /// Any other members, such as `constitutional_grounding`, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Reasoning {
+    /// A rationale held with a confidence.
+    ///
+    /// # Returns
+    /// The reasoning, or a ProtocolError if the confidence is not finite
+    pub fn new(rationale: &str, confidence: f64) -> Result<Self> {
+        let confidence = Number::from_f64(confidence)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Confidence {} is not finite", confidence)))?;
+        Ok(Reasoning { rationale: rationale.to_string(), confidence, extra: Map::new() })
+    }
+
+    /// Set a member the struct has no field for, e.g. `uncertainties`.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Check that the confidence is in [0, 1].
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        self.check(&mut report, "");
+        report.violations
+    }
+
+    /// Report violations at the reasoning's fields, the reasoning at `path` in its
+    /// parent, or at the root if `path` is empty.
+    pub(crate) fn check(&self, report: &mut FieldReport, path: &str) {
+        let at: &[&str] = if path.is_empty() { &["confidence"] } else { &[path, "confidence"] };
+        report.confidence(at, &self.confidence);
+    }
+
+    /// The reasoning's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("rationale".to_string(), Value::from(self.rationale.as_str()));
+        map.insert("confidence".to_string(), Value::Number(self.confidence.clone()));
+        Value::Object(map)
+    }
+}
+
+/// What a targeted amendment does at its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmendmentOperation {
+    /// Put new text where nothing is yet
+    Add,
+    /// Replace the text there
+    Modify,
+    /// Remove the text there
+    Repeal,
+}
+
+impl AmendmentOperation {
+    /// The operation as written in an amendment, e.g. `modify`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AmendmentOperation::Add => "add",
+            AmendmentOperation::Modify => "modify",
+            AmendmentOperation::Repeal => "repeal",
+        }
+    }
+}
+
+impl FromStr for AmendmentOperation {
+    type Err = ConstitutionalError;
+
+    fn from_str(operation: &str) -> Result<AmendmentOperation> {
+        match operation {
+            "add" => Ok(AmendmentOperation::Add),
+            "modify" => Ok(AmendmentOperation::Modify),
+            "repeal" => Ok(AmendmentOperation::Repeal),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown amendment operation {:?}", operation))),
+        }
+    }
+}
+
+impl fmt::Display for AmendmentOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A proposal to change the constitution: either a whole article to add or replace, or
+/// an operation at the address of an article, section or clause (see `document.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Amendment {
+    /// Unique amendment identifier, a UUID or content ID
+    pub id: String,
+    /// The agent proposing the amendment
+    pub proposer_agent: String,
+    /// The article as amended, with its `article_id`; None for a targeted amendment
+    pub article: Option<Value>,
+    /// What a targeted amendment does
+    pub operation: Option<AmendmentOperation>,
+    /// The address a targeted amendment changes
+    pub target: Option<Address>,
+    /// The new text of an add or modify: a string for a clause, a section or article
+    /// object otherwise
+    pub text: Option<Value>,
+    /// Why the article should change
+    pub reasoning: Reasoning,
+    /// IDs of the amendments that must be applied before this one, the `depends_on`
+    /// member, left out when empty
+    pub depends_on: Vec<String>,
+    /// When the amendment was proposed, RFC 3339
+    pub timestamp: String,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Amendment {
+    /// Start building an amendment.
+    pub fn builder() -> AmendmentBuilder {
+        AmendmentBuilder::default()
+    }
+
+    /// The ID of the article amended or containing the target; None only if the fields
+    /// were changed to name none.
+    pub fn article_id(&self) -> Option<String> {
+        match (&self.target, &self.article) {
+            (Some(target), _) => Some(target.article_id()),
+            (None, Some(article)) => article.get("article_id").and_then(Value::as_str).map(str::to_string),
+            (None, None) => None,
+        }
+    }
+
+    /// Apply the amendment to a constitution.
+    ///
+    /// # Returns
+    /// The article it replaced, or the text a targeted amendment replaced or removed, if
+    /// any; an error if the operation does not fit the constitution, which is then
+    /// unchanged
+    pub fn apply(&self, constitution: &mut Constitution) -> Result<Option<Value>> {
+        check_change(self.article.as_ref(), self.operation, self.target.as_ref(), self.text.as_ref())
+            .map_err(ConstitutionalError::ProtocolError)?;
+        match (self.operation, &self.target, &self.article) {
+            (Some(operation), Some(target), _) => constitution.change(operation, target, self.text.as_ref()),
+            (_, _, article) => constitution.amend(article.clone().unwrap_or_default()),
+        }
+    }
+
+    /// Check the amendment's fields: an `id` that is a UUID or content ID, a non-empty
+    /// `proposer_agent`, a `reasoning` confidence in [0, 1], `depends_on` IDs and an
+    /// RFC 3339 `timestamp`.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["id"], &self.id);
+        report.agent(&["proposer_agent"], &self.proposer_agent);
+        self.reasoning.check(&mut report, "reasoning");
+        for (index, dependency) in self.depends_on.iter().enumerate() {
+            report.id(&["depends_on", &index.to_string()], dependency);
+        }
+        report.timestamp(&["timestamp"], &self.timestamp);
+        report.violations
+    }
+
+    /// The amendment's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("id".to_string(), Value::from(self.id.as_str()));
+        map.insert("proposer_agent".to_string(), Value::from(self.proposer_agent.as_str()));
+        if let Some(article) = &self.article {
+            map.insert("article".to_string(), article.clone());
+        }
+        if let Some(operation) = self.operation {
+            map.insert("operation".to_string(), Value::from(operation.as_str()));
+        }
+        if let Some(target) = &self.target {
+            map.insert("target".to_string(), Value::from(target.to_string()));
+        }
+        if let Some(text) = &self.text {
+            map.insert("text".to_string(), text.clone());
+        }
+        map.insert("reasoning".to_string(), self.reasoning.to_value());
+        if !self.depends_on.is_empty() {
+            map.insert("depends_on".to_string(), Value::from(self.depends_on.clone()));
+        }
+        map.insert("timestamp".to_string(), Value::from(self.timestamp.as_str()));
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Builds an `Amendment`, checking that every member is set.
+#[derive(Debug, Clone, Default)]
+pub struct AmendmentBuilder {
+    id: Option<String>,
+    proposer_agent: Option<String>,
+    article: Option<Value>,
+    operation: Option<AmendmentOperation>,
+    target: Option<Address>,
+    text: Option<Value>,
+    reasoning: Option<Reasoning>,
+    depends_on: Vec<String>,
+    timestamp: Option<String>,
+    schema_version: Option<u32>,
+    extra: Map<String, Value>,
+}
+
+impl AmendmentBuilder {
+    /// Set the amendment's ID.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Set the proposing agent.
+    pub fn proposer_agent(mut self, agent: &str) -> Self {
+        self.proposer_agent = Some(agent.to_string());
+        self
+    }
+
+    /// Set the article as amended.
+    pub fn article(mut self, article: Value) -> Self {
+        self.article = Some(article);
+        self
+    }
+
+    /// Make the amendment add text at an address where there is none.
+    pub fn add(self, target: Address, text: Value) -> Self {
+        self.targeting(AmendmentOperation::Add, target, Some(text))
+    }
+
+    /// Make the amendment replace the text at an address.
+    pub fn modify(self, target: Address, text: Value) -> Self {
+        self.targeting(AmendmentOperation::Modify, target, Some(text))
+    }
+
+    /// Make the amendment remove the text at an address.
+    pub fn repeal(self, target: Address) -> Self {
+        self.targeting(AmendmentOperation::Repeal, target, None)
+    }
+
+    fn targeting(mut self, operation: AmendmentOperation, target: Address, text: Option<Value>) -> Self {
+        self.operation = Some(operation);
+        self.target = Some(target);
+        self.text = text;
+        self
+    }
+
+    /// Set the reasoning.
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Add an amendment that must be applied before this one.
+    pub fn depends_on(mut self, id: &str) -> Self {
+        self.depends_on.push(id.to_string());
+        self
+    }
+
+    /// Set the proposal timestamp.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Set the version of the schema the object follows.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set a member the struct has no field for.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Build the amendment.
+    ///
+    /// # Returns
+    /// The amendment, or a ProtocolError naming a member that was not set, if it has both
+    /// an article and a target or neither, or if the article has no string `article_id`
+    pub fn build(self) -> Result<Amendment> {
+        check_extra(&self.extra, AMENDMENT_FIELDS, "an amendment")?;
+        check_change(self.article.as_ref(), self.operation, self.target.as_ref(), self.text.as_ref())
+            .map_err(ConstitutionalError::ProtocolError)?;
+        let amendment = Amendment {
+            id: required(self.id, "an amendment", "an id")?,
+            proposer_agent: required(self.proposer_agent, "an amendment", "a proposer_agent")?,
+            article: self.article,
+            operation: self.operation,
+            target: self.target,
+            text: self.text,
+            reasoning: required(self.reasoning, "an amendment", "reasoning")?,
+            depends_on: self.depends_on,
+            timestamp: required(self.timestamp, "an amendment", "a timestamp")?,
+            schema_version: self.schema_version,
+            extra: self.extra,
+        };
+        Ok(amendment)
+    }
+}
+
+/// How an agent votes on a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteChoice {
+    Approve,
+    Reject,
+    Abstain,
+}
+
+impl VoteChoice {
+    /// The choice as written in a vote, e.g. `approve`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoteChoice::Approve => "approve",
+            VoteChoice::Reject => "reject",
+            VoteChoice::Abstain => "abstain",
+        }
+    }
+}
+
+impl FromStr for VoteChoice {
+    type Err = ConstitutionalError;
+
+    fn from_str(choice: &str) -> Result<VoteChoice> {
+        match choice {
+            "approve" => Ok(VoteChoice::Approve),
+            "reject" => Ok(VoteChoice::Reject),
+            "abstain" => Ok(VoteChoice::Abstain),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown vote {:?}", choice))),
+        }
+    }
+}
+
+impl fmt::Display for VoteChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An agent's vote on a contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vote {
+    /// The contract voted on
+    pub contract_id: String,
+    /// The agent voting
+    pub voter_agent: String,
+    /// The vote, the `vote` member
+    pub choice: VoteChoice,
+    /// Why the agent votes so, if it says
+    pub reasoning: Option<Reasoning>,
+    /// When the vote was cast, RFC 3339
+    pub timestamp: String,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Vote {
+    /// Start building a vote.
+    pub fn builder() -> VoteBuilder {
+        VoteBuilder::default()
+    }
+
+    /// Check the vote's fields: a `contract_id` that is a UUID or content ID, a non-empty
+    /// `voter_agent`, a `reasoning` confidence in [0, 1] if there is reasoning, and an
+    /// RFC 3339 `timestamp`.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["contract_id"], &self.contract_id);
+        report.agent(&["voter_agent"], &self.voter_agent);
+        if let Some(reasoning) = &self.reasoning {
+            reasoning.check(&mut report, "reasoning");
+        }
+        report.timestamp(&["timestamp"], &self.timestamp);
+        report.violations
+    }
+
+    /// The vote's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("contract_id".to_string(), Value::from(self.contract_id.as_str()));
+        map.insert("voter_agent".to_string(), Value::from(self.voter_agent.as_str()));
+        map.insert("vote".to_string(), Value::from(self.choice.as_str()));
+        if let Some(reasoning) = &self.reasoning {
+            map.insert("reasoning".to_string(), reasoning.to_value());
+        }
+        map.insert("timestamp".to_string(), Value::from(self.timestamp.as_str()));
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Builds a `Vote`, checking that every required member is set.
+#[derive(Debug, Clone, Default)]
+pub struct VoteBuilder {
+    contract_id: Option<String>,
+    voter_agent: Option<String>,
+    choice: Option<VoteChoice>,
+    reasoning: Option<Reasoning>,
+    timestamp: Option<String>,
+    schema_version: Option<u32>,
+    extra: Map<String, Value>,
+}
+
+impl VoteBuilder {
+    /// Set the contract voted on.
+    pub fn contract_id(mut self, id: &str) -> Self {
+        self.contract_id = Some(id.to_string());
+        self
+    }
+
+    /// Set the voting agent.
+    pub fn voter_agent(mut self, agent: &str) -> Self {
+        self.voter_agent = Some(agent.to_string());
+        self
+    }
+
+    /// Set the vote.
+    pub fn choice(mut self, choice: VoteChoice) -> Self {
+        self.choice = Some(choice);
+        self
+    }
+
+    /// Give the reasoning behind the vote.
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Set when the vote was cast.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Set the version of the schema the object follows.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set a member the struct has no field for.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Build the vote.
+    ///
+    /// # Returns
+    /// The vote, or a ProtocolError naming a required member that was not set
+    pub fn build(self) -> Result<Vote> {
+        check_extra(&self.extra, VOTE_FIELDS, "a vote")?;
+        Ok(Vote {
+            contract_id: required(self.contract_id, "a vote", "a contract_id")?,
+            voter_agent: required(self.voter_agent, "a vote", "a voter_agent")?,
+            choice: required(self.choice, "a vote", "a choice")?,
+            reasoning: self.reasoning,
+            timestamp: required(self.timestamp, "a vote", "a timestamp")?,
+            schema_version: self.schema_version,
+            extra: self.extra,
+        })
+    }
+}
+
+/// How a ruling settles a challenged contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulingOutcome {
+    /// The contract stands.
+    Upheld,
+    /// The contract is invalidated and its action rolled back.
+    Invalidated,
+}
+
+impl RulingOutcome {
+    /// The outcome as written in a ruling, e.g. `upheld`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RulingOutcome::Upheld => "upheld",
+            RulingOutcome::Invalidated => "invalidated",
+        }
+    }
+}
+
+impl FromStr for RulingOutcome {
+    type Err = ConstitutionalError;
+
+    fn from_str(outcome: &str) -> Result<RulingOutcome> {
+        match outcome {
+            "upheld" => Ok(RulingOutcome::Upheld),
+            "invalidated" => Ok(RulingOutcome::Invalidated),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown ruling outcome {:?}", outcome))),
+        }
+    }
+}
+
+impl fmt::Display for RulingOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The decision settling a challenged contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ruling {
+    /// Unique ruling identifier, a UUID or content ID
+    pub id: String,
+    /// The contract ruled on
+    pub contract_id: String,
+    /// Semantic hash of the contract as ruled on
+    pub contract_hash: Option<String>,
+    /// The challenge the ruling resolves
+    pub challenge_id: Option<String>,
+    /// Semantic hash of the challenge
+    pub challenge_hash: Option<String>,
+    /// The agent ruling
+    pub arbiter_agent: String,
+    /// Whether the contract stands
+    pub outcome: RulingOutcome,
+    /// Why
+    pub reasoning: Reasoning,
+    /// Semantic hashes of the earlier rulings cited as precedent
+    pub precedents: Vec<String>,
+    /// When the ruling was made, RFC 3339
+    pub timestamp: String,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Ruling {
+    /// Start building a ruling.
+    pub fn builder() -> RulingBuilder {
+        RulingBuilder::default()
+    }
+
+    /// Check the ruling's fields: UUIDs or content IDs for `id`, `contract_id` and
+    /// `challenge_id`, SHA-256 hashes for `contract_hash`, `challenge_hash` and each
+    /// precedent, a non-empty `arbiter_agent`, a `reasoning` confidence in [0, 1] and an
+    /// RFC 3339 `timestamp`.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["id"], &self.id);
+        report.id(&["contract_id"], &self.contract_id);
+        if let Some(hash) = &self.contract_hash {
+            report.hash(&["contract_hash"], hash);
+        }
+        if let Some(challenge_id) = &self.challenge_id {
+            report.id(&["challenge_id"], challenge_id);
+        }
+        if let Some(hash) = &self.challenge_hash {
+            report.hash(&["challenge_hash"], hash);
+        }
+        report.agent(&["arbiter_agent"], &self.arbiter_agent);
+        self.reasoning.check(&mut report, "reasoning");
+        for (index, precedent) in self.precedents.iter().enumerate() {
+            report.hash(&["precedents", &index.to_string()], precedent);
+        }
+        report.timestamp(&["timestamp"], &self.timestamp);
+        report.violations
+    }
+
+    /// The ruling's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("id".to_string(), Value::from(self.id.as_str()));
+        map.insert("contract_id".to_string(), Value::from(self.contract_id.as_str()));
+        for (name, member) in [
+            ("contract_hash", &self.contract_hash),
+            ("challenge_id", &self.challenge_id),
+            ("challenge_hash", &self.challenge_hash),
+        ] {
+            if let Some(member) = member {
+                map.insert(name.to_string(), Value::from(member.as_str()));
+            }
+        }
+        map.insert("arbiter_agent".to_string(), Value::from(self.arbiter_agent.as_str()));
+        map.insert("outcome".to_string(), Value::from(self.outcome.as_str()));
+        map.insert("reasoning".to_string(), self.reasoning.to_value());
+        if !self.precedents.is_empty() {
+            map.insert("precedents".to_string(), Value::from(self.precedents.clone()));
+        }
+        map.insert("timestamp".to_string(), Value::from(self.timestamp.as_str()));
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Builds a `Ruling`, checking that every member is set.
+#[derive(Debug, Clone, Default)]
+pub struct RulingBuilder {
+    id: Option<String>,
+    contract_id: Option<String>,
+    contract_hash: Option<String>,
+    challenge_id: Option<String>,
+    challenge_hash: Option<String>,
+    arbiter_agent: Option<String>,
+    outcome: Option<RulingOutcome>,
+    reasoning: Option<Reasoning>,
+    precedents: Vec<String>,
+    timestamp: Option<String>,
+    schema_version: Option<u32>,
+    extra: Map<String, Value>,
+}
+
+impl RulingBuilder {
+    /// Set the ruling's ID.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Set the contract ruled on.
+    pub fn contract_id(mut self, id: &str) -> Self {
+        self.contract_id = Some(id.to_string());
+        self
+    }
+
+    /// Resolve a challenge, taking the contract and its hash from the challenge.
+    ///
+    /// # Returns
+    /// The builder, or an error if the challenge cannot be hashed
+    pub fn challenge(mut self, challenge: &Challenge) -> Result<Self> {
+        self.contract_id = Some(challenge.contract_id.clone());
+        self.contract_hash = Some(challenge.contract_hash.clone());
+        self.challenge_id = Some(challenge.id.clone());
+        self.challenge_hash = Some(challenge.semantic_hash()?);
+        Ok(self)
+    }
+
+    /// Set the ruling agent.
+    pub fn arbiter_agent(mut self, agent: &str) -> Self {
+        self.arbiter_agent = Some(agent.to_string());
+        self
+    }
+
+    /// Set the outcome.
+    pub fn outcome(mut self, outcome: RulingOutcome) -> Self {
+        self.outcome = Some(outcome);
+        self
+    }
+
+    /// Set the reasoning.
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Cite an earlier ruling as precedent, by its semantic hash.
+    pub fn precedent(mut self, hash: &str) -> Self {
+        self.precedents.push(hash.to_string());
+        self
+    }
+
+    /// Set when the ruling was made.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Set the version of the schema the object follows.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set a member the struct has no field for.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Build the ruling.
+    ///
+    /// # Returns
+    /// The ruling, or a ProtocolError naming a member that was not set
+    pub fn build(self) -> Result<Ruling> {
+        check_extra(&self.extra, RULING_FIELDS, "a ruling")?;
+        Ok(Ruling {
+            id: required(self.id, "a ruling", "an id")?,
+            contract_id: required(self.contract_id, "a ruling", "a contract_id")?,
+            contract_hash: self.contract_hash,
+            challenge_id: self.challenge_id,
+            challenge_hash: self.challenge_hash,
+            arbiter_agent: required(self.arbiter_agent, "a ruling", "an arbiter_agent")?,
+            outcome: required(self.outcome, "a ruling", "an outcome")?,
+            reasoning: required(self.reasoning, "a ruling", "reasoning")?,
+            precedents: self.precedents,
+            timestamp: required(self.timestamp, "a ruling", "a timestamp")?,
+            schema_version: self.schema_version,
+            extra: self.extra,
+        })
+    }
+}
+
+/// What a challenger claims is wrong with a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeGrounds {
+    /// The contract's recorded hash is not the hash of its content.
+    HashMismatch,
+    /// The contract skipped a step the protocol requires.
+    ProceduralViolation,
+    /// The contract's action violates the constitution.
+    ConstitutionalViolation,
+    /// The action was executed other than as the contract states.
+    ExecutionInconsistency,
+    /// The contract games agents' reputations.
+    ReputationManipulation,
+}
+
+impl ChallengeGrounds {
+    /// The grounds as written in a challenge, e.g. `hash_mismatch`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChallengeGrounds::HashMismatch => "hash_mismatch",
+            ChallengeGrounds::ProceduralViolation => "procedural_violation",
+            ChallengeGrounds::ConstitutionalViolation => "constitutional_violation",
+            ChallengeGrounds::ExecutionInconsistency => "execution_inconsistency",
+            ChallengeGrounds::ReputationManipulation => "reputation_manipulation",
+        }
+    }
+}
+
+impl FromStr for ChallengeGrounds {
+    type Err = ConstitutionalError;
+
+    fn from_str(grounds: &str) -> Result<ChallengeGrounds> {
+        match grounds {
+            "hash_mismatch" => Ok(ChallengeGrounds::HashMismatch),
+            "procedural_violation" => Ok(ChallengeGrounds::ProceduralViolation),
+            "constitutional_violation" => Ok(ChallengeGrounds::ConstitutionalViolation),
+            "execution_inconsistency" => Ok(ChallengeGrounds::ExecutionInconsistency),
+            "reputation_manipulation" => Ok(ChallengeGrounds::ReputationManipulation),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown challenge grounds {:?}", grounds))),
+        }
+    }
+}
+
+impl fmt::Display for ChallengeGrounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An agent's claim, within the optimistic window, that a contract is invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge {
+    /// Unique challenge identifier, a UUID or content ID
+    pub id: String,
+    /// The contract challenged
+    pub contract_id: String,
+    /// Semantic hash of the contract as challenged
+    pub contract_hash: String,
+    /// The agent challenging
+    pub challenger_agent: String,
+    /// What is claimed to be wrong
+    pub grounds: ChallengeGrounds,
+    /// What substantiates the claim
+    pub evidence: Vec<Evidence>,
+    /// Why
+    pub reasoning: Reasoning,
+    /// When the challenge was raised, RFC 3339
+    pub timestamp: String,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl Challenge {
+    /// Start building a challenge.
+    pub fn builder() -> ChallengeBuilder {
+        ChallengeBuilder::default()
+    }
+
+    /// Whether the challenge is against this contract as it stands: its ID and semantic
+    /// hash match the challenge's.
+    ///
+    /// # Returns
+    /// The answer, or an error if the contract cannot be hashed or the recorded hash is
+    /// malformed
+    pub fn challenges(&self, contract: &Contract) -> Result<bool> {
+        let options = contract.canonical_options();
+        Ok(contract.id == self.contract_id
+            && verify_semantic_hash_with(&contract.canonical_value(), &self.contract_hash, &options)?)
+    }
+
+    /// Check the challenge's fields: UUIDs or content IDs for `id` and `contract_id`, a
+    /// SHA-256 `contract_hash`, a non-empty `challenger_agent`, parseable evidence
+    /// pointers, a `reasoning` confidence in [0, 1] and an RFC 3339 `timestamp`.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["id"], &self.id);
+        report.id(&["contract_id"], &self.contract_id);
+        report.hash(&["contract_hash"], &self.contract_hash);
+        report.agent(&["challenger_agent"], &self.challenger_agent);
+        for (index, evidence) in self.evidence.iter().enumerate() {
+            report.evidence_pointer(&["evidence", &index.to_string(), "pointer"], &evidence.pointer);
+        }
+        self.reasoning.check(&mut report, "reasoning");
+        report.timestamp(&["timestamp"], &self.timestamp);
+        report.violations
+    }
+
+    /// The challenge's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("id".to_string(), Value::from(self.id.as_str()));
+        map.insert("contract_id".to_string(), Value::from(self.contract_id.as_str()));
+        map.insert("contract_hash".to_string(), Value::from(self.contract_hash.as_str()));
+        map.insert("challenger_agent".to_string(), Value::from(self.challenger_agent.as_str()));
+        map.insert("grounds".to_string(), Value::from(self.grounds.as_str()));
+        map.insert("evidence".to_string(), self.evidence.iter().map(Evidence::to_value).collect());
+        map.insert("reasoning".to_string(), self.reasoning.to_value());
+        map.insert("timestamp".to_string(), Value::from(self.timestamp.as_str()));
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+/// Builds a `Challenge`, checking that every member is set.
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeBuilder {
+    id: Option<String>,
+    contract_id: Option<String>,
+    contract_hash: Option<String>,
+    challenger_agent: Option<String>,
+    grounds: Option<ChallengeGrounds>,
+    evidence: Vec<Evidence>,
+    reasoning: Option<Reasoning>,
+    timestamp: Option<String>,
+    schema_version: Option<u32>,
+    extra: Map<String, Value>,
+}
+
+impl ChallengeBuilder {
+    /// Set the challenge's ID.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Set the contract challenged, by its ID and semantic hash.
+    pub fn contract(mut self, id: &str, hash: &str) -> Self {
+        self.contract_id = Some(id.to_string());
+        self.contract_hash = Some(hash.to_string());
+        self
+    }
+
+    /// Set the challenging agent.
+    pub fn challenger_agent(mut self, agent: &str) -> Self {
+        self.challenger_agent = Some(agent.to_string());
+        self
+    }
+
+    /// Set the grounds.
+    pub fn grounds(mut self, grounds: ChallengeGrounds) -> Self {
+        self.grounds = Some(grounds);
+        self
+    }
+
+    /// Add a piece of evidence.
+    pub fn evidence(mut self, evidence: Evidence) -> Self {
+        self.evidence.push(evidence);
+        self
+    }
+
+    /// Set the reasoning.
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Set when the challenge was raised.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Set the version of the schema the object follows.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set a member the struct has no field for.
+    pub fn field(mut self, name: &str, value: Value) -> Self {
+        self.extra.insert(name.to_string(), value);
+        self
+    }
+
+    /// Build the challenge.
+    ///
+    /// # Returns
+    /// The challenge, or a ProtocolError naming a member that was not set
+    pub fn build(self) -> Result<Challenge> {
+        check_extra(&self.extra, CHALLENGE_FIELDS, "a challenge")?;
+        Ok(Challenge {
+            id: required(self.id, "a challenge", "an id")?,
+            contract_id: required(self.contract_id, "a challenge", "a contract_id")?,
+            contract_hash: required(self.contract_hash, "a challenge", "a contract_hash")?,
+            challenger_agent: required(self.challenger_agent, "a challenge", "a challenger_agent")?,
+            grounds: required(self.grounds, "a challenge", "grounds")?,
+            evidence: self.evidence,
+            reasoning: required(self.reasoning, "a challenge", "reasoning")?,
+            timestamp: required(self.timestamp, "a challenge", "a timestamp")?,
+            schema_version: self.schema_version,
+            extra: self.extra,
+        })
+    }
+}
+
+/// Where a dispute stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeStatus {
+    /// Awaiting a ruling.
+    Open,
+    /// Settled by a ruling.
+    Resolved,
+    /// Dropped by the challenger before a ruling.
+    Withdrawn,
+}
+
+impl DisputeStatus {
+    /// The status as written in a dispute record, e.g. `open`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DisputeStatus::Open => "open",
+            DisputeStatus::Resolved => "resolved",
+            DisputeStatus::Withdrawn => "withdrawn",
+        }
+    }
+}
+
+impl FromStr for DisputeStatus {
+    type Err = ConstitutionalError;
+
+    fn from_str(status: &str) -> Result<DisputeStatus> {
+        match status {
+            "open" => Ok(DisputeStatus::Open),
+            "resolved" => Ok(DisputeStatus::Resolved),
+            "withdrawn" => Ok(DisputeStatus::Withdrawn),
+            _ => Err(ConstitutionalError::ProtocolError(format!("Unknown dispute status {:?}", status))),
+        }
+    }
+}
+
+impl fmt::Display for DisputeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The record of a challenge being adjudicated: which challenge, until when a ruling is
+/// due, and how it ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisputeRecord {
+    /// The challenge disputed
+    pub challenge_id: String,
+    /// Semantic hash of the challenge
+    pub challenge_hash: String,
+    /// The contract challenged
+    pub contract_id: String,
+    /// Where the dispute stands
+    pub status: DisputeStatus,
+    /// When the dispute opened, the challenge's timestamp
+    pub opened_at: String,
+    /// When the dispute window closes, RFC 3339
+    pub closes_at: String,
+    /// The ruling settling the dispute, once it is resolved
+    pub ruling_id: Option<String>,
+    /// Version of the schema the object follows; absent means version 1
+    pub schema_version: Option<u32>,
+    /// Any other members, by name
+    pub extra: Map<String, Value>,
+}
+
+impl DisputeRecord {
+    /// Open a dispute over a challenge.
+    ///
+    /// # Arguments
+    /// * `challenge` - The challenge raised
+    /// * `closes_at` - When the window for ruling on it closes, RFC 3339
+    ///
+    /// # Returns
+    /// The open dispute, or an error if the challenge cannot be hashed
+    pub fn open(challenge: &Challenge, closes_at: &str) -> Result<DisputeRecord> {
+        Ok(DisputeRecord {
+            challenge_id: challenge.id.clone(),
+            challenge_hash: challenge.semantic_hash()?,
+            contract_id: challenge.contract_id.clone(),
+            status: DisputeStatus::Open,
+            opened_at: challenge.timestamp.clone(),
+            closes_at: closes_at.to_string(),
+            ruling_id: None,
+            schema_version: None,
+            extra: Map::new(),
+        })
+    }
+
+    /// Settle the dispute with a ruling.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the dispute is not open
+    pub fn resolve(&mut self, ruling_id: &str) -> Result<()> {
+        self.close(DisputeStatus::Resolved)?;
+        self.ruling_id = Some(ruling_id.to_string());
+        Ok(())
+    }
+
+    /// Drop the dispute without a ruling.
+    ///
+    /// # Returns
+    /// Ok, or a ProtocolError if the dispute is not open
+    pub fn withdraw(&mut self) -> Result<()> {
+        self.close(DisputeStatus::Withdrawn)
+    }
+
+    fn close(&mut self, status: DisputeStatus) -> Result<()> {
+        if self.status != DisputeStatus::Open {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "The dispute over {} is {} already",
+                self.challenge_id, self.status
+            )));
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Whether the dispute is awaiting a ruling at a time: it is open, and the time is at
+    /// or after `opened_at` and before `closes_at`.
+    ///
+    /// # Returns
+    /// The answer, or a ProtocolError if a timestamp is malformed
+    pub fn is_open_at(&self, at: &str) -> Result<bool> {
+        let window = validity(Some(&self.opened_at), Some(&self.closes_at), at)?;
+        Ok(self.status == DisputeStatus::Open && window == Validity::Valid)
+    }
+
+    /// Check the record's fields: UUIDs or content IDs for `challenge_id`, `contract_id`
+    /// and `ruling_id`, a SHA-256 `challenge_hash`, and RFC 3339 `opened_at` and
+    /// `closes_at` with the window closing after it opens.
+    ///
+    /// # Returns
+    /// Every violation, each with the JSON Pointer of its field
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        report.id(&["challenge_id"], &self.challenge_id);
+        report.hash(&["challenge_hash"], &self.challenge_hash);
+        report.id(&["contract_id"], &self.contract_id);
+        report.span(
+            ["opened_at", "closes_at"],
+            Some(&self.opened_at),
+            Some(&self.closes_at),
+            ViolationKind::EmptyDisputeWindow,
+        );
+        if let Some(ruling_id) = &self.ruling_id {
+            report.id(&["ruling_id"], ruling_id);
+        }
+        report.violations
+    }
+
+    /// The record's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut map = self.extra.clone();
+        map.insert("challenge_id".to_string(), Value::from(self.challenge_id.as_str()));
+        map.insert("challenge_hash".to_string(), Value::from(self.challenge_hash.as_str()));
+        map.insert("contract_id".to_string(), Value::from(self.contract_id.as_str()));
+        map.insert("status".to_string(), Value::from(self.status.as_str()));
+        map.insert("opened_at".to_string(), Value::from(self.opened_at.as_str()));
+        map.insert("closes_at".to_string(), Value::from(self.closes_at.as_str()));
+        if let Some(ruling_id) = &self.ruling_id {
+            map.insert("ruling_id".to_string(), Value::from(ruling_id.as_str()));
+        }
+        if let Some(version) = self.schema_version {
+            map.insert("schema_version".to_string(), Value::from(version));
+        }
+        Value::Object(map)
+    }
+}
+
+const AMENDMENT_FIELDS: &[&str] = &[
+    "id",
+    "proposer_agent",
+    "article",
+    "operation",
+    "target",
+    "text",
+    "reasoning",
+    "depends_on",
+    "timestamp",
+    "schema_version",
+];
+const VOTE_FIELDS: &[&str] = &["contract_id", "voter_agent", "vote", "reasoning", "timestamp", "schema_version"];
+const RULING_FIELDS: &[&str] = &[
+    "id",
+    "contract_id",
+    "contract_hash",
+    "challenge_id",
+    "challenge_hash",
+    "arbiter_agent",
+    "outcome",
+    "reasoning",
+    "precedents",
+    "timestamp",
+    "schema_version",
+];
+const CHALLENGE_FIELDS: &[&str] = &[
+    "id",
+    "contract_id",
+    "contract_hash",
+    "challenger_agent",
+    "grounds",
+    "evidence",
+    "reasoning",
+    "timestamp",
+    "schema_version",
+];
+
+fn required<T>(value: Option<T>, object: &str, member: &str) -> Result<T> {
+    value.ok_or_else(|| ConstitutionalError::ProtocolError(format!("{} needs {}", capitalized(object), member)))
+}
+
+/// Extra members must not shadow the ones with fields of their own.
+pub(crate) fn check_extra(extra: &Map<String, Value>, fields: &[&str], object: &str) -> Result<()> {
+    match extra.keys().find(|name| fields.contains(&name.as_str())) {
+        Some(name) => Err(ConstitutionalError::ProtocolError(format!("{} is not an extra member of {}", name, object))),
+        None => Ok(()),
+    }
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// A resolved dispute names its ruling, and no other does.
+fn check_ruling(status: DisputeStatus, ruling_id: Option<&String>) -> std::result::Result<(), String> {
+    match (status, ruling_id) {
+        (DisputeStatus::Resolved, None) => Err("A resolved dispute needs a ruling_id".to_string()),
+        (DisputeStatus::Open | DisputeStatus::Withdrawn, Some(_)) => {
+            Err(format!("A dispute that is {} has no ruling_id", status))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// An amendment has a whole article with a string `article_id`, or an operation and a
+/// target instead, with a text unless it repeals.
+fn check_change(
+    article: Option<&Value>,
+    operation: Option<AmendmentOperation>,
+    target: Option<&Address>,
+    text: Option<&Value>,
+) -> std::result::Result<(), String> {
+    match (article, operation, target) {
+        (Some(article), None, None) if text.is_none() => match article.get("article_id") {
+            Some(Value::String(_)) => Ok(()),
+            _ => Err("An amended article needs a string article_id".to_string()),
+        },
+        (Some(_), _, _) => Err("An amendment with an article has no operation, target or text".to_string()),
+        (None, Some(AmendmentOperation::Repeal), Some(target)) if text.is_some() => {
+            Err(format!("An amendment repealing {} has no text", target))
+        }
+        (None, Some(operation), Some(target)) if text.is_none() && operation != AmendmentOperation::Repeal => {
+            Err(format!("An amendment to {} {} needs a text", operation, target))
+        }
+        (None, Some(_), Some(_)) => Ok(()),
+        _ => Err("An amendment needs an article, or an operation and a target".to_string()),
+    }
+}
+
+macro_rules! canonicalize_objects {
+    ($($ty:ty),*) => {
+        $(
+            impl Canonicalize for $ty {
+                fn canonical_value(&self) -> Value {
+                    self.to_value()
+                }
+            }
+
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                    self.to_value().serialize(serializer)
+                }
+            }
+        )*
+    };
+}
+
+canonicalize_objects!(Evidence, Reasoning, Amendment, Vote, Ruling, Challenge, DisputeRecord);
+
+impl<'de> Deserialize<'de> for Evidence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Evidence, D::Error> {
+        let mut map = object(deserializer, "evidence")?;
+        Ok(Evidence {
+            evidence_type: take(&mut map, "type")?,
+            pointer: take(&mut map, "pointer")?,
+            description: take_optional(&mut map, "description")?,
+            extra: map,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Reasoning {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Reasoning, D::Error> {
+        let mut map = object(deserializer, "reasoning")?;
+        Ok(Reasoning { rationale: take(&mut map, "rationale")?, confidence: take(&mut map, "confidence")?, extra: map })
+    }
+}
+
+impl<'de> Deserialize<'de> for Amendment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Amendment, D::Error> {
+        let mut map = object(deserializer, "an amendment")?;
+        let article = take_optional(&mut map, "article")?;
+        let operation: Option<String> = take_optional(&mut map, "operation")?;
+        let operation = operation
+            .map(|operation| operation.parse().map_err(|e| de::Error::custom(format!("operation: {}", e))))
+            .transpose()?;
+        let target: Option<String> = take_optional(&mut map, "target")?;
+        let target =
+            target.map(|target| target.parse().map_err(|e| de::Error::custom(format!("target: {}", e)))).transpose()?;
+        let text = take_optional(&mut map, "text")?;
+        check_change(article.as_ref(), operation, target.as_ref(), text.as_ref()).map_err(de::Error::custom)?;
+        Ok(Amendment {
+            id: take(&mut map, "id")?,
+            proposer_agent: take(&mut map, "proposer_agent")?,
+            article,
+            operation,
+            target,
+            text,
+            reasoning: take(&mut map, "reasoning")?,
+            depends_on: take_optional(&mut map, "depends_on")?.unwrap_or_default(),
+            timestamp: take(&mut map, "timestamp")?,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Vote {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vote, D::Error> {
+        let mut map = object(deserializer, "a vote")?;
+        let choice: String = take(&mut map, "vote")?;
+        Ok(Vote {
+            contract_id: take(&mut map, "contract_id")?,
+            voter_agent: take(&mut map, "voter_agent")?,
+            choice: choice.parse().map_err(|e| de::Error::custom(format!("vote: {}", e)))?,
+            reasoning: take_optional(&mut map, "reasoning")?,
+            timestamp: take(&mut map, "timestamp")?,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Ruling {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Ruling, D::Error> {
+        let mut map = object(deserializer, "a ruling")?;
+        let outcome: String = take(&mut map, "outcome")?;
+        Ok(Ruling {
+            id: take(&mut map, "id")?,
+            contract_id: take(&mut map, "contract_id")?,
+            contract_hash: take_optional(&mut map, "contract_hash")?,
+            challenge_id: take_optional(&mut map, "challenge_id")?,
+            challenge_hash: take_optional(&mut map, "challenge_hash")?,
+            arbiter_agent: take(&mut map, "arbiter_agent")?,
+            outcome: outcome.parse().map_err(|e| de::Error::custom(format!("outcome: {}", e)))?,
+            reasoning: take(&mut map, "reasoning")?,
+            precedents: take_optional(&mut map, "precedents")?.unwrap_or_default(),
+            timestamp: take(&mut map, "timestamp")?,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Challenge {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Challenge, D::Error> {
+        let mut map = object(deserializer, "a challenge")?;
+        let grounds: String = take(&mut map, "grounds")?;
+        Ok(Challenge {
+            id: take(&mut map, "id")?,
+            contract_id: take(&mut map, "contract_id")?,
+            contract_hash: take(&mut map, "contract_hash")?,
+            challenger_agent: take(&mut map, "challenger_agent")?,
+            grounds: grounds.parse().map_err(|e| de::Error::custom(format!("grounds: {}", e)))?,
+            evidence: take(&mut map, "evidence")?,
+            reasoning: take(&mut map, "reasoning")?,
+            timestamp: take(&mut map, "timestamp")?,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for DisputeRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<DisputeRecord, D::Error> {
+        let mut map = object(deserializer, "a dispute record")?;
+        let status: String = take(&mut map, "status")?;
+        let status: DisputeStatus = status.parse().map_err(|e| de::Error::custom(format!("status: {}", e)))?;
+        let ruling_id = take_optional(&mut map, "ruling_id")?;
+        check_ruling(status, ruling_id.as_ref()).map_err(de::Error::custom)?;
+        Ok(DisputeRecord {
+            challenge_id: take(&mut map, "challenge_id")?,
+            challenge_hash: take(&mut map, "challenge_hash")?,
+            contract_id: take(&mut map, "contract_id")?,
+            status,
+            opened_at: take(&mut map, "opened_at")?,
+            closes_at: take(&mut map, "closes_at")?,
+            ruling_id,
+            schema_version: take_optional(&mut map, "schema_version")?,
+            extra: map,
+        })
+    }
+}
+
+/// The map of an object being deserialized, or an error naming what was expected. This
+/// and `take`/`take_optional` are shared by every hand-written `Deserialize` in the crate.
+pub(crate) fn object<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    what: &str,
+) -> std::result::Result<Map<String, Value>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(de::Error::custom(format!("expected {}", what))),
+    }
+}
+
+/// Remove a required field from the map and deserialize it.
+pub(crate) fn take<T: de::DeserializeOwned, E: de::Error>(
+    map: &mut Map<String, Value>,
+    name: &'static str,
+) -> std::result::Result<T, E> {
+    let value = map.remove(name).ok_or_else(|| E::missing_field(name))?;
+    serde_json::from_value(value).map_err(|e| E::custom(format!("{}: {}", name, e)))
+}
+
+/// Remove an optional field from the map and deserialize it if present.
+pub(crate) fn take_optional<T: de::DeserializeOwned, E: de::Error>(
+    map: &mut Map<String, Value>,
+    name: &'static str,
+) -> std::result::Result<Option<T>, E> {
+    map.remove(name)
+        .map(|value| serde_json::from_value(value).map_err(|e| E::custom(format!("{}: {}", name, e))))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::contract;
+    use crate::semantic_hash;
+    use serde_json::json;
+
+    fn amendment() -> Value {
+        json!({
+            "id": "7d4c2a8e-3b1f-4e6a-9c5d-0f8e7a6b5c4d",
+            "proposer_agent": "Claude",
+            "article": {"article_id": "III", "title": "Obligations", "clauses": ["a", "c"]},
+            "reasoning": {"rationale": "Clarifies Article III.1", "confidence": 0.87, "uncertainties": ["scope"]},
+            "timestamp": "2025-11-20T14:30:00Z"
+        })
+    }
+
+    fn ruling_with(reasoning: Reasoning) -> Ruling {
+        Ruling::builder()
+            .id("e1f0")
+            .contract_id("c-17")
+            .arbiter_agent("DeepSeek")
+            .outcome(RulingOutcome::Invalidated)
+            .reasoning(reasoning)
+            .timestamp("2025-11-28T00:00:00Z")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_objects_match_untyped_values() {
+        let reasoning = Reasoning::new("Clarifies Article III.1", 0.87).unwrap();
+        let built = Amendment::builder()
+            .id("7d4c2a8e-3b1f-4e6a-9c5d-0f8e7a6b5c4d")
+            .proposer_agent("Claude")
+            .article(json!({"article_id": "III", "title": "Obligations", "clauses": ["a", "c"]}))
+            .reasoning(reasoning.field("uncertainties", json!(["scope"])))
+            .timestamp("2025-11-20T14:30:00Z")
+            .build()
+            .unwrap();
+        assert_eq!(serde_json::from_value::<Amendment>(amendment()).unwrap(), built);
+        assert_eq!(built.semantic_hash().unwrap(), semantic_hash(&amendment()).unwrap());
+        assert_eq!(built.article_id().as_deref(), Some("III"));
+
+        let vote = json!({"contract_id": "c-17", "voter_agent": "Gemini", "vote": "abstain", "timestamp": "2025-11"});
+        let typed: Vote = serde_json::from_value(vote.clone()).unwrap();
+        assert_eq!(typed.choice, VoteChoice::Abstain);
+        assert_eq!(typed.reasoning, None);
+        assert_eq!(serde_json::to_value(&typed).unwrap(), vote);
+
+        let evidence = Evidence::new("archive_reference", "sha256:abc123def456").description("The original vote");
+        let read: Evidence = serde_json::from_value(evidence.to_value()).unwrap();
+        assert_eq!(read, evidence);
+        assert_eq!(evidence.to_value()["type"], "archive_reference");
+
+        let ruling = ruling_with(Reasoning::new("The cited article does not exist", 0.95).unwrap());
+        assert_eq!(serde_json::from_value::<Ruling>(ruling.to_value()).unwrap(), ruling);
+        assert_eq!(ruling.canonical_json().unwrap(), crate::canonicalize(&ruling.to_value(), true).unwrap());
+    }
+
+    #[test]
+    fn test_challenges_and_disputes() {
+        let contract = contract();
+        let challenge = Challenge::builder()
+            .id("9b2e4f6a-1c3d-4e5f-8a7b-6c5d4e3f2a1b")
+            .contract(&contract.id, &contract.semantic_hash().unwrap())
+            .challenger_agent("Grok")
+            .grounds(ChallengeGrounds::ConstitutionalViolation)
+            .evidence(Evidence::new("citation", "Article-III.1"))
+            .reasoning(Reasoning::new("Exceeds the mandate of Article III.1", 0.8).unwrap())
+            .timestamp("2025-11-22T10:00:00Z")
+            .build()
+            .unwrap();
+        assert_eq!(challenge.validate(), []);
+        assert!(challenge.challenges(&contract).unwrap());
+        let mut changed = contract.clone();
+        changed.proposer_agent = "Gemini".to_string();
+        assert!(!challenge.challenges(&changed).unwrap());
+        let value = challenge.to_value();
+        assert_eq!(value["grounds"], "constitutional_violation");
+        assert_eq!(serde_json::from_value::<Challenge>(value.clone()).unwrap(), challenge);
+        assert_eq!(challenge.semantic_hash().unwrap(), semantic_hash(&value).unwrap());
+
+        let mut dispute = DisputeRecord::open(&challenge, "2025-11-29T10:00:00Z").unwrap();
+        assert_eq!(dispute.validate(), []);
+        assert!(dispute.is_open_at("2025-11-25T00:00:00Z").unwrap());
+        assert!(!dispute.is_open_at("2025-11-29T10:00:00Z").unwrap());
+        dispute.resolve("e1f04a2b-7c6d-4e5f-9a8b-1c2d3e4f5a6b").unwrap();
+        assert!(dispute.withdraw().is_err());
+        assert!(!dispute.is_open_at("2025-11-25T00:00:00Z").unwrap());
+        let value = dispute.to_value();
+        assert_eq!(serde_json::from_value::<DisputeRecord>(value.clone()).unwrap(), dispute);
+        let mut unruled = value.as_object().cloned().unwrap();
+        unruled.remove("ruling_id");
+        assert!(serde_json::from_value::<DisputeRecord>(Value::Object(unruled)).is_err());
+
+        dispute.challenge_hash = "a3f5".to_string();
+        dispute.closes_at = "2025-11-22T09:00:00Z".to_string();
+        let kinds: Vec<ViolationKind> = dispute.validate().into_iter().map(|v| v.kind).collect();
+        assert_eq!(kinds, [ViolationKind::InvalidHash("a3f5".to_string()), ViolationKind::EmptyDisputeWindow]);
+    }
+
+    #[test]
+    fn test_malformed_objects_fail() {
+        let unnumbered = amendment().as_object().cloned().map(|mut map| {
+            map["article"] = json!({"title": "Obligations"});
+            Value::Object(map)
+        });
+        assert!(serde_json::from_value::<Amendment>(unnumbered.unwrap()).is_err());
+        let vote = json!({"contract_id": "c-17", "voter_agent": "Gemini", "vote": "maybe", "timestamp": "2025-11-21"});
+        assert!(serde_json::from_value::<Vote>(vote).unwrap_err().to_string().contains("maybe"));
+        let unsure = json!({"rationale": "Clarifies Article III.1", "confidence": "high"});
+        assert!(serde_json::from_value::<Reasoning>(unsure).is_err());
+        assert!(Reasoning::new("Clarifies Article III.1", f64::NAN).is_err());
+        // An integral confidence stays an integer, as the untyped value has it
+        let certain = json!({"rationale": "Clarifies Article III.1", "confidence": 1});
+        assert_eq!(serde_json::from_value::<Reasoning>(certain.clone()).unwrap().to_value(), certain);
+
+        let unreasoned = Ruling::builder().id("e1f0").contract_id("c-17").arbiter_agent("DeepSeek");
+        let error = unreasoned.outcome(RulingOutcome::Upheld).build().unwrap_err();
+        assert!(error.to_string().contains("A ruling needs reasoning"), "{}", error);
+        assert!(Vote::builder().field("vote", json!("approve")).build().is_err());
+
+        let negative = Reasoning::new("Clarifies Article III.1", -0.1).unwrap();
+        assert_eq!(negative.validate()[0].pointer.to_string(), "/confidence");
+        let violations = ruling_with(negative).validate();
+        let pointers: Vec<String> = violations.iter().map(|v| v.pointer.to_string()).collect();
+        assert_eq!(pointers, ["/id", "/contract_id", "/reasoning/confidence"]);
+
+        let mut constitution = Constitution::from_value(json!({"articles": [{"article_id": "III"}]})).unwrap();
+        let amendment: Amendment = serde_json::from_value(amendment()).unwrap();
+        assert_eq!(amendment.apply(&mut constitution).unwrap(), Some(json!({"article_id": "III"})));
+        assert_eq!(constitution.article("III"), amendment.article.as_ref());
+    }
+}
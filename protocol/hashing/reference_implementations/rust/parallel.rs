@@ -0,0 +1,145 @@
+/// parallel.rs - Parallel canonicalization of top-level members (`rayon` feature)
+///
+/// A constitution snapshot is one object with thousands of top-level articles, and each
+/// member's canonical form depends only on its own subtree and its path. With the `rayon`
+/// feature, members are deep-sorted (or streamed) and serialized on the rayon thread pool,
+/// then stitched together in collation order, so the output is byte-for-byte that of
+/// the sequential path.
+///
+/// Pruning, key normalization and collision checks are applied while stitching, in input
+/// order, so errors are the ones the sequential path would report first. `max_nodes`
+/// counts nodes across the whole document in visiting order, which workers cannot share
+/// cheaply, so documents canonicalized under a node limit stay sequential.
+
+use crate::incremental::{canonical_member, write_members};
+use crate::{CanonicalizeOptions, ConstitutionalError, JsonPointer, Result};
+use rayon::prelude::*;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Fewest top-level members for which spreading work over threads pays for itself.
+const MIN_PARALLEL_MEMBERS: usize = 64;
+
+/// The top-level object of `data`, if it is worth canonicalizing in parallel.
+pub(crate) fn eligible<'a>(data: &'a Value, options: &CanonicalizeOptions) -> Option<&'a Map<String, Value>> {
+    match data {
+        Value::Object(map) if map.len() >= MIN_PARALLEL_MEMBERS && options.max_nodes.is_none() => Some(map),
+        _ => None,
+    }
+}
+
+/// Write the canonical form of the object `map` to `sink`, canonicalizing its members in
+/// parallel. The bytes are exactly those of `write_streaming` or `deep_sort`.
+pub(crate) fn write_parallel<W: Write + ?Sized>(
+    map: &Map<String, Value>,
+    options: &CanonicalizeOptions,
+    sink: &mut W,
+) -> Result<()> {
+    if options.exclude_paths.iter().any(JsonPointer::is_root) {
+        return Err(ConstitutionalError::canonicalization("Cannot exclude the document root"));
+    }
+
+    let members: Vec<(&String, &Value)> = map
+        .iter()
+        .filter(|(token, _)| {
+            !options.exclude_paths.iter().any(|p| p.is_child(&[], token)) && !options.schema_hints.excludes(&[], token)
+        })
+        .collect();
+    let canonical: Vec<Result<Option<String>>> = members
+        .par_iter()
+        .map(|(token, value)| canonical_member(token, value, options))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut parts = Vec::with_capacity(members.len());
+    for ((token, _), member) in members.iter().zip(canonical) {
+        let Some(text) = member? else {
+            continue;
+        };
+        let key = match options.normalization {
+            Some(form) => form.apply(token),
+            None => token.to_string(),
+        };
+        if !seen.insert(key.clone()) {
+            return Err(ConstitutionalError::canonicalization_at(
+                format!("Key {:?} collides with another key after Unicode normalization", key),
+                &[],
+            ));
+        }
+        parts.push((key, text));
+    }
+    write_members(&mut parts, options, sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::Scratch;
+    use crate::{deep_sort, stream, write_canonical, ArraySortPolicy, NormalizationForm, PrunePolicy};
+    use serde_json::json;
+
+    fn sequential(data: &Value, options: &CanonicalizeOptions) -> Result<String> {
+        let mut out = Vec::new();
+        if stream::streamable(options) {
+            stream::write_streaming(data, options, &mut Scratch::default(), &mut out)?;
+        } else {
+            write_canonical(&deep_sort(data.clone(), options)?, options, &mut out)?;
+        }
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn parallel(data: &Value, options: &CanonicalizeOptions) -> Result<String> {
+        let mut out = Vec::new();
+        write_parallel(eligible(data, options).expect("eligible"), options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn snapshot(members: usize) -> Value {
+        let mut map = Map::new();
+        for i in 0..members {
+            map.insert(
+                format!("article-{:03}", members - i),
+                json!({"title": "Transparency", "clauses": ["b", "a"], "amended": null, "refs": [{"n": i}, {}]}),
+            );
+        }
+        map.insert("note".to_string(), Value::Null);
+        map.insert("signature".to_string(), json!("ed25519:abc"));
+        Value::Object(map)
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let data = snapshot(200);
+        for options in [
+            CanonicalizeOptions::new(),
+            CanonicalizeOptions::new().exclude_field("signature").prune(PrunePolicy::Nulls),
+            CanonicalizeOptions::new().prune(PrunePolicy::NullsAndEmpty).array_sort(ArraySortPolicy::Always),
+            CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc),
+        ] {
+            assert_eq!(parallel(&data, &options).unwrap(), sequential(&data, &options).unwrap());
+        }
+
+        // A small object, or any object under a node limit, stays sequential
+        assert!(eligible(&snapshot(10), &CanonicalizeOptions::new()).is_none());
+        assert!(eligible(&data, &CanonicalizeOptions::new().max_nodes(1_000_000)).is_none());
+    }
+
+    #[test]
+    fn test_parallel_errors_match_sequential() {
+        let mut data = snapshot(100);
+        data["article-050"] = json!({"deep": {"deeper": {"deepest": {"x": 1}}}});
+        data["caf\u{e9}"] = json!(1);
+        data["cafe\u{301}"] = json!(2);
+
+        let options = CanonicalizeOptions::new().max_depth(4);
+        let err = parallel(&data, &options).unwrap_err();
+        assert_eq!(err.pointer().map(|p| p.to_string()).as_deref(), Some("/article-050/deep/deeper/deepest/x"));
+
+        let options = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+        assert_eq!(
+            parallel(&data, &options).unwrap_err().to_string(),
+            sequential(&data, &options).unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,143 @@
+/// parse.rs - Strict JSON text parsing for OCP canonicalization
+///
+/// serde_json silently keeps the last value when an object repeats a key, while other
+/// parsers keep the first or reject the document. Two verifiers could therefore read
+/// different contracts from the same bytes and still agree on a semantic hash, so raw
+/// input is checked for duplicate keys before it is parsed into a `Value`.
+
+use crate::{ConstitutionalError, Result};
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Parse JSON text into a `Value`, rejecting any object that repeats a key.
+///
+/// Errors carry the JSON Pointer of the value being read when parsing stopped: the
+/// object holding a repeated key, or the value containing a syntax error.
+pub(crate) fn parse_unique_keys(text: &str) -> Result<Value> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let mut path = Vec::new();
+    UniqueKeys { path: &mut path }
+        .deserialize(&mut deserializer)
+        .and_then(|()| deserializer.end())
+        .map_err(|e| ConstitutionalError::canonicalization_at(format!("Invalid JSON input: {}", e), &path))?;
+
+    serde_json::from_str(text).map_err(|e| ConstitutionalError::canonicalization(format!("Invalid JSON input: {}", e)))
+}
+
+/// Validating visitor that walks a document without building it, tracking the path of
+/// the current value for error reporting. On error the path is left where parsing stopped.
+struct UniqueKeys<'p> {
+    path: &'p mut Vec<String>,
+}
+
+impl<'de, 'p> DeserializeSeed<'de> for UniqueKeys<'p> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'p> Visitor<'de> for UniqueKeys<'p> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        let path = self.path;
+        for index in 0.. {
+            path.push(index.to_string());
+            let element = seq.next_element_seed(UniqueKeys { path: &mut *path })?;
+            path.pop();
+            if element.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Under serde_json's `arbitrary_precision` feature numbers also arrive here, as a
+    // single-entry map, which never trips the duplicate check.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let path = self.path;
+        let mut seen = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if !seen.insert(key.clone()) {
+                return Err(de::Error::custom(format!("duplicate key {:?} in object", key)));
+            }
+            path.push(key);
+            map.next_value_seed(UniqueKeys { path: &mut *path })?;
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_unique_keys() {
+        let value = parse_unique_keys(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap();
+        assert_eq!(value, json!({"a": [1, {"b": null}], "c": "d"}));
+    }
+
+    #[test]
+    fn test_duplicate_keys_rejected_with_path() {
+        let cases = [
+            (r#"{"claim": "a", "claim": "b"}"#, ""),
+            (r#"{"evidence": [{"ptr": 1}, {"ptr": 2, "ptr": 3}]}"#, "/evidence/1"),
+            // Escapes are decoded before comparing, so "\u0061" repeats "a"
+            (r#"{"x": {"a": 1, "\u0061": 2}}"#, "/x"),
+        ];
+
+        for (text, pointer) in cases {
+            match parse_unique_keys(text) {
+                Err(err @ ConstitutionalError::CanonicalizationError { .. }) => {
+                    assert!(err.to_string().contains("duplicate key"), "{}", err);
+                    assert_eq!(err.pointer().map(ToString::to_string).as_deref(), Some(pointer));
+                }
+                other => panic!("expected duplicate key error for {}, got {:?}", text, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_json_rejected() {
+        assert!(parse_unique_keys(r#"{"a": 1"#).is_err());
+        assert!(parse_unique_keys(r#"{"a": 1} trailing"#).is_err());
+
+        let err = parse_unique_keys(r#"{"votes": [true, tru]}"#).unwrap_err();
+        assert_eq!(err.pointer().map(ToString::to_string).as_deref(), Some("/votes/1"));
+    }
+}
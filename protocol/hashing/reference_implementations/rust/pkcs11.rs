@@ -0,0 +1,219 @@
+/// pkcs11.rs - Signing with keys held in a hardware token
+///
+/// Constitutional root keys belong in an HSM or on a YubiKey, where the private key never
+/// enters process memory. A `Pkcs11Signer` holds a logged-in session with a token and
+/// implements `Signer` by asking the token to sign, so `sign`, `sign_with` and `sign_as`
+/// take it wherever they take a `Keypair`.
+///
+/// The key is found by label: a private key object and a public key object sharing one
+/// `CKA_LABEL`. Ed25519 keys (`CKK_EC_EDWARDS`) sign with `CKM_EDDSA`. secp256k1 keys
+/// (`CKK_EC` on the secp256k1 curve) sign the SHA-256 of the message with `CKM_ECDSA`
+/// and need the `secp256k1` feature as well. Requires the `pkcs11` feature.
+
+use crate::algorithm::hex;
+use crate::{ConstitutionalError, PublicKey, Result, SignatureScheme, Signer};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// DER of the secp256k1 curve's OID, 1.3.132.0.10, as `CKA_EC_PARAMS` holds it.
+const SECP256K1_PARAMS: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+/// A `Signer` whose key stays on a PKCS#11 token.
+pub struct Pkcs11Signer {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+    public_key: PublicKey,
+    scheme: SignatureScheme,
+}
+
+impl Pkcs11Signer {
+    /// Load a token's PKCS#11 module, log in and find the key to sign with.
+    ///
+    /// # Arguments
+    /// * `module` - Path of the PKCS#11 module, e.g. `/usr/lib/libykcs11.so`
+    /// * `token` - Label of the token
+    /// * `key` - Label of the key on the token
+    /// * `pin` - The token's user PIN
+    ///
+    /// # Returns
+    /// The signer, or a HashingError if the module cannot be loaded, the token or key is
+    /// not found, the PIN is wrong or the key cannot sign protocol signatures
+    pub fn open(module: impl AsRef<Path>, token: &str, key: &str, pin: &str) -> Result<Pkcs11Signer> {
+        let pkcs11 = Pkcs11::new(module.as_ref()).map_err(token_error)?;
+        pkcs11.initialize(CInitializeArgs::OsThreads).map_err(token_error)?;
+        let mut slot = None;
+        for candidate in pkcs11.get_slots_with_token().map_err(token_error)? {
+            if pkcs11.get_token_info(candidate).map_err(token_error)?.label() == token {
+                slot = Some(candidate);
+                break;
+            }
+        }
+        let slot = slot.ok_or_else(|| signer_error(format!("No token labelled {:?}", token)))?;
+        let session = pkcs11.open_ro_session(slot).map_err(token_error)?;
+        session.login(UserType::User, Some(&AuthPin::new(pin.to_string()))).map_err(token_error)?;
+        Pkcs11Signer::from_session(session, key)
+    }
+
+    /// Sign with a key of a session the caller has opened and logged in, e.g. to use
+    /// several keys of one token, which a module can only be initialized for once.
+    ///
+    /// # Returns
+    /// The signer, or a HashingError if the key is not found or cannot sign protocol
+    /// signatures
+    pub fn from_session(session: Session, key: &str) -> Result<Pkcs11Signer> {
+        let find = |class| {
+            let template = [Attribute::Class(class), Attribute::Label(key.as_bytes().to_vec())];
+            match session.find_objects(&template).map_err(token_error)?.as_slice() {
+                [object] => Ok(*object),
+                [] => Err(signer_error(format!("No key labelled {:?} on the token", key))),
+                _ => Err(signer_error(format!("Several keys are labelled {:?}", key))),
+            }
+        };
+        let private = find(ObjectClass::PRIVATE_KEY)?;
+        let public = find(ObjectClass::PUBLIC_KEY)?;
+        let attributes = session
+            .get_attributes(public, &[AttributeType::KeyType, AttributeType::EcParams, AttributeType::EcPoint])
+            .map_err(token_error)?;
+        let (scheme, public_key) = token_public_key(&attributes)?;
+        Ok(Pkcs11Signer { session: Mutex::new(session), key: private, public_key, scheme })
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn public_key(&self) -> Result<PublicKey> {
+        Ok(self.public_key)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<String> {
+        // A panic elsewhere leaves nothing half-done in the session itself
+        let session = self.session.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bytes = match self.scheme {
+            SignatureScheme::Ed25519 => session.sign(&Mechanism::Eddsa, self.key, message).map_err(token_error)?,
+            #[cfg(feature = "secp256k1")]
+            _ => {
+                use sha2::{Digest, Sha256};
+                let raw = session.sign(&Mechanism::Ecdsa, self.key, &Sha256::digest(message)).map_err(token_error)?;
+                let signature = k256::ecdsa::Signature::from_slice(&raw)
+                    .map_err(|_| signer_error("The token returned a malformed ECDSA signature"))?;
+                // Tokens need not return the low-S form, the only one verifiers accept
+                signature.normalize_s().unwrap_or(signature).to_bytes().to_vec()
+            }
+        };
+        Ok(format!("{}:{}", self.scheme, hex(&bytes)))
+    }
+}
+
+impl fmt::Debug for Pkcs11Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pkcs11Signer").field("public_key", &self.public_key).finish_non_exhaustive()
+    }
+}
+
+/// The scheme and public key of a token's public key object, from its key type, curve
+/// and point.
+fn token_public_key(attributes: &[Attribute]) -> Result<(SignatureScheme, PublicKey)> {
+    let (mut key_type, mut params, mut point) = (None, None, None);
+    for attribute in attributes {
+        match attribute {
+            Attribute::KeyType(kind) => key_type = Some(*kind),
+            Attribute::EcParams(bytes) => params = Some(bytes.as_slice()),
+            Attribute::EcPoint(bytes) => point = Some(bytes.as_slice()),
+            _ => {}
+        }
+    }
+    let point = point.ok_or_else(|| signer_error("The token's public key has no EC point"))?;
+    if key_type == Some(KeyType::EC_EDWARDS) {
+        let key = octets(point, 32)
+            .and_then(|bytes| PublicKey::from_parts("ed25519", bytes))
+            .ok_or_else(|| signer_error("The token's Ed25519 public key is malformed"))?;
+        return Ok((SignatureScheme::Ed25519, key));
+    }
+    if key_type != Some(KeyType::EC) || params != Some(SECP256K1_PARAMS) {
+        return Err(signer_error("Only Ed25519 and secp256k1 keys on a token can sign"));
+    }
+    secp256k1_key(point)
+}
+
+#[cfg(feature = "secp256k1")]
+fn secp256k1_key(point: &[u8]) -> Result<(SignatureScheme, PublicKey)> {
+    // Tokens hold the uncompressed point; keys are written compressed
+    let compressed = match (octets(point, 65), octets(point, 33)) {
+        (Some([0x04, xy @ ..]), _) => {
+            let mut compressed = vec![0x02 | (xy[63] & 1)];
+            compressed.extend_from_slice(&xy[..32]);
+            Some(compressed)
+        }
+        (_, Some(compressed)) => Some(compressed.to_vec()),
+        _ => None,
+    };
+    let key = compressed
+        .and_then(|bytes| PublicKey::from_parts("secp256k1", &bytes))
+        .ok_or_else(|| signer_error("The token's secp256k1 public key is malformed"))?;
+    Ok((SignatureScheme::Secp256k1, key))
+}
+
+#[cfg(not(feature = "secp256k1"))]
+fn secp256k1_key(_point: &[u8]) -> Result<(SignatureScheme, PublicKey)> {
+    Err(signer_error("Signing with a secp256k1 key on a token needs the secp256k1 feature"))
+}
+
+/// The `len` bytes of an EC point, raw or wrapped in a DER OCTET STRING as PKCS#11
+/// 3.0 has tokens return them.
+fn octets(point: &[u8], len: usize) -> Option<&[u8]> {
+    match point {
+        _ if point.len() == len => Some(point),
+        [0x04, wrapped, rest @ ..] if *wrapped as usize == len && rest.len() == len => Some(rest),
+        _ => None,
+    }
+}
+
+fn token_error(error: cryptoki::error::Error) -> ConstitutionalError {
+    signer_error(format!("PKCS#11: {}", error))
+}
+
+fn signer_error(message: impl Into<String>) -> ConstitutionalError {
+    ConstitutionalError::HashingError(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keypair;
+
+    #[test]
+    fn test_token_public_keys() {
+        let key = Keypair::from_secret(&[7; 32]).public_key();
+        let mut wrapped = vec![0x04, 0x20];
+        wrapped.extend_from_slice(&key.to_bytes());
+        for point in [key.to_bytes(), wrapped] {
+            let attributes = [Attribute::KeyType(KeyType::EC_EDWARDS), Attribute::EcPoint(point)];
+            assert_eq!(token_public_key(&attributes).unwrap(), (SignatureScheme::Ed25519, key));
+        }
+
+        let truncated = [Attribute::KeyType(KeyType::EC_EDWARDS), Attribute::EcPoint(vec![0x04, 0x20, 1])];
+        assert!(token_public_key(&truncated).is_err());
+        let rsa = [Attribute::KeyType(KeyType::RSA), Attribute::EcPoint(key.to_bytes())];
+        assert!(token_public_key(&rsa).is_err());
+        assert!(Pkcs11Signer::open("/nonexistent/libpkcs11.so", "root", "constitution", "1234").is_err());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_secp256k1_token_keys() {
+        let key = Keypair::from_secp256k1_secret(&[7; 32]).unwrap().public_key();
+        let attributes = [
+            Attribute::KeyType(KeyType::EC),
+            Attribute::EcParams(SECP256K1_PARAMS.to_vec()),
+            Attribute::EcPoint([&[0x04, 0x21][..], &key.to_bytes()].concat()),
+        ];
+        assert_eq!(token_public_key(&attributes).unwrap(), (SignatureScheme::Secp256k1, key));
+        let other_curve = [Attribute::KeyType(KeyType::EC), Attribute::EcPoint(key.to_bytes())];
+        assert!(token_public_key(&other_curve).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+/// pointer.rs - RFC 6901 JSON Pointers for OCP canonicalization
+///
+/// Pointers name the members removed before hashing (e.g. `/signature`, or
+/// `/evidence/0/hash` inside an array element) and the location reported in path-qualified
+/// errors. Array indices refer to positions in the input, before any array sorting.
+
+use crate::{ConstitutionalError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed RFC 6901 JSON Pointer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /// Parse pointer text such as `/evidence/0/signature`.
+    ///
+    /// # Arguments
+    /// * `text` - Empty (the whole document) or a sequence of `/`-prefixed reference tokens
+    ///
+    /// # Returns
+    /// The pointer, or a CanonicalizationError if text is non-empty without a leading `/`
+    /// or contains a `~` not followed by `0` or `1`
+    pub fn parse(text: &str) -> Result<JsonPointer> {
+        if text.is_empty() {
+            return Ok(JsonPointer { tokens: Vec::new() });
+        }
+        let Some(rest) = text.strip_prefix('/') else {
+            return Err(malformed(text, "must be empty or start with '/'"));
+        };
+
+        let tokens = rest
+            .split('/')
+            .map(|token| unescape(token).ok_or_else(|| malformed(text, "'~' must be followed by '0' or '1'")))
+            .collect::<Result<Vec<String>>>()?;
+        Ok(JsonPointer { tokens })
+    }
+
+    /// Build a pointer from unescaped reference tokens.
+    pub fn from_tokens<I, S>(tokens: I) -> JsonPointer
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        JsonPointer { tokens: tokens.into_iter().map(Into::into).collect() }
+    }
+
+    /// The unescaped reference tokens, outermost first.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// True for the empty pointer, which refers to the whole document.
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// True if this pointer names the child `token` of the value at `parent`.
+    pub(crate) fn is_child(&self, parent: &[String], token: &str) -> bool {
+        self.tokens.len() == parent.len() + 1
+            && self.tokens[..parent.len()] == *parent
+            && self.tokens[parent.len()] == token
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = ConstitutionalError;
+
+    fn from_str(text: &str) -> Result<JsonPointer> {
+        JsonPointer::parse(text)
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&json_pointer(&self.tokens))
+    }
+}
+
+/// Render reference tokens as a JSON Pointer (RFC 6901), e.g. `/evidence/0/claim`.
+pub(crate) fn json_pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Decode `~1` and `~0` (in that order, per RFC 6901 4); None on any other `~` sequence.
+fn unescape(token: &str) -> Option<String> {
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => out.push('~'),
+                Some('1') => out.push('/'),
+                _ => return None,
+            },
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+fn malformed(text: &str, reason: &str) -> ConstitutionalError {
+    ConstitutionalError::canonicalization(format!("Malformed JSON Pointer {:?}: {}", text, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_pointer_rendering() {
+        assert_eq!(json_pointer(&[]), "");
+        assert_eq!(
+            json_pointer(&["a/b".to_string(), "m~n".to_string(), "0".to_string()]),
+            "/a~1b/m~0n/0"
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        // Examples from RFC 6901 5
+        for (text, tokens) in [
+            ("", vec![]),
+            ("/foo/0", vec!["foo", "0"]),
+            ("/", vec![""]),
+            ("/a~1b", vec!["a/b"]),
+            ("/m~0n", vec!["m~n"]),
+            ("/~01", vec!["~1"]),
+        ] {
+            let pointer: JsonPointer = text.parse().unwrap();
+            assert_eq!(pointer, JsonPointer::from_tokens(tokens));
+            assert_eq!(pointer.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_malformed_pointers_rejected() {
+        for text in ["signature", "/a~", "/a~2b", "#/signature"] {
+            match JsonPointer::parse(text) {
+                Err(ConstitutionalError::CanonicalizationError { message, .. }) => {
+                    assert!(message.contains("Malformed"), "{}", message)
+                }
+                other => panic!("expected malformed pointer error for {:?}, got {:?}", text, other),
+            }
+        }
+    }
+}
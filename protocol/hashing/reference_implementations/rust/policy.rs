@@ -0,0 +1,289 @@
+/// policy.rs - Which agents may sign which actions
+///
+/// A valid signature says who signed, not whether they were entitled to. A
+/// `SignaturePolicy` lists the roles allowed to sign each action type; evaluating a
+/// signed contract against it and an `AgentRegistry` checks, in order, that:
+///
+/// 1. the payload names its `action_type`,
+/// 2. the signature verifies over the payload's hash,
+/// 3. the contract was valid at the given time, inside its `valid_from`/`valid_until`,
+/// 4. the signing key belongs to a registered agent,
+/// 5. the key was valid for that agent at the given time,
+/// 6. the policy lets some role sign the action, and
+/// 7. the agent holds one of those roles.
+///
+/// The outcome is a `Decision`: allowed, with the agent and the role that allowed it, or
+/// denied with the first check that failed. `Decision::to_value` gives it as JSON for
+/// audit logs:
+///
+/// ```json
+/// {"decision":"deny","reason":"missing_role","agent":"gemini","action_type":"amend",
+///  "allowed":["amender"],"message":"gemini holds none of the roles that may sign amend: amender"}
+/// ```
+
+use crate::contract::validity;
+use crate::{
+    verify_signed_with, AgentRegistry, CanonicalizeOptions, ConstitutionalError, PublicKey, Result, SignedObject,
+    Validity,
+};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The roles allowed to sign each action type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignaturePolicy {
+    allowed: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl SignaturePolicy {
+    /// A policy allowing nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let agents holding `role` sign contracts whose `action_type` is `action_type`.
+    pub fn allow(mut self, action_type: &str, role: &str) -> Self {
+        self.allowed.entry(action_type.to_string()).or_default().insert(role.to_string());
+        self
+    }
+
+    /// The roles allowed to sign an action type, sorted.
+    pub fn roles_for(&self, action_type: &str) -> impl Iterator<Item = &str> {
+        self.allowed.get(action_type).into_iter().flatten().map(String::as_str)
+    }
+
+    /// Decide whether a signed contract's signer was entitled to sign it.
+    ///
+    /// # Arguments
+    /// * `signed` - The signed contract, its signer a public key
+    /// * `registry` - The agents, their keys and their roles
+    /// * `at` - When the contract was signed, RFC 3339
+    /// * `options` - Canonicalization options the payload was hashed under
+    ///
+    /// # Returns
+    /// The decision, or an error if the signer, signature, timestamp or validity window
+    /// is malformed
+    pub fn evaluate(
+        &self,
+        signed: &SignedObject,
+        registry: &AgentRegistry,
+        at: &str,
+        options: &CanonicalizeOptions,
+    ) -> Result<Decision> {
+        let Some(action_type) = signed.payload.get("action_type").and_then(Value::as_str) else {
+            return Ok(Decision::Deny(Denial::NoActionType));
+        };
+        if !verify_signed_with(signed, options)? {
+            return Ok(Decision::Deny(Denial::InvalidSignature));
+        }
+        let window = |name: &str| match signed.payload.get(name) {
+            None => Ok(None),
+            Some(Value::String(text)) => Ok(Some(text.as_str())),
+            Some(_) => Err(ConstitutionalError::ProtocolError(format!("The payload's {} is not a string", name))),
+        };
+        match validity(window("valid_from")?, window("valid_until")?, at)? {
+            Validity::Valid => {}
+            Validity::NotYetValid => return Ok(Decision::Deny(Denial::NotYetValid { at: at.to_string() })),
+            Validity::Expired => return Ok(Decision::Deny(Denial::Expired { at: at.to_string() })),
+        }
+        let key: PublicKey = signed.signer.parse()?;
+        let Some(agent) = registry.agent_of(&key) else {
+            return Ok(Decision::Deny(Denial::UnknownSigner { signer: signed.signer.clone() }));
+        };
+        if !registry.valid_at(agent, &key, at)? {
+            return Ok(Decision::Deny(Denial::KeyNotValid { agent: agent.to_string(), at: at.to_string() }));
+        }
+        let Some(allowed) = self.allowed.get(action_type) else {
+            return Ok(Decision::Deny(Denial::NoRule { action_type: action_type.to_string() }));
+        };
+        match allowed.iter().find(|role| registry.has_role(agent, role)) {
+            Some(role) => Ok(Decision::Allow { agent: agent.to_string(), role: role.clone() }),
+            None => Ok(Decision::Deny(Denial::MissingRole {
+                agent: agent.to_string(),
+                action_type: action_type.to_string(),
+                allowed: allowed.iter().cloned().collect(),
+            })),
+        }
+    }
+}
+
+/// Whether a signer was entitled to sign a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The agent may sign the action, through the role named.
+    Allow { agent: String, role: String },
+    /// The agent may not, for the reason given.
+    Deny(Denial),
+}
+
+impl Decision {
+    /// Whether the decision allows the signature.
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Decision::Allow { .. })
+    }
+
+    /// The decision as JSON, for audit logs.
+    pub fn to_value(&self) -> Value {
+        let denial = match self {
+            Decision::Allow { agent, role } => return json!({"decision": "allow", "agent": agent, "role": role}),
+            Decision::Deny(denial) => denial,
+        };
+        let mut value = match denial {
+            Denial::NoActionType | Denial::InvalidSignature => json!({}),
+            Denial::NotYetValid { at } | Denial::Expired { at } => json!({"at": at}),
+            Denial::UnknownSigner { signer } => json!({"signer": signer}),
+            Denial::KeyNotValid { agent, at } => json!({"agent": agent, "at": at}),
+            Denial::NoRule { action_type } => json!({"action_type": action_type}),
+            Denial::MissingRole { agent, action_type, allowed } => {
+                json!({"agent": agent, "action_type": action_type, "allowed": allowed})
+            }
+        };
+        value["decision"] = json!("deny");
+        value["reason"] = json!(denial.code());
+        value["message"] = json!(denial.to_string());
+        value
+    }
+}
+
+/// Why a signature was not allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    /// The payload has no string `action_type`.
+    NoActionType,
+    /// The signature does not verify, or the envelope is not the payload's hash.
+    InvalidSignature,
+    /// The contract was signed before its `valid_from`.
+    NotYetValid { at: String },
+    /// The contract was signed at or after its `valid_until`.
+    Expired { at: String },
+    /// The signing key belongs to no registered agent.
+    UnknownSigner { signer: String },
+    /// The key was not valid for its agent at the time: outside its window, or revoked.
+    KeyNotValid { agent: String, at: String },
+    /// The policy lets no role sign the action.
+    NoRule { action_type: String },
+    /// The agent holds none of the roles allowed to sign the action.
+    MissingRole { agent: String, action_type: String, allowed: Vec<String> },
+}
+
+impl Denial {
+    /// Short machine-readable name of the reason, e.g. `missing_role`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Denial::NoActionType => "no_action_type",
+            Denial::InvalidSignature => "invalid_signature",
+            Denial::NotYetValid { .. } => "not_yet_valid",
+            Denial::Expired { .. } => "expired",
+            Denial::UnknownSigner { .. } => "unknown_signer",
+            Denial::KeyNotValid { .. } => "key_not_valid",
+            Denial::NoRule { .. } => "no_rule",
+            Denial::MissingRole { .. } => "missing_role",
+        }
+    }
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Denial::NoActionType => f.write_str("The payload has no action_type"),
+            Denial::InvalidSignature => f.write_str("The signature does not verify"),
+            Denial::NotYetValid { at } => write!(f, "The contract was not yet valid at {}", at),
+            Denial::Expired { at } => write!(f, "The contract had expired at {}", at),
+            Denial::UnknownSigner { signer } => write!(f, "{} is no registered agent's key", signer),
+            Denial::KeyNotValid { agent, at } => write!(f, "The key was not valid for {} at {}", agent, at),
+            Denial::NoRule { action_type } => write!(f, "No role may sign {}", action_type),
+            Denial::MissingRole { agent, action_type, allowed } => write!(
+                f,
+                "{} holds none of the roles that may sign {}: {}",
+                agent,
+                action_type,
+                allowed.join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+
+    fn setup() -> (SignaturePolicy, AgentRegistry) {
+        let mut registry = AgentRegistry::new();
+        registry.register("claude", Keypair::from_secret(&[1; 32]).public_key(), "2025-01-01T00:00:00Z").unwrap();
+        registry.register("gemini", Keypair::from_secret(&[2; 32]).public_key(), "2025-01-01T00:00:00Z").unwrap();
+        registry.grant_role("claude", "amender").unwrap();
+        registry.grant_role("gemini", "reviewer").unwrap();
+        let policy = SignaturePolicy::new()
+            .allow("amend", "amender")
+            .allow("review", "reviewer")
+            .allow("review", "amender");
+        (policy, registry)
+    }
+
+    fn evaluate(seed: u8, action_type: &str, at: &str) -> Decision {
+        let (policy, registry) = setup();
+        let contract = json!({"contract_id": "c-17", "action_type": action_type});
+        let signed = sign(&contract, &Keypair::from_secret(&[seed; 32])).unwrap();
+        policy.evaluate(&signed, &registry, at, &CanonicalizeOptions::new()).unwrap()
+    }
+
+    #[test]
+    fn test_roles_decide() {
+        let now = "2025-06-01T00:00:00Z";
+        assert_eq!(evaluate(1, "amend", now), Decision::Allow { agent: "claude".into(), role: "amender".into() });
+        assert!(evaluate(1, "review", now).is_allowed());
+        assert!(evaluate(2, "review", now).is_allowed());
+        let denied = evaluate(2, "amend", now);
+        assert_eq!(
+            denied,
+            Decision::Deny(Denial::MissingRole {
+                agent: "gemini".into(),
+                action_type: "amend".into(),
+                allowed: vec!["amender".into()]
+            })
+        );
+        assert_eq!(denied.to_value()["reason"], "missing_role");
+        assert_eq!(denied.to_value()["decision"], "deny");
+        let allowed = json!({"decision": "allow", "agent": "claude", "role": "amender"});
+        assert_eq!(evaluate(1, "amend", now).to_value(), allowed);
+    }
+
+    #[test]
+    fn test_denials() {
+        let now = "2025-06-01T00:00:00Z";
+        assert_eq!(evaluate(1, "repeal", now), Decision::Deny(Denial::NoRule { action_type: "repeal".into() }));
+        assert!(matches!(evaluate(9, "amend", now), Decision::Deny(Denial::UnknownSigner { .. })));
+        assert!(matches!(evaluate(1, "amend", "2024-06-01T00:00:00Z"), Decision::Deny(Denial::KeyNotValid { .. })));
+
+        let (policy, registry) = setup();
+        let options = CanonicalizeOptions::new();
+        let mut signed = sign(&json!({"action_type": "amend"}), &Keypair::from_secret(&[1; 32])).unwrap();
+        signed.payload["action_type"] = json!("review");
+        let decision = policy.evaluate(&signed, &registry, now, &options).unwrap();
+        assert_eq!(decision, Decision::Deny(Denial::InvalidSignature));
+        let untyped = sign(&json!({"contract_id": "c-17"}), &Keypair::from_secret(&[1; 32])).unwrap();
+        let decision = policy.evaluate(&untyped, &registry, now, &options).unwrap();
+        assert_eq!(decision.to_value()["reason"], "no_action_type");
+        assert_eq!(policy.roles_for("review").collect::<Vec<_>>(), ["amender", "reviewer"]);
+    }
+
+    #[test]
+    fn test_expired_contracts_are_denied() {
+        let (policy, registry) = setup();
+        let options = CanonicalizeOptions::new();
+        let contract = json!({"action_type": "amend", "valid_from": "2025-03-01T00:00:00Z",
+            "valid_until": "2025-04-01T00:00:00+02:00"});
+        let signed = sign(&contract, &Keypair::from_secret(&[1; 32])).unwrap();
+        let decide = |at| policy.evaluate(&signed, &registry, at, &options).unwrap();
+        assert!(decide("2025-03-01T00:00:00Z").is_allowed());
+        assert!(decide("2025-03-31T21:59:59Z").is_allowed());
+        let expired = Decision::Deny(Denial::Expired { at: "2025-03-31T22:00:00Z".into() });
+        assert_eq!(decide("2025-03-31T22:00:00Z"), expired);
+        assert_eq!(decide("2025-02-28T23:59:59Z").to_value()["reason"], "not_yet_valid");
+
+        let malformed = sign(&json!({"action_type": "amend", "valid_until": 2025}), &Keypair::from_secret(&[1; 32]));
+        assert!(policy.evaluate(&malformed.unwrap(), &registry, "2025-03-01T00:00:00Z", &options).is_err());
+    }
+}
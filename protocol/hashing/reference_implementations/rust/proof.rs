@@ -0,0 +1,353 @@
+/// proof.rs - Interchange formats for Merkle proofs
+///
+/// Proofs travel between parties that may not share this crate, so each proof type has
+/// two fixed encodings.
+///
+/// JSON: an object of the proof's fields, the algorithm by identifier and hashes as
+/// lowercase hex, written in canonical form with arrays kept in order:
+///
+/// ```json
+/// {"algorithm":"sha256","index":2,"path":["5f0c...","a1d4..."],"tree_size":5}
+/// {"algorithm":"sha256","new_size":8,"old_size":3,"path":["..."]}
+/// {"algorithm":"sha256","hashes":["..."],"indices":[1,4],"tree_size":9}
+/// ```
+///
+/// Binary: a kind byte (1 inclusion, 2 consistency, 3 multiproof), the algorithm's
+/// multicodec code as an unsigned LEB128 varint, the proof's integers as varints, then a
+/// varint count and the 32-byte hashes. A multiproof's indices are written as the first
+/// index followed by the gaps between consecutive indices.
+
+use crate::registry::{read_varint, write_varint};
+use crate::{
+    canonicalize_serializable, ArraySortPolicy, CanonicalizeOptions, ConsistencyProof, ConstitutionalError,
+    HashAlgorithm, InclusionProof, MultiProof, Result, SemanticHash,
+};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+const INCLUSION: u8 = 1;
+const CONSISTENCY: u8 = 2;
+const MULTI: u8 = 3;
+
+const INCLUSION_FIELDS: &[&str] = &["algorithm", "index", "tree_size", "path"];
+const CONSISTENCY_FIELDS: &[&str] = &["algorithm", "old_size", "new_size", "path"];
+const MULTI_FIELDS: &[&str] = &["algorithm", "tree_size", "indices", "hashes"];
+
+impl InclusionProof {
+    /// The canonical JSON form.
+    pub fn to_json(&self) -> Result<String> {
+        canonical_json(self)
+    }
+
+    /// The binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = header(INCLUSION, self.algorithm);
+        write_varint(self.index, &mut out);
+        write_varint(self.tree_size, &mut out);
+        write_hashes(&self.path, &mut out);
+        out
+    }
+
+    /// Parse the binary form.
+    ///
+    /// # Returns
+    /// The proof, or a HashingError if the bytes are not an inclusion proof
+    pub fn from_bytes(bytes: &[u8]) -> Result<InclusionProof> {
+        let mut reader = Reader::open(bytes, INCLUSION)?;
+        let proof = InclusionProof {
+            algorithm: reader.algorithm()?,
+            index: reader.varint()?,
+            tree_size: reader.varint()?,
+            path: reader.hashes()?,
+        };
+        reader.finish(proof)
+    }
+}
+
+impl ConsistencyProof {
+    /// The canonical JSON form.
+    pub fn to_json(&self) -> Result<String> {
+        canonical_json(self)
+    }
+
+    /// The binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = header(CONSISTENCY, self.algorithm);
+        write_varint(self.old_size, &mut out);
+        write_varint(self.new_size, &mut out);
+        write_hashes(&self.path, &mut out);
+        out
+    }
+
+    /// Parse the binary form.
+    ///
+    /// # Returns
+    /// The proof, or a HashingError if the bytes are not a consistency proof
+    pub fn from_bytes(bytes: &[u8]) -> Result<ConsistencyProof> {
+        let mut reader = Reader::open(bytes, CONSISTENCY)?;
+        let proof = ConsistencyProof {
+            algorithm: reader.algorithm()?,
+            old_size: reader.varint()?,
+            new_size: reader.varint()?,
+            path: reader.hashes()?,
+        };
+        reader.finish(proof)
+    }
+}
+
+impl MultiProof {
+    /// The canonical JSON form.
+    pub fn to_json(&self) -> Result<String> {
+        canonical_json(self)
+    }
+
+    /// The binary form. Indices must be strictly increasing, as `prove_multi` makes them.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = header(MULTI, self.algorithm);
+        write_varint(self.tree_size, &mut out);
+        write_varint(self.indices.len() as u64, &mut out);
+        let mut previous = 0;
+        for index in &self.indices {
+            write_varint(index.wrapping_sub(previous), &mut out);
+            previous = *index;
+        }
+        write_hashes(&self.hashes, &mut out);
+        out
+    }
+
+    /// Parse the binary form.
+    ///
+    /// # Returns
+    /// The proof, or a HashingError if the bytes are not a multiproof
+    pub fn from_bytes(bytes: &[u8]) -> Result<MultiProof> {
+        let mut reader = Reader::open(bytes, MULTI)?;
+        let algorithm = reader.algorithm()?;
+        let tree_size = reader.varint()?;
+        let count = reader.count(1)?;
+        let mut indices = Vec::with_capacity(count);
+        let mut previous = 0u64;
+        for _ in 0..count {
+            previous = previous.wrapping_add(reader.varint()?);
+            indices.push(previous);
+        }
+        let proof = MultiProof { algorithm, tree_size, indices, hashes: reader.hashes()? };
+        reader.finish(proof)
+    }
+}
+
+impl Serialize for InclusionProof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("InclusionProof", INCLUSION_FIELDS.len())?;
+        state.serialize_field("algorithm", self.algorithm.identifier())?;
+        state.serialize_field("index", &self.index)?;
+        state.serialize_field("tree_size", &self.tree_size)?;
+        state.serialize_field("path", &self.path)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for InclusionProof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<InclusionProof, D::Error> {
+        let mut map = object(Value::deserialize(deserializer)?, INCLUSION_FIELDS)?;
+        Ok(InclusionProof {
+            algorithm: algorithm(&mut map)?,
+            index: field(&mut map, "index")?,
+            tree_size: field(&mut map, "tree_size")?,
+            path: field(&mut map, "path")?,
+        })
+    }
+}
+
+impl Serialize for ConsistencyProof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ConsistencyProof", CONSISTENCY_FIELDS.len())?;
+        state.serialize_field("algorithm", self.algorithm.identifier())?;
+        state.serialize_field("old_size", &self.old_size)?;
+        state.serialize_field("new_size", &self.new_size)?;
+        state.serialize_field("path", &self.path)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ConsistencyProof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<ConsistencyProof, D::Error> {
+        let mut map = object(Value::deserialize(deserializer)?, CONSISTENCY_FIELDS)?;
+        Ok(ConsistencyProof {
+            algorithm: algorithm(&mut map)?,
+            old_size: field(&mut map, "old_size")?,
+            new_size: field(&mut map, "new_size")?,
+            path: field(&mut map, "path")?,
+        })
+    }
+}
+
+impl Serialize for MultiProof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MultiProof", MULTI_FIELDS.len())?;
+        state.serialize_field("algorithm", self.algorithm.identifier())?;
+        state.serialize_field("tree_size", &self.tree_size)?;
+        state.serialize_field("indices", &self.indices)?;
+        state.serialize_field("hashes", &self.hashes)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for MultiProof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<MultiProof, D::Error> {
+        let mut map = object(Value::deserialize(deserializer)?, MULTI_FIELDS)?;
+        Ok(MultiProof {
+            algorithm: algorithm(&mut map)?,
+            tree_size: field(&mut map, "tree_size")?,
+            indices: field(&mut map, "indices")?,
+            hashes: field(&mut map, "hashes")?,
+        })
+    }
+}
+
+fn canonical_json<T: Serialize>(proof: &T) -> Result<String> {
+    canonicalize_serializable(proof, &CanonicalizeOptions::new().array_sort(ArraySortPolicy::Never))
+}
+
+/// The members of a proof object, which must have exactly the fields `fields`.
+fn object<E: de::Error>(value: Value, fields: &'static [&'static str]) -> std::result::Result<Map<String, Value>, E> {
+    let Value::Object(map) = value else {
+        return Err(E::custom("expected a proof object"));
+    };
+    if let Some(key) = map.keys().find(|key| !fields.contains(&key.as_str())) {
+        return Err(E::unknown_field(key, fields));
+    }
+    Ok(map)
+}
+
+fn field<T: DeserializeOwned, E: de::Error>(map: &mut Map<String, Value>, name: &'static str) -> std::result::Result<T, E> {
+    let value = map.remove(name).ok_or_else(|| E::missing_field(name))?;
+    serde_json::from_value(value).map_err(|e| E::custom(format!("{}: {}", name, e)))
+}
+
+fn algorithm<E: de::Error>(map: &mut Map<String, Value>) -> std::result::Result<HashAlgorithm, E> {
+    field::<String, E>(map, "algorithm")?.parse().map_err(E::custom)
+}
+
+fn header(kind: u8, algorithm: HashAlgorithm) -> Vec<u8> {
+    let mut out = vec![kind];
+    write_varint(algorithm.multihash_code(), &mut out);
+    out
+}
+
+fn write_hashes(hashes: &[SemanticHash], out: &mut Vec<u8>) {
+    write_varint(hashes.len() as u64, out);
+    for hash in hashes {
+        out.extend_from_slice(hash.as_bytes());
+    }
+}
+
+/// Cursor over a binary proof.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn open(bytes: &'a [u8], kind: u8) -> Result<Reader<'a>> {
+        match bytes.split_first() {
+            Some((first, rest)) if *first == kind => Ok(Reader(rest)),
+            _ => Err(malformed("wrong proof kind")),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let (value, rest) = read_varint(self.0).ok_or_else(|| malformed("truncated varint"))?;
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn algorithm(&mut self) -> Result<HashAlgorithm> {
+        let code = self.varint()?;
+        HashAlgorithm::all()
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.multihash_code() == code)
+            .ok_or_else(|| malformed("unknown or disabled algorithm"))
+    }
+
+    /// A count of items at least `item_len` bytes each, checked against the bytes left so
+    /// a forged count cannot make the reader allocate more than the input.
+    fn count(&mut self, item_len: usize) -> Result<usize> {
+        let count = self.varint()?;
+        match usize::try_from(count) {
+            Ok(count) if count.checked_mul(item_len).is_some_and(|len| len <= self.0.len()) => Ok(count),
+            _ => Err(malformed("count exceeds the proof")),
+        }
+    }
+
+    fn hashes(&mut self) -> Result<Vec<SemanticHash>> {
+        let count = self.count(32)?;
+        let (hashes, rest) = self.0.split_at(count * 32);
+        self.0 = rest;
+        hashes.chunks(32).map(SemanticHash::try_from).collect()
+    }
+
+    fn finish<T>(self, proof: T) -> Result<T> {
+        if self.0.is_empty() {
+            Ok(proof)
+        } else {
+            Err(malformed("trailing bytes"))
+        }
+    }
+}
+
+fn malformed(reason: &str) -> ConstitutionalError {
+    ConstitutionalError::HashingError(format!("Malformed binary proof: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_consistency, verify_inclusion, verify_multi, MerkleTree};
+    use serde_json::json;
+
+    #[test]
+    fn test_proof_formats_round_trip() {
+        let leaves: Vec<_> = (0..9).map(|i| SemanticHash::of(&json!({"entry": i})).unwrap()).collect();
+        let tree = MerkleTree::from_leaves(&leaves, HashAlgorithm::Sha256).unwrap();
+
+        let inclusion = tree.prove_inclusion(2).unwrap();
+        let text = inclusion.to_json().unwrap();
+        assert!(text.starts_with(r#"{"algorithm":"sha256","index":2,"path":[""#) && text.ends_with(r#"],"tree_size":9}"#));
+        assert_eq!(text, serde_json::to_string(&serde_json::from_str::<Value>(&text).unwrap()).unwrap());
+        let parsed: InclusionProof = serde_json::from_str(&text).unwrap();
+        assert!(verify_inclusion(&leaves[2], &parsed, &tree.root()));
+        assert_eq!(InclusionProof::from_bytes(&inclusion.to_bytes()).unwrap(), inclusion);
+        assert_eq!(inclusion.to_bytes().len(), 1 + 1 + 1 + 1 + 1 + 32 * inclusion.path.len());
+
+        let consistency = tree.prove_consistency(3, 9).unwrap();
+        let parsed: ConsistencyProof = serde_json::from_str(&consistency.to_json().unwrap()).unwrap();
+        assert!(verify_consistency(&tree.root_at(3).unwrap(), &tree.root(), &parsed));
+        assert_eq!(ConsistencyProof::from_bytes(&consistency.to_bytes()).unwrap(), consistency);
+
+        let multi = tree.prove_multi(&[1, 4, 8]).unwrap();
+        let parsed: MultiProof = serde_json::from_str(&multi.to_json().unwrap()).unwrap();
+        assert!(verify_multi(&[leaves[1], leaves[4], leaves[8]], &parsed, &tree.root()));
+        assert_eq!(MultiProof::from_bytes(&multi.to_bytes()).unwrap(), multi);
+    }
+
+    #[test]
+    fn test_malformed_proofs_rejected() {
+        let leaves = [SemanticHash::of(&json!({"entry": 0})).unwrap(); 4];
+        let proof = MerkleTree::from_leaves(&leaves, HashAlgorithm::Sha256).unwrap().prove_inclusion(1).unwrap();
+        let bytes = proof.to_bytes();
+        assert!(ConsistencyProof::from_bytes(&bytes).is_err());
+        assert!(InclusionProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(InclusionProof::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(InclusionProof::from_bytes(&[1, 0x12, 0, 1, 0xff, 0xff, 0xff, 0x0f]).is_err());
+
+        for text in [
+            r#"{"algorithm":"md5","index":0,"tree_size":1,"path":[]}"#,
+            r#"{"algorithm":"sha256","index":0,"tree_size":1}"#,
+            r#"{"algorithm":"sha256","index":0,"tree_size":1,"path":[],"root":"00"}"#,
+            r#"{"algorithm":"sha256","index":-1,"tree_size":1,"path":[]}"#,
+            r#"["sha256",0,1,[]]"#,
+        ] {
+            assert!(serde_json::from_str::<InclusionProof>(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+/// quorum.rs - Quorum certificates over contract hashes
+///
+/// A contract is ratified once at least `t` of the `n` registered agents have signed its
+/// hash envelope. A `QuorumCertificate` gathers those signatures into one object that
+/// downstream code can store, hash and check as a unit:
+///
+/// ```json
+/// {"hash_envelope":{...},"agent_set":"5d1c...","threshold":3,
+///  "signatures":{"ed25519:3b6a...":"ed25519:92a0...","ed25519:8a88...":"ed25519:e556..."}}
+/// ```
+///
+/// `agent_set` is the semantic hash of the `AgentSet` the quorum was reached in, its
+/// `{"agents":[...],"threshold":t}` form, so a certificate cannot be checked against a
+/// smaller set or a lower threshold than it was formed under. Signatures are the ones
+/// `signing.rs` makes, keyed by signer, so each agent counts once.
+
+use crate::objects::take;
+use crate::signing::signing_message;
+use crate::{CanonicalizeOptions, ConstitutionalError, HashEnvelope, PublicKey, Result, SemanticHash, SignedObject};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const FIELDS: &[&str] = &["hash_envelope", "agent_set", "threshold", "signatures"];
+
+/// The registered agents and how many of them form a quorum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentSet {
+    /// Public keys in their text form, sorted
+    agents: Vec<String>,
+    threshold: usize,
+}
+
+impl AgentSet {
+    /// A set of agents of which `threshold` must sign.
+    ///
+    /// # Returns
+    /// The set, or a ProtocolError if a key repeats or the threshold is not between 1 and
+    /// the number of agents
+    pub fn new(agents: &[PublicKey], threshold: usize) -> Result<AgentSet> {
+        let mut keys: Vec<String> = agents.iter().map(PublicKey::to_string).collect();
+        keys.sort();
+        keys.dedup();
+        if keys.len() != agents.len() {
+            return Err(ConstitutionalError::ProtocolError("An agent set lists an agent twice".to_string()));
+        }
+        if threshold == 0 || threshold > keys.len() {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "A threshold of {} is impossible for {} agents",
+                threshold,
+                keys.len()
+            )));
+        }
+        Ok(AgentSet { agents: keys, threshold })
+    }
+
+    /// Number of registered agents.
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Whether the set has no agents; never true for a set `new` accepted.
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Number of signatures a quorum needs.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Whether `signer`, a public key in text form, is registered.
+    pub fn contains(&self, signer: &str) -> bool {
+        self.agents.binary_search_by(|agent| agent.as_str().cmp(signer)).is_ok()
+    }
+
+    /// The semantic hash of the set, which certificates formed in it record.
+    pub fn digest(&self) -> Result<SemanticHash> {
+        SemanticHash::of(&json!({"agents": self.agents, "threshold": self.threshold}))
+    }
+}
+
+/// Collects agents' signatures over one envelope until they form a quorum.
+#[derive(Debug, Clone)]
+pub struct QuorumCollector {
+    envelope: HashEnvelope,
+    set: AgentSet,
+    message: Vec<u8>,
+    signatures: BTreeMap<String, String>,
+}
+
+impl QuorumCollector {
+    /// Start collecting signatures over `envelope` from the agents of `set`.
+    pub fn new(envelope: HashEnvelope, set: AgentSet) -> Result<QuorumCollector> {
+        let message = signing_message(&envelope)?;
+        Ok(QuorumCollector { envelope, set, message, signatures: BTreeMap::new() })
+    }
+
+    /// Add an agent's signature over the envelope.
+    ///
+    /// # Returns
+    /// The certificate once the signatures reach the threshold, None before; a
+    /// ProtocolError if the signer is not registered or the signature does not verify
+    pub fn add(&mut self, signer: &PublicKey, signature: &str) -> Result<Option<QuorumCertificate>> {
+        let name = signer.to_string();
+        if !self.set.contains(&name) {
+            return Err(ConstitutionalError::ProtocolError(format!("{} is not a registered agent", name)));
+        }
+        if !signer.verify_message(&self.message, signature)? {
+            return Err(ConstitutionalError::ProtocolError(format!("Invalid signature from {}", name)));
+        }
+        self.signatures.insert(name, signature.to_string());
+        self.certificate()
+    }
+
+    /// Add an agent's signed object, which must carry the envelope being collected for.
+    pub fn add_signed(&mut self, signed: &SignedObject) -> Result<Option<QuorumCertificate>> {
+        if signed.hash_envelope != self.envelope {
+            return Err(ConstitutionalError::ProtocolError("The signed object is over another hash".to_string()));
+        }
+        self.add(&signed.signer.parse()?, &signed.signature)
+    }
+
+    /// Number of distinct agents who have signed.
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Whether nobody has signed yet.
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// The certificate, if the signatures collected so far reach the threshold.
+    pub fn certificate(&self) -> Result<Option<QuorumCertificate>> {
+        if self.signatures.len() < self.set.threshold {
+            return Ok(None);
+        }
+        Ok(Some(QuorumCertificate {
+            hash_envelope: self.envelope.clone(),
+            agent_set: self.set.digest()?,
+            threshold: self.set.threshold as u64,
+            signatures: self.signatures.clone(),
+        }))
+    }
+}
+
+/// Proof that a quorum of an agent set signed a hash envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumCertificate {
+    /// Semantic hash of the ratified contract and how it was computed
+    pub hash_envelope: HashEnvelope,
+    /// Semantic hash of the agent set the quorum was reached in
+    pub agent_set: SemanticHash,
+    /// Signatures the set required
+    pub threshold: u64,
+    /// Signature over the envelope of each signer, by public key
+    pub signatures: BTreeMap<String, String>,
+}
+
+impl QuorumCertificate {
+    /// Check the certificate against the agent set it claims to be formed in.
+    ///
+    /// # Returns
+    /// true if the set is the one recorded and at least its threshold of registered
+    /// agents validly signed the envelope, false otherwise; an error if a signer or
+    /// signature is malformed
+    pub fn verify(&self, set: &AgentSet) -> Result<bool> {
+        if self.agent_set != set.digest()? || self.threshold != set.threshold as u64 {
+            return Ok(false);
+        }
+        let message = signing_message(&self.hash_envelope)?;
+        let mut valid = 0;
+        for (signer, signature) in &self.signatures {
+            if !set.contains(signer) || !signer.parse::<PublicKey>()?.verify_message(&message, signature)? {
+                return Ok(false);
+            }
+            valid += 1;
+        }
+        Ok(valid >= set.threshold)
+    }
+
+    /// The certificate's canonical form.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).expect("certificates serialize to JSON")
+    }
+
+    /// The semantic hash of the certificate, to refer to the ratification by.
+    pub fn hash(&self, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        SemanticHash::compute(&self.to_value(), options)
+    }
+}
+
+impl Serialize for QuorumCertificate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("QuorumCertificate", FIELDS.len())?;
+        state.serialize_field("hash_envelope", &self.hash_envelope)?;
+        state.serialize_field("agent_set", &self.agent_set)?;
+        state.serialize_field("threshold", &self.threshold)?;
+        state.serialize_field("signatures", &self.signatures)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for QuorumCertificate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<QuorumCertificate, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a quorum certificate object"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        Ok(QuorumCertificate {
+            hash_envelope: take(&mut map, "hash_envelope")?,
+            agent_set: take(&mut map, "agent_set")?,
+            threshold: take(&mut map, "threshold")?,
+            signatures: take(&mut map, "signatures")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+    use serde_json::json;
+
+    fn agents() -> Vec<Keypair> {
+        (1..=5).map(|i| Keypair::from_secret(&[i; 32])).collect()
+    }
+
+    fn contract() -> Value {
+        json!({"contract_id": "c-17", "action_type": "amend"})
+    }
+
+    #[test]
+    fn test_quorum_forms_at_threshold() {
+        let keypairs = agents();
+        let keys: Vec<_> = keypairs.iter().map(Keypair::public_key).collect();
+        let set = AgentSet::new(&keys, 3).unwrap();
+        let signed: Vec<_> = keypairs.iter().map(|k| sign(&contract(), k).unwrap()).collect();
+
+        let mut collector = QuorumCollector::new(signed[0].hash_envelope.clone(), set.clone()).unwrap();
+        assert!(collector.add_signed(&signed[0]).unwrap().is_none());
+        // A second signature from the same agent does not count twice
+        assert!(collector.add_signed(&signed[0]).unwrap().is_none());
+        assert!(collector.add_signed(&signed[3]).unwrap().is_none());
+        let certificate = collector.add_signed(&signed[4]).unwrap().unwrap();
+        assert_eq!(certificate.signatures.len(), 3);
+        assert!(certificate.verify(&set).unwrap());
+
+        let stored = serde_json::to_string(&certificate).unwrap();
+        let restored: QuorumCertificate = serde_json::from_str(&stored).unwrap();
+        let options = CanonicalizeOptions::new();
+        assert_eq!(restored.hash(&options).unwrap(), certificate.hash(&options).unwrap());
+
+        let outsider = Keypair::from_secret(&[9; 32]);
+        assert!(collector.add_signed(&sign(&contract(), &outsider).unwrap()).is_err());
+        let forged = SignedObject { signature: signed[2].signature.clone(), ..signed[1].clone() };
+        assert!(collector.add_signed(&forged).is_err());
+    }
+
+    #[test]
+    fn test_certificate_is_bound_to_its_agent_set() {
+        let keypairs = agents();
+        let keys: Vec<_> = keypairs.iter().map(Keypair::public_key).collect();
+        let set = AgentSet::new(&keys, 2).unwrap();
+        let signed: Vec<_> = keypairs.iter().map(|k| sign(&contract(), k).unwrap()).collect();
+        let mut collector = QuorumCollector::new(signed[0].hash_envelope.clone(), set.clone()).unwrap();
+        collector.add_signed(&signed[0]).unwrap();
+        let certificate = collector.add_signed(&signed[1]).unwrap().unwrap();
+
+        assert!(!certificate.verify(&AgentSet::new(&keys, 3).unwrap()).unwrap());
+        assert!(!certificate.verify(&AgentSet::new(&keys[..4], 2).unwrap()).unwrap());
+        let mut short = certificate.clone();
+        short.signatures.pop_first();
+        assert!(!short.verify(&set).unwrap());
+
+        assert!(AgentSet::new(&keys, 0).is_err());
+        assert!(AgentSet::new(&keys, 6).is_err());
+        assert!(AgentSet::new(&[keys[0], keys[0]], 1).is_err());
+    }
+}
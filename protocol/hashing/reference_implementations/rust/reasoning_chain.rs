@@ -0,0 +1,215 @@
+/// reasoning_chain.rs - Reasoning as a hash-linked chain of steps
+///
+/// A `Reasoning` is one rationale. A `ReasoningChain` is an argument in ordered steps,
+/// each a `Reasoning` with a hash over the step and the hash of the step before it, the
+/// semantic hash of:
+///
+/// ```json
+/// {"reasoning":{"rationale":"Article III.1 limits amendments to...","confidence":0.9},"previous":"5c1d..."}
+/// ```
+///
+/// the first step without `previous`. Each hash so commits to every step up to its own,
+/// and the last one, the chain's `head`, to the whole argument: changing, dropping or
+/// reordering a step leaves its hash, or a later step's, wrong, which `verify` finds.
+///
+/// The chain's JSON is its steps with their hashes, so it canonicalizes and hashes like
+/// any protocol object:
+///
+/// ```json
+/// {"steps":[{"reasoning":{...},"hash":"5c1d..."},{"reasoning":{...},"hash":"a3f5..."}]}
+/// ```
+
+use crate::objects::{object, take};
+use crate::validation::FieldReport;
+use crate::{semantic_hash, verify_semantic_hash, Canonicalize, Reasoning, Result, Violation};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+
+/// One step of a reasoning chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReasoningStep {
+    /// The step's rationale and confidence
+    pub reasoning: Reasoning,
+    /// Semantic hash of the step linked to the previous one
+    pub hash: String,
+}
+
+/// What a step's hash is over.
+fn link(reasoning: &Reasoning, previous: Option<&str>) -> Value {
+    let mut link = json!({"reasoning": reasoning.to_value()});
+    if let Some(previous) = previous {
+        link["previous"] = Value::from(previous);
+    }
+    link
+}
+
+/// An argument in ordered steps, each hash-linked to the one before it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReasoningChain {
+    /// The steps, first to last
+    pub steps: Vec<ReasoningStep>,
+}
+
+impl ReasoningChain {
+    /// An empty chain.
+    pub fn new() -> Self {
+        ReasoningChain::default()
+    }
+
+    /// Append a step, linking it to the last one.
+    ///
+    /// # Returns
+    /// The step's hash, or an error if the step cannot be hashed
+    pub fn push(&mut self, reasoning: Reasoning) -> Result<&str> {
+        let hash = semantic_hash(&link(&reasoning, self.head()))?;
+        self.steps.push(ReasoningStep { reasoning, hash });
+        Ok(&self.steps[self.steps.len() - 1].hash)
+    }
+
+    /// Append a step, builder style.
+    ///
+    /// # Returns
+    /// The chain, or an error if the step cannot be hashed
+    pub fn step(mut self, reasoning: Reasoning) -> Result<Self> {
+        self.push(reasoning)?;
+        Ok(self)
+    }
+
+    /// The last step's hash, committing to the whole chain; None if it is empty.
+    pub fn head(&self) -> Option<&str> {
+        self.steps.last().map(|step| step.hash.as_str())
+    }
+
+    /// The last step, which the others lead to.
+    pub fn conclusion(&self) -> Option<&Reasoning> {
+        self.steps.last().map(|step| &step.reasoning)
+    }
+
+    /// Recompute every step's hash from its reasoning and the hash recorded before it.
+    ///
+    /// # Returns
+    /// true if every recorded hash matches, false otherwise; an error if a step cannot be
+    /// hashed
+    pub fn verify(&self) -> Result<bool> {
+        let mut previous = None;
+        for step in &self.steps {
+            if !verify_semantic_hash(&link(&step.reasoning, previous), &step.hash)? {
+                return Ok(false);
+            }
+            previous = Some(step.hash.as_str());
+        }
+        Ok(true)
+    }
+
+    /// Check that every step's confidence is in [0, 1].
+    ///
+    /// # Returns
+    /// Every violation, each at `/steps/<index>/reasoning/confidence`
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut report = FieldReport::default();
+        for (index, step) in self.steps.iter().enumerate() {
+            report.confidence(&["steps", &index.to_string(), "reasoning", "confidence"], &step.reasoning.confidence);
+        }
+        report.violations
+    }
+
+    /// The chain's JSON object.
+    pub fn to_value(&self) -> Value {
+        let steps: Vec<Value> =
+            self.steps.iter().map(|step| json!({"reasoning": step.reasoning.to_value(), "hash": step.hash})).collect();
+        json!({ "steps": steps })
+    }
+}
+
+impl Canonicalize for ReasoningChain {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl Serialize for ReasoningChain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReasoningStep {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<ReasoningStep, D::Error> {
+        let mut map = object(deserializer, "a reasoning step")?;
+        let step = ReasoningStep { reasoning: take(&mut map, "reasoning")?, hash: take(&mut map, "hash")? };
+        only(map, "a reasoning step")?;
+        Ok(step)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReasoningChain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<ReasoningChain, D::Error> {
+        let mut map = object(deserializer, "a reasoning chain")?;
+        let chain = ReasoningChain { steps: take(&mut map, "steps")? };
+        only(map, "a reasoning chain")?;
+        Ok(chain)
+    }
+}
+
+/// Steps and chains have no extra members, since the hashes would not cover them.
+fn only<E: de::Error>(rest: Map<String, Value>, what: &str) -> std::result::Result<(), E> {
+    match rest.keys().next() {
+        Some(name) => Err(E::custom(format!("{} has no member {:?}", what, name))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> ReasoningChain {
+        ReasoningChain::new()
+            .step(Reasoning::new("The amendment changes Article III.1", 0.95).unwrap())
+            .unwrap()
+            .step(Reasoning::new("Article III.1 may only be clarified, not extended", 0.8).unwrap())
+            .unwrap()
+            .step(Reasoning::new("The change is a clarification", 0.7).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_chain_links_steps() {
+        let chain = chain();
+        assert!(chain.verify().unwrap());
+        assert_eq!(chain.validate(), []);
+        let first = semantic_hash(&json!({"reasoning": chain.steps[0].reasoning.to_value()})).unwrap();
+        assert_eq!(chain.steps[0].hash, first);
+        let second = json!({"reasoning": chain.steps[1].reasoning.to_value(), "previous": first});
+        assert_eq!(chain.steps[1].hash, semantic_hash(&second).unwrap());
+        assert_eq!(chain.head(), Some(chain.steps[2].hash.as_str()));
+        assert_eq!(chain.conclusion().unwrap().rationale, "The change is a clarification");
+
+        let read: ReasoningChain = serde_json::from_value(chain.to_value()).unwrap();
+        assert_eq!(read, chain);
+        assert_eq!(read.semantic_hash().unwrap(), chain.semantic_hash().unwrap());
+        let mut extended = chain.to_value();
+        extended["steps"][0]["note"] = json!("unhashed");
+        assert!(serde_json::from_value::<ReasoningChain>(extended).is_err());
+    }
+
+    #[test]
+    fn test_tampered_steps_fail() {
+        // An intermediate step changed with its own hash recomputed still breaks the next link
+        let mut changed = chain();
+        changed.steps[1].reasoning.rationale = "Article III.1 may be extended".to_string();
+        assert!(!changed.verify().unwrap());
+        let relinked = link(&changed.steps[1].reasoning, Some(&changed.steps[0].hash));
+        changed.steps[1].hash = semantic_hash(&relinked).unwrap();
+        assert!(!changed.verify().unwrap());
+
+        let mut dropped = chain();
+        dropped.steps.remove(1);
+        assert!(!dropped.verify().unwrap());
+        let mut reordered = chain();
+        reordered.steps.swap(0, 1);
+        assert!(!reordered.verify().unwrap());
+        assert!(ReasoningChain::new().verify().unwrap());
+    }
+}
@@ -0,0 +1,295 @@
+/// redact.rs - Redactable hashing for selective disclosure
+///
+/// An agent may have to show a contract to a party that must not see all of it, say with
+/// `reasoning.rationale` withheld, while the party still checks it against the hash the
+/// agent committed to. A plain semantic hash cannot do that: every byte of the contract
+/// is needed to recompute it. Here the document is hashed as a Merkle tree of its object
+/// members instead:
+///
+/// * a leaf (any value but an object) hashes `0x00`, a random 32-byte salt and the
+///   value's canonical form;
+/// * an object hashes `0x01` and, in key collation order, each member's key (length
+///   prefixed) and its child hash;
+/// * the root hashes the version tag, `ocp-redactable:` and the top-level object hash.
+///
+/// Redacting a member replaces its value with `{"ocp:redacted": "<hex child hash>"}` and
+/// drops the salts under it, so the root still verifies. The salts keep a withheld
+/// value from being guessed and checked against its hash. The key of a redacted member
+/// stays visible. Arrays are leaves and are disclosed or withheld whole.
+
+use crate::algorithm::{hex, unhex, Hasher};
+use crate::hmac::constant_time_eq;
+use crate::incremental::canonical_member_at;
+use crate::merkle::check_algorithm;
+use crate::{
+    check_child, versioned_hasher, CanonicalizeOptions, ConstitutionalError, JsonPointer, Nonce, Result, SemanticHash,
+};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Key of the marker object standing in for a redacted value.
+pub const REDACTED_KEY: &str = "ocp:redacted";
+
+const ROOT_TAG: &[u8] = b"ocp-redactable:";
+const LEAF_PREFIX: u8 = 0x00;
+const OBJECT_PREFIX: u8 = 0x01;
+
+/// A document with a salt for every leaf, some of whose members may be redacted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactableDocument {
+    /// The document, with redacted members replaced by markers
+    pub document: Value,
+    /// Salt of each disclosed leaf, by the JSON Pointer of the leaf in `document`
+    pub salts: BTreeMap<String, Nonce>,
+}
+
+impl RedactableDocument {
+    /// Salt every leaf of an object with a fresh nonce.
+    ///
+    /// # Returns
+    /// The salted document, or an error if it is not an object or nonces cannot be drawn
+    pub fn new(document: Value) -> Result<RedactableDocument> {
+        if !document.is_object() {
+            return Err(ConstitutionalError::canonicalization_at("A redactable document must be an object", &[]));
+        }
+        let mut salts = BTreeMap::new();
+        salt_leaves(&document, &mut Vec::new(), &mut salts)?;
+        Ok(RedactableDocument { document, salts })
+    }
+
+    /// The redactable root, the same before and after any redaction.
+    ///
+    /// # Arguments
+    /// * `options` - Canonicalization options the leaves are canonicalized under
+    ///
+    /// # Returns
+    /// The root, or an error if a disclosed leaf has no salt, a marker is malformed, or
+    /// the document breaks the options' rules
+    pub fn root(&self, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+        check_algorithm(options.hash_algorithm)?;
+        let Value::Object(map) = &self.document else {
+            return Err(ConstitutionalError::canonicalization_at("A redactable document must be an object", &[]));
+        };
+        let mut hasher = versioned_hasher(options);
+        hasher.update(ROOT_TAG);
+        hasher.update(&self.object_hash(map, &[], options)?);
+        SemanticHash::try_from(hasher.finalize().as_slice())
+    }
+
+    /// A copy with the members at `paths` redacted.
+    ///
+    /// # Arguments
+    /// * `paths` - Pointers to object members, e.g. `/reasoning/rationale`; a member that
+    ///   is itself an object is redacted with everything under it
+    /// * `options` - Canonicalization options the root is computed under
+    ///
+    /// # Returns
+    /// The redacted document, or a CanonicalizationError if a path does not name an
+    /// object member (the root, an array element, or a member under a redaction)
+    pub fn redact(&self, paths: &[JsonPointer], options: &CanonicalizeOptions) -> Result<RedactableDocument> {
+        let mut redacted = self.clone();
+        for path in paths {
+            let Some((key, parent)) = path.tokens().split_last() else {
+                return Err(ConstitutionalError::canonicalization_at("Cannot redact the document root", &[]));
+            };
+            // Markers hold no members, so members under a redaction are never found
+            let value = match redacted.document.pointer(&JsonPointer::from_tokens(parent.iter().cloned()).to_string()) {
+                Some(object @ Value::Object(map)) if marker_hash(object).is_none() => map.get(key),
+                _ => None,
+            };
+            let hash = match value {
+                Some(value) if marker_hash(value).is_none() => redacted.member_hash(parent, key, value, options)?,
+                _ => {
+                    return Err(ConstitutionalError::canonicalization_at(
+                        "No disclosed object member to redact",
+                        path.tokens(),
+                    ))
+                }
+            };
+            let text = path.to_string();
+            redacted.salts.retain(|pointer, _| {
+                pointer != &text && !pointer.strip_prefix(text.as_str()).is_some_and(|rest| rest.starts_with('/'))
+            });
+            let mut marker = Map::new();
+            marker.insert(REDACTED_KEY.to_string(), Value::String(hex(&hash)));
+            if let Some(slot) = redacted.document.pointer_mut(&text) {
+                *slot = Value::Object(marker);
+            }
+        }
+        Ok(redacted)
+    }
+
+    /// Hash of the members of the object at `path`.
+    fn object_hash(&self, map: &Map<String, Value>, path: &[String], options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+        let mut members = Vec::with_capacity(map.len());
+        for (key, value) in map {
+            if options.exclude_paths.iter().any(|p| p.is_child(path, key)) || options.schema_hints.excludes(path, key) {
+                continue;
+            }
+            if let Some(hash) = self.member_hash_pruned(path, key, value, options)? {
+                let key = match options.normalization {
+                    Some(form) => form.apply(key),
+                    None => key.clone(),
+                };
+                members.push((key, hash));
+            }
+        }
+        members.sort_by(|a, b| options.key_collation.compare(&a.0, &b.0));
+
+        let mut hasher = Hasher::new(options.hash_algorithm);
+        hasher.update(&[OBJECT_PREFIX]);
+        for (key, hash) in &members {
+            hasher.update(&(key.len() as u64).to_be_bytes());
+            hasher.update(key.as_bytes());
+            hasher.update(hash);
+        }
+        Ok(hasher.finalize())
+    }
+
+    fn member_hash(&self, parent: &[String], key: &str, value: &Value, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+        let hash = self.member_hash_pruned(parent, key, value, options)?;
+        hash.ok_or_else(|| ConstitutionalError::canonicalization_at("Cannot redact a pruned member", &child(parent, key)))
+    }
+
+    /// Hash of the member `key` of the object at `parent`, or None if it is pruned.
+    fn member_hash_pruned(
+        &self,
+        parent: &[String],
+        key: &str,
+        value: &Value,
+        options: &CanonicalizeOptions,
+    ) -> Result<Option<Vec<u8>>> {
+        let path = child(parent, key);
+        check_child(options, &path, 0, Some(key))?;
+        if let Some(hash) = marker_hash(value) {
+            return match unhex(hash).filter(|hash| hash.len() == options.hash_algorithm.digest_len()) {
+                Some(hash) => Ok(Some(hash)),
+                None => Err(ConstitutionalError::canonicalization_at("Malformed redaction marker", &path)),
+            };
+        }
+        if let Value::Object(map) = value {
+            if map.is_empty() && options.prune.drops(value) {
+                return Ok(None);
+            }
+            return self.object_hash(map, &path, options).map(Some);
+        }
+
+        let Some(text) = canonical_member_at(parent, key, value, options)? else {
+            return Ok(None);
+        };
+        let pointer = JsonPointer::from_tokens(path.iter().cloned()).to_string();
+        let Some(salt) = self.salts.get(&pointer) else {
+            return Err(ConstitutionalError::canonicalization_at("Disclosed value has no salt", &path));
+        };
+        let mut hasher = Hasher::new(options.hash_algorithm);
+        hasher.update(&[LEAF_PREFIX]);
+        hasher.update(salt.as_bytes());
+        hasher.update(text.as_bytes());
+        Ok(Some(hasher.finalize()))
+    }
+}
+
+/// Check a possibly redacted document against the redactable root it was committed with.
+///
+/// # Arguments
+/// * `document` - The disclosed document and the salts of its disclosed leaves
+/// * `root` - Redactable root of the full document
+/// * `options` - Canonicalization options the root was computed under
+///
+/// # Returns
+/// true if the document's root matches, false otherwise; an error for a malformed
+/// disclosure
+pub fn verify_redacted(document: &RedactableDocument, root: &SemanticHash, options: &CanonicalizeOptions) -> Result<bool> {
+    Ok(constant_time_eq(document.root(options)?.as_bytes(), root.as_bytes()))
+}
+
+/// The hash a redaction marker carries, if `value` is one.
+fn marker_hash(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.get(REDACTED_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+fn child(parent: &[String], key: &str) -> Vec<String> {
+    let mut path = parent.to_vec();
+    path.push(key.to_string());
+    path
+}
+
+fn salt_leaves(value: &Value, path: &mut Vec<String>, salts: &mut BTreeMap<String, Nonce>) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                path.push(key.clone());
+                salt_leaves(value, path, salts)?;
+                path.pop();
+            }
+        }
+        _ => {
+            salts.insert(JsonPointer::from_tokens(path.iter().cloned()).to_string(), Nonce::generate()?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract() -> Value {
+        json!({
+            "contract_id": "c-17",
+            "reasoning": {"rationale": "internal deliberations", "confidence": 0.9},
+            "parties": ["b", "a"],
+            "signature": "ed25519:abc"
+        })
+    }
+
+    #[test]
+    fn test_redacted_document_still_verifies() {
+        let options = CanonicalizeOptions::new();
+        let full = RedactableDocument::new(contract()).unwrap();
+        let root = full.root(&options).unwrap();
+        assert!(verify_redacted(&full, &root, &options).unwrap());
+
+        let rationale = JsonPointer::parse("/reasoning/rationale").unwrap();
+        let shown = full.redact(&[rationale], &options).unwrap();
+        assert!(shown.document["reasoning"]["rationale"][REDACTED_KEY].is_string());
+        assert!(!shown.salts.contains_key("/reasoning/rationale"));
+        assert!(verify_redacted(&shown, &root, &options).unwrap());
+
+        // Whole subtrees redact too, and redactions compose
+        let hidden = shown.redact(&[JsonPointer::parse("/reasoning").unwrap()], &options).unwrap();
+        assert!(hidden.salts.keys().all(|pointer| !pointer.starts_with("/reasoning")));
+        assert!(verify_redacted(&hidden, &root, &options).unwrap());
+
+        let mut tampered = shown.clone();
+        tampered.document["reasoning"]["confidence"] = json!(0.1);
+        assert!(!verify_redacted(&tampered, &root, &options).unwrap());
+        // The same document under fresh salts has an unrelated root
+        assert_ne!(RedactableDocument::new(contract()).unwrap().root(&options).unwrap(), root);
+    }
+
+    #[test]
+    fn test_redaction_errors_and_options() {
+        let options = CanonicalizeOptions::new().exclude_field("signature");
+        let full = RedactableDocument::new(contract()).unwrap();
+        let root = full.root(&options).unwrap();
+        let mut unsigned = full.clone();
+        unsigned.document["signature"] = json!("ed25519:other");
+        assert_eq!(unsigned.root(&options).unwrap(), root);
+
+        for path in ["", "/parties/0", "/missing", "/reasoning/rationale/deeper"] {
+            assert!(full.redact(&[JsonPointer::parse(path).unwrap()], &options).is_err(), "{}", path);
+        }
+        let shown = full.redact(&[JsonPointer::parse("/reasoning").unwrap()], &options).unwrap();
+        assert!(shown.redact(&[JsonPointer::parse("/reasoning/rationale").unwrap()], &options).is_err());
+
+        let mut unsalted = full.clone();
+        unsalted.salts.remove("/contract_id");
+        assert!(unsalted.root(&options).is_err());
+        assert!(RedactableDocument::new(json!([1])).is_err());
+    }
+}
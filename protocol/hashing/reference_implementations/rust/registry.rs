@@ -0,0 +1,395 @@
+/// registry.rs - Runtime registry of hash algorithms for self-describing verification
+///
+/// A verifier receives objects hashed by many OCP deployments. Each deployment may use a
+/// different algorithm, and some use algorithms this crate does not ship. An
+/// `AlgorithmRegistry` maps algorithm identifiers and multicodec codes to
+/// implementations. `verify` reads the algorithm from the expected hash and dispatches to
+/// it. The hash may be tagged (`sha256:9f86...`), an envelope, or a multihash (a varint
+/// code, a varint length and the digest). Deployments add their own algorithms by
+/// implementing `DigestAlgorithm`.
+///
+/// `HashEnvelope` only names the built-in algorithms, so a custom algorithm's hashes are
+/// exchanged tagged or as multihashes.
+
+use crate::algorithm::Hasher;
+use crate::encoding::{decode_digest, decode_multibase, digest_text};
+use crate::hmac::constant_time_eq;
+use crate::{
+    canonicalize_to_writer, CanonicalizeOptions, ConstitutionalError, ExpectedHash, HashAlgorithm, HashEnvelope, Result,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// A hash function that semantic hashes can be computed with.
+pub trait DigestAlgorithm: Send + Sync {
+    /// Identifier naming the algorithm in tagged hashes, e.g. `sha256`; it must not
+    /// contain `:`.
+    fn identifier(&self) -> &str;
+
+    /// Multicodec code of the algorithm, if it has one.
+    fn multihash_code(&self) -> Option<u64> {
+        None
+    }
+
+    /// Length of the digest in bytes.
+    fn digest_len(&self) -> usize;
+
+    /// Start a hash.
+    fn start(&self) -> Box<dyn DigestState>;
+}
+
+/// A hash in progress under a `DigestAlgorithm`.
+pub trait DigestState {
+    /// Feed bytes to the hash.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// The digest of every byte fed.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+impl DigestAlgorithm for HashAlgorithm {
+    fn identifier(&self) -> &str {
+        HashAlgorithm::identifier(*self)
+    }
+
+    fn multihash_code(&self) -> Option<u64> {
+        Some(HashAlgorithm::multihash_code(*self))
+    }
+
+    fn digest_len(&self) -> usize {
+        HashAlgorithm::digest_len(*self)
+    }
+
+    fn start(&self) -> Box<dyn DigestState> {
+        Box::new(Hasher::new(*self))
+    }
+}
+
+impl DigestState for Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        Hasher::update(self, bytes);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Hasher::finalize(*self)
+    }
+}
+
+/// Feeds canonical bytes written to it into a `DigestState`.
+struct StateWriter(Box<dyn DigestState>);
+
+impl Write for StateWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash algorithms by identifier and multicodec code.
+#[derive(Clone)]
+pub struct AlgorithmRegistry {
+    by_identifier: HashMap<String, Arc<dyn DigestAlgorithm>>,
+    by_code: HashMap<u64, Arc<dyn DigestAlgorithm>>,
+}
+
+impl Default for AlgorithmRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for AlgorithmRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut identifiers: Vec<_> = self.by_identifier.keys().collect();
+        identifiers.sort();
+        f.debug_struct("AlgorithmRegistry").field("algorithms", &identifiers).finish()
+    }
+}
+
+impl AlgorithmRegistry {
+    /// A registry of every built-in algorithm compiled in.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for algorithm in HashAlgorithm::all() {
+            // Built-in identifiers and codes are distinct
+            let _ = registry.register(*algorithm);
+        }
+        registry
+    }
+
+    /// A registry with no algorithms.
+    pub fn empty() -> Self {
+        AlgorithmRegistry { by_identifier: HashMap::new(), by_code: HashMap::new() }
+    }
+
+    /// Add an algorithm.
+    ///
+    /// # Returns
+    /// Ok, or a HashingError if its identifier is malformed or it reuses the identifier
+    /// or multicodec code of an algorithm already registered
+    pub fn register(&mut self, algorithm: impl DigestAlgorithm + 'static) -> Result<()> {
+        let identifier = algorithm.identifier().to_string();
+        if identifier.is_empty() || identifier.contains(':') {
+            return Err(ConstitutionalError::HashingError(format!("Invalid algorithm identifier {:?}", identifier)));
+        }
+        if self.by_identifier.contains_key(&identifier) {
+            return Err(ConstitutionalError::HashingError(format!("Algorithm {:?} is already registered", identifier)));
+        }
+        let code = algorithm.multihash_code();
+        if let Some((code, existing)) = code.and_then(|code| self.by_code.get_key_value(&code)) {
+            return Err(ConstitutionalError::HashingError(format!(
+                "Multihash code {:#x} is already registered to {:?}",
+                code,
+                existing.identifier()
+            )));
+        }
+
+        let algorithm: Arc<dyn DigestAlgorithm> = Arc::new(algorithm);
+        if let Some(code) = code {
+            self.by_code.insert(code, Arc::clone(&algorithm));
+        }
+        self.by_identifier.insert(identifier, algorithm);
+        Ok(())
+    }
+
+    /// The algorithm with this identifier.
+    pub fn get(&self, identifier: &str) -> Option<&dyn DigestAlgorithm> {
+        self.by_identifier.get(identifier).map(|algorithm| &**algorithm)
+    }
+
+    /// The algorithm with this multicodec code.
+    pub fn get_by_code(&self, code: u64) -> Option<&dyn DigestAlgorithm> {
+        self.by_code.get(&code).map(|algorithm| &**algorithm)
+    }
+
+    /// Semantic hash of data under a registered algorithm, tagged with its identifier.
+    ///
+    /// # Arguments
+    /// * `data` - Input JSON value to hash
+    /// * `identifier` - Identifier of the algorithm to hash with
+    /// * `options` - Canonicalization options; their own algorithm is ignored
+    ///
+    /// # Returns
+    /// The hash as `identifier:digest`, the digest in the options' encoding
+    pub fn hash(&self, data: &Value, identifier: &str, options: &CanonicalizeOptions) -> Result<String> {
+        let digest = digest_under(self.lookup(identifier)?, data, options)?;
+        Ok(format!("{}:{}", identifier, digest_text(options.hash_encoding, &digest)?))
+    }
+
+    /// Semantic hash of data under a registered algorithm, as a binary multihash.
+    pub fn multihash(&self, data: &Value, identifier: &str, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+        let algorithm = self.lookup(identifier)?;
+        let code = algorithm.multihash_code().ok_or_else(|| {
+            ConstitutionalError::HashingError(format!("Algorithm {:?} has no multihash code", identifier))
+        })?;
+        let digest = digest_under(algorithm, data, options)?;
+        let mut out = Vec::with_capacity(digest.len() + 4);
+        write_varint(code, &mut out);
+        write_varint(digest.len() as u64, &mut out);
+        out.extend_from_slice(&digest);
+        Ok(out)
+    }
+
+    /// Verify data against an expected hash, under whichever algorithm it names.
+    ///
+    /// # Arguments
+    /// * `data` - Input JSON value to verify
+    /// * `expected` - A `HashEnvelope`, a `SemanticHash`, a serialized envelope, a tagged
+    ///   hash, a multibase multihash, or a bare digest under the options' algorithm
+    /// * `options` - Canonicalization options; an envelope overrides their version
+    ///
+    /// # Returns
+    /// true if hash matches, false otherwise; a HashingError if the hash names an
+    /// algorithm that is not registered
+    pub fn verify<'a>(
+        &self,
+        data: &Value,
+        expected: impl Into<ExpectedHash<'a>>,
+        options: &CanonicalizeOptions,
+    ) -> Result<bool> {
+        match expected.into() {
+            ExpectedHash::Envelope(envelope) => self.verify_envelope(data, envelope, options),
+            ExpectedHash::Hash(hash) => {
+                self.verify_bytes(data, options.hash_algorithm.identifier(), hash.as_bytes(), options)
+            }
+            ExpectedHash::Text(text) if text.trim_start().starts_with('{') => {
+                let envelope: HashEnvelope = serde_json::from_str(text)
+                    .map_err(|e| ConstitutionalError::HashingError(format!("Malformed hash envelope: {}", e)))?;
+                self.verify_envelope(data, &envelope, options)
+            }
+            ExpectedHash::Text(text) => self.verify_text(data, text, options),
+        }
+    }
+
+    /// Verify data against a binary multihash, under the algorithm its code names.
+    pub fn verify_multihash(&self, data: &Value, multihash: &[u8], options: &CanonicalizeOptions) -> Result<bool> {
+        let Some((code, digest)) = split_multihash(multihash) else {
+            return Ok(false);
+        };
+        let algorithm = self.get_by_code(code).ok_or_else(|| {
+            ConstitutionalError::HashingError(format!("No algorithm is registered for multihash code {:#x}", code))
+        })?;
+        Ok(digest.len() == algorithm.digest_len() && constant_time_eq(&digest_under(algorithm, data, options)?, digest))
+    }
+
+    fn verify_envelope(&self, data: &Value, envelope: &HashEnvelope, options: &CanonicalizeOptions) -> Result<bool> {
+        let options = options.clone().canon_version(envelope.canon_version);
+        self.verify_text(data, &format!("{}:{}", envelope.algorithm.identifier(), envelope.digest), &options)
+    }
+
+    fn verify_text(&self, data: &Value, text: &str, options: &CanonicalizeOptions) -> Result<bool> {
+        if let Some((identifier, encoded)) = text.split_once(':') {
+            let Some(expected) = decode_digest(encoded, self.lookup(identifier)?.digest_len()) else {
+                return Ok(false);
+            };
+            return self.verify_bytes(data, identifier, &expected, options);
+        }
+        let default = options.hash_algorithm;
+        if let Some(expected) = decode_digest(text, default.digest_len()) {
+            return self.verify_bytes(data, default.identifier(), &expected, options);
+        }
+        match decode_multibase(text) {
+            Some(multihash) => self.verify_multihash(data, &multihash, options),
+            None => Ok(false),
+        }
+    }
+
+    fn verify_bytes(&self, data: &Value, identifier: &str, expected: &[u8], options: &CanonicalizeOptions) -> Result<bool> {
+        Ok(constant_time_eq(&digest_under(self.lookup(identifier)?, data, options)?, expected))
+    }
+
+    fn lookup(&self, identifier: &str) -> Result<&dyn DigestAlgorithm> {
+        self.get(identifier).ok_or_else(|| {
+            ConstitutionalError::HashingError(format!("No algorithm is registered as {:?}", identifier))
+        })
+    }
+}
+
+/// The version tag and canonical form of data, hashed under `algorithm`.
+fn digest_under(algorithm: &dyn DigestAlgorithm, data: &Value, options: &CanonicalizeOptions) -> Result<Vec<u8>> {
+    let mut writer = StateWriter(algorithm.start());
+    writer.0.update(options.canon_version.tag().as_bytes());
+    canonicalize_to_writer(data, options, &mut writer)?;
+    Ok(writer.0.finalize())
+}
+
+/// Unsigned LEB128, as multiformats encodes codes and lengths.
+pub(crate) fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// A varint and the bytes after it; multiformats allows at most nine bytes.
+pub(crate) fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+/// The code and digest of a multihash, if its length prefix matches.
+fn split_multihash(multihash: &[u8]) -> Option<(u64, &[u8])> {
+    let (code, rest) = read_varint(multihash)?;
+    let (len, digest) = read_varint(rest)?;
+    (digest.len() as u64 == len).then_some((code, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic_hash, semantic_hash_envelope, Encoding, SemanticHash};
+    use serde_json::json;
+
+    /// FNV-1a, standing in for a deployment's own algorithm.
+    struct Fnv;
+
+    struct FnvState(u64);
+
+    impl DigestAlgorithm for Fnv {
+        fn identifier(&self) -> &str {
+            "fnv1a-64"
+        }
+
+        fn multihash_code(&self) -> Option<u64> {
+            Some(0x300000)
+        }
+
+        fn digest_len(&self) -> usize {
+            8
+        }
+
+        fn start(&self) -> Box<dyn DigestState> {
+            Box::new(FnvState(0xcbf2_9ce4_8422_2325))
+        }
+    }
+
+    impl DigestState for FnvState {
+        fn update(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+
+        fn finalize(self: Box<Self>) -> Vec<u8> {
+            self.0.to_be_bytes().to_vec()
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatches_builtins() {
+        let registry = AlgorithmRegistry::new();
+        let data = json!({"contract": "c-1", "terms": ["b", "a"]});
+        let options = CanonicalizeOptions::new();
+        let hash = semantic_hash(&data).unwrap();
+
+        assert!(registry.verify(&data, &hash, &options).unwrap());
+        assert!(registry.verify(&data, format!("sha256:{}", hash).as_str(), &options).unwrap());
+        assert!(registry.verify(&data, SemanticHash::of(&data).unwrap(), &options).unwrap());
+        let sha512 = options.clone().hash_algorithm(HashAlgorithm::Sha512).hash_encoding(Encoding::Base64Url);
+        let envelope = semantic_hash_envelope(&data, &sha512).unwrap();
+        assert!(registry.verify(&data, &envelope, &options).unwrap());
+        assert_eq!(registry.hash(&data, "sha256", &options).unwrap(), format!("sha256:{}", hash));
+
+        let multihash = registry.multihash(&data, "sha256", &options).unwrap();
+        assert_eq!(&multihash[..2], &[0x12, 0x20]);
+        assert!(registry.verify_multihash(&data, &multihash, &options).unwrap());
+        let text = Encoding::Base58Btc.encode_text(&multihash).unwrap();
+        assert!(registry.verify(&data, text.as_str(), &options).unwrap());
+        assert!(!registry.verify(&json!({"contract": "c-2"}), text.as_str(), &options).unwrap());
+        assert!(registry.verify(&data, "md5:00", &options).is_err());
+        assert!(!registry.verify(&data, "not a hash", &options).unwrap());
+    }
+
+    #[test]
+    fn test_registry_custom_algorithms() {
+        let mut registry = AlgorithmRegistry::empty();
+        let data = json!({"id": "obj-1"});
+        let options = CanonicalizeOptions::new();
+        assert!(registry.verify(&data, semantic_hash(&data).unwrap().as_str(), &options).is_err());
+        registry.register(Fnv).unwrap();
+        assert!(registry.register(Fnv).is_err());
+
+        let tagged = registry.hash(&data, "fnv1a-64", &options).unwrap();
+        assert!(tagged.starts_with("fnv1a-64:") && tagged.len() == "fnv1a-64:".len() + 16);
+        assert!(registry.verify(&data, tagged.as_str(), &options).unwrap());
+        assert!(!registry.verify(&json!({"id": "obj-2"}), tagged.as_str(), &options).unwrap());
+
+        let multihash = registry.multihash(&data, "fnv1a-64", &options).unwrap();
+        assert_eq!(&multihash[..4], &[0x80, 0x80, 0xc0, 0x01]);
+        assert!(registry.verify_multihash(&data, &multihash, &options).unwrap());
+        assert!(!registry.verify_multihash(&data, &multihash[..multihash.len() - 1], &options).unwrap());
+    }
+}
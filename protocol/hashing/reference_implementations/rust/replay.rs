@@ -0,0 +1,270 @@
+/// replay.rs - Replay protection for signed objects
+///
+/// A signature stays valid forever, so a signed contract submitted twice verifies twice.
+/// A `ReplayGuard` accepts each signed object at most once, by fields of its payload,
+/// which the signature covers:
+///
+/// - Sequence mode: `nonce` is an unsigned integer that must exceed the last one
+///   accepted from the same signer.
+/// - Window mode: `timestamp` is an RFC 3339 date-time within the window of now, either
+///   way to allow for clock skew, and `nonce` is a string not seen from the signer
+///   within the window. Nonces older than the window are forgotten, since the timestamp
+///   check rejects their objects anyway.
+///
+/// What has been seen is kept by a `NonceStore`, in memory by default; a ledger shares
+/// one store between its verifiers so a replay is caught by whichever gets it. Signers
+/// are keyed by their canonical key, so an uppercase-hex or `did:key` spelling of a key
+/// shares its nonces.
+
+use crate::timestamp::unix_seconds;
+use crate::{
+    verify_signed_with, CanonicalizeOptions, ConstitutionalError, Did, DidResolver, KeyResolver, PublicKey, Result,
+    SignedObject,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Record of the nonces each signer has used. A signer is named by the text form of its
+/// public key, or by its DID if that is of a method other than `did:key`.
+pub trait NonceStore: Send + Sync {
+    /// Record a signer's nonce, stamped with the object's timestamp in Unix seconds.
+    ///
+    /// # Returns
+    /// true if the nonce is new, false if the signer used it before
+    fn insert(&self, signer: &str, nonce: &str, timestamp: i64) -> Result<bool>;
+
+    /// Record a signer's sequence number if it exceeds every earlier one, atomically.
+    ///
+    /// # Returns
+    /// true if it did and was recorded, false otherwise
+    fn advance(&self, signer: &str, sequence: u64) -> Result<bool>;
+
+    /// Forget nonces stamped before a Unix time. Keeping them is never wrong.
+    fn prune(&self, _before: i64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A `NonceStore` in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    state: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    /// Timestamp of each signer's nonce
+    nonces: HashMap<(String, String), i64>,
+    /// Each signer's highest sequence number
+    sequences: HashMap<String, u64>,
+}
+
+impl MemoryNonceStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn insert(&self, signer: &str, nonce: &str, timestamp: i64) -> Result<bool> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = (signer.to_string(), nonce.to_string());
+        if state.nonces.contains_key(&key) {
+            return Ok(false);
+        }
+        state.nonces.insert(key, timestamp);
+        Ok(true)
+    }
+
+    fn advance(&self, signer: &str, sequence: u64) -> Result<bool> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match state.sequences.get(signer) {
+            Some(last) if *last >= sequence => Ok(false),
+            _ => {
+                state.sequences.insert(signer.to_string(), sequence);
+                Ok(true)
+            }
+        }
+    }
+
+    fn prune(&self, before: i64) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.nonces.retain(|_, timestamp| *timestamp >= before);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Sequence,
+    Window(Duration),
+}
+
+/// Accepts each signed object once.
+#[derive(Clone)]
+pub struct ReplayGuard {
+    store: Arc<dyn NonceStore>,
+    mode: Mode,
+}
+
+impl fmt::Debug for ReplayGuard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReplayGuard").field("mode", &self.mode).finish_non_exhaustive()
+    }
+}
+
+impl ReplayGuard {
+    /// A guard requiring each signer's `nonce` to increase.
+    pub fn sequence(store: Arc<dyn NonceStore>) -> Self {
+        ReplayGuard { store, mode: Mode::Sequence }
+    }
+
+    /// A guard requiring a `timestamp` within `window` of now and a `nonce` unused
+    /// within it.
+    pub fn window(store: Arc<dyn NonceStore>, window: Duration) -> Self {
+        ReplayGuard { store, mode: Mode::Window(window) }
+    }
+
+    /// Accept a signed object unless it was seen before or is out of its window, by the
+    /// system clock. The signature is not checked; `verify_signed_once` does both.
+    ///
+    /// # Returns
+    /// true if the object is accepted, and is then rejected if presented again; false
+    /// if it is a replay or stale; a ProtocolError if the payload lacks the fields the
+    /// guard needs
+    pub fn check(&self, signed: &SignedObject) -> Result<bool> {
+        self.check_at(signed, SystemTime::now())
+    }
+
+    /// `check` at a given time.
+    pub fn check_at(&self, signed: &SignedObject, now: SystemTime) -> Result<bool> {
+        let field = |name| signed.payload.as_object().and_then(|payload| payload.get(name));
+        let missing = |what: &str| ConstitutionalError::ProtocolError(format!("The payload has no {}", what));
+        let signer = canonical_signer(&signed.signer)?;
+        match self.mode {
+            Mode::Sequence => {
+                let sequence = field("nonce").and_then(Value::as_u64).ok_or_else(|| missing("integer nonce"))?;
+                self.store.advance(&signer, sequence)
+            }
+            Mode::Window(window) => {
+                let nonce = field("nonce").and_then(Value::as_str).ok_or_else(|| missing("string nonce"))?;
+                let timestamp = field("timestamp")
+                    .and_then(Value::as_str)
+                    .and_then(unix_seconds)
+                    .ok_or_else(|| missing("RFC 3339 timestamp"))?;
+                let now = match now.duration_since(UNIX_EPOCH) {
+                    Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+                    Err(before) => -i64::try_from(before.duration().as_secs()).unwrap_or(i64::MAX),
+                };
+                let window = i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+                if timestamp.abs_diff(now) > window.unsigned_abs() {
+                    return Ok(false);
+                }
+                self.store.prune(now.saturating_sub(window))?;
+                self.store.insert(&signer, nonce, timestamp)
+            }
+        }
+    }
+}
+
+/// The name a signer's nonces are kept under: its public key in canonical text form,
+/// resolving a `did:key`, or the DID itself for other methods.
+fn canonical_signer(signer: &str) -> Result<String> {
+    if !signer.starts_with("did:") {
+        return Ok(signer.parse::<PublicKey>()?.to_string());
+    }
+    let did: Did = signer.parse()?;
+    if did.method() != KeyResolver.method() {
+        return Ok(did.to_string());
+    }
+    match KeyResolver.resolve(&did)?.as_slice() {
+        [key] => Ok(key.to_string()),
+        _ => Err(ConstitutionalError::ProtocolError(format!("Not a single-key did:key: {}", did))),
+    }
+}
+
+/// Check a signed object and accept it once. The signature is checked first, so a
+/// forged object cannot use up a signer's nonce.
+///
+/// # Returns
+/// true if the signature verifies and the object is not a replay, false otherwise; an
+/// error if it is malformed or lacks the fields the guard needs
+pub fn verify_signed_once(signed: &SignedObject, options: &CanonicalizeOptions, guard: &ReplayGuard) -> Result<bool> {
+    Ok(verify_signed_with(signed, options)? && guard.check(signed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+    use serde_json::json;
+
+    fn signed(nonce: Value, timestamp: &str) -> SignedObject {
+        let contract = json!({"contract_id": "c-17", "nonce": nonce, "timestamp": timestamp});
+        sign(&contract, &Keypair::from_secret(&[7; 32])).unwrap()
+    }
+
+    fn at(text: &str) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(unix_seconds(text).unwrap() as u64)
+    }
+
+    #[test]
+    fn test_sequence_nonces_increase() {
+        let guard = ReplayGuard::sequence(Arc::new(MemoryNonceStore::new()));
+        let options = CanonicalizeOptions::new();
+        let first = signed(json!(1), "2025-11-20T12:00:00Z");
+        assert!(verify_signed_once(&first, &options, &guard).unwrap());
+        assert!(!verify_signed_once(&first, &options, &guard).unwrap());
+        assert!(verify_signed_once(&signed(json!(5), "2025-11-20T12:00:00Z"), &options, &guard).unwrap());
+        assert!(!guard.check(&signed(json!(4), "2025-11-20T12:00:00Z")).unwrap());
+
+        // Another signer's sequence is its own
+        let other = sign(&json!({"nonce": 1}), &Keypair::from_secret(&[8; 32])).unwrap();
+        assert!(guard.check(&other).unwrap());
+        // A forgery does not advance the sequence
+        let mut forged = signed(json!(9), "2025-11-20T12:00:00Z");
+        forged.signature = first.signature.clone();
+        assert!(!verify_signed_once(&forged, &options, &guard).unwrap());
+        assert!(guard.check(&signed(json!(9), "2025-11-20T12:00:00Z")).unwrap());
+        assert!(guard.check(&signed(json!("9"), "2025-11-20T12:00:00Z")).is_err());
+    }
+
+    #[test]
+    fn test_signer_spellings_share_nonces() {
+        let guard = ReplayGuard::sequence(Arc::new(MemoryNonceStore::new()));
+        let first = signed(json!(3), "2025-11-20T12:00:00Z");
+        assert!(guard.check(&first).unwrap());
+
+        // The same key in uppercase hex or as a did:key is the same signer
+        let key: PublicKey = first.signer.parse().unwrap();
+        let upper =
+            SignedObject { signer: first.signer.to_uppercase().replacen("ED25519", "ed25519", 1), ..first.clone() };
+        assert!(!guard.check(&upper).unwrap());
+        let did = SignedObject { signer: Did::key(&key).to_string(), ..first.clone() };
+        assert!(!guard.check(&did).unwrap());
+        assert!(guard.check(&SignedObject { signer: "ed25519:zz".into(), ..first }).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_window() {
+        let store = Arc::new(MemoryNonceStore::new());
+        let guard = ReplayGuard::window(store.clone(), Duration::from_secs(300));
+        let now = at("2025-11-20T12:00:00Z");
+        let fresh = signed(json!("a1"), "2025-11-20T11:58:00Z");
+        assert!(guard.check_at(&fresh, now).unwrap());
+        assert!(!guard.check_at(&fresh, now).unwrap());
+        // Slightly ahead of the verifier's clock is fine, too far either way is not
+        assert!(guard.check_at(&signed(json!("a2"), "2025-11-20T12:04:00+00:00"), now).unwrap());
+        assert!(!guard.check_at(&signed(json!("a3"), "2025-11-20T11:54:59Z"), now).unwrap());
+        assert!(!guard.check_at(&signed(json!("a4"), "2025-11-20T12:05:01Z"), now).unwrap());
+        // Nonces out of the window are forgotten, their objects being stale by then
+        assert!(!guard.check_at(&fresh, at("2025-11-20T12:10:00Z")).unwrap());
+        assert!(guard.check_at(&signed(json!("b1"), "2025-11-20T12:09:00Z"), at("2025-11-20T12:10:00Z")).unwrap());
+        assert_eq!(store.state.lock().unwrap().nonces.len(), 1);
+        assert!(guard.check_at(&signed(json!("a5"), "noon"), now).is_err());
+    }
+}
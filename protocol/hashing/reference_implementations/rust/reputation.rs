@@ -0,0 +1,353 @@
+/// reputation.rs - Agents' reputations from the objects on a ledger
+///
+/// Replaying a ledger, its protocol objects in order, gives each agent the outcomes of
+/// what it did:
+///
+/// - a contract the agent proposed is `Ratified` when its voters' latest votes approve
+///   more than reject, `Rejected` when they reject more than approve, and `Invalidated`
+///   when a ruling invalidates it;
+/// - a challenge the agent raised is `ChallengeUpheld` when the ruling resolving it
+///   invalidates the contract, and `ChallengeDismissed` when it upholds it.
+///
+/// Rulings and challenges about contracts that are not on the ledger are passed over.
+/// `ScoringRule`s turn outcomes into points, `FixedPoints` by default, and a
+/// `ReputationScorer` adds up every rule's points per agent into a
+/// `ReputationSnapshot`:
+///
+/// ```json
+/// {"height":42,"rules":["fixed_points"],"scores":{"claude":3,"gemini":-1,"grok":2}}
+/// ```
+///
+/// Scores are integers, summed in ledger order, so every node replaying the same ledger
+/// under the same rules gets the same snapshot and hash. `weight` turns a score into a
+/// weight for `ConfidenceAggregator` or similar weighting.
+
+use crate::{Canonicalize, ConstitutionalObject, RulingOutcome, VoteChoice};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// What came of something an agent did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReputationOutcome {
+    /// A contract the agent proposed was approved by its voters.
+    Ratified,
+    /// A contract the agent proposed was rejected by its voters.
+    Rejected,
+    /// A contract the agent proposed was invalidated by a ruling.
+    Invalidated,
+    /// A challenge the agent raised was upheld.
+    ChallengeUpheld,
+    /// A challenge the agent raised was dismissed.
+    ChallengeDismissed,
+}
+
+impl ReputationOutcome {
+    /// The outcome as written, e.g. `challenge_upheld`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReputationOutcome::Ratified => "ratified",
+            ReputationOutcome::Rejected => "rejected",
+            ReputationOutcome::Invalidated => "invalidated",
+            ReputationOutcome::ChallengeUpheld => "challenge_upheld",
+            ReputationOutcome::ChallengeDismissed => "challenge_dismissed",
+        }
+    }
+}
+
+impl fmt::Display for ReputationOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An outcome for an agent, about a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReputationEvent {
+    /// The agent the outcome counts for or against
+    pub agent: String,
+    /// What came of it
+    pub outcome: ReputationOutcome,
+    /// The contract it concerns
+    pub contract_id: String,
+}
+
+/// The outcomes on a ledger: rulings' in ledger order, then votes' in the order the
+/// contracts appear.
+pub fn reputation_events(ledger: &[ConstitutionalObject]) -> Vec<ReputationEvent> {
+    let mut proposers: HashMap<&str, &str> = HashMap::new();
+    let mut contracts = Vec::new();
+    let mut challengers: HashMap<&str, &str> = HashMap::new();
+    let mut votes: HashMap<&str, BTreeMap<&str, VoteChoice>> = HashMap::new();
+    let mut events = Vec::new();
+    let event = |agent: &str, outcome, contract_id: &str| ReputationEvent {
+        agent: agent.to_string(),
+        outcome,
+        contract_id: contract_id.to_string(),
+    };
+    for object in ledger {
+        match object {
+            ConstitutionalObject::Contract(contract)
+                if proposers.insert(&contract.id, &contract.proposer_agent).is_none() =>
+            {
+                contracts.push(contract.id.as_str());
+            }
+            ConstitutionalObject::Challenge(challenge) => {
+                challengers.insert(&challenge.id, &challenge.challenger_agent);
+            }
+            ConstitutionalObject::Vote(vote) => {
+                votes.entry(&vote.contract_id).or_default().insert(&vote.voter_agent, vote.choice);
+            }
+            ConstitutionalObject::Ruling(ruling) => {
+                let Some(proposer) = proposers.get(ruling.contract_id.as_str()) else {
+                    continue;
+                };
+                let challenger = ruling.challenge_id.as_deref().and_then(|id| challengers.get(id));
+                match ruling.outcome {
+                    RulingOutcome::Invalidated => {
+                        events.push(event(proposer, ReputationOutcome::Invalidated, &ruling.contract_id));
+                        if let Some(challenger) = challenger {
+                            events.push(event(challenger, ReputationOutcome::ChallengeUpheld, &ruling.contract_id));
+                        }
+                    }
+                    RulingOutcome::Upheld => {
+                        if let Some(challenger) = challenger {
+                            events.push(event(challenger, ReputationOutcome::ChallengeDismissed, &ruling.contract_id));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for contract_id in contracts {
+        let Some(choices) = votes.get(contract_id) else {
+            continue;
+        };
+        let count = |choice| choices.values().filter(|c| **c == choice).count();
+        let (approve, reject) = (count(VoteChoice::Approve), count(VoteChoice::Reject));
+        if approve != reject {
+            let outcome = if approve > reject { ReputationOutcome::Ratified } else { ReputationOutcome::Rejected };
+            events.push(event(proposers[contract_id], outcome, contract_id));
+        }
+    }
+    events
+}
+
+/// Turns outcomes into points.
+pub trait ScoringRule: Send + Sync {
+    /// The rule's name, recorded in snapshots.
+    fn name(&self) -> &str;
+
+    /// Points an outcome earns its agent; negative for a penalty.
+    fn points(&self, event: &ReputationEvent) -> i64;
+}
+
+/// A fixed number of points per outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedPoints {
+    points: BTreeMap<ReputationOutcome, i64>,
+}
+
+impl Default for FixedPoints {
+    /// +1 for a ratified contract and -1 for a rejected one, -3 for an invalidated
+    /// contract, +2 for an upheld challenge and -1 for a dismissed one.
+    fn default() -> Self {
+        let points = [
+            (ReputationOutcome::Ratified, 1),
+            (ReputationOutcome::Rejected, -1),
+            (ReputationOutcome::Invalidated, -3),
+            (ReputationOutcome::ChallengeUpheld, 2),
+            (ReputationOutcome::ChallengeDismissed, -1),
+        ];
+        FixedPoints { points: points.into_iter().collect() }
+    }
+}
+
+impl FixedPoints {
+    /// Set the points an outcome earns.
+    pub fn points(mut self, outcome: ReputationOutcome, points: i64) -> Self {
+        self.points.insert(outcome, points);
+        self
+    }
+}
+
+impl ScoringRule for FixedPoints {
+    fn name(&self) -> &str {
+        "fixed_points"
+    }
+
+    fn points(&self, event: &ReputationEvent) -> i64 {
+        self.points.get(&event.outcome).copied().unwrap_or(0)
+    }
+}
+
+/// Scores agents by a set of rules.
+#[derive(Clone)]
+pub struct ReputationScorer {
+    rules: Vec<Arc<dyn ScoringRule>>,
+}
+
+impl fmt::Debug for ReputationScorer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.rules.iter().map(|rule| rule.name()).collect();
+        f.debug_struct("ReputationScorer").field("rules", &names).finish()
+    }
+}
+
+impl Default for ReputationScorer {
+    /// `FixedPoints` with its default points.
+    fn default() -> Self {
+        ReputationScorer::new().rule(FixedPoints::default())
+    }
+}
+
+impl ReputationScorer {
+    /// A scorer with no rules yet.
+    pub fn new() -> Self {
+        ReputationScorer { rules: Vec::new() }
+    }
+
+    /// Add a rule; each outcome earns the points of every rule.
+    pub fn rule(mut self, rule: impl ScoringRule + 'static) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Replay a ledger and score its agents.
+    ///
+    /// # Returns
+    /// The snapshot with every agent that had an outcome, scores saturating at the
+    /// bounds of an i64
+    pub fn score(&self, ledger: &[ConstitutionalObject]) -> ReputationSnapshot {
+        let mut scores: BTreeMap<String, i64> = BTreeMap::new();
+        for event in reputation_events(ledger) {
+            let score = scores.entry(event.agent.clone()).or_default();
+            for rule in &self.rules {
+                *score = score.saturating_add(rule.points(&event));
+            }
+        }
+        ReputationSnapshot {
+            height: ledger.len() as u64,
+            rules: self.rules.iter().map(|rule| rule.name().to_string()).collect(),
+            scores,
+        }
+    }
+}
+
+/// Agents' scores after replaying a ledger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReputationSnapshot {
+    /// Number of ledger entries replayed
+    pub height: u64,
+    /// Names of the rules scored by, in order
+    pub rules: Vec<String>,
+    /// Each agent's score
+    pub scores: BTreeMap<String, i64>,
+}
+
+impl ReputationSnapshot {
+    /// An agent's score; 0 for an agent with no outcomes.
+    pub fn score(&self, agent: &str) -> i64 {
+        self.scores.get(agent).copied().unwrap_or(0)
+    }
+
+    /// An agent's weight: `base` plus its score, never below 0.
+    pub fn weight(&self, agent: &str, base: u32) -> u32 {
+        let weight = i64::from(base).saturating_add(self.score(agent));
+        weight.clamp(0, i64::from(u32::MAX)) as u32
+    }
+
+    /// The snapshot's JSON object.
+    pub fn to_value(&self) -> Value {
+        json!({"height": self.height, "rules": self.rules, "scores": self.scores})
+    }
+}
+
+impl Canonicalize for ReputationSnapshot {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl Serialize for ReputationSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> ConstitutionalObject {
+        ConstitutionalObject::from_value(value).unwrap()
+    }
+
+    fn contract(id: &str, proposer: &str) -> ConstitutionalObject {
+        object(json!({
+            "object_type": "Contract", "id": id, "proposer_agent": proposer, "action_type": "amend", "action": {},
+            "evidence": [], "reasoning": {"rationale": "Needed", "confidence": 0.9}, "timestamp": "2025-11-20T14:30:00Z"
+        }))
+    }
+
+    fn vote(contract_id: &str, voter: &str, choice: &str) -> ConstitutionalObject {
+        object(json!({
+            "object_type": "Vote", "contract_id": contract_id, "voter_agent": voter, "vote": choice,
+            "timestamp": "2025-11-21T09:00:00Z"
+        }))
+    }
+
+    fn ledger() -> Vec<ConstitutionalObject> {
+        vec![
+            contract("c-1", "claude"),
+            contract("c-2", "gemini"),
+            vote("c-1", "gemini", "approve"),
+            vote("c-1", "grok", "reject"),
+            vote("c-1", "grok", "approve"),
+            vote("c-2", "claude", "reject"),
+            object(json!({
+                "object_type": "Challenge", "id": "ch-1", "contract_id": "c-2", "contract_hash": "a3f5",
+                "challenger_agent": "grok", "grounds": "hash_mismatch", "evidence": [],
+                "reasoning": {"rationale": "Tampered", "confidence": 0.9}, "timestamp": "2025-11-22T10:00:00Z"
+            })),
+            object(json!({
+                "object_type": "Ruling", "id": "r-1", "contract_id": "c-2", "challenge_id": "ch-1",
+                "arbiter_agent": "deepseek", "outcome": "invalidated",
+                "reasoning": {"rationale": "Tampered", "confidence": 0.95}, "timestamp": "2025-11-28T00:00:00Z"
+            })),
+        ]
+    }
+
+    #[test]
+    fn test_outcomes_from_ledger() {
+        let outcomes: Vec<(String, ReputationOutcome)> =
+            reputation_events(&ledger()).into_iter().map(|event| (event.agent, event.outcome)).collect();
+        let expected = [
+            ("gemini", ReputationOutcome::Invalidated),
+            ("grok", ReputationOutcome::ChallengeUpheld),
+            ("claude", ReputationOutcome::Ratified),
+            ("gemini", ReputationOutcome::Rejected),
+        ];
+        assert_eq!(outcomes, expected.map(|(agent, outcome)| (agent.to_string(), outcome)));
+    }
+
+    #[test]
+    fn test_snapshot_scores() {
+        let snapshot = ReputationScorer::default().score(&ledger());
+        assert_eq!(snapshot.to_value(), json!({
+            "height": 8, "rules": ["fixed_points"], "scores": {"claude": 1, "gemini": -4, "grok": 2}
+        }));
+        assert_eq!(snapshot.weight("gemini", 1), 0);
+        assert_eq!(snapshot.weight("grok", 1), 3);
+        assert_eq!(snapshot.weight("deepseek", 1), 1);
+        let again = ReputationScorer::default().score(&ledger());
+        assert_eq!(again.semantic_hash().unwrap(), snapshot.semantic_hash().unwrap());
+
+        let harsh = ReputationScorer::new().rule(FixedPoints::default().points(ReputationOutcome::Rejected, -10));
+        assert_eq!(harsh.score(&ledger()).score("gemini"), -13);
+    }
+}
@@ -0,0 +1,300 @@
+/// resolve.rs - Fetching cited evidence and checking it against its pointer
+///
+/// An `EvidenceResolver` fetches the bytes an evidence pointer names. Built in are a
+/// `DirectoryResolver` for a content-addressed store on disk, a `MemoryResolver` for an
+/// archive store or cache held in memory, and an `HttpResolver` for URLs, which takes
+/// the HTTP client as a function so the crate does not pick one. `EvidenceResolvers`
+/// asks each resolver in the order they were added and uses the first that handles the
+/// pointer.
+///
+/// Pointers that embed a hash say what the fetched content must be: the bytes of a
+/// `sha256:<hex>` blob must have that digest, and the object behind an
+/// `ocp:<algorithm>:<hex>` content ID must have that content ID. Content fetched for
+/// other kinds of pointer can only be checked to exist.
+
+use crate::algorithm::{hex, Hasher};
+use crate::{content_id, CanonicalizeOptions, ConstitutionalError, EvidencePointer, HashAlgorithm, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Fetches the content behind some kinds of evidence pointer.
+pub trait EvidenceResolver: Send + Sync {
+    /// Whether this resolver fetches the pointer.
+    fn handles(&self, pointer: &EvidencePointer) -> bool;
+
+    /// The bytes the pointer names.
+    ///
+    /// # Returns
+    /// The bytes, or an error if they cannot be fetched
+    fn fetch(&self, pointer: &EvidencePointer) -> Result<Vec<u8>>;
+}
+
+/// Resolver of digests, content IDs and archive entries from a directory: the content
+/// with a digest or content ID under `<root>/<algorithm>/<hex>`, archive entries under
+/// `<root>/archive/<entry>`.
+#[derive(Debug, Clone)]
+pub struct DirectoryResolver {
+    root: PathBuf,
+}
+
+impl DirectoryResolver {
+    /// A resolver reading from a directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectoryResolver { root: root.into() }
+    }
+
+    /// Where the content a pointer names is kept, if this resolver keeps it.
+    pub fn path(&self, pointer: &EvidencePointer) -> Option<PathBuf> {
+        match pointer {
+            EvidencePointer::Digest { algorithm, digest } | EvidencePointer::ContentId { algorithm, digest } => {
+                Some(self.root.join(algorithm).join(digest))
+            }
+            // A leading dot would let `..` climb out of the directory
+            EvidencePointer::Archive(entry) if !entry.starts_with('.') => Some(self.root.join("archive").join(entry)),
+            _ => None,
+        }
+    }
+}
+
+impl EvidenceResolver for DirectoryResolver {
+    fn handles(&self, pointer: &EvidencePointer) -> bool {
+        self.path(pointer).is_some()
+    }
+
+    fn fetch(&self, pointer: &EvidencePointer) -> Result<Vec<u8>> {
+        let path = self
+            .path(pointer)
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("{} is not kept in a directory", pointer)))?;
+        Ok(std::fs::read(path)?)
+    }
+}
+
+/// Resolver of evidence held in memory, by pointer, such as an archive store's entries.
+#[derive(Debug, Default)]
+pub struct MemoryResolver {
+    content: RwLock<HashMap<EvidencePointer, Vec<u8>>>,
+}
+
+impl MemoryResolver {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep content under a pointer, replacing any kept before.
+    pub fn insert(&self, pointer: EvidencePointer, bytes: Vec<u8>) {
+        self.content.write().unwrap_or_else(|e| e.into_inner()).insert(pointer, bytes);
+    }
+}
+
+impl EvidenceResolver for MemoryResolver {
+    fn handles(&self, pointer: &EvidencePointer) -> bool {
+        self.content.read().unwrap_or_else(|e| e.into_inner()).contains_key(pointer)
+    }
+
+    fn fetch(&self, pointer: &EvidencePointer) -> Result<Vec<u8>> {
+        self.content
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(pointer)
+            .cloned()
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("No evidence kept at {}", pointer)))
+    }
+}
+
+/// Fetches a URL's body.
+type Fetch = dyn Fn(&str) -> Result<Vec<u8>> + Send + Sync;
+
+/// Resolver of `http` and `https` URLs through a client the caller supplies.
+#[derive(Clone)]
+pub struct HttpResolver {
+    fetch: Arc<Fetch>,
+}
+
+impl fmt::Debug for HttpResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpResolver").finish_non_exhaustive()
+    }
+}
+
+impl HttpResolver {
+    /// A resolver that fetches each URL with `fetch`, which returns the body of a
+    /// successful response and an error otherwise.
+    pub fn new(fetch: impl Fn(&str) -> Result<Vec<u8>> + Send + Sync + 'static) -> Self {
+        HttpResolver { fetch: Arc::new(fetch) }
+    }
+}
+
+impl EvidenceResolver for HttpResolver {
+    fn handles(&self, pointer: &EvidencePointer) -> bool {
+        matches!(pointer, EvidencePointer::Url(_))
+    }
+
+    fn fetch(&self, pointer: &EvidencePointer) -> Result<Vec<u8>> {
+        match pointer {
+            EvidencePointer::Url(url) => (self.fetch)(url),
+            _ => Err(ConstitutionalError::ProtocolError(format!("{} is not a URL", pointer))),
+        }
+    }
+}
+
+/// Evidence resolvers, asked in the order they were added.
+#[derive(Clone, Default)]
+pub struct EvidenceResolvers {
+    resolvers: Vec<Arc<dyn EvidenceResolver>>,
+}
+
+impl fmt::Debug for EvidenceResolvers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EvidenceResolvers").field("resolvers", &self.resolvers.len()).finish()
+    }
+}
+
+impl EvidenceResolvers {
+    /// No resolvers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a resolver, asked after those added before it.
+    pub fn register(&mut self, resolver: impl EvidenceResolver + 'static) {
+        self.resolvers.push(Arc::new(resolver));
+    }
+
+    /// The bytes a pointer names, from the first resolver that handles it.
+    ///
+    /// # Returns
+    /// The bytes, or a ProtocolError if no resolver handles the pointer; the resolver's
+    /// error if fetching fails
+    pub fn fetch(&self, pointer: &EvidencePointer) -> Result<Vec<u8>> {
+        let resolver = self
+            .resolvers
+            .iter()
+            .find(|resolver| resolver.handles(pointer))
+            .ok_or_else(|| ConstitutionalError::ProtocolError(format!("No resolver for evidence {}", pointer)))?;
+        resolver.fetch(pointer)
+    }
+
+    /// Fetch the evidence a pointer names and check it against the hash the pointer
+    /// embeds, if any.
+    ///
+    /// # Returns
+    /// true if the content was fetched and matches, false if it does not match; an
+    /// error if it cannot be fetched, a HashingError if the pointer names an unknown
+    /// algorithm
+    pub fn verify(&self, pointer: &EvidencePointer) -> Result<bool> {
+        let bytes = self.fetch(pointer)?;
+        content_matches(pointer, &bytes)
+    }
+}
+
+/// Whether fetched content is what its pointer names.
+fn content_matches(pointer: &EvidencePointer, bytes: &[u8]) -> Result<bool> {
+    match pointer {
+        EvidencePointer::Digest { algorithm, digest } => {
+            let mut hasher = Hasher::new(algorithm.parse()?);
+            hasher.update(bytes);
+            Ok(hex(&hasher.finalize()) == *digest)
+        }
+        EvidencePointer::ContentId { algorithm, .. } => {
+            let algorithm: HashAlgorithm = algorithm.parse()?;
+            let Ok(object) = serde_json::from_slice::<Value>(bytes) else {
+                return Ok(false);
+            };
+            let options = CanonicalizeOptions::new().hash_algorithm(algorithm);
+            Ok(content_id(&object, &options)? == pointer.to_string())
+        }
+        _ => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::builder;
+    use crate::{Evidence, ViolationKind};
+    use serde_json::json;
+
+    fn pointer(text: &str) -> EvidencePointer {
+        text.parse().unwrap()
+    }
+
+    fn digest_of(bytes: &[u8]) -> EvidencePointer {
+        let mut hasher = Hasher::new(HashAlgorithm::Sha256);
+        hasher.update(bytes);
+        pointer(&format!("sha256:{}", hex(&hasher.finalize())))
+    }
+
+    #[test]
+    fn test_resolvers_check_embedded_hashes() {
+        let root = std::env::temp_dir().join(format!("ocp-evidence-{}", std::process::id()));
+        let report = b"Quarterly audit of the archive";
+        let blob = digest_of(report);
+        let path = DirectoryResolver::new(&root).path(&blob).unwrap();
+        assert_eq!(path.parent().unwrap(), root.join("sha256"));
+        std::fs::create_dir_all(root.join("sha256")).unwrap();
+        std::fs::write(&path, report).unwrap();
+
+        let vote = json!({"voter_agent": "Gemini", "vote": "approve"});
+        let vote_id = pointer(&content_id(&vote, &CanonicalizeOptions::new()).unwrap());
+        let archive = MemoryResolver::new();
+        archive.insert(vote_id.clone(), serde_json::to_vec(&vote).unwrap());
+        archive.insert(pointer("archive://0000001"), b"{}".to_vec());
+
+        let mut resolvers = EvidenceResolvers::new();
+        resolvers.register(archive);
+        resolvers.register(DirectoryResolver::new(&root));
+        resolvers.register(HttpResolver::new(|url| match url {
+            "https://example.org/report" => Ok(b"report".to_vec()),
+            _ => Err(ConstitutionalError::ProtocolError(format!("404 {}", url))),
+        }));
+
+        assert!(resolvers.verify(&blob).unwrap());
+        assert!(resolvers.verify(&vote_id).unwrap());
+        assert!(resolvers.verify(&pointer("archive://0000001")).unwrap());
+        assert!(resolvers.verify(&pointer("https://example.org/report")).unwrap());
+
+        std::fs::write(&path, b"Quarterly audit, edited").unwrap();
+        assert!(!resolvers.verify(&blob).unwrap());
+        assert!(resolvers.verify(&digest_of(b"never stored")).is_err());
+        assert!(resolvers.verify(&pointer("https://example.org/missing")).is_err());
+        assert!(resolvers.verify(&pointer("Article-III.1")).is_err());
+        assert!(DirectoryResolver::new(&root).path(&pointer("archive://..")).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_contract_verifies_its_evidence() {
+        let memory = MemoryResolver::new();
+        let kept = digest_of(b"minutes of the session");
+        memory.insert(kept.clone(), b"minutes of the session".to_vec());
+        memory.insert(digest_of(b"original"), b"tampered".to_vec());
+        let mut resolvers = EvidenceResolvers::new();
+        resolvers.register(memory);
+
+        let contract = |pointers: &[String]| {
+            let mut builder = builder();
+            for pointer in pointers {
+                builder = builder.evidence(Evidence::new("archive_reference", pointer));
+            }
+            builder.build().unwrap()
+        };
+        assert_eq!(contract(&[kept.to_string()]).verify_evidence(&resolvers), []);
+
+        let cited = [
+            kept.to_string(),
+            digest_of(b"original").to_string(),
+            digest_of(b"lost").to_string(),
+            "the minutes".to_string(),
+        ];
+        let violations = contract(&cited).verify_evidence(&resolvers);
+        let pointers: Vec<String> = violations.iter().map(|v| v.pointer.to_string()).collect();
+        assert_eq!(pointers, ["/evidence/1/pointer", "/evidence/2/pointer", "/evidence/3/pointer"]);
+        assert_eq!(violations[0].kind, ViolationKind::EvidenceMismatch(cited[1].clone()));
+        assert!(matches!(violations[1].kind, ViolationKind::EvidenceUnavailable(_)));
+        assert_eq!(violations[2].kind, ViolationKind::InvalidEvidencePointer("the minutes".to_string()));
+    }
+}
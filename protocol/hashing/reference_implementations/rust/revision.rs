@@ -0,0 +1,478 @@
+/// revision.rs - Applying an amendment to produce the next constitution revision
+///
+/// Each applied amendment turns one revision of the constitution into the next. What it
+/// changed is recorded as three hashes: the state root before, the state root after and
+/// the amendment's semantic hash.
+///
+/// ```json
+/// {"amendment_id":"8e5d...","amendment_hash":"5c1d...","prev_root":"a3f5...","new_root":"9b2e..."}
+/// ```
+///
+/// Applying is deterministic: the same amendment on the same revision gives the same
+/// next revision on every node. So anyone holding the previous revision and the
+/// amendment can check a record by applying it again.
+///
+/// `changelog` compares any two revisions, however many amendments apart, article by
+/// article: which were added, modified or repealed, each with its semantic hash before
+/// and after, between the two state roots.
+///
+/// ```json
+/// {"from":"a3f5...","to":"9b2e...",
+///  "changes":[{"article_id":"III","change":"modified","old_hash":"5c1d...","new_hash":"e1f0..."}]}
+/// ```
+///
+/// A `RevisionHistory` keeps every revision from the first, each identified by its
+/// number, counting the amendments applied since the first, and its state root. A
+/// contract names the revision it was evaluated against in its `constitution_revision`
+/// member, which the history can check:
+///
+/// ```json
+/// {"constitution_revision":{"number":3,"state_root":"9b2e..."}}
+/// ```
+
+use crate::objects::{object, take};
+use crate::{
+    Amendment, Canonicalize, CanonicalizeOptions, Constitution, ConstitutionalError, Contract, Result, SemanticHash,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What applying an amendment did to the constitution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmendmentRecord {
+    /// The amendment applied
+    pub amendment_id: String,
+    /// The amendment's semantic hash, under its own canonicalization options
+    pub amendment_hash: String,
+    /// The state root of the revision it was applied to
+    pub prev_root: SemanticHash,
+    /// The state root of the revision it produced
+    pub new_root: SemanticHash,
+}
+
+impl AmendmentRecord {
+    /// Check the record by applying the amendment to the revision it names as previous.
+    ///
+    /// # Arguments
+    /// * `previous` - The revision the amendment was applied to
+    /// * `amendment` - The amendment
+    /// * `options` - Canonicalization options the state roots were computed under
+    ///
+    /// # Returns
+    /// true if applying it again gives the same record, false otherwise; an error if it
+    /// cannot be applied
+    pub fn verify(
+        &self,
+        previous: &Constitution,
+        amendment: &Amendment,
+        options: &CanonicalizeOptions,
+    ) -> Result<bool> {
+        Ok(apply_amendment(previous, amendment, options)?.1 == *self)
+    }
+
+    /// The record's JSON object.
+    pub fn to_value(&self) -> Value {
+        json!({
+            "amendment_id": self.amendment_id,
+            "amendment_hash": self.amendment_hash,
+            "prev_root": self.prev_root.to_hex(),
+            "new_root": self.new_root.to_hex(),
+        })
+    }
+}
+
+/// Apply a validated amendment to a revision of the constitution.
+///
+/// # Arguments
+/// * `constitution` - The current revision, left as it is
+/// * `amendment` - The amendment, replacing an article or changing one address
+/// * `options` - Canonicalization options the state roots are computed under
+///
+/// # Returns
+/// The next revision and the record of the change, or ValidationFailed if the amendment
+/// is invalid, or the error `Amendment::apply` gives if it does not fit the constitution
+pub fn apply_amendment(
+    constitution: &Constitution,
+    amendment: &Amendment,
+    options: &CanonicalizeOptions,
+) -> Result<(Constitution, AmendmentRecord)> {
+    let violations = amendment.validate();
+    if !violations.is_empty() {
+        return Err(ConstitutionalError::ValidationFailed(violations));
+    }
+    let mut next = constitution.clone();
+    amendment.apply(&mut next)?;
+    let record = AmendmentRecord {
+        amendment_id: amendment.id.clone(),
+        amendment_hash: amendment.semantic_hash()?,
+        prev_root: constitution.state_root(options)?,
+        new_root: next.state_root(options)?,
+    };
+    Ok((next, record))
+}
+
+/// How an article differs between two revisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only the later revision has it
+    Added,
+    /// Both have it, with different hashes
+    Modified,
+    /// Only the earlier revision has it
+    Repealed,
+}
+
+impl ChangeKind {
+    /// The kind as written in a changelog, e.g. `modified`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Repealed => "repealed",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One article that differs between two revisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArticleChange {
+    /// The article's `article_id`
+    pub article_id: String,
+    /// How it differs
+    pub kind: ChangeKind,
+    /// Its semantic hash in the earlier revision; None if added
+    pub old_hash: Option<SemanticHash>,
+    /// Its semantic hash in the later revision; None if repealed
+    pub new_hash: Option<SemanticHash>,
+}
+
+impl ArticleChange {
+    /// The change's JSON object, the hashes in hex and left out when absent.
+    pub fn to_value(&self) -> Value {
+        let mut change = json!({"article_id": self.article_id, "change": self.kind.as_str()});
+        if let Some(hash) = &self.old_hash {
+            change["old_hash"] = Value::from(hash.to_hex());
+        }
+        if let Some(hash) = &self.new_hash {
+            change["new_hash"] = Value::from(hash.to_hex());
+        }
+        change
+    }
+}
+
+/// What changed between two revisions of the constitution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Changelog {
+    /// The state root of the earlier revision
+    pub from: SemanticHash,
+    /// The state root of the later revision
+    pub to: SemanticHash,
+    /// The articles that differ, in `article_id` order
+    pub changes: Vec<ArticleChange>,
+}
+
+impl Changelog {
+    /// The changes of one kind.
+    pub fn of_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &ArticleChange> {
+        self.changes.iter().filter(move |change| change.kind == kind)
+    }
+
+    /// Whether no article differs; the header may still, if the roots do.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The changelog's JSON object.
+    pub fn to_value(&self) -> Value {
+        let changes: Vec<Value> = self.changes.iter().map(ArticleChange::to_value).collect();
+        json!({"from": self.from.to_hex(), "to": self.to.to_hex(), "changes": changes})
+    }
+}
+
+/// Compare two revisions of the constitution article by article.
+///
+/// # Arguments
+/// * `before` - The earlier revision
+/// * `after` - The later revision
+/// * `options` - Canonicalization options the articles and roots are hashed under
+///
+/// # Returns
+/// The changelog, or an error if an article or header cannot be hashed
+pub fn changelog(before: &Constitution, after: &Constitution, options: &CanonicalizeOptions) -> Result<Changelog> {
+    let mut hashes: BTreeMap<&str, (Option<SemanticHash>, Option<SemanticHash>)> = BTreeMap::new();
+    for (id, hash) in before.article_hashes(options)? {
+        hashes.entry(id).or_default().0 = Some(hash);
+    }
+    for (id, hash) in after.article_hashes(options)? {
+        hashes.entry(id).or_default().1 = Some(hash);
+    }
+    let changes = hashes
+        .into_iter()
+        .filter_map(|(id, (old_hash, new_hash))| {
+            let kind = match (&old_hash, &new_hash) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Repealed,
+                (Some(old), Some(new)) if old != new => ChangeKind::Modified,
+                _ => return None,
+            };
+            Some(ArticleChange { article_id: id.to_string(), kind, old_hash, new_hash })
+        })
+        .collect();
+    Ok(Changelog { from: before.state_root(options)?, to: after.state_root(options)?, changes })
+}
+
+/// The member of a contract naming the revision it was evaluated against.
+pub const REVISION_MEMBER: &str = "constitution_revision";
+
+/// Identifies a revision: the number of amendments applied since the first revision,
+/// and the revision's state root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RevisionId {
+    /// 0 for the first revision, one more for each amendment after it
+    pub number: u64,
+    /// The revision's state root
+    pub state_root: SemanticHash,
+}
+
+impl RevisionId {
+    /// The ID's JSON object, its root in hex.
+    pub fn to_value(&self) -> Value {
+        json!({"number": self.number, "state_root": self.state_root.to_hex()})
+    }
+}
+
+impl Serialize for RevisionId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RevisionId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<RevisionId, D::Error> {
+        let mut map = object(deserializer, "a revision ID")?;
+        Ok(RevisionId { number: take(&mut map, "number")?, state_root: take(&mut map, "state_root")? })
+    }
+}
+
+/// One revision of the constitution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    /// The revision's number and state root
+    pub id: RevisionId,
+    /// The constitution as of the revision
+    pub constitution: Constitution,
+    /// The amendment that produced it; None for the first revision
+    pub record: Option<AmendmentRecord>,
+}
+
+/// Every revision of the constitution, from the first to the current one.
+#[derive(Debug, Clone)]
+pub struct RevisionHistory {
+    options: CanonicalizeOptions,
+    revisions: Vec<Revision>,
+}
+
+impl RevisionHistory {
+    /// Start a history at its first revision, number 0.
+    ///
+    /// # Arguments
+    /// * `genesis` - The constitution before any amendment
+    /// * `options` - Canonicalization options every state root is computed under
+    ///
+    /// # Returns
+    /// The history, or an error if the constitution cannot be hashed
+    pub fn new(genesis: Constitution, options: CanonicalizeOptions) -> Result<Self> {
+        let id = RevisionId { number: 0, state_root: genesis.state_root(&options)? };
+        Ok(RevisionHistory { options, revisions: vec![Revision { id, constitution: genesis, record: None }] })
+    }
+
+    /// Apply an amendment to the current revision, making the next one current.
+    ///
+    /// # Returns
+    /// The new revision, or the error `apply_amendment` gives, leaving the history as it was
+    pub fn apply(&mut self, amendment: &Amendment) -> Result<&Revision> {
+        let current = self.current();
+        let (constitution, record) = apply_amendment(&current.constitution, amendment, &self.options)?;
+        let id = RevisionId { number: current.id.number + 1, state_root: record.new_root };
+        self.revisions.push(Revision { id, constitution, record: Some(record) });
+        Ok(self.current())
+    }
+
+    /// The options every state root is computed under.
+    pub fn options(&self) -> &CanonicalizeOptions {
+        &self.options
+    }
+
+    /// The current revision, the last one.
+    pub fn current(&self) -> &Revision {
+        &self.revisions[self.revisions.len() - 1]
+    }
+
+    /// Every revision, first to current.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    /// The revision with the given number.
+    pub fn revision(&self, number: u64) -> Option<&Revision> {
+        usize::try_from(number).ok().and_then(|index| self.revisions.get(index))
+    }
+
+    /// The constitution as of the revision with the given number.
+    pub fn as_of(&self, number: u64) -> Option<&Constitution> {
+        self.revision(number).map(|revision| &revision.constitution)
+    }
+
+    /// Check that a contract names, in its `constitution_revision` member, a revision
+    /// of this history by both number and state root.
+    ///
+    /// # Returns
+    /// true if it does, false if it names a revision the history does not have; a
+    /// ProtocolError if it names none or the member is malformed
+    pub fn verify_evaluated(&self, contract: &Contract) -> Result<bool> {
+        let claim = contract.extra.get(REVISION_MEMBER).ok_or_else(|| {
+            ConstitutionalError::ProtocolError(format!("The contract has no {} member", REVISION_MEMBER))
+        })?;
+        let id = RevisionId::deserialize(claim)
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid {}: {}", REVISION_MEMBER, e)))?;
+        Ok(self.revision(id.number).is_some_and(|revision| revision.id == id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::builder;
+    use crate::{parse_markdown, Address, AmendmentOperation, Reasoning};
+
+    const DOCUMENT: &str = "# Constitution\n\n## Version 2.1\n\n## ARTICLE I — DEFINITIONS\n\n### 1.1 Agents\n\n\
+        Agents are reasoning systems.\n\nNew agents may join by amendment.\n\n### 1.2 Human Sovereign\n\n\
+        Humans retain ultimate authority.\n";
+
+    fn amendment(id: &str) -> crate::AmendmentBuilder {
+        Amendment::builder()
+            .id(id)
+            .proposer_agent("Claude")
+            .reasoning(Reasoning::new("Clarifies Article I.1", 0.9).unwrap())
+            .timestamp("2025-11-20T14:30:00Z")
+    }
+
+    #[test]
+    fn test_apply_records_the_roots() {
+        let options = CanonicalizeOptions::new();
+        let constitution = parse_markdown(DOCUMENT).unwrap();
+        let target = Address::clause(1, 1, 2);
+        let text = json!("New agents may join through Article X.");
+        let modify = amendment("8e5d1c2b-7a6f-4e3d-9c8b-1a2b3c4d5e6f").modify(target, text.clone()).build().unwrap();
+        assert_eq!(serde_json::from_value::<Amendment>(modify.to_value()).unwrap(), modify);
+        assert_eq!(modify.to_value()["target"], "article-1.1.2");
+
+        let (next, record) = apply_amendment(&constitution, &modify, &options).unwrap();
+        assert_eq!(next.resolve(&target), Some(&text));
+        assert_eq!(constitution.resolve(&target), Some(&json!("New agents may join by amendment.")));
+        assert_eq!(record.prev_root, constitution.state_root(&options).unwrap());
+        assert_eq!(record.new_root, next.state_root(&options).unwrap());
+        assert_eq!(record.amendment_hash, modify.semantic_hash().unwrap());
+        assert!(record.verify(&constitution, &modify, &options).unwrap());
+        assert!(!record.verify(&next, &modify, &options).unwrap());
+
+        // Adding a section and repealing a clause each give a new revision
+        let section = json!({"title": "Arbiters", "clauses": ["An arbiter resolves disputes."]});
+        let add = amendment("9f6e2d3c-8b7a-4f4e-8d9c-2b3c4d5e6f7a").add(Address::section(1, 3), section);
+        let (added, _) = apply_amendment(&next, &add.build().unwrap(), &options).unwrap();
+        assert_eq!(added.resolve(&Address::clause(1, 3, 1)), Some(&json!("An arbiter resolves disputes.")));
+        let repeal = amendment("0a7f3e4d-9c8b-4a5f-9e0d-3c4d5e6f7a8b").repeal(Address::clause(1, 1, 1));
+        let (repealed, _) = apply_amendment(&added, &repeal.build().unwrap(), &options).unwrap();
+        assert_eq!(repealed.resolve(&Address::clause(1, 1, 1)), Some(&text));
+        assert_eq!(repealed.resolve(&Address::clause(1, 1, 2)), None);
+    }
+
+    #[test]
+    fn test_amendments_that_do_not_fit() {
+        let options = CanonicalizeOptions::new();
+        let constitution = parse_markdown(DOCUMENT).unwrap();
+        let id = "8e5d1c2b-7a6f-4e3d-9c8b-1a2b3c4d5e6f";
+        let missing = amendment(id).modify(Address::clause(1, 2, 5), json!("text")).build().unwrap();
+        assert!(apply_amendment(&constitution, &missing, &options).is_err());
+        let existing = amendment(id).add(Address::section(1, 2), json!({"title": "T", "clauses": []})).build().unwrap();
+        assert!(apply_amendment(&constitution, &existing, &options).is_err());
+        let not_a_clause = amendment(id).modify(Address::clause(1, 1, 1), json!({"text": "a"})).build().unwrap();
+        assert!(apply_amendment(&constitution, &not_a_clause, &options).is_err());
+
+        let invalid = amendment("not an id").repeal(Address::article(1)).build().unwrap();
+        let err = apply_amendment(&constitution, &invalid, &options).unwrap_err();
+        assert!(matches!(err, ConstitutionalError::ValidationFailed(_)), "{}", err);
+        assert!(amendment(id).repeal(Address::article(1)).article(json!({"article_id": "I"})).build().is_err());
+        assert!(amendment(id).build().is_err());
+        let mut textless = amendment(id).modify(Address::article(1), json!({})).build().unwrap().to_value();
+        textless.as_object_mut().unwrap().remove("text");
+        assert!(serde_json::from_value::<Amendment>(textless).is_err());
+        assert_eq!("repeal".parse::<AmendmentOperation>().unwrap(), AmendmentOperation::Repeal);
+    }
+
+    #[test]
+    fn test_changelog_lists_each_article() {
+        let options = CanonicalizeOptions::new();
+        let before = parse_markdown(DOCUMENT).unwrap();
+        let mut after = before.clone();
+        let text = json!("Humans retain authority.");
+        after.change(AmendmentOperation::Modify, &Address::clause(1, 2, 1), Some(&text)).unwrap();
+        let article = json!({"title": "Amendments", "sections": []});
+        after.change(AmendmentOperation::Add, &Address::article(10), Some(&article)).unwrap();
+
+        let log = changelog(&before, &after, &options).unwrap();
+        assert_eq!((log.from, log.to), (before.state_root(&options).unwrap(), after.state_root(&options).unwrap()));
+        let kinds: Vec<(&str, ChangeKind)> = log.changes.iter().map(|c| (c.article_id.as_str(), c.kind)).collect();
+        assert_eq!(kinds, [("I", ChangeKind::Modified), ("X", ChangeKind::Added)]);
+        let modified = log.of_kind(ChangeKind::Modified).next().unwrap();
+        assert_eq!(modified.old_hash, Some(before.hash_at(&Address::article(1), &options).unwrap()));
+        assert_eq!(modified.new_hash, Some(after.hash_at(&Address::article(1), &options).unwrap()));
+        let added = after.hash_at(&Address::article(10), &options).unwrap().to_hex();
+        assert_eq!(log.to_value()["changes"][1], json!({"article_id": "X", "change": "added", "new_hash": added}));
+
+        let back = changelog(&after, &before, &options).unwrap();
+        assert_eq!(back.of_kind(ChangeKind::Repealed).count(), 1);
+        assert!(changelog(&before, &before, &options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_history_numbers_revisions() {
+        let options = CanonicalizeOptions::new();
+        let genesis = parse_markdown(DOCUMENT).unwrap();
+        let mut history = RevisionHistory::new(genesis.clone(), options.clone()).unwrap();
+        let text = json!("New agents may join through Article X.");
+        let modify = amendment("8e5d1c2b-7a6f-4e3d-9c8b-1a2b3c4d5e6f").modify(Address::clause(1, 1, 2), text);
+        let id = history.apply(&modify.build().unwrap()).unwrap().id;
+        assert_eq!(id.number, 1);
+        assert_eq!(id.state_root, history.current().constitution.state_root(&options).unwrap());
+        assert_eq!(history.as_of(0), Some(&genesis));
+        let record = history.revision(1).unwrap().record.as_ref().unwrap();
+        assert_eq!(record.prev_root, history.revisions()[0].id.state_root);
+        assert!(history.apply(&amendment("9f6e").repeal(Address::article(7)).build().unwrap()).is_err());
+        assert_eq!(history.revisions().len(), 2);
+
+        // A contract names the revision it was evaluated against
+        let contract = |claim: Value| {
+            builder()
+                .action(json!({"target": "article-1.1.2"}))
+                .reasoning(Reasoning::new("Clarifies Article I.1", 0.9).unwrap())
+                .field(REVISION_MEMBER, claim)
+                .build()
+                .unwrap()
+        };
+        assert!(history.verify_evaluated(&contract(id.to_value())).unwrap());
+        let stale = RevisionId { number: 1, state_root: history.revisions()[0].id.state_root };
+        assert!(!history.verify_evaluated(&contract(stale.to_value())).unwrap());
+        assert!(!history.verify_evaluated(&contract(RevisionId { number: 9, ..id }.to_value())).unwrap());
+        assert!(history.verify_evaluated(&contract(json!({"number": 1}))).is_err());
+        assert_eq!(serde_json::from_value::<RevisionId>(id.to_value()).unwrap(), id);
+    }
+}
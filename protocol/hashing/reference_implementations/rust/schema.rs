@@ -0,0 +1,432 @@
+/// schema.rs - JSON Schemas of the typed protocol objects
+///
+/// Producers that are not written in Rust validate their payloads against JSON Schemas
+/// (draft-07) before hashing. `json_schema` gives the schema of each typed object, built
+/// from the same members, required members and value rules as the Rust type and its
+/// `validate`; `protocol/schemas/` ships the ones for amendments, votes, challenges,
+/// dispute records and rulings. The normative `contract.schema.json` there is stricter
+/// than the generated contract schema, which only covers the members `Contract` has
+/// fields for.
+///
+/// `validate_against_schema` checks a value against one of these schemas and, like
+/// `validate`, reports every violation with its location rather than stopping at the
+/// first. It understands the keywords the schemas use: `type`, `required`, `properties`,
+/// `items`, `enum`, `minLength`, `minimum`, `maximum`, `format` and `$ref` to the
+/// schema's own `definitions`. Besides `date-time`, IDs have the custom format `ocp-id`:
+/// a lowercase UUID or a content ID (see `content_id.rs`), evidence pointers the custom
+/// format `evidence-pointer` (see `evidence.rs`) and hashes the custom format
+/// `semantic-hash`, which validators that do not know them ignore. Members a schema does
+/// not list are allowed, as the typed objects keep them in `extra`.
+
+use crate::validation::FieldReport;
+use crate::{ConstitutionalError, JsonPointer, Result, Violation, ViolationKind, ACTION_TYPES};
+use serde_json::{json, Map, Value};
+
+/// The object types `json_schema` and `validate_against_schema` know.
+pub const OBJECT_TYPES: &[&str] =
+    &["Contract", "Amendment", "Evidence", "Reasoning", "Vote", "Challenge", "DisputeRecord", "Ruling"];
+
+/// The JSON Schema of a typed protocol object.
+///
+/// # Arguments
+/// * `object_type` - One of `OBJECT_TYPES`, e.g. `Vote`
+///
+/// # Returns
+/// The schema, or a ProtocolError if the object type is unknown
+pub fn json_schema(object_type: &str) -> Result<Value> {
+    let schema = match object_type {
+        "Contract" => versioned(
+            "Contract proposal",
+            &["id", "proposer_agent", "action_type", "action", "evidence", "reasoning", "timestamp"],
+            json!({
+                "id": id("Unique contract identifier"),
+                "proposer_agent": agent("The agent proposing the contract"),
+                "action_type": {"type": "string", "enum": ACTION_TYPES, "description": "Category of the action"},
+                "action": {"type": "object", "description": "What is proposed"},
+                "evidence": {"type": "array", "items": {"$ref": "#/definitions/Evidence"}},
+                "reasoning": {"$ref": "#/definitions/Reasoning"},
+                "timestamp": timestamp("When the contract was submitted"),
+                "valid_from": timestamp("When the contract becomes valid"),
+                "valid_until": timestamp("When the contract expires"),
+            }),
+        ),
+        "Amendment" => versioned(
+            "Amendment to the constitution",
+            &["id", "proposer_agent", "reasoning", "timestamp"],
+            json!({
+                "id": id("Unique amendment identifier"),
+                "proposer_agent": agent("The agent proposing the amendment"),
+                "article": {
+                    "type": "object",
+                    "required": ["article_id"],
+                    "properties": {"article_id": {"type": "string"}},
+                    "description": "The article as amended, unless the amendment has a target",
+                },
+                "operation": {"type": "string", "enum": ["add", "modify", "repeal"]},
+                "target": {
+                    "type": "string",
+                    "description": "Address of the article, section or clause changed, e.g. article-3.1.2",
+                },
+                "text": {"description": "The new clause string, or section or article object"},
+                "reasoning": {"$ref": "#/definitions/Reasoning"},
+                "depends_on": {
+                    "type": "array",
+                    "items": id("An amendment to apply first"),
+                    "description": "The amendments that must be applied before this one",
+                },
+                "timestamp": timestamp("When the amendment was proposed"),
+            }),
+        ),
+        "Evidence" => evidence(),
+        "Reasoning" => reasoning(),
+        "Vote" => versioned(
+            "Vote on a contract",
+            &["contract_id", "voter_agent", "vote", "timestamp"],
+            json!({
+                "contract_id": id("The contract voted on"),
+                "voter_agent": agent("The agent voting"),
+                "vote": {"type": "string", "enum": ["approve", "reject", "abstain"]},
+                "reasoning": {"$ref": "#/definitions/Reasoning"},
+                "timestamp": timestamp("When the vote was cast"),
+            }),
+        ),
+        "Challenge" => versioned(
+            "Challenge to a contract",
+            &[
+                "id",
+                "contract_id",
+                "contract_hash",
+                "challenger_agent",
+                "grounds",
+                "evidence",
+                "reasoning",
+                "timestamp",
+            ],
+            json!({
+                "id": id("Unique challenge identifier"),
+                "contract_id": id("The contract challenged"),
+                "contract_hash": hash("Semantic hash of the contract as challenged"),
+                "challenger_agent": agent("The agent challenging"),
+                "grounds": {
+                    "type": "string",
+                    "enum": [
+                        "hash_mismatch",
+                        "procedural_violation",
+                        "constitutional_violation",
+                        "execution_inconsistency",
+                        "reputation_manipulation",
+                    ],
+                    "description": "What is claimed to be wrong",
+                },
+                "evidence": {"type": "array", "items": {"$ref": "#/definitions/Evidence"}},
+                "reasoning": {"$ref": "#/definitions/Reasoning"},
+                "timestamp": timestamp("When the challenge was raised"),
+            }),
+        ),
+        "DisputeRecord" => versioned(
+            "Record of a disputed challenge",
+            &["challenge_id", "challenge_hash", "contract_id", "status", "opened_at", "closes_at"],
+            json!({
+                "challenge_id": id("The challenge disputed"),
+                "challenge_hash": hash("Semantic hash of the challenge"),
+                "contract_id": id("The contract challenged"),
+                "status": {"type": "string", "enum": ["open", "resolved", "withdrawn"]},
+                "opened_at": timestamp("When the dispute opened"),
+                "closes_at": timestamp("When the dispute window closes"),
+                "ruling_id": id("The ruling settling the dispute, once it is resolved"),
+            }),
+        ),
+        "Ruling" => versioned(
+            "Ruling settling a challenged contract",
+            &["id", "contract_id", "arbiter_agent", "outcome", "reasoning", "timestamp"],
+            json!({
+                "id": id("Unique ruling identifier"),
+                "contract_id": id("The contract ruled on"),
+                "contract_hash": hash("Semantic hash of the contract as ruled on"),
+                "challenge_id": id("The challenge the ruling resolves"),
+                "challenge_hash": hash("Semantic hash of the challenge"),
+                "arbiter_agent": agent("The agent ruling"),
+                "outcome": {"type": "string", "enum": ["upheld", "invalidated"]},
+                "reasoning": {"$ref": "#/definitions/Reasoning"},
+                "precedents": {
+                    "type": "array",
+                    "items": hash("An earlier ruling cited as precedent"),
+                    "description": "The earlier rulings cited as precedent",
+                },
+                "timestamp": timestamp("When the ruling was made"),
+            }),
+        ),
+        _ => return Err(ConstitutionalError::ProtocolError(format!("Unknown object type {:?}", object_type))),
+    };
+    let mut schema = schema.as_object().cloned().expect("schemas are objects");
+    schema.insert("$schema".to_string(), json!("http://json-schema.org/draft-07/schema#"));
+    let file = snake_case(object_type);
+    schema.insert("$id".to_string(), json!(format!("https://constitutional-ai.org/schemas/{}.schema.json", file)));
+    if !matches!(object_type, "Evidence" | "Reasoning") {
+        schema.insert("definitions".to_string(), json!({"Evidence": evidence(), "Reasoning": reasoning()}));
+    }
+    Ok(Value::Object(schema))
+}
+
+/// Check a value against the JSON Schema of a typed protocol object.
+///
+/// # Arguments
+/// * `value` - The payload, e.g. a vote from a non-Rust producer
+/// * `object_type` - One of `OBJECT_TYPES`
+///
+/// # Returns
+/// Every violation, each with the JSON Pointer of the offending value; empty if the
+/// value matches. A ProtocolError if the object type is unknown
+pub fn validate_against_schema(value: &Value, object_type: &str) -> Result<Vec<Violation>> {
+    let schema = json_schema(object_type)?;
+    let mut checker = Checker { root: &schema, violations: Vec::new() };
+    checker.check(value, &schema, &mut Vec::new());
+    Ok(checker.violations)
+}
+
+fn object(title: &str, required: &[&str], properties: Value) -> Value {
+    json!({"title": format!("OCP {}", title), "type": "object", "required": required, "properties": properties})
+}
+
+/// A top-level object's schema, with the optional `schema_version` every one may carry.
+fn versioned(title: &str, required: &[&str], mut properties: Value) -> Value {
+    properties["schema_version"] = json!({
+        "type": "integer",
+        "minimum": 1,
+        "description": "Version of the schema the object follows; absent means 1"
+    });
+    object(title, required, properties)
+}
+
+fn evidence() -> Value {
+    object(
+        "Evidence",
+        &["type", "pointer"],
+        json!({
+            "type": {"type": "string", "description": "Category of evidence"},
+            "pointer": {"type": "string", "format": "evidence-pointer", "description": "Where the evidence is"},
+            "description": {"type": "string", "description": "Why the evidence is relevant"},
+        }),
+    )
+}
+
+fn reasoning() -> Value {
+    object(
+        "Reasoning",
+        &["rationale", "confidence"],
+        json!({
+            "rationale": {"type": "string"},
+            "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+        }),
+    )
+}
+
+fn id(description: &str) -> Value {
+    let description = format!("{}: a UUID, or a content ID ocp:<algorithm>:<hex>", description);
+    json!({"type": "string", "format": "ocp-id", "description": description})
+}
+
+fn hash(description: &str) -> Value {
+    json!({"type": "string", "format": "semantic-hash", "description": format!("{}, SHA-256", description)})
+}
+
+fn agent(description: &str) -> Value {
+    json!({"type": "string", "minLength": 1, "description": description})
+}
+
+fn timestamp(description: &str) -> Value {
+    json!({"type": "string", "format": "date-time", "description": description})
+}
+
+/// Walks a value and the schema it should match, collecting violations.
+struct Checker<'s> {
+    root: &'s Value,
+    violations: Vec<Violation>,
+}
+
+impl Checker<'_> {
+    fn check(&mut self, value: &Value, schema: &Value, path: &mut Vec<String>) {
+        let Some(schema) = schema.as_object() else {
+            return;
+        };
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            match reference.strip_prefix("#/definitions/").and_then(|name| self.root["definitions"].get(name)) {
+                Some(definition) => self.check(value, definition, path),
+                None => self.push(format!("unresolvable $ref {:?} in the schema", reference), path),
+            }
+            return;
+        }
+        if let Some(Value::String(expected)) = schema.get("type") {
+            if !has_type(value, expected) {
+                self.push(format!("expected {}, found {}", expected, type_name(value)), path);
+                return;
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                self.push(format!("{} is not one of {}", value, Value::Array(allowed.clone())), path);
+            }
+        }
+        self.strings(value, schema, path);
+        self.numbers(value, schema, path);
+        self.members(value, schema, path);
+        if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+            for (i, item) in items.iter().enumerate() {
+                path.push(i.to_string());
+                self.check(item, item_schema, path);
+                path.pop();
+            }
+        }
+    }
+
+    fn strings(&mut self, value: &Value, schema: &Map<String, Value>, path: &[String]) {
+        let Value::String(text) = value else {
+            return;
+        };
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if (text.chars().count() as u64) < min {
+                self.push(format!("shorter than {} characters", min), path);
+            }
+        }
+        // The formats are the ones `validate` checks, and are reported as it reports them
+        let tokens: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut report = FieldReport::default();
+        match schema.get("format").and_then(Value::as_str) {
+            Some("ocp-id") => report.id(&tokens, text),
+            Some("date-time") => report.timestamp(&tokens, text),
+            Some("evidence-pointer") => report.evidence_pointer(&tokens, text),
+            Some("semantic-hash") => report.hash(&tokens, text),
+            _ => {}
+        }
+        self.violations.extend(report.violations);
+    }
+
+    fn numbers(&mut self, value: &Value, schema: &Map<String, Value>, path: &[String]) {
+        let Some(n) = value.as_f64() else {
+            return;
+        };
+        if schema.get("minimum").and_then(Value::as_f64).is_some_and(|min| n < min) {
+            self.push(format!("{} is below the minimum {}", value, schema["minimum"]), path);
+        }
+        if schema.get("maximum").and_then(Value::as_f64).is_some_and(|max| n > max) {
+            self.push(format!("{} is above the maximum {}", value, schema["maximum"]), path);
+        }
+    }
+
+    fn members(&mut self, value: &Value, schema: &Map<String, Value>, path: &mut Vec<String>) {
+        let Value::Object(map) = value else {
+            return;
+        };
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !map.contains_key(name) {
+                self.push(format!("missing required member {:?}", name), path);
+            }
+        }
+        for (name, member_schema) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+            if let Some(member) = map.get(name) {
+                path.push(name.clone());
+                self.check(member, member_schema, path);
+                path.pop();
+            }
+        }
+    }
+
+    fn push(&mut self, message: String, path: &[String]) {
+        self.violations.push(Violation {
+            kind: ViolationKind::SchemaMismatch(message),
+            pointer: JsonPointer::from_tokens(path.iter().cloned()),
+        });
+    }
+}
+
+/// `DisputeRecord` is `dispute_record`.
+fn snake_case(object_type: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in object_type.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_schemas_are_generated() {
+        let shipped = [
+            ("Amendment", include_str!("../../../schemas/amendment.schema.json")),
+            ("Vote", include_str!("../../../schemas/vote.schema.json")),
+            ("Challenge", include_str!("../../../schemas/challenge.schema.json")),
+            ("DisputeRecord", include_str!("../../../schemas/dispute_record.schema.json")),
+            ("Ruling", include_str!("../../../schemas/ruling.schema.json")),
+        ];
+        for (object_type, text) in shipped {
+            let file: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(file, json_schema(object_type).unwrap(), "{}", object_type);
+        }
+        for object_type in OBJECT_TYPES {
+            assert_eq!(json_schema(object_type).unwrap()["type"], "object");
+        }
+        assert!(json_schema("Treaty").is_err());
+        assert!(validate_against_schema(&json!({}), "Treaty").is_err());
+    }
+
+    #[test]
+    fn test_validate_against_schema() {
+        let vote = json!({
+            "contract_id": "550e8400-e29b-41d4-a716-446655440000",
+            "voter_agent": "Gemini",
+            "vote": "approve",
+            "reasoning": {"rationale": "Well grounded", "confidence": 0.9},
+            "timestamp": "2025-11-21T09:00:00Z",
+            "weight": 2
+        });
+        assert_eq!(validate_against_schema(&vote, "Vote").unwrap(), []);
+
+        let mut bad = vote.clone();
+        bad["contract_id"] = json!("c-17");
+        bad["voter_agent"] = json!("");
+        bad["vote"] = json!("maybe");
+        bad["reasoning"] = json!({"confidence": 1.5});
+        bad.as_object_mut().unwrap().remove("timestamp");
+        let violations = validate_against_schema(&bad, "Vote").unwrap();
+        let pointers: Vec<String> = violations.iter().map(|v| v.pointer.to_string()).collect();
+        assert_eq!(pointers, ["", "/contract_id", "/reasoning", "/reasoning/confidence", "/vote", "/voter_agent"]);
+        assert_eq!(violations[1].kind, ViolationKind::InvalidId("c-17".to_string()));
+        assert_eq!(violations[0].kind, ViolationKind::SchemaMismatch(r#"missing required member "timestamp""#.into()));
+
+        let contract = json!({"id": 17, "evidence": [{"type": "computation"}], "action_type": "amend"});
+        let violations = validate_against_schema(&contract, "Contract").unwrap();
+        let pointers: Vec<String> = violations.iter().map(|v| v.pointer.to_string()).collect();
+        assert_eq!(pointers, ["", "", "", "", "/evidence/0", "/id"]);
+        assert_eq!(validate_against_schema(&json!([]), "Evidence").unwrap().len(), 1);
+    }
+}
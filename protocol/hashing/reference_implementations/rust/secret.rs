@@ -0,0 +1,109 @@
+/// secret.rs - Secret values wiped from memory when dropped
+///
+/// Secret keys, HMAC keys and commitment nonces that outlive their use can be read back
+/// from freed memory, core dumps or swap. Wherever this crate hands out or holds such a
+/// value it is a `Secret`, which overwrites its bytes when dropped, never prints them and
+/// has to be `expose`d to be read, so each reading is visible at the call site. The
+/// keys inside `ed25519-dalek`, `k256` and `blst` wipe themselves the same way.
+///
+/// A `Secret<Vec<u8>>` or `Secret<String>` wipes the buffer it holds when dropped, not
+/// earlier buffers it outgrew, so fill it to its final size before wrapping it.
+
+use std::fmt;
+
+/// A value whose bytes can be overwritten in place.
+pub trait Wipe {
+    /// Overwrite every byte of the value with zeros.
+    fn wipe(&mut self);
+}
+
+impl<const N: usize> Wipe for [u8; N] {
+    fn wipe(&mut self) {
+        wipe(self);
+    }
+}
+
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {
+        wipe(self);
+    }
+}
+
+impl Wipe for String {
+    fn wipe(&mut self) {
+        // SAFETY: zero bytes are valid UTF-8, so the string stays well-formed
+        wipe(unsafe { self.as_bytes_mut() });
+    }
+}
+
+/// A secret value, wiped when dropped and redacted from `Debug`.
+///
+/// Not `Clone`, so each copy of the secret is an explicit decision.
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    /// Take ownership of a secret value.
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// The secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// The secret value, to fill in place.
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T: Wipe> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+/// Overwrite secret bytes in a way the optimizer cannot elide.
+pub(crate) fn wipe(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid, exclusive reference to an initialized u8
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_wipe_and_redact() {
+        let mut bytes = [7u8; 32];
+        bytes.wipe();
+        assert_eq!(bytes, [0; 32]);
+        let mut text = "correct horse".to_string();
+        text.wipe();
+        assert_eq!(text, "\0".repeat(13));
+        let mut buffer = vec![1u8, 2, 3];
+        buffer.wipe();
+        assert_eq!(buffer, [0, 0, 0]);
+
+        let mut secret = Secret::new([0u8; 4]);
+        secret.expose_mut()[0] = 9;
+        assert_eq!(secret.expose(), &[9, 0, 0, 0]);
+        assert_eq!(format!("{:?}", Secret::from("hunter2".to_string())), "Secret([REDACTED])");
+    }
+}
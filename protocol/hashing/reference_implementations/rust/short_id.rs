@@ -0,0 +1,197 @@
+/// short_id.rs - Truncated hash identifiers for human-facing references
+///
+/// A 64-character digest is unreadable in a UI. A short ID is a prefix of the lowercase
+/// hex semantic hash, like a git short commit ID, and is only meaningful relative to the
+/// set of hashes it has to be told apart from. `ShortIdRegistry` holds that set: a short
+/// ID is issued only if no other registered hash shares it, and resolved back to the one
+/// full hash that starts with it.
+
+use crate::{semantic_digest, CanonicalizeOptions, ConstitutionalError, Result};
+use crate::algorithm::{hex, unhex};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+/// Shortest prefix accepted, in hex digits.
+pub const MIN_SHORT_ID_LEN: usize = 4;
+
+/// Full semantic hashes that short IDs are issued and resolved against.
+#[derive(Debug, Clone, Default)]
+pub struct ShortIdRegistry {
+    hashes: BTreeSet<String>,
+}
+
+impl ShortIdRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a full hash. Hex in either case is accepted and stored lowercase.
+    ///
+    /// # Arguments
+    /// * `hash` - Full hex semantic hash
+    ///
+    /// # Returns
+    /// true if the hash was not registered yet; a HashingError if it is not hex
+    pub fn insert(&mut self, hash: &str) -> Result<bool> {
+        if hash.is_empty() || unhex(hash).is_none() {
+            return Err(ConstitutionalError::HashingError(format!("{:?} is not a hex hash", hash)));
+        }
+        Ok(self.hashes.insert(hash.to_ascii_lowercase()))
+    }
+
+    /// Whether the full hash is registered.
+    pub fn contains(&self, hash: &str) -> bool {
+        self.hashes.contains(&hash.to_ascii_lowercase())
+    }
+
+    /// Number of registered hashes.
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether no hashes are registered.
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Expand a short ID to the full hash it abbreviates.
+    ///
+    /// # Arguments
+    /// * `short` - Hex prefix of a registered hash, in either case
+    ///
+    /// # Returns
+    /// The full hash, or a HashingError if no registered hash or more than one starts
+    /// with `short`
+    pub fn resolve(&self, short: &str) -> Result<String> {
+        let prefix = short.to_ascii_lowercase();
+        let mut matches = self.matching(&prefix);
+        match (matches.next(), matches.next()) {
+            (Some(hash), None) => Ok(hash.clone()),
+            (None, _) => Err(ConstitutionalError::HashingError(format!("No registered hash starts with {:?}", short))),
+            (Some(_), Some(_)) => Err(ConstitutionalError::HashingError(format!(
+                "Short ID {:?} is ambiguous: {} registered hashes start with it",
+                short,
+                2 + matches.count()
+            ))),
+        }
+    }
+
+    /// Abbreviate a full hash to `len` hex digits, checking that no other registered
+    /// hash starts with the same digits. The hash itself need not be registered.
+    ///
+    /// # Arguments
+    /// * `hash` - Full hex semantic hash
+    /// * `len` - Number of hex digits to keep, at least `MIN_SHORT_ID_LEN`
+    ///
+    /// # Returns
+    /// The short ID, or a HashingError if it is too short, longer than the hash, or
+    /// collides with another registered hash
+    pub fn abbreviate(&self, hash: &str, len: usize) -> Result<String> {
+        if len < MIN_SHORT_ID_LEN || len > hash.len() {
+            return Err(ConstitutionalError::HashingError(format!(
+                "Short ID length must be between {} and {}, got {}",
+                MIN_SHORT_ID_LEN,
+                hash.len(),
+                len
+            )));
+        }
+        let hash = hash.to_ascii_lowercase();
+        // Hex digits are ASCII, so `len` is a char boundary once the hash is known to be hex
+        if unhex(&hash).is_none() {
+            return Err(ConstitutionalError::HashingError(format!("{:?} is not a hex hash", hash)));
+        }
+        let short = &hash[..len];
+        if let Some(other) = self.matching(short).find(|other| **other != hash) {
+            return Err(ConstitutionalError::HashingError(format!(
+                "Short ID {:?} collides with registered hash {}; use a longer one",
+                short, other
+            )));
+        }
+        Ok(short.to_string())
+    }
+
+    fn matching<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.hashes
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |hash| hash.starts_with(prefix))
+    }
+}
+
+/// Calculate a short identifier for data: the first `len` hex digits of its semantic
+/// hash, provided no other hash in `registry` starts with them.
+///
+/// # Arguments
+/// * `data` - Input JSON value to identify
+/// * `len` - Number of hex digits, at least `MIN_SHORT_ID_LEN`
+/// * `registry` - Hashes the short ID must be told apart from
+///
+/// # Returns
+/// The short ID, or a HashingError on a collision
+pub fn short_id(data: &Value, len: usize, registry: &ShortIdRegistry) -> Result<String> {
+    short_id_with(data, len, &CanonicalizeOptions::default(), registry)
+}
+
+/// Calculate a short identifier for data canonicalized and hashed with explicit options.
+/// Short IDs are always hex, whatever the options' hash encoding.
+///
+/// # Arguments
+/// * `data` - Input JSON value to identify
+/// * `len` - Number of hex digits, at least `MIN_SHORT_ID_LEN`
+/// * `options` - Canonicalization options
+/// * `registry` - Hashes the short ID must be told apart from
+///
+/// # Returns
+/// The short ID, or a HashingError on a collision
+pub fn short_id_with(
+    data: &Value,
+    len: usize,
+    options: &CanonicalizeOptions,
+    registry: &ShortIdRegistry,
+) -> Result<String> {
+    registry.abbreviate(&hex(&semantic_digest(data, options)?), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic_hash;
+    use serde_json::json;
+
+    #[test]
+    fn test_short_ids_resolve() {
+        let contract = json!({"type": "contract", "parties": ["a", "b"]});
+        let evidence = json!({"type": "evidence", "claim": "delivered"});
+        let mut registry = ShortIdRegistry::new();
+        let full = semantic_hash(&contract).unwrap();
+        assert!(registry.insert(&full).unwrap());
+        assert!(!registry.insert(&full.to_uppercase()).unwrap());
+        registry.insert(&semantic_hash(&evidence).unwrap()).unwrap();
+
+        let short = short_id(&contract, 8, &registry).unwrap();
+        assert_eq!(short, full[..8]);
+        assert_eq!(registry.resolve(&short).unwrap(), full);
+        assert_eq!(registry.resolve(&short.to_uppercase()).unwrap(), full);
+        assert!(short_id(&contract, 3, &registry).is_err());
+        assert!(short_id(&contract, 65, &registry).is_err());
+        assert!(registry.insert("not hex").is_err());
+    }
+
+    #[test]
+    fn test_short_id_collisions() {
+        let mut registry = ShortIdRegistry::new();
+        registry.insert(&format!("abcd1234{}", "0".repeat(56))).unwrap();
+        registry.insert(&format!("abcd5678{}", "0".repeat(56))).unwrap();
+
+        let hash = format!("abcd1234{}", "f".repeat(56));
+        assert!(registry.abbreviate(&hash, 4).is_err());
+        assert!(registry.abbreviate(&hash, 8).is_err());
+        assert_eq!(registry.abbreviate(&hash, 9).unwrap(), "abcd1234f");
+
+        let err = registry.resolve("abcd").unwrap_err().to_string();
+        assert!(err.contains("2 registered hashes"), "{}", err);
+        assert!(registry.resolve("abce").is_err());
+        assert_eq!(registry.resolve("abcd5").unwrap(), format!("abcd5678{}", "0".repeat(56)));
+    }
+}
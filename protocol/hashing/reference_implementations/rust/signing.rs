@@ -0,0 +1,672 @@
+/// signing.rs - Signed attestations over semantic hashes
+///
+/// An agent attests to a contract by signing its semantic hash. What is signed is the
+/// hash envelope, so the signature also covers the algorithm, canonicalization version
+/// and encoding the hash was computed under: the message is `ocp-signature:` followed by
+/// the canonical JSON of the envelope. A `SignedObject` carries the payload, its
+/// envelope, the signer's public key and the signature, and serializes as
+///
+/// ```json
+/// {"payload":{...},"hash_envelope":{...},"signer":"ed25519:3b6a...","signature":"ed25519:92a0..."}
+/// ```
+///
+/// Keys and signatures are written as the scheme, a colon and lowercase hex, so a
+/// verifier can tell what it is holding before it parses the bytes and dispatches on it.
+/// Ed25519 is the default. With the `secp256k1` feature, agents can sign with the
+/// secp256k1 keys they already hold: ECDSA over the SHA-256 of the message, as a 64-byte
+/// compact signature (`secp256k1:`), or with a recovery byte appended
+/// (`secp256k1-recoverable:`) so the signer's key can be recovered from the signature.
+/// secp256k1 public keys are 33-byte compressed SEC1 points.
+///
+/// Signing goes through the `Signer` trait, so a key held in an HSM (the `pkcs11`
+/// feature) signs the same objects as a `Keypair` in memory.
+
+use crate::algorithm::{hex, unhex};
+use crate::commitment::os_random;
+use crate::objects::take;
+use crate::secret::wipe;
+use crate::{
+    canonicalize_with, semantic_hash_envelope, CanonicalizeOptions, ConstitutionalError, Did, DidResolvers,
+    HashEnvelope, Result, Secret,
+};
+use ed25519_dalek::Verifier;
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+const MESSAGE_TAG: &[u8] = b"ocp-signature:";
+const FIELDS: &[&str] = &["payload", "hash_envelope", "signer", "signature"];
+
+/// Scheme of a signature, written as its prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SignatureScheme {
+    /// Ed25519 (RFC 8032), the default.
+    #[default]
+    Ed25519,
+    /// ECDSA over secp256k1 with SHA-256, as 64-byte `r || s`. Requires the `secp256k1`
+    /// feature.
+    #[cfg(feature = "secp256k1")]
+    Secp256k1,
+    /// `Secp256k1` followed by the recovery byte. Requires the `secp256k1` feature.
+    #[cfg(feature = "secp256k1")]
+    Secp256k1Recoverable,
+}
+
+impl SignatureScheme {
+    /// Prefix naming the scheme in signatures, e.g. `secp256k1-recoverable`.
+    pub fn identifier(self) -> &'static str {
+        match self {
+            SignatureScheme::Ed25519 => "ed25519",
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => "secp256k1",
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1Recoverable => "secp256k1-recoverable",
+        }
+    }
+}
+
+impl FromStr for SignatureScheme {
+    type Err = ConstitutionalError;
+
+    fn from_str(identifier: &str) -> Result<SignatureScheme> {
+        match identifier {
+            "ed25519" => Ok(SignatureScheme::Ed25519),
+            #[cfg(feature = "secp256k1")]
+            "secp256k1" => Ok(SignatureScheme::Secp256k1),
+            #[cfg(feature = "secp256k1")]
+            "secp256k1-recoverable" => Ok(SignatureScheme::Secp256k1Recoverable),
+            _ => Err(ConstitutionalError::HashingError(format!(
+                "Unknown or disabled signature scheme {:?}",
+                identifier
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.identifier())
+    }
+}
+
+/// A signing key, its public key and the scheme it signs with.
+#[derive(Clone)]
+pub struct Keypair {
+    key: SecretKey,
+    scheme: SignatureScheme,
+}
+
+#[derive(Clone)]
+enum SecretKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+impl Keypair {
+    /// A fresh Ed25519 keypair from the operating system's random number generator.
+    ///
+    /// # Returns
+    /// The keypair, or an IoError if no random bytes could be read
+    pub fn generate() -> Result<Keypair> {
+        let mut secret = Secret::new([0u8; 32]);
+        os_random(secret.expose_mut())?;
+        Ok(Keypair::from_secret(secret.expose()))
+    }
+
+    /// The keypair of a 32-byte Ed25519 secret key.
+    pub fn from_secret(secret: &[u8; 32]) -> Keypair {
+        let key = SecretKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(secret));
+        Keypair { key, scheme: SignatureScheme::Ed25519 }
+    }
+
+    /// A fresh secp256k1 keypair from the operating system's random number generator.
+    ///
+    /// # Returns
+    /// The keypair, or an IoError if no random bytes could be read
+    #[cfg(feature = "secp256k1")]
+    pub fn generate_secp256k1() -> Result<Keypair> {
+        let mut secret = Secret::new([0u8; 32]);
+        // All but a negligible fraction of 32-byte strings are valid scalars
+        loop {
+            os_random(secret.expose_mut())?;
+            if let Ok(keypair) = Keypair::from_secp256k1_secret(secret.expose()) {
+                return Ok(keypair);
+            }
+        }
+    }
+
+    /// The keypair of a 32-byte secp256k1 secret scalar, signing with `Secp256k1`.
+    ///
+    /// # Returns
+    /// The keypair, or a HashingError if the scalar is zero or not below the group order
+    #[cfg(feature = "secp256k1")]
+    pub fn from_secp256k1_secret(secret: &[u8; 32]) -> Result<Keypair> {
+        let key = k256::ecdsa::SigningKey::from_slice(secret)
+            .map_err(|_| ConstitutionalError::HashingError("Not a valid secp256k1 secret key".to_string()))?;
+        Ok(Keypair { key: SecretKey::Secp256k1(key), scheme: SignatureScheme::Secp256k1 })
+    }
+
+    /// Sign with recoverable signatures. Only secp256k1 keys have them; Ed25519 keypairs
+    /// are returned unchanged.
+    #[cfg(feature = "secp256k1")]
+    pub fn recoverable(mut self) -> Self {
+        if let SecretKey::Secp256k1(_) = self.key {
+            self.scheme = SignatureScheme::Secp256k1Recoverable;
+        }
+        self
+    }
+
+    /// The keypair of a secret key as `secret_bytes` returned it, signing with `scheme`.
+    ///
+    /// # Returns
+    /// The keypair, or a HashingError if the bytes are not a secret key of the scheme's
+    /// curve
+    pub fn restore(scheme: SignatureScheme, secret: &[u8; 32]) -> Result<Keypair> {
+        match scheme {
+            SignatureScheme::Ed25519 => Ok(Keypair::from_secret(secret)),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => Keypair::from_secp256k1_secret(secret),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1Recoverable => Ok(Keypair::from_secp256k1_secret(secret)?.recoverable()),
+        }
+    }
+
+    /// The scheme signatures are made with.
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// The 32-byte secret key. Keep it out of logs and unencrypted storage.
+    pub fn secret_bytes(&self) -> Secret<[u8; 32]> {
+        let mut secret = Secret::new([0u8; 32]);
+        match &self.key {
+            SecretKey::Ed25519(key) => {
+                let mut bytes = key.to_bytes();
+                secret.expose_mut().copy_from_slice(&bytes);
+                wipe(&mut bytes);
+            }
+            #[cfg(feature = "secp256k1")]
+            SecretKey::Secp256k1(key) => {
+                let mut bytes = key.to_bytes();
+                secret.expose_mut().copy_from_slice(&bytes);
+                wipe(&mut bytes);
+            }
+        }
+        secret
+    }
+
+    /// The public key.
+    pub fn public_key(&self) -> PublicKey {
+        match &self.key {
+            SecretKey::Ed25519(key) => PublicKey(Key::Ed25519(key.verifying_key())),
+            #[cfg(feature = "secp256k1")]
+            SecretKey::Secp256k1(key) => PublicKey(Key::Secp256k1(sec1_bytes(key.verifying_key()))),
+        }
+    }
+
+    /// Sign a message, returning the signature as `<scheme>:<hex>`.
+    pub(crate) fn sign_message(&self, message: &[u8]) -> String {
+        let bytes = match (&self.key, self.scheme) {
+            (SecretKey::Ed25519(key), _) => ed25519_dalek::Signer::sign(key, message).to_bytes().to_vec(),
+            #[cfg(feature = "secp256k1")]
+            (SecretKey::Secp256k1(key), SignatureScheme::Secp256k1Recoverable) => {
+                let (signature, recovery) = key.sign_recoverable(message).expect("ECDSA signing with a valid key");
+                let mut bytes = signature.to_bytes().to_vec();
+                bytes.push(recovery.to_byte());
+                bytes
+            }
+            #[cfg(feature = "secp256k1")]
+            (SecretKey::Secp256k1(key), _) => {
+                let signature: k256::ecdsa::Signature = k256::ecdsa::signature::Signer::sign(key, message);
+                signature.to_bytes().to_vec()
+            }
+        };
+        format!("{}:{}", self.scheme, hex(&bytes))
+    }
+}
+
+/// Holder of a secret key that signs with it: a `Keypair` in memory, or a key that
+/// never leaves a hardware token.
+pub trait Signer: Send + Sync {
+    /// The public key the signatures verify under.
+    fn public_key(&self) -> Result<PublicKey>;
+
+    /// Sign a message, returning the signature as `<scheme>:<hex>`.
+    fn sign_message(&self, message: &[u8]) -> Result<String>;
+}
+
+impl Signer for Keypair {
+    fn public_key(&self) -> Result<PublicKey> {
+        Ok(Keypair::public_key(self))
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<String> {
+        Ok(Keypair::sign_message(self, message))
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Keypair").field("public_key", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+/// A public key, written as `ed25519:<hex>` or `secp256k1:<hex>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey(Key);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    /// Compressed SEC1 point
+    #[cfg(feature = "secp256k1")]
+    Secp256k1([u8; 33]),
+}
+
+impl PublicKey {
+    /// The key bytes: 32 for Ed25519, a 33-byte compressed point for secp256k1.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match &self.0 {
+            Key::Ed25519(key) => key.as_bytes().to_vec(),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(point) => point.to_vec(),
+        }
+    }
+
+    /// Prefix of the key's text form, which is also the name of its curve.
+    pub(crate) fn prefix(&self) -> &'static str {
+        match self.0 {
+            Key::Ed25519(_) => "ed25519",
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(_) => "secp256k1",
+        }
+    }
+
+    /// Check a signature, as written by `Keypair`, over a message.
+    ///
+    /// # Returns
+    /// true if the signature is valid, false if it is not; a HashingError if it is
+    /// malformed or its scheme does not fit the key
+    pub(crate) fn verify_message(&self, message: &[u8], signature: &str) -> Result<bool> {
+        let (scheme, bytes) = split_tagged(signature, "signature")?;
+        let scheme: SignatureScheme = scheme.parse()?;
+        let malformed = || ConstitutionalError::HashingError(format!("Malformed {} signature", scheme));
+        match (&self.0, scheme) {
+            (Key::Ed25519(key), SignatureScheme::Ed25519) => {
+                let signature = ed25519_dalek::Signature::from_slice(&bytes).map_err(|_| malformed())?;
+                Ok(key.verify(message, &signature).is_ok())
+            }
+            #[cfg(feature = "secp256k1")]
+            (Key::Secp256k1(point), SignatureScheme::Secp256k1) => {
+                let signature = k256::ecdsa::Signature::from_slice(&bytes).map_err(|_| malformed())?;
+                let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(|_| malformed())?;
+                Ok(k256::ecdsa::signature::Verifier::verify(&key, message, &signature).is_ok())
+            }
+            #[cfg(feature = "secp256k1")]
+            (Key::Secp256k1(_), SignatureScheme::Secp256k1Recoverable) => {
+                Ok(recover_key(message, &bytes).ok_or_else(malformed)? == Some(*self))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(ConstitutionalError::HashingError(format!(
+                "A {} signature cannot be checked against a {} key",
+                scheme,
+                self.prefix()
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.prefix(), hex(&self.to_bytes()))
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = ConstitutionalError;
+
+    fn from_str(text: &str) -> Result<PublicKey> {
+        let (prefix, bytes) = split_tagged(text, "public key")?;
+        PublicKey::from_parts(prefix, &bytes)
+            .ok_or_else(|| ConstitutionalError::HashingError(format!("Not a public key: {}", text)))
+    }
+}
+
+impl PublicKey {
+    /// The key of the curve named by `prefix` with the given bytes, if they are one.
+    pub(crate) fn from_parts(prefix: &str, bytes: &[u8]) -> Option<PublicKey> {
+        let key = match prefix {
+            "ed25519" => <[u8; 32]>::try_from(bytes)
+                .ok()
+                .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok())
+                .map(Key::Ed25519),
+            #[cfg(feature = "secp256k1")]
+            "secp256k1" => k256::ecdsa::VerifyingKey::from_sec1_bytes(bytes)
+                .ok()
+                .map(|key| Key::Secp256k1(sec1_bytes(&key))),
+            _ => None,
+        };
+        key.map(PublicKey)
+    }
+}
+
+/// A payload with its hash envelope and a signature over that envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedObject {
+    /// The attested document
+    pub payload: Value,
+    /// Semantic hash of the payload and how it was computed
+    pub hash_envelope: HashEnvelope,
+    /// The signer: a public key such as `ed25519:<hex>`, or a DID its key resolves from
+    pub signer: String,
+    /// Signature over the envelope, as `<scheme>:<hex>`
+    pub signature: String,
+}
+
+/// Sign data with default canonicalization options.
+///
+/// # Arguments
+/// * `data` - Document to attest to, e.g. a contract
+/// * `keypair` - The signer's keypair, or any other `Signer`
+///
+/// # Returns
+/// The signed object, or an error if the data cannot be hashed or the signer fails
+pub fn sign(data: &Value, keypair: &dyn Signer) -> Result<SignedObject> {
+    sign_with(data, keypair, &CanonicalizeOptions::default())
+}
+
+/// Sign data hashed under explicit options.
+///
+/// # Arguments
+/// * `data` - Document to attest to
+/// * `keypair` - The signer's keypair, or any other `Signer`
+/// * `options` - Canonicalization options the payload is hashed under
+///
+/// # Returns
+/// The signed object, or an error if the data cannot be hashed or the signer fails
+pub fn sign_with(data: &Value, keypair: &dyn Signer, options: &CanonicalizeOptions) -> Result<SignedObject> {
+    let hash_envelope = semantic_hash_envelope(data, options)?;
+    let signature = keypair.sign_message(&signing_message(&hash_envelope)?)?;
+    Ok(SignedObject { payload: data.clone(), hash_envelope, signer: keypair.public_key()?.to_string(), signature })
+}
+
+/// Sign data as an identity, recording its DID as the signer instead of the public key.
+///
+/// # Arguments
+/// * `data` - Document to attest to
+/// * `keypair` - The signer's keypair or other `Signer`, one of the keys `signer`
+///   resolves to
+/// * `signer` - The signer's DID
+/// * `options` - Canonicalization options the payload is hashed under
+///
+/// # Returns
+/// The signed object, or an error if the data cannot be hashed or the signer fails
+pub fn sign_as(data: &Value, keypair: &dyn Signer, signer: &Did, options: &CanonicalizeOptions) -> Result<SignedObject> {
+    let signed = sign_with(data, keypair, options)?;
+    Ok(SignedObject { signer: signer.to_string(), ..signed })
+}
+
+/// Check a signed object hashed with default options: that the envelope is the payload's
+/// hash and that the signer signed it. A `did:key` signer is resolved to its key.
+///
+/// # Returns
+/// true if both hold, false otherwise; an error if the signer or signature is malformed
+pub fn verify_signed(signed: &SignedObject) -> Result<bool> {
+    verify_signed_with(signed, &CanonicalizeOptions::default())
+}
+
+/// Check a signed object whose payload was hashed under explicit options. The
+/// envelope's algorithm and version override those of `options`.
+///
+/// # Returns
+/// true if the hash and signature both check out, false otherwise; an error if the
+/// signer or signature is malformed
+pub fn verify_signed_with(signed: &SignedObject, options: &CanonicalizeOptions) -> Result<bool> {
+    verify_signed_resolving(signed, options, &DidResolvers::new())
+}
+
+/// Check a signed object whose signer may be a DID of any method `resolvers` knows.
+/// The signature must be made by one of the keys the DID resolves to.
+///
+/// # Returns
+/// true if the hash and signature both check out, false otherwise; an error if the
+/// signer cannot be resolved or the signature fits none of its keys
+pub fn verify_signed_resolving(
+    signed: &SignedObject,
+    options: &CanonicalizeOptions,
+    resolvers: &DidResolvers,
+) -> Result<bool> {
+    let keys = if signed.signer.starts_with("did:") {
+        resolvers.resolve(&signed.signer.parse()?)?
+    } else {
+        vec![signed.signer.parse()?]
+    };
+    let message = signing_message(&signed.hash_envelope)?;
+    // A DID may resolve to keys of several schemes; the signature has to fit one of them
+    let (mut fitted, mut error) = (false, None);
+    for key in &keys {
+        match key.verify_message(&message, &signed.signature) {
+            Ok(true) => return signed.hash_envelope.verify(&signed.payload, options),
+            Ok(false) => fitted = true,
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    match error {
+        Some(error) if !fitted => Err(error),
+        _ => Ok(false),
+    }
+}
+
+/// Recover the signer of a signed object from its `secp256k1-recoverable` signature,
+/// without trusting its `signer` field.
+///
+/// # Returns
+/// The signer's public key, or a HashingError if the signature is not a valid
+/// recoverable signature over the envelope
+#[cfg(feature = "secp256k1")]
+pub fn recover_signer(signed: &SignedObject) -> Result<PublicKey> {
+    let invalid = || ConstitutionalError::HashingError("Not a valid recoverable signature".to_string());
+    let (scheme, bytes) = split_tagged(&signed.signature, "signature")?;
+    if scheme.parse::<SignatureScheme>()? != SignatureScheme::Secp256k1Recoverable {
+        return Err(invalid());
+    }
+    recover_key(&signing_message(&signed.hash_envelope)?, &bytes).ok_or_else(invalid)?.ok_or_else(invalid)
+}
+
+/// The key that made a 65-byte recoverable signature over `message`: None for
+/// malformed bytes, Some(None) for a signature no key made.
+#[cfg(feature = "secp256k1")]
+fn recover_key(message: &[u8], bytes: &[u8]) -> Option<Option<PublicKey>> {
+    let (recovery, signature) = bytes.split_last()?;
+    if signature.len() != 64 {
+        return None;
+    }
+    let signature = k256::ecdsa::Signature::from_slice(signature).ok()?;
+    let recovery = k256::ecdsa::RecoveryId::from_byte(*recovery)?;
+    let key = k256::ecdsa::VerifyingKey::recover_from_msg(message, &signature, recovery).ok();
+    Some(key.map(|key| PublicKey(Key::Secp256k1(sec1_bytes(&key)))))
+}
+
+#[cfg(feature = "secp256k1")]
+fn sec1_bytes(key: &k256::ecdsa::VerifyingKey) -> [u8; 33] {
+    let mut point = [0u8; 33];
+    point.copy_from_slice(key.to_encoded_point(true).as_bytes());
+    point
+}
+
+/// The bytes a signature over an envelope covers.
+pub(crate) fn signing_message(envelope: &HashEnvelope) -> Result<Vec<u8>> {
+    let envelope = serde_json::to_value(envelope).map_err(|e| ConstitutionalError::HashingError(e.to_string()))?;
+    let mut message = MESSAGE_TAG.to_vec();
+    message.extend_from_slice(canonicalize_with(&envelope, &CanonicalizeOptions::new())?.as_bytes());
+    Ok(message)
+}
+
+/// The prefix and bytes of a `<prefix>:<hex>` key or signature.
+pub(crate) fn split_tagged<'a>(text: &'a str, what: &str) -> Result<(&'a str, Vec<u8>)> {
+    text.split_once(':')
+        .and_then(|(prefix, digits)| Some((prefix, unhex(digits)?)))
+        .ok_or_else(|| ConstitutionalError::HashingError(format!("Malformed {}: {}", what, text)))
+}
+
+impl Serialize for SignedObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SignedObject", FIELDS.len())?;
+        state.serialize_field("payload", &self.payload)?;
+        state.serialize_field("hash_envelope", &self.hash_envelope)?;
+        state.serialize_field("signer", &self.signer)?;
+        state.serialize_field("signature", &self.signature)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SignedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SignedObject, D::Error> {
+        let Value::Object(mut map) = Value::deserialize(deserializer)? else {
+            return Err(de::Error::custom("expected a signed object"));
+        };
+        if let Some(key) = map.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+            return Err(de::Error::unknown_field(key, FIELDS));
+        }
+        Ok(SignedObject {
+            payload: map.remove("payload").ok_or_else(|| de::Error::missing_field("payload"))?,
+            hash_envelope: take(&mut map, "hash_envelope")?,
+            signer: take(&mut map, "signer")?,
+            signature: take(&mut map, "signature")?,
+        })
+    }
+}
+
+/// A keypair and a contract to sign, shared by the tests of every signature format.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use crate::Keypair;
+    use serde_json::{json, Value};
+
+    pub(crate) fn keypair() -> Keypair {
+        Keypair::from_secret(&[7; 32])
+    }
+
+    pub(crate) fn contract() -> Value {
+        json!({"contract_id": "c-17", "action_type": "amend", "parties": ["agent-claude", "agent-gemini"]})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{contract, keypair};
+    use super::*;
+    use crate::Encoding;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signed = sign(&contract(), &keypair()).unwrap();
+        assert!(signed.signer.starts_with("ed25519:"));
+        assert!(verify_signed(&signed).unwrap());
+
+        let stored = serde_json::to_string(&signed).unwrap();
+        assert!(stored.starts_with(r#"{"payload":{"#));
+        assert_eq!(serde_json::from_str::<SignedObject>(&stored).unwrap(), signed);
+
+        let mut tampered = signed.clone();
+        tampered.payload["action_type"] = json!("repeal");
+        assert!(!verify_signed(&tampered).unwrap());
+
+        // A signature moved onto another signer's object fails
+        let mut forged = sign(&contract(), &Keypair::from_secret(&[8; 32])).unwrap();
+        forged.signature = signed.signature.clone();
+        assert!(!verify_signed(&forged).unwrap());
+    }
+
+    #[test]
+    fn test_signature_covers_the_envelope() {
+        let options = CanonicalizeOptions::new().hash_encoding(Encoding::Base64Url);
+        let signed = sign_with(&contract(), &keypair(), &options).unwrap();
+        assert!(verify_signed_with(&signed, &options).unwrap());
+
+        // Re-encoding the digest keeps the hash right but breaks the signature
+        let mut reencoded = signed.clone();
+        reencoded.hash_envelope = semantic_hash_envelope(&contract(), &CanonicalizeOptions::new()).unwrap();
+        assert!(reencoded.hash_envelope.verify(&reencoded.payload, &options).unwrap());
+        assert!(!verify_signed_with(&reencoded, &options).unwrap());
+    }
+
+    #[test]
+    fn test_keys_and_malformed_input() {
+        let public = keypair().public_key();
+        assert_eq!(public.to_string().parse::<PublicKey>().unwrap(), public);
+        assert_eq!(public.to_bytes().len(), 32);
+        assert_eq!(Keypair::from_secret(keypair().secret_bytes().expose()).public_key(), public);
+        assert!(!format!("{:?}", keypair()).contains(&hex(&[7; 32])));
+
+        let signed = sign(&contract(), &keypair()).unwrap();
+        for (signer, signature) in [
+            ("rsa:00", signed.signature.as_str()),
+            (signed.signer.as_str(), "ed25519:abcd"),
+            (signed.signer.as_str(), "ed25519:zz"),
+        ] {
+            let malformed = SignedObject { signer: signer.to_string(), signature: signature.to_string(), ..signed.clone() };
+            assert!(verify_signed(&malformed).is_err(), "{} {}", signer, signature);
+        }
+        assert!(serde_json::from_str::<SignedObject>(r#"{"payload":{},"signer":"x","signature":"y"}"#).is_err());
+    }
+
+    #[test]
+    fn test_signing_delegates_to_signers() {
+        /// A signer that, like a hardware token, only exposes signing.
+        struct Token(Keypair, std::sync::atomic::AtomicUsize);
+
+        impl Signer for Token {
+            fn public_key(&self) -> Result<PublicKey> {
+                Ok(self.0.public_key())
+            }
+
+            fn sign_message(&self, message: &[u8]) -> Result<String> {
+                self.1.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Signer::sign_message(&self.0, message)
+            }
+        }
+
+        let token = Token(keypair(), Default::default());
+        let signed = sign(&contract(), &token).unwrap();
+        assert_eq!(token.1.into_inner(), 1);
+        assert_eq!(signed, sign(&contract(), &keypair()).unwrap());
+        assert!(verify_signed(&signed).unwrap());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_secp256k1_signatures() {
+        let keypair = Keypair::from_secp256k1_secret(&[7; 32]).unwrap();
+        assert_eq!(keypair.scheme(), SignatureScheme::Secp256k1);
+        let signed = sign(&contract(), &keypair).unwrap();
+        assert!(signed.signer.starts_with("secp256k1:02") || signed.signer.starts_with("secp256k1:03"));
+        assert!(signed.signature.starts_with("secp256k1:"));
+        assert!(verify_signed(&signed).unwrap());
+        assert!(recover_signer(&signed).is_err());
+
+        let recoverable = sign(&contract(), &keypair.clone().recoverable()).unwrap();
+        assert!(recoverable.signature.starts_with("secp256k1-recoverable:"));
+        assert!(verify_signed(&recoverable).unwrap());
+        assert_eq!(recover_signer(&recoverable).unwrap().to_string(), recoverable.signer);
+
+        let mut tampered = recoverable.clone();
+        let other = semantic_hash_envelope(&json!({"other": 1}), &CanonicalizeOptions::new()).unwrap();
+        tampered.hash_envelope.digest = other.digest;
+        assert!(!verify_signed(&tampered).unwrap());
+
+        // Schemes must fit the key they are checked against
+        let ed25519 = sign(&contract(), &Keypair::from_secret(&[7; 32])).unwrap();
+        let mixed = SignedObject { signer: signed.signer.clone(), ..ed25519 };
+        assert!(verify_signed(&mixed).is_err());
+        assert!(Keypair::from_secp256k1_secret(&[0; 32]).is_err());
+        assert_eq!(Keypair::from_secret(&[7; 32]).recoverable().scheme(), SignatureScheme::Ed25519);
+    }
+}
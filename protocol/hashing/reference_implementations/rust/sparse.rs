@@ -0,0 +1,254 @@
+/// sparse.rs - Sparse Merkle tree committing to the current value of every object ID
+///
+/// The constitution's state is a map from article and contract IDs to their current
+/// values. A `SparseMerkleTree` commits to that map with one root. Each ID is placed by
+/// its key, the semantic hash of the ID as a JSON string, which picks a path through a
+/// binary tree 256 levels deep. The leaf stores the semantic hash of the current value.
+/// Proofs show either that an ID has a given value, or that it has no value at all.
+///
+/// The tree is stored compressed, as in Diem's sparse Merkle tree. An empty subtree
+/// hashes to 32 zero bytes. A subtree holding exactly one entry is that entry's leaf
+/// node, the hash of `0x00`, the key and the value hash. Other subtrees are internal
+/// nodes, as in `merkle.rs`. The root thus depends only on the entries, never on the
+/// order they were inserted in, and proofs are as long as the keys' common prefixes.
+
+use crate::hmac::constant_time_eq;
+use crate::merkle::{check_algorithm, hash_node, prefixed_hash, LEAF_PREFIX};
+use crate::{CanonicalizeOptions, HashAlgorithm, Result, SemanticHash, TopLevelPolicy};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Hash of an empty subtree.
+const EMPTY: SemanticHash = SemanticHash::ZERO;
+
+/// Depth of the tree: one level per key bit.
+const DEPTH: usize = 256;
+
+/// Key of an object ID in a sparse Merkle tree: its semantic hash as a bare JSON string,
+/// whatever top-level policy `options` set.
+pub fn state_key(id: &str, options: &CanonicalizeOptions) -> Result<SemanticHash> {
+    SemanticHash::compute(&Value::String(id.to_string()), &options.clone().top_level(TopLevelPolicy::Native))
+}
+
+/// Sparse Merkle tree from object IDs to the semantic hashes of their current values.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree {
+    options: CanonicalizeOptions,
+    leaves: BTreeMap<SemanticHash, SemanticHash>,
+}
+
+impl SparseMerkleTree {
+    /// An empty tree whose keys and values are hashed under `options`.
+    ///
+    /// # Returns
+    /// The tree, or a HashingError if the options' algorithm is not 256-bit
+    pub fn new(options: &CanonicalizeOptions) -> Result<SparseMerkleTree> {
+        check_algorithm(options.hash_algorithm)?;
+        Ok(SparseMerkleTree { options: options.clone(), leaves: BTreeMap::new() })
+    }
+
+    /// Set the current value of an ID, inserting it or replacing its old value.
+    ///
+    /// # Returns
+    /// The hash of the value it replaced, if any
+    pub fn insert(&mut self, id: &str, value: &Value) -> Result<Option<SemanticHash>> {
+        let value_hash = SemanticHash::compute(value, &self.options)?;
+        self.insert_hash(id, value_hash)
+    }
+
+    /// Set the current value of an ID by its semantic hash.
+    pub fn insert_hash(&mut self, id: &str, value_hash: SemanticHash) -> Result<Option<SemanticHash>> {
+        Ok(self.leaves.insert(state_key(id, &self.options)?, value_hash))
+    }
+
+    /// Delete an ID.
+    ///
+    /// # Returns
+    /// The hash of its value, if it had one
+    pub fn remove(&mut self, id: &str) -> Result<Option<SemanticHash>> {
+        Ok(self.leaves.remove(&state_key(id, &self.options)?))
+    }
+
+    /// The hash of an ID's current value.
+    pub fn get(&self, id: &str) -> Result<Option<SemanticHash>> {
+        Ok(self.leaves.get(&state_key(id, &self.options)?).copied())
+    }
+
+    /// Number of IDs with a value.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Whether no ID has a value.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// The state root.
+    pub fn root(&self) -> SemanticHash {
+        let entries: Vec<_> = self.leaves.iter().map(|(key, value)| (*key, *value)).collect();
+        subtree_hash(self.algorithm(), &entries, 0)
+    }
+
+    /// Proof of an ID's current value, or of its absence.
+    pub fn prove(&self, id: &str) -> Result<SparseProof> {
+        let key = state_key(id, &self.options)?;
+        let algorithm = self.algorithm();
+        let mut entries: Vec<_> = self.leaves.iter().map(|(key, value)| (*key, *value)).collect();
+        let mut start = 0;
+        let mut siblings = Vec::new();
+        // Entries are sorted by key, so each level splits them into two runs
+        while entries.len() - start > 1 {
+            let depth = siblings.len();
+            let split = start + entries[start..].partition_point(|(other, _)| bit(other, depth) == 0);
+            if bit(&key, depth) == 0 {
+                siblings.push(subtree_hash(algorithm, &entries[split..], depth + 1));
+                entries.truncate(split);
+            } else {
+                siblings.push(subtree_hash(algorithm, &entries[start..split], depth + 1));
+                start = split;
+            }
+        }
+        Ok(SparseProof { algorithm, siblings, leaf: entries.get(start).copied() })
+    }
+
+    fn algorithm(&self) -> HashAlgorithm {
+        self.options.hash_algorithm
+    }
+}
+
+/// Proof of the value, or the absence, of one key in a sparse Merkle tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseProof {
+    /// Algorithm the tree's nodes are hashed with
+    pub algorithm: HashAlgorithm,
+    /// Sibling hashes from the root down to where the key's path ends
+    pub siblings: Vec<SemanticHash>,
+    /// The (key, value hash) leaf where the path ends, or None if it ends in an empty
+    /// subtree. For an absent key this is some other key's leaf.
+    pub leaf: Option<(SemanticHash, SemanticHash)>,
+}
+
+impl SparseProof {
+    /// Check that `key` has the value hash `value` under the state root `root`.
+    pub fn verify_membership(&self, root: &SemanticHash, key: &SemanticHash, value: &SemanticHash) -> bool {
+        self.leaf == Some((*key, *value)) && self.leads_to(root, key)
+    }
+
+    /// Check that `key` has no value under the state root `root`.
+    pub fn verify_non_membership(&self, root: &SemanticHash, key: &SemanticHash) -> bool {
+        let ends_elsewhere = match &self.leaf {
+            None => true,
+            // The other leaf must sit on the key's path, or the proof shows nothing
+            Some((other, _)) => other != key && (0..self.siblings.len()).all(|d| bit(other, d) == bit(key, d)),
+        };
+        ends_elsewhere && self.leads_to(root, key)
+    }
+
+    /// Whether hashing up the key's path from the proof's end reaches `root`.
+    fn leads_to(&self, root: &SemanticHash, key: &SemanticHash) -> bool {
+        if check_algorithm(self.algorithm).is_err() || self.siblings.len() > DEPTH {
+            return false;
+        }
+        let mut hash = match &self.leaf {
+            Some((key, value)) => leaf_hash(self.algorithm, key, value),
+            None => EMPTY,
+        };
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = match bit(key, depth) {
+                0 => hash_node(self.algorithm, &hash, sibling),
+                _ => hash_node(self.algorithm, sibling, &hash),
+            };
+        }
+        constant_time_eq(hash.as_bytes(), root.as_bytes())
+    }
+}
+
+/// Hash of the subtree at `depth` holding `entries`, which share their first `depth` bits.
+fn subtree_hash(algorithm: HashAlgorithm, entries: &[(SemanticHash, SemanticHash)], depth: usize) -> SemanticHash {
+    match entries {
+        [] => EMPTY,
+        [(key, value)] => leaf_hash(algorithm, key, value),
+        _ => {
+            let split = entries.partition_point(|(key, _)| bit(key, depth) == 0);
+            hash_node(
+                algorithm,
+                &subtree_hash(algorithm, &entries[..split], depth + 1),
+                &subtree_hash(algorithm, &entries[split..], depth + 1),
+            )
+        }
+    }
+}
+
+fn leaf_hash(algorithm: HashAlgorithm, key: &SemanticHash, value: &SemanticHash) -> SemanticHash {
+    prefixed_hash(algorithm, LEAF_PREFIX, &[key.as_bytes(), value.as_bytes()])
+}
+
+/// Bit `depth` of a key, most significant first.
+fn bit(key: &SemanticHash, depth: usize) -> u8 {
+    key.as_bytes()[depth / 8] >> (7 - depth % 8) & 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sparse_tree_updates_and_proofs() {
+        let options = CanonicalizeOptions::new();
+        let mut state = SparseMerkleTree::new(&options).unwrap();
+        let empty_root = state.root();
+        assert_eq!(empty_root, EMPTY);
+
+        for i in 0..20 {
+            state.insert(&format!("article-{}", i), &json!({"text": format!("v1 of {}", i)})).unwrap();
+        }
+        let root = state.root();
+        let old = state.insert("article-3", &json!({"text": "amended"})).unwrap();
+        assert_eq!(old, Some(SemanticHash::of(&json!({"text": "v1 of 3"})).unwrap()));
+        assert_ne!(state.root(), root);
+
+        // The root depends only on the entries, not on history
+        let mut rebuilt = SparseMerkleTree::new(&options).unwrap();
+        for i in (0..20).rev() {
+            let text = if i == 3 { "amended".to_string() } else { format!("v1 of {}", i) };
+            rebuilt.insert(&format!("article-{}", i), &json!({"text": text})).unwrap();
+        }
+        assert_eq!(rebuilt.root(), state.root());
+        assert!(rebuilt.remove("article-19").unwrap().is_some());
+        assert_eq!(rebuilt.get("article-19").unwrap(), None);
+        assert_eq!(rebuilt.len(), 19);
+        for i in 0..19 {
+            rebuilt.remove(&format!("article-{}", i)).unwrap();
+        }
+        assert_eq!(rebuilt.root(), empty_root);
+    }
+
+    #[test]
+    fn test_sparse_membership_and_absence() {
+        let options = CanonicalizeOptions::new();
+        let mut state = SparseMerkleTree::new(&options).unwrap();
+        for i in 0..10 {
+            state.insert(&format!("contract-{}", i), &json!({"status": "active"})).unwrap();
+        }
+        let root = state.root();
+        let active = SemanticHash::of(&json!({"status": "active"})).unwrap();
+        let key = state_key("contract-4", &options).unwrap();
+
+        let proof = state.prove("contract-4").unwrap();
+        assert!(proof.verify_membership(&root, &key, &active));
+        assert!(!proof.verify_membership(&root, &key, &EMPTY));
+        assert!(!proof.verify_non_membership(&root, &key));
+
+        for id in ["contract-10", "contract-x", "article-1"] {
+            let absent = state_key(id, &options).unwrap();
+            let proof = state.prove(id).unwrap();
+            assert!(proof.verify_non_membership(&root, &absent), "{}", id);
+            assert!(!proof.verify_membership(&root, &absent, &active));
+        }
+
+        let only = SparseMerkleTree::new(&options).unwrap().prove("contract-1").unwrap();
+        assert!(only.verify_non_membership(&EMPTY, &state_key("contract-1", &options).unwrap()));
+    }
+}
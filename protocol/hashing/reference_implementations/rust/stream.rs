@@ -0,0 +1,425 @@
+/// stream.rs - Canonicalization without an intermediate sorted tree
+///
+/// `deep_sort` builds a sorted copy of the whole document before `write_canonical`
+/// serializes it, so hashing a 100 MB archive snapshot would hold a second 100 MB tree in
+/// memory. When no array has to be ordered by the canonical form of its elements, the
+/// canonical bytes can instead be produced in one pass over the borrowed input: each
+/// object's members are normalized and ordered as it is entered, leaves are rewritten as
+/// they are written, and an array of primitives is sorted in a buffer of its own.
+///
+/// Memory is then bounded by the nesting depth plus the widest object and the longest
+/// primitive array, not by the size of the document. Pruning empty containers and the
+/// array policies that sort containers need a finished subtree before its first byte can
+/// be written, so options selecting them fall back to `deep_sort`.
+
+use crate::{
+    check_child, check_limit, compare_primitives, is_primitive_array, is_uniform_primitive_array, sort_leaf,
+    write_leaf, ArraySortPolicy, CanonicalizeOptions, ConstitutionalError, JsonPointer, Limit, PrunePolicy, Result,
+    TopLevelPolicy, WRITE_CHUNK,
+};
+use crate::escape::write_string;
+use crate::intern::Scratch;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::io::Write;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// True if `write_streaming` produces the canonical form under these options.
+pub(crate) fn streamable(options: &CanonicalizeOptions) -> bool {
+    let sorts_containers = |policy: ArraySortPolicy| {
+        !matches!(policy, ArraySortPolicy::Never | ArraySortPolicy::PrimitivesOnly | ArraySortPolicy::MixedPrimitives)
+    };
+    options.prune != PrunePolicy::NullsAndEmpty
+        && !sorts_containers(options.array_sort)
+        && !options.field_array_sort.iter().any(|(_, policy)| sorts_containers(*policy))
+        && !options.schema_hints.array_policies().any(sorts_containers)
+}
+
+/// An object key as written: the input key, or its interned normalized form.
+enum Key<'a> {
+    Input(&'a str),
+    Normalized(Rc<str>),
+}
+
+impl Deref for Key<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Key::Input(key) => key,
+            Key::Normalized(key) => key,
+        }
+    }
+}
+
+/// A member or element ready to be written.
+enum Child<'a> {
+    /// An object or array, entered when it is reached
+    Container(&'a Value),
+    /// A primitive already passed through `sort_leaf`
+    Leaf(Cow<'a, Value>),
+}
+
+/// An open container on the `write_streaming` work stack, with a flag for its first child.
+enum StreamFrame<'a> {
+    /// Members as (normalized key, reference token, value), in collation order
+    Object(std::vec::IntoIter<(Key<'a>, &'a str, Child<'a>)>, bool),
+    /// Elements kept in input order, checked as they are reached
+    Array(std::iter::Enumerate<std::slice::Iter<'a, Value>>, bool),
+    /// Primitive elements already normalized and sorted
+    Sorted(std::vec::IntoIter<Cow<'a, Value>>, bool),
+}
+
+/// Write the canonical form of `value` to `sink` without deep-sorting a copy of it.
+///
+/// Only valid when `streamable(options)` holds, and after `check_top_level` has accepted
+/// the value. On error, part of the output may already have been written.
+pub(crate) fn write_streaming<W: Write + ?Sized>(
+    value: &Value,
+    options: &CanonicalizeOptions,
+    scratch: &mut Scratch,
+    sink: &mut W,
+) -> Result<()> {
+    if options.exclude_paths.iter().any(JsonPointer::is_root) {
+        return Err(ConstitutionalError::canonicalization("Cannot exclude the document root"));
+    }
+
+    let wrapped = !value.is_object() && options.top_level != TopLevelPolicy::Native;
+    if wrapped {
+        sink.write_all(b"{\"value\":")?;
+    }
+    write_value(value, options, &[], None, scratch, sink)?;
+    if wrapped {
+        sink.write_all(b"}")?;
+    }
+    Ok(())
+}
+
+/// Stream the value found at `start`, under the member name `field` if its parent is an
+/// object. Exclusions, hints and errors are resolved relative to the whole document.
+pub(crate) fn write_value<W: Write + ?Sized>(
+    value: &Value,
+    options: &CanonicalizeOptions,
+    start: &[String],
+    field: Option<&str>,
+    scratch: &mut Scratch,
+    sink: &mut W,
+) -> Result<()> {
+    // `scratch.path` holds the reference tokens leading to the container currently open
+    scratch.path.reset(start);
+    let mut nodes = 1;
+    check_limit(Limit::Nodes, nodes, options.max_nodes, &scratch.path)?;
+
+    let mut buffer = String::with_capacity(WRITE_CHUNK);
+    let out = &mut buffer;
+    let mut stack: Vec<StreamFrame> = Vec::new();
+    match value {
+        Value::Object(_) | Value::Array(_) => {
+            let policy = options.array_sort_at(&scratch.path, field);
+            stack.extend(open(value, policy, options, scratch, &mut nodes, out)?);
+        }
+        leaf => write_leaf(&*rewrite_leaf(leaf, options, field, &scratch.path)?, options, out),
+    }
+
+    while let Some(frame) = stack.last_mut() {
+        if out.len() >= WRITE_CHUNK {
+            sink.write_all(out.as_bytes())?;
+            out.clear();
+        }
+
+        let path = &mut scratch.path;
+        let child = match frame {
+            StreamFrame::Object(entries, first) => entries.next().map(|(key, token, child)| {
+                separate(first, out);
+                write_string(&key, options.escaping, out);
+                out.push(':');
+                match child {
+                    Child::Leaf(leaf) => {
+                        write_leaf(&leaf, options, out);
+                        None
+                    }
+                    Child::Container(container) => {
+                        path.push(token);
+                        Some((container, true))
+                    }
+                }
+            }),
+            StreamFrame::Array(items, first) => match items.next() {
+                Some((index, item)) => {
+                    path.push_index(index);
+                    if excluded_last(options, path) {
+                        path.pop();
+                        continue;
+                    }
+                    nodes += 1;
+                    check_child(options, path, nodes, None)?;
+                    separate(first, out);
+                    Some(Some((item, false)))
+                }
+                None => None,
+            },
+            StreamFrame::Sorted(items, first) => items.next().map(|leaf| {
+                separate(first, out);
+                write_leaf(&leaf, options, out);
+                None
+            }),
+        };
+
+        match child {
+            // A leaf was written; move on to the next child
+            Some(None) => {}
+            Some(Some((child, member))) => {
+                let field = if member { scratch.path.last().map(String::as_str) } else { None };
+                let policy = options.array_sort_at(&scratch.path, field);
+                match open(child, policy, options, scratch, &mut nodes, out)? {
+                    Some(frame) => stack.push(frame),
+                    None => scratch.path.pop(),
+                }
+            }
+            // The container is exhausted
+            None => {
+                let closing = match stack.pop() {
+                    Some(StreamFrame::Object(..)) => '}',
+                    _ => ']',
+                };
+                out.push(closing);
+                if !stack.is_empty() {
+                    scratch.path.pop();
+                }
+            }
+        }
+    }
+
+    sink.write_all(out.as_bytes())?;
+    Ok(())
+}
+
+/// Enter the value at `scratch.path`: write the opening bracket of a container and return
+/// its frame, or write a primitive and return None. `policy` applies to an array.
+///
+/// An object's members are all checked, normalized and ordered here, and its primitive
+/// members rewritten by `sort_leaf`, so pruned members and key collisions are known
+/// before the first of them is written.
+fn open<'a>(
+    value: &'a Value,
+    policy: ArraySortPolicy,
+    options: &CanonicalizeOptions,
+    scratch: &mut Scratch,
+    nodes: &mut usize,
+    out: &mut String,
+) -> Result<Option<StreamFrame<'a>>> {
+    let path = &mut scratch.path;
+    match value {
+        Value::Object(map) => {
+            let entries = object_entries(map, options, scratch, nodes)?;
+            out.push('{');
+            Ok(Some(StreamFrame::Object(entries.into_iter(), true)))
+        }
+        Value::Array(arr) if policy != ArraySortPolicy::Never && is_primitive_array(arr) => {
+            let mut items = Vec::with_capacity(arr.len());
+            for (index, item) in arr.iter().enumerate() {
+                path.push_index(index);
+                if !excluded_last(options, path) {
+                    *nodes += 1;
+                    check_child(options, path, *nodes, None)?;
+                    items.push(rewrite_leaf(item, options, None, path)?);
+                }
+                path.pop();
+            }
+            if policy != ArraySortPolicy::PrimitivesOnly || is_uniform_primitive_array(&items) {
+                items.sort_by(|a, b| compare_primitives(a, b, options));
+            }
+            out.push('[');
+            Ok(Some(StreamFrame::Sorted(items.into_iter(), true)))
+        }
+        Value::Array(arr) => {
+            out.push('[');
+            Ok(Some(StreamFrame::Array(arr.iter().enumerate(), true)))
+        }
+        leaf => {
+            write_leaf(&*rewrite_leaf(leaf, options, None, path)?, options, out);
+            Ok(None)
+        }
+    }
+}
+
+/// The members of the object at `scratch.path` that survive exclusion and pruning, in
+/// collation order of their normalized keys.
+fn object_entries<'a>(
+    map: &'a Map<String, Value>,
+    options: &CanonicalizeOptions,
+    scratch: &mut Scratch,
+    nodes: &mut usize,
+) -> Result<Vec<(Key<'a>, &'a str, Child<'a>)>> {
+    let Scratch { path, keys } = scratch;
+    let mut entries = Vec::with_capacity(map.len());
+    for (token, child) in map {
+        if excluded(options, path, token) {
+            continue;
+        }
+        *nodes += 1;
+        path.push(token);
+        check_child(options, path, *nodes, Some(token))?;
+        let child = match child {
+            Value::Object(_) | Value::Array(_) => Child::Container(child),
+            leaf => {
+                let leaf = rewrite_leaf(leaf, options, Some(token), path)?;
+                if options.prune.drops(&leaf) {
+                    path.pop();
+                    continue;
+                }
+                Child::Leaf(leaf)
+            }
+        };
+        path.pop();
+        let key = match options.normalization {
+            Some(form) => Key::Normalized(keys.normalize(form, token)),
+            None => Key::Input(token),
+        };
+        entries.push((key, token.as_str(), child));
+    }
+
+    entries.sort_by(|a, b| options.key_collation.compare(&a.0, &b.0));
+    // Equal keys are adjacent once sorted; only normalization can produce them
+    if let Some(pair) = entries.windows(2).find(|pair| *pair[0].0 == *pair[1].0) {
+        return Err(ConstitutionalError::canonicalization_at(
+            format!("Key {:?} collides with another key after Unicode normalization", &*pair[1].0),
+            path,
+        ));
+    }
+    Ok(entries)
+}
+
+/// `sort_leaf` without copying the strings, booleans and nulls it would return unchanged.
+fn rewrite_leaf<'a>(
+    value: &'a Value,
+    options: &CanonicalizeOptions,
+    field: Option<&str>,
+    path: &[String],
+) -> Result<Cow<'a, Value>> {
+    match value {
+        Value::String(s)
+            if options.normalization.is_none()
+                && !field.is_some_and(|field| options.timestamp_fields.iter().any(|f| f == field)) =>
+        {
+            check_limit(Limit::StringLength, s.len(), options.max_string_length, path)?;
+            Ok(Cow::Borrowed(value))
+        }
+        Value::Bool(_) | Value::Null => Ok(Cow::Borrowed(value)),
+        _ => sort_leaf(value.clone(), options, field, path).map(Cow::Owned),
+    }
+}
+
+/// True if the value whose token was just pushed onto `path` is excluded.
+fn excluded_last(options: &CanonicalizeOptions, path: &[String]) -> bool {
+    match path.split_last() {
+        Some((token, parent)) => excluded(options, parent, token),
+        None => false,
+    }
+}
+
+fn excluded(options: &CanonicalizeOptions, parent: &[String], token: &str) -> bool {
+    options.exclude_paths.iter().any(|p| p.is_child(parent, token)) || options.schema_hints.excludes(parent, token)
+}
+
+fn separate(first: &mut bool, out: &mut String) {
+    if !std::mem::replace(first, false) {
+        out.push(',');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deep_sort, write_canonical, ArraySortPolicy, CanonicalizationProfile, KeyCollation, NormalizationForm,
+        NumberPolicy,
+    };
+    use serde_json::json;
+
+    fn streamed(value: &Value, options: &CanonicalizeOptions) -> Result<String> {
+        let mut out = Vec::new();
+        write_streaming(value, options, &mut Scratch::default(), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    /// The canonical form by way of a deep-sorted copy.
+    fn materialized(value: &Value, options: &CanonicalizeOptions) -> Result<String> {
+        let sorted = deep_sort(value.clone(), options)?;
+        let sorted = if value.is_object() || options.top_level == TopLevelPolicy::Native {
+            sorted
+        } else {
+            json!({ "value": sorted })
+        };
+        let mut out = Vec::new();
+        write_canonical(&sorted, options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_streaming_matches_deep_sort() {
+        let documents = [
+            json!({
+                "z": [3, "b", null, true, 1.5, -0.0],
+                "caf\u{e9}": {"steps": [{"b": 1, "a": [2, 1]}, [["y", "x"]]], "empty": {}},
+                "\u{10000}": "astral", "\u{e000}": "private use",
+                "evidence": [{"hash": "h0", "claim": "c0"}, {"hash": "h1", "claim": "c1"}],
+                "timestamp": "2025-11-20T14:30:00+02:00",
+                "note": null
+            }),
+            json!([{"b": 2, "a": 1}, [2, 1], "s"]),
+            json!("cafe\u{301}"),
+        ];
+        let option_sets = [
+            CanonicalizeOptions::new(),
+            CanonicalizeOptions::new().strict(false),
+            CanonicalizeOptions::new().strict(false).top_level(TopLevelPolicy::Native),
+            CanonicalizeOptions::new().profile(CanonicalizationProfile::Jcs).strict(false),
+            CanonicalizeOptions::new()
+                .strict(false)
+                .unicode_normalization(NormalizationForm::Nfd)
+                .key_collation(KeyCollation::Utf16CodeUnits)
+                .number_policy(NumberPolicy::Normalize)
+                .prune(PrunePolicy::Nulls)
+                .normalize_timestamps("timestamp")
+                .exclude_path(JsonPointer::parse("/evidence/1/hash").unwrap())
+                .array_sort_for("z", ArraySortPolicy::Never),
+        ];
+
+        for options in &option_sets {
+            assert!(streamable(options));
+            for document in &documents {
+                let expected = materialized(document, options);
+                match (streamed(document, options), expected) {
+                    (Ok(streamed), Ok(expected)) => assert_eq!(streamed, expected),
+                    (streamed, expected) => assert_eq!(streamed.is_err(), expected.is_err()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_streaming_errors_and_fallback() {
+        let colliding = json!({"outer": {"caf\u{e9}": 1, "cafe\u{301}": 2}});
+        let options = CanonicalizeOptions::new().unicode_normalization(NormalizationForm::Nfc);
+        let err = streamed(&colliding, &options).unwrap_err();
+        assert_eq!(err.to_string(), materialized(&colliding, &options).unwrap_err().to_string());
+        assert_eq!(err.pointer().map(|p| p.to_string()).as_deref(), Some("/outer"));
+
+        let wide = json!({"a": [1, 2, 3]});
+        let limited = CanonicalizeOptions::new().max_nodes(4);
+        assert!(matches!(
+            streamed(&wide, &limited),
+            Err(ConstitutionalError::LimitExceeded { limit: Limit::Nodes, max: 4, .. })
+        ));
+
+        for options in [
+            CanonicalizeOptions::new().prune(PrunePolicy::NullsAndEmpty),
+            CanonicalizeOptions::new().array_sort(ArraySortPolicy::Always),
+            CanonicalizeOptions::new().array_sort_for("evidence", ArraySortPolicy::ByElementHash),
+        ] {
+            assert!(!streamable(&options));
+        }
+    }
+}
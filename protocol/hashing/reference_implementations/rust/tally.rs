@@ -0,0 +1,357 @@
+/// tally.rs - Counting the votes on a contract
+///
+/// `tally` counts the votes cast on one contract under `TallyRules`: the agents
+/// eligible to vote, the fraction of them that must take part for a quorum, and the
+/// fraction of the decisive votes, approvals and rejections, that must approve.
+/// Abstentions count towards the quorum but not towards approval. Votes from agents
+/// that are not eligible are set aside, and when an agent votes more than once its
+/// latest vote counts.
+///
+/// The result is a `TallyResult` that records the rules, every counted vote and the
+/// outcome. Fractions are kept as integer ratios, so the result hashes the same on
+/// every node, and anyone holding the votes can recompute it and check it against the
+/// hash recorded for a ratification with `verify_tally`:
+///
+/// ```json
+/// {"contract_id":"c-17","eligible":["claude","gemini","grok"],"quorum":[2,3],"threshold":[1,2],
+///  "votes":{"claude":"approve","gemini":"reject"},"approve":1,"reject":1,"abstain":0,"outcome":"approved"}
+/// ```
+
+use crate::timestamp::utc_timestamp;
+use crate::{
+    AgentRegistry, Canonicalize, CanonicalizeOptions, ConstitutionalError, Result, SignedObject, Vote, VoteChoice,
+};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Who may vote and what it takes to approve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TallyRules {
+    eligible: BTreeSet<String>,
+    /// Fraction of the eligible agents that must vote, as numerator and denominator
+    quorum: (u64, u64),
+    /// Fraction of approvals and rejections that must be approvals
+    threshold: (u64, u64),
+}
+
+impl TallyRules {
+    /// Rules for a set of eligible agents. By default every one of them must vote, and
+    /// approvals must be at least half of the approvals and rejections.
+    ///
+    /// # Returns
+    /// The rules, or a ProtocolError if no agent is eligible
+    pub fn new<S: AsRef<str>>(eligible: &[S]) -> Result<TallyRules> {
+        let eligible: BTreeSet<String> = eligible.iter().map(|agent| agent.as_ref().to_string()).collect();
+        if eligible.is_empty() {
+            return Err(ConstitutionalError::ProtocolError("A tally needs an eligible agent".to_string()));
+        }
+        Ok(TallyRules { eligible, quorum: (1, 1), threshold: (1, 2) })
+    }
+
+    /// Require at least `numerator / denominator` of the eligible agents to vote.
+    ///
+    /// # Returns
+    /// The rules, or a ProtocolError if the fraction is not between 0 and 1
+    pub fn quorum(mut self, numerator: u64, denominator: u64) -> Result<Self> {
+        self.quorum = fraction(numerator, denominator)?;
+        Ok(self)
+    }
+
+    /// Require at least `numerator / denominator` of the approvals and rejections to be
+    /// approvals.
+    ///
+    /// # Returns
+    /// The rules, or a ProtocolError if the fraction is not between 0 and 1
+    pub fn threshold(mut self, numerator: u64, denominator: u64) -> Result<Self> {
+        self.threshold = fraction(numerator, denominator)?;
+        Ok(self)
+    }
+
+    /// Whether an agent may vote.
+    pub fn is_eligible(&self, agent: &str) -> bool {
+        self.eligible.contains(agent)
+    }
+}
+
+fn fraction(numerator: u64, denominator: u64) -> Result<(u64, u64)> {
+    if denominator == 0 || numerator > denominator {
+        return Err(ConstitutionalError::ProtocolError(format!(
+            "{}/{} is not a fraction between 0 and 1",
+            numerator, denominator
+        )));
+    }
+    Ok((numerator, denominator))
+}
+
+/// Whether `part / whole` is at least `numerator / denominator`, without rounding.
+fn at_least(part: u64, whole: u64, (numerator, denominator): (u64, u64)) -> bool {
+    u128::from(part) * u128::from(denominator) >= u128::from(numerator) * u128::from(whole)
+}
+
+/// What a tally decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TallyOutcome {
+    /// Quorum was reached and the approvals met the threshold.
+    Approved,
+    /// Quorum was reached and the approvals fell short.
+    Rejected,
+    /// Too few eligible agents voted.
+    NoQuorum,
+}
+
+impl TallyOutcome {
+    /// The outcome as written in a tally, e.g. `no_quorum`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TallyOutcome::Approved => "approved",
+            TallyOutcome::Rejected => "rejected",
+            TallyOutcome::NoQuorum => "no_quorum",
+        }
+    }
+}
+
+impl fmt::Display for TallyOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The count of the votes on a contract, with the rules it was counted under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TallyResult {
+    /// The contract voted on
+    pub contract_id: String,
+    /// The rules the votes were counted under
+    pub rules: TallyRules,
+    /// Each eligible voter's counted vote
+    pub votes: BTreeMap<String, VoteChoice>,
+    /// Agents whose votes were set aside as ineligible, sorted
+    pub ineligible: Vec<String>,
+    /// What the count decided
+    pub outcome: TallyOutcome,
+}
+
+impl TallyResult {
+    /// Number of counted votes with a choice.
+    pub fn count(&self, choice: VoteChoice) -> u64 {
+        self.votes.values().filter(|vote| **vote == choice).count() as u64
+    }
+
+    /// The tally's JSON object. Ineligible voters are listed only if there are any.
+    pub fn to_value(&self) -> Value {
+        let votes: BTreeMap<&str, &str> =
+            self.votes.iter().map(|(agent, choice)| (agent.as_str(), choice.as_str())).collect();
+        let mut value = json!({
+            "contract_id": self.contract_id,
+            "eligible": self.rules.eligible,
+            "quorum": [self.rules.quorum.0, self.rules.quorum.1],
+            "threshold": [self.rules.threshold.0, self.rules.threshold.1],
+            "votes": votes,
+            "approve": self.count(VoteChoice::Approve),
+            "reject": self.count(VoteChoice::Reject),
+            "abstain": self.count(VoteChoice::Abstain),
+            "outcome": self.outcome.as_str(),
+        });
+        if !self.ineligible.is_empty() {
+            value["ineligible"] = json!(self.ineligible);
+        }
+        value
+    }
+}
+
+impl Canonicalize for TallyResult {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl Serialize for TallyResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+/// Count the votes on a contract.
+///
+/// # Arguments
+/// * `contract_id` - The contract voted on
+/// * `votes` - The votes cast, whose signatures the caller has checked
+/// * `rules` - Who may vote and what it takes to approve
+///
+/// # Returns
+/// The tally, or a ProtocolError if a vote is on another contract or has a malformed
+/// timestamp, or an agent cast different votes at the same time
+pub fn tally(contract_id: &str, votes: &[Vote], rules: &TallyRules) -> Result<TallyResult> {
+    let mut latest: BTreeMap<&str, (String, VoteChoice)> = BTreeMap::new();
+    let mut ineligible = BTreeSet::new();
+    for vote in votes {
+        if vote.contract_id != contract_id {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "A vote by {} is on {}, not {}",
+                vote.voter_agent, vote.contract_id, contract_id
+            )));
+        }
+        let at = utc_timestamp(&vote.timestamp)?;
+        if !rules.is_eligible(&vote.voter_agent) {
+            ineligible.insert(vote.voter_agent.clone());
+            continue;
+        }
+        match latest.get(vote.voter_agent.as_str()) {
+            Some((seen, choice)) if *seen == at && *choice != vote.choice => {
+                return Err(ConstitutionalError::ProtocolError(format!(
+                    "{} cast different votes at {}",
+                    vote.voter_agent, vote.timestamp
+                )));
+            }
+            Some((seen, _)) if *seen >= at => {}
+            _ => {
+                latest.insert(&vote.voter_agent, (at, vote.choice));
+            }
+        }
+    }
+
+    let votes: BTreeMap<String, VoteChoice> =
+        latest.into_iter().map(|(agent, (_, choice))| (agent.to_string(), choice)).collect();
+    let mut result = TallyResult {
+        contract_id: contract_id.to_string(),
+        rules: rules.clone(),
+        votes,
+        ineligible: ineligible.into_iter().collect(),
+        outcome: TallyOutcome::NoQuorum,
+    };
+    let (approve, reject) = (result.count(VoteChoice::Approve), result.count(VoteChoice::Reject));
+    result.outcome = if !at_least(result.votes.len() as u64, rules.eligible.len() as u64, rules.quorum) {
+        TallyOutcome::NoQuorum
+    } else if approve > 0 && at_least(approve, approve + reject, rules.threshold) {
+        TallyOutcome::Approved
+    } else {
+        TallyOutcome::Rejected
+    };
+    Ok(result)
+}
+
+/// Check signed votes and count them.
+///
+/// # Arguments
+/// * `contract_id` - The contract voted on
+/// * `signed` - Signed votes, each payload a `Vote`
+/// * `registry` - The agents and their keys
+/// * `rules` - Who may vote and what it takes to approve
+/// * `options` - Canonicalization options the votes were hashed under
+///
+/// # Returns
+/// The tally, or a ProtocolError if a payload is not a vote, or a vote was not signed
+/// by a key valid for its voter when it was cast, or `tally` fails
+pub fn tally_signed(
+    contract_id: &str,
+    signed: &[SignedObject],
+    registry: &AgentRegistry,
+    rules: &TallyRules,
+    options: &CanonicalizeOptions,
+) -> Result<TallyResult> {
+    let mut votes = Vec::with_capacity(signed.len());
+    for object in signed {
+        let vote: Vote = serde_json::from_value(object.payload.clone())
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid vote: {}", e)))?;
+        if !registry.verify_signed_at(object, &vote.voter_agent, &vote.timestamp, options)? {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "The vote by {} is not signed by a key of theirs",
+                vote.voter_agent
+            )));
+        }
+        votes.push(vote);
+    }
+    tally(contract_id, &votes, rules)
+}
+
+/// Recount votes and check the tally against the semantic hash recorded for it.
+///
+/// # Returns
+/// true if the recount hashes to `recorded_hash`, false otherwise; an error if the
+/// votes cannot be counted
+pub fn verify_tally(contract_id: &str, votes: &[Vote], rules: &TallyRules, recorded_hash: &str) -> Result<bool> {
+    Ok(tally(contract_id, votes, rules)?.semantic_hash()? == recorded_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign, Keypair};
+
+    fn vote(agent: &str, choice: VoteChoice, timestamp: &str) -> Vote {
+        Vote::builder().contract_id("c-17").voter_agent(agent).choice(choice).timestamp(timestamp).build().unwrap()
+    }
+
+    fn rules() -> TallyRules {
+        TallyRules::new(&["claude", "gemini", "grok"]).unwrap().quorum(2, 3).unwrap().threshold(1, 2).unwrap()
+    }
+
+    #[test]
+    fn test_tally_counts_latest_eligible_votes() {
+        let votes = [
+            vote("gemini", VoteChoice::Approve, "2025-11-21T09:00:00Z"),
+            vote("claude", VoteChoice::Reject, "2025-11-21T09:00:00Z"),
+            vote("gemini", VoteChoice::Reject, "2025-11-21T08:00:00Z"),
+            vote("mallory", VoteChoice::Reject, "2025-11-21T09:00:00Z"),
+        ];
+        let result = tally("c-17", &votes, &rules()).unwrap();
+        assert_eq!(result.outcome, TallyOutcome::Approved);
+        assert_eq!(result.ineligible, ["mallory"]);
+        let expected = json!({
+            "contract_id": "c-17", "eligible": ["claude", "gemini", "grok"], "quorum": [2, 3], "threshold": [1, 2],
+            "votes": {"claude": "reject", "gemini": "approve"}, "approve": 1, "reject": 1, "abstain": 0,
+            "ineligible": ["mallory"], "outcome": "approved"
+        });
+        assert_eq!(serde_json::to_value(&result).unwrap(), expected);
+
+        // The order the votes arrive in does not change the tally or its hash
+        let mut reordered = votes.to_vec();
+        reordered.reverse();
+        let hash = result.semantic_hash().unwrap();
+        assert!(verify_tally("c-17", &reordered, &rules(), &hash).unwrap());
+        assert!(!verify_tally("c-17", &votes[..2], &rules(), &hash).unwrap());
+    }
+
+    #[test]
+    fn test_quorum_and_threshold() {
+        let at = "2025-11-21T09:00:00Z";
+        let outcome = |votes: &[Vote], rules: &TallyRules| tally("c-17", votes, rules).unwrap().outcome;
+        let approve = vote("claude", VoteChoice::Approve, at);
+        let abstain = vote("grok", VoteChoice::Abstain, at);
+        assert_eq!(outcome(std::slice::from_ref(&approve), &rules()), TallyOutcome::NoQuorum);
+        assert_eq!(outcome(&[approve.clone(), abstain.clone()], &rules()), TallyOutcome::Approved);
+        let abstentions = [abstain.clone(), vote("gemini", VoteChoice::Abstain, at)];
+        assert_eq!(outcome(&abstentions, &rules()), TallyOutcome::Rejected);
+        let reject = vote("gemini", VoteChoice::Reject, at);
+        let two_thirds = rules().threshold(2, 3).unwrap();
+        assert_eq!(outcome(&[approve.clone(), reject.clone()], &two_thirds), TallyOutcome::Rejected);
+
+        assert!(rules().quorum(4, 3).is_err());
+        assert!(TallyRules::new::<&str>(&[]).is_err());
+        let other = Vote { contract_id: "c-18".to_string(), ..approve.clone() };
+        assert!(tally("c-17", &[other], &rules()).is_err());
+        let changed = Vote { choice: VoteChoice::Reject, ..approve.clone() };
+        assert!(tally("c-17", &[approve, changed], &rules()).is_err());
+    }
+
+    #[test]
+    fn test_signed_votes_must_be_the_voters() {
+        let mut registry = AgentRegistry::new();
+        let claude = Keypair::from_secret(&[1; 32]);
+        let gemini = Keypair::from_secret(&[2; 32]);
+        registry.register("claude", claude.public_key(), "2025-01-01T00:00:00Z").unwrap();
+        registry.register("gemini", gemini.public_key(), "2025-01-01T00:00:00Z").unwrap();
+        let options = CanonicalizeOptions::new();
+        let at = "2025-11-21T09:00:00Z";
+        let cast = |agent, keypair: &Keypair| sign(&vote(agent, VoteChoice::Approve, at).to_value(), keypair).unwrap();
+
+        let signed = [cast("claude", &claude), cast("gemini", &gemini)];
+        let result = tally_signed("c-17", &signed, &registry, &rules(), &options).unwrap();
+        assert_eq!(result.count(VoteChoice::Approve), 2);
+        // Gemini cannot cast Claude's vote
+        let forged = [cast("claude", &gemini)];
+        assert!(tally_signed("c-17", &forged, &registry, &rules(), &options).is_err());
+    }
+}
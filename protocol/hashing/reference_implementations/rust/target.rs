@@ -0,0 +1,181 @@
+/// target.rs - Resolving amendment targets to constitution addresses
+///
+/// Contracts name what they amend in their action's `target`, written in more than one
+/// way. A `TargetResolver` maps each to the `Address` it means:
+///
+/// ```text
+/// article-3.1.2           an address as `Address` writes it
+/// amendment-article-3     the legacy target of contracts amending Article III
+/// Article-III.1           a citation, as evidence pointers write it: section 3.1
+/// Article III, art. 3.1   the same, loosely: any case, Roman or Arabic numbers,
+///                         spaces, hyphens or underscores between the parts
+/// agents-clause           an alias the resolver was given
+/// ```
+///
+/// A contract whose target names a whole article may narrow it with the `article`
+/// member of its action's `parameters`, as in `{"target":"amendment-article-3",
+/// "parameters":{"article":"III.1"}}`. Before a contract is ratified, `check` confirms
+/// that the place it targets exists in the constitution.
+
+use crate::document::from_roman;
+use crate::{Address, Constitution, ConstitutionalError, Contract, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Maps target strings, and any aliases given, to constitution addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetResolver {
+    /// Addresses by normalized alias
+    aliases: BTreeMap<String, Address>,
+}
+
+impl TargetResolver {
+    /// A resolver with no aliases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve a name to an address, ahead of the target forms. Names are compared
+    /// ignoring case and the choice of spaces, hyphens or underscores.
+    pub fn alias(mut self, name: &str, address: Address) -> Self {
+        self.aliases.insert(normalize(name), address);
+        self
+    }
+
+    /// Resolve a target string.
+    ///
+    /// # Returns
+    /// The address, or a ProtocolError naming the target if it is no alias and of no
+    /// known form
+    pub fn resolve(&self, target: &str) -> Result<Address> {
+        let name = normalize(target);
+        if let Some(address) = self.aliases.get(&name) {
+            return Ok(*address);
+        }
+        read(&name).ok_or_else(|| ConstitutionalError::ProtocolError(format!("Unknown amendment target {:?}", target)))
+    }
+
+    /// Resolve a contract's target: its action's `target`, narrowed by the `article` of
+    /// its `parameters` if the target is a whole article and the parameter is within it.
+    ///
+    /// # Returns
+    /// The address, or a ProtocolError if the action has no string `target`, either
+    /// does not resolve, or the parameter names a place outside the target
+    pub fn resolve_contract(&self, contract: &Contract) -> Result<Address> {
+        self.resolve_action(&contract.id, &contract.action)
+    }
+
+    /// Resolve the target of a contract's action, as `resolve_contract` does.
+    pub(crate) fn resolve_action(&self, contract_id: &str, action: &Value) -> Result<Address> {
+        let Some(target) = action.get("target").and_then(Value::as_str) else {
+            return Err(ConstitutionalError::ProtocolError(format!("Contract {} names no target", contract_id)));
+        };
+        let address = self.resolve(target)?;
+        let Some(article) = action.pointer("/parameters/article").and_then(Value::as_str) else {
+            return Ok(address);
+        };
+        let narrowed = self.resolve(&format!("article-{}", article))?;
+        if !address.contains(&narrowed) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Contract {} targets {} but its parameters name {}",
+                contract_id, address, narrowed
+            )));
+        }
+        Ok(narrowed)
+    }
+
+    /// Check that a contract's target exists in the constitution.
+    ///
+    /// # Returns
+    /// The address, or a ProtocolError if it does not resolve or nothing is there
+    pub fn check(&self, contract: &Contract, constitution: &Constitution) -> Result<Address> {
+        let address = self.resolve_contract(contract)?;
+        match constitution.resolve(&address) {
+            Some(_) => Ok(address),
+            None => Err(ConstitutionalError::ProtocolError(format!(
+                "Contract {} targets {}, which is not in the constitution",
+                contract.id, address
+            ))),
+        }
+    }
+}
+
+/// Lowercase, with runs of spaces, underscores and hyphens, and any `.` after `art`,
+/// made one hyphen.
+fn normalize(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        match c {
+            ' ' | '_' | '-' => {
+                if !normalized.ends_with('-') {
+                    normalized.push('-');
+                }
+            }
+            c => normalized.extend(c.to_lowercase()),
+        }
+    }
+    normalized.replace("art.-", "article-").replace("art.", "article-")
+}
+
+/// An address from a normalized target of one of the known forms.
+fn read(name: &str) -> Option<Address> {
+    let rest = name.strip_prefix("amendment-").unwrap_or(name);
+    let rest = rest.strip_prefix("article-").or_else(|| rest.strip_prefix("article"))?.trim_start_matches('-');
+    let (article, rest) = rest.split_once('.').map_or((rest, None), |(article, rest)| (article, Some(rest)));
+    let number = match article.parse::<u32>() {
+        Ok(_) => article.to_string(),
+        Err(_) => from_roman(&article.to_ascii_uppercase())?.to_string(),
+    };
+    let canonical = match rest {
+        Some(rest) => format!("article-{}.{}", number, rest),
+        None => format!("article-{}", number),
+    };
+    canonical.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::builder;
+    use crate::parse_markdown;
+    use serde_json::json;
+
+    #[test]
+    fn test_target_forms_resolve() {
+        let resolver = TargetResolver::new().alias("Agents Clause", Address::clause(1, 1, 1));
+        let cases = [
+            ("article-3.1.2", Address::clause(3, 1, 2)),
+            ("amendment-article-3", Address::article(3)),
+            ("Article-III.1", Address::section(3, 1)),
+            ("Article III", Address::article(3)),
+            ("ARTICLE_XII.4", Address::section(12, 4)),
+            ("art. 3.1", Address::section(3, 1)),
+            ("agents-clause", Address::clause(1, 1, 1)),
+        ];
+        for (target, address) in cases {
+            assert_eq!(resolver.resolve(target).unwrap(), address, "{}", target);
+        }
+        for target in ["agent-claude", "article-IIII", "article-3.0", "fraud-proof-entry-001", ""] {
+            assert!(resolver.resolve(target).is_err(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn test_contract_targets_are_checked() {
+        let markdown = "## ARTICLE III — OBLIGATIONS\n\n### 3.1 Truthfulness\n\nAgents tell the truth.\n";
+        let constitution = parse_markdown(markdown).unwrap();
+        let contract = |action: Value| builder().action(action).build().unwrap();
+        let resolver = TargetResolver::new();
+        let narrowed = contract(json!({"target": "amendment-article-3", "parameters": {"article": "III.1"}}));
+        assert_eq!(resolver.check(&narrowed, &constitution).unwrap(), Address::section(3, 1));
+        let whole = contract(json!({"target": "amendment-article-3", "operation": "modify"}));
+        assert_eq!(resolver.check(&whole, &constitution).unwrap(), Address::article(3));
+
+        let missing = contract(json!({"target": "Article-III.2"}));
+        assert!(resolver.resolve_contract(&missing).is_ok());
+        assert!(resolver.check(&missing, &constitution).is_err());
+        let outside = contract(json!({"target": "amendment-article-3", "parameters": {"article": "IV.1"}}));
+        assert!(resolver.check(&outside, &constitution).is_err());
+        assert!(resolver.check(&contract(json!({"operation": "modify"})), &constitution).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+/// timestamp.rs - RFC 3339 timestamp normalization for OCP canonicalization
+///
+/// `2025-11-20T14:30:00+02:00` and `2025-11-20T12:30:00Z` name the same instant but hash
+/// differently as strings. Timestamps under configured field names are rewritten to UTC
+/// with a fixed number of fractional digits, e.g. `2025-11-20T12:30:00.000Z`.
+
+use crate::{ConstitutionalError, Result};
+
+/// Rewrite an RFC 3339 date-time as UTC with exactly `precision` fractional digits.
+///
+/// Extra fractional digits are truncated, missing ones zero-padded. A leap second
+/// (`:60`) is kept as written. Returns None if `text` is not an RFC 3339 date-time or
+/// its UTC form falls outside years 0000-9999.
+pub(crate) fn normalize_rfc3339(text: &str, precision: usize) -> Option<String> {
+    let b = text.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    if !matches!(b[10], b'T' | b't') {
+        return None;
+    }
+
+    let year = digits(&b[0..4])?;
+    let month = digits(&b[5..7])?;
+    let day = digits(&b[8..10])?;
+    let hour = digits(&b[11..13])?;
+    let minute = digits(&b[14..16])?;
+    let second = digits(&b[17..19])?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    let mut fraction = "";
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let len = after_dot.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        fraction = &after_dot[..len];
+        rest = &after_dot[len..];
+    }
+
+    let offset_minutes = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hours = digits(&[*h1, *h2])?;
+            let minutes = digits(&[*m1, *m2])?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 60 + minutes;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+
+    // Offsets are whole minutes, so seconds (including a leap second) carry over unchanged
+    let local_minutes = days_from_civil(year, month, day) * 1440 + hour * 60 + minute;
+    let utc_minutes = local_minutes - offset_minutes;
+    let (year, month, day) = civil_from_days(utc_minutes.div_euclid(1440));
+    let minute_of_day = utc_minutes.rem_euclid(1440);
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+
+    let mut out = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        minute_of_day / 60,
+        minute_of_day % 60,
+        second
+    );
+    if precision > 0 {
+        out.push('.');
+        out.extend(fraction.chars().chain(std::iter::repeat('0')).take(precision));
+    }
+    out.push('Z');
+    Some(out)
+}
+
+/// An RFC 3339 date-time in UTC with nanosecond digits, so that timestamps of protocol
+/// objects compare as strings.
+///
+/// # Returns
+/// The normalized timestamp, or a ProtocolError if `text` is not an RFC 3339 date-time
+pub(crate) fn utc_timestamp(text: &str) -> Result<String> {
+    normalize_rfc3339(text, 9)
+        .ok_or_else(|| ConstitutionalError::ProtocolError(format!("Not an RFC 3339 timestamp: {:?}", text)))
+}
+
+/// Seconds since 1970-01-01T00:00:00Z of an RFC 3339 date-time, fractions dropped. A
+/// leap second counts as the second after it.
+pub(crate) fn unix_seconds(text: &str) -> Option<i64> {
+    let utc = normalize_rfc3339(text, 0)?;
+    let b = utc.as_bytes();
+    let days = days_from_civil(digits(&b[0..4])?, digits(&b[5..7])?, digits(&b[8..10])?);
+    Some(days * 86_400 + digits(&b[11..13])? * 3600 + digits(&b[14..16])? * 60 + digits(&b[17..19])?)
+}
+
+/// The RFC 3339 date-time in UTC of seconds since 1970-01-01T00:00:00Z, the inverse of
+/// `unix_seconds`.
+pub(crate) fn rfc3339_from_unix(seconds: i64) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second = seconds.rem_euclid(86_400);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, second / 3600, second / 60 % 60, second % 60)
+}
+
+fn digits(bytes: &[u8]) -> Option<i64> {
+    bytes.iter().try_fold(0i64, |acc, b| {
+        b.is_ascii_digit().then(|| acc * 10 + i64::from(b - b'0'))
+    })
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_rfc3339() {
+        let cases = [
+            ("2025-11-20T14:30:00+02:00", 3, "2025-11-20T12:30:00.000Z"),
+            ("2025-11-20T12:30:00Z", 3, "2025-11-20T12:30:00.000Z"),
+            ("2025-11-20t12:30:00.5z", 3, "2025-11-20T12:30:00.500Z"),
+            ("2025-11-20T12:30:00.123456789Z", 3, "2025-11-20T12:30:00.123Z"),
+            ("2025-11-20T12:30:00.123456789Z", 0, "2025-11-20T12:30:00Z"),
+            // Offsets that cross day, month and leap-year boundaries
+            ("2025-12-31T22:00:00-05:30", 0, "2026-01-01T03:30:00Z"),
+            ("2024-03-01T00:15:00+01:00", 0, "2024-02-29T23:15:00Z"),
+            ("1970-01-01T00:00:00-00:00", 0, "1970-01-01T00:00:00Z"),
+            ("2016-12-31T23:59:60Z", 0, "2016-12-31T23:59:60Z"),
+        ];
+        for (input, precision, expected) in cases {
+            assert_eq!(normalize_rfc3339(input, precision).as_deref(), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_unix_seconds() {
+        assert_eq!(unix_seconds("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(unix_seconds("2025-11-20T14:30:00.999+02:00"), Some(1_763_641_800));
+        assert_eq!(unix_seconds("1969-12-31T23:59:59Z"), Some(-1));
+        assert_eq!(unix_seconds("2016-12-31T23:59:60Z"), unix_seconds("2017-01-01T00:00:00Z"));
+        assert_eq!(unix_seconds("noon"), None);
+        assert_eq!(rfc3339_from_unix(1_763_641_800), "2025-11-20T12:30:00Z");
+        assert_eq!(rfc3339_from_unix(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_non_timestamps_rejected() {
+        for text in [
+            "2025-11-20",
+            "2025-11-20T14:30:00",
+            "2025-11-20 14:30:00Z",
+            "2025-02-29T00:00:00Z",
+            "2025-11-20T24:00:00Z",
+            "2025-11-20T14:30:00.Z",
+            "2025-11-20T14:30:00+0200",
+            "0000-01-01T00:30:00+01:00",
+            "yesterday at noon",
+        ] {
+            assert_eq!(normalize_rfc3339(text, 3), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,614 @@
+/// validation.rs - Strict-mode validation for OCP canonicalization
+///
+/// Canonicalization stops at the first problem it meets. Validation instead walks the
+/// whole document and reports every violation with its location, so a producer can fix a
+/// rejected contract in one pass. Raw JSON text is scanned leniently: `NaN`/`Infinity`
+/// literals (as Python's `json.dumps` emits them), unquoted keys and lone surrogate escapes
+/// are recorded and skipped over rather than aborting the scan; only malformed syntax ends it.
+///
+/// The typed protocol objects (`Contract`, `Amendment`, `Vote`, `Challenge`,
+/// `DisputeRecord`, `Ruling`) have a `validate` method that reports field-level violations
+/// the same way: IDs that are neither UUIDs nor content IDs, hashes that are not SHA-256
+/// digests, timestamps that are not RFC 3339, confidences outside [0, 1], unknown action
+/// types and empty agent names.
+
+use crate::content_id::parse_content_id;
+use crate::encoding::decode_digest;
+use crate::{normalize_rfc3339, CanonicalizeOptions, EvidencePointer, JsonPointer, TopLevelPolicy, ACTION_TYPES};
+use serde_json::{Number, Value};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A single reason a document cannot be canonicalized in strict mode, or a protocol
+/// object's field is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The top-level value is not an object and the top-level policy is `Reject`.
+    NonObjectTopLevel,
+    /// Nesting exceeds the configured `max_depth` (reported once per document).
+    MaxDepthExceeded(usize),
+    /// `NaN`, `Infinity`, `-Infinity`, or a number that overflows an f64.
+    NonFiniteNumber(String),
+    /// A `\uXXXX` escape encoding half of a surrogate pair without the other half.
+    LoneSurrogate(String),
+    /// An object key that appears more than once in the same object.
+    DuplicateKey(String),
+    /// An object key written without quotes, e.g. `{1: "a"}`.
+    NonStringKey(String),
+    /// Text that is not JSON at all; scanning stops here.
+    InvalidJson(String),
+    /// An ID that is neither a lowercase UUID nor a content ID.
+    InvalidId(String),
+    /// A timestamp that is not an RFC 3339 date-time.
+    InvalidTimestamp(String),
+    /// A confidence outside [0, 1].
+    ConfidenceOutOfRange(String),
+    /// An action type that is not one of `ACTION_TYPES`.
+    UnknownActionType(String),
+    /// An agent named by the empty string.
+    EmptyAgent,
+    /// A validity window that ends at or before it begins.
+    EmptyValidityWindow,
+    /// A dispute window that closes at or before it opens.
+    EmptyDisputeWindow,
+    /// A hash that is not a SHA-256 digest in a text encoding.
+    InvalidHash(String),
+    /// An evidence pointer that `EvidencePointer` cannot parse.
+    InvalidEvidencePointer(String),
+    /// Evidence no resolver could fetch, and why.
+    EvidenceUnavailable(String),
+    /// Evidence whose content does not match the hash its pointer embeds.
+    EvidenceMismatch(String),
+    /// A value that does not match a protocol object's JSON Schema.
+    SchemaMismatch(String),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ViolationKind::NonObjectTopLevel => f.write_str("top-level value is not an object"),
+            ViolationKind::MaxDepthExceeded(depth) => write!(f, "maximum nesting depth of {} exceeded", depth),
+            ViolationKind::NonFiniteNumber(n) => write!(f, "non-finite number {}", n),
+            ViolationKind::LoneSurrogate(escape) => write!(f, "lone surrogate {}", escape),
+            ViolationKind::DuplicateKey(key) => write!(f, "duplicate key {:?}", key),
+            ViolationKind::NonStringKey(key) => write!(f, "non-string key {}", key),
+            ViolationKind::InvalidJson(reason) => write!(f, "invalid JSON: {}", reason),
+            ViolationKind::InvalidId(id) => write!(f, "ID {:?} is not a UUID or content ID", id),
+            ViolationKind::InvalidTimestamp(text) => write!(f, "timestamp {:?} is not RFC 3339", text),
+            ViolationKind::ConfidenceOutOfRange(n) => write!(f, "confidence {} is outside [0, 1]", n),
+            ViolationKind::UnknownActionType(action) => write!(f, "unknown action type {:?}", action),
+            ViolationKind::EmptyAgent => f.write_str("empty agent name"),
+            ViolationKind::EmptyValidityWindow => f.write_str("the validity window ends before it begins"),
+            ViolationKind::EmptyDisputeWindow => f.write_str("the dispute window closes before it opens"),
+            ViolationKind::InvalidHash(hash) => write!(f, "hash {:?} is not a SHA-256 digest", hash),
+            ViolationKind::InvalidEvidencePointer(pointer) => write!(f, "invalid evidence pointer {:?}", pointer),
+            ViolationKind::EvidenceUnavailable(reason) => write!(f, "evidence unavailable: {}", reason),
+            ViolationKind::EvidenceMismatch(pointer) => write!(f, "evidence does not match {}", pointer),
+            ViolationKind::SchemaMismatch(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// A violation and the JSON Pointer of the value it concerns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub pointer: JsonPointer,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.pointer.to_string())
+    }
+}
+
+/// Report every strict-mode violation in a parsed value.
+///
+/// # Arguments
+/// * `data` - Input JSON value to validate
+/// * `options` - Canonicalization options supplying the depth limit and top-level policy
+///
+/// # Returns
+/// All violations in document order; empty if the value is valid
+pub fn validate(data: &Value, options: &CanonicalizeOptions) -> Vec<Violation> {
+    let mut report = Report::new(options);
+    if !data.is_object() {
+        report.top_level(&[]);
+    }
+
+    // Explicit stack of (value, path), as in deep_sort
+    let mut stack = vec![(data, Vec::new())];
+    while let Some((value, path)) = stack.pop() {
+        report.depth(&path);
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map.iter().rev() {
+                    stack.push((v, child_path(&path, k.clone())));
+                }
+            }
+            Value::Array(arr) => {
+                for (i, v) in arr.iter().enumerate().rev() {
+                    stack.push((v, child_path(&path, i.to_string())));
+                }
+            }
+            Value::Number(n) if n.as_f64().is_some_and(|f| !f.is_finite()) => {
+                report.push(ViolationKind::NonFiniteNumber(n.to_string()), &path);
+            }
+            _ => {}
+        }
+    }
+    report.violations
+}
+
+/// Report every strict-mode violation in raw JSON text, including those a parsed `Value`
+/// can no longer show: duplicate keys, lone surrogates, non-finite literals and unquoted keys.
+///
+/// # Arguments
+/// * `json` - Raw JSON text
+/// * `options` - Canonicalization options supplying the depth limit and top-level policy
+///
+/// # Returns
+/// All violations in document order; empty if the text is valid
+pub fn validate_str(json: &str, options: &CanonicalizeOptions) -> Vec<Violation> {
+    let mut scanner = Scanner {
+        bytes: json.as_bytes(),
+        pos: 0,
+        path: Vec::new(),
+        report: Report::new(options),
+    };
+    if let Err(reason) = scanner.document() {
+        let reason = format!("{} at byte {}", reason, scanner.pos);
+        scanner.report.push(ViolationKind::InvalidJson(reason), &scanner.path);
+    }
+    scanner.report.violations
+}
+
+fn child_path(path: &[String], token: String) -> Vec<String> {
+    let mut child = path.to_vec();
+    child.push(token);
+    child
+}
+
+/// Violations collected so far, with the checks shared by both entry points.
+struct Report<'o> {
+    options: &'o CanonicalizeOptions,
+    violations: Vec<Violation>,
+    depth_reported: bool,
+}
+
+impl<'o> Report<'o> {
+    fn new(options: &'o CanonicalizeOptions) -> Self {
+        Report { options, violations: Vec::new(), depth_reported: false }
+    }
+
+    fn push(&mut self, kind: ViolationKind, path: &[String]) {
+        self.violations.push(Violation { kind, pointer: JsonPointer::from_tokens(path.iter().cloned()) });
+    }
+
+    fn top_level(&mut self, path: &[String]) {
+        if self.options.top_level == TopLevelPolicy::Reject {
+            self.push(ViolationKind::NonObjectTopLevel, path);
+        }
+    }
+
+    fn depth(&mut self, path: &[String]) {
+        match self.options.max_depth {
+            Some(max_depth) if path.len() > max_depth && !self.depth_reported => {
+                self.depth_reported = true;
+                self.push(ViolationKind::MaxDepthExceeded(max_depth), path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Field-level violations of a protocol object, each at the path of its field.
+#[derive(Default)]
+pub(crate) struct FieldReport {
+    pub(crate) violations: Vec<Violation>,
+}
+
+impl FieldReport {
+    fn push(&mut self, kind: ViolationKind, path: &[&str]) {
+        self.violations.push(Violation { kind, pointer: JsonPointer::from_tokens(path.iter().copied()) });
+    }
+
+    /// An ID must be a UUID in lowercase 8-4-4-4-12 hex groups, or a content ID.
+    pub(crate) fn id(&mut self, path: &[&str], id: &str) {
+        let groups: Vec<&str> = id.split('-').collect();
+        let uuid = groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+            && groups.iter().all(|group| group.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')));
+        if !uuid && parse_content_id(id).is_none() {
+            self.push(ViolationKind::InvalidId(id.to_string()), path);
+        }
+    }
+
+    pub(crate) fn timestamp(&mut self, path: &[&str], text: &str) {
+        if normalize_rfc3339(text, 9).is_none() {
+            self.push(ViolationKind::InvalidTimestamp(text.to_string()), path);
+        }
+    }
+
+    pub(crate) fn confidence(&mut self, path: &[&str], confidence: &Number) {
+        if !confidence.as_f64().is_some_and(|f| (0.0..=1.0).contains(&f)) {
+            self.push(ViolationKind::ConfidenceOutOfRange(confidence.to_string()), path);
+        }
+    }
+
+    pub(crate) fn action_type(&mut self, path: &[&str], action_type: &str) {
+        if !ACTION_TYPES.contains(&action_type) {
+            self.push(ViolationKind::UnknownActionType(action_type.to_string()), path);
+        }
+    }
+
+    pub(crate) fn agent(&mut self, path: &[&str], agent: &str) {
+        if agent.is_empty() {
+            self.push(ViolationKind::EmptyAgent, path);
+        }
+    }
+
+    /// A semantic hash must be a SHA-256 digest, in hex or multibase.
+    pub(crate) fn hash(&mut self, path: &[&str], hash: &str) {
+        if decode_digest(hash, 32).is_none() {
+            self.push(ViolationKind::InvalidHash(hash.to_string()), path);
+        }
+    }
+
+    /// Each end of a validity window must be RFC 3339, and the window must not be empty.
+    pub(crate) fn window(&mut self, valid_from: Option<&str>, valid_until: Option<&str>) {
+        self.span(["valid_from", "valid_until"], valid_from, valid_until, ViolationKind::EmptyValidityWindow);
+    }
+
+    /// Each end of a span of time must be RFC 3339, and the span must not be empty.
+    pub(crate) fn span(&mut self, names: [&str; 2], start: Option<&str>, end: Option<&str>, empty: ViolationKind) {
+        for (name, text) in names.iter().zip([start, end]) {
+            if let Some(text) = text {
+                self.timestamp(&[name], text);
+            }
+        }
+        let ends = start.zip(end).map(|(start, end)| (normalize_rfc3339(start, 9), normalize_rfc3339(end, 9)));
+        if let Some((Some(start), Some(end))) = ends {
+            if end <= start {
+                self.push(empty, &[names[1]]);
+            }
+        }
+    }
+
+    pub(crate) fn evidence_pointer(&mut self, path: &[&str], pointer: &str) {
+        if EvidencePointer::parse(pointer).is_err() {
+            self.push(ViolationKind::InvalidEvidencePointer(pointer.to_string()), path);
+        }
+    }
+}
+
+/// Keys seen so far in an open object, or the index of the current element in an open array.
+enum Frame {
+    Object(HashSet<String>),
+    Array(usize),
+}
+
+/// Lenient single-pass scanner over JSON text. Syntax errors are returned as `Err`; every
+/// other problem is reported and scanning continues.
+struct Scanner<'a, 'o> {
+    bytes: &'a [u8],
+    pos: usize,
+    path: Vec<String>,
+    report: Report<'o>,
+}
+
+type Scan<T> = std::result::Result<T, String>;
+
+impl<'a, 'o> Scanner<'a, 'o> {
+    fn document(&mut self) -> Scan<()> {
+        self.skip_ws();
+        if self.peek() != Some(b'{') {
+            self.report.top_level(&[]);
+        }
+
+        let mut stack: Vec<Frame> = Vec::new();
+        'value: loop {
+            self.skip_ws();
+            self.report.depth(&self.path);
+            match self.peek() {
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    if !self.eat(b'}') {
+                        let mut keys = HashSet::new();
+                        self.member_key(&mut keys)?;
+                        stack.push(Frame::Object(keys));
+                        continue 'value;
+                    }
+                }
+                Some(b'[') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    if !self.eat(b']') {
+                        stack.push(Frame::Array(0));
+                        self.path.push("0".to_string());
+                        continue 'value;
+                    }
+                }
+                Some(b'"') => {
+                    self.string()?;
+                }
+                _ => self.scalar()?,
+            }
+
+            // A value just ended; close every container it completes
+            while let Some(frame) = stack.last_mut() {
+                self.path.pop();
+                self.skip_ws();
+                match (frame, self.next()) {
+                    (Frame::Object(keys), Some(b',')) => {
+                        self.skip_ws();
+                        self.member_key(keys)?;
+                        continue 'value;
+                    }
+                    (Frame::Array(index), Some(b',')) => {
+                        *index += 1;
+                        self.path.push(index.to_string());
+                        continue 'value;
+                    }
+                    (Frame::Object(_), Some(b'}')) | (Frame::Array(_), Some(b']')) => {
+                        stack.pop();
+                    }
+                    (Frame::Object(_), _) => return Err("expected ',' or '}'".to_string()),
+                    (Frame::Array(_), _) => return Err("expected ',' or ']'".to_string()),
+                }
+            }
+
+            self.skip_ws();
+            return match self.peek() {
+                None => Ok(()),
+                Some(_) => Err("trailing characters".to_string()),
+            };
+        }
+    }
+
+    /// Read a member key and the following `:`, then descend into the member's path.
+    fn member_key(&mut self, keys: &mut HashSet<String>) -> Scan<()> {
+        let key = if self.peek() == Some(b'"') {
+            self.string()?
+        } else {
+            let start = self.pos;
+            while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b"+-._".contains(&b)) {
+                self.pos += 1;
+            }
+            if self.pos == start {
+                return Err("expected object key".to_string());
+            }
+            let key = String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned();
+            self.report.push(ViolationKind::NonStringKey(key.clone()), &self.path);
+            key
+        };
+
+        if !keys.insert(key.clone()) {
+            self.report.push(ViolationKind::DuplicateKey(key.clone()), &self.path);
+        }
+        self.skip_ws();
+        if !self.eat(b':') {
+            return Err("expected ':'".to_string());
+        }
+        self.path.push(key);
+        Ok(())
+    }
+
+    /// Read a string starting at its opening quote and return its decoded contents,
+    /// with lone surrogates decoded as U+FFFD.
+    fn string(&mut self) -> Scan<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while self.peek().is_some_and(|b| b != b'"' && b != b'\\' && b >= 0x20) {
+                self.pos += 1;
+            }
+            // Input is a &str and the run stops only at ASCII bytes, so it is valid UTF-8
+            out.push_str(&String::from_utf8_lossy(&self.bytes[start..self.pos]));
+
+            match self.next() {
+                Some(b'"') => return Ok(out),
+                Some(b'\\') => {}
+                Some(_) => return Err("control character in string".to_string()),
+                None => return Err("unterminated string".to_string()),
+            }
+            match self.next() {
+                Some(b'"') => out.push('"'),
+                Some(b'\\') => out.push('\\'),
+                Some(b'/') => out.push('/'),
+                Some(b'b') => out.push('\u{08}'),
+                Some(b'f') => out.push('\u{0c}'),
+                Some(b'n') => out.push('\n'),
+                Some(b'r') => out.push('\r'),
+                Some(b't') => out.push('\t'),
+                Some(b'u') => {
+                    let unit = self.hex4()?;
+                    out.push(self.code_point(unit)?);
+                }
+                _ => return Err("invalid escape".to_string()),
+            }
+        }
+    }
+
+    /// Complete a `\u` escape, joining a high surrogate with a following low one.
+    fn code_point(&mut self, unit: u16) -> Scan<char> {
+        let lone = |scanner: &mut Self, unit: u16| {
+            scanner.report.push(ViolationKind::LoneSurrogate(format!("\\u{:04x}", unit)), &scanner.path);
+            Ok('\u{fffd}')
+        };
+        match unit {
+            0xd800..=0xdbff if self.bytes[self.pos..].starts_with(b"\\u") => {
+                let resume = self.pos;
+                self.pos += 2;
+                let low = self.hex4()?;
+                if (0xdc00..=0xdfff).contains(&low) {
+                    let c = 0x10000 + ((u32::from(unit) - 0xd800) << 10) + (u32::from(low) - 0xdc00);
+                    Ok(char::from_u32(c).unwrap_or('\u{fffd}'))
+                } else {
+                    // Leave the second escape to be read on its own
+                    self.pos = resume;
+                    lone(self, unit)
+                }
+            }
+            0xd800..=0xdfff => lone(self, unit),
+            _ => Ok(char::from_u32(u32::from(unit)).unwrap_or('\u{fffd}')),
+        }
+    }
+
+    fn hex4(&mut self) -> Scan<u16> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).ok_or("truncated \\u escape")?;
+        let text = std::str::from_utf8(digits).map_err(|_| "invalid \\u escape")?;
+        let unit = u16::from_str_radix(text, 16).map_err(|_| "invalid \\u escape")?;
+        self.pos += 4;
+        Ok(unit)
+    }
+
+    /// Read a literal or number, reporting non-finite ones.
+    fn scalar(&mut self) -> Scan<()> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b)) {
+            self.pos += 1;
+        }
+        let token = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        match token {
+            "true" | "false" | "null" => Ok(()),
+            "NaN" | "Infinity" | "-Infinity" => {
+                self.report.push(ViolationKind::NonFiniteNumber(token.to_string()), &self.path);
+                Ok(())
+            }
+            _ if is_json_number(token) => {
+                // Without arbitrary precision, serde_json cannot hold numbers beyond f64 range
+                if cfg!(not(feature = "arbitrary_precision")) && token.parse::<f64>().is_ok_and(f64::is_infinite) {
+                    self.report.push(ViolationKind::NonFiniteNumber(token.to_string()), &self.path);
+                }
+                Ok(())
+            }
+            "" => Err("expected value".to_string()),
+            _ => {
+                self.pos = start;
+                Err(format!("invalid literal {:?}", token))
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let matched = self.peek() == Some(b);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+}
+
+/// True for the RFC 8259 number grammar: `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+fn is_json_number(token: &str) -> bool {
+    let b = token.strip_prefix('-').unwrap_or(token).as_bytes();
+    let int_len = b.iter().take_while(|c| c.is_ascii_digit()).count();
+    if int_len == 0 || (int_len > 1 && b[0] == b'0') {
+        return false;
+    }
+    let mut rest = &b[int_len..];
+    if let Some(frac) = rest.strip_prefix(b".") {
+        let len = frac.iter().take_while(|c| c.is_ascii_digit()).count();
+        if len == 0 {
+            return false;
+        }
+        rest = &frac[len..];
+    }
+    if let Some(exp) = rest.strip_prefix(b"e").or_else(|| rest.strip_prefix(b"E")) {
+        let exp = exp.strip_prefix(b"+").or_else(|| exp.strip_prefix(b"-")).unwrap_or(exp);
+        let len = exp.iter().take_while(|c| c.is_ascii_digit()).count();
+        return len > 0 && len == exp.len();
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn kinds_at(violations: &[Violation]) -> Vec<(ViolationKind, String)> {
+        violations.iter().map(|v| (v.kind.clone(), v.pointer.to_string())).collect()
+    }
+
+    #[test]
+    fn test_validate_str_reports_every_violation() {
+        let text = r#"{
+            "claim": "a\ud800b",
+            "evidence": [1, NaN, {"score": -Infinity, "score": 2}],
+            "claim": "again",
+            "votes": {1: "yes"},
+            "ok": "\ud83d\ude00"
+        }"#;
+        let options = CanonicalizeOptions::new();
+
+        assert_eq!(
+            kinds_at(&validate_str(text, &options)),
+            vec![
+                (ViolationKind::LoneSurrogate("\\ud800".into()), "/claim".into()),
+                (ViolationKind::NonFiniteNumber("NaN".into()), "/evidence/1".into()),
+                (ViolationKind::NonFiniteNumber("-Infinity".into()), "/evidence/2/score".into()),
+                (ViolationKind::DuplicateKey("score".into()), "/evidence/2".into()),
+                (ViolationKind::DuplicateKey("claim".into()), "".into()),
+                (ViolationKind::NonStringKey("1".into()), "/votes".into()),
+            ]
+        );
+        assert!(validate_str(r#"{"a": [1, {"b": "\u00e9"}], "c": -0.5e3}"#, &options).is_empty());
+    }
+
+    #[test]
+    fn test_validate_str_depth_top_level_and_syntax() {
+        let options = CanonicalizeOptions::new().max_depth(2);
+        assert_eq!(
+            kinds_at(&validate_str(r#"[[[[1]]], [[[2]]]]"#, &options)),
+            vec![
+                (ViolationKind::NonObjectTopLevel, "".into()),
+                (ViolationKind::MaxDepthExceeded(2), "/0/0/0".into()),
+            ]
+        );
+
+        let options = CanonicalizeOptions::new();
+        let violations = validate_str(r#"{"a": Infinity, "b": tru}"#, &options);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].kind, ViolationKind::NonFiniteNumber("Infinity".into()));
+        assert!(matches!(violations[1].kind, ViolationKind::InvalidJson(_)));
+        assert_eq!(violations[1].pointer.to_string(), "/b");
+
+        for text in [r#"{"a": 01}"#, r#"{"a": 1"#, r#"{"a" 1}"#, r#"{"a": 1} x"#, "\"\\x\""] {
+            assert!(
+                validate_str(text, &options).iter().any(|v| matches!(v.kind, ViolationKind::InvalidJson(_))),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_value() {
+        let options = CanonicalizeOptions::new().max_depth(1);
+        let violations = validate(&json!({"a": {"b": {"c": 1}}, "d": [[1]]}), &options);
+        assert_eq!(kinds_at(&violations), vec![(ViolationKind::MaxDepthExceeded(1), "/a/b".into())]);
+
+        let native = CanonicalizeOptions::new().top_level(TopLevelPolicy::Native);
+        assert!(validate(&json!([1, 2]), &native).is_empty());
+        assert_eq!(validate(&json!([1, 2]), &CanonicalizeOptions::new())[0].kind, ViolationKind::NonObjectTopLevel);
+    }
+}
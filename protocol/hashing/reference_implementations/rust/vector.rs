@@ -0,0 +1,147 @@
+/// vector.rs - Position-binding vector commitments over ordered lists
+///
+/// In the article list, the order carries meaning: Article III comes third, and moving it
+/// changes the constitution. A plain Merkle tree over the article hashes proves that an
+/// article is in the list, but the leaf says nothing about where. A `VectorCommitment`
+/// makes each leaf the hash of `ocp-vector:`, the 0-based index as a big-endian u64 and
+/// the item's semantic hash. The proof of an item at one index thus cannot be replayed
+/// for another index, and swapping two items changes the root.
+///
+/// Proofs are `InclusionProof`s over those position leaves, so they serialize as in
+/// `proof.rs`.
+
+use crate::algorithm::Hasher;
+use crate::merkle::{check_algorithm, digest};
+use crate::{
+    semantic_hash_batch, verify_inclusion, CanonicalizeOptions, HashAlgorithm, InclusionProof, MerkleTree, Result,
+    SemanticHash,
+};
+use serde_json::Value;
+
+const POSITION_TAG: &[u8] = b"ocp-vector:";
+
+/// A commitment to an ordered list of semantic hashes, binding each to its index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorCommitment {
+    tree: MerkleTree,
+}
+
+impl VectorCommitment {
+    /// Commit to a list of objects, in order.
+    ///
+    /// # Arguments
+    /// * `items` - The list, e.g. a constitution's `articles` array
+    /// * `options` - Canonicalization options the items are hashed under
+    ///
+    /// # Returns
+    /// The commitment, or the first error hashing an item raised
+    pub fn build(items: &[Value], options: &CanonicalizeOptions) -> Result<VectorCommitment> {
+        let hashes = semantic_hash_batch(items, options).into_iter().collect::<Result<Vec<_>>>()?;
+        VectorCommitment::from_hashes(&hashes, options.hash_algorithm)
+    }
+
+    /// Commit to a list of semantic hashes computed earlier.
+    ///
+    /// # Returns
+    /// The commitment, or a HashingError if the algorithm's digest is not 256 bits
+    pub fn from_hashes(hashes: &[SemanticHash], algorithm: HashAlgorithm) -> Result<VectorCommitment> {
+        check_algorithm(algorithm)?;
+        let leaves: Vec<_> =
+            hashes.iter().enumerate().map(|(index, hash)| position_hash(algorithm, index as u64, hash)).collect();
+        Ok(VectorCommitment { tree: MerkleTree::from_leaves(&leaves, algorithm)? })
+    }
+
+    /// The commitment root.
+    pub fn root(&self) -> SemanticHash {
+        self.tree.root()
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Proof that the item at `index` is the one committed to there.
+    ///
+    /// # Returns
+    /// The proof, or None if there is no item at `index`
+    pub fn prove(&self, index: usize) -> Option<InclusionProof> {
+        self.tree.prove_inclusion(index)
+    }
+}
+
+/// Check that `hash` is the item at `index` of the list committed to by `root`.
+///
+/// # Arguments
+/// * `hash` - Semantic hash of the item
+/// * `index` - Position the item is claimed to have
+/// * `proof` - Proof from `VectorCommitment::prove`
+/// * `root` - Trusted commitment root
+///
+/// # Returns
+/// true if the item is at that position, false otherwise
+pub fn verify_position(hash: &SemanticHash, index: u64, proof: &InclusionProof, root: &SemanticHash) -> bool {
+    check_algorithm(proof.algorithm).is_ok()
+        && proof.index == index
+        && verify_inclusion(&position_hash(proof.algorithm, index, hash), proof, root)
+}
+
+/// Leaf of the item `hash` at `index`.
+fn position_hash(algorithm: HashAlgorithm, index: u64, hash: &SemanticHash) -> SemanticHash {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(POSITION_TAG);
+    hasher.update(&index.to_be_bytes());
+    hasher.update(hash.as_bytes());
+    digest(hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn articles() -> Vec<Value> {
+        ["I", "II", "III", "IV", "V", "VI", "VII"].iter().map(|id| json!({"article_id": id})).collect()
+    }
+
+    #[test]
+    fn test_proofs_bind_position() {
+        let options = CanonicalizeOptions::new();
+        let items = articles();
+        let vector = VectorCommitment::build(&items, &options).unwrap();
+        let root = vector.root();
+        assert_eq!(vector.len(), 7);
+
+        let third = SemanticHash::compute(&items[2], &options).unwrap();
+        let proof = vector.prove(2).unwrap();
+        assert!(verify_position(&third, 2, &proof, &root));
+        assert!(!verify_position(&third, 3, &proof, &root));
+        let fourth = SemanticHash::compute(&items[3], &options).unwrap();
+        assert!(!verify_position(&fourth, 2, &proof, &root));
+
+        // Relabelling the proof's index does not move the item either
+        let mut moved = proof.clone();
+        moved.index = 3;
+        assert!(!verify_position(&third, 3, &moved, &root));
+        assert!(vector.prove(7).is_none());
+    }
+
+    #[test]
+    fn test_reordering_changes_the_root() {
+        let options = CanonicalizeOptions::new();
+        let mut items = articles();
+        let root = VectorCommitment::build(&items, &options).unwrap().root();
+        items.swap(0, 1);
+        assert_ne!(VectorCommitment::build(&items, &options).unwrap().root(), root);
+
+        // The same hashes in a plain Merkle tree keep their roots apart from the vector's
+        let hashes: Vec<_> = items.iter().map(|item| SemanticHash::compute(item, &options).unwrap()).collect();
+        assert_ne!(MerkleTree::from_leaves(&hashes, HashAlgorithm::Sha256).unwrap().root(), root);
+        assert!(VectorCommitment::from_hashes(&hashes, HashAlgorithm::Sha512).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+/// workflow.rs - Ratifying amendments from proposal to the next revision
+///
+/// A `RatificationWorkflow` runs the governance loop over the pieces the rest of the
+/// crate provides:
+///
+/// 1. `propose`: a signed `amend` contract, whose `action` names the semantic hash of
+///    the amendment it proposes as `amendment_hash`, is checked against the signature
+///    policy, or against its proposer's keys if there is none, its `target`, if it
+///    has one, is resolved and checked against the amendment and the constitution, and
+///    it is opened for votes;
+/// 2. `vote`: signed votes on it are collected, each checked against its voter's keys;
+/// 3. `close`: the votes are tallied under the `TallyRules`, and if the amendment is
+///    approved it is applied to the current revision of the constitution.
+///
+/// Each step emits a `WorkflowEvent`, hashable like any protocol object, and appends it
+/// to the workflow's `EventStore`, so the store holds the whole history of every
+/// ratification. Events are stamped by the workflow's `Clock`, and signatures are
+/// checked against the keys valid by it, not at the payload's own timestamp, so a
+/// revoked key cannot backdate its way in. A fixed clock makes a run reproducible:
+///
+/// ```json
+/// {"event":"proposed","contract_id":"550e...","contract_hash":"a3f5...","amendment_hash":"5c1d...",
+///  "proposer":"Claude","at":"2025-11-20T14:30:00Z"}
+/// {"event":"applied","contract_id":"550e...","amendment_id":"8e5d...","amendment_hash":"5c1d...",
+///  "prev_root":"a3f5...","new_root":"9b2e...","revision":1,"at":"2025-11-27T14:30:00Z"}
+/// ```
+
+use crate::timestamp::rfc3339_from_unix;
+use crate::{
+    semantic_hash, tally_signed, Address, AgentRegistry, Amendment, AmendmentOperation, AmendmentRecord, Canonicalize,
+    ConstitutionalError, Contract, Decision, Result, Revision, RevisionHistory, RevisionId, SignaturePolicy,
+    SignedObject, TallyOutcome, TallyRules, TargetResolver, Vote, VoteChoice,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The source of the time events are stamped with.
+pub trait Clock: Send + Sync {
+    /// The current time, RFC 3339.
+    fn now(&self) -> String;
+}
+
+/// The system's clock, to the second, in UTC.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> String {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        rfc3339_from_unix(seconds as i64)
+    }
+}
+
+/// Where a workflow's events are kept.
+pub trait EventStore: Send + Sync {
+    /// Append an event after every one appended before it.
+    fn append(&self, event: &WorkflowEvent) -> Result<()>;
+}
+
+/// An `EventStore` in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryEventStore {
+    events: Mutex<Vec<WorkflowEvent>>,
+}
+
+impl MemoryEventStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events appended, in order.
+    pub fn events(&self) -> Vec<WorkflowEvent> {
+        self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl EventStore for MemoryEventStore {
+    fn append(&self, event: &WorkflowEvent) -> Result<()> {
+        self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(event.clone());
+        Ok(())
+    }
+}
+
+/// A step of a ratification, as appended to the event store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkflowEvent {
+    /// A contract proposing an amendment was opened for votes.
+    Proposed { contract_id: String, contract_hash: String, amendment_hash: String, proposer: String, at: String },
+    /// A vote on it was collected.
+    Voted { contract_id: String, voter: String, vote: VoteChoice, vote_hash: String, at: String },
+    /// Its votes were tallied.
+    Tallied { contract_id: String, outcome: TallyOutcome, tally_hash: String, at: String },
+    /// Its amendment was applied, producing a revision.
+    Applied { contract_id: String, record: AmendmentRecord, revision: RevisionId, at: String },
+}
+
+impl WorkflowEvent {
+    /// The event's name, its `event` member, e.g. `proposed`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkflowEvent::Proposed { .. } => "proposed",
+            WorkflowEvent::Voted { .. } => "voted",
+            WorkflowEvent::Tallied { .. } => "tallied",
+            WorkflowEvent::Applied { .. } => "applied",
+        }
+    }
+
+    /// The contract the event is about.
+    pub fn contract_id(&self) -> &str {
+        match self {
+            WorkflowEvent::Proposed { contract_id, .. }
+            | WorkflowEvent::Voted { contract_id, .. }
+            | WorkflowEvent::Tallied { contract_id, .. }
+            | WorkflowEvent::Applied { contract_id, .. } => contract_id,
+        }
+    }
+
+    /// The event's JSON object.
+    pub fn to_value(&self) -> Value {
+        let mut value = match self {
+            WorkflowEvent::Proposed { contract_id, contract_hash, amendment_hash, proposer, at } => json!({
+                "contract_id": contract_id,
+                "contract_hash": contract_hash,
+                "amendment_hash": amendment_hash,
+                "proposer": proposer,
+                "at": at,
+            }),
+            WorkflowEvent::Voted { contract_id, voter, vote, vote_hash, at } => json!({
+                "contract_id": contract_id,
+                "voter": voter,
+                "vote": vote.as_str(),
+                "vote_hash": vote_hash,
+                "at": at,
+            }),
+            WorkflowEvent::Tallied { contract_id, outcome, tally_hash, at } => json!({
+                "contract_id": contract_id,
+                "outcome": outcome.as_str(),
+                "tally_hash": tally_hash,
+                "at": at,
+            }),
+            WorkflowEvent::Applied { contract_id, record, revision, at } => json!({
+                "contract_id": contract_id,
+                "amendment_id": record.amendment_id,
+                "amendment_hash": record.amendment_hash,
+                "prev_root": record.prev_root.to_hex(),
+                "new_root": record.new_root.to_hex(),
+                "revision": revision.number,
+                "at": at,
+            }),
+        };
+        value["event"] = Value::from(self.name());
+        value
+    }
+}
+
+impl Canonicalize for WorkflowEvent {
+    fn canonical_value(&self) -> Value {
+        self.to_value()
+    }
+}
+
+/// A contract open for votes.
+#[derive(Debug, Clone)]
+struct Proposal {
+    amendment: Amendment,
+    votes: Vec<SignedObject>,
+}
+
+/// Drives amendments from proposal through votes and tally to the next revision.
+pub struct RatificationWorkflow {
+    history: RevisionHistory,
+    registry: AgentRegistry,
+    rules: TallyRules,
+    policy: Option<SignaturePolicy>,
+    resolver: TargetResolver,
+    clock: Arc<dyn Clock>,
+    store: Arc<dyn EventStore>,
+    open: BTreeMap<String, Proposal>,
+}
+
+impl fmt::Debug for RatificationWorkflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RatificationWorkflow")
+            .field("revision", &self.history.current().id)
+            .field("rules", &self.rules)
+            .field("policy", &self.policy)
+            .field("open", &self.open.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl RatificationWorkflow {
+    /// A workflow over a constitution's history, with no signature policy, the system
+    /// clock and events kept in memory.
+    ///
+    /// # Arguments
+    /// * `history` - The constitution's revisions, amended at the current one
+    /// * `registry` - The agents and their keys
+    /// * `rules` - Who may vote and what it takes to approve
+    pub fn new(history: RevisionHistory, registry: AgentRegistry, rules: TallyRules) -> Self {
+        RatificationWorkflow {
+            history,
+            registry,
+            rules,
+            policy: None,
+            resolver: TargetResolver::new(),
+            clock: Arc::new(SystemClock),
+            store: Arc::new(MemoryEventStore::new()),
+            open: BTreeMap::new(),
+        }
+    }
+
+    /// Resolve contract targets with a resolver other than one without aliases.
+    pub fn resolver(mut self, resolver: TargetResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Require proposals to be signed by an agent the policy allows to sign them.
+    pub fn policy(mut self, policy: SignaturePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Stamp events with a clock other than the system's.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Append events to a store other than the one in memory.
+    pub fn store(mut self, store: Arc<dyn EventStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// The constitution's revisions, up to the last amendment applied.
+    pub fn history(&self) -> &RevisionHistory {
+        &self.history
+    }
+
+    /// IDs of the contracts open for votes.
+    pub fn open(&self) -> impl Iterator<Item = &str> {
+        self.open.keys().map(String::as_str)
+    }
+
+    fn emit(&self, event: WorkflowEvent) -> Result<WorkflowEvent> {
+        self.store.append(&event)?;
+        Ok(event)
+    }
+
+    /// Open a signed contract proposing an amendment for votes.
+    ///
+    /// # Arguments
+    /// * `signed` - The signed contract, its `action_type` `amend` and its `action`
+    ///   naming the amendment's semantic hash as `amendment_hash`
+    /// * `amendment` - The amendment proposed
+    ///
+    /// # Returns
+    /// The `proposed` event; ValidationFailed if the contract or amendment is invalid,
+    /// or a ProtocolError if the payload is not a contract, does not propose this
+    /// amendment or is open already, or the policy denies the signature, or without a
+    /// policy it is not by a key of the proposer
+    pub fn propose(&mut self, signed: &SignedObject, amendment: Amendment) -> Result<WorkflowEvent> {
+        let contract = Contract::from_value(signed.payload.clone())?;
+        let violations = contract.validate().into_iter().chain(amendment.validate()).collect::<Vec<_>>();
+        if !violations.is_empty() {
+            return Err(ConstitutionalError::ValidationFailed(violations));
+        }
+        let amendment_hash = amendment.semantic_hash()?;
+        if contract.action_type != "amend" || contract.action.get("amendment_hash") != Some(&json!(amendment_hash)) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Contract {} does not propose amendment {}",
+                contract.id, amendment.id
+            )));
+        }
+        if self.open.contains_key(&contract.id) {
+            return Err(ConstitutionalError::ProtocolError(format!("Contract {} is open already", contract.id)));
+        }
+        if contract.action.get("target").is_some() {
+            self.check_target(&contract, &amendment)?;
+        }
+        let at = self.clock.now();
+        let options = self.history.options();
+        match &self.policy {
+            Some(policy) => match policy.evaluate(signed, &self.registry, &at, options)? {
+                Decision::Allow { agent, .. } if agent == contract.proposer_agent => {}
+                Decision::Allow { agent, .. } => {
+                    return Err(ConstitutionalError::ProtocolError(format!(
+                        "Contract {} is signed by {}, not its proposer",
+                        contract.id, agent
+                    )))
+                }
+                Decision::Deny(denial) => return Err(ConstitutionalError::ProtocolError(denial.to_string())),
+            },
+            None => {
+                // Checked at the workflow's time, since a revoked key could backdate its payload
+                if !self.registry.verify_signed_at(signed, &contract.proposer_agent, &at, options)? {
+                    return Err(ConstitutionalError::ProtocolError(format!(
+                        "Contract {} is not signed by a key of {}",
+                        contract.id, contract.proposer_agent
+                    )));
+                }
+            }
+        }
+        let event = WorkflowEvent::Proposed {
+            contract_id: contract.id.clone(),
+            contract_hash: contract.semantic_hash()?,
+            amendment_hash,
+            proposer: contract.proposer_agent,
+            at,
+        };
+        let event = self.emit(event)?;
+        self.open.insert(contract.id, Proposal { amendment, votes: Vec::new() });
+        Ok(event)
+    }
+
+    /// Check that a contract's target contains what its amendment changes, and exists in
+    /// the current revision; for an amendment adding at the target itself, that the
+    /// place it is added to exists.
+    fn check_target(&self, contract: &Contract, amendment: &Amendment) -> Result<()> {
+        let target = self.resolver.resolve_contract(contract)?;
+        let changed = amendment.target.or_else(|| amendment.article_id().and_then(|id| Address::of_article(&id)));
+        if let Some(changed) = changed.filter(|changed| !target.contains(changed)) {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "Contract {} targets {} but its amendment changes {}",
+                contract.id, target, changed
+            )));
+        }
+        let constitution = &self.history.current().constitution;
+        if amendment.operation == Some(AmendmentOperation::Add) && amendment.target == Some(target) {
+            return match target.parent() {
+                Some(parent) if constitution.resolve(&parent).is_none() => Err(ConstitutionalError::ProtocolError(
+                    format!("Contract {} adds to {}, which is not in the constitution", contract.id, parent),
+                )),
+                _ => Ok(()),
+            };
+        }
+        self.resolver.check(contract, constitution).map(|_| ())
+    }
+
+    /// Collect a signed vote on an open contract.
+    ///
+    /// # Returns
+    /// The `voted` event, or a ProtocolError if the payload is not a vote, the contract
+    /// is not open, or the vote is not signed by a key valid for its voter now
+    pub fn vote(&mut self, signed: &SignedObject) -> Result<WorkflowEvent> {
+        let vote: Vote = serde_json::from_value(signed.payload.clone())
+            .map_err(|e| ConstitutionalError::ProtocolError(format!("Invalid vote: {}", e)))?;
+        if !self.open.contains_key(&vote.contract_id) {
+            return Err(ConstitutionalError::ProtocolError(format!("Contract {} is not open", vote.contract_id)));
+        }
+        let at = self.clock.now();
+        if !self.registry.verify_signed_at(signed, &vote.voter_agent, &at, self.history.options())? {
+            return Err(ConstitutionalError::ProtocolError(format!(
+                "The vote by {} is not signed by a key of theirs",
+                vote.voter_agent
+            )));
+        }
+        let event = WorkflowEvent::Voted {
+            contract_id: vote.contract_id.clone(),
+            voter: vote.voter_agent.clone(),
+            vote: vote.choice,
+            vote_hash: vote.semantic_hash()?,
+            at,
+        };
+        let event = self.emit(event)?;
+        if let Some(proposal) = self.open.get_mut(&vote.contract_id) {
+            proposal.votes.push(signed.clone());
+        }
+        Ok(event)
+    }
+
+    /// Close voting on a contract: tally the votes and, if the amendment is approved,
+    /// apply it to the current revision.
+    ///
+    /// # Returns
+    /// The `tallied` event, followed by the `applied` event if the amendment was
+    /// applied; a ProtocolError if the contract is not open, or the error the tally or
+    /// applying the amendment gives, in which case the contract stays open
+    pub fn close(&mut self, contract_id: &str) -> Result<Vec<WorkflowEvent>> {
+        let Some(proposal) = self.open.get(contract_id) else {
+            return Err(ConstitutionalError::ProtocolError(format!("Contract {} is not open", contract_id)));
+        };
+        let result = tally_signed(contract_id, &proposal.votes, &self.registry, &self.rules, self.history.options())?;
+        let mut history = self.history.clone();
+        let applied = match result.outcome {
+            TallyOutcome::Approved => Some(history.apply(&proposal.amendment)?.clone()),
+            _ => None,
+        };
+        self.open.remove(contract_id);
+        self.history = history;
+        let mut events = vec![self.emit(WorkflowEvent::Tallied {
+            contract_id: contract_id.to_string(),
+            outcome: result.outcome,
+            tally_hash: semantic_hash(&result.to_value())?,
+            at: self.clock.now(),
+        })?];
+        if let Some(Revision { id, record: Some(record), .. }) = applied {
+            events.push(self.emit(WorkflowEvent::Applied {
+                contract_id: contract_id.to_string(),
+                record,
+                revision: id,
+                at: self.clock.now(),
+            })?);
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::fixtures::{builder, CONTRACT_ID};
+    use crate::{parse_markdown, sign, Address, CanonicalizeOptions, Keypair, Reasoning};
+
+    struct FixedClock(&'static str);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    fn amendment() -> Amendment {
+        Amendment::builder()
+            .id("8e5d1c2b-7a6f-4e3d-9c8b-1a2b3c4d5e6f")
+            .proposer_agent("Claude")
+            .modify(Address::clause(1, 1, 1), json!("Agents are autonomous reasoning systems."))
+            .reasoning(Reasoning::new("Clarifies Article I.1", 0.9).unwrap())
+            .timestamp("2025-11-20T14:30:00Z")
+            .build()
+            .unwrap()
+    }
+
+    fn contract(amendment: &Amendment) -> Value {
+        builder()
+            .action(json!({"target": "article-1.1.1", "amendment_hash": amendment.semantic_hash().unwrap()}))
+            .reasoning(Reasoning::new("Clarifies Article I.1", 0.9).unwrap())
+            .build()
+            .unwrap()
+            .to_value()
+    }
+
+    fn vote(voter: &str, choice: &str) -> Value {
+        json!({"contract_id": CONTRACT_ID, "voter_agent": voter, "vote": choice, "timestamp": "2025-11-21T09:00:00Z"})
+    }
+
+    fn setup(store: Arc<MemoryEventStore>) -> (RatificationWorkflow, Vec<Keypair>) {
+        let keys: Vec<Keypair> = (1..=3).map(|i| Keypair::from_secret(&[i; 32])).collect();
+        let mut registry = AgentRegistry::new();
+        for (agent, key) in ["Claude", "Gemini", "Grok"].iter().zip(&keys) {
+            registry.register(agent, key.public_key(), "2025-01-01T00:00:00Z").unwrap();
+        }
+        let genesis = parse_markdown("## ARTICLE I — DEFINITIONS\n\n### 1.1 Agents\n\nAgents are systems.\n").unwrap();
+        let history = RevisionHistory::new(genesis, CanonicalizeOptions::new()).unwrap();
+        let rules = TallyRules::new(&["Claude", "Gemini", "Grok"]).unwrap().quorum(2, 3).unwrap();
+        let workflow = RatificationWorkflow::new(history, registry, rules)
+            .clock(Arc::new(FixedClock("2025-11-27T14:30:00Z")))
+            .store(store);
+        (workflow, keys)
+    }
+
+    #[test]
+    fn test_ratification_applies_the_amendment() {
+        let store = Arc::new(MemoryEventStore::new());
+        let (mut workflow, keys) = setup(store.clone());
+        let amendment = amendment();
+        let proposed = workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment.clone()).unwrap();
+        assert_eq!(proposed.contract_id(), CONTRACT_ID);
+        assert!(workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment.clone()).is_err());
+        workflow.vote(&sign(&vote("Gemini", "approve"), &keys[1]).unwrap()).unwrap();
+        workflow.vote(&sign(&vote("Grok", "reject"), &keys[2]).unwrap()).unwrap();
+        workflow.vote(&sign(&vote("Claude", "approve"), &keys[0]).unwrap()).unwrap();
+        assert!(workflow.vote(&sign(&vote("Grok", "approve"), &keys[1]).unwrap()).is_err());
+
+        let events = workflow.close(CONTRACT_ID).unwrap();
+        let names: Vec<&str> = store.events().iter().map(WorkflowEvent::name).collect();
+        assert_eq!(names, ["proposed", "voted", "voted", "voted", "tallied", "applied"]);
+        assert_eq!(events[1], store.events()[5]);
+        let current = workflow.history().current();
+        assert_eq!(current.id.number, 1);
+        let text = json!("Agents are autonomous reasoning systems.");
+        assert_eq!(current.constitution.resolve(&Address::clause(1, 1, 1)), Some(&text));
+        assert_eq!(events[1].to_value()["new_root"], current.id.state_root.to_hex());
+        assert!(events[1].semantic_hash().is_ok());
+        assert_eq!(workflow.open().count(), 0);
+        assert!(workflow.close(CONTRACT_ID).is_err());
+    }
+
+    #[test]
+    fn test_rejected_and_unauthorized_proposals() {
+        let store = Arc::new(MemoryEventStore::new());
+        let (mut workflow, keys) = setup(store.clone());
+        let amendment = amendment();
+        // Signed by someone other than the proposer, or for another amendment
+        assert!(workflow.propose(&sign(&contract(&amendment), &keys[1]).unwrap(), amendment.clone()).is_err());
+        let mut other = contract(&amendment);
+        other["action"]["amendment_hash"] = json!("a3f5");
+        assert!(workflow.propose(&sign(&other, &keys[0]).unwrap(), amendment.clone()).is_err());
+        // Or targeting a place other than the amendment's
+        let mut elsewhere = contract(&amendment);
+        elsewhere["action"]["target"] = json!("Article-II");
+        assert!(workflow.propose(&sign(&elsewhere, &keys[0]).unwrap(), amendment.clone()).is_err());
+        let mut workflow = workflow.policy(SignaturePolicy::new().allow("amend", "amender"));
+        assert!(workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment.clone()).is_err());
+        assert!(store.events().is_empty());
+
+        let (mut workflow, keys) = setup(store.clone());
+        workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment).unwrap();
+        workflow.vote(&sign(&vote("Grok", "reject"), &keys[2]).unwrap()).unwrap();
+        workflow.vote(&sign(&vote("Gemini", "reject"), &keys[1]).unwrap()).unwrap();
+        let events = workflow.close(CONTRACT_ID).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].to_value()["outcome"], "rejected");
+        assert_eq!(workflow.history().current().id.number, 0);
+    }
+
+    #[test]
+    fn test_revoked_keys_cannot_backdate() {
+        let store = Arc::new(MemoryEventStore::new());
+        let (mut workflow, keys) = setup(store.clone());
+        let amendment = amendment();
+        // Both payloads are dated before the revocations, but arrive after them
+        workflow.registry.revoke("Claude", &keys[0].public_key(), "2025-11-25T00:00:00Z", "compromised").unwrap();
+        assert!(workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment.clone()).is_err());
+        assert!(store.events().is_empty());
+
+        let (mut workflow, keys) = setup(store.clone());
+        workflow.propose(&sign(&contract(&amendment), &keys[0]).unwrap(), amendment).unwrap();
+        workflow.registry.revoke("Grok", &keys[2].public_key(), "2025-11-22T00:00:00Z", "compromised").unwrap();
+        assert!(workflow.vote(&sign(&vote("Grok", "approve"), &keys[2]).unwrap()).is_err());
+        workflow.vote(&sign(&vote("Gemini", "approve"), &keys[1]).unwrap()).unwrap();
+        assert_eq!(store.events().len(), 2);
+    }
+}